@@ -0,0 +1,56 @@
+//! Quoting from a `BondingCurveView` against decoding the bonding curve for every quote
+//!
+//! Each iteration quotes a buy on every curve of the corpus: `borsh_decode` decodes the
+//! account data as `get_bonding_curve_account` does, `view` reads the reserves at their fixed
+//! offsets and quotes from them. Run with `cargo bench --bench account_view`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use pumpfun_sdk::accounts::{BondingCurveAccount, BondingCurveView};
+
+const AMOUNT_SOL: u64 = 100_000_000;
+
+fn corpus() -> Vec<Vec<u8>> {
+    (0..1000u64)
+        .map(|i| {
+            let sold = i * 700_000_000_000;
+            let curve = BondingCurveAccount::new(
+                6966180631402821399,
+                1_073_000_000_000_000 - sold,
+                30_000_000_000 + i * 20_000_000,
+                793_100_000_000_000 - sold,
+                i * 20_000_000,
+                1_000_000_000_000_000,
+                false,
+            );
+            let mut data = borsh::to_vec(&curve).unwrap();
+            data.resize(150, 0);
+            data
+        })
+        .collect()
+}
+
+fn bench_account_view(c: &mut Criterion) {
+    let corpus = corpus();
+    let views: Vec<BondingCurveView> = corpus.iter().map(|data| BondingCurveView::new(1, data.clone()).unwrap()).collect();
+    let mut group = c.benchmark_group("bonding_curve_quote");
+    group.throughput(Throughput::Elements(corpus.len() as u64));
+    group.bench_function("borsh_decode", |b| {
+        b.iter(|| {
+            for data in &corpus {
+                let curve = BondingCurveAccount::from_account_data(black_box(data)).unwrap();
+                black_box(curve.get_buy_price(AMOUNT_SOL).unwrap());
+            }
+        })
+    });
+    group.bench_function("view", |b| {
+        b.iter(|| {
+            for view in &views {
+                black_box(black_box(view).get_buy_price(AMOUNT_SOL).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_account_view);
+criterion_main!(benches);
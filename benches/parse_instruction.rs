@@ -0,0 +1,119 @@
+//! Throughput of `LogFilter::parse_instruction` over a firehose-like mix of transactions
+//!
+//! The corpus mirrors the shape of `logsSubscribe` notifications for the Pump.fun program:
+//! mostly buys and sells, some create-and-buys, and transactions that only mention the
+//! program alongside others. Run with `cargo bench --bench parse_instruction`.
+//!
+//! To compare with the parser before the allocation-free rewrite, check out `74b212e^` with
+//! this file, the `criterion` dev-dependency and this `[[bench]]` entry copied in, run
+//! `cargo bench --bench parse_instruction -- --save-baseline before`, then run
+//! `cargo bench --bench parse_instruction -- --baseline before` on the current tree.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use pumpfun_sdk::common::logs_filters::LogFilter;
+use solana_sdk::pubkey::Pubkey;
+
+const PROGRAM: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+const COMPUTE_BUDGET: &str = "ComputeBudget111111111111111111111111111111";
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+fn create_event(mint: &Pubkey, user: &Pubkey) -> String {
+    let mut data = vec![27, 114, 169, 77, 222, 235, 99, 118];
+    for field in ["Token", "TKN", "https://ipfs.io/ipfs/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG"] {
+        data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        data.extend_from_slice(field.as_bytes());
+    }
+    data.extend_from_slice(mint.as_ref());
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(user.as_ref());
+    BASE64.encode(data)
+}
+
+fn trade_event(mint: &Pubkey, user: &Pubkey, is_buy: bool) -> String {
+    let mut data = vec![189, 219, 127, 211, 78, 230, 97, 238];
+    data.extend_from_slice(mint.as_ref());
+    data.extend_from_slice(&100_000_000u64.to_le_bytes());
+    data.extend_from_slice(&3_500_000_000_000u64.to_le_bytes());
+    data.push(is_buy as u8);
+    data.extend_from_slice(user.as_ref());
+    for value in [1_700_000_000u64, 30_100_000_000, 1_069_500_000_000_000, 100_000_000, 789_500_000_000_000] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    BASE64.encode(data)
+}
+
+fn compute_budget_logs() -> Vec<String> {
+    [
+        format!("Program {} invoke [1]", COMPUTE_BUDGET),
+        format!("Program {} success", COMPUTE_BUDGET),
+        format!("Program {} invoke [1]", COMPUTE_BUDGET),
+        format!("Program {} success", COMPUTE_BUDGET),
+    ]
+    .into()
+}
+
+/// Logs of a Pump.fun instruction with its token CPIs and the event self-CPI
+fn instruction_logs(name: &str, event: String) -> Vec<String> {
+    vec![
+        format!("Program {} invoke [1]", PROGRAM),
+        format!("Program log: Instruction: {}", name),
+        format!("Program {} invoke [2]", TOKEN_PROGRAM),
+        "Program log: Instruction: Transfer".to_string(),
+        format!("Program {} consumed 4645 of 180000 compute units", TOKEN_PROGRAM),
+        format!("Program {} success", TOKEN_PROGRAM),
+        "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+        "Program 11111111111111111111111111111111 success".to_string(),
+        format!("Program data: {}", event),
+        format!("Program {} invoke [2]", PROGRAM),
+        format!("Program {} consumed 2003 of 160000 compute units", PROGRAM),
+        format!("Program {} success", PROGRAM),
+        format!("Program {} consumed 42000 of 200000 compute units", PROGRAM),
+        format!("Program {} success", PROGRAM),
+    ]
+}
+
+fn corpus() -> Vec<Vec<String>> {
+    (0..1000)
+        .map(|i| {
+            let mint = Pubkey::new_unique();
+            let user = Pubkey::new_unique();
+            let mut logs = compute_budget_logs();
+            match i % 10 {
+                0 => {
+                    logs.extend(instruction_logs("Create", create_event(&mint, &user)));
+                    logs.extend(instruction_logs("Buy", trade_event(&mint, &user, true)));
+                }
+                1 => {
+                    // another program's transaction that only transfers tokens
+                    logs.extend([
+                        format!("Program {} invoke [1]", TOKEN_PROGRAM),
+                        "Program log: Instruction: TransferChecked".to_string(),
+                        format!("Program {} success", TOKEN_PROGRAM),
+                    ]);
+                }
+                2..=5 => logs.extend(instruction_logs("Buy", trade_event(&mint, &user, true))),
+                _ => logs.extend(instruction_logs("Sell", trade_event(&mint, &user, false))),
+            }
+            logs
+        })
+        .collect()
+}
+
+fn bench_parse_instruction(c: &mut Criterion) {
+    let corpus = corpus();
+    let bot_wallet = Some(Pubkey::new_unique());
+    let mut group = c.benchmark_group("parse_instruction");
+    group.throughput(Throughput::Elements(corpus.len() as u64));
+    group.bench_function("firehose_mix", |b| {
+        b.iter(|| {
+            for logs in &corpus {
+                black_box(LogFilter::parse_instruction(black_box(logs), bot_wallet).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse_instruction);
+criterion_main!(benches);
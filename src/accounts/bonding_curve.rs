@@ -15,11 +15,13 @@
 //! - `real_sol_reserves`: Actual SOL reserves available for trading
 //! - `token_total_supply`: Total supply of tokens
 //! - `complete`: Whether the bonding curve is complete/finalized
+//! - `version`: Layout the account was decoded from
 //!
 //! # Methods
 //!
 //! - `new`: Creates a new bonding curve instance
 //! - `get_buy_price`: Calculates the amount of tokens received for a given SOL amount
+//! - `get_buy_sol_cost`: Calculates the SOL charged, fee included, for buying a token amount
 //! - `get_sell_price`: Calculates the amount of SOL received for selling tokens
 //! - `get_market_cap_sol`: Calculates the current market cap in SOL
 //! - `get_final_market_cap_sol`: Calculates the final market cap in SOL after all tokens are sold
@@ -27,6 +29,10 @@
 
 use borsh::{BorshDeserialize, BorshSerialize};
 
+use crate::constants::tokens::token_price_sol;
+
+use super::BondingCurveVersion;
+
 /// Represents a bonding curve for token pricing and liquidity management
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct BondingCurveAccount {
@@ -44,6 +50,9 @@ pub struct BondingCurveAccount {
     pub token_total_supply: u64,
     /// Whether the bonding curve is complete/finalized
     pub complete: bool,
+    /// Layout the account was decoded from, see [`from_account_data`](Self::from_account_data)
+    #[borsh(skip)]
+    pub version: BondingCurveVersion,
 }
 
 impl BondingCurveAccount {
@@ -74,6 +83,7 @@ impl BondingCurveAccount {
             real_sol_reserves,
             token_total_supply,
             complete,
+            version: BondingCurveVersion::CURRENT,
         }
     }
 
@@ -115,6 +125,40 @@ impl BondingCurveAccount {
         })
     }
 
+    /// Calculates the SOL the program charges for buying an exact token amount
+    ///
+    /// Mirrors the program's own pricing: the curve cost rounded up by one lamport plus the
+    /// protocol fee rounded up, so a `max_sol_cost` of at least this value will not fail at
+    /// the quoted curve state.
+    ///
+    /// # Arguments
+    /// * `amount` - Amount of tokens to buy
+    /// * `fee_basis_points` - Fee in basis points (1/100th of a percent)
+    ///
+    /// # Returns
+    /// * `Ok(u64)` - Amount of SOL charged including fees
+    /// * `Err(&str)` - Error message if curve is complete or the amount can't be bought
+    pub fn get_buy_sol_cost(&self, amount: u64, fee_basis_points: u64) -> Result<u64, &'static str> {
+        if self.complete {
+            return Err("Curve is complete");
+        }
+
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        if amount >= self.virtual_token_reserves {
+            return Err("Amount exceeds virtual token reserves");
+        }
+
+        let sol_cost: u128 = (amount as u128) * (self.virtual_sol_reserves as u128)
+            / ((self.virtual_token_reserves - amount) as u128)
+            + 1;
+        let fee: u128 = (sol_cost * (fee_basis_points as u128) + 9_999) / 10_000;
+
+        u64::try_from(sol_cost + fee).map_err(|_| "Buy cost overflows u64")
+    }
+
     /// Calculates the amount of SOL received for selling tokens
     ///
     /// # Arguments
@@ -144,7 +188,7 @@ impl BondingCurveAccount {
         Ok((n - a) as u64)
     }
 
-    /// Calculates the current market cap in SOL
+    /// Calculates the current market cap in SOL, as lamports
     pub fn get_market_cap_sol(&self) -> u64 {
         if self.virtual_token_reserves == 0 {
             return 0;
@@ -154,7 +198,7 @@ impl BondingCurveAccount {
             / (self.virtual_token_reserves as u128)) as u64
     }
 
-    /// Calculates the final market cap in SOL after all tokens are sold
+    /// Calculates the final market cap in SOL after all tokens are sold, as lamports
     ///
     /// # Arguments
     /// * `fee_basis_points` - Fee in basis points (1/100th of a percent)
@@ -197,11 +241,9 @@ impl BondingCurveAccount {
         (total_sell_value + fee) as u64
     }
 
+    /// SOL per whole token at the current virtual reserves
     pub fn get_token_price(&self) -> f64 {
-        let v_sol = self.virtual_sol_reserves as f64 / 100_000_000.0;
-        let v_tokens = self.virtual_token_reserves as f64 / 100_000.0;
-        let token_price = v_sol / v_tokens;
-        token_price
+        token_price_sol(self.virtual_sol_reserves, self.virtual_token_reserves)
     }
 }
 
@@ -267,6 +309,31 @@ mod tests {
         assert!(bonding_curve.get_sell_price(100, 250).is_err());
     }
 
+    #[test]
+    fn test_buy_sol_cost_matches_buy_price() {
+        let bonding_curve = BondingCurveAccount::new(
+            1,
+            1_073_000_000_000_000,
+            30_000_000_000,
+            793_100_000_000_000,
+            0,
+            1_000_000_000_000_000,
+            false,
+        );
+
+        // The tokens quoted for a SOL amount never cost more than that amount plus rounding
+        let tokens = bonding_curve.get_buy_price(1_000_000_000).unwrap();
+        let cost = bonding_curve.get_buy_sol_cost(tokens, 0).unwrap();
+        assert!(cost <= 1_000_000_001);
+        assert!(cost >= 999_000_000);
+
+        // A 1% fee is added on top, rounded up
+        let cost_with_fee = bonding_curve.get_buy_sol_cost(tokens, 100).unwrap();
+        assert_eq!(cost_with_fee, cost + (cost + 99) / 100);
+
+        assert!(bonding_curve.get_buy_sol_cost(bonding_curve.virtual_token_reserves, 0).is_err());
+    }
+
     #[test]
     fn test_market_cap_calculations() {
         let bonding_curve: BondingCurveAccount = get_bonding_curve();
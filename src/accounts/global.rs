@@ -22,10 +22,36 @@
 //!
 //! - `new`: Creates a new global account instance
 //! - `get_initial_buy_price`: Calculates the initial amount of tokens received for a given SOL amount
+//! - `initial_bonding_curve`: Returns the bonding curve every new mint starts with
+//! - `initial_buy_quote`: Quotes a dev buy on a new mint for launch planning
 
 use solana_sdk::pubkey::Pubkey;
-use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Serialize, Deserialize};
+
+use crate::constants::tokens::{tokens_to_ui, LAMPORTS_PER_SOL};
+
+use super::BondingCurveAccount;
+
+/// Outcome of a dev buy on a freshly created mint
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InitialBuyQuote {
+    /// Tokens the buy receives, in raw units
+    pub tokens: u64,
+    /// Lamports the program charges for `tokens`, fee included
+    pub sol_cost: u64,
+    /// Share of the total supply the buy receives, in percent
+    pub pct_of_total_supply: f64,
+    /// Spot price after the buy, in SOL per token
+    pub post_price: f64,
+}
+
+impl InitialBuyQuote {
+    /// Upper bound on the SOL cost sent with the buy for `slippage_basis_points`
+    pub fn max_sol_cost_with(&self, slippage_basis_points: u64) -> u64 {
+        self.sol_cost + self.sol_cost * slippage_basis_points / 10_000
+    }
+}
+
 /// Represents the global configuration account for token pricing and fees
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalAccount {
@@ -111,6 +137,50 @@ impl GlobalAccount {
             self.initial_real_token_reserves
         }
     }
+
+    /// Returns the bonding curve every new mint starts with
+    pub fn initial_bonding_curve(&self) -> BondingCurveAccount {
+        BondingCurveAccount::new(
+            0,
+            self.initial_virtual_token_reserves,
+            self.initial_virtual_sol_reserves,
+            self.initial_real_token_reserves,
+            0,
+            self.token_total_supply,
+            false,
+        )
+    }
+
+    /// Quotes a dev buy of `amount_sol` lamports, fee included, on a new mint
+    ///
+    /// This is the quote `create_and_buy` executes, so it needs no mint to exist.
+    ///
+    /// # Arguments
+    /// * `amount_sol` - Lamports to spend, protocol fee included
+    ///
+    /// # Returns
+    /// Tokens received, their cost, share of supply and the resulting spot price
+    pub fn initial_buy_quote(&self, amount_sol: u64) -> Result<InitialBuyQuote, &'static str> {
+        let curve = self.initial_bonding_curve();
+        let net_sol = ((amount_sol as u128) * 10_000 / (10_000 + self.fee_basis_points as u128)) as u64;
+        let tokens = curve.get_buy_price(net_sol)?;
+        let sol_cost = curve.get_buy_sol_cost(tokens, self.fee_basis_points)?;
+
+        // The curve keeps the SOL paid net of the fee, rounded up like the program does
+        let curve_sol = curve.get_buy_sol_cost(tokens, 0)?;
+        let virtual_sol = self.initial_virtual_sol_reserves as f64 + curve_sol as f64;
+        let virtual_tokens = tokens_to_ui(self.initial_virtual_token_reserves - tokens);
+
+        Ok(InitialBuyQuote {
+            tokens,
+            sol_cost,
+            pct_of_total_supply: match self.token_total_supply {
+                0 => 0.0,
+                supply => tokens as f64 / supply as f64 * 100.0,
+            },
+            post_price: (virtual_sol / LAMPORTS_PER_SOL as f64) / virtual_tokens,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -157,6 +227,31 @@ mod tests {
         assert!(price <= global.initial_real_token_reserves);
     }
 
+    #[test]
+    fn test_initial_buy_quote() {
+        // Mainnet parameters
+        let global = GlobalAccount::new(
+            1,
+            true,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            1_073_000_000_000_000,
+            30_000_000_000,
+            793_100_000_000_000,
+            1_000_000_000_000_000,
+            100,
+        );
+
+        let quote = global.initial_buy_quote(2_000_000_000).unwrap();
+        assert!(quote.sol_cost <= 2_000_000_000 + 2);
+        assert!(quote.pct_of_total_supply > 6.0 && quote.pct_of_total_supply < 7.0);
+        assert!(quote.post_price > 0.000_000_028 * 1.1);
+        assert_eq!(quote.max_sol_cost_with(0), quote.sol_cost);
+        assert_eq!(quote.max_sol_cost_with(1_000), quote.sol_cost + quote.sol_cost / 10);
+
+        assert_eq!(global.initial_buy_quote(0).unwrap().tokens, 0);
+    }
+
     #[test]
     fn test_global_account_max_reserves() {
         let mut global: GlobalAccount = get_global();
@@ -0,0 +1,146 @@
+//! Version-tolerant decoding of bonding curve accounts
+//!
+//! Program upgrades append fields to the bonding curve account, and the SDK should keep
+//! trading while it catches up. [`BondingCurveAccount::from_account_data`] tries the layout the
+//! SDK models first, then the other known layouts, and tags the account with the one that
+//! matched. A layout matches when the account holds at least its fields and any bytes
+//! after them are zero, which is how the program pads accounts allocated larger than their
+//! fields.
+//!
+//! Layouts other than the current one are only tried with the `account-layout-fallbacks`
+//! feature, on by default. Decoding one logs a warning once per layout and counts it in
+//! [`non_current_layouts_seen`].
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use borsh::BorshDeserialize;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::BondingCurveAccount;
+
+/// A known layout of the bonding curve account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BondingCurveVersion {
+    /// Discriminator, reserves, supply and `complete`, the layout the SDK models
+    #[default]
+    Original,
+    /// [`Original`](Self::Original) followed by the creator's address
+    WithCreator,
+}
+
+impl BondingCurveVersion {
+    pub const CURRENT: Self = Self::Original;
+
+    pub const ALL: [Self; 2] = [Self::Original, Self::WithCreator];
+
+    /// Bytes of the account's fields, discriminator included
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Original => 8 + 5 * 8 + 1,
+            Self::WithCreator => 8 + 5 * 8 + 1 + 32,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Original => 0,
+            Self::WithCreator => 1,
+        }
+    }
+
+    fn matches(self, data: &[u8]) -> bool {
+        data.len() >= self.size() && data[self.size()..].iter().all(|byte| *byte == 0)
+    }
+}
+
+/// Layouts tried after [`BondingCurveVersion::CURRENT`]
+#[cfg(feature = "account-layout-fallbacks")]
+pub const FALLBACK_VERSIONS: &[BondingCurveVersion] = &[BondingCurveVersion::WithCreator];
+#[cfg(not(feature = "account-layout-fallbacks"))]
+pub const FALLBACK_VERSIONS: &[BondingCurveVersion] = &[];
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("bonding curve account of {len} bytes matches no known layout")]
+pub struct UnknownLayout {
+    pub len: usize,
+}
+
+static SEEN: [AtomicU64; BondingCurveVersion::ALL.len()] = [AtomicU64::new(0), AtomicU64::new(0)];
+
+/// How many accounts of each layout other than the current one were decoded
+pub fn non_current_layouts_seen() -> Vec<(BondingCurveVersion, u64)> {
+    BondingCurveVersion::ALL
+        .into_iter()
+        .filter(|version| *version != BondingCurveVersion::CURRENT)
+        .map(|version| (version, SEEN[version.index()].load(Ordering::Relaxed)))
+        .collect()
+}
+
+impl BondingCurveVersion {
+    /// Layout of the data of a bonding curve account, the current one first
+    ///
+    /// Layouts other than the current one are logged and counted as for
+    /// [`BondingCurveAccount::from_account_data`].
+    pub fn detect(data: &[u8]) -> Result<Self, UnknownLayout> {
+        let version = std::iter::once(Self::CURRENT)
+            .chain(FALLBACK_VERSIONS.iter().copied())
+            .find(|version| version.matches(data))
+            .ok_or(UnknownLayout { len: data.len() })?;
+
+        if version != Self::CURRENT && SEEN[version.index()].fetch_add(1, Ordering::Relaxed) == 0 {
+            tracing::warn!(?version, len = data.len(), "bonding curve account in a layout the SDK does not model; the program was likely upgraded");
+        }
+        Ok(version)
+    }
+}
+
+impl BondingCurveAccount {
+    /// Decodes the data of a bonding curve account in any known layout
+    pub fn from_account_data(data: &[u8]) -> Result<Self, UnknownLayout> {
+        let version = BondingCurveVersion::detect(data)?;
+        let mut curve = Self::deserialize(&mut &data[..]).map_err(|_| UnknownLayout { len: data.len() })?;
+        curve.version = version;
+        Ok(curve)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::to_vec;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    #[test]
+    fn test_layouts_are_detected() {
+        let curve = BondingCurveAccount::new(7, 1_000, 30, 800, 5, 1_000, false);
+        let original = to_vec(&curve).unwrap();
+        assert_eq!(original.len(), BondingCurveVersion::Original.size());
+
+        let decoded = BondingCurveAccount::from_account_data(&original).unwrap();
+        assert_eq!((decoded.version, decoded.real_token_reserves), (BondingCurveVersion::Original, 800));
+        let mut padded = original.clone();
+        padded.resize(150, 0);
+        assert_eq!(BondingCurveAccount::from_account_data(&padded).unwrap().version, BondingCurveVersion::Original);
+
+        assert_eq!(BondingCurveAccount::from_account_data(&original[..40]).unwrap_err(), UnknownLayout { len: 40 });
+
+        let mut with_creator = original.clone();
+        with_creator.extend_from_slice(Pubkey::new_unique().as_ref());
+        with_creator.resize(150, 0);
+        let decoded = BondingCurveAccount::from_account_data(&with_creator);
+        if cfg!(feature = "account-layout-fallbacks") {
+            let decoded = decoded.unwrap();
+            assert_eq!((decoded.version, decoded.virtual_token_reserves), (BondingCurveVersion::WithCreator, 1_000));
+            assert!(non_current_layouts_seen().iter().any(|(version, seen)| *version == BondingCurveVersion::WithCreator && *seen > 0));
+        } else {
+            assert!(decoded.is_err());
+        }
+
+        let mut unknown = with_creator;
+        unknown[120] = 1;
+        assert!(BondingCurveAccount::from_account_data(&unknown).is_err());
+    }
+}
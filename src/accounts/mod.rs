@@ -6,9 +6,16 @@
 //!
 //! - `BondingCurve`: Represents a bonding curve account.
 //! - `Global`: Represents the global configuration account.
+//!
+//! Bonding curves are decoded from any known layout of the account, see [`layout`], or read
+//! without decoding through a [`view::BondingCurveView`].
 
 mod bonding_curve;
 mod global;
+pub mod layout;
+pub mod view;
 
 pub use bonding_curve::*;
 pub use global::*;
+pub use layout::{BondingCurveVersion, UnknownLayout};
+pub use view::BondingCurveView;
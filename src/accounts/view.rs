@@ -0,0 +1,208 @@
+//! Zero-copy views of bonding curve accounts
+//!
+//! Quoting reads three or four `u64`s of a bonding curve, yet decoding the account copies all
+//! of it. A [`BondingCurveView`] keeps the fetched bytes in an `Arc<(slot, data)>` shared by
+//! every thread quoting from it, and its accessors read fields straight from their fixed
+//! [`offsets`]. The offsets are checked against the Borsh layout of [`BondingCurveAccount`] by
+//! the tests, so building a view only fails on data of an unknown layout; the full account is
+//! only decoded, once, by [`BondingCurveView::account`].
+
+use std::sync::{Arc, OnceLock};
+
+use borsh::BorshDeserialize;
+
+use super::{BondingCurveAccount, BondingCurveVersion, UnknownLayout};
+
+/// Byte offsets of the bonding curve fields, shared by every known layout
+pub mod offsets {
+    pub const DISCRIMINATOR: usize = 0;
+    pub const VIRTUAL_TOKEN_RESERVES: usize = 8;
+    pub const VIRTUAL_SOL_RESERVES: usize = 16;
+    pub const REAL_TOKEN_RESERVES: usize = 24;
+    pub const REAL_SOL_RESERVES: usize = 32;
+    pub const TOKEN_TOTAL_SUPPLY: usize = 40;
+    pub const COMPLETE: usize = 48;
+}
+
+const _: () = assert!(offsets::COMPLETE + 1 == BondingCurveVersion::Original.size());
+
+#[inline]
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Raw data of a bonding curve account read at fixed offsets, see the [module docs](self)
+#[derive(Debug)]
+pub struct BondingCurveView {
+    raw: Arc<(u64, Vec<u8>)>,
+    version: BondingCurveVersion,
+    account: OnceLock<BondingCurveAccount>,
+}
+
+impl BondingCurveView {
+    /// View of account `data` fetched at `slot`
+    pub fn new(slot: u64, data: Vec<u8>) -> Result<Self, UnknownLayout> {
+        Self::from_raw(Arc::new((slot, data)))
+    }
+
+    /// View sharing `raw`, the slot and data of a bonding curve account
+    pub fn from_raw(raw: Arc<(u64, Vec<u8>)>) -> Result<Self, UnknownLayout> {
+        let version = BondingCurveVersion::detect(&raw.1)?;
+        Ok(Self { raw, version, account: OnceLock::new() })
+    }
+
+    /// Slot and data the view reads from
+    pub fn raw(&self) -> &Arc<(u64, Vec<u8>)> {
+        &self.raw
+    }
+
+    pub fn slot(&self) -> u64 {
+        self.raw.0
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.raw.1
+    }
+
+    pub fn version(&self) -> BondingCurveVersion {
+        self.version
+    }
+
+    #[inline]
+    pub fn discriminator(&self) -> u64 {
+        read_u64(self.data(), offsets::DISCRIMINATOR)
+    }
+
+    #[inline]
+    pub fn virtual_token_reserves(&self) -> u64 {
+        read_u64(self.data(), offsets::VIRTUAL_TOKEN_RESERVES)
+    }
+
+    #[inline]
+    pub fn virtual_sol_reserves(&self) -> u64 {
+        read_u64(self.data(), offsets::VIRTUAL_SOL_RESERVES)
+    }
+
+    #[inline]
+    pub fn real_token_reserves(&self) -> u64 {
+        read_u64(self.data(), offsets::REAL_TOKEN_RESERVES)
+    }
+
+    #[inline]
+    pub fn real_sol_reserves(&self) -> u64 {
+        read_u64(self.data(), offsets::REAL_SOL_RESERVES)
+    }
+
+    #[inline]
+    pub fn token_total_supply(&self) -> u64 {
+        read_u64(self.data(), offsets::TOKEN_TOTAL_SUPPLY)
+    }
+
+    #[inline]
+    pub fn complete(&self) -> bool {
+        self.data()[offsets::COMPLETE] != 0
+    }
+
+    /// [`BondingCurveAccount::get_buy_price`] from the reserves at their offsets
+    pub fn get_buy_price(&self, amount: u64) -> Result<u64, &'static str> {
+        if self.complete() {
+            return Err("Curve is complete");
+        }
+        if amount == 0 {
+            return Ok(0);
+        }
+
+        let virtual_sol_reserves = self.virtual_sol_reserves() as u128;
+        let virtual_token_reserves = self.virtual_token_reserves() as u128;
+        let r = virtual_sol_reserves * virtual_token_reserves / (virtual_sol_reserves + amount as u128) + 1;
+        Ok(((virtual_token_reserves - r) as u64).min(self.real_token_reserves()))
+    }
+
+    /// The full account, decoded on first use
+    pub fn account(&self) -> &BondingCurveAccount {
+        self.account.get_or_init(|| {
+            let mut account = BondingCurveAccount::deserialize(&mut self.data()).expect("layout was detected");
+            account.version = self.version;
+            account
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    fn fixtures() -> Vec<Vec<u8>> {
+        let curves = [
+            BondingCurveAccount::new(6966180631402821399, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000, false),
+            BondingCurveAccount::new(6966180631402821399, 812_345_678_901_234, 39_627_000_001, 532_445_678_901_234, 9_627_000_001, 1_000_000_000_000_000, false),
+            BondingCurveAccount::new(6966180631402821399, 279_900_000_000_000, 115_005_359_056, 0, 85_005_359_056, 1_000_000_000_000_000, true),
+            BondingCurveAccount::new(u64::MAX, u64::MAX, u64::MAX - 1, 1, 2, u64::MAX, false),
+        ];
+        let mut fixtures = vec![];
+        for curve in curves {
+            let data = borsh::to_vec(&curve).unwrap();
+            let mut padded = data.clone();
+            padded.resize(150, 0);
+            fixtures.push(data);
+            fixtures.push(padded);
+        }
+        if cfg!(feature = "account-layout-fallbacks") {
+            let mut with_creator = fixtures[2].clone();
+            with_creator.extend_from_slice(Pubkey::new_unique().as_ref());
+            with_creator.resize(150, 0);
+            fixtures.push(with_creator);
+        }
+        fixtures
+    }
+
+    #[test]
+    fn test_offset_readers_agree_with_borsh() {
+        for data in fixtures() {
+            let parsed = BondingCurveAccount::from_account_data(&data).unwrap();
+            let view = BondingCurveView::new(42, data).unwrap();
+            assert_eq!(view.slot(), 42);
+            assert_eq!(view.version(), parsed.version);
+            assert_eq!(view.discriminator(), parsed.discriminator);
+            assert_eq!(view.virtual_token_reserves(), parsed.virtual_token_reserves);
+            assert_eq!(view.virtual_sol_reserves(), parsed.virtual_sol_reserves);
+            assert_eq!(view.real_token_reserves(), parsed.real_token_reserves);
+            assert_eq!(view.real_sol_reserves(), parsed.real_sol_reserves);
+            assert_eq!(view.token_total_supply(), parsed.token_total_supply);
+            assert_eq!(view.complete(), parsed.complete);
+            assert_eq!(view.get_buy_price(1_000_000), parsed.get_buy_price(1_000_000));
+            assert_eq!(view.account().get_sell_price(1_000_000, 100), parsed.get_sell_price(1_000_000, 100));
+        }
+
+        assert_eq!(BondingCurveView::new(1, vec![0; 40]).unwrap_err(), UnknownLayout { len: 40 });
+    }
+
+    /// Canary: a new current layout or a moved field must be reviewed against the offsets
+    #[test]
+    fn test_layout_canary() {
+        assert_eq!(BondingCurveVersion::CURRENT, BondingCurveVersion::Original);
+        assert_eq!(BondingCurveVersion::CURRENT.size(), offsets::COMPLETE + 1);
+        assert_eq!(BondingCurveVersion::ALL, [BondingCurveVersion::Original, BondingCurveVersion::WithCreator]);
+    }
+
+    #[test]
+    fn test_offsets_match_borsh_layout() {
+        let sample = BondingCurveAccount::new(1, 2, 3, 4, 5, 6, true);
+        let data = borsh::to_vec(&sample).unwrap();
+        assert_eq!(data.len(), BondingCurveVersion::Original.size());
+        let fields = [
+            (offsets::DISCRIMINATOR, 1),
+            (offsets::VIRTUAL_TOKEN_RESERVES, 2),
+            (offsets::VIRTUAL_SOL_RESERVES, 3),
+            (offsets::REAL_TOKEN_RESERVES, 4),
+            (offsets::REAL_SOL_RESERVES, 5),
+            (offsets::TOKEN_TOTAL_SUPPLY, 6),
+        ];
+        for (offset, value) in fields {
+            assert_eq!(read_u64(&data, offset), value, "field at offset {}", offset);
+        }
+        assert_eq!(data[offsets::COMPLETE], 1);
+    }
+}
@@ -0,0 +1,187 @@
+//! Derived addresses for the Pump.fun Solana Program
+//!
+//! This module exposes every Program Derived Address and associated token account the SDK
+//! needs when building Pump.fun instructions, so downstream code does not have to re-derive
+//! them from seeds.
+//!
+//! # Addresses
+//!
+//! - `global`: Global configuration PDA
+//! - `mint_authority`: Mint authority PDA shared by all Pump.fun tokens
+//! - `bonding_curve`: Bonding curve PDA of a mint
+//! - `bonding_curve_ata`: Token account holding the bonding curve's token reserves
+//! - `metadata`: Metaplex metadata PDA of a mint
+//! - `creator_vault`: Creator fee vault PDA of a token creator
+//! - `user_ata`: Associated token account of a user for a mint
+//!
+//! Token accounts depend on the mint's token program, SPL Token or Token-2022, so the helpers
+//! deriving them take it as a parameter; `get_token_program` resolves it for a live mint.
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants;
+
+pub use crate::pumpfun::common::{
+    get_ata, get_bonding_curve_pda, get_global_pda, get_metadata_pda, get_mint_authority_pda,
+};
+
+/// Returns the token account holding the bonding curve's token reserves for `mint`, owned by
+/// `token_program`
+///
+/// # Example
+///
+/// ```
+/// use std::str::FromStr;
+/// use solana_sdk::pubkey::Pubkey;
+/// use pumpfun_sdk::{addresses::get_bonding_curve_ata, constants::accounts::TOKEN_PROGRAM};
+///
+/// let mint = Pubkey::from_str("9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump").unwrap();
+/// let ata = get_bonding_curve_ata(&mint, &TOKEN_PROGRAM).unwrap();
+/// assert_eq!(ata.to_string(), "4oPaRNdUyHNPn7oba9RnY66mkCUeUMZNVLmJ2X6J5a4Z");
+/// ```
+#[inline]
+pub fn get_bonding_curve_ata(mint: &Pubkey, token_program: &Pubkey) -> Option<Pubkey> {
+    get_bonding_curve_pda(mint)
+        .map(|bonding_curve| get_ata(&bonding_curve, mint, token_program))
+}
+
+/// Returns the creator fee vault PDA of `creator`
+///
+/// # Example
+///
+/// ```
+/// use std::str::FromStr;
+/// use solana_sdk::pubkey::Pubkey;
+/// use pumpfun_sdk::addresses::get_creator_vault_pda;
+///
+/// let creator = Pubkey::from_str("CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM").unwrap();
+/// assert_eq!(get_creator_vault_pda(&creator).to_string(), "Ab79eFyx9rVxZaAzzkQvFtRGZMxXQrRwubeRAhVYQ5cW");
+/// ```
+#[inline]
+pub fn get_creator_vault_pda(creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[constants::seeds::CREATOR_VAULT_SEED, creator.as_ref()],
+        &constants::accounts::PUMPFUN,
+    ).0
+}
+
+/// Returns the associated token account of `owner` for `mint`, owned by `token_program`
+#[inline]
+pub fn get_user_ata(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_ata(owner, mint, token_program)
+}
+
+/// Every address the SDK derives for trading a single mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DerivedAddresses {
+    /// Token mint the addresses were derived for
+    pub mint: Pubkey,
+    /// Bonding curve PDA of the mint
+    pub bonding_curve: Pubkey,
+    /// Token account holding the bonding curve's token reserves
+    pub bonding_curve_ata: Pubkey,
+    /// Metaplex metadata PDA of the mint
+    pub metadata: Pubkey,
+    /// Global configuration PDA
+    pub global: Pubkey,
+    /// Mint authority PDA
+    pub mint_authority: Pubkey,
+    /// Creator fee vault PDA, only known once the creator is supplied
+    pub creator_vault: Option<Pubkey>,
+    /// Associated token account of the user for the mint
+    pub user_ata: Pubkey,
+}
+
+impl DerivedAddresses {
+    /// Derives all addresses of `mint`, owned by `token_program`, for `user`
+    ///
+    /// Returns `None` if no bonding curve PDA can be derived for `mint`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::str::FromStr;
+    /// use solana_sdk::pubkey::Pubkey;
+    /// use pumpfun_sdk::{addresses::DerivedAddresses, constants::accounts::TOKEN_PROGRAM};
+    ///
+    /// let mint = Pubkey::from_str("9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump").unwrap();
+    /// let user = Pubkey::new_unique();
+    /// let addresses = DerivedAddresses::new(&mint, &user, &TOKEN_PROGRAM).unwrap();
+    /// assert_eq!(addresses.bonding_curve.to_string(), "TBHe5tJnuT4CQbHorJ1uVdfUoaYGPKgfCpiv2jgesVN");
+    /// assert!(addresses.creator_vault.is_none());
+    /// ```
+    pub fn new(mint: &Pubkey, user: &Pubkey, token_program: &Pubkey) -> Option<Self> {
+        let bonding_curve = get_bonding_curve_pda(mint)?;
+        Some(Self {
+            mint: *mint,
+            bonding_curve,
+            bonding_curve_ata: get_ata(&bonding_curve, mint, token_program),
+            metadata: get_metadata_pda(mint),
+            global: get_global_pda(),
+            mint_authority: get_mint_authority_pda(),
+            creator_vault: None,
+            user_ata: get_user_ata(user, mint, token_program),
+        })
+    }
+
+    /// Fills in the creator fee vault for `creator`
+    pub fn with_creator(mut self, creator: &Pubkey) -> Self {
+        self.creator_vault = Some(get_creator_vault_pda(creator));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    const MINT: &str = "9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump";
+    const USER: &str = "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM";
+
+    fn pubkey(s: &str) -> Pubkey {
+        Pubkey::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_program_wide_pdas() {
+        assert_eq!(get_global_pda(), pubkey("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf"));
+        assert_eq!(get_mint_authority_pda(), pubkey("TSLvdd1pWpHVjahSpsvCXUbgwsL3JAcvokwaKt1eokM"));
+    }
+
+    #[test]
+    fn test_derived_addresses_for_mainnet_mint() {
+        let addresses = DerivedAddresses::new(&pubkey(MINT), &pubkey(USER), &constants::accounts::TOKEN_PROGRAM).unwrap().with_creator(&pubkey(USER));
+
+        assert_eq!(
+            addresses,
+            DerivedAddresses {
+                mint: pubkey(MINT),
+                bonding_curve: pubkey("TBHe5tJnuT4CQbHorJ1uVdfUoaYGPKgfCpiv2jgesVN"),
+                bonding_curve_ata: pubkey("4oPaRNdUyHNPn7oba9RnY66mkCUeUMZNVLmJ2X6J5a4Z"),
+                metadata: pubkey("9XQ1xyaxP4ALeRbzj9Rm9KByCuwcLjf5yJ4nbyKLxG5u"),
+                global: pubkey("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf"),
+                mint_authority: pubkey("TSLvdd1pWpHVjahSpsvCXUbgwsL3JAcvokwaKt1eokM"),
+                creator_vault: Some(pubkey("Ab79eFyx9rVxZaAzzkQvFtRGZMxXQrRwubeRAhVYQ5cW")),
+                user_ata: pubkey("Hef95Xg4LAHNT7kwmfQi1pZRFXhGQkXynSWY6FqSRBqh"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_token_2022_user_ata() {
+        let ata = get_user_ata(&pubkey(USER), &pubkey(MINT), &constants::accounts::TOKEN_2022_PROGRAM);
+        assert_eq!(ata, pubkey("618Mv7V98t8Fgagj1ZUErasAXcmi83PZ5se8YXMPMjw9"));
+
+        let addresses = DerivedAddresses::new(&pubkey(MINT), &pubkey(USER), &constants::accounts::TOKEN_2022_PROGRAM).unwrap();
+        assert_eq!(addresses.user_ata, ata);
+        assert_eq!(Some(addresses.bonding_curve_ata), get_bonding_curve_ata(&pubkey(MINT), &constants::accounts::TOKEN_2022_PROGRAM));
+    }
+
+    #[test]
+    fn test_event_authority_matches_seed() {
+        let (event_authority, _) = Pubkey::find_program_address(&[b"__event_authority"], &constants::accounts::PUMPFUN);
+        assert_eq!(event_authority, constants::accounts::EVENT_AUTHORITY);
+    }
+}
@@ -0,0 +1,298 @@
+//! Blocking facade over the async [`PumpFun`] client
+//!
+//! This module is only available with the `blocking` cargo feature. It lets synchronous
+//! applications use the SDK without managing a tokio runtime themselves.
+//!
+//! # Trade-offs
+//!
+//! - Each [`BlockingPumpFun`] owns one current-thread tokio runtime, created in
+//!   [`BlockingPumpFun::new`] and reused by every call. Background work belonging to the
+//!   client (gRPC keep-alives, connection pools) only makes progress while a call is
+//!   running, so the first call after a long idle period may pay for a reconnect.
+//! - Calls block the calling thread until the underlying future completes, including
+//!   confirmation waits. Use one client per thread if calls need to run in parallel.
+//! - The blocking methods must not be called from inside an async context; tokio panics
+//!   when a runtime is blocked on from within another runtime.
+//! - Subscriptions run on a dedicated thread with their own runtime so events keep flowing
+//!   between calls, and are consumed through an [`EventIter`].
+
+use std::{
+    sync::{mpsc, Arc},
+    thread::JoinHandle,
+};
+
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Keypair,
+};
+use tokio::{
+    runtime::{Builder, Runtime},
+    sync::oneshot,
+};
+
+use crate::{
+    accounts::InitialBuyQuote,
+    common::{cache_stats::CacheStats, commitment::EventCommitment, latency::LatencyReport, logs_data::TradeInfo, logs_events::PumpfunEvent, logs_subscribe, Cluster, TradeTag},
+    ipfs::{self, CreateTokenMetadata, TokenMetadataIPFS},
+    fee_clients::SubmissionOptions,
+    trade::{cancel::CancellationToken, params::CreateParams, tip_spend::TipSpendReport},
+    pumpfun::{ata::PrecreatedAta, create::CreateResult, idl::CompatibilityReport, metadata::MetadataPolicy, mint_safety::{MintSafetyError, MintSafetyReport}, snapshot::{SnapshotOptions, TokenSnapshot}},
+    PumpFun,
+};
+
+/// Synchronous wrapper around [`PumpFun`]
+pub struct BlockingPumpFun {
+    inner: PumpFun,
+    runtime: Runtime,
+}
+
+impl BlockingPumpFun {
+    /// Creates the client and its runtime, see [`PumpFun::try_new`]
+    pub fn new(payer: Arc<Keypair>, cluster: &Cluster) -> Result<Self, anyhow::Error> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let inner = runtime.block_on(PumpFun::try_new(payer, cluster))?;
+        Ok(Self { inner, runtime })
+    }
+
+    /// Returns the wrapped async client
+    #[inline]
+    pub fn inner(&self) -> &PumpFun {
+        &self.inner
+    }
+
+    /// Tag every subsequent trade with `trade_tag`
+    pub fn set_trade_tag(&mut self, trade_tag: Option<TradeTag>) {
+        self.inner.set_trade_tag(trade_tag);
+    }
+
+    /// Create a new token
+    pub fn create(&self, mint: Keypair, ipfs: TokenMetadataIPFS, metadata_policy: MetadataPolicy) -> Result<CreateResult, anyhow::Error> {
+        self.runtime.block_on(self.inner.create(mint, ipfs, metadata_policy))
+    }
+
+    /// Create a new token with a mint keypair the SDK generates
+    pub fn create_with_generated_mint(&self, vanity_suffix: Option<&str>, ipfs: TokenMetadataIPFS, metadata_policy: MetadataPolicy) -> Result<CreateResult, anyhow::Error> {
+        self.runtime.block_on(self.inner.create_with_generated_mint(vanity_suffix, ipfs, metadata_policy))
+    }
+
+    /// Create a token and buy it per `params`
+    pub fn create_and_buy(&self, params: CreateParams) -> Result<CreateResult, anyhow::Error> {
+        self.runtime.block_on(self.inner.create_and_buy(params))
+    }
+
+    pub fn create_and_buy_with_tip(&self, payer: Arc<Keypair>, params: CreateParams) -> Result<CreateResult, anyhow::Error> {
+        self.runtime.block_on(self.inner.create_and_buy_with_tip(payer, params))
+    }
+
+    /// Buy tokens
+    pub fn buy(&self, mint: Pubkey, amount_sol: u64, slippage_basis_points: Option<u64>, extra_instructions: Option<Vec<Instruction>>, cancel: Option<CancellationToken>) -> Result<LatencyReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.buy(mint, amount_sol, slippage_basis_points, extra_instructions, cancel))
+    }
+
+    /// Buy tokens using the fee clients
+    pub fn buy_with_tip(&self, mint: Pubkey, amount_sol: u64, slippage_basis_points: Option<u64>, submission_options: Option<SubmissionOptions>, extra_instructions: Option<Vec<Instruction>>, cancel: Option<CancellationToken>, land_by_slot: Option<u64>) -> Result<LatencyReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.buy_with_tip(mint, amount_sol, slippage_basis_points, submission_options, extra_instructions, cancel, land_by_slot))
+    }
+
+    /// Largest `amount_sol` the payer can currently buy `mint` with
+    pub fn max_buyable_sol(&self, mint: &Pubkey, slippage_basis_points: Option<u64>, with_tip: bool) -> Result<u64, anyhow::Error> {
+        self.runtime.block_on(self.inner.max_buyable_sol(mint, slippage_basis_points, with_tip))
+    }
+
+    /// Sell tokens
+    pub fn sell(&self, mint: Pubkey, amount_token: Option<u64>, slippage_basis_points: Option<u64>, extra_instructions: Option<Vec<Instruction>>, cancel: Option<CancellationToken>) -> Result<LatencyReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.sell(mint, amount_token, slippage_basis_points, extra_instructions, cancel))
+    }
+
+    /// Sell tokens by percentage
+    pub fn sell_by_percent(&self, mint: Pubkey, percent: u64, slippage_basis_points: Option<u64>, extra_instructions: Option<Vec<Instruction>>) -> Result<LatencyReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.sell_by_percent(mint, percent, slippage_basis_points, extra_instructions))
+    }
+
+    pub fn sell_by_percent_with_tip(&self, mint: Pubkey, percent: u64, slippage_basis_points: Option<u64>, submission_options: Option<SubmissionOptions>, extra_instructions: Option<Vec<Instruction>>) -> Result<LatencyReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.sell_by_percent_with_tip(mint, percent, slippage_basis_points, submission_options, extra_instructions))
+    }
+
+    /// Sell tokens using the fee clients
+    pub fn sell_with_tip(&self, mint: Pubkey, amount_token: Option<u64>, slippage_basis_points: Option<u64>, submission_options: Option<SubmissionOptions>, extra_instructions: Option<Vec<Instruction>>, cancel: Option<CancellationToken>) -> Result<LatencyReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.sell_with_tip(mint, amount_token, slippage_basis_points, submission_options, extra_instructions, cancel))
+    }
+
+    pub fn get_sol_balance(&self, payer: &Pubkey) -> Result<u64, anyhow::Error> {
+        self.runtime.block_on(self.inner.get_sol_balance(payer))
+    }
+
+    pub fn get_payer_sol_balance(&self) -> Result<u64, anyhow::Error> {
+        self.runtime.block_on(self.inner.get_payer_sol_balance())
+    }
+
+    pub fn get_token_balance(&self, payer: &Pubkey, mint: &Pubkey) -> Result<u64, anyhow::Error> {
+        self.runtime.block_on(self.inner.get_token_balance(payer, mint))
+    }
+
+    pub fn get_payer_token_balance(&self, mint: &Pubkey) -> Result<u64, anyhow::Error> {
+        self.runtime.block_on(self.inner.get_payer_token_balance(mint))
+    }
+
+    pub fn get_initial_buy_quote(&self, amount_sol: u64, slippage_basis_points: Option<u64>) -> Result<(InitialBuyQuote, u64), anyhow::Error> {
+        self.runtime.block_on(self.inner.get_initial_buy_quote(amount_sol, slippage_basis_points))
+    }
+
+    pub fn get_token_snapshot(&self, mint: &Pubkey, options: SnapshotOptions) -> TokenSnapshot {
+        self.runtime.block_on(self.inner.get_token_snapshot(mint, options))
+    }
+
+    pub fn precreate_atas(&self, mints: &[Pubkey]) -> Result<Vec<PrecreatedAta>, anyhow::Error> {
+        self.runtime.block_on(self.inner.precreate_atas(mints))
+    }
+
+    pub fn check_mint_safety(&self, mint: &Pubkey) -> Result<MintSafetyReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.check_mint_safety(mint))
+    }
+
+    pub fn check_mints_safety(&self, mints: &[Pubkey]) -> Result<Vec<Result<MintSafetyReport, MintSafetyError>>, anyhow::Error> {
+        self.runtime.block_on(self.inner.check_mints_safety(mints))
+    }
+
+    pub fn cache_stats(&self) -> Vec<CacheStats> {
+        self.runtime.block_on(self.inner.cache_stats())
+    }
+
+    pub fn clear_caches(&self) {
+        self.runtime.block_on(self.inner.clear_caches())
+    }
+
+    pub fn tip_spend_report(&self, days: impl std::ops::RangeBounds<chrono::NaiveDate>) -> TipSpendReport {
+        self.inner.tip_spend_report(days)
+    }
+
+    pub fn verify_program_compatibility(&self) -> Result<CompatibilityReport, anyhow::Error> {
+        self.runtime.block_on(self.inner.verify_program_compatibility())
+    }
+
+    #[inline]
+    pub fn get_payer_pubkey(&self) -> Pubkey {
+        self.inner.get_payer_pubkey()
+    }
+
+    #[inline]
+    pub fn get_token_price(&self, virtual_sol_reserves: u64, virtual_token_reserves: u64) -> f64 {
+        self.inner.get_token_price(virtual_sol_reserves, virtual_token_reserves)
+    }
+
+    #[inline]
+    pub fn get_buy_price(&self, amount: u64, trade_info: &TradeInfo) -> u64 {
+        self.inner.get_buy_price(amount, trade_info)
+    }
+
+    pub fn transfer_sol(&self, payer: &Keypair, receive_wallet: &Pubkey, amount: u64) -> Result<(), anyhow::Error> {
+        self.runtime.block_on(self.inner.transfer_sol(payer, receive_wallet, amount))
+    }
+
+    /// Upload token metadata (and its image, when given as a local file) to IPFS
+    pub fn create_token_metadata(&self, metadata: CreateTokenMetadata, api_key: &str) -> Result<TokenMetadataIPFS, anyhow::Error> {
+        self.runtime.block_on(ipfs::create_token_metadata(metadata, api_key))
+    }
+
+    /// Upload a base64 encoded file to IPFS and return its gateway URL
+    pub fn upload_base64_file(&self, base64_string: &str, api_key: &str) -> Result<String, anyhow::Error> {
+        self.runtime.block_on(ipfs::upload_base64_file(base64_string, api_key))
+    }
+
+    /// Subscribe to Pump.fun events over WebSocket
+    ///
+    /// The subscription runs on its own thread; events are delivered through the returned
+    /// iterator until it is dropped or [`EventIter::shutdown`] is called.
+    pub fn tokens_subscription(
+        &self,
+        ws_url: &str,
+        commitment: impl Into<EventCommitment>,
+        bot_wallet: Option<Pubkey>,
+    ) -> Result<EventIter, anyhow::Error> {
+        let ws_url = ws_url.to_string();
+        let commitment: EventCommitment = commitment.into();
+        let (event_tx, event_rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+        let thread = std::thread::spawn(move || {
+            let runtime = match Builder::new_current_thread().enable_all().build() {
+                Ok(runtime) => runtime,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+
+            runtime.block_on(async move {
+                let callback = move |event: PumpfunEvent| {
+                    let _ = event_tx.send(event);
+                };
+
+                let handle = match logs_subscribe::tokens_subscription(&ws_url, commitment, callback, bot_wallet).await {
+                    Ok(handle) => handle,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+
+                let _ = ready_tx.send(Ok(()));
+                let _ = stop_rx.await;
+                handle.shutdown().await;
+            });
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(EventIter { receiver: event_rx, stop: Some(stop_tx), thread: Some(thread) }),
+            Ok(Err(e)) => Err(anyhow::anyhow!("Failed to start subscription: {}", e)),
+            Err(_) => Err(anyhow::anyhow!("Subscription thread exited before starting")),
+        }
+    }
+}
+
+/// Blocking iterator over subscription events
+///
+/// Iteration ends once the subscription stops. Dropping the iterator shuts the
+/// subscription down and joins its thread.
+pub struct EventIter {
+    receiver: mpsc::Receiver<PumpfunEvent>,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventIter {
+    /// Returns the next event if one is already queued, without blocking
+    pub fn try_next(&self) -> Option<PumpfunEvent> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Stops the subscription and waits for its thread to exit
+    pub fn shutdown(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Iterator for EventIter {
+    type Item = PumpfunEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for EventIter {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
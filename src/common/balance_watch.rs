@@ -0,0 +1,254 @@
+//! SOL deposits to and withdrawals from a set of wallets
+//!
+//! A balance subscription reads the wallets' balances once, then follows every change of
+//! their accounts over WebSocket and reports the difference as a [`BalanceChange`]. Changes
+//! smaller than the threshold, such as transaction fees, accumulate until they add up to it,
+//! so the reported deltas always sum to the balance difference. After a reconnect the
+//! balances are read again, reporting anything that changed while disconnected.
+//!
+//! Every change of the balance is reported, including the SOL the SDK's own trades spend
+//! and receive.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use futures::StreamExt;
+use solana_client::{nonblocking::pubsub_client::PubsubClient, rpc_config::RpcAccountInfoConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use tokio::sync::mpsc;
+
+use super::{
+    heartbeat::{Heartbeat, RECONNECT_DELAY},
+    logs_subscribe::SubscriptionHandle,
+    SolanaRpcClient,
+};
+
+/// Default smallest change reported, above the fees of a typical transaction
+pub const DEFAULT_BALANCE_THRESHOLD: u64 = 100_000;
+
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// A change of a watched wallet's SOL balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub wallet: Pubkey,
+    /// Lamports since the previous change reported, positive for deposits
+    pub delta: i64,
+    pub new_balance: u64,
+    pub slot: u64,
+}
+
+impl BalanceChange {
+    #[inline]
+    pub fn is_deposit(&self) -> bool {
+        self.delta > 0
+    }
+}
+
+/// Last reported balance of each wallet, turning balance updates into [`BalanceChange`]s
+#[derive(Debug, Clone)]
+pub struct BalanceTracker {
+    threshold: u64,
+    balances: HashMap<Pubkey, (u64, u64)>,
+}
+
+impl BalanceTracker {
+    pub fn new(threshold: u64) -> Self {
+        Self { threshold, balances: HashMap::new() }
+    }
+
+    /// Sets the balance of `wallet` without reporting it, for the initial snapshot
+    pub fn snapshot(&mut self, wallet: Pubkey, balance: u64, slot: u64) {
+        self.balances.insert(wallet, (balance, slot));
+    }
+
+    /// Reports the change of `wallet` to `balance` at `slot`, if it reaches the threshold
+    ///
+    /// Updates older than the last balance seen and wallets without a snapshot are ignored.
+    pub fn update(&mut self, wallet: Pubkey, balance: u64, slot: u64) -> Option<BalanceChange> {
+        let (reported, seen_slot) = self.balances.get_mut(&wallet)?;
+        if slot < *seen_slot {
+            return None;
+        }
+        *seen_slot = slot;
+        if balance.abs_diff(*reported) < self.threshold.max(1) {
+            return None;
+        }
+        let delta = balance as i64 - *reported as i64;
+        *reported = balance;
+        Some(BalanceChange { wallet, delta, new_balance: balance, slot })
+    }
+}
+
+/// Balances of `wallets` and the slot they were read at; missing accounts hold nothing
+async fn fetch_balances(rpc: &SolanaRpcClient, wallets: &[Pubkey], commitment: CommitmentConfig) -> Result<(Vec<u64>, u64), anyhow::Error> {
+    let mut balances = Vec::with_capacity(wallets.len());
+    let mut slot = 0;
+    for chunk in wallets.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let response = rpc.get_multiple_accounts_with_commitment(chunk, commitment).await?;
+        slot = slot.max(response.context.slot);
+        balances.extend(response.value.iter().map(|account| account.as_ref().map_or(0, |account| account.lamports)));
+    }
+    Ok((balances, slot))
+}
+
+/// Reads the balances of `wallets` into `tracker`, reporting those that changed since the
+/// previous read
+async fn refresh<F>(rpc: &SolanaRpcClient, wallets: &[Pubkey], commitment: CommitmentConfig, tracker: &mut BalanceTracker, callback: &F) -> Result<(), anyhow::Error>
+where
+    F: Fn(BalanceChange),
+{
+    let (balances, slot) = fetch_balances(rpc, wallets, commitment).await?;
+    for (wallet, balance) in wallets.iter().zip(balances) {
+        match tracker.update(*wallet, balance, slot) {
+            Some(change) => callback(change),
+            None if !tracker.balances.contains_key(wallet) => tracker.snapshot(*wallet, balance, slot),
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Subscribe to the SOL balance changes of `wallets` of at least `threshold` lamports
+///
+/// Each wallet gets its own `accountSubscribe` on a shared connection. The balances are read
+/// before subscribing, so the first change reported is a real one, and again after every
+/// reconnect. Fails if the initial balances can't be read.
+pub async fn balance_changes_subscription<F>(
+    ws_url: &str,
+    rpc: Arc<SolanaRpcClient>,
+    commitment: CommitmentConfig,
+    wallets: HashSet<Pubkey>,
+    threshold: u64,
+    callback: F,
+) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+where
+    F: Fn(BalanceChange) + Send + Sync + 'static,
+{
+    let wallets: Vec<Pubkey> = wallets.into_iter().collect();
+    let mut tracker = BalanceTracker::new(threshold);
+    refresh(&rpc, &wallets, commitment, &mut tracker, &callback).await?;
+    let sub_client = PubsubClient::new(ws_url).await?;
+    let ws_url = ws_url.to_string();
+
+    // Create channel for unsubscribe
+    let (unsub_tx, _) = mpsc::channel(1);
+
+    let heartbeat = Heartbeat::new();
+    let task_heartbeat = heartbeat.clone();
+
+    let task = tokio::spawn(async move {
+        let mut next_client = Some(sub_client);
+        loop {
+            let sub_client = match next_client.take() {
+                Some(sub_client) => sub_client,
+                None => match PubsubClient::new(&ws_url).await {
+                    Ok(sub_client) => sub_client,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "failed to reconnect the balance subscription");
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                },
+            };
+
+            let config = RpcAccountInfoConfig { commitment: Some(commitment), ..Default::default() };
+            let mut streams = Vec::with_capacity(wallets.len());
+            for wallet in &wallets {
+                match sub_client.account_subscribe(wallet, Some(config.clone())).await {
+                    Ok((stream, _)) => streams.push(stream.map(move |update| (*wallet, update))),
+                    Err(e) => tracing::warn!(%wallet, error = %e, "failed to subscribe to the wallet's balance"),
+                }
+            }
+            // changes made while (re)subscribing are only seen by reading the balances again
+            if let Err(e) = refresh(&rpc, &wallets, commitment, &mut tracker, &callback).await {
+                tracing::warn!(error = %e, "failed to read the wallets' balances");
+            }
+
+            let mut stream = futures::stream::select_all(streams);
+            while let Some((wallet, update)) = stream.next().await {
+                task_heartbeat.beat();
+                if let Some(change) = tracker.update(wallet, update.value.lamports, update.context.slot) {
+                    callback(change);
+                }
+            }
+            tracing::warn!("balance subscription stream ended, reconnecting");
+            drop(stream);
+            drop(sub_client);
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+
+    Ok(SubscriptionHandle {
+        task,
+        unsub_fn: Box::new(move || {
+            let _ = unsub_tx.try_send(());
+        }),
+        heartbeat,
+        latency: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Mutex, time::Duration};
+
+    use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+    use super::*;
+
+    #[test]
+    fn test_small_changes_accumulate_until_the_threshold() {
+        let wallet = Pubkey::new_unique();
+        let mut tracker = BalanceTracker::new(10_000);
+        assert_eq!(tracker.update(wallet, 5, 1), None, "no snapshot yet");
+
+        tracker.snapshot(wallet, 1_000_000, 10);
+        assert_eq!(tracker.update(wallet, 995_000, 11), None);
+        let withdrawal = tracker.update(wallet, 990_000, 12).unwrap();
+        assert_eq!(withdrawal, BalanceChange { wallet, delta: -10_000, new_balance: 990_000, slot: 12 });
+        assert!(!withdrawal.is_deposit());
+
+        assert_eq!(tracker.update(wallet, 5_000_000, 11), None, "older than the last update");
+        let deposit = tracker.update(wallet, 5_000_000, 13).unwrap();
+        assert_eq!((deposit.delta, deposit.is_deposit()), (4_010_000, true));
+    }
+
+    /// `solana-test-validator` in the background, then
+    /// `cargo test -- --ignored test_airdrop_is_reported_as_a_deposit`
+    #[tokio::test]
+    #[ignore]
+    async fn test_airdrop_is_reported_as_a_deposit() {
+        let rpc = Arc::new(SolanaRpcClient::new_with_commitment("http://127.0.0.1:8899".to_string(), CommitmentConfig::confirmed()));
+        let (wallet, funded) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let signature = rpc.request_airdrop(&funded, LAMPORTS_PER_SOL).await.unwrap();
+        while !rpc.confirm_transaction(&signature).await.unwrap() {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let received = changes.clone();
+        let wallets = HashSet::from([wallet, funded]);
+        let handle = balance_changes_subscription("ws://127.0.0.1:8900", rpc.clone(), CommitmentConfig::confirmed(), wallets, DEFAULT_BALANCE_THRESHOLD, move |change| {
+            received.lock().unwrap().push(change);
+        })
+        .await
+        .unwrap();
+        assert!(changes.lock().unwrap().is_empty(), "the funded balance is part of the snapshot");
+
+        rpc.request_airdrop(&wallet, 2 * LAMPORTS_PER_SOL).await.unwrap();
+        for _ in 0..100 {
+            if !changes.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        handle.shutdown().await;
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].wallet, changes[0].delta, changes[0].new_balance), (wallet, 2 * LAMPORTS_PER_SOL as i64, 2 * LAMPORTS_PER_SOL));
+    }
+}
@@ -0,0 +1,341 @@
+//! Bounded least-recently-used maps that survive restarts
+//!
+//! Per-mint lookups such as mint → creator are slow to rebuild from chain data. A
+//! [`PersistentLru`] keeps up to `capacity` entries, evicting the least recently used, and a
+//! single background task writes them to a file every flush interval and when the last clone
+//! is dropped, so concurrent users never race on the file.
+//!
+//! Files start with the SHA-256 of the encoded entries. A file whose checksum or encoding
+//! doesn't match, e.g. after a crash mid-write on a filesystem without atomic renames, is
+//! discarded with a warning and the cache starts empty.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use solana_sdk::hash::hash;
+use tokio::sync::{mpsc, oneshot};
+
+/// Length of the checksum preceding the entries in a cache file
+const CHECKSUM_LEN: usize = 32;
+
+/// Default interval between writes of a changed cache
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Encoding of the entries in a cache file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheFormat {
+    #[default]
+    Json,
+    Bincode,
+}
+
+impl CacheFormat {
+    fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, anyhow::Error> {
+        Ok(match self {
+            CacheFormat::Json => serde_json::to_vec(value)?,
+            CacheFormat::Bincode => bincode::serialize(value)?,
+        })
+    }
+
+    fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, anyhow::Error> {
+        Ok(match self {
+            CacheFormat::Json => serde_json::from_slice(bytes)?,
+            CacheFormat::Bincode => bincode::deserialize(bytes)?,
+        })
+    }
+}
+
+/// Entries by key, with a use counter ordering them from least to most recently used
+struct Lru<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+    next_use: u64,
+    /// Changed since the last write
+    dirty: bool,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Lru<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), entries: HashMap::new(), order: BTreeMap::new(), next_use: 0, dirty: false }
+    }
+
+    fn touch(&mut self, key: &K) -> Option<V> {
+        let (value, last_use) = self.entries.get_mut(key)?;
+        self.order.remove(last_use);
+        *last_use = self.next_use;
+        self.order.insert(self.next_use, key.clone());
+        self.next_use += 1;
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if let Some((_, last_use)) = self.entries.remove(&key) {
+            self.order.remove(&last_use);
+        }
+        self.entries.insert(key.clone(), (value, self.next_use));
+        self.order.insert(self.next_use, key);
+        self.next_use += 1;
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else { break };
+            self.entries.remove(&oldest);
+        }
+        self.dirty = true;
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, last_use) = self.entries.remove(key)?;
+        self.order.remove(&last_use);
+        self.dirty = true;
+        Some(value)
+    }
+
+    /// Entries from least to most recently used
+    fn snapshot(&self) -> Vec<(K, V)> {
+        self.order.values().map(|key| (key.clone(), self.entries[key].0.clone())).collect()
+    }
+}
+
+/// A bounded LRU map, optionally persisted to a file; clones share the entries
+pub struct PersistentLru<K, V> {
+    lru: Arc<Mutex<Lru<K, V>>>,
+    /// Flush requests for the writer task, `None` for a cache without a file
+    flushes: Option<mpsc::UnboundedSender<oneshot::Sender<Result<(), String>>>>,
+}
+
+impl<K, V> Clone for PersistentLru<K, V> {
+    fn clone(&self) -> Self {
+        Self { lru: self.lru.clone(), flushes: self.flushes.clone() }
+    }
+}
+
+impl<K, V> PersistentLru<K, V>
+where
+    K: Hash + Eq + Clone + Serialize + DeserializeOwned + Send + 'static,
+    V: Clone + Serialize + DeserializeOwned + Send + 'static,
+{
+    /// A cache kept only in memory
+    pub fn in_memory(capacity: usize) -> Self {
+        Self { lru: Arc::new(Mutex::new(Lru::new(capacity))), flushes: None }
+    }
+
+    /// Loads the entries saved at `path`, if any, and writes changes back every
+    /// `flush_interval`; must be called within a tokio runtime
+    ///
+    /// A missing file starts an empty cache, as does a corrupted one, which is logged and
+    /// overwritten by the next write.
+    pub async fn open(path: impl AsRef<Path>, format: CacheFormat, capacity: usize, flush_interval: Duration) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut lru = Lru::new(capacity);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => match decode_file::<K, V>(&bytes, format) {
+                Ok(entries) => entries.into_iter().for_each(|(key, value)| lru.insert(key, value)),
+                Err(e) => tracing::warn!(path = %path.display(), error = %e, "discarding corrupted cache file"),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        lru.dirty = false;
+
+        let lru = Arc::new(Mutex::new(lru));
+        let (flushes, requests) = mpsc::unbounded_channel();
+        tokio::spawn(write_entries(lru.clone(), path, format, flush_interval, requests));
+        Ok(Self { lru, flushes: Some(flushes) })
+    }
+
+    /// The value of `key`, marking it most recently used
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.lru.lock().unwrap().touch(key)
+    }
+
+    /// Inserts or replaces `key`, evicting the least recently used entry when full
+    pub fn insert(&self, key: K, value: V) {
+        self.lru.lock().unwrap().insert(key, value);
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.lru.lock().unwrap().remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lru.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the entries to the file now, if they changed; a no-op for in-memory caches
+    pub async fn flush(&self) -> Result<(), anyhow::Error> {
+        let Some(flushes) = &self.flushes else {
+            return Ok(());
+        };
+        let (done, written) = oneshot::channel();
+        flushes.send(done).map_err(|_| anyhow::anyhow!("cache writer stopped"))?;
+        written.await?.map_err(|e| anyhow::anyhow!(e))
+    }
+}
+
+fn encode_file<K: Serialize, V: Serialize>(entries: &[(K, V)], format: CacheFormat) -> Result<Vec<u8>, anyhow::Error> {
+    let payload = format.encode(&entries)?;
+    let mut bytes = hash(&payload).to_bytes().to_vec();
+    bytes.extend(payload);
+    Ok(bytes)
+}
+
+fn decode_file<K: DeserializeOwned, V: DeserializeOwned>(bytes: &[u8], format: CacheFormat) -> Result<Vec<(K, V)>, anyhow::Error> {
+    if bytes.len() < CHECKSUM_LEN {
+        return Err(anyhow::anyhow!("file is {} bytes, shorter than its checksum", bytes.len()));
+    }
+    let (checksum, payload) = bytes.split_at(CHECKSUM_LEN);
+    if hash(payload).to_bytes() != checksum {
+        return Err(anyhow::anyhow!("checksum mismatch"));
+    }
+    format.decode(payload)
+}
+
+/// The only writer of a cache file: writes changed entries every `flush_interval`, on request,
+/// and once more after the last clone of the cache is dropped
+async fn write_entries<K, V>(
+    lru: Arc<Mutex<Lru<K, V>>>,
+    path: PathBuf,
+    format: CacheFormat,
+    flush_interval: Duration,
+    mut requests: mpsc::UnboundedReceiver<oneshot::Sender<Result<(), String>>>,
+) where
+    K: Hash + Eq + Clone + Serialize,
+    V: Clone + Serialize,
+{
+    let mut interval = tokio::time::interval(flush_interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        let request = tokio::select! {
+            _ = interval.tick() => None,
+            request = requests.recv() => match request {
+                Some(done) => Some(done),
+                None => {
+                    if let Err(e) = write_if_dirty(&lru, &path, format).await {
+                        tracing::warn!(path = %path.display(), error = %e, "failed to write cache file");
+                    }
+                    return;
+                }
+            },
+        };
+
+        let result = write_if_dirty(&lru, &path, format).await.map_err(|e| e.to_string());
+        if let Err(e) = &result {
+            tracing::warn!(path = %path.display(), error = %e, "failed to write cache file");
+        }
+        if let Some(done) = request {
+            let _ = done.send(result);
+        }
+    }
+}
+
+async fn write_if_dirty<K, V>(lru: &Mutex<Lru<K, V>>, path: &Path, format: CacheFormat) -> Result<(), anyhow::Error>
+where
+    K: Hash + Eq + Clone + Serialize,
+    V: Clone + Serialize,
+{
+    let entries = {
+        let mut lru = lru.lock().unwrap();
+        if !lru.dirty {
+            return Ok(());
+        }
+        lru.dirty = false;
+        lru.snapshot()
+    };
+    let written = async {
+        let temp = path.with_extension("tmp");
+        tokio::fs::write(&temp, encode_file(&entries, format)?).await?;
+        tokio::fs::rename(&temp, path).await?;
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+    if written.is_err() {
+        // Retry with the next write
+        lru.lock().unwrap().dirty = true;
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("pumpfun-cache-{}", Pubkey::new_unique()))
+    }
+
+    #[test]
+    fn test_evicts_the_least_recently_used() {
+        let cache = PersistentLru::in_memory(2);
+        cache.insert(1, 10);
+        cache.insert(2, 20);
+        assert_eq!(cache.get(&1), Some(10));
+        cache.insert(3, 30);
+
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some(10));
+        assert_eq!(cache.get(&3), Some(30));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_entries_survive_restarts() {
+        for format in [CacheFormat::Json, CacheFormat::Bincode] {
+            let path = temp_path();
+            let (mint, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+            let cache = PersistentLru::open(&path, format, 2, DEFAULT_FLUSH_INTERVAL).await.unwrap();
+            cache.insert(Pubkey::new_unique(), Pubkey::new_unique());
+            cache.insert(mint, creator);
+            cache.flush().await.unwrap();
+            drop(cache);
+
+            let restarted = PersistentLru::<Pubkey, Pubkey>::open(&path, format, 2, DEFAULT_FLUSH_INTERVAL).await.unwrap();
+            assert_eq!(restarted.len(), 2);
+
+            // Recency survives too: the entry used last before the restart is evicted last
+            restarted.insert(Pubkey::new_unique(), Pubkey::new_unique());
+            assert_eq!(restarted.get(&mint), Some(creator));
+            assert_eq!(restarted.len(), 2);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_files_are_discarded() {
+        let path = temp_path();
+        let cache = PersistentLru::open(&path, CacheFormat::Json, 10, DEFAULT_FLUSH_INTERVAL).await.unwrap();
+        cache.insert(1u64, 2u64);
+        cache.flush().await.unwrap();
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 2;
+        bytes[last] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let restarted = PersistentLru::<u64, u64>::open(&path, CacheFormat::Json, 10, DEFAULT_FLUSH_INTERVAL).await.unwrap();
+        assert!(restarted.is_empty());
+
+        std::fs::write(&path, b"short").unwrap();
+        let restarted = PersistentLru::<u64, u64>::open(&path, CacheFormat::Json, 10, DEFAULT_FLUSH_INTERVAL).await.unwrap();
+        assert!(restarted.is_empty());
+
+        // The next write replaces the corrupted file
+        restarted.insert(3, 4);
+        restarted.flush().await.unwrap();
+        let recovered = PersistentLru::<u64, u64>::open(&path, CacheFormat::Json, 10, DEFAULT_FLUSH_INTERVAL).await.unwrap();
+        assert_eq!(recovered.get(&3), Some(4));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
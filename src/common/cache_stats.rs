@@ -0,0 +1,89 @@
+//! Hit and miss counters of the SDK's in-memory caches
+//!
+//! Every cache counts its lookups in a [`CacheCounters`] and describes itself as a
+//! [`CacheStats`], which `PumpFun::cache_stats` collects. Counters are cumulative; clearing a
+//! cache drops its entries but keeps counting.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Default)]
+pub struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounters {
+    pub const fn new() -> Self {
+        Self { hits: AtomicU64::new(0), misses: AtomicU64::new(0) }
+    }
+
+    /// Counts a lookup and passes its result through
+    #[inline]
+    pub fn record<T>(&self, lookup: Option<T>) -> Option<T> {
+        let counter = if lookup.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        lookup
+    }
+
+    /// Stats of a cache holding `entries`, the oldest of them `oldest_entry_age` old
+    pub fn stats(&self, name: &'static str, entries: usize, oldest_entry_age: Option<Duration>) -> CacheStats {
+        CacheStats {
+            name,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries,
+            oldest_entry_age,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub name: &'static str,
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    /// Age of the oldest entry, `None` when the cache is empty or doesn't track ages
+    #[serde(rename = "oldest_entry_age_ms", serialize_with = "serialize_millis")]
+    pub oldest_entry_age: Option<Duration>,
+}
+
+impl CacheStats {
+    /// Share of lookups served from the cache, `None` before the first lookup
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+fn serialize_millis<S: Serializer>(age: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match age {
+        Some(age) => serializer.serialize_some(&(age.as_millis() as u64)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_lookups() {
+        let counters = CacheCounters::new();
+        assert_eq!(counters.stats("test", 0, None).hit_rate(), None);
+
+        assert_eq!(counters.record(Some(1)), Some(1));
+        counters.record(None::<u8>);
+        counters.record(Some(2));
+        let stats = counters.stats("test", 2, Some(Duration::from_millis(1500)));
+
+        assert_eq!((stats.hits, stats.misses), (2, 1));
+        assert!((stats.hit_rate().unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(serde_json::to_value(&stats).unwrap()["oldest_entry_age_ms"], 1500);
+    }
+}
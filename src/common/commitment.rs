@@ -0,0 +1,94 @@
+//! Commitment of streamed events, meaning the same for WebSocket and gRPC subscriptions
+//!
+//! A lower level delivers events sooner but may report transactions that a fork later drops:
+//!
+//! | level       | delivered                               | can be rolled back |
+//! |-------------|-----------------------------------------|--------------------|
+//! | `Processed` | as the leader's block is replayed       | yes, if its slot is skipped |
+//! | `Confirmed` | about 0.5-1s later, once voted on       | practically never  |
+//! | `Finalized` | about 13s later, 32 slots deep          | no                 |
+//!
+//! Sniping wants `Processed`; anything acting on balances or history wants `Confirmed` or
+//! above. Some WebSocket providers refuse `Processed` for `logsSubscribe`, see
+//! [`SubscriptionRejected`](super::logs_subscribe::SubscriptionRejected).
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventCommitment {
+    /// Lowest latency; the event's slot may still be skipped
+    Processed,
+    /// Voted on by a supermajority of stake
+    #[default]
+    Confirmed,
+    /// Rooted, the slowest and the only level that never rolls back
+    Finalized,
+}
+
+impl EventCommitment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventCommitment::Processed => "processed",
+            EventCommitment::Confirmed => "confirmed",
+            EventCommitment::Finalized => "finalized",
+        }
+    }
+}
+
+impl fmt::Display for EventCommitment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EventCommitment {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "processed" => Ok(EventCommitment::Processed),
+            "confirmed" => Ok(EventCommitment::Confirmed),
+            "finalized" => Ok(EventCommitment::Finalized),
+            _ => Err(format!("unknown commitment `{}`; use processed, confirmed or finalized", value)),
+        }
+    }
+}
+
+impl From<CommitmentConfig> for EventCommitment {
+    fn from(commitment: CommitmentConfig) -> Self {
+        match commitment.commitment {
+            CommitmentLevel::Processed => EventCommitment::Processed,
+            CommitmentLevel::Confirmed => EventCommitment::Confirmed,
+            CommitmentLevel::Finalized => EventCommitment::Finalized,
+        }
+    }
+}
+
+impl From<EventCommitment> for CommitmentConfig {
+    fn from(commitment: EventCommitment) -> Self {
+        match commitment {
+            EventCommitment::Processed => CommitmentConfig::processed(),
+            EventCommitment::Confirmed => CommitmentConfig::confirmed(),
+            EventCommitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips() {
+        for commitment in [EventCommitment::Processed, EventCommitment::Confirmed, EventCommitment::Finalized] {
+            assert_eq!(EventCommitment::from(CommitmentConfig::from(commitment)), commitment);
+            assert_eq!(commitment.to_string().parse::<EventCommitment>(), Ok(commitment));
+        }
+        assert_eq!(CommitmentConfig::from(EventCommitment::Processed), CommitmentConfig::processed());
+        assert!("recent".parse::<EventCommitment>().unwrap_err().contains("`recent`"));
+    }
+}
@@ -0,0 +1,498 @@
+//! Loading `Cluster` configuration from TOML files and environment variables
+//!
+//! Values are resolved in three layers:
+//!
+//! 1. the TOML file, if any
+//! 2. environment variables named `{PREFIX}_{FIELD}` (e.g. `PUMPFUN_RPC_URL`,
+//!    `PUMPFUN_PRIORITY_FEE_UNIT_PRICE`), which override file values; lists are comma-separated
+//! 3. secret fields written as `env:VAR_NAME`, which are replaced by the value of `VAR_NAME`
+//!    so auth tokens don't have to live in the file
+//!
+//! See `config/cluster.example.toml` for a complete example.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::common::Cluster;
+
+/// Prefix of environment indirections in secret fields
+pub const ENV_INDIRECTION_PREFIX: &str = "env:";
+
+#[derive(Debug, Clone, Copy)]
+enum FieldKind {
+    String,
+    List,
+    Bool,
+    Integer,
+    Float,
+}
+
+/// Fields that can be set from the environment, as `(table, field, kind)`
+///
+/// Fields of a nested table are read from `{PREFIX}_{TABLE}_{FIELD}`.
+const ENV_FIELDS: &[(Option<&str>, &str, FieldKind)] = &[
+    (None, "rpc_url", FieldKind::String),
+    (None, "read_rpc_url", FieldKind::String),
+    (None, "send_rpc_url", FieldKind::String),
+    (None, "block_engine_url", FieldKind::String),
+    (None, "nextblock_url", FieldKind::String),
+    (None, "nextblock_fallback_urls", FieldKind::List),
+    (None, "nextblock_auth_token", FieldKind::String),
+    (None, "zeroslot_url", FieldKind::String),
+    (None, "zeroslot_auth_token", FieldKind::String),
+    (None, "commitment", FieldKind::String),
+    (None, "cluster_kind", FieldKind::String),
+    (None, "skip_genesis_check", FieldKind::Bool),
+    (None, "use_jito", FieldKind::Bool),
+    (None, "use_nextblock", FieldKind::Bool),
+    (None, "use_zeroslot", FieldKind::Bool),
+    (None, "clients", FieldKind::List),
+    (None, "verify_program_on_startup", FieldKind::Bool),
+    (None, "slippage_mode", FieldKind::String),
+    (None, "skip_balance_check", FieldKind::Bool),
+    (None, "retry_fee_recipient_rotation", FieldKind::Bool),
+    (None, "app_name", FieldKind::String),
+    (None, "ws_url", FieldKind::String),
+    (None, "grpc_url", FieldKind::String),
+    (Some("priority_fee"), "unit_limit", FieldKind::Integer),
+    (Some("priority_fee"), "buy_unit_limit", FieldKind::Integer),
+    (Some("priority_fee"), "sell_unit_limit", FieldKind::Integer),
+    (Some("priority_fee"), "create_unit_limit", FieldKind::Integer),
+    (Some("priority_fee"), "unit_price", FieldKind::Integer),
+    (Some("priority_fee"), "buy_tip_fee", FieldKind::Float),
+    (Some("priority_fee"), "sell_tip_fee", FieldKind::Float),
+    (Some("nextblock_options"), "front_running_protection", FieldKind::Bool),
+    (Some("nextblock_options"), "experimental_front_running_protection", FieldKind::Bool),
+    (Some("nextblock_options"), "snipe_transaction", FieldKind::Bool),
+    (Some("rpc_send_options"), "skip_preflight", FieldKind::Bool),
+    (Some("rpc_send_options"), "preflight_commitment", FieldKind::String),
+    (Some("rpc_send_options"), "max_retries", FieldKind::Integer),
+    (Some("rpc_send_options"), "min_context_slot", FieldKind::Integer),
+    (Some("sell_floor"), "min_acceptable_lamports", FieldKind::Integer),
+    (Some("sell_floor"), "allow_below", FieldKind::Bool),
+    (Some("partial_fill"), "min_fill_ratio_bps", FieldKind::Integer),
+    (Some("partial_fill"), "min_real_token_reserves", FieldKind::Integer),
+    (Some("partial_fill"), "allow_partial_fill", FieldKind::Bool),
+    (Some("buy_floor"), "min_buy_lamports", FieldKind::Integer),
+    (Some("buy_floor"), "allow_below", FieldKind::Bool),
+];
+const SECRET_FIELDS: &[&str] = &["nextblock_auth_token", "zeroslot_auth_token"];
+
+impl Cluster {
+    /// Loads a cluster from a TOML file, resolving `env:VAR_NAME` secrets
+    pub fn from_toml(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let table = read_table(path.as_ref())?;
+        build_cluster(table)
+    }
+
+    /// Loads a cluster from environment variables named `{prefix}_{FIELD}`
+    pub fn from_env(prefix: &str) -> Result<Self, anyhow::Error> {
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, prefix, |name| std::env::var(name).ok())?;
+        build_cluster(table)
+    }
+
+    /// Loads a cluster from a TOML file with environment variables layered on top
+    pub fn load(path: impl AsRef<Path>, prefix: &str) -> Result<Self, anyhow::Error> {
+        let mut table = read_table(path.as_ref())?;
+        apply_env_overrides(&mut table, prefix, |name| std::env::var(name).ok())?;
+        build_cluster(table)
+    }
+
+    /// Parses a cluster from TOML text, resolving `env:VAR_NAME` secrets
+    pub fn from_toml_str(content: &str) -> Result<Self, anyhow::Error> {
+        let table: Table = toml::from_str(content).context("Invalid cluster config")?;
+        build_cluster(table)
+    }
+}
+
+fn read_table(path: &Path) -> Result<Table, anyhow::Error> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read cluster config {}", path.display()))?;
+    toml::from_str(&content).with_context(|| format!("Invalid cluster config {}", path.display()))
+}
+
+fn build_cluster(mut table: Table) -> Result<Cluster, anyhow::Error> {
+    resolve_secrets(&mut table, |name| std::env::var(name).ok())?;
+    Cluster::deserialize(Value::Table(table)).map_err(|e| anyhow!("Invalid cluster config: {}", e))
+}
+
+fn env_name(prefix: &str, field: &str) -> String {
+    if prefix.is_empty() {
+        field.to_uppercase()
+    } else {
+        format!("{}_{}", prefix.trim_end_matches('_'), field.to_uppercase())
+    }
+}
+
+fn apply_env_overrides(
+    table: &mut Table,
+    prefix: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(), anyhow::Error> {
+    for (section, field, kind) in ENV_FIELDS {
+        let name = match section {
+            Some(section) => env_name(prefix, &format!("{}_{}", section, field)),
+            None => env_name(prefix, field),
+        };
+        let Some(raw) = lookup(&name) else {
+            continue;
+        };
+        let value = parse_env_value(&name, &raw, *kind)?;
+
+        let target = match section {
+            Some(section) => {
+                let entry = table.entry(section.to_string()).or_insert_with(|| Value::Table(Table::new()));
+                entry.as_table_mut()
+                    .ok_or_else(|| anyhow!("Invalid cluster config: `{}` must be a table", section))?
+            }
+            None => &mut *table,
+        };
+        target.insert(field.to_string(), value);
+    }
+
+    Ok(())
+}
+
+fn parse_env_value(name: &str, raw: &str, kind: FieldKind) -> Result<Value, anyhow::Error> {
+    let trimmed = raw.trim();
+    Ok(match kind {
+        FieldKind::String => Value::String(raw.to_string()),
+        FieldKind::List => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        ),
+        FieldKind::Bool => Value::Boolean(trimmed.parse::<bool>()
+            .map_err(|_| anyhow!("Invalid value for {}: expected true or false, got {:?}", name, raw))?),
+        FieldKind::Integer => Value::Integer(trimmed.parse::<i64>()
+            .map_err(|_| anyhow!("Invalid value for {}: expected an integer, got {:?}", name, raw))?),
+        FieldKind::Float => Value::Float(trimmed.parse::<f64>()
+            .map_err(|_| anyhow!("Invalid value for {}: expected a number, got {:?}", name, raw))?),
+    })
+}
+
+fn resolve_secrets(table: &mut Table, lookup: impl Fn(&str) -> Option<String>) -> Result<(), anyhow::Error> {
+    for field in SECRET_FIELDS {
+        if let Some(Value::String(value)) = table.get_mut(*field) {
+            if let Some(var) = value.strip_prefix(ENV_INDIRECTION_PREFIX) {
+                let var = var.trim().to_string();
+                *value = lookup(&var).ok_or_else(|| {
+                    anyhow!("Invalid cluster config: `{}` references environment variable {}, which is not set", field, var)
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Serde helpers representing `CommitmentConfig` as `"processed"`, `"confirmed"` or `"finalized"`
+pub(crate) mod commitment_serde {
+    use std::str::FromStr;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+    use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+
+    pub fn serialize<S: Serializer>(commitment: &CommitmentConfig, serializer: S) -> Result<S::Ok, S::Error> {
+        let level = match commitment.commitment {
+            CommitmentLevel::Processed => "processed",
+            CommitmentLevel::Confirmed => "confirmed",
+            CommitmentLevel::Finalized => "finalized",
+        };
+        serializer.serialize_str(level)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<CommitmentConfig, D::Error> {
+        let level = String::deserialize(deserializer)?;
+        CommitmentConfig::from_str(&level).map_err(|_| {
+            serde::de::Error::custom(format!(
+                "invalid commitment {:?}, expected processed, confirmed or finalized",
+                level
+            ))
+        })
+    }
+
+    pub fn default_commitment() -> CommitmentConfig {
+        CommitmentConfig::confirmed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+
+    use super::*;
+    use crate::{common::{genesis::ClusterKind, PriorityFee, TipAmount}, fee_clients::ClientType, pumpfun::common::{BuyFloor, PartialFillGuard, SellFloor, SlippageMode}};
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn sample_cluster() -> Cluster {
+        Cluster::new(
+            "https://rpc.example.com".to_string(),
+            "https://block-engine.example.com".to_string(),
+            "https://nextblock.example.com".to_string(),
+            "nb-token".to_string(),
+            "https://zeroslot.example.com".to_string(),
+            "zs-token".to_string(),
+            PriorityFee { unit_limit: Some(90_000), sell_unit_limit: Some(40_000), unit_price: 250_000, buy_tip_fee: TipAmount::Sol(0.001), sell_tip_fee: TipAmount::Lamports(200_000), ..Default::default() },
+            CommitmentConfig::processed(),
+            true,
+            false,
+            true,
+        ).with_nextblock_fallback_urls(vec!["https://ny.example.com".to_string()])
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cluster = sample_cluster();
+        let content = toml::to_string(&cluster).unwrap();
+        let parsed = Cluster::from_toml_str(&content).unwrap();
+
+        assert_eq!(toml::to_string(&parsed).unwrap(), content);
+        assert_eq!(parsed.priority_fee, cluster.priority_fee);
+        assert_eq!(parsed.commitment, CommitmentConfig::processed());
+    }
+
+    #[test]
+    fn test_debug_output_masks_secrets() {
+        let mut cluster = sample_cluster();
+        cluster.rpc_url = "https://rpc.example.com/?api-key=rpc-key".to_string();
+        let debug = format!("{:?}", cluster);
+
+        for secret in ["nb-token", "zs-token", "rpc-key"] {
+            assert!(!debug.contains(secret), "{}", debug);
+        }
+        assert!(debug.contains("nextblock_auth_token: ***"), "{}", debug);
+        assert!(debug.contains("https://rpc.example.com/?api-key=***"), "{}", debug);
+        assert_eq!(cluster.zeroslot_auth_token.expose(), "zs-token");
+    }
+
+    #[test]
+    fn test_defaults_for_optional_fields() {
+        let cluster = Cluster::from_toml_str("rpc_url = \"https://rpc.example.com\"").unwrap();
+
+        assert_eq!(cluster.priority_fee, PriorityFee::default());
+        assert_eq!(cluster.commitment, CommitmentConfig::confirmed());
+        assert!(!cluster.use_jito && !cluster.use_nextblock && !cluster.use_zeroslot);
+    }
+
+    #[test]
+    fn test_tips_resolve_to_exact_lamports() {
+        let cluster = Cluster::from_toml_str(
+            "rpc_url = \"x\"\n[priority_fee]\nbuy_tip_fee = 0.001001\nsell_tip_fee = { lamports = 1000001 }",
+        ).unwrap();
+        // 0.001001 * 1e9 is 1000999.99..., which truncation would pay as 1_000_999
+        assert!(matches!(cluster.priority_fee.buy_tip_fee, TipAmount::Lamports(1_001_000)));
+        assert!(matches!(cluster.priority_fee.sell_tip_fee, TipAmount::Lamports(1_000_001)));
+
+        let err = Cluster::from_toml_str("rpc_url = \"x\"\n[priority_fee]\nbuy_tip_fee = -0.1").unwrap_err();
+        assert!(err.to_string().contains("-0.1"), "{}", err);
+    }
+
+    #[test]
+    fn test_missing_field_is_named() {
+        let err = Cluster::from_toml_str("use_jito = true").unwrap_err();
+        assert!(err.to_string().contains("rpc_url"), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_commitment_is_named() {
+        let err = Cluster::from_toml_str("rpc_url = \"x\"\ncommitment = \"fast\"").unwrap_err();
+        assert!(err.to_string().contains("fast"), "{}", err);
+    }
+
+    #[test]
+    fn test_env_overrides_file_values() {
+        let mut table: Table = toml::from_str(
+            "rpc_url = \"https://file.example.com\"\nuse_jito = false\n[priority_fee]\nunit_limit = 1000",
+        ).unwrap();
+
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[
+            ("PUMPFUN_RPC_URL", "https://env.example.com"),
+            ("PUMPFUN_USE_JITO", "true"),
+            ("PUMPFUN_PRIORITY_FEE_UNIT_PRICE", "42"),
+            ("PUMPFUN_NEXTBLOCK_OPTIONS_SNIPE_TRANSACTION", "false"),
+            ("PUMPFUN_NEXTBLOCK_FALLBACK_URLS", "https://ny.example.com, https://tokyo.example.com"),
+        ])).unwrap();
+        let cluster = Cluster::deserialize(Value::Table(table)).unwrap();
+
+        assert_eq!(cluster.rpc_url, "https://env.example.com");
+        assert!(cluster.use_jito);
+        assert_eq!(cluster.priority_fee.unit_limit, Some(1000));
+        assert_eq!(cluster.priority_fee.unit_price, 42);
+        assert_eq!(cluster.nextblock_fallback_urls, vec!["https://ny.example.com", "https://tokyo.example.com"]);
+        assert!(!cluster.nextblock_options.snipe_transaction);
+        assert!(cluster.nextblock_options.front_running_protection);
+    }
+
+    #[test]
+    fn test_rpc_send_options() {
+        let cluster = Cluster::from_toml_str(
+            "rpc_url = \"x\"\n[rpc_send_options]\nskip_preflight = true\npreflight_commitment = \"processed\"",
+        ).unwrap();
+        assert!(cluster.rpc_send_options.skip_preflight);
+        assert_eq!(cluster.rpc_send_options.preflight_commitment, Some(CommitmentLevel::Processed));
+        assert_eq!(cluster.rpc_send_options.max_retries, None);
+
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[
+            ("PUMPFUN_RPC_URL", "x"),
+            ("PUMPFUN_RPC_SEND_OPTIONS_MAX_RETRIES", "3"),
+            ("PUMPFUN_RPC_SEND_OPTIONS_MIN_CONTEXT_SLOT", "250000000"),
+        ])).unwrap();
+        let cluster = Cluster::deserialize(Value::Table(table)).unwrap();
+        assert!(!cluster.rpc_send_options.skip_preflight);
+        assert_eq!(cluster.rpc_send_options.max_retries, Some(3));
+        assert_eq!(cluster.rpc_send_options.min_context_slot, Some(250_000_000));
+    }
+
+    #[test]
+    fn test_slippage_mode() {
+        assert_eq!(Cluster::from_toml_str("rpc_url = \"x\"").unwrap().slippage_mode, SlippageMode::MaxSolCost);
+
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_SLIPPAGE_MODE", "min_tokens_out")])).unwrap();
+        assert_eq!(Cluster::deserialize(Value::Table(table)).unwrap().slippage_mode, SlippageMode::MinTokensOut);
+    }
+
+    #[test]
+    fn test_app_name() {
+        assert_eq!(Cluster::from_toml_str("rpc_url = \"x\"").unwrap().app_name, None);
+        assert_eq!(Cluster::from_toml_str("rpc_url = \"x\"\napp_name = \"sniper\"").unwrap().app_name.as_deref(), Some("sniper"));
+    }
+
+    #[test]
+    fn test_cluster_kind() {
+        let cluster = Cluster::from_toml_str("rpc_url = \"x\"").unwrap();
+        assert_eq!((cluster.cluster_kind, cluster.skip_genesis_check), (None, false));
+        assert_eq!(Cluster::from_toml_str("rpc_url = \"x\"\ncluster_kind = \"devnet\"").unwrap().cluster_kind, Some(ClusterKind::Devnet));
+
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_SKIP_GENESIS_CHECK", "true")])).unwrap();
+        assert!(Cluster::deserialize(Value::Table(table)).unwrap().skip_genesis_check);
+
+        let err = Cluster::from_toml_str("rpc_url = \"x\"\ncluster_kind = \"testnet\"").unwrap_err();
+        assert!(err.to_string().contains("cluster kind"), "{}", err);
+    }
+
+    #[test]
+    fn test_skip_balance_check() {
+        assert!(!Cluster::from_toml_str("rpc_url = \"x\"").unwrap().skip_balance_check);
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_SKIP_BALANCE_CHECK", "true")])).unwrap();
+        assert!(Cluster::deserialize(Value::Table(table)).unwrap().skip_balance_check);
+    }
+
+    #[test]
+    fn test_retry_fee_recipient_rotation() {
+        assert!(!Cluster::from_toml_str("rpc_url = \"x\"").unwrap().retry_fee_recipient_rotation);
+        assert!(Cluster::from_toml_str("rpc_url = \"x\"\nretry_fee_recipient_rotation = true").unwrap().retry_fee_recipient_rotation);
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_RETRY_FEE_RECIPIENT_ROTATION", "true")])).unwrap();
+        assert!(Cluster::deserialize(Value::Table(table)).unwrap().retry_fee_recipient_rotation);
+    }
+
+    #[test]
+    fn test_sell_floor() {
+        assert_eq!(Cluster::from_toml_str("rpc_url = \"x\"").unwrap().sell_floor, SellFloor::default());
+        let cluster = Cluster::from_toml_str("rpc_url = \"x\"\n[sell_floor]\nmin_acceptable_lamports = 10000").unwrap();
+        assert_eq!(cluster.sell_floor, SellFloor { min_acceptable_lamports: 10_000, allow_below: false });
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_SELL_FLOOR_ALLOW_BELOW", "true")])).unwrap();
+        assert!(Cluster::deserialize(Value::Table(table)).unwrap().sell_floor.allow_below);
+    }
+
+    #[test]
+    fn test_partial_fill() {
+        assert_eq!(Cluster::from_toml_str("rpc_url = \"x\"").unwrap().partial_fill, PartialFillGuard::default());
+        let cluster = Cluster::from_toml_str("rpc_url = \"x\"\n[partial_fill]\nmin_fill_ratio_bps = 8000").unwrap();
+        assert_eq!(cluster.partial_fill, PartialFillGuard { min_fill_ratio_bps: 8_000, ..Default::default() });
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_PARTIAL_FILL_ALLOW_PARTIAL_FILL", "true")])).unwrap();
+        assert!(Cluster::deserialize(Value::Table(table)).unwrap().partial_fill.allow_partial_fill);
+    }
+
+    #[test]
+    fn test_buy_floor() {
+        assert_eq!(Cluster::from_toml_str("rpc_url = \"x\"").unwrap().buy_floor, BuyFloor::default());
+        let cluster = Cluster::from_toml_str("rpc_url = \"x\"\n[buy_floor]\nmin_buy_lamports = 10000").unwrap();
+        assert_eq!(cluster.buy_floor, BuyFloor { min_buy_lamports: 10_000, allow_below: false });
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_BUY_FLOOR_ALLOW_BELOW", "true")])).unwrap();
+        assert!(Cluster::deserialize(Value::Table(table)).unwrap().buy_floor.allow_below);
+    }
+
+    #[test]
+    fn test_event_endpoints() {
+        let cluster = Cluster::from_toml_str("rpc_url = \"x\"").unwrap();
+        assert_eq!((cluster.ws_url.as_str(), cluster.grpc_url.as_str()), ("", ""));
+        let cluster = Cluster::from_toml_str("rpc_url = \"x\"\nws_url = \"wss://ws\"").unwrap();
+        assert_eq!(cluster.ws_url, "wss://ws");
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_GRPC_URL", "https://grpc")])).unwrap();
+        assert_eq!(Cluster::deserialize(Value::Table(table)).unwrap().grpc_url, "https://grpc");
+    }
+
+    #[test]
+    fn test_read_and_send_endpoints_default_to_rpc_url() {
+        let cluster = Cluster::from_toml_str("rpc_url = \"https://rpc\"").unwrap();
+        assert_eq!((cluster.read_endpoint(), cluster.send_endpoint()), ("https://rpc", "https://rpc"));
+        let cluster = Cluster::from_toml_str("rpc_url = \"https://rpc\"\nsend_rpc_url = \"https://premium\"").unwrap();
+        assert_eq!((cluster.read_endpoint(), cluster.send_endpoint()), ("https://rpc", "https://premium"));
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_READ_RPC_URL", "https://cheap")])).unwrap();
+        assert_eq!(Cluster::deserialize(Value::Table(table)).unwrap().read_endpoint(), "https://cheap");
+    }
+
+    #[test]
+    fn test_clients_by_label() {
+        let cluster = Cluster::from_toml_str("rpc_url = \"x\"\nuse_nextblock = true\nclients = [\"jito\", \"0slot\"]").unwrap();
+        assert_eq!(cluster.clients, vec![ClientType::Jito, ClientType::ZeroSlot]);
+        assert!([ClientType::Jito, ClientType::NextBlock, ClientType::ZeroSlot].iter().all(|client| cluster.is_enabled(client)));
+
+        let mut table = Table::new();
+        apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_RPC_URL", "x"), ("PUMPFUN_CLIENTS", "NextBlock, my-relay")])).unwrap();
+        let cluster = Cluster::deserialize(Value::Table(table)).unwrap();
+        assert_eq!(cluster.clients, vec![ClientType::NextBlock, ClientType::Custom("my-relay".to_string())]);
+        assert!(!cluster.is_enabled(&ClientType::Jito));
+
+        let err = Cluster::from_toml_str("rpc_url = \"x\"\nclients = [\"\"]").unwrap_err();
+        assert!(err.to_string().contains("client label"), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_env_value_is_named() {
+        let mut table = Table::new();
+        let err = apply_env_overrides(&mut table, "PUMPFUN", lookup(&[("PUMPFUN_USE_JITO", "yes")])).unwrap_err();
+        assert!(err.to_string().contains("PUMPFUN_USE_JITO"), "{}", err);
+    }
+
+    #[test]
+    fn test_secret_indirection() {
+        let mut table: Table = toml::from_str(
+            "rpc_url = \"x\"\nnextblock_auth_token = \"env:NB_TOKEN\"\nzeroslot_auth_token = \"plain\"",
+        ).unwrap();
+
+        resolve_secrets(&mut table, lookup(&[("NB_TOKEN", "secret")])).unwrap();
+
+        assert_eq!(table["nextblock_auth_token"].as_str(), Some("secret"));
+        assert_eq!(table["zeroslot_auth_token"].as_str(), Some("plain"));
+    }
+
+    #[test]
+    fn test_missing_secret_variable_is_named() {
+        let mut table: Table = toml::from_str("zeroslot_auth_token = \"env:MISSING_TOKEN\"").unwrap();
+        let err = resolve_secrets(&mut table, lookup(&[])).unwrap_err();
+        assert!(err.to_string().contains("MISSING_TOKEN"), "{}", err);
+    }
+}
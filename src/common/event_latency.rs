@@ -0,0 +1,463 @@
+//! Delivery latency of events per source
+//!
+//! An [`EventLatency`] shared by several subscriptions records, for every event they deliver,
+//! the wall-clock arrival time minus the block time of the event's slot, and aggregates it
+//! into a [`LatencyHistogram`] per [`EventSource`] and [`EventKind`]. Block times have second
+//! resolution, so single samples are off by up to a second; compare the distributions.
+//!
+//! Block times are cached per slot. gRPC subscriptions of a client built with
+//! `YellowstoneGrpc::with_event_latency` also stream block metadata, which supplies them
+//! without any RPC call. The slots still unknown after a resolve interval are looked up with
+//! one `getBlockTime` per slot, whatever the number of events in it, at most
+//! [`MAX_LOOKUPS_PER_BATCH`] per interval. Events of slots whose lookups keep failing are
+//! counted as unresolved.
+//!
+//! A [`EventLatencyHook`] installed with [`set_event_latency_hook`] is called with every
+//! sample, e.g. to feed a metrics histogram.
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{Arc, Mutex, RwLock, Weak},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::clock::UnixTimestamp;
+
+use super::{logs_events::PumpfunEvent, SolanaRpcClient};
+
+/// Default time events wait for their block time before it is looked up over RPC
+pub const DEFAULT_RESOLVE_INTERVAL: Duration = Duration::from_secs(1);
+/// Most `getBlockTime` calls made per resolve interval
+pub const MAX_LOOKUPS_PER_BATCH: usize = 8;
+/// Upper bounds of the histogram buckets in milliseconds; the last bucket holds the rest
+pub const BUCKET_BOUNDS_MS: [u64; 12] = [100, 200, 400, 600, 800, 1_000, 1_500, 2_000, 3_000, 5_000, 10_000, 30_000];
+
+const MAX_CACHED_SLOTS: usize = 4_096;
+const MAX_PENDING_SLOTS: usize = 1_024;
+const MAX_LOOKUP_ATTEMPTS: u32 = 5;
+
+lazy_static::lazy_static! {
+    static ref EVENT_LATENCY_HOOK: RwLock<Option<Arc<dyn EventLatencyHook>>> = RwLock::new(None);
+}
+
+/// Transport an event was delivered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+    WebSocket,
+    Grpc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Create,
+    DevTrade,
+    UserTrade,
+    BotTrade,
+}
+
+impl EventKind {
+    /// Kind and slot of `event`, `None` for events that belong to no transaction
+    pub fn of(event: &PumpfunEvent) -> Option<(Self, u64)> {
+        match event {
+            PumpfunEvent::NewToken(token) => Some((Self::Create, token.slot)),
+            PumpfunEvent::NewDevTrade(trade) => Some((Self::DevTrade, trade.slot)),
+            PumpfunEvent::NewUserTrade(trade) => Some((Self::UserTrade, trade.slot)),
+            PumpfunEvent::NewBotTrade(trade) => Some((Self::BotTrade, trade.slot)),
+            _ => None,
+        }
+    }
+}
+
+/// Latency of one delivered event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySample {
+    pub source: EventSource,
+    pub kind: EventKind,
+    pub slot: u64,
+    /// Arrival time minus block time, zero when the local clock is behind the block's
+    pub latency_ms: u64,
+}
+
+/// Receives every latency sample; implementations must not block the caller
+pub trait EventLatencyHook: Send + Sync {
+    fn event_latency(&self, sample: &LatencySample);
+}
+
+/// Installs `hook` for all recorders, or removes it with `None`
+pub fn set_event_latency_hook(hook: Option<Arc<dyn EventLatencyHook>>) {
+    *EVENT_LATENCY_HOOK.write().unwrap() = hook;
+}
+
+/// Latencies in the buckets of [`BUCKET_BOUNDS_MS`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    /// Samples per bucket, one more than there are bounds
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum_ms: u64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self { counts: vec![0; BUCKET_BOUNDS_MS.len() + 1], count: 0, sum_ms: 0, min_ms: u64::MAX, max_ms: 0 }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn observe(&mut self, latency_ms: u64) {
+        let bucket = BUCKET_BOUNDS_MS.iter().position(|bound| latency_ms <= *bound).unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ms = self.sum_ms.saturating_add(latency_ms);
+        self.min_ms = self.min_ms.min(latency_ms);
+        self.max_ms = self.max_ms.max(latency_ms);
+    }
+
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+        self.count += other.count;
+        self.sum_ms = self.sum_ms.saturating_add(other.sum_ms);
+        self.min_ms = self.min_ms.min(other.min_ms);
+        self.max_ms = self.max_ms.max(other.max_ms);
+    }
+
+    pub fn mean_ms(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.sum_ms / self.count)
+    }
+
+    /// Upper bound of the bucket holding the `quantile` (0 to 1) of the samples, capped at the
+    /// largest sample
+    pub fn quantile_ms(&self, quantile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(BUCKET_BOUNDS_MS.get(bucket).map_or(self.max_ms, |bound| (*bound).min(self.max_ms)));
+            }
+        }
+        Some(self.max_ms)
+    }
+}
+
+/// Histogram of one source and kind of events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLatency {
+    pub source: EventSource,
+    pub kind: EventKind,
+    pub histogram: LatencyHistogram,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventLatencyReport {
+    /// Sorted by source, then kind
+    pub histograms: Vec<SourceLatency>,
+    /// Events dropped because their block time could not be found
+    pub unresolved: u64,
+    /// Events waiting for their block time
+    pub pending: u64,
+    /// `getBlockTime` calls made
+    pub lookups: u64,
+}
+
+impl EventLatencyReport {
+    pub fn get(&self, source: EventSource, kind: EventKind) -> Option<&LatencyHistogram> {
+        self.histograms.iter().find(|latency| latency.source == source && latency.kind == kind).map(|latency| &latency.histogram)
+    }
+
+    /// All kinds of events of `source` together
+    pub fn source(&self, source: EventSource) -> LatencyHistogram {
+        let mut merged = LatencyHistogram::default();
+        for latency in self.histograms.iter().filter(|latency| latency.source == source) {
+            merged.merge(&latency.histogram);
+        }
+        merged
+    }
+}
+
+struct Arrival {
+    source: EventSource,
+    kind: EventKind,
+    at: SystemTime,
+}
+
+struct Pending {
+    arrivals: Vec<Arrival>,
+    first_at: SystemTime,
+    attempts: u32,
+}
+
+#[derive(Default)]
+struct State {
+    block_times: HashMap<u64, UnixTimestamp>,
+    cached_order: VecDeque<u64>,
+    pending: BTreeMap<u64, Pending>,
+    histograms: BTreeMap<(EventSource, EventKind), LatencyHistogram>,
+    unresolved: u64,
+    lookups: u64,
+}
+
+impl State {
+    fn cache(&mut self, slot: u64, block_time: UnixTimestamp) {
+        if self.block_times.insert(slot, block_time).is_none() {
+            self.cached_order.push_back(slot);
+            if self.cached_order.len() > MAX_CACHED_SLOTS {
+                if let Some(oldest) = self.cached_order.pop_front() {
+                    self.block_times.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    fn observe(&mut self, slot: u64, block_time: UnixTimestamp, arrival: Arrival, samples: &mut Vec<LatencySample>) {
+        let block_time_ms = block_time.max(0) as u64 * 1_000;
+        let arrival_ms = arrival.at.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as u64);
+        let latency_ms = arrival_ms.saturating_sub(block_time_ms);
+        self.histograms.entry((arrival.source, arrival.kind)).or_default().observe(latency_ms);
+        samples.push(LatencySample { source: arrival.source, kind: arrival.kind, slot, latency_ms });
+    }
+}
+
+/// Delivery latencies of the events of the subscriptions sharing it, see the
+/// [module docs](self)
+pub struct EventLatency {
+    rpc: Arc<SolanaRpcClient>,
+    state: Mutex<State>,
+}
+
+impl EventLatency {
+    /// Starts a recorder looking block times up on `rpc`; must be called in a Tokio runtime
+    pub fn new(rpc: Arc<SolanaRpcClient>) -> Arc<Self> {
+        Self::with_resolve_interval(rpc, DEFAULT_RESOLVE_INTERVAL)
+    }
+
+    /// Like [`EventLatency::new`], looking up block times still unknown after `interval`
+    pub fn with_resolve_interval(rpc: Arc<SolanaRpcClient>, interval: Duration) -> Arc<Self> {
+        let latency = Arc::new(Self { rpc, state: Mutex::new(State::default()) });
+        tokio::spawn(resolve_loop(Arc::downgrade(&latency), interval));
+        latency
+    }
+
+    /// Records the arrival of `event` from `source` now
+    pub fn record(&self, source: EventSource, event: &PumpfunEvent) {
+        if let Some((kind, slot)) = EventKind::of(event) {
+            self.record_arrival(source, kind, slot, SystemTime::now());
+        }
+    }
+
+    fn record_arrival(&self, source: EventSource, kind: EventKind, slot: u64, at: SystemTime) {
+        if slot == 0 {
+            return;
+        }
+        let arrival = Arrival { source, kind, at };
+        let mut samples = Vec::with_capacity(1);
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Some(block_time) = state.block_times.get(&slot).copied() {
+                state.observe(slot, block_time, arrival, &mut samples);
+            } else {
+                state.pending.entry(slot).or_insert_with(|| Pending { arrivals: vec![], first_at: at, attempts: 0 }).arrivals.push(arrival);
+                if state.pending.len() > MAX_PENDING_SLOTS {
+                    if let Some((_, dropped)) = state.pending.pop_first() {
+                        state.unresolved += dropped.arrivals.len() as u64;
+                    }
+                }
+            }
+        }
+        publish(&samples);
+    }
+
+    /// Sets the block time of `slot`, e.g. from a block metadata update, resolving the events
+    /// waiting for it
+    pub fn observe_block_time(&self, slot: u64, block_time: UnixTimestamp) {
+        let mut samples = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            state.cache(slot, block_time);
+            if let Some(pending) = state.pending.remove(&slot) {
+                for arrival in pending.arrivals {
+                    state.observe(slot, block_time, arrival, &mut samples);
+                }
+            }
+        }
+        publish(&samples);
+    }
+
+    pub fn report(&self) -> EventLatencyReport {
+        let state = self.state.lock().unwrap();
+        EventLatencyReport {
+            histograms: state
+                .histograms
+                .iter()
+                .map(|((source, kind), histogram)| SourceLatency { source: *source, kind: *kind, histogram: histogram.clone() })
+                .collect(),
+            unresolved: state.unresolved,
+            pending: state.pending.values().map(|pending| pending.arrivals.len() as u64).sum(),
+            lookups: state.lookups,
+        }
+    }
+
+    /// Looks up the block times of the oldest slots that waited at least `interval`
+    async fn resolve(&self, interval: Duration) {
+        let due: Vec<u64> = {
+            let mut state = self.state.lock().unwrap();
+            let cutoff = SystemTime::now() - interval;
+            let due: Vec<u64> = state.pending.iter().filter(|(_, pending)| pending.first_at <= cutoff).map(|(slot, _)| *slot).take(MAX_LOOKUPS_PER_BATCH).collect();
+            state.lookups += due.len() as u64;
+            due
+        };
+        let lookups = due.iter().map(|slot| self.rpc.get_block_time(*slot));
+        let results = futures::future::join_all(lookups).await;
+
+        for (slot, result) in due.into_iter().zip(results) {
+            match result {
+                Ok(block_time) => self.observe_block_time(slot, block_time),
+                Err(e) => {
+                    let mut state = self.state.lock().unwrap();
+                    let Some(pending) = state.pending.get_mut(&slot) else {
+                        continue;
+                    };
+                    // blocks are only served once confirmed, so early failures are retried
+                    pending.attempts += 1;
+                    if pending.attempts >= MAX_LOOKUP_ATTEMPTS {
+                        tracing::debug!(slot, error = %e, "dropping events whose block time was not found");
+                        let dropped = state.pending.remove(&slot).map_or(0, |pending| pending.arrivals.len());
+                        state.unresolved += dropped as u64;
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn resolve_loop(latency: Weak<EventLatency>, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let Some(latency) = latency.upgrade() else {
+            return;
+        };
+        latency.resolve(interval).await;
+    }
+}
+
+fn publish(samples: &[LatencySample]) {
+    if samples.is_empty() {
+        return;
+    }
+    if let Some(hook) = EVENT_LATENCY_HOOK.read().unwrap().clone() {
+        for sample in samples {
+            hook.event_latency(sample);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::{json, Value};
+    use solana_rpc_client::{rpc_client::RpcClientConfig, rpc_sender::{RpcSender, RpcTransportStats}};
+    use solana_rpc_client_api::{client_error::Result as ClientResult, request::RpcRequest};
+    use solana_sdk::commitment_config::CommitmentConfig;
+
+    use super::*;
+
+    /// Answers `getBlockTime` with the slot as the time, counting the calls
+    struct BlockTimes(Arc<AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl RpcSender for BlockTimes {
+        async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+            assert_eq!(request, RpcRequest::GetBlockTime);
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(params[0].clone())
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "block-times".to_string()
+        }
+    }
+
+    fn rpc() -> (Arc<SolanaRpcClient>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let rpc = SolanaRpcClient::new_sender(BlockTimes(calls.clone()), RpcClientConfig::with_commitment(CommitmentConfig::confirmed()));
+        (Arc::new(rpc), calls)
+    }
+
+    fn at_ms(unix_ms: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(unix_ms)
+    }
+
+    #[test]
+    fn test_histogram_quantiles() {
+        let mut histogram = LatencyHistogram::default();
+        assert_eq!((histogram.mean_ms(), histogram.quantile_ms(0.5)), (None, None));
+        for latency_ms in [50, 150, 350, 350, 900, 45_000] {
+            histogram.observe(latency_ms);
+        }
+        assert_eq!(histogram.counts[..4], [1, 1, 2, 0]);
+        assert_eq!(histogram.counts[BUCKET_BOUNDS_MS.len()], 1);
+        assert_eq!((histogram.min_ms, histogram.max_ms, histogram.mean_ms()), (50, 45_000, Some(7_800)));
+        assert_eq!(histogram.quantile_ms(0.5), Some(400));
+        assert_eq!(histogram.quantile_ms(0.99), Some(45_000));
+        assert_eq!(histogram.quantile_ms(0.0), Some(100));
+    }
+
+    #[tokio::test]
+    async fn test_block_meta_resolves_without_rpc() {
+        let (rpc, calls) = rpc();
+        let latency = EventLatency::with_resolve_interval(rpc, Duration::from_secs(60));
+        latency.record_arrival(EventSource::Grpc, EventKind::Create, 10, at_ms(100_400));
+        latency.record_arrival(EventSource::WebSocket, EventKind::Create, 10, at_ms(101_200));
+        assert_eq!(latency.report().pending, 2);
+
+        latency.observe_block_time(10, 100);
+        latency.record_arrival(EventSource::Grpc, EventKind::UserTrade, 10, at_ms(100_900));
+        let report = latency.report();
+        assert_eq!((report.pending, report.lookups, calls.load(Ordering::Relaxed)), (0, 0, 0));
+        assert_eq!(report.get(EventSource::Grpc, EventKind::Create).unwrap().max_ms, 400);
+        assert_eq!(report.get(EventSource::WebSocket, EventKind::Create).unwrap().max_ms, 1_200);
+        let grpc = report.source(EventSource::Grpc);
+        assert_eq!((grpc.count, grpc.min_ms, grpc.max_ms), (2, 400, 900));
+    }
+
+    #[tokio::test]
+    async fn test_lookups_are_made_once_per_slot() {
+        let (rpc, calls) = rpc();
+        let latency = EventLatency::with_resolve_interval(rpc, Duration::from_millis(10));
+        for _ in 0..50 {
+            latency.record_arrival(EventSource::WebSocket, EventKind::UserTrade, 3_000, at_ms(3_000_750));
+            latency.record_arrival(EventSource::WebSocket, EventKind::BotTrade, 3_001, at_ms(3_001_250));
+        }
+        for _ in 0..100 {
+            if latency.report().pending == 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let report = latency.report();
+        assert_eq!((report.pending, report.lookups, calls.load(Ordering::Relaxed)), (0, 2, 2));
+        assert_eq!(report.get(EventSource::WebSocket, EventKind::UserTrade).unwrap().count, 50);
+        assert_eq!(report.get(EventSource::WebSocket, EventKind::BotTrade).unwrap().mean_ms(), Some(250));
+
+        latency.record_arrival(EventSource::WebSocket, EventKind::Create, 3_000, at_ms(3_000_100));
+        assert_eq!(latency.report().source(EventSource::WebSocket).count, 101, "cached block times resolve at once");
+    }
+}
@@ -0,0 +1,155 @@
+//! Genesis hash check catching an RPC node of the wrong cluster
+//!
+//! The SDK's program constants are those of a specific cluster. Pointed at another one, e.g.
+//! a devnet RPC with mainnet accounts, trades fail deep inside with "account not found".
+//! When `Cluster::cluster_kind` is set, `PumpFun::try_new` compares the node's genesis hash
+//! with that [`ClusterKind`] first and fails with [`ClusterMismatch`] instead.
+
+use std::{collections::HashMap, fmt, str::FromStr, sync::RwLock};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use solana_sdk::hash::Hash;
+use thiserror::Error;
+
+use super::SolanaRpcClient;
+
+pub const MAINNET_BETA_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+pub const DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+
+lazy_static::lazy_static! {
+    /// Genesis hash of each RPC URL checked so far
+    static ref GENESIS_HASHES: RwLock<HashMap<String, Hash>> = RwLock::new(HashMap::new());
+}
+
+/// Cluster an RPC node is expected to serve, identified by its genesis hash
+///
+/// Written as `"mainnet-beta"`, `"devnet"`, or the base58 genesis hash of any other cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum ClusterKind {
+    #[default]
+    MainnetBeta,
+    Devnet,
+    /// A cluster with this genesis hash, e.g. a local validator
+    Custom(Hash),
+}
+
+impl ClusterKind {
+    pub fn genesis_hash(&self) -> Hash {
+        match self {
+            ClusterKind::MainnetBeta => Hash::from_str(MAINNET_BETA_GENESIS_HASH).unwrap(),
+            ClusterKind::Devnet => Hash::from_str(DEVNET_GENESIS_HASH).unwrap(),
+            ClusterKind::Custom(hash) => *hash,
+        }
+    }
+
+    /// The cluster with `genesis_hash`, named if it is a known one
+    pub fn from_genesis_hash(genesis_hash: Hash) -> Self {
+        [ClusterKind::MainnetBeta, ClusterKind::Devnet]
+            .into_iter()
+            .find(|kind| kind.genesis_hash() == genesis_hash)
+            .unwrap_or(ClusterKind::Custom(genesis_hash))
+    }
+}
+
+impl fmt::Display for ClusterKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClusterKind::MainnetBeta => f.write_str("mainnet-beta"),
+            ClusterKind::Devnet => f.write_str("devnet"),
+            ClusterKind::Custom(hash) => write!(f, "{}", hash),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Invalid cluster kind {0:?}: use mainnet-beta, devnet or a base58 genesis hash")]
+pub struct ParseClusterKindError(pub String);
+
+impl FromStr for ClusterKind {
+    type Err = ParseClusterKindError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "mainnet-beta" | "mainnet" => Ok(ClusterKind::MainnetBeta),
+            "devnet" => Ok(ClusterKind::Devnet),
+            _ => Hash::from_str(value)
+                .map(ClusterKind::from_genesis_hash)
+                .map_err(|_| ParseClusterKindError(value.to_string())),
+        }
+    }
+}
+
+impl From<ClusterKind> for String {
+    fn from(kind: ClusterKind) -> Self {
+        kind.to_string()
+    }
+}
+
+impl TryFrom<String> for ClusterKind {
+    type Error = ParseClusterKindError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// The RPC node serves another cluster than the configured one
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("RPC node is on {actual}, not {expected}; check rpc_url and cluster_kind, or set skip_genesis_check for a validator with its own program ids")]
+pub struct ClusterMismatch {
+    pub expected: ClusterKind,
+    pub actual: ClusterKind,
+}
+
+impl ClusterMismatch {
+    /// The mismatch somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&ClusterMismatch> {
+        error.chain().find_map(|cause| cause.downcast_ref::<ClusterMismatch>())
+    }
+}
+
+/// Fails unless `genesis_hash` is the one of `expected`
+pub fn ensure_cluster(expected: ClusterKind, genesis_hash: Hash) -> Result<(), ClusterMismatch> {
+    if expected.genesis_hash() == genesis_hash {
+        Ok(())
+    } else {
+        Err(ClusterMismatch { expected, actual: ClusterKind::from_genesis_hash(genesis_hash) })
+    }
+}
+
+/// Checks the node behind `rpc` serves `expected`, fetching its genesis hash once per RPC URL
+pub async fn check_cluster(rpc: &SolanaRpcClient, expected: ClusterKind) -> Result<(), anyhow::Error> {
+    let url = rpc.url();
+    let cached = GENESIS_HASHES.read().unwrap().get(&url).copied();
+    let genesis_hash = match cached {
+        Some(genesis_hash) => genesis_hash,
+        None => {
+            let genesis_hash = rpc.get_genesis_hash().await.with_context(|| format!("Failed to fetch the genesis hash of {}", url))?;
+            GENESIS_HASHES.write().unwrap().insert(url, genesis_hash);
+            genesis_hash
+        }
+    };
+    Ok(ensure_cluster(expected, genesis_hash)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_kinds_parse_and_match_their_genesis_hash() {
+        assert_eq!("mainnet-beta".parse(), Ok(ClusterKind::MainnetBeta));
+        assert_eq!(DEVNET_GENESIS_HASH.parse(), Ok(ClusterKind::Devnet));
+        let local = Hash::new_unique();
+        assert_eq!(local.to_string().parse(), Ok(ClusterKind::Custom(local)));
+        assert!("testnet".parse::<ClusterKind>().is_err());
+
+        assert!(ensure_cluster(ClusterKind::MainnetBeta, Hash::from_str(MAINNET_BETA_GENESIS_HASH).unwrap()).is_ok());
+        assert!(ensure_cluster(ClusterKind::Custom(local), local).is_ok());
+        let mismatch = ensure_cluster(ClusterKind::MainnetBeta, Hash::from_str(DEVNET_GENESIS_HASH).unwrap()).unwrap_err();
+        assert_eq!(mismatch, ClusterMismatch { expected: ClusterKind::MainnetBeta, actual: ClusterKind::Devnet });
+        assert!(mismatch.to_string().starts_with("RPC node is on devnet, not mainnet-beta"));
+    }
+}
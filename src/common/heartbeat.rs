@@ -0,0 +1,89 @@
+//! Liveness tracking for event streams
+//!
+//! A half-open connection delivers nothing without ever failing, which looks exactly like a
+//! quiet market. Subscriptions stamp a [`Heartbeat`] on every message they receive and treat a
+//! stream that stays silent for [`StreamHealth::stale_after`] as stale: they emit
+//! `PumpfunEvent::Stale` and reconnect.
+
+use std::{
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Silence after which a stream is considered stale
+pub const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(30);
+/// Interval of client-initiated gRPC pings
+pub const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(10);
+/// Pause between a stream ending and the next connection attempt
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamHealth {
+    pub stale_after: Duration,
+    /// Only used by gRPC streams; WebSocket pings are answered by the client library and
+    /// not visible to the subscription
+    pub ping_interval: Duration,
+}
+
+impl Default for StreamHealth {
+    fn default() -> Self {
+        Self { stale_after: DEFAULT_STALE_AFTER, ping_interval: DEFAULT_PING_INTERVAL }
+    }
+}
+
+/// Time of the last message received on a stream, shared between the stream and its observers
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    last_message_ms: Arc<AtomicU64>,
+}
+
+impl Heartbeat {
+    /// Starts as if a message had just been received
+    pub fn new() -> Self {
+        Self { last_message_ms: Arc::new(AtomicU64::new(now_ms())) }
+    }
+
+    pub fn beat(&self) {
+        self.last_message_ms.store(now_ms(), Ordering::Relaxed);
+    }
+
+    pub fn last_message_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.last_message_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn silent_for(&self) -> Duration {
+        SystemTime::now().duration_since(self.last_message_at()).unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn is_stale(&self, stale_after: Duration) -> bool {
+        self.silent_for() >= stale_after
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_millis() as u64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beat_resets_silence() {
+        let heartbeat = Heartbeat::new();
+        heartbeat.last_message_ms.store(now_ms() - 5_000, Ordering::Relaxed);
+        assert!(heartbeat.is_stale(Duration::from_secs(2)));
+
+        let observer = heartbeat.clone();
+        heartbeat.beat();
+        assert!(!observer.is_stale(Duration::from_secs(2)));
+        assert!(observer.silent_for() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,247 @@
+//! Per-stage latency breakdown of a trade
+//!
+//! Every trade path runs its stages through a [`StageTimer`] and returns the resulting
+//! [`LatencyReport`], which serializes with serde for structured logging.
+//!
+//! Offsets are in microseconds from the start of the trade; `started_at_unix_ms` anchors them
+//! to wall-clock time. `Submit` covers handing the transaction to one client and `Confirm`
+//! the wait for it to land; both are recorded per client.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{pumpfun::fee_recipient::FeeRecipientUsed, trade::deadline::DeadlineOutcome};
+
+/// A stage of a trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Fetching the global and bonding curve accounts (and token balances for sells)
+    Quote,
+    /// Fetching the recent blockhash
+    Blockhash,
+    /// Building and signing the transactions
+    BuildSign,
+    /// Simulating the transaction before sending it
+    Preflight,
+    /// Submitting through one client
+    Submit,
+    /// Waiting for confirmation
+    Confirm,
+}
+
+/// Start and stop of one stage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: Stage,
+    /// Client the stage ran through, for `Submit` and `Confirm` stages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Microseconds from the start of the trade to the start of the stage
+    pub start_us: u64,
+    /// Microseconds from the start of the trade to the end of the stage
+    pub end_us: u64,
+    /// Whether the stage completed without error
+    pub succeeded: bool,
+}
+
+impl StageTiming {
+    /// Builds the timing of a stage that ran between `start` and `end`
+    pub fn between(
+        stage: Stage,
+        client: Option<String>,
+        origin: Instant,
+        start: Instant,
+        end: Instant,
+        succeeded: bool,
+    ) -> Self {
+        Self {
+            stage,
+            client,
+            start_us: micros_since(origin, start),
+            end_us: micros_since(origin, end),
+            succeeded,
+        }
+    }
+
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        Duration::from_micros(self.end_us.saturating_sub(self.start_us))
+    }
+}
+
+/// Latency breakdown attached to a trade result
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyReport {
+    /// Wall-clock time the trade started, in milliseconds since the Unix epoch
+    pub started_at_unix_ms: u64,
+    /// Microseconds from the start of the trade until the report was finished
+    pub total_us: u64,
+    /// Stages in the order they were recorded
+    pub stages: Vec<StageTiming>,
+    /// Fee recipient a buy or sell paid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_recipient: Option<FeeRecipientUsed>,
+    /// Whether a trade sent with `land_by_slot` landed in time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<DeadlineOutcome>,
+}
+
+impl LatencyReport {
+    /// Returns the first recorded timing of `stage`
+    pub fn stage(&self, stage: Stage) -> Option<&StageTiming> {
+        self.stages.iter().find(|timing| timing.stage == stage)
+    }
+
+    /// Whether a transaction of the trade was confirmed through any client
+    pub fn confirmed(&self) -> bool {
+        self.stages.iter().any(|timing| timing.stage == Stage::Confirm && timing.succeeded)
+    }
+
+    /// Returns the per-client submission timings
+    pub fn submissions(&self) -> impl Iterator<Item = &StageTiming> {
+        self.stages.iter().filter(|timing| timing.stage == Stage::Submit)
+    }
+
+    #[inline]
+    pub fn total(&self) -> Duration {
+        Duration::from_micros(self.total_us)
+    }
+
+    pub fn with_fee_recipient(mut self, fee_recipient: Option<FeeRecipientUsed>) -> Self {
+        self.fee_recipient = fee_recipient;
+        self
+    }
+}
+
+/// Records stage timings relative to the start of a trade
+#[derive(Debug, Clone)]
+pub struct StageTimer {
+    origin: Instant,
+    started_at_unix_ms: u64,
+    stages: Vec<StageTiming>,
+    deadline: Option<DeadlineOutcome>,
+}
+
+impl StageTimer {
+    pub fn new() -> Self {
+        let started_at_unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        Self { origin: Instant::now(), started_at_unix_ms, stages: Vec::new(), deadline: None }
+    }
+
+    /// Returns the instant the trade started, for timing stages on other tasks
+    #[inline]
+    pub fn origin(&self) -> Instant {
+        self.origin
+    }
+
+    /// Records a stage timed elsewhere, e.g. on a spawned submission task
+    pub fn push(&mut self, timing: StageTiming) {
+        self.stages.push(timing);
+    }
+
+    /// Records whether the trade landed by its deadline slot
+    pub fn set_deadline(&mut self, deadline: DeadlineOutcome) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Awaits `future` as `stage`, recording whether it succeeded
+    pub async fn time<T, E, F>(&mut self, stage: Stage, future: F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let start = Instant::now();
+        let result = future.await;
+        self.push(StageTiming::between(stage, None, self.origin, start, Instant::now(), result.is_ok()));
+        result
+    }
+
+    /// Runs `f` as `stage`, recording whether it succeeded
+    pub fn time_sync<T, E>(&mut self, stage: Stage, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let start = Instant::now();
+        let result = f();
+        self.push(StageTiming::between(stage, None, self.origin, start, Instant::now(), result.is_ok()));
+        result
+    }
+
+    /// Stops the timer and returns the report
+    pub fn finish(self) -> LatencyReport {
+        LatencyReport {
+            started_at_unix_ms: self.started_at_unix_ms,
+            total_us: micros_since(self.origin, Instant::now()),
+            stages: self.stages,
+            fee_recipient: None,
+            deadline: self.deadline,
+        }
+    }
+}
+
+impl Default for StageTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline]
+fn micros_since(origin: Instant, at: Instant) -> u64 {
+    at.saturating_duration_since(origin).as_micros() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stage_timer_records_stages_in_order() {
+        let mut timer = StageTimer::new();
+
+        let value = timer.time(Stage::Quote, async {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Ok::<_, ()>(42)
+        }).await;
+        assert_eq!(value, Ok(42));
+
+        let failed = timer.time_sync(Stage::BuildSign, || Err::<(), _>("boom"));
+        assert!(failed.is_err());
+
+        let origin = timer.origin();
+        timer.push(StageTiming::between(Stage::Submit, Some("Jito".to_string()), origin, origin, Instant::now(), true));
+
+        let report = timer.finish();
+        let stages: Vec<Stage> = report.stages.iter().map(|timing| timing.stage).collect();
+        assert_eq!(stages, vec![Stage::Quote, Stage::BuildSign, Stage::Submit]);
+
+        let quote = report.stage(Stage::Quote).unwrap();
+        assert!(quote.succeeded);
+        assert!(quote.duration() >= Duration::from_millis(5));
+        assert!(!report.stage(Stage::BuildSign).unwrap().succeeded);
+        assert_eq!(report.submissions().count(), 1);
+        assert!(report.total_us >= quote.end_us);
+    }
+
+    #[test]
+    fn test_report_serde_round_trip() {
+        let report = LatencyReport {
+            started_at_unix_ms: 1_700_000_000_000,
+            total_us: 1_900_000,
+            stages: vec![
+                StageTiming { stage: Stage::Quote, client: None, start_us: 0, end_us: 120_000, succeeded: true },
+                StageTiming { stage: Stage::Submit, client: Some("NextBlock".to_string()), start_us: 150_000, end_us: 1_900_000, succeeded: false },
+            ],
+            fee_recipient: None,
+            deadline: None,
+        };
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("\"stage\":\"quote\""));
+        assert!(!json.contains("\"client\":null"));
+        assert_eq!(serde_json::from_str::<LatencyReport>(&json).unwrap(), report);
+    }
+}
@@ -1,7 +1,7 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
 
-use crate::error::{ClientError, ClientResult};
+use crate::error::ClientResult;
 
 #[derive(Debug)]
 pub enum DexInstruction {
@@ -11,8 +11,11 @@ pub enum DexInstruction {
     Other,
 }
 
+/// A `CreateEvent`; its borsh layout is the event's, after the discriminator
 #[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
 pub struct CreateTokenInfo {
+    /// Slot of the transaction; not part of the on-chain event
+    #[borsh(skip)]
     pub slot: u64,
     pub name: String,
     pub symbol: String,
@@ -22,8 +25,11 @@ pub struct CreateTokenInfo {
     pub user: Pubkey,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+/// A `TradeEvent`; its borsh layout is the event's, after the discriminator
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, serde::Serialize, serde::Deserialize)]
 pub struct TradeInfo {
+    /// Slot of the transaction; not part of the on-chain event
+    #[borsh(skip)]
     pub slot: u64,
     pub mint: Pubkey,
     pub sol_amount: u64,
@@ -35,6 +41,24 @@ pub struct TradeInfo {
     pub virtual_token_reserves: u64,
     pub real_sol_reserves: u64,
     pub real_token_reserves: u64,
+    /// The trade is part of the transaction that created the token, e.g. the dev's initial buy;
+    /// not part of the on-chain event
+    #[borsh(skip)]
+    #[serde(default)]
+    pub is_initial_buy: bool,
+    /// Transaction of the trade, default if the source did not say; not part of the on-chain
+    /// event
+    #[borsh(skip)]
+    #[serde(default)]
+    pub signature: Signature,
+    /// Another program made the trade by calling Pump.fun; not part of the on-chain event
+    #[borsh(skip)]
+    #[serde(default)]
+    pub via_cpi: bool,
+    /// Top-level program of a trade made through CPI; not part of the on-chain event
+    #[borsh(skip)]
+    #[serde(default)]
+    pub cpi_program: Option<Pubkey>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
@@ -45,6 +69,47 @@ pub struct CompleteInfo {
     pub timestamp: u64,
 }
 
+/// A `SetParamsEvent`, emitted when the global account's parameters change
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
+pub struct SetParamsInfo {
+    pub fee_recipient: Pubkey,
+    pub initial_virtual_token_reserves: u64,
+    pub initial_virtual_sol_reserves: u64,
+    pub initial_real_token_reserves: u64,
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64,
+}
+
+/// A Pump.fun event, decoded by its discriminator with
+/// [`decode_event`](super::logs_events::decode_event)
+#[derive(Clone, Debug, PartialEq)]
+pub enum PumpfunEventPayload {
+    Create(CreateTokenInfo),
+    Trade(TradeInfo),
+    Complete(CompleteInfo),
+    SetParams(SetParamsInfo),
+    /// An event this SDK does not know, only returned while
+    /// [`set_report_unknown_events`](super::logs_events::set_report_unknown_events) is on
+    UnknownEvent {
+        discriminator: [u8; 8],
+        /// Bytes of the event, discriminator included
+        len: usize,
+    },
+}
+
+impl PumpfunEventPayload {
+    /// The event's name in the program IDL
+    pub fn name(&self) -> &'static str {
+        match self {
+            PumpfunEventPayload::Create(_) => "CreateEvent",
+            PumpfunEventPayload::Trade(_) => "TradeEvent",
+            PumpfunEventPayload::Complete(_) => "CompleteEvent",
+            PumpfunEventPayload::SetParams(_) => "SetParamsEvent",
+            PumpfunEventPayload::UnknownEvent { .. } => "unknown event",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize)]
 pub struct SwapBaseInLog {
     pub log_type: u8,
@@ -67,24 +132,24 @@ pub trait EventTrait: Sized + std::fmt::Debug {
 
 impl EventTrait for CreateTokenInfo {
     fn from_bytes(bytes: &[u8]) -> ClientResult<Self> {
-        CreateTokenInfo::try_from_slice(bytes).map_err(|e| ClientError::Other(e.to_string()))
+        Ok(CreateTokenInfo::try_from_slice(bytes)?)
     }
 }
 
 impl EventTrait for TradeInfo {
     fn from_bytes(bytes: &[u8]) -> ClientResult<Self> {
-        TradeInfo::try_from_slice(bytes).map_err(|e| ClientError::Other(e.to_string()))
+        Ok(TradeInfo::try_from_slice(bytes)?)
     }
 }
 
 impl EventTrait for CompleteInfo {
     fn from_bytes(bytes: &[u8]) -> ClientResult<Self> {
-        CompleteInfo::try_from_slice(bytes).map_err(|e| ClientError::Other(e.to_string()))
+        Ok(CompleteInfo::try_from_slice(bytes)?)
     }
 }
 
 impl EventTrait for SwapBaseInLog {
     fn from_bytes(bytes: &[u8]) -> ClientResult<Self> {
-        SwapBaseInLog::try_from_slice(bytes).map_err(|e| ClientError::Other(e.to_string()))
+        Ok(SwapBaseInLog::try_from_slice(bytes)?)
     }
 }
\ No newline at end of file
@@ -1,17 +1,44 @@
 use base64::engine::general_purpose;
 use base64::Engine;
+use borsh::BorshDeserialize;
 use regex::Regex;
-use crate::common::logs_data::{CreateTokenInfo, TradeInfo, EventTrait};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use crate::common::logs_data::{CompleteInfo, CreateTokenInfo, TradeInfo, EventTrait, PumpfunEventPayload, SetParamsInfo};
+use crate::common::logs_parser::ensure_len;
+use crate::common::wallet_activity::WalletTrade;
+use crate::common::watermark::Watermark;
+use crate::constants::discriminators;
+use crate::error::ClientResult;
+use crate::trade::own_events::OwnTradeEvent;
 
 pub const PROGRAM_DATA: &str = "Program data: ";
 
-#[derive(Debug)]
+lazy_static::lazy_static! {
+    static ref RAY_LOG: Regex = Regex::new(r"ray_log: (?P<base64>[A-Za-z0-9+/=]+)").expect("valid ray_log pattern");
+}
+
+#[derive(Debug, Clone)]
 pub enum PumpfunEvent {
     NewToken(CreateTokenInfo),
     NewDevTrade(TradeInfo),
     NewUserTrade(TradeInfo),
     NewBotTrade(TradeInfo),
     Error(String),
+    /// Nothing was received for this long; the subscription is reconnecting
+    Stale(Duration),
+    /// A transport accepted a transaction of one of the SDK's own trades; never parsed from
+    /// the chain, see [`own_events`](crate::trade::own_events)
+    OwnTradeSubmitted(OwnTradeEvent),
+    /// A transaction of one of the SDK's own trades landed
+    OwnTradeConfirmed(OwnTradeEvent),
+    /// A transaction of one of the SDK's own trades was refused or did not land
+    OwnTradeFailed(OwnTradeEvent),
+    /// Every event of the source up to the slot was delivered, see
+    /// [`watermark`](crate::common::watermark)
+    Watermark(Watermark),
+    /// A trade of a watched wallet, see [`wallet_activity`](crate::common::wallet_activity)
+    WalletTrade(WalletTrade),
 }
 
 
@@ -27,37 +54,64 @@ pub enum DexEvent {
 // pub struct PumpEvent {}
 
 impl PumpfunEvent {
+    /// The last create and trade events of a transaction's logs, identified by their
+    /// discriminators
     pub fn parse_logs(logs: &Vec<String>) -> (Option<CreateTokenInfo>, Option<TradeInfo>) {
         let mut create_info: Option<CreateTokenInfo> = None;
         let mut trade_info: Option<TradeInfo> = None;
 
-        if !logs.is_empty() {
-            let logs_iter = logs.iter().peekable();
+        for l in logs.iter().rev() {
+            let Some(log) = l.strip_prefix(PROGRAM_DATA) else {
+                continue;
+            };
+            let Ok(bytes) = general_purpose::STANDARD.decode(log) else {
+                continue;
+            };
+            match decode_event(&bytes) {
+                Some(PumpfunEventPayload::Create(e)) if create_info.is_none() => create_info = Some(e),
+                Some(PumpfunEventPayload::Trade(e)) if trade_info.is_none() => trade_info = Some(e),
+                _ => {}
+            }
+        }
+        (create_info, trade_info)
+    }
+}
 
-            for l in logs_iter.rev() {
-                if let Some(log) = l.strip_prefix(PROGRAM_DATA) {
-                    let borsh_bytes = general_purpose::STANDARD.decode(log).unwrap();
-                    let slice: &[u8] = &borsh_bytes[8..];
+static REPORT_UNKNOWN_EVENTS: AtomicBool = AtomicBool::new(false);
 
-                    if create_info.is_none() {
-                        if let Ok(e) = CreateTokenInfo::from_bytes(slice) {
-                            create_info = Some(e);
-                            continue;
-                        }
-                    }
+/// Makes [`decode_event`] return events it does not know as
+/// [`PumpfunEventPayload::UnknownEvent`] instead of `None`, to notice new program events
+pub fn set_report_unknown_events(report: bool) {
+    REPORT_UNKNOWN_EVENTS.store(report, Ordering::Relaxed);
+}
 
-                    if trade_info.is_none() {
-                        if let Ok(e) = TradeInfo::from_bytes(slice) {
-                            trade_info = Some(e);
-                        }
-                    }
-                }
-            }
+/// Decodes the bytes of a `Program data:` log by their discriminator; `None` if they are not a
+/// Pump.fun event or do not decode
+///
+/// Fields the program appended after the known layout of an event are ignored.
+pub fn decode_event(bytes: &[u8]) -> Option<PumpfunEventPayload> {
+    match try_decode_event(bytes) {
+        Ok(PumpfunEventPayload::UnknownEvent { discriminator, len }) => {
+            tracing::debug!(?discriminator, len, "unknown event in program data");
+            REPORT_UNKNOWN_EVENTS.load(Ordering::Relaxed).then_some(PumpfunEventPayload::UnknownEvent { discriminator, len })
         }
-        (create_info, trade_info)
+        result => result.ok(),
     }
 }
 
+pub(crate) fn try_decode_event(bytes: &[u8]) -> ClientResult<PumpfunEventPayload> {
+    ensure_len(bytes, "discriminator", 0, 8)?;
+    let (discriminator, mut body) = bytes.split_at(8);
+    let discriminator: [u8; 8] = discriminator.try_into().expect("8 bytes");
+    Ok(match discriminator {
+        discriminators::CREATE_EVENT => PumpfunEventPayload::Create(CreateTokenInfo::deserialize(&mut body)?),
+        discriminators::TRADE_EVENT => PumpfunEventPayload::Trade(TradeInfo::deserialize(&mut body)?),
+        discriminators::COMPLETE_EVENT => PumpfunEventPayload::Complete(CompleteInfo::deserialize(&mut body)?),
+        discriminators::SET_PARAMS_EVENT => PumpfunEventPayload::SetParams(SetParamsInfo::deserialize(&mut body)?),
+        _ => PumpfunEventPayload::UnknownEvent { discriminator, len: bytes.len() },
+    })
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RaydiumEvent {}
 
@@ -69,11 +123,11 @@ impl RaydiumEvent {
             let logs_iter = logs.iter().peekable();
 
             for l in logs_iter.rev() {
-                let re = Regex::new(r"ray_log: (?P<base64>[A-Za-z0-9+/=]+)").unwrap();
-
-                if let Some(caps) = re.captures(l) {
+                if let Some(caps) = RAY_LOG.captures(l) {
                     if let Some(base64) = caps.name("base64") {
-                        let bytes = general_purpose::STANDARD.decode(base64.as_str()).unwrap();
+                        let Ok(bytes) = general_purpose::STANDARD.decode(base64.as_str()) else {
+                            continue;
+                        };
 
                         if let Ok(e) = T::from_bytes(&bytes) {
                             event = Some(e);
@@ -4,24 +4,38 @@ use crate::error::ClientResult;
 use solana_sdk::pubkey::Pubkey;
 pub struct LogFilter;
 
+/// What a top-level Pump.fun instruction emits, from its `Program log: Instruction:` line
+#[derive(Clone, Copy)]
+enum InstructionKind {
+    Create,
+    Trade,
+}
+
 impl LogFilter {
     const PROGRAM_ID: &'static str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
-    
+    const INVOKE_PREFIX: &'static str = "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P invoke";
+    const SUCCESS_LOG: &'static str = "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P success";
+    const INSTRUCTION_PREFIX: &'static str = "Program log: Instruction: ";
+    const DATA_PREFIX: &'static str = "Program data: ";
+
     /// Parse transaction logs and return instruction type and data
+    ///
+    /// Trades in a transaction that also creates a token are marked `is_initial_buy`. Log lines
+    /// are borrowed, not copied, and only the longest `Program data:` payload of each top-level
+    /// instruction is decoded.
     pub fn parse_instruction(logs: &[String], bot_wallet: Option<Pubkey>) -> ClientResult<Vec<DexInstruction>> {
         let mut current_instruction = None;
-        let mut program_data = String::new();
+        let mut program_data: &str = "";
         let mut invoke_depth = 0;
-        let mut last_data_len = 0;
         let mut instructions = Vec::new();
         for log in logs {
+            let log = log.as_str();
             // Check program invocation
-            if log.contains(&format!("Program {} invoke", Self::PROGRAM_ID)) {
+            if log.starts_with(Self::INVOKE_PREFIX) {
                 invoke_depth += 1;
                 if invoke_depth == 1 {  // Only reset state at top level call
                     current_instruction = None;
-                    program_data.clear();
-                    last_data_len = 0;
+                    program_data = "";
                 }
                 continue;
             }
@@ -32,57 +46,152 @@ impl LogFilter {
             }
             
             // Identify instruction type (only at top level)
-            if invoke_depth == 1 && log.contains("Program log: Instruction:") {
-                if log.contains("Create") {
-                    current_instruction = Some("create");
-                } else if log.contains("Buy") || log.contains("Sell") {
-                    current_instruction = Some("trade");
+            if invoke_depth == 1 {
+                if let Some(name) = log.strip_prefix(Self::INSTRUCTION_PREFIX) {
+                    if name.starts_with("Create") {
+                        current_instruction = Some(InstructionKind::Create);
+                    } else if name.starts_with("Buy") || name.starts_with("Sell") {
+                        current_instruction = Some(InstructionKind::Trade);
+                    }
+                    continue;
                 }
-                continue;
             }
             
-            // Collect Program data
-            if log.starts_with("Program data: ") {
-                let data = log.trim_start_matches("Program data: ");
-                if data.len() > last_data_len {
-                    program_data = data.to_string();
-                    last_data_len = data.len();
+            // Collect Program data, keeping the longest payload
+            if let Some(data) = log.strip_prefix(Self::DATA_PREFIX) {
+                if data.len() > program_data.len() {
+                    program_data = data;
                 }
+                continue;
             }
             
             // Check if program ends
-            if log.contains(&format!("Program {} success", Self::PROGRAM_ID)) {
+            if log == Self::SUCCESS_LOG {
                 invoke_depth -= 1;
-                if invoke_depth == 0 {  // Only process data when top level program ends
-                    if let Some(instruction_type) = current_instruction {
-                        if !program_data.is_empty() {
-                            match instruction_type {
-                                "create" => {
-                                    if let Ok(token_info) = parse_create_token_data(&program_data) {
-                                        instructions.push(DexInstruction::CreateToken(token_info));
-                                    }
-                                },
-                                "trade" => {
-                                    if let Ok(trade_info) = parse_trade_data(&program_data) {
-                                        if let Some(bot_wallet_pubkey) = bot_wallet {
-                                            if trade_info.user.to_string() == bot_wallet_pubkey.to_string() {
-                                                instructions.push(DexInstruction::BotTrade(trade_info));
-                                            } else {
-                                                instructions.push(DexInstruction::UserTrade(trade_info));
-                                            }
-                                        } else {
-                                            instructions.push(DexInstruction::UserTrade(trade_info));
-                                        }
-                                    }
-                                },
-                                _ => {}
+                if invoke_depth == 0 && !program_data.is_empty() {  // Only process data when top level program ends
+                    match current_instruction {
+                        Some(InstructionKind::Create) => {
+                            if let Ok(token_info) = parse_create_token_data(program_data) {
+                                instructions.push(DexInstruction::CreateToken(token_info));
+                            }
+                        }
+                        Some(InstructionKind::Trade) => {
+                            if let Ok(trade_info) = parse_trade_data(program_data) {
+                                if bot_wallet == Some(trade_info.user) {
+                                    instructions.push(DexInstruction::BotTrade(trade_info));
+                                } else {
+                                    instructions.push(DexInstruction::UserTrade(trade_info));
+                                }
                             }
                         }
+                        None => {}
                     }
                 }
             }
         }
 
+        if instructions.iter().any(|instruction| matches!(instruction, DexInstruction::CreateToken(_))) {
+            for instruction in &mut instructions {
+                if let DexInstruction::UserTrade(trade_info) | DexInstruction::BotTrade(trade_info) = instruction {
+                    trade_info.is_initial_buy = true;
+                }
+            }
+        }
+
         Ok(instructions)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+    use super::*;
+
+    const PROGRAM: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+
+    fn create_event(mint: &Pubkey, user: &Pubkey) -> String {
+        let mut data = vec![27, 114, 169, 77, 222, 235, 99, 118];
+        for field in ["Token", "TKN", "https://ipfs.io/ipfs/Qm"] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(mint.as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(user.as_ref());
+        BASE64.encode(data)
+    }
+
+    fn trade_event(mint: &Pubkey, user: &Pubkey) -> String {
+        let mut data = vec![189, 219, 127, 211, 78, 230, 97, 238];
+        data.extend_from_slice(mint.as_ref());
+        data.extend_from_slice(&100_000_000u64.to_le_bytes());
+        data.extend_from_slice(&3_500_000_000_000u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(user.as_ref());
+        for value in [1_700_000_000u64, 30_100_000_000, 1_069_500_000_000_000, 100_000_000, 789_500_000_000_000] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        BASE64.encode(data)
+    }
+
+    /// Log lines of a program instruction emitting `event`
+    fn instruction_logs(name: &str, event: String) -> Vec<String> {
+        vec![
+            format!("Program {} invoke [1]", PROGRAM),
+            format!("Program log: Instruction: {}", name),
+            "Program 11111111111111111111111111111111 invoke [2]".to_string(),
+            "Program 11111111111111111111111111111111 success".to_string(),
+            format!("Program data: {}", event),
+            format!("Program {} consumed 42000 of 200000 compute units", PROGRAM),
+            format!("Program {} success", PROGRAM),
+        ]
+    }
+
+    #[test]
+    fn test_trades_in_create_transactions_are_initial_buys() {
+        let mint = Pubkey::new_unique();
+        let dev = Pubkey::new_unique();
+        let mut create_and_buy = vec![
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]".to_string(),
+            "Program ComputeBudget111111111111111111111111111111 success".to_string(),
+        ];
+        create_and_buy.extend(instruction_logs("Create", create_event(&mint, &dev)));
+        create_and_buy.extend(instruction_logs("Buy", trade_event(&mint, &dev)));
+
+        let instructions = LogFilter::parse_instruction(&create_and_buy, None).unwrap();
+        assert!(matches!(&instructions[0], DexInstruction::CreateToken(token) if token.mint == mint));
+        assert!(matches!(&instructions[1], DexInstruction::UserTrade(trade) if trade.user == dev && trade.is_initial_buy));
+
+        let buy = instruction_logs("Buy", trade_event(&mint, &Pubkey::new_unique()));
+        let instructions = LogFilter::parse_instruction(&buy, None).unwrap();
+        assert!(matches!(&instructions[0], DexInstruction::UserTrade(trade) if !trade.is_initial_buy));
+    }
+
+    #[test]
+    fn test_markers_are_matched_exactly_and_the_longest_payload_is_parsed() {
+        assert_eq!(LogFilter::INVOKE_PREFIX, format!("Program {} invoke", LogFilter::PROGRAM_ID));
+        assert_eq!(LogFilter::SUCCESS_LOG, format!("Program {} success", LogFilter::PROGRAM_ID));
+
+        let mint = Pubkey::new_unique();
+        let bot = Pubkey::new_unique();
+        // the event is emitted through a self-CPI next to a shorter payload
+        let mut sell = instruction_logs("Sell", trade_event(&mint, &bot));
+        sell.splice(2..2, [
+            format!("Program {} invoke [2]", PROGRAM),
+            "Program data: AAAA".to_string(),
+            format!("Program {} success", PROGRAM),
+        ]);
+        let mut logs = vec![
+            "Program data: not ours".to_string(),
+            format!("Program log: mentions Program {} invoke [1]", PROGRAM),
+        ];
+        logs.extend(sell);
+        logs.extend(instruction_logs("Buy", trade_event(&mint, &Pubkey::new_unique())));
+
+        let instructions = LogFilter::parse_instruction(&logs, Some(bot)).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert!(matches!(&instructions[0], DexInstruction::BotTrade(trade) if trade.user == bot && trade.mint == mint));
+        assert!(matches!(&instructions[1], DexInstruction::UserTrade(trade) if trade.user != bot));
+    }
+}
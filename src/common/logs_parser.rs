@@ -1,10 +1,9 @@
-use std::str::FromStr;
-
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
 use crate::error::{ClientError, ClientResult};
 use crate::common::{
-    logs_data::{DexInstruction, CreateTokenInfo, TradeInfo}, 
+    logs_data::{DexInstruction, CreateTokenInfo, PumpfunEventPayload, TradeInfo},
+    logs_events::try_decode_event,
     logs_filters::LogFilter
 };
 
@@ -26,149 +25,161 @@ where
     Ok(())
 }
 
-// Add parsing function
+/// The error of event data that decoded as `payload` rather than an `expected` event
+fn unexpected_event(expected: &str, payload: &PumpfunEventPayload) -> ClientError {
+    ClientError::Parse("event data".to_string(), format!("expected a {}, found {}", expected, payload.name()))
+}
+
+/// Decodes the base64 data of a `CreateEvent`
 pub fn parse_create_token_data(data: &str) -> ClientResult<CreateTokenInfo> {
-    // First do base64 decoding
-    let decoded = BASE64.decode(data)
-        .map_err(|e| ClientError::Other(format!("Failed to decode base64: {}", e)))?;
-    
-    // Skip prefix bytes (if any)
-    let mut cursor = if decoded.len() > 8 { 8 } else { 0 };
-    
-    // Read name length and name
-    if cursor + 4 > decoded.len() {
-        return Err(ClientError::Other("Data too short for name length".to_string()));
+    match try_decode_event(&BASE64.decode(data)?)? {
+        PumpfunEventPayload::Create(token_info) => Ok(token_info),
+        other => Err(unexpected_event("CreateEvent", &other)),
+    }
+}
+
+/// Decodes the base64 data of a `TradeEvent`
+pub fn parse_trade_data(data: &str) -> ClientResult<TradeInfo> {
+    match try_decode_event(&BASE64.decode(data)?)? {
+        PumpfunEventPayload::Trade(trade_info) => Ok(trade_info),
+        other => Err(unexpected_event("TradeEvent", &other)),
+    }
+}
+
+/// Fails with [`ClientError::DataTooShort`] unless `data` holds `needed` bytes at `offset`
+pub(crate) fn ensure_len(data: &[u8], field: &'static str, offset: usize, needed: usize) -> ClientResult<()> {
+    if offset.saturating_add(needed) > data.len() {
+        return Err(ClientError::DataTooShort { field, offset, needed, len: data.len() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        time::Instant,
+    };
+
+    use super::*;
+    use crate::{
+        common::{
+            logs_data::CompleteInfo,
+            logs_events::{decode_event, set_report_unknown_events},
+        },
+        constants::discriminators,
+    };
+
+    /// Counts allocations per thread, so concurrently running tests don't skew the counts
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
     }
-    let name_len = read_u32(&decoded[cursor..]) as usize;
-    cursor += 4;
-    
-    if cursor + name_len > decoded.len() {
-        return Err(ClientError::Other(format!("Data too short for name: need {} bytes", name_len)));
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
     }
-    let name = String::from_utf8(decoded[cursor..cursor + name_len].to_vec())
-        .map_err(|e| ClientError::Other(format!("Invalid UTF-8 in name: {}", e)))?;
-    cursor += name_len;
-    
-    // Read symbol length and symbol
-    if cursor + 4 > decoded.len() {
-        return Err(ClientError::Other("Data too short for symbol length".to_string()));
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations_of<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (result, ALLOCATIONS.with(Cell::get) - before)
     }
-    let symbol_len = read_u32(&decoded[cursor..]) as usize;
-    cursor += 4;
-    
-    if cursor + symbol_len > decoded.len() {
-        return Err(ClientError::Other(format!("Data too short for symbol: need {} bytes", symbol_len)));
+
+    fn trade_data() -> String {
+        let mut data = discriminators::TRADE_EVENT.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+        data.extend_from_slice(&2_000u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&[0u8; 40]);
+        BASE64.encode(data)
     }
-    let symbol = String::from_utf8(decoded[cursor..cursor + symbol_len].to_vec())
-        .map_err(|e| ClientError::Other(format!("Invalid UTF-8 in symbol: {}", e)))?;
-    cursor += symbol_len;
-    
-    // Read URI length and URI
-    if cursor + 4 > decoded.len() {
-        return Err(ClientError::Other("Data too short for URI length".to_string()));
+
+    #[test]
+    fn test_parsing_allocates_only_the_decoded_buffer_and_strings() {
+        let trade = trade_data();
+        let (parsed, allocations) = allocations_of(|| parse_trade_data(&trade));
+        assert!(parsed.is_ok());
+        assert_eq!(allocations, 1, "only the base64 decode buffer");
+
+        let mut data = discriminators::CREATE_EVENT.to_vec();
+        for field in ["Token", "TKN", "https://ipfs.io/ipfs/Qm"] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.extend_from_slice(&[7u8; 96]);
+        let create = BASE64.encode(data);
+        let (parsed, allocations) = allocations_of(|| parse_create_token_data(&create));
+        assert_eq!(parsed.unwrap().user, Pubkey::new_from_array([7; 32]));
+        assert_eq!(allocations, 4, "the decode buffer and the three strings");
     }
-    let uri_len = read_u32(&decoded[cursor..]) as usize;
-    cursor += 4;
-    
-    if cursor + uri_len > decoded.len() {
-        return Err(ClientError::Other(format!("Data too short for URI: need {} bytes", uri_len)));
+
+    /// `cargo test --release -- --ignored --nocapture bench_parse_trade_data`
+    #[test]
+    #[ignore]
+    fn bench_parse_trade_data() {
+        const EVENTS: usize = 1_000_000;
+        let trade = trade_data();
+        let started = Instant::now();
+        let (_, allocations) = allocations_of(|| {
+            for _ in 0..EVENTS {
+                std::hint::black_box(parse_trade_data(std::hint::black_box(&trade)).unwrap());
+            }
+        });
+        let elapsed = started.elapsed();
+        println!(
+            "{} trade events in {:?}: {:.0} events/s, {:.2} allocations per event",
+            EVENTS,
+            elapsed,
+            EVENTS as f64 / elapsed.as_secs_f64(),
+            allocations as f64 / EVENTS as f64
+        );
     }
-    let uri = String::from_utf8(decoded[cursor..cursor + uri_len].to_vec())
-        .map_err(|e| ClientError::Other(format!("Invalid UTF-8 in uri: {}", e)))?;
-    cursor += uri_len;
-    
-    // Make sure there is enough data to read public keys
-    if cursor + 32 * 3 > decoded.len() {
-        return Err(ClientError::Other("Data too short for public keys".to_string()));
+
+    #[test]
+    fn test_truncated_or_mismatched_data_is_rejected() {
+        let mut data = discriminators::TRADE_EVENT.to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+
+        assert!(matches!(parse_trade_data(&BASE64.encode(&data)), Err(ClientError::BorshError(_))));
+        let err = parse_trade_data(&BASE64.encode([1, 2, 3])).unwrap_err();
+        assert!(matches!(err, ClientError::DataTooShort { field: "discriminator", offset: 0, needed: 8, len: 3 }), "{}", err);
+        assert!(matches!(parse_trade_data("not base64!"), Err(ClientError::Base64(_))));
+
+        let err = parse_create_token_data(&trade_data()).unwrap_err();
+        assert_eq!(err.to_string(), "Parse error: event data, details: expected a CreateEvent, found TradeEvent");
     }
-    
-    // Parse Mint Public Key
-    let mint = bs58::encode(&decoded[cursor..cursor+32]).into_string();
-    cursor += 32;
-
-    // Parse Bonding Curve Public Key
-    let bonding_curve = bs58::encode(&decoded[cursor..cursor+32]).into_string();
-    cursor += 32;
-
-    // Parse User Public Key
-    let user = bs58::encode(&decoded[cursor..cursor+32]).into_string();
-
-    Ok(CreateTokenInfo {
-        slot: 0,
-        name,
-        symbol,
-        uri,
-        mint: Pubkey::from_str(&mint).unwrap(),
-        bonding_curve: Pubkey::from_str(&bonding_curve).unwrap(),
-        user: Pubkey::from_str(&user).unwrap(),
-    })
-}
 
-fn read_u32(data: &[u8]) -> u32 {
-    let mut bytes = [0u8; 4];
-    bytes.copy_from_slice(&data[..4]);
-    u32::from_le_bytes(bytes)
+    #[test]
+    fn test_events_are_decoded_by_discriminator() {
+        let complete = CompleteInfo { user: Pubkey::new_unique(), mint: Pubkey::new_unique(), bonding_curve: Pubkey::new_unique(), timestamp: 1_700_000_000 };
+        let mut data = discriminators::COMPLETE_EVENT.to_vec();
+        data.extend_from_slice(&borsh::to_vec(&complete).unwrap());
+        // fields a newer program appends are ignored
+        data.extend_from_slice(&[9u8; 16]);
+        assert_eq!(decode_event(&data), Some(PumpfunEventPayload::Complete(complete)));
+
+        let mut unknown = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        unknown.extend_from_slice(&[0u8; 24]);
+        assert_eq!(decode_event(&unknown), None);
+        set_report_unknown_events(true);
+        let reported = decode_event(&unknown);
+        set_report_unknown_events(false);
+        assert_eq!(reported, Some(PumpfunEventPayload::UnknownEvent { discriminator: [1, 2, 3, 4, 5, 6, 7, 8], len: 32 }));
+    }
 }
-
-pub fn parse_trade_data(data: &str) -> ClientResult<TradeInfo> {
-    let engine = base64::engine::general_purpose::STANDARD;
-    let decoded = engine.decode(data).map_err(|e| 
-        ClientError::Parse(
-            "Failed to decode base64".to_string(),
-            e.to_string()
-        )
-    )?;
-
-    let mut cursor = 8;  // Skip prefix
-
-    // 1. Mint (32 bytes)
-    let mint = bs58::encode(&decoded[cursor..cursor + 32]).into_string();
-    cursor += 32;
-
-    // 2. Sol Amount (8 bytes)
-    let sol_amount = u64::from_le_bytes(decoded[cursor..cursor + 8].try_into().unwrap());
-    cursor += 8;
-
-    // 3. Token Amount (8 bytes)
-    let token_amount = u64::from_le_bytes(decoded[cursor..cursor + 8].try_into().unwrap());
-    cursor += 8;
-
-    // 4. Is Buy (1 byte)
-    let is_buy = decoded[cursor] != 0;
-    cursor += 1;
-
-    // 5. User (32 bytes)
-    let user = bs58::encode(&decoded[cursor..cursor + 32]).into_string();
-    cursor += 32;
-
-    // 6. Timestamp (8 bytes)
-    let timestamp = i64::from_le_bytes(decoded[cursor..cursor + 8].try_into().unwrap());
-    cursor += 8;
-
-    // 7. Virtual Sol Reserves (8 bytes)
-    let virtual_sol_reserves = u64::from_le_bytes(decoded[cursor..cursor + 8].try_into().unwrap());
-    cursor += 8;
-
-    // 8. Virtual Token Reserves (8 bytes)
-    let virtual_token_reserves = u64::from_le_bytes(decoded[cursor..cursor + 8].try_into().unwrap());
-    cursor += 8;
-
-    let real_sol_reserves = u64::from_le_bytes(decoded[cursor..cursor + 8].try_into().unwrap());
-    cursor += 8;
-
-    let real_token_reserves = u64::from_le_bytes(decoded[cursor..cursor + 8].try_into().unwrap());
-
-    Ok(TradeInfo {
-        slot: 0,
-        mint: Pubkey::from_str(&mint).unwrap(),
-        sol_amount,
-        token_amount,
-        is_buy,
-        user: Pubkey::from_str(&user).unwrap(),
-        timestamp,
-        virtual_sol_reserves,
-        virtual_token_reserves,
-        real_sol_reserves,
-        real_token_reserves,
-    })
-}
\ No newline at end of file
@@ -3,13 +3,20 @@ use solana_client::{
     rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter}
 };
 
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
-use std::sync::Arc;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::{collections::HashSet, str::FromStr, sync::Arc, time::SystemTime};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use futures::StreamExt;
-use crate::{constants, common::{
-    logs_data::DexInstruction, logs_events::DexEvent, logs_filters::LogFilter
+use thiserror::Error;
+use crate::{constants, pumpfun::token_age, common::{
+    commitment::EventCommitment,
+    event_latency::{EventLatency, EventLatencyReport, EventSource},
+    heartbeat::{Heartbeat, StreamHealth, RECONNECT_DELAY},
+    logs_data::{CreateTokenInfo, DexInstruction, TradeInfo}, logs_filters::LogFilter,
+    wallet_activity::{wallet_trades, RecentSignatures, RECENT_SIGNATURES_CAPACITY},
+    watermark::Watermarks,
+    TradeTag,
 }};
 
 use super::logs_events::PumpfunEvent;
@@ -18,6 +25,10 @@ use super::logs_events::PumpfunEvent;
 pub struct SubscriptionHandle {
     pub task: JoinHandle<()>,
     pub unsub_fn: Box<dyn Fn() + Send>,
+    /// Stamped on every message the subscription receives
+    pub heartbeat: Heartbeat,
+    /// Recorder of the delivery latency of the subscription's events, when instrumented
+    pub latency: Option<Arc<EventLatency>>,
 }
 
 impl SubscriptionHandle {
@@ -25,6 +36,40 @@ impl SubscriptionHandle {
         (self.unsub_fn)();
         self.task.abort();
     }
+
+    /// When the subscription last received a message, for external health checks
+    #[inline]
+    pub fn last_message_at(&self) -> SystemTime {
+        self.heartbeat.last_message_at()
+    }
+
+    /// Delivery latencies recorded so far, `None` unless the subscription is instrumented
+    pub fn latency_report(&self) -> Option<EventLatencyReport> {
+        self.latency.as_ref().map(|latency| latency.report())
+    }
+}
+
+/// The WebSocket provider refused to subscribe at `commitment`
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("logsSubscribe at {commitment} commitment was rejected: {reason}")]
+pub struct SubscriptionRejected {
+    pub commitment: EventCommitment,
+    pub reason: String,
+}
+
+/// Subscribes to the logs mentioning `address` and unsubscribes again, so a provider refusing
+/// `commitment` fails the setup rather than the spawned task
+async fn check_commitment(sub_client: &PubsubClient, address: String, commitment: EventCommitment) -> Result<(), SubscriptionRejected> {
+    let logs_filter = RpcTransactionLogsFilter::Mentions(vec![address]);
+    let logs_config = RpcTransactionLogsConfig { commitment: Some(commitment.into()) };
+    match sub_client.logs_subscribe(logs_filter, logs_config).await {
+        Ok((stream, unsubscribe)) => {
+            drop(stream);
+            unsubscribe().await;
+            Ok(())
+        }
+        Err(e) => Err(SubscriptionRejected { commitment, reason: e.to_string() }),
+    }
 }
 
 pub async fn create_pubsub_client(ws_url: &str) -> PubsubClient {
@@ -32,62 +77,179 @@ pub async fn create_pubsub_client(ws_url: &str) -> PubsubClient {
 }
 
 /// 启动订阅
+///
+/// Fails with [`SubscriptionRejected`] if the provider refuses `commitment`.
 pub async fn tokens_subscription<F>(
     ws_url: &str,
-    commitment: CommitmentConfig,
+    commitment: impl Into<EventCommitment>,
     callback: F,
     bot_wallet: Option<Pubkey>,
 ) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
 where
     F: Fn(PumpfunEvent) + Send + Sync + 'static,
 {
-    let program_address = constants::accounts::PUMPFUN.to_string();
-    let logs_filter = RpcTransactionLogsFilter::Mentions(vec![program_address]);
+    tokens_subscription_with_health(ws_url, commitment, callback, bot_wallet, StreamHealth::default()).await
+}
 
-    let logs_config = RpcTransactionLogsConfig {
-        commitment: Some(commitment),
-    };
+/// Like [`tokens_subscription`], recording the delivery latency of every event in `latency`
+pub async fn tokens_subscription_with_latency<F>(
+    ws_url: &str,
+    commitment: impl Into<EventCommitment>,
+    callback: F,
+    bot_wallet: Option<Pubkey>,
+    latency: Arc<EventLatency>,
+) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+where
+    F: Fn(PumpfunEvent) + Send + Sync + 'static,
+{
+    instrumented_tokens_subscription(ws_url, commitment, callback, bot_wallet, StreamHealth::default(), Some(latency), None).await
+}
 
-    // Create PubsubClient
-    let sub_client = Arc::new(PubsubClient::new(ws_url).await.unwrap());
+/// Like [`tokens_subscription`], tracking the delivered slots in `watermarks` and recording a
+/// gap whenever the subscription reconnects, see [`watermark`](crate::common::watermark)
+pub async fn tokens_subscription_with_watermarks<F>(
+    ws_url: &str,
+    commitment: impl Into<EventCommitment>,
+    callback: F,
+    bot_wallet: Option<Pubkey>,
+    watermarks: Arc<Watermarks>,
+) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+where
+    F: Fn(PumpfunEvent) + Send + Sync + 'static,
+{
+    instrumented_tokens_subscription(ws_url, commitment, callback, bot_wallet, StreamHealth::default(), None, Some(watermarks)).await
+}
+
+/// Like [`tokens_subscription`], with a custom staleness threshold
+///
+/// When the stream ends, or nothing arrives for `health.stale_after`, the callback receives
+/// `PumpfunEvent::Error` or `PumpfunEvent::Stale` and the subscription reconnects.
+pub async fn tokens_subscription_with_health<F>(
+    ws_url: &str,
+    commitment: impl Into<EventCommitment>,
+    callback: F,
+    bot_wallet: Option<Pubkey>,
+    health: StreamHealth,
+) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+where
+    F: Fn(PumpfunEvent) + Send + Sync + 'static,
+{
+    instrumented_tokens_subscription(ws_url, commitment, callback, bot_wallet, health, None, None).await
+}
+
+async fn instrumented_tokens_subscription<F>(
+    ws_url: &str,
+    commitment: impl Into<EventCommitment>,
+    callback: F,
+    bot_wallet: Option<Pubkey>,
+    health: StreamHealth,
+    latency: Option<Arc<EventLatency>>,
+    watermarks: Option<Arc<Watermarks>>,
+) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+where
+    F: Fn(PumpfunEvent) + Send + Sync + 'static,
+{
+    let program_address = constants::accounts::PUMPFUN.to_string();
+    let commitment = commitment.into();
 
-    let sub_client_clone = Arc::clone(&sub_client);
+    // Create PubsubClient; later connections are made by the task when reconnecting
+    let sub_client = PubsubClient::new(ws_url).await?;
+    check_commitment(&sub_client, program_address.clone(), commitment).await?;
+    let ws_url = ws_url.to_string();
 
     // Create channel for unsubscribe
     let (unsub_tx, _) = mpsc::channel(1);
 
+    let heartbeat = Heartbeat::new();
+    let task_heartbeat = heartbeat.clone();
+    let task_latency = latency.clone();
+    let callback = move |event: PumpfunEvent| {
+        if let Some(latency) = &task_latency {
+            latency.record(EventSource::WebSocket, &event);
+        }
+        callback(event);
+    };
+    let task_watermarks = watermarks.clone();
+    let delivered = move |slot: u64| {
+        if let Some(watermarks) = &watermarks {
+            watermarks.observe(EventSource::WebSocket, slot);
+        }
+    };
+
     // Start subscription task
     let task = tokio::spawn(async move {
-        let (mut stream, _) = sub_client_clone.logs_subscribe(logs_filter, logs_config).await.unwrap();
-
+        let mut next_client = Some(sub_client);
         loop {
-            let msg = stream.next().await;
-            match msg {
-                Some(msg) => {
-                    if let Some(_err) = msg.value.err {
+            let sub_client = match next_client.take() {
+                Some(sub_client) => sub_client,
+                None => match PubsubClient::new(&ws_url).await {
+                    Ok(sub_client) => sub_client,
+                    Err(e) => {
+                        callback(PumpfunEvent::Error(format!("Failed to reconnect to {}: {}", ws_url, e)));
+                        tokio::time::sleep(RECONNECT_DELAY).await;
                         continue;
                     }
+                },
+            };
 
-                    let instructions = LogFilter::parse_instruction(&msg.value.logs, bot_wallet).unwrap();
-                    for instruction in instructions {
-                        match instruction {
-                            DexInstruction::CreateToken(token_info) => {
-                                callback(PumpfunEvent::NewToken(token_info));
+            let logs_filter = RpcTransactionLogsFilter::Mentions(vec![program_address.clone()]);
+            let logs_config = RpcTransactionLogsConfig { commitment: Some(commitment.into()) };
+            match sub_client.logs_subscribe(logs_filter, logs_config).await {
+                Ok((mut stream, _)) => {
+                    task_heartbeat.beat();
+                    loop {
+                        let msg = match tokio::time::timeout(health.stale_after, stream.next()).await {
+                            Ok(Some(msg)) => msg,
+                            Ok(None) => {
+                                callback(PumpfunEvent::Error("Token subscription stream ended".to_string()));
+                                break;
+                            }
+                            Err(_) => {
+                                callback(PumpfunEvent::Stale(task_heartbeat.silent_for()));
+                                break;
                             }
-                            DexInstruction::UserTrade(trade_info) => {
-                                callback(PumpfunEvent::NewUserTrade(trade_info));
+                        };
+                        task_heartbeat.beat();
+
+                        if let Some(_err) = msg.value.err {
+                            delivered(msg.context.slot);
+                            continue;
+                        }
+
+                        let instructions = match LogFilter::parse_instruction(&msg.value.logs, bot_wallet) {
+                            Ok(instructions) => instructions,
+                            Err(e) => {
+                                callback(PumpfunEvent::Error(format!("Failed to parse logs of {}: {}", msg.value.signature, e)));
+                                delivered(msg.context.slot);
+                                continue;
                             }
-                            DexInstruction::BotTrade(trade_info) => {
-                                callback(PumpfunEvent::NewBotTrade(trade_info));
+                        };
+                        let signature = Signature::from_str(&msg.value.signature).unwrap_or_default();
+                        for instruction in instructions {
+                            match instruction {
+                                DexInstruction::CreateToken(token_info) => {
+                                    let token_info = CreateTokenInfo { slot: msg.context.slot, ..token_info };
+                                    token_age::record_create_event(&token_info);
+                                    callback(PumpfunEvent::NewToken(token_info));
+                                }
+                                DexInstruction::UserTrade(trade_info) => {
+                                    callback(PumpfunEvent::NewUserTrade(TradeInfo { signature, slot: msg.context.slot, ..trade_info }));
+                                }
+                                DexInstruction::BotTrade(trade_info) => {
+                                    callback(PumpfunEvent::NewBotTrade(TradeInfo { signature, slot: msg.context.slot, ..trade_info }));
+                                }
+                                _ => {}
                             }
-                            _ => {}
                         }
+                        delivered(msg.context.slot);
                     }
                 }
-                None => {
-                    println!("Token subscription stream ended");
-                }
-            }   
+                Err(e) => callback(PumpfunEvent::Error(SubscriptionRejected { commitment, reason: e.to_string() }.to_string())),
+            }
+            if let Some(watermarks) = &task_watermarks {
+                watermarks.disconnected(EventSource::WebSocket);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
         }
     });
 
@@ -97,6 +259,84 @@ where
         unsub_fn: Box::new(move || {
             let _ = unsub_tx.try_send(());
         }),
+        heartbeat,
+        latency,
+    })
+}
+
+/// Subscribe to the Pump.fun trades of `wallets`
+///
+/// RPC nodes accept a single address per `mentions` filter, so each wallet gets its own logs
+/// subscription on a shared connection. Transactions involving several watched wallets are
+/// delivered once per wallet and de-duplicated by signature. Trades are passed to `callback`
+/// as `PumpfunEvent::WalletTrade`; a wallet that can't be subscribed and the end of the stream
+/// are reported as `PumpfunEvent::Error`. Fails with [`SubscriptionRejected`] if the provider
+/// refuses `commitment`.
+pub async fn wallet_activity_subscription<F>(
+    ws_url: &str,
+    commitment: impl Into<EventCommitment>,
+    wallets: HashSet<Pubkey>,
+    trade_tag: Option<TradeTag>,
+    callback: F,
+) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+where
+    F: Fn(PumpfunEvent) + Send + Sync + 'static,
+{
+    let commitment = commitment.into();
+    let sub_client = Arc::new(PubsubClient::new(ws_url).await?);
+    if let Some(wallet) = wallets.iter().next() {
+        check_commitment(&sub_client, wallet.to_string(), commitment).await?;
+    }
+
+    // Create channel for unsubscribe
+    let (unsub_tx, _) = mpsc::channel(1);
+
+    let heartbeat = Heartbeat::new();
+    let task_heartbeat = heartbeat.clone();
+
+    let task = tokio::spawn(async move {
+        let mut streams = Vec::with_capacity(wallets.len());
+        for wallet in &wallets {
+            let logs_filter = RpcTransactionLogsFilter::Mentions(vec![wallet.to_string()]);
+            let logs_config = RpcTransactionLogsConfig { commitment: Some(commitment.into()) };
+            match sub_client.logs_subscribe(logs_filter, logs_config).await {
+                Ok((stream, _)) => streams.push(stream),
+                Err(e) => {
+                    tracing::warn!(%wallet, error = %e, "failed to subscribe to the wallet's logs");
+                    callback(PumpfunEvent::Error(format!("Failed to subscribe to wallet {}: {}", wallet, e)));
+                }
+            }
+        }
+
+        let mut stream = futures::stream::select_all(streams);
+        let mut recent = RecentSignatures::new(RECENT_SIGNATURES_CAPACITY);
+        while let Some(msg) = stream.next().await {
+            task_heartbeat.beat();
+            if msg.value.err.is_some() {
+                continue;
+            }
+            let Ok(signature) = Signature::from_str(&msg.value.signature) else {
+                continue;
+            };
+            if !recent.insert(signature) {
+                continue;
+            }
+
+            for trade in wallet_trades(signature, msg.context.slot, &msg.value.logs, &wallets, trade_tag.as_ref()) {
+                callback(PumpfunEvent::WalletTrade(trade));
+            }
+        }
+        tracing::warn!("wallet activity subscription stream ended");
+        callback(PumpfunEvent::Error("Wallet activity subscription stream ended".to_string()));
+    });
+
+    Ok(SubscriptionHandle {
+        task,
+        unsub_fn: Box::new(move || {
+            let _ = unsub_tx.try_send(());
+        }),
+        heartbeat,
+        latency: None,
     })
 }
 
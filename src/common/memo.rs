@@ -0,0 +1,94 @@
+//! SPL Memo instructions for attaching text to SDK transactions
+
+use anyhow::anyhow;
+use solana_sdk::instruction::Instruction;
+
+use crate::constants::accounts::MEMO_PROGRAM;
+
+/// Longest memo, in bytes, that still fits alongside a typical trade in one transaction
+pub const MAX_MEMO_LEN: usize = 566;
+
+/// Builds an SPL Memo instruction carrying `text`
+///
+/// The memo requires no signers, so it can be appended to any transaction, e.g. through
+/// the `extra_instructions` of the buy, sell and create builders.
+pub fn memo_instruction(text: &str) -> Instruction {
+    Instruction::new_with_bytes(MEMO_PROGRAM, text.as_bytes(), vec![])
+}
+
+/// Memo attached to every transaction the client sends, for attributing trades
+///
+/// The memo instruction is built once when the tag is created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeTag {
+    instruction: Instruction,
+}
+
+impl TradeTag {
+    pub fn new(tag: &str) -> Result<Self, anyhow::Error> {
+        if tag.is_empty() {
+            return Err(anyhow!("Trade tag cannot be empty"));
+        }
+        if tag.len() > MAX_MEMO_LEN {
+            return Err(anyhow!("Trade tag is {} bytes, at most {} are allowed", tag.len(), MAX_MEMO_LEN));
+        }
+        Ok(Self { instruction: memo_instruction(tag) })
+    }
+
+    /// Returns the tag text
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // Built from a `&str` in `new`
+        std::str::from_utf8(&self.instruction.data).unwrap_or_default()
+    }
+
+    /// Returns the memo instruction carrying the tag
+    #[inline]
+    pub fn instruction(&self) -> Instruction {
+        self.instruction.clone()
+    }
+
+    /// Whether transaction `logs` show the tag's memo
+    ///
+    /// The memo program logs each memo as `Memo (len N): "text"`, with `text` debug-escaped.
+    pub fn appears_in(&self, logs: &[String]) -> bool {
+        let memo = format!("Memo (len {}): {:?}", self.as_str().len(), self.as_str());
+        logs.iter().any(|log| log.strip_prefix("Program log: ") == Some(memo.as_str()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memo_instruction() {
+        let instruction = memo_instruction("order-42");
+        assert_eq!(instruction.program_id, MEMO_PROGRAM);
+        assert_eq!(instruction.data, b"order-42");
+        assert!(instruction.accounts.is_empty());
+    }
+
+    #[test]
+    fn test_trade_tag_length_limit() {
+        let tag = TradeTag::new("bot-7").unwrap();
+        assert_eq!(tag.as_str(), "bot-7");
+        assert_eq!(tag.instruction(), memo_instruction("bot-7"));
+
+        assert!(TradeTag::new("").is_err());
+        assert!(TradeTag::new(&"x".repeat(MAX_MEMO_LEN)).is_ok());
+        let err = TradeTag::new(&"x".repeat(MAX_MEMO_LEN + 1)).unwrap_err();
+        assert!(err.to_string().contains("567 bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_trade_tag_appears_in_logs() {
+        let tag = TradeTag::new("bot \"7\"").unwrap();
+        let logs = vec![
+            format!("Program {} invoke [1]", MEMO_PROGRAM),
+            "Program log: Memo (len 7): \"bot \\\"7\\\"\"".to_string(),
+        ];
+        assert!(tag.appears_in(&logs));
+        assert!(!TradeTag::new("bot-7").unwrap().appears_in(&logs));
+    }
+}
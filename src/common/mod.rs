@@ -1,8 +1,34 @@
+pub mod balance_watch;
+pub mod cache;
+pub mod cache_stats;
+pub mod commitment;
+pub mod config;
+pub mod event_latency;
+pub mod genesis;
+pub mod heartbeat;
+pub mod latency;
 pub mod logs_data;
+#[doc(hidden)]
 pub mod logs_parser;
 pub mod logs_filters;
 pub mod logs_subscribe;
 pub mod logs_events;
+pub mod memo;
+pub mod reorder;
+pub mod replay;
+pub mod secret;
+pub mod self_test;
+pub mod stats;
+#[doc(hidden)]
+pub mod tasks;
+pub(crate) mod tls;
+pub mod tx_packing;
 pub mod types;
+#[doc(hidden)]
+pub mod user_agent;
+pub mod wallet_activity;
+pub mod watchlist;
+pub mod watermark;
 
 pub use types::*;
+pub use memo::{memo_instruction, TradeTag};
@@ -0,0 +1,204 @@
+//! Slot-ordered delivery of events merged from several sources
+//!
+//! WebSocket, gRPC and backfill sources deliver the same chain in different orders. A
+//! [`ReorderBuffer`] holds events for a short window and releases them sorted by
+//! [`OrderKey`], trading a little latency for ordering: an event is released once the newest
+//! slot seen is `window_slots` past it, or after waiting `max_delay`. Events older than what
+//! was already released are delivered immediately, flagged `out_of_order`.
+//!
+//! Sources that overlap within the window deliver the same key twice; the second copy is
+//! dropped. Late duplicates still need a signature dedup in front of the buffer.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use solana_sdk::signature::Signature;
+use tokio::sync::mpsc;
+
+/// How often [`reorder_events`] releases events whose delay ran out
+const RELEASE_TICK: Duration = Duration::from_millis(50);
+
+/// Position of an event on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderKey {
+    pub slot: u64,
+    pub signature: Signature,
+    /// Index of the event among the events of its transaction
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReorderConfig {
+    /// Slots an event is held for while newer slots arrive
+    pub window_slots: u64,
+    /// Longest an event is held when no newer slots arrive
+    pub max_delay: Duration,
+}
+
+impl Default for ReorderConfig {
+    fn default() -> Self {
+        Self { window_slots: 2, max_delay: Duration::from_secs(1) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ordered<T> {
+    pub key: OrderKey,
+    pub event: T,
+    /// The event arrived after later events were released
+    pub out_of_order: bool,
+    /// Time the event spent in the buffer
+    pub held_for: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReorderStats {
+    pub delivered: u64,
+    pub out_of_order: u64,
+    /// Copies of a key that was still buffered
+    pub duplicates: u64,
+    pub max_held_for: Duration,
+}
+
+pub struct ReorderBuffer<T> {
+    config: ReorderConfig,
+    pending: BTreeMap<OrderKey, (T, Instant)>,
+    newest_slot: u64,
+    last_released: Option<OrderKey>,
+    stats: ReorderStats,
+}
+
+impl<T> ReorderBuffer<T> {
+    pub fn new(config: ReorderConfig) -> Self {
+        Self { config, pending: BTreeMap::new(), newest_slot: 0, last_released: None, stats: ReorderStats::default() }
+    }
+
+    /// Buffers `event` received at `now`, returning the events it makes due, in order
+    pub fn push(&mut self, key: OrderKey, event: T, now: Instant) -> Vec<Ordered<T>> {
+        if self.last_released.is_some_and(|last| key <= last) {
+            self.stats.delivered += 1;
+            self.stats.out_of_order += 1;
+            return vec![Ordered { key, event, out_of_order: true, held_for: Duration::ZERO }];
+        }
+        if self.pending.contains_key(&key) {
+            self.stats.duplicates += 1;
+            return vec![];
+        }
+
+        self.newest_slot = self.newest_slot.max(key.slot);
+        self.pending.insert(key, (event, now));
+        self.release_due(now)
+    }
+
+    /// Releases, in order, every event whose window or delay has passed, along with the events
+    /// sorted before it
+    pub fn release_due(&mut self, now: Instant) -> Vec<Ordered<T>> {
+        let due = self
+            .pending
+            .iter()
+            .filter(|(key, (_, received))| {
+                key.slot + self.config.window_slots <= self.newest_slot || now.duration_since(*received) >= self.config.max_delay
+            })
+            .map(|(key, _)| *key)
+            .last();
+        match due {
+            Some(due) => self.release_through(Some(due), now),
+            None => vec![],
+        }
+    }
+
+    /// Releases everything still buffered, in order
+    pub fn drain(&mut self, now: Instant) -> Vec<Ordered<T>> {
+        self.release_through(None, now)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn stats(&self) -> ReorderStats {
+        self.stats
+    }
+
+    fn release_through(&mut self, last: Option<OrderKey>, now: Instant) -> Vec<Ordered<T>> {
+        let rest = match last.and_then(|last| self.pending.range(last..).nth(1).map(|(key, _)| *key)) {
+            Some(first_kept) => self.pending.split_off(&first_kept),
+            None => BTreeMap::new(),
+        };
+        let released = std::mem::replace(&mut self.pending, rest);
+
+        released
+            .into_iter()
+            .map(|(key, (event, received))| {
+                let held_for = now.duration_since(received);
+                self.stats.delivered += 1;
+                self.stats.max_held_for = self.stats.max_held_for.max(held_for);
+                self.last_released = Some(key);
+                Ordered { key, event, out_of_order: false, held_for }
+            })
+            .collect()
+    }
+}
+
+/// Delivers the events of `input` to `callback` in order until `input` closes, then
+/// delivers what is still buffered
+pub async fn reorder_events<T, F>(mut input: mpsc::Receiver<(OrderKey, T)>, config: ReorderConfig, callback: F)
+where
+    F: Fn(Ordered<T>),
+{
+    let mut buffer = ReorderBuffer::new(config);
+    let mut tick = tokio::time::interval(RELEASE_TICK);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        let released = tokio::select! {
+            item = input.recv() => match item {
+                Some((key, event)) => buffer.push(key, event, Instant::now()),
+                None => {
+                    buffer.drain(Instant::now()).into_iter().for_each(&callback);
+                    return;
+                }
+            },
+            _ = tick.tick() => buffer.release_due(Instant::now()),
+        };
+        released.into_iter().for_each(&callback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(slot: u64, index: u32) -> OrderKey {
+        OrderKey { slot, signature: Signature::default(), index }
+    }
+
+    #[test]
+    fn test_events_are_released_in_slot_order() {
+        let mut buffer = ReorderBuffer::new(ReorderConfig::default());
+        let now = Instant::now();
+
+        assert!(buffer.push(key(11, 0), "b", now).is_empty());
+        assert!(buffer.push(key(10, 1), "a2", now).is_empty());
+        assert!(buffer.push(key(10, 0), "a1", now).is_empty());
+        assert!(buffer.push(key(10, 0), "a1", now).is_empty());
+
+        let released: Vec<&str> = buffer.push(key(12, 0), "c", now).into_iter().map(|ordered| ordered.event).collect();
+        assert_eq!(released, vec!["a1", "a2"]);
+        assert_eq!(buffer.stats().duplicates, 1);
+
+        let late = buffer.push(key(9, 0), "late", now);
+        assert!(late[0].out_of_order);
+
+        let released: Vec<&str> = buffer.release_due(now + Duration::from_secs(1)).into_iter().map(|ordered| ordered.event).collect();
+        assert_eq!(released, vec!["b", "c"]);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.stats().out_of_order, 1);
+    }
+}
@@ -0,0 +1,546 @@
+//! Replaying recorded events to backtest strategies
+//!
+//! A [`ReplaySource`] reads the JSON lines `pumpfun watch --json` prints and emits them again as
+//! [`PumpfunEvent`]s, through a callback like the live subscriptions or as a stream, paced by
+//! their original timing, a multiple of it, or as fast as possible. Lines are timed by their
+//! `received_at_ms`, or for trades recorded without it by their on-chain `timestamp`.
+//!
+//! [`ReplaySource::rpc_client`] is an RPC client answering from the replayed market: the
+//! bonding curve of each mint is the one its latest replayed trade left, and transactions are
+//! recorded rather than sent and confirm in the slot being replayed. Set it as the `rpc` of a
+//! `PumpFun` built with `skip_genesis_check` and no fee clients, and buys and sells (with a
+//! token amount) run against history unmodified. The SDK's own trades found in a recording
+//! are not replayed.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::{stream, Stream, StreamExt};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_rpc_client::{
+    rpc_client::RpcClientConfig,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client_api::{
+    client_error::Result as ClientResult,
+    request::{RpcError, RpcRequest},
+};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    hash::hashv,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use super::{
+    logs_data::{CreateTokenInfo, TradeInfo},
+    logs_events::PumpfunEvent,
+    SolanaRpcClient,
+};
+use crate::{
+    accounts::{BondingCurveAccount, GlobalAccount},
+    constants,
+    pumpfun::common::{get_bonding_curve_pda, get_global_pda},
+};
+
+/// Balance of the replay wallet unless set with [`ReplaySource::with_balance`]
+const DEFAULT_BALANCE: u64 = 100 * constants::tokens::LAMPORTS_PER_SOL;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    /// A line is not JSON or not a valid event, by 1-based line number
+    Parse { line: usize, error: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "failed to read the recording: {}", e),
+            ReplayError::Parse { line, error } => write!(f, "invalid event on line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(e: std::io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+/// How fast a recording is replayed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReplaySpeed {
+    /// With the recorded time between events
+    Original,
+    /// With the recorded time between events divided by the multiplier
+    Multiplier(f64),
+    /// Without waiting between events
+    #[default]
+    AsFastAsPossible,
+}
+
+impl ReplaySpeed {
+    fn delay(self, recorded: Duration) -> Duration {
+        match self {
+            ReplaySpeed::Original => recorded,
+            ReplaySpeed::Multiplier(multiplier) if multiplier > 0.0 => recorded.div_f64(multiplier),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// An event of a recording and when it was received, in unix milliseconds, if known
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    pub at_ms: Option<u64>,
+    pub event: PumpfunEvent,
+}
+
+/// A transaction sent through the replay RPC client
+#[derive(Debug, Clone)]
+pub struct ReplayedSend {
+    /// Slot being replayed when it was sent
+    pub slot: u64,
+    pub signature: Signature,
+    pub transaction: VersionedTransaction,
+}
+
+/// The market as of the event being replayed
+#[derive(Debug, Default)]
+struct Market {
+    slot: u64,
+    global: Option<GlobalAccount>,
+    balance: u64,
+    /// Bonding curves by bonding curve address
+    curves: HashMap<Pubkey, BondingCurveAccount>,
+    sent: Vec<ReplayedSend>,
+}
+
+impl Market {
+    fn apply(&mut self, event: &PumpfunEvent) {
+        match event {
+            PumpfunEvent::NewToken(token) => {
+                self.slot = self.slot.max(token.slot);
+                if let (Some(global), Some(address)) = (&self.global, get_bonding_curve_pda(&token.mint)) {
+                    let curve = BondingCurveAccount::new(
+                        0,
+                        global.initial_virtual_token_reserves,
+                        global.initial_virtual_sol_reserves,
+                        global.initial_real_token_reserves,
+                        0,
+                        global.token_total_supply,
+                        false,
+                    );
+                    self.curves.entry(address).or_insert(curve);
+                }
+            }
+            PumpfunEvent::NewDevTrade(trade) | PumpfunEvent::NewUserTrade(trade) | PumpfunEvent::NewBotTrade(trade) => {
+                self.slot = self.slot.max(trade.slot);
+                if let Some(address) = get_bonding_curve_pda(&trade.mint) {
+                    let curve = BondingCurveAccount::new(
+                        0,
+                        trade.virtual_token_reserves,
+                        trade.virtual_sol_reserves,
+                        trade.real_token_reserves,
+                        trade.real_sol_reserves,
+                        constants::tokens::TOTAL_SUPPLY,
+                        trade.real_token_reserves == 0,
+                    );
+                    self.curves.insert(address, curve);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn account(&self, address: &Pubkey) -> Option<Account> {
+        let data = if *address == get_global_pda() {
+            bincode::serialize(self.global.as_ref()?).ok()?
+        } else {
+            borsh::to_vec(self.curves.get(address)?).ok()?
+        };
+        let lamports = Rent::default().minimum_balance(data.len());
+        Some(Account { lamports, data, owner: constants::accounts::PUMPFUN, ..Default::default() })
+    }
+}
+
+/// RPC node serving the replayed market to a `PumpFun` client
+struct ReplayRpc {
+    market: Arc<Mutex<Market>>,
+}
+
+impl ReplayRpc {
+    fn encoded_account(market: &Market, address: &Value) -> Value {
+        let account = address
+            .as_str()
+            .and_then(|address| address.parse::<Pubkey>().ok())
+            .and_then(|address| Some(UiAccount::encode(&address, &market.account(&address)?, UiAccountEncoding::Base64, None, None)));
+        json!(account)
+    }
+
+    fn send_transaction(market: &mut Market, params: &Value) -> ClientResult<Value> {
+        let encoded = params[0].as_str().ok_or_else(|| RpcError::RpcRequestError("missing transaction".to_string()))?;
+        let bytes = STANDARD.decode(encoded).map_err(|e| RpcError::RpcRequestError(e.to_string()))?;
+        let transaction: VersionedTransaction = bincode::deserialize(&bytes).map_err(|e| RpcError::RpcRequestError(e.to_string()))?;
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        market.sent.push(ReplayedSend { slot: market.slot, signature, transaction });
+        Ok(json!(signature.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for ReplayRpc {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let mut market = self.market.lock().unwrap();
+        let context = json!({ "slot": market.slot });
+        let value = match request {
+            RpcRequest::GetAccountInfo => Self::encoded_account(&market, &params[0]),
+            RpcRequest::GetMultipleAccounts => {
+                let addresses = params[0].as_array().cloned().unwrap_or_default();
+                json!(addresses.iter().map(|address| Self::encoded_account(&market, address)).collect::<Vec<_>>())
+            }
+            RpcRequest::GetBalance => json!(market.balance),
+            RpcRequest::GetLatestBlockhash => json!({
+                "blockhash": hashv(&[&market.slot.to_le_bytes()]).to_string(),
+                "lastValidBlockHeight": market.slot + 150,
+            }),
+            RpcRequest::GetSignatureStatuses => {
+                let statuses = params[0].as_array().cloned().unwrap_or_default();
+                json!(statuses
+                    .iter()
+                    .map(|signature| {
+                        let sent = market.sent.iter().find(|sent| signature.as_str() == Some(sent.signature.to_string().as_str()))?;
+                        Some(json!({ "slot": sent.slot, "confirmations": null, "err": null, "status": { "Ok": null }, "confirmationStatus": "confirmed" }))
+                    })
+                    .collect::<Vec<_>>())
+            }
+            RpcRequest::SimulateTransaction => json!({ "err": null, "logs": [], "accounts": null, "unitsConsumed": 0, "returnData": null }),
+            RpcRequest::SendTransaction => return Self::send_transaction(&mut market, &params),
+            RpcRequest::GetSlot | RpcRequest::GetBlockHeight => return Ok(json!(market.slot)),
+            RpcRequest::GetMinimumBalanceForRentExemption => return Ok(json!(Rent::default().minimum_balance(params[0].as_u64().unwrap_or_default() as usize))),
+            RpcRequest::GetVersion => return Ok(json!({ "solana-core": "2.1.16", "feature-set": 0 })),
+            request => return Err(RpcError::RpcRequestError(format!("{} is not served by a replay", request)).into()),
+        };
+        Ok(json!({ "context": context, "value": value }))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "replay".to_string()
+    }
+}
+
+/// A recording of events to replay
+pub struct ReplaySource {
+    events: Vec<RecordedEvent>,
+    skipped: usize,
+    speed: ReplaySpeed,
+    market: Arc<Mutex<Market>>,
+}
+
+impl ReplaySource {
+    /// Reads the recording at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a recording, one JSON event per line; blank lines, lines that are not events and
+    /// the SDK's own trades are skipped
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, ReplayError> {
+        let (mut events, mut skipped) = (Vec::new(), 0);
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parse_error = |error: String| ReplayError::Parse { line: index + 1, error };
+            let value: Value = serde_json::from_str(&line).map_err(|e| parse_error(e.to_string()))?;
+            match parse_event(value).map_err(|e| parse_error(e.to_string()))? {
+                Some(event) => events.push(event),
+                None => skipped += 1,
+            }
+        }
+        let market = Market { balance: DEFAULT_BALANCE, ..Default::default() };
+        Ok(Self { events, skipped, speed: ReplaySpeed::default(), market: Arc::new(Mutex::new(market)) })
+    }
+
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Global account served by [`ReplaySource::rpc_client`]; new tokens start on its initial
+    /// reserves. Without it, trades fail to read the global account
+    pub fn with_global(self, global: GlobalAccount) -> Self {
+        self.market.lock().unwrap().global = Some(global);
+        self
+    }
+
+    /// Lamports of every wallet as seen by [`ReplaySource::rpc_client`]
+    pub fn with_balance(self, lamports: u64) -> Self {
+        self.market.lock().unwrap().balance = lamports;
+        self
+    }
+
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// Lines of the recording that were not replayable events
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// An RPC client answering from the market as of the event being replayed
+    pub fn rpc_client(&self) -> Arc<SolanaRpcClient> {
+        let sender = ReplayRpc { market: self.market.clone() };
+        Arc::new(SolanaRpcClient::new_sender(sender, RpcClientConfig::with_commitment(CommitmentConfig::confirmed())))
+    }
+
+    /// Transactions sent through [`ReplaySource::rpc_client`] so far
+    pub fn sent_transactions(&self) -> Vec<ReplayedSend> {
+        self.market.lock().unwrap().sent.clone()
+    }
+
+    /// The events, paced by the replay speed; the market served by the RPC client moves to each
+    /// event as it is yielded
+    pub fn stream(&self) -> impl Stream<Item = PumpfunEvent> + Send + 'static {
+        let (speed, market) = (self.speed, self.market.clone());
+        let paced = self.events.clone().into_iter().scan(None, |last_at_ms: &mut Option<u64>, recorded| {
+            let delay = match (*last_at_ms, recorded.at_ms) {
+                (Some(last), Some(at)) => Duration::from_millis(at.saturating_sub(last)),
+                _ => Duration::ZERO,
+            };
+            *last_at_ms = recorded.at_ms.or(*last_at_ms);
+            Some((speed.delay(delay), recorded.event))
+        });
+        stream::iter(paced).then(move |(delay, event)| {
+            let market = market.clone();
+            async move {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                market.lock().unwrap().apply(&event);
+                event
+            }
+        })
+    }
+
+    /// Passes every event to `callback`, as a subscription would, returning how many were
+    /// replayed
+    pub async fn replay<F>(&self, callback: F) -> usize
+    where
+        F: Fn(PumpfunEvent),
+    {
+        let mut events = Box::pin(self.stream());
+        let mut replayed = 0;
+        while let Some(event) = events.next().await {
+            callback(event);
+            replayed += 1;
+        }
+        replayed
+    }
+}
+
+/// A recorded line as an event; `None` if it is not one to replay
+fn parse_event(value: Value) -> Result<Option<RecordedEvent>, serde_json::Error> {
+    let at_ms = value.get("received_at_ms").and_then(Value::as_u64);
+    let trade = |value| serde_json::from_value::<TradeRecord>(value).map(TradeInfo::from);
+    let event = match value.get("event").and_then(Value::as_str) {
+        Some("new_token") => PumpfunEvent::NewToken(serde_json::from_value::<TokenRecord>(value)?.into()),
+        Some("dev_trade") => PumpfunEvent::NewDevTrade(trade(value)?),
+        Some("user_trade") => PumpfunEvent::NewUserTrade(trade(value)?),
+        Some("bot_trade") => PumpfunEvent::NewBotTrade(trade(value)?),
+        Some("error") => PumpfunEvent::Error(value["error"].as_str().unwrap_or_default().to_string()),
+        Some("stale") => PumpfunEvent::Stale(Duration::from_millis(value["silent_ms"].as_u64().unwrap_or_default())),
+        Some("watermark") => PumpfunEvent::Watermark(serde_json::from_value(value)?),
+        _ => return Ok(None),
+    };
+    let at_ms = at_ms.or(match &event {
+        PumpfunEvent::NewDevTrade(trade) | PumpfunEvent::NewUserTrade(trade) | PumpfunEvent::NewBotTrade(trade) if trade.timestamp > 0 => Some(trade.timestamp as u64 * 1_000),
+        _ => None,
+    });
+    Ok(Some(RecordedEvent { at_ms, event }))
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: fmt::Display,
+{
+    String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+}
+
+#[derive(Deserialize)]
+struct TokenRecord {
+    slot: u64,
+    name: String,
+    symbol: String,
+    uri: String,
+    #[serde(deserialize_with = "from_str")]
+    mint: Pubkey,
+    #[serde(deserialize_with = "from_str")]
+    bonding_curve: Pubkey,
+    #[serde(deserialize_with = "from_str")]
+    user: Pubkey,
+}
+
+impl From<TokenRecord> for CreateTokenInfo {
+    fn from(record: TokenRecord) -> Self {
+        CreateTokenInfo {
+            slot: record.slot,
+            name: record.name,
+            symbol: record.symbol,
+            uri: record.uri,
+            mint: record.mint,
+            bonding_curve: record.bonding_curve,
+            user: record.user,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TradeRecord {
+    slot: u64,
+    #[serde(deserialize_with = "from_str")]
+    mint: Pubkey,
+    is_buy: bool,
+    #[serde(default)]
+    is_initial_buy: bool,
+    sol_amount: u64,
+    token_amount: u64,
+    #[serde(deserialize_with = "from_str")]
+    user: Pubkey,
+    timestamp: i64,
+    virtual_sol_reserves: u64,
+    virtual_token_reserves: u64,
+    real_sol_reserves: u64,
+    real_token_reserves: u64,
+    #[serde(default, deserialize_with = "from_str")]
+    signature: Signature,
+}
+
+impl From<TradeRecord> for TradeInfo {
+    fn from(record: TradeRecord) -> Self {
+        TradeInfo {
+            slot: record.slot,
+            mint: record.mint,
+            sol_amount: record.sol_amount,
+            token_amount: record.token_amount,
+            is_buy: record.is_buy,
+            user: record.user,
+            timestamp: record.timestamp,
+            virtual_sol_reserves: record.virtual_sol_reserves,
+            virtual_token_reserves: record.virtual_token_reserves,
+            real_sol_reserves: record.real_sol_reserves,
+            real_token_reserves: record.real_token_reserves,
+            is_initial_buy: record.is_initial_buy,
+            signature: record.signature,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use solana_sdk::{signature::Keypair, signer::Signer, system_instruction, transaction::Transaction};
+
+    use super::*;
+    use crate::pumpfun::{common::get_bonding_curve_account_with, curve_cache::CurveRead};
+
+    fn recording(mint: &Pubkey) -> String {
+        let token = json!({
+            "event": "new_token", "slot": 10, "name": "Pepe", "symbol": "PEPE", "uri": "https://ipfs.io/ipfs/pepe",
+            "mint": mint.to_string(), "bonding_curve": Pubkey::new_unique().to_string(), "user": Pubkey::new_unique().to_string(),
+            "received_at_ms": 1_000,
+        });
+        let trade = |slot: u64, at_ms: u64, sol: u64, virtual_sol: u64| json!({
+            "event": "user_trade", "slot": slot, "mint": mint.to_string(), "is_buy": true, "sol_amount": sol, "token_amount": 1,
+            "user": Pubkey::new_unique().to_string(), "timestamp": 0, "virtual_sol_reserves": virtual_sol,
+            "virtual_token_reserves": 1_000_000_000_000_000u64, "real_sol_reserves": virtual_sol - 30_000_000_000,
+            "real_token_reserves": 700_000_000_000_000u64, "received_at_ms": at_ms,
+        });
+        let own = json!({ "event": "own_trade_confirmed", "synthetic": true, "mint": mint.to_string() });
+        [token, trade(11, 1_040, 2_000_000_000, 32_000_000_000), own, trade(12, 1_080, 9_000_000_000, 41_000_000_000)]
+            .iter()
+            .map(Value::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Buys whenever the curve served by the replay quotes at least three million tokens for 0.1 SOL
+    async fn decisions(source: &ReplaySource, payer: &Keypair, mint: &Pubkey) -> Vec<(u64, u64, Signature)> {
+        let rpc = source.rpc_client();
+        let mut events = Box::pin(source.stream());
+        let mut decisions = Vec::new();
+        while let Some(event) = events.next().await {
+            let PumpfunEvent::NewUserTrade(trade) = event else { continue };
+            let curve = get_bonding_curve_account_with(&rpc, mint, CurveRead::Fresh).await.unwrap();
+            let tokens = curve.get_buy_price(100_000_000).unwrap();
+            if tokens >= 3_000_000_000_000 {
+                let blockhash = rpc.get_latest_blockhash().await.unwrap();
+                let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+                let transaction = Transaction::new_signed_with_payer(&[transfer], Some(&payer.pubkey()), &[payer], blockhash);
+                let signature = rpc.send_transaction(&transaction).await.unwrap();
+                decisions.push((trade.slot, tokens, signature));
+            }
+        }
+        decisions
+    }
+
+    #[tokio::test]
+    async fn test_same_recording_gives_same_decisions() {
+        let (mint, payer) = (Pubkey::new_unique(), Keypair::new());
+        let text = recording(&mint);
+
+        let first = ReplaySource::from_reader(text.as_bytes()).unwrap();
+        assert_eq!((first.events().len(), first.skipped()), (3, 1));
+        let second = ReplaySource::from_reader(text.as_bytes()).unwrap();
+
+        let first_run = decisions(&first, &payer, &mint).await;
+        assert_eq!(first_run.len(), 1, "only the first trade leaves the curve cheap enough");
+        assert_eq!(first_run, decisions(&second, &payer, &mint).await);
+        assert_eq!(first.sent_transactions()[0].slot, 11);
+
+        let status = first.rpc_client().get_signature_status(&first_run[0].2).await.unwrap();
+        assert_eq!(status, Some(Ok(())));
+    }
+
+    #[tokio::test]
+    async fn test_replays_are_paced_by_speed() {
+        let mint = Pubkey::new_unique();
+        let text = recording(&mint);
+
+        let started = Instant::now();
+        let replayed = ReplaySource::from_reader(text.as_bytes()).unwrap().with_speed(ReplaySpeed::Original).replay(|_| {}).await;
+        assert_eq!(replayed, 3);
+        assert!(started.elapsed() >= Duration::from_millis(80), "took {:?}", started.elapsed());
+
+        let started = Instant::now();
+        ReplaySource::from_reader(text.as_bytes()).unwrap().with_speed(ReplaySpeed::Multiplier(4.0)).replay(|_| {}).await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(20) && elapsed < Duration::from_millis(80), "took {:?}", elapsed);
+    }
+}
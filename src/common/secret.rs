@@ -0,0 +1,113 @@
+//! Keeping credentials out of logs
+//!
+//! Auth tokens are held in a [`Secret`], which formats as `***` with both `{}` and `{:?}`, so
+//! logging a [`Cluster`](super::Cluster) or a fee client cannot leak them. URLs may carry their
+//! own `api-key` query parameter; [`redact_api_keys`] masks it in URLs and messages before they
+//! end up in errors or tracing events.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// What secrets and redacted API keys are formatted as
+pub const REDACTED: &str = "***";
+
+/// Query parameters masked by [`redact_api_keys`]
+const API_KEY_PARAMS: &[&str] = &["api-key=", "api_key=", "apikey="];
+
+/// A value that is never formatted; read it with [`Secret::expose`]
+///
+/// Serializes as the value itself, so configuration files round-trip.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret<T>(T);
+
+impl<T> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl Secret<String> {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Secret<String> {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret<String> {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Masks the value of every `api-key` query parameter in `text`, a URL or a message quoting one
+pub fn redact_api_keys(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    let mut rest = text;
+    loop {
+        let lowercase = rest.to_ascii_lowercase();
+        let Some((start, param)) = API_KEY_PARAMS.iter().filter_map(|param| lowercase.find(param).map(|start| (start, param.len()))).min() else {
+            break;
+        };
+        let value_start = start + param;
+        let value_len = rest[value_start..].find(|c: char| matches!(c, '&' | '#' | '"' | '\'' | ')' | '>') || c.is_whitespace()).unwrap_or(rest.len() - value_start);
+        redacted.push_str(&rest[..value_start]);
+        if value_len > 0 {
+            redacted.push_str(REDACTED);
+        }
+        rest = &rest[value_start + value_len..];
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_are_not_formatted() {
+        let token = Secret::from("hunter2");
+        assert_eq!(format!("{}", token), "***");
+        assert_eq!(format!("{:?}", Some(token.clone())), "Some(***)");
+        assert_eq!(token.expose(), "hunter2");
+        assert_eq!(serde_json::to_string(&token).unwrap(), "\"hunter2\"");
+    }
+
+    #[test]
+    fn test_api_keys_are_masked_in_urls_and_messages() {
+        assert_eq!(redact_api_keys("https://ny.0slot.trade/?api-key=abc123"), "https://ny.0slot.trade/?api-key=***");
+        assert_eq!(redact_api_keys("https://rpc.example/?cluster=main&API_KEY=abc&x=1"), "https://rpc.example/?cluster=main&API_KEY=***&x=1");
+        assert_eq!(
+            redact_api_keys("error sending request for url (https://a/?apikey=k1): to https://b/?api-key=k2"),
+            "error sending request for url (https://a/?apikey=***): to https://b/?api-key=***"
+        );
+        assert_eq!(redact_api_keys("https://ny.nextblock.io"), "https://ny.nextblock.io");
+    }
+}
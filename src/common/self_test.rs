@@ -0,0 +1,237 @@
+//! Startup self-test of every configured component
+//!
+//! A wrong URL or auth token otherwise only shows up on the first real trade. The self-test
+//! makes one cheap real call per component: the RPC node, each fee client, and, when given,
+//! the gRPC endpoint and the IPFS provider. Failures carry a hint naming the setting to check.
+
+use std::{
+    fmt,
+    future::Future,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::{Serialize, Serializer};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction};
+
+use crate::{
+    common::{secret::Secret, user_agent::user_agent, PriorityFee, SolanaRpcClient},
+    grpc::YellowstoneGrpc,
+    ipfs,
+    fee_clients::{startup::FeeClientStartup, ClientType, FeeClient},
+    pumpfun::common::{create_priority_fee_instructions, sign_versioned_transaction},
+};
+
+/// Time each check gets before it fails
+pub const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What `PumpFun::self_test` checks beyond the RPC node and the fee clients
+#[derive(Debug, Clone)]
+pub struct SelfTestOptions {
+    /// Yellowstone gRPC endpoint to connect to and ping
+    pub grpc_endpoint: Option<String>,
+    /// Pinata API key to check
+    pub ipfs_api_key: Option<Secret<String>>,
+    /// Send each fee client a zero-value self-transfer, paying its tip, and wait for it to confirm
+    pub spend_allowed: bool,
+    pub timeout: Duration,
+}
+
+impl Default for SelfTestOptions {
+    fn default() -> Self {
+        Self { grpc_endpoint: None, ipfs_api_key: None, spend_allowed: false, timeout: DEFAULT_CHECK_TIMEOUT }
+    }
+}
+
+/// How checking one component went
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentCheck {
+    /// `rpc`, `grpc`, `ipfs` or the fee client's label
+    pub component: String,
+    pub passed: bool,
+    #[serde(rename = "latency_ms", serialize_with = "serialize_millis")]
+    pub latency: Duration,
+    /// What was observed on success, the error on failure
+    pub detail: String,
+    /// What to check when the component failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    /// User agent the SDK sent, `pumpfun-sdk/<version>` and the application name
+    pub user_agent: String,
+    pub checks: Vec<ComponentCheck>,
+}
+
+impl SelfTestReport {
+    #[inline]
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ComponentCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.user_agent)?;
+        for check in &self.checks {
+            let status = if check.passed { "ok  " } else { "FAIL" };
+            writeln!(f, "{} {:<10} {:>6}ms  {}", status, check.component, check.latency.as_millis(), check.detail)?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "     hint: {}", hint)?;
+            }
+        }
+        let failed = self.failures().count();
+        write!(f, "{} of {} checks passed", self.checks.len() - failed, self.checks.len())
+    }
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// Runs every check concurrently; the RPC node is listed first, then the fee clients
+pub async fn self_test(
+    rpc: &Arc<SolanaRpcClient>,
+    payer: &Arc<Keypair>,
+    fee_clients: &[Arc<FeeClient>],
+    startup: &[FeeClientStartup],
+    priority_fee: PriorityFee,
+    options: &SelfTestOptions,
+) -> SelfTestReport {
+    let rpc_check = check("rpc", options.timeout, rpc_hint(), async {
+        rpc.get_latest_blockhash().await?;
+        let balance = rpc.get_balance(&payer.pubkey()).await?;
+        Ok(format!("payer {} holds {} lamports", payer.pubkey(), balance))
+    });
+    let fee_client_checks = futures::future::join_all(fee_clients.iter().map(|client| async move {
+        let client_type = client.get_client_type().await;
+        let label = client_type.to_string().to_lowercase();
+        check(&label, options.timeout, fee_client_hint(&client_type), fee_client_call(rpc, payer, client, priority_fee, options.spend_allowed)).await
+    }));
+    let grpc_check = async {
+        let endpoint = options.grpc_endpoint.clone()?;
+        Some(check("grpc", options.timeout, "check the gRPC endpoint URL and that it accepts this machine".to_string(), async move {
+            let round_trip = YellowstoneGrpc::new(endpoint).ping(options.timeout).await?;
+            Ok(format!("ping answered in {}ms", round_trip.as_millis()))
+        }).await)
+    };
+    let ipfs_check = async {
+        let api_key = options.ipfs_api_key.as_ref()?.expose();
+        Some(check("ipfs", options.timeout, "check the Pinata API key (JWT) and its permissions".to_string(), async move {
+            ipfs::test_authentication(api_key).await?;
+            Ok("API key accepted".to_string())
+        }).await)
+    };
+
+    let (rpc_check, fee_client_checks, grpc_check, ipfs_check) = tokio::join!(rpc_check, fee_client_checks, grpc_check, ipfs_check);
+    let mut checks = vec![rpc_check];
+    checks.extend(fee_client_checks);
+    checks.extend(startup.iter().filter(|startup| !startup.is_ok()).map(startup_check));
+    checks.extend(grpc_check);
+    checks.extend(ipfs_check);
+    SelfTestReport { user_agent: user_agent(), checks }
+}
+
+/// Fetches the tip account and, when spending is allowed, lands a zero-value self-transfer
+async fn fee_client_call(
+    rpc: &SolanaRpcClient,
+    payer: &Keypair,
+    client: &Arc<FeeClient>,
+    priority_fee: PriorityFee,
+    spend_allowed: bool,
+) -> Result<String, anyhow::Error> {
+    let tip_account = Pubkey::from_str(&client.get_tip_account().await?)?;
+    if !spend_allowed {
+        return Ok(format!("tip account {}", tip_account));
+    }
+
+    let mut instructions = create_priority_fee_instructions(priority_fee, priority_fee.buy_limit());
+    instructions.push(system_instruction::transfer(&payer.pubkey(), &payer.pubkey(), 0));
+    instructions.push(system_instruction::transfer(&payer.pubkey(), &tip_account, priority_fee.buy_tip_fee.lamports()));
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let transaction = sign_versioned_transaction(&instructions, &[payer], recent_blockhash)?;
+    let signature = client.send_transaction(&transaction).await?;
+    Ok(format!("self-transfer {} confirmed", signature))
+}
+
+async fn check(
+    component: &str,
+    timeout: Duration,
+    hint: String,
+    call: impl Future<Output = Result<String, anyhow::Error>>,
+) -> ComponentCheck {
+    let start = Instant::now();
+    let result = match tokio::time::timeout(timeout, call).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow::anyhow!("no response within {:?}", timeout)),
+    };
+    let (passed, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(e) => (false, format!("{:#}", e)),
+    };
+    ComponentCheck { component: component.to_string(), passed, latency: start.elapsed(), detail, hint: (!passed).then_some(hint) }
+}
+
+/// A failing check for a fee client that didn't start
+fn startup_check(startup: &FeeClientStartup) -> ComponentCheck {
+    ComponentCheck {
+        component: startup.client_type.to_string().to_lowercase(),
+        passed: false,
+        latency: startup.elapsed,
+        detail: format!("not started: {}", startup.error.as_deref().unwrap_or_default()),
+        hint: Some(fee_client_hint(&startup.client_type)),
+    }
+}
+
+fn rpc_hint() -> String {
+    "check `rpc_url` and that the node is reachable".to_string()
+}
+
+fn fee_client_hint(client_type: &ClientType) -> String {
+    match client_type {
+        ClientType::Jito => "check `block_engine_url`".to_string(),
+        ClientType::NextBlock => "check `nextblock_url`, `nextblock_fallback_urls` and `nextblock_auth_token`".to_string(),
+        ClientType::ZeroSlot => "check `zeroslot_url` and `zeroslot_auth_token`".to_string(),
+        ClientType::Rpc => rpc_hint(),
+        ClientType::Custom(label) => format!("check the configuration of the custom client `{}`", label),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failures_carry_hints() {
+        let passed = check("rpc", Duration::from_secs(1), rpc_hint(), async { Ok("fine".to_string()) }).await;
+        let timed_out = check("grpc", Duration::from_millis(10), "check the endpoint".to_string(), async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(String::new())
+        }).await;
+        let not_started = startup_check(&FeeClientStartup {
+            client_type: ClientType::NextBlock,
+            elapsed: Duration::from_millis(3),
+            error: Some("401 Unauthorized".to_string()),
+        });
+
+        assert!(passed.passed && passed.hint.is_none());
+        assert!(!timed_out.passed && timed_out.detail.contains("no response"));
+        assert_eq!(not_started.component, "nextblock");
+        assert!(not_started.hint.as_deref().unwrap().contains("nextblock_auth_token"));
+
+        let report = SelfTestReport { user_agent: "pumpfun-sdk/2.4.3".to_string(), checks: vec![passed, timed_out, not_started] };
+        assert!(!report.passed());
+        assert_eq!(report.failures().count(), 2);
+        assert!(report.to_string().starts_with("pumpfun-sdk/2.4.3\n"));
+        assert!(report.to_string().ends_with("1 of 3 checks passed"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["checks"][2]["latency_ms"], 3);
+    }
+}
@@ -0,0 +1,369 @@
+//! Rolling per-mint trade statistics from the event stream
+//!
+//! [`StatsTracker`] keeps, per mint, buys, sells, net SOL inflow and new unique buyers in
+//! fixed ring buffers of time buckets, so any window up to the longest configured one can be
+//! queried without keeping individual trades. Time is taken from the trade timestamps, so
+//! results don't depend on when events are processed. Memory is bounded: at most `max_mints`
+//! mints are kept, evicting the least recently traded, and each remembers at most
+//! `max_unique_buyers` buyers.
+//!
+//! [`run_stats_stage`] feeds a tracker from a channel of events and emits a [`StatsSnapshot`]
+//! of every tracked mint on an interval.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::mpsc;
+
+use super::{logs_data::TradeInfo, logs_events::PumpfunEvent};
+
+#[derive(Debug, Clone)]
+pub struct StatsConfig {
+    /// Windows reported in snapshots; the longest one sets the ring buffer length
+    pub windows: Vec<Duration>,
+    /// Resolution of the ring buffers; at least a second
+    pub bucket: Duration,
+    pub max_mints: usize,
+    pub max_unique_buyers: usize,
+    /// Start tracking every mint a `NewToken` event announces
+    pub track_new_tokens: bool,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            windows: vec![Duration::from_secs(10), Duration::from_secs(60), Duration::from_secs(300)],
+            bucket: Duration::from_secs(1),
+            max_mints: 10_000,
+            max_unique_buyers: 1_024,
+            track_new_tokens: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WindowStats {
+    pub window: Duration,
+    pub buys: u64,
+    pub sells: u64,
+    /// Buyers whose first buy of the mint falls in the window
+    pub new_buyers: u64,
+    /// Lamports bought minus lamports sold
+    pub net_sol_inflow: i64,
+    pub trades_per_sec: f64,
+}
+
+impl WindowStats {
+    /// Buys per sell; `None` without sells
+    pub fn buy_sell_ratio(&self) -> Option<f64> {
+        (self.sells > 0).then(|| self.buys as f64 / self.sells as f64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsSnapshot {
+    pub mint: Pubkey,
+    /// Unix time of the snapshot, in seconds
+    pub at: i64,
+    pub first_trade_at: i64,
+    pub total_buys: u64,
+    pub total_sells: u64,
+    pub net_sol_inflow: i64,
+    pub unique_buyers: u64,
+    /// More than `max_unique_buyers` bought, so unique buyer counts are lower bounds
+    pub buyers_capped: bool,
+    pub windows: Vec<WindowStats>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+    index: i64,
+    buys: u64,
+    sells: u64,
+    new_buyers: u64,
+    net_sol_inflow: i64,
+}
+
+/// Statistics of one mint
+#[derive(Debug, Clone)]
+pub struct MintStats {
+    bucket_secs: i64,
+    buckets: Box<[Bucket]>,
+    first_trade_at: i64,
+    last_trade_at: i64,
+    total_buys: u64,
+    total_sells: u64,
+    net_sol_inflow: i64,
+    buyers: HashSet<Pubkey>,
+    max_unique_buyers: usize,
+    buyers_capped: bool,
+}
+
+impl MintStats {
+    fn new(config: &StatsConfig, at: i64) -> Self {
+        let bucket_secs = config.bucket.as_secs().max(1) as i64;
+        let longest = config.windows.iter().max().map_or(1, |window| window.as_secs().max(1) as i64);
+        let len = ((longest + bucket_secs - 1) / bucket_secs) as usize;
+        Self {
+            bucket_secs,
+            buckets: vec![Bucket { index: i64::MIN, ..Default::default() }; len].into_boxed_slice(),
+            first_trade_at: at,
+            last_trade_at: at,
+            total_buys: 0,
+            total_sells: 0,
+            net_sol_inflow: 0,
+            buyers: HashSet::new(),
+            max_unique_buyers: config.max_unique_buyers,
+            buyers_capped: false,
+        }
+    }
+
+    fn record(&mut self, trade: &TradeInfo) {
+        let sol = trade.sol_amount.min(i64::MAX as u64) as i64;
+        let new_buyer = trade.is_buy && self.insert_buyer(trade.user);
+        if trade.is_buy {
+            self.total_buys += 1;
+            self.net_sol_inflow = self.net_sol_inflow.saturating_add(sol);
+        } else {
+            self.total_sells += 1;
+            self.net_sol_inflow = self.net_sol_inflow.saturating_sub(sol);
+        }
+        self.first_trade_at = self.first_trade_at.min(trade.timestamp);
+        self.last_trade_at = self.last_trade_at.max(trade.timestamp);
+
+        let index = trade.timestamp.div_euclid(self.bucket_secs);
+        let newest = self.last_trade_at.div_euclid(self.bucket_secs);
+        if index <= newest - self.buckets.len() as i64 {
+            // Older than the ring buffer; only the totals count it
+            return;
+        }
+        let len = self.buckets.len() as i64;
+        let bucket = &mut self.buckets[index.rem_euclid(len) as usize];
+        if bucket.index != index {
+            *bucket = Bucket { index, ..Default::default() };
+        }
+        if trade.is_buy {
+            bucket.buys += 1;
+            bucket.net_sol_inflow = bucket.net_sol_inflow.saturating_add(sol);
+        } else {
+            bucket.sells += 1;
+            bucket.net_sol_inflow = bucket.net_sol_inflow.saturating_sub(sol);
+        }
+        if new_buyer {
+            bucket.new_buyers += 1;
+        }
+    }
+
+    fn insert_buyer(&mut self, buyer: Pubkey) -> bool {
+        if self.buyers.contains(&buyer) {
+            return false;
+        }
+        if self.buyers.len() >= self.max_unique_buyers {
+            self.buyers_capped = true;
+            return false;
+        }
+        self.buyers.insert(buyer)
+    }
+
+    /// Statistics of the `window` ending at `now`, in unix seconds
+    pub fn window(&self, window: Duration, now: i64) -> WindowStats {
+        let newest = now.div_euclid(self.bucket_secs);
+        let count = (window.as_secs().max(1) as i64 + self.bucket_secs - 1) / self.bucket_secs;
+        let oldest = newest - count.min(self.buckets.len() as i64);
+
+        let mut stats = WindowStats { window, ..Default::default() };
+        for bucket in self.buckets.iter().filter(|bucket| bucket.index > oldest && bucket.index <= newest) {
+            stats.buys += bucket.buys;
+            stats.sells += bucket.sells;
+            stats.new_buyers += bucket.new_buyers;
+            stats.net_sol_inflow = stats.net_sol_inflow.saturating_add(bucket.net_sol_inflow);
+        }
+        stats.trades_per_sec = (stats.buys + stats.sells) as f64 / window.as_secs_f64().max(1.0);
+        stats
+    }
+
+    fn snapshot(&self, mint: Pubkey, windows: &[Duration], now: i64) -> StatsSnapshot {
+        StatsSnapshot {
+            mint,
+            at: now,
+            first_trade_at: self.first_trade_at,
+            total_buys: self.total_buys,
+            total_sells: self.total_sells,
+            net_sol_inflow: self.net_sol_inflow,
+            unique_buyers: self.buyers.len() as u64,
+            buyers_capped: self.buyers_capped,
+            windows: windows.iter().map(|window| self.window(*window, now)).collect(),
+        }
+    }
+}
+
+pub struct StatsTracker {
+    config: StatsConfig,
+    mints: HashMap<Pubkey, MintStats>,
+    tracked: HashSet<Pubkey>,
+    /// Newest trade timestamp seen, the tracker's notion of now
+    clock: i64,
+}
+
+impl StatsTracker {
+    pub fn new(config: StatsConfig) -> Self {
+        Self { config, mints: HashMap::new(), tracked: HashSet::new(), clock: 0 }
+    }
+
+    pub fn record_event(&mut self, event: &PumpfunEvent) {
+        match event {
+            PumpfunEvent::NewToken(token) if self.config.track_new_tokens => self.track(token.mint),
+            PumpfunEvent::NewDevTrade(trade) | PumpfunEvent::NewUserTrade(trade) | PumpfunEvent::NewBotTrade(trade) => self.record(trade),
+            _ => {}
+        }
+    }
+
+    pub fn record(&mut self, trade: &TradeInfo) {
+        self.clock = self.clock.max(trade.timestamp);
+        if !self.mints.contains_key(&trade.mint) && self.mints.len() >= self.config.max_mints {
+            self.evict_least_recent();
+        }
+        let config = &self.config;
+        self.mints.entry(trade.mint).or_insert_with(|| MintStats::new(config, trade.timestamp)).record(trade);
+    }
+
+    /// Includes `mint` in periodic snapshots
+    pub fn track(&mut self, mint: Pubkey) {
+        if self.tracked.len() < self.config.max_mints {
+            self.tracked.insert(mint);
+        }
+    }
+
+    pub fn untrack(&mut self, mint: &Pubkey) {
+        self.tracked.remove(mint);
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<&MintStats> {
+        self.mints.get(mint)
+    }
+
+    /// Snapshot of `mint` at the newest trade time seen
+    pub fn snapshot(&self, mint: &Pubkey) -> Option<StatsSnapshot> {
+        self.mints.get(mint).map(|stats| stats.snapshot(*mint, &self.config.windows, self.clock))
+    }
+
+    /// Snapshots of the tracked mints that have traded
+    pub fn tracked_snapshots(&self) -> Vec<StatsSnapshot> {
+        self.tracked.iter().filter_map(|mint| self.snapshot(mint)).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mints.is_empty()
+    }
+
+    fn evict_least_recent(&mut self) {
+        let least_recent = self.mints.iter().min_by_key(|(_, stats)| stats.last_trade_at).map(|(mint, _)| *mint);
+        if let Some(mint) = least_recent {
+            self.mints.remove(&mint);
+            self.tracked.remove(&mint);
+        }
+    }
+}
+
+/// Feeds `tracker` from `events` until the channel closes, passing snapshots of the tracked
+/// mints to `on_snapshot` every `interval`
+///
+/// The tracker stays queryable from other tasks while the stage runs.
+pub async fn run_stats_stage<F>(tracker: Arc<Mutex<StatsTracker>>, mut events: mpsc::Receiver<PumpfunEvent>, interval: Duration, on_snapshot: F)
+where
+    F: Fn(StatsSnapshot),
+{
+    let mut tick = tokio::time::interval(interval);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => tracker.lock().unwrap().record_event(&event),
+                None => return,
+            },
+            _ = tick.tick() => {
+                let snapshots = tracker.lock().unwrap().tracked_snapshots();
+                snapshots.into_iter().for_each(&on_snapshot);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn trade(mint: Pubkey, user: Pubkey, is_buy: bool, sol_amount: u64, timestamp: i64) -> TradeInfo {
+        TradeInfo { mint, user, is_buy, sol_amount, timestamp, ..Default::default() }
+    }
+
+    #[test]
+    fn test_windows_count_recent_trades() {
+        let mut tracker = StatsTracker::new(StatsConfig::default());
+        let mint = Pubkey::new_unique();
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        tracker.record(&trade(mint, alice, true, 1_000, 1_000));
+        tracker.record(&trade(mint, alice, true, 500, 1_050));
+        tracker.record(&trade(mint, bob, true, 2_000, 1_055));
+        tracker.record(&trade(mint, alice, false, 700, 1_058));
+
+        let snapshot = tracker.snapshot(&mint).unwrap();
+        assert_eq!((snapshot.total_buys, snapshot.total_sells, snapshot.unique_buyers), (3, 1, 2));
+        assert_eq!(snapshot.net_sol_inflow, 2_800);
+
+        let ten = snapshot.windows[0];
+        assert_eq!((ten.buys, ten.sells, ten.new_buyers), (2, 1, 1));
+        assert_eq!(ten.net_sol_inflow, 1_800);
+        assert_eq!(ten.buy_sell_ratio(), Some(2.0));
+        assert_eq!((snapshot.windows[1].buys, snapshot.windows[1].new_buyers), (3, 2));
+    }
+
+    #[test]
+    fn test_memory_is_bounded() {
+        let config = StatsConfig { max_mints: 2, max_unique_buyers: 2, ..Default::default() };
+        let mut tracker = StatsTracker::new(config);
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        for (i, mint) in mints.iter().enumerate() {
+            for _ in 0..3 {
+                tracker.record(&trade(*mint, Pubkey::new_unique(), true, 1, i as i64));
+            }
+        }
+
+        assert_eq!(tracker.len(), 2);
+        assert!(tracker.get(&mints[0]).is_none());
+        let snapshot = tracker.snapshot(&mints[2]).unwrap();
+        assert_eq!(snapshot.unique_buyers, 2);
+        assert!(snapshot.buyers_capped);
+    }
+
+    /// `cargo test --release -- --ignored --nocapture bench_stats_throughput`
+    #[test]
+    #[ignore]
+    fn bench_stats_throughput() {
+        const TRADES: usize = 2_000_000;
+        let mints: Vec<Pubkey> = (0..5_000).map(|_| Pubkey::new_unique()).collect();
+        let users: Vec<Pubkey> = (0..50_000).map(|_| Pubkey::new_unique()).collect();
+        let trades: Vec<TradeInfo> = (0..TRADES)
+            .map(|i| trade(mints[i * 7 % mints.len()], users[i * 13 % users.len()], i % 3 != 0, 1_000_000, (i / 2_000) as i64))
+            .collect();
+
+        let mut tracker = StatsTracker::new(StatsConfig::default());
+        let started = Instant::now();
+        for trade in &trades {
+            tracker.record(trade);
+        }
+        let elapsed = started.elapsed();
+        println!("{} trades in {:?}: {:.0} trades/s", TRADES, elapsed, TRADES as f64 / elapsed.as_secs_f64());
+    }
+}
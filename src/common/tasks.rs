@@ -0,0 +1,146 @@
+//! Tracking of background tasks owned by a `PumpFun` client
+//!
+//! Every task the client spawns for itself (cache refreshers, keep-alives, subscriptions
+//! started through the client) is registered here so it can be stopped on shutdown instead
+//! of outliving the client.
+
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tokio::task::{AbortHandle, JoinHandle};
+
+/// Default time `PumpFun::shutdown` waits for background tasks to finish
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Default)]
+pub struct TaskRegistry {
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+    subscriptions: Mutex<Vec<AbortHandle>>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawns `future` on the current runtime and tracks it
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.track(tokio::spawn(future));
+    }
+
+    /// Tracks an already spawned background task
+    pub fn track(&self, handle: JoinHandle<()>) {
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(handle);
+    }
+
+    /// Tracks the task of a subscription whose handle is owned by the caller
+    pub fn track_subscription(&self, handle: AbortHandle) {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.retain(|subscription| !subscription.is_finished());
+        subscriptions.push(handle);
+    }
+
+    /// Number of tracked tasks and subscriptions that are still running
+    pub fn active(&self) -> usize {
+        let tasks = self.tasks.lock().unwrap().iter().filter(|task| !task.is_finished()).count();
+        let subscriptions = self.subscriptions.lock().unwrap().iter().filter(|sub| !sub.is_finished()).count();
+        tasks + subscriptions
+    }
+
+    /// Aborts every tracked task and waits up to `timeout` for all of them to finish.
+    ///
+    /// Returns `false` if some task was still running when the timeout elapsed.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        let tasks: Vec<JoinHandle<()>> = std::mem::take(&mut *self.tasks.lock().unwrap());
+        let subscriptions: Vec<AbortHandle> = std::mem::take(&mut *self.subscriptions.lock().unwrap());
+
+        for subscription in &subscriptions {
+            subscription.abort();
+        }
+        for task in &tasks {
+            task.abort();
+        }
+
+        let deadline = Instant::now() + timeout;
+        let joined = tokio::time::timeout(timeout, futures::future::join_all(tasks)).await.is_ok();
+        if !joined {
+            return false;
+        }
+
+        while subscriptions.iter().any(|subscription| !subscription.is_finished()) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+        }
+
+        true
+    }
+
+    fn abort_all(&self) {
+        if let Ok(tasks) = self.tasks.lock() {
+            tasks.iter().for_each(|task| task.abort());
+        }
+        if let Ok(subscriptions) = self.subscriptions.lock() {
+            subscriptions.iter().for_each(|subscription| subscription.abort());
+        }
+    }
+}
+
+impl Drop for TaskRegistry {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_leaves_no_tasks_alive() {
+        let metrics = tokio::runtime::Handle::current().metrics();
+        let registry = TaskRegistry::new();
+
+        registry.spawn(futures::future::pending());
+        registry.spawn(async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+        let subscription = tokio::spawn(futures::future::pending::<()>());
+        registry.track_subscription(subscription.abort_handle());
+
+        assert_eq!(registry.active(), 3);
+        assert_eq!(metrics.num_alive_tasks(), 3);
+
+        assert!(registry.shutdown(DEFAULT_SHUTDOWN_TIMEOUT).await);
+        assert!(subscription.await.unwrap_err().is_cancelled());
+
+        assert_eq!(registry.active(), 0);
+        assert_eq!(metrics.num_alive_tasks(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_drop_aborts_tasks() {
+        let registry = TaskRegistry::new();
+        let task = tokio::spawn(futures::future::pending::<()>());
+        let abort_handle = task.abort_handle();
+        registry.track(task);
+
+        drop(registry);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert!(abort_handle.is_finished());
+    }
+}
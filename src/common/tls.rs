@@ -0,0 +1,33 @@
+//! Process-wide rustls crypto provider handling
+//!
+//! rustls needs exactly one process-wide `CryptoProvider`. Applications that already
+//! installed their own (for example aws-lc-rs) keep it; the SDK only installs the provider
+//! selected by its cargo features (`ring`, the default, or `aws-lc-rs`) when none exists.
+
+use anyhow::anyhow;
+use rustls::crypto::CryptoProvider;
+
+#[cfg(feature = "aws-lc-rs")]
+use rustls::crypto::aws_lc_rs::default_provider;
+#[cfg(all(feature = "ring", not(feature = "aws-lc-rs")))]
+use rustls::crypto::ring::default_provider;
+
+#[cfg(not(any(feature = "ring", feature = "aws-lc-rs")))]
+compile_error!("pumpfun-sdk requires either the `ring` or the `aws-lc-rs` feature");
+
+/// Makes sure a process-wide rustls `CryptoProvider` is installed.
+///
+/// Does nothing when a provider is already installed. Otherwise installs the provider
+/// selected by the crate features. Losing an installation race to another thread is not an
+/// error as long as a provider ends up installed; any other failure is returned.
+pub fn ensure_crypto_provider() -> Result<(), anyhow::Error> {
+    if CryptoProvider::get_default().is_some() {
+        return Ok(());
+    }
+
+    match default_provider().install_default() {
+        Ok(()) => Ok(()),
+        Err(_) if CryptoProvider::get_default().is_some() => Ok(()),
+        Err(e) => Err(anyhow!("Failed to install crypto provider: {:?}", e)),
+    }
+}
@@ -0,0 +1,289 @@
+//! Packing instructions into as few transactions as fit
+//!
+//! Maintenance work such as creating token accounts or moving funds produces more
+//! instructions than one transaction holds. [`pack_instructions`] fills transactions greedily,
+//! in order, up to the packet size and optionally a compute unit budget. Instructions that
+//! must land together, e.g. creating a token account and transferring into it, go in one
+//! [`InstructionGroup`] and are never split.
+
+use std::ops::Range;
+
+use solana_hash::Hash;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    signature::Keypair,
+    signer::Signer,
+    transaction::VersionedTransaction,
+};
+use thiserror::Error;
+
+use crate::pumpfun::common::{check_signers, SignerMismatch};
+
+/// Most compute units a transaction may request
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+
+/// Bytes of a signature in a serialized transaction
+const SIGNATURE_SIZE: usize = 64;
+
+/// Instructions that always land in the same transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructionGroup {
+    pub instructions: Vec<Instruction>,
+    /// Compute units the instructions need, requested with a compute unit limit when non-zero
+    pub compute_units: u32,
+}
+
+impl InstructionGroup {
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Self { instructions, compute_units: 0 }
+    }
+
+    pub fn with_compute_units(mut self, compute_units: u32) -> Self {
+        self.compute_units = compute_units;
+        self
+    }
+}
+
+impl From<Instruction> for InstructionGroup {
+    fn from(instruction: Instruction) -> Self {
+        Self::new(vec![instruction])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackingLimits {
+    /// Largest serialized transaction, at most [`PACKET_DATA_SIZE`]
+    pub max_size: usize,
+    /// Most compute units the groups of one transaction may need; `None` packs by size only
+    pub max_compute_units: Option<u32>,
+    /// Price per compute unit, in micro-lamports, set on every transaction if given
+    pub compute_unit_price: Option<u64>,
+}
+
+impl Default for PackingLimits {
+    fn default() -> Self {
+        Self { max_size: PACKET_DATA_SIZE, max_compute_units: Some(MAX_COMPUTE_UNITS), compute_unit_price: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PackingError {
+    #[error("instruction group {group} is {size} bytes in a transaction of its own, over the {max}-byte limit")]
+    GroupTooLarge { group: usize, size: usize, max: usize },
+    #[error("instruction group {group} needs {compute_units} compute units, over the {max} of a transaction")]
+    GroupOverComputeBudget { group: usize, compute_units: u32, max: u32 },
+    #[error("a transaction needs at least one signer")]
+    NoSigner,
+    #[error("failed to build transaction: {0}")]
+    Build(String),
+    #[error(transparent)]
+    Signers(#[from] SignerMismatch),
+}
+
+/// A packed, signed transaction and the groups it carries
+#[derive(Debug, Clone)]
+pub struct PackedTransaction {
+    pub transaction: VersionedTransaction,
+    /// Indexes of the groups in the transaction, in the order they were given
+    pub groups: Range<usize>,
+}
+
+/// Packs `groups` in order into transactions paid by the first of `signers`, each signed by
+/// the signers its instructions need
+///
+/// Each transaction starts with a compute unit price if `limits` sets one and a compute unit
+/// limit covering its groups if they need any.
+pub fn pack_instructions(
+    groups: impl IntoIterator<Item = impl Into<InstructionGroup>>,
+    signers: &[&Keypair],
+    blockhash: Hash,
+    limits: &PackingLimits,
+) -> Result<Vec<VersionedTransaction>, PackingError> {
+    Ok(pack_groups(groups, signers, blockhash, limits)?.into_iter().map(|packed| packed.transaction).collect())
+}
+
+/// [`pack_instructions`], reporting which groups went into each transaction
+pub fn pack_groups(
+    groups: impl IntoIterator<Item = impl Into<InstructionGroup>>,
+    signers: &[&Keypair],
+    blockhash: Hash,
+    limits: &PackingLimits,
+) -> Result<Vec<PackedTransaction>, PackingError> {
+    let payer = signers.first().ok_or(PackingError::NoSigner)?;
+    let packer = Packer { payer, blockhash, limits };
+    let mut packed = Vec::new();
+    let mut batch: Vec<InstructionGroup> = Vec::new();
+    let mut start = 0;
+
+    for (index, group) in groups.into_iter().map(Into::into).enumerate() {
+        if let Some(max) = limits.max_compute_units {
+            if group.compute_units > max {
+                return Err(PackingError::GroupOverComputeBudget { group: index, compute_units: group.compute_units, max });
+            }
+        }
+        batch.push(group);
+        if packer.fits(&batch)? {
+            continue;
+        }
+        let group = batch.pop().expect("just pushed");
+        if batch.is_empty() {
+            let size = packer.size(&packer.compile(&[group])?);
+            return Err(PackingError::GroupTooLarge { group: index, size, max: limits.max_size });
+        }
+        packed.push(PackedTransaction { transaction: packer.sign(&batch, signers)?, groups: start..index });
+        start = index;
+        batch = vec![group];
+        if !packer.fits(&batch)? {
+            let size = packer.size(&packer.compile(&batch)?);
+            return Err(PackingError::GroupTooLarge { group: index, size, max: limits.max_size });
+        }
+    }
+    if !batch.is_empty() {
+        let end = start + batch.len();
+        packed.push(PackedTransaction { transaction: packer.sign(&batch, signers)?, groups: start..end });
+    }
+    Ok(packed)
+}
+
+struct Packer<'a> {
+    payer: &'a Keypair,
+    blockhash: Hash,
+    limits: &'a PackingLimits,
+}
+
+impl Packer<'_> {
+    fn instructions(&self, batch: &[InstructionGroup]) -> Vec<Instruction> {
+        let compute_units: u32 = batch.iter().map(|group| group.compute_units).sum();
+        let mut instructions = Vec::new();
+        instructions.extend(self.limits.compute_unit_price.map(ComputeBudgetInstruction::set_compute_unit_price));
+        if compute_units > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(compute_units));
+        }
+        instructions.extend(batch.iter().flat_map(|group| group.instructions.iter().cloned()));
+        instructions
+    }
+
+    fn compile(&self, batch: &[InstructionGroup]) -> Result<v0::Message, PackingError> {
+        v0::Message::try_compile(&self.payer.pubkey(), &self.instructions(batch), &[], self.blockhash).map_err(|e| PackingError::Build(e.to_string()))
+    }
+
+    /// Serialized size of the signed transaction of `message`
+    fn size(&self, message: &v0::Message) -> usize {
+        let signatures = message.header.num_required_signatures as usize;
+        let message_size = bincode::serialized_size(&VersionedMessage::V0(message.clone())).unwrap_or(u64::MAX) as usize;
+        short_vec_len(signatures) + signatures * SIGNATURE_SIZE + message_size
+    }
+
+    fn fits(&self, batch: &[InstructionGroup]) -> Result<bool, PackingError> {
+        let compute_units: u64 = batch.iter().map(|group| group.compute_units as u64).sum();
+        if self.limits.max_compute_units.is_some_and(|max| compute_units > max as u64) {
+            return Ok(false);
+        }
+        // instructions referencing too many accounts do not compile; the batch then does not fit
+        Ok(self.compile(batch).is_ok_and(|message| self.size(&message) <= self.limits.max_size.min(PACKET_DATA_SIZE)))
+    }
+
+    fn sign(&self, batch: &[InstructionGroup], signers: &[&Keypair]) -> Result<VersionedTransaction, PackingError> {
+        let message = self.compile(batch)?;
+        let required = &message.account_keys[..message.header.num_required_signatures as usize];
+        let signers: Vec<&Keypair> = signers.iter().copied().filter(|signer| required.contains(&signer.pubkey())).collect();
+        let message = VersionedMessage::V0(message);
+        check_signers(&message, &signers.iter().map(|signer| signer.pubkey()).collect::<Vec<_>>())?;
+        VersionedTransaction::try_new(message, &signers).map_err(|e| PackingError::Build(e.to_string()))
+    }
+}
+
+/// Bytes of the compact length prefix of `len`
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+    use solana_sdk::{pubkey::Pubkey, system_instruction};
+
+    use super::*;
+
+    fn memo(bytes: usize, accounts: usize) -> Instruction {
+        let accounts = (0..accounts).map(|_| solana_sdk::instruction::AccountMeta::new_readonly(Pubkey::new_unique(), false)).collect();
+        Instruction::new_with_bytes(Pubkey::new_unique(), &vec![7; bytes], accounts)
+    }
+
+    /// Instructions of `transaction` other than its compute budget ones
+    fn carried(transaction: &VersionedTransaction) -> Vec<Vec<u8>> {
+        let keys = transaction.message.static_account_keys();
+        transaction
+            .message
+            .instructions()
+            .iter()
+            .filter(|instruction| keys[instruction.program_id_index as usize] != solana_sdk::compute_budget::id())
+            .map(|instruction| instruction.data.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_packed_transactions_fit_and_keep_every_instruction_in_order() {
+        let payer = Keypair::new();
+        let mut rng = rand::rng();
+        for _ in 0..50 {
+            let groups: Vec<InstructionGroup> = (0..rng.random_range(1..60))
+                .map(|index: usize| {
+                    let instructions = (0..rng.random_range(1..=3))
+                        .map(|offset: usize| {
+                            let mut instruction = memo(rng.random_range(1..200), rng.random_range(0..4));
+                            instruction.data[0] = ((index * 3 + offset) % 256) as u8;
+                            instruction.data.extend_from_slice(&(index * 3 + offset).to_le_bytes());
+                            instruction
+                        })
+                        .collect();
+                    InstructionGroup::new(instructions).with_compute_units(rng.random_range(0..200_000))
+                })
+                .collect();
+            let limits = PackingLimits { compute_unit_price: Some(1_000), ..Default::default() };
+
+            let packed = pack_groups(groups.clone(), &[&payer], Hash::new_unique(), &limits).unwrap();
+            let mut next = 0;
+            for PackedTransaction { transaction, groups: range } in &packed {
+                assert!(bincode::serialized_size(transaction).unwrap() as usize <= PACKET_DATA_SIZE);
+                assert_eq!(range.start, next);
+                next = range.end;
+                let expected: Vec<Vec<u8>> = groups[range.clone()].iter().flat_map(|group| group.instructions.iter().map(|instruction| instruction.data.clone())).collect();
+                assert_eq!(carried(transaction), expected, "groups are kept whole and in order");
+                assert!(groups[range.clone()].iter().map(|group| group.compute_units).sum::<u32>() <= MAX_COMPUTE_UNITS);
+                assert!(transaction.verify_with_results().iter().all(|verified| *verified));
+            }
+            assert_eq!(next, groups.len(), "every group is packed exactly once");
+        }
+    }
+
+    #[test]
+    fn test_groups_are_not_split_and_oversized_groups_are_rejected() {
+        let (payer, recipient) = (Keypair::new(), Keypair::new());
+        // a pair that must land together, needing the recipient's signature too
+        let pair = InstructionGroup::new(vec![
+            system_instruction::transfer(&payer.pubkey(), &recipient.pubkey(), 1),
+            system_instruction::transfer(&recipient.pubkey(), &payer.pubkey(), 1),
+        ]);
+        let groups = vec![InstructionGroup::from(memo(500, 0)), pair.clone(), InstructionGroup::from(memo(500, 0))];
+        let packed = pack_groups(groups, &[&payer, &recipient], Hash::new_unique(), &PackingLimits::default()).unwrap();
+        assert!(packed.iter().any(|packed| packed.transaction.signatures.len() == 2 && carried(&packed.transaction).len() >= 2));
+        assert!(packed.iter().all(|packed| packed.transaction.signatures.len() == packed.transaction.message.header().num_required_signatures as usize));
+
+        let err = pack_instructions(vec![memo(1_300, 0)], &[&payer], Hash::new_unique(), &PackingLimits::default()).unwrap_err();
+        assert!(matches!(err, PackingError::GroupTooLarge { group: 0, .. }), "{}", err);
+        let heavy = InstructionGroup::from(memo(10, 0)).with_compute_units(MAX_COMPUTE_UNITS + 1);
+        assert!(matches!(pack_instructions(vec![heavy], &[&payer], Hash::new_unique(), &PackingLimits::default()), Err(PackingError::GroupOverComputeBudget { .. })));
+
+        let limits = PackingLimits { max_compute_units: Some(100_000), ..Default::default() };
+        let light: Vec<InstructionGroup> = (0..4).map(|_| InstructionGroup::from(memo(10, 0)).with_compute_units(40_000)).collect();
+        assert_eq!(pack_instructions(light, &[&payer], Hash::new_unique(), &limits).unwrap().len(), 2);
+    }
+}
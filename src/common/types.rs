@@ -1,29 +1,131 @@
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
-use serde::Deserialize;
-use crate::{constants::trade::{DEFAULT_BUY_TIP_FEE, DEFAULT_COMPUTE_UNIT_LIMIT, DEFAULT_COMPUTE_UNIT_PRICE, DEFAULT_SELL_TIP_FEE}, jito::FeeClient};
+use solana_sdk::commitment_config::CommitmentConfig;
+use serde::{Deserialize, Serialize};
+use crate::{common::{config::commitment_serde, genesis::ClusterKind, secret::{redact_api_keys, Secret}},constants::{tokens::LAMPORTS_PER_SOL, trade::{DEFAULT_BUY_TIP_FEE, DEFAULT_BUY_UNIT_LIMIT, DEFAULT_COMPUTE_UNIT_PRICE, DEFAULT_CREATE_UNIT_LIMIT, DEFAULT_SELL_TIP_FEE, DEFAULT_SELL_UNIT_LIMIT}}, fee_clients::{ClientType, NextBlockOptions}, pumpfun::common::{BuyFloor, PartialFillGuard, SellFloor, SlippageMode}, trade::RpcSendOptions};
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum FeeType {
-    Jito,
-    NextBlock,
-}
+#[deprecated(note = "use `fee_clients::ClientType`, which also covers 0slot and custom clients")]
+pub type FeeType = ClientType;
 
-#[derive(Debug, Clone)]
+/// Connection and fee settings; see [`Cluster::from_toml`] and [`Cluster::from_env`]
+///
+/// Its `Debug` output masks the auth tokens and `api-key` parameters of the URLs.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Cluster {
     pub rpc_url: String,
+    /// Endpoint of account reads, quotes and blockhashes; `rpc_url` when empty
+    #[serde(default)]
+    pub read_rpc_url: String,
+    /// Endpoint transactions are sent and confirmed through; `rpc_url` when empty
+    #[serde(default)]
+    pub send_rpc_url: String,
+    #[serde(default)]
     pub block_engine_url: String,
+    #[serde(default)]
     pub nextblock_url: String,
-    pub nextblock_auth_token: String,
+    /// Regional NextBlock endpoints tried, in order, when `nextblock_url` fails
+    #[serde(default)]
+    pub nextblock_fallback_urls: Vec<String>,
+    #[serde(default)]
+    pub nextblock_auth_token: Secret<String>,
+    /// NextBlock routing flags used unless a trade overrides them
+    #[serde(default)]
+    pub nextblock_options: NextBlockOptions,
+    #[serde(default)]
     pub zeroslot_url: String,
-    pub zeroslot_auth_token: String,
+    #[serde(default)]
+    pub zeroslot_auth_token: Secret<String>,
+    #[serde(default)]
     pub use_jito: bool,
+    #[serde(default)]
     pub use_nextblock: bool,
+    #[serde(default)]
     pub use_zeroslot: bool,
+    /// Enabled clients by label, e.g. `["jito", "0slot"]`, in addition to the `use_*` flags
+    #[serde(default)]
+    pub clients: Vec<ClientType>,
+    #[serde(default)]
     pub priority_fee: PriorityFee,
+    #[serde(default = "commitment_serde::default_commitment", with = "commitment_serde")]
     pub commitment: CommitmentConfig,
+    /// Cluster the RPC node must serve, checked by its genesis hash in `PumpFun::try_new`;
+    /// unchecked when not set
+    #[serde(default)]
+    pub cluster_kind: Option<ClusterKind>,
+    /// Skip the genesis hash check, e.g. for a local validator with its own program ids
+    #[serde(default)]
+    pub skip_genesis_check: bool,
+    /// Check the deployed program's IDL against the SDK when the client is constructed
+    #[serde(default)]
+    pub verify_program_on_startup: bool,
+    /// Preflight and retry settings of transactions sent over plain RPC
+    #[serde(default)]
+    pub rpc_send_options: RpcSendOptions,
+    /// How buys apply their slippage tolerance
+    #[serde(default)]
+    pub slippage_mode: SlippageMode,
+    /// Send buys without first checking the payer can afford them, saving a round trip
+    #[serde(default)]
+    pub skip_balance_check: bool,
+    /// Rebuild and send a buy or sell once more when it failed on a rotated fee recipient
+    #[serde(default)]
+    pub retry_fee_recipient_rotation: bool,
+    /// Lowest minimum output sells are sent with
+    #[serde(default)]
+    pub sell_floor: SellFloor,
+    /// When buys from a nearly exhausted curve are refused
+    #[serde(default)]
+    pub partial_fill: PartialFillGuard,
+    /// Smallest buy that is sent
+    #[serde(default)]
+    pub buy_floor: BuyFloor,
+    /// Appended to the SDK's user agent on outbound requests, see [`user_agent`](super::user_agent)
+    #[serde(default)]
+    pub app_name: Option<String>,
+    /// WebSocket endpoint of `PumpFun::watch_mints` when no `grpc_url` is set
+    #[serde(default)]
+    pub ws_url: String,
+    /// Yellowstone gRPC endpoint `PumpFun::watch_mints` subscribes through when set
+    #[serde(default)]
+    pub grpc_url: String,
+}
+
+impl fmt::Debug for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fallback_urls: Vec<String> = self.nextblock_fallback_urls.iter().map(|url| redact_api_keys(url)).collect();
+        f.debug_struct("Cluster")
+            .field("rpc_url", &redact_api_keys(&self.rpc_url))
+            .field("read_rpc_url", &redact_api_keys(&self.read_rpc_url))
+            .field("send_rpc_url", &redact_api_keys(&self.send_rpc_url))
+            .field("block_engine_url", &redact_api_keys(&self.block_engine_url))
+            .field("nextblock_url", &redact_api_keys(&self.nextblock_url))
+            .field("nextblock_fallback_urls", &fallback_urls)
+            .field("nextblock_auth_token", &self.nextblock_auth_token)
+            .field("nextblock_options", &self.nextblock_options)
+            .field("zeroslot_url", &redact_api_keys(&self.zeroslot_url))
+            .field("zeroslot_auth_token", &self.zeroslot_auth_token)
+            .field("use_jito", &self.use_jito)
+            .field("use_nextblock", &self.use_nextblock)
+            .field("use_zeroslot", &self.use_zeroslot)
+            .field("clients", &self.clients)
+            .field("priority_fee", &self.priority_fee)
+            .field("commitment", &self.commitment)
+            .field("cluster_kind", &self.cluster_kind)
+            .field("skip_genesis_check", &self.skip_genesis_check)
+            .field("verify_program_on_startup", &self.verify_program_on_startup)
+            .field("rpc_send_options", &self.rpc_send_options)
+            .field("slippage_mode", &self.slippage_mode)
+            .field("skip_balance_check", &self.skip_balance_check)
+            .field("retry_fee_recipient_rotation", &self.retry_fee_recipient_rotation)
+            .field("sell_floor", &self.sell_floor)
+            .field("partial_fill", &self.partial_fill)
+            .field("buy_floor", &self.buy_floor)
+            .field("app_name", &self.app_name)
+            .field("ws_url", &redact_api_keys(&self.ws_url))
+            .field("grpc_url", &redact_api_keys(&self.grpc_url))
+            .finish()
+    }
 }
 
 impl Cluster {
@@ -42,52 +144,329 @@ impl Cluster {
     ) -> Self {
         Self { 
             rpc_url, 
+            read_rpc_url: String::new(),
+            send_rpc_url: String::new(),
             block_engine_url, 
             nextblock_url, 
-            nextblock_auth_token, 
+            nextblock_fallback_urls: vec![],
+            nextblock_auth_token: nextblock_auth_token.into(),
+            nextblock_options: NextBlockOptions::default(),
             zeroslot_url, 
-            zeroslot_auth_token, 
+            zeroslot_auth_token: zeroslot_auth_token.into(),
             priority_fee, 
             commitment, 
+            cluster_kind: None,
+            skip_genesis_check: false,
             use_jito, 
             use_nextblock, 
-            use_zeroslot 
+            use_zeroslot,
+            clients: vec![],
+            verify_program_on_startup: false,
+            rpc_send_options: RpcSendOptions::default(),
+            slippage_mode: SlippageMode::default(),
+            skip_balance_check: false,
+            retry_fee_recipient_rotation: false,
+            sell_floor: SellFloor::default(),
+            partial_fill: PartialFillGuard::default(),
+            buy_floor: BuyFloor::default(),
+            app_name: None,
+            ws_url: String::new(),
+            grpc_url: String::new(),
         }
     }
-}
 
-#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+    /// Sends and confirms transactions through `send_rpc_url` and reads through `read_rpc_url`;
+    /// an empty URL falls back to `rpc_url`
+    pub fn with_rpc_urls(mut self, read_rpc_url: String, send_rpc_url: String) -> Self {
+        self.read_rpc_url = read_rpc_url;
+        self.send_rpc_url = send_rpc_url;
+        self
+    }
+
+    /// Endpoint of reads: `read_rpc_url`, or `rpc_url` when unset
+    pub fn read_endpoint(&self) -> &str {
+        if self.read_rpc_url.is_empty() { &self.rpc_url } else { &self.read_rpc_url }
+    }
+
+    /// Endpoint of sends and their confirmation: `send_rpc_url`, or `rpc_url` when unset
+    pub fn send_endpoint(&self) -> &str {
+        if self.send_rpc_url.is_empty() { &self.rpc_url } else { &self.send_rpc_url }
+    }
+
+    /// Sets the regional NextBlock endpoints to fail over to
+    pub fn with_nextblock_fallback_urls(mut self, urls: Vec<String>) -> Self {
+        self.nextblock_fallback_urls = urls;
+        self
+    }
+
+    /// Sets the default NextBlock routing flags
+    pub fn with_nextblock_options(mut self, options: NextBlockOptions) -> Self {
+        self.nextblock_options = options;
+        self
+    }
+
+    /// Enables the clients labeled in `clients`, alongside any enabled through the `use_*` flags
+    pub fn with_clients(mut self, clients: Vec<ClientType>) -> Self {
+        self.clients = clients;
+        self
+    }
+
+    /// Whether `client_type` is enabled by its `use_*` flag or listed in `clients`
+    pub fn is_enabled(&self, client_type: &ClientType) -> bool {
+        let flag = match client_type {
+            ClientType::Jito => self.use_jito,
+            ClientType::NextBlock => self.use_nextblock,
+            ClientType::ZeroSlot => self.use_zeroslot,
+            _ => false,
+        };
+        flag || self.clients.contains(client_type)
+    }
+
+    /// Sets the cluster the RPC node must serve, see [`genesis`](super::genesis)
+    pub fn with_cluster_kind(mut self, cluster_kind: ClusterKind) -> Self {
+        self.cluster_kind = Some(cluster_kind);
+        self
+    }
+
+    /// Skips the genesis hash check of `PumpFun::try_new`, even with a `cluster_kind` set
+    pub fn with_skip_genesis_check(mut self, skip: bool) -> Self {
+        self.skip_genesis_check = skip;
+        self
+    }
+
+    /// Enables the IDL compatibility check at construction, see `PumpFun::verify_program_compatibility`
+    pub fn with_program_verification(mut self, enabled: bool) -> Self {
+        self.verify_program_on_startup = enabled;
+        self
+    }
+
+    /// Sets the preflight and retry settings of plain RPC sends
+    pub fn with_rpc_send_options(mut self, options: RpcSendOptions) -> Self {
+        self.rpc_send_options = options;
+        self
+    }
+
+    /// Sets how buys apply their slippage tolerance
+    pub fn with_slippage_mode(mut self, mode: SlippageMode) -> Self {
+        self.slippage_mode = mode;
+        self
+    }
+
+    /// Skips the balance check buys run before sending, see `pumpfun::balance`
+    pub fn with_skip_balance_check(mut self, skip: bool) -> Self {
+        self.skip_balance_check = skip;
+        self
+    }
+
+    /// Retries buys and sells once on a rotated fee recipient, see `pumpfun::fee_recipient`
+    pub fn with_retry_fee_recipient_rotation(mut self, enabled: bool) -> Self {
+        self.retry_fee_recipient_rotation = enabled;
+        self
+    }
+
+    /// Sets the lowest minimum output sells are sent with, see `pumpfun::common::SellFloor`
+    pub fn with_sell_floor(mut self, sell_floor: SellFloor) -> Self {
+        self.sell_floor = sell_floor;
+        self
+    }
+
+    /// Sets when buys from a nearly exhausted curve are refused, see
+    /// `pumpfun::common::PartialFillGuard`
+    pub fn with_partial_fill(mut self, partial_fill: PartialFillGuard) -> Self {
+        self.partial_fill = partial_fill;
+        self
+    }
+
+    /// Sets the smallest buy that is sent, see `pumpfun::common::BuyFloor`
+    pub fn with_buy_floor(mut self, buy_floor: BuyFloor) -> Self {
+        self.buy_floor = buy_floor;
+        self
+    }
+
+    /// Sets the application name relays and RPC providers see after `pumpfun-sdk/<version>`
+    pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Sets the WebSocket endpoint of event subscriptions
+    pub fn with_ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.ws_url = ws_url.into();
+        self
+    }
+
+    /// Sets the Yellowstone gRPC endpoint of event subscriptions, preferred over `ws_url`
+    pub fn with_grpc_url(mut self, grpc_url: impl Into<String>) -> Self {
+        self.grpc_url = grpc_url.into();
+        self
+    }
+
+    /// Returns `nextblock_url` followed by the fallback endpoints
+    pub fn nextblock_urls(&self) -> Vec<String> {
+        std::iter::once(self.nextblock_url.clone())
+            .chain(self.nextblock_fallback_urls.iter().cloned())
+            .collect()
+    }
+}
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
 pub struct PriorityFee {
-    pub unit_limit: u32,
+    /// Compute unit limit of operations without their own limit; without it each operation
+    /// requests its typical consumption with headroom, see [`DEFAULT_BUY_UNIT_LIMIT`]
+    pub unit_limit: Option<u32>,
+    pub buy_unit_limit: Option<u32>,
+    pub sell_unit_limit: Option<u32>,
+    /// Limit of create-and-buy transactions
+    pub create_unit_limit: Option<u32>,
     pub unit_price: u64,
-    pub buy_tip_fee: f64,
-    pub sell_tip_fee: f64,
+    pub buy_tip_fee: TipAmount,
+    pub sell_tip_fee: TipAmount,
 }
 
 impl Default for PriorityFee {
     fn default() -> Self {
         Self { 
-            unit_limit: DEFAULT_COMPUTE_UNIT_LIMIT, 
+            unit_limit: None,
+            buy_unit_limit: None,
+            sell_unit_limit: None,
+            create_unit_limit: None,
             unit_price: DEFAULT_COMPUTE_UNIT_PRICE, 
-            buy_tip_fee: DEFAULT_BUY_TIP_FEE, 
-            sell_tip_fee: DEFAULT_SELL_TIP_FEE 
+            buy_tip_fee: TipAmount::Sol(DEFAULT_BUY_TIP_FEE), 
+            sell_tip_fee: TipAmount::Sol(DEFAULT_SELL_TIP_FEE) 
+        }
+    }
+}
+
+impl PriorityFee {
+    /// Compute units a buy requests
+    pub fn buy_limit(&self) -> u32 {
+        self.buy_unit_limit.or(self.unit_limit).unwrap_or(DEFAULT_BUY_UNIT_LIMIT)
+    }
+
+    /// Compute units a sell requests
+    pub fn sell_limit(&self) -> u32 {
+        self.sell_unit_limit.or(self.unit_limit).unwrap_or(DEFAULT_SELL_UNIT_LIMIT)
+    }
+
+    /// Compute units a create and buy requests
+    pub fn create_limit(&self) -> u32 {
+        self.create_unit_limit.or(self.unit_limit).unwrap_or(DEFAULT_CREATE_UNIT_LIMIT)
+    }
+}
+
+/// A tip paid to a relay's tip account
+///
+/// `Sol` amounts are rounded to the nearest lamport by [`TipAmount::lamports`]; configs convert
+/// them when parsed, so the tip sent is always the lamport amount the config resolved to. In a
+/// config a bare number is SOL and `{ lamports = 1000000 }` is exact; tips serialize as lamports.
+/// Tips compare by the lamports they pay.
+#[derive(Debug, Clone, Copy)]
+pub enum TipAmount {
+    Lamports(u64),
+    Sol(f64),
+}
+
+impl TipAmount {
+    /// The tip in lamports, rounding SOL to the nearest lamport (halves away from zero);
+    /// negative and NaN amounts pay nothing
+    pub fn lamports(self) -> u64 {
+        match self {
+            TipAmount::Lamports(lamports) => lamports,
+            // `as` saturates, so NaN and negative amounts become 0
+            TipAmount::Sol(sol) => (sol * LAMPORTS_PER_SOL as f64).round() as u64,
+        }
+    }
+
+    /// Converts a SOL amount to lamports, rejecting amounts no tip can be
+    pub fn from_sol(sol: f64) -> Result<Self, String> {
+        if !sol.is_finite() || sol < 0.0 {
+            return Err(format!("invalid tip {} SOL, expected a non-negative amount", sol));
         }
+        Ok(TipAmount::Lamports(TipAmount::Sol(sol).lamports()))
+    }
+}
+
+impl PartialEq for TipAmount {
+    fn eq(&self, other: &Self) -> bool {
+        self.lamports() == other.lamports()
+    }
+}
+
+impl Eq for TipAmount {}
+
+impl Serialize for TipAmount {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut tip = serializer.serialize_struct("TipAmount", 1)?;
+        tip.serialize_field("lamports", &self.lamports())?;
+        tip.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for TipAmount {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TipVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for TipVisitor {
+            type Value = TipAmount;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a tip in SOL or a table like { lamports = 1000000 }")
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, sol: f64) -> Result<TipAmount, E> {
+                TipAmount::from_sol(sol).map_err(E::custom)
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, sol: i64) -> Result<TipAmount, E> {
+                self.visit_f64(sol as f64)
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, sol: u64) -> Result<TipAmount, E> {
+                self.visit_f64(sol as f64)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<TipAmount, A::Error> {
+                let mut tip = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    if tip.is_some() {
+                        return Err(serde::de::Error::custom("a tip takes one of `lamports` or `sol`"));
+                    }
+                    tip = Some(match key.as_str() {
+                        "lamports" => TipAmount::Lamports(map.next_value()?),
+                        "sol" => TipAmount::from_sol(map.next_value()?).map_err(serde::de::Error::custom)?,
+                        other => return Err(serde::de::Error::unknown_field(other, &["lamports", "sol"])),
+                    });
+                }
+                tip.ok_or_else(|| serde::de::Error::missing_field("lamports"))
+            }
+        }
+
+        deserializer.deserialize_any(TipVisitor)
     }
 }
 
 pub type SolanaRpcClient = solana_client::nonblocking::rpc_client::RpcClient;
 
-pub struct MethodArgs {
-    pub payer: Arc<Keypair>,
-    pub rpc: Arc<RpcClient>,
-    pub nonblocking_rpc: Arc<SolanaRpcClient>,
-    pub jito_client: Arc<FeeClient>,
+/// The RPC clients of a trade: quotes, account reads and blockhashes go to `read`, the
+/// transactions and their confirmation to `send`
+#[derive(Clone)]
+pub struct RpcClients {
+    pub read: Arc<SolanaRpcClient>,
+    pub send: Arc<SolanaRpcClient>,
 }
 
-impl MethodArgs {
-    pub fn new(payer: Arc<Keypair>, rpc: Arc<RpcClient>, nonblocking_rpc: Arc<SolanaRpcClient>, jito_client: Arc<FeeClient>) -> Self {
-        Self { payer, rpc, nonblocking_rpc, jito_client }
+impl RpcClients {
+    pub fn new(read: Arc<SolanaRpcClient>, send: Arc<SolanaRpcClient>) -> Self {
+        Self { read, send }
     }
 }
 
+/// One client for both reads and sends
+impl From<Arc<SolanaRpcClient>> for RpcClients {
+    fn from(rpc: Arc<SolanaRpcClient>) -> Self {
+        Self { read: rpc.clone(), send: rpc }
+    }
+}
@@ -0,0 +1,77 @@
+//! Identification of the SDK on outbound requests
+//!
+//! Relays and RPC providers see `pumpfun-sdk/<version>` as the user agent of HTTP requests and
+//! in the `x-client` metadata of gRPC requests, followed by the application name set through
+//! [`Cluster::with_app_name`](super::Cluster::with_app_name). The name is process-wide; the
+//! last client constructed with one sets it.
+
+use std::sync::RwLock;
+
+use tonic::{service::Interceptor, Request, Status};
+
+/// Version of the SDK, as published
+pub const SDK_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// gRPC metadata key carrying the [`user_agent`]
+pub const CLIENT_METADATA_KEY: &str = "x-client";
+
+lazy_static::lazy_static! {
+    static ref APP_NAME: RwLock<Option<String>> = RwLock::new(None);
+}
+
+/// Sets the application name appended to the user agent, or removes it with `None`
+///
+/// Characters not allowed in an HTTP header are replaced with `_`.
+pub fn set_app_name(app_name: Option<String>) {
+    let app_name = app_name.map(|name| name.chars().map(|c| if c.is_ascii_graphic() || c == ' ' { c } else { '_' }).collect());
+    *APP_NAME.write().unwrap() = app_name;
+}
+
+/// `pumpfun-sdk/<version>`, followed by the application name if one is set
+pub fn user_agent() -> String {
+    match APP_NAME.read().unwrap().as_deref() {
+        Some(app_name) if !app_name.trim().is_empty() => format!("pumpfun-sdk/{} {}", SDK_VERSION, app_name.trim()),
+        _ => format!("pumpfun-sdk/{}", SDK_VERSION),
+    }
+}
+
+/// HTTP client builder sending the [`user_agent`]
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().user_agent(user_agent())
+}
+
+/// HTTP client sending the [`user_agent`]
+pub fn http_client() -> reqwest::Client {
+    http_client_builder().build().unwrap_or_default()
+}
+
+/// Adds the [`user_agent`] to every gRPC request as [`CLIENT_METADATA_KEY`] metadata
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientHeaderInterceptor;
+
+impl Interceptor for ClientHeaderInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Ok(value) = user_agent().parse() {
+            request.metadata_mut().insert(CLIENT_METADATA_KEY, value);
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_agent_names_the_sdk_and_the_app() {
+        assert!(user_agent().starts_with(&format!("pumpfun-sdk/{}", SDK_VERSION)));
+
+        set_app_name(Some("sniper\n1.2".to_string()));
+        assert_eq!(user_agent(), format!("pumpfun-sdk/{} sniper_1.2", SDK_VERSION));
+        let request = ClientHeaderInterceptor.call(Request::new(())).unwrap();
+        assert_eq!(request.metadata().get(CLIENT_METADATA_KEY).unwrap().to_str().unwrap(), user_agent());
+
+        set_app_name(None);
+        assert_eq!(user_agent(), format!("pumpfun-sdk/{}", SDK_VERSION));
+    }
+}
@@ -0,0 +1,168 @@
+//! Trades made by a set of watched wallets
+//!
+//! Wallet activity subscriptions report every Pump.fun trade a watched wallet makes, including
+//! trades made outside the SDK (e.g. manually from a browser), so position tracking stays
+//! consistent with the chain. Each trade is labeled with the acting wallet and, when the
+//! client tags its trades with a [`TradeTag`], whether the SDK initiated it.
+//!
+//! Only bonding curve trades are parsed; trades on the AMM after graduation are not reported.
+
+use std::collections::{HashSet, VecDeque};
+
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use super::{
+    logs_data::{DexInstruction, TradeInfo},
+    logs_filters::LogFilter,
+    TradeTag,
+};
+
+/// Number of recent signatures remembered to drop transactions delivered more than once
+pub(crate) const RECENT_SIGNATURES_CAPACITY: usize = 1_024;
+
+/// Who initiated a wallet trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeInitiator {
+    /// The transaction carries the client's trade tag
+    Sdk,
+    /// The transaction doesn't carry the client's trade tag
+    External,
+    /// The client has no trade tag, so SDK trades can't be told apart
+    Unknown,
+}
+
+/// A Pump.fun trade made by a watched wallet
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletTrade {
+    pub wallet: Pubkey,
+    pub signature: Signature,
+    pub initiator: TradeInitiator,
+    pub trade: TradeInfo,
+}
+
+/// Extracts the trades of `wallets` from the logs of one successful transaction
+pub fn wallet_trades(
+    signature: Signature,
+    slot: u64,
+    logs: &[String],
+    wallets: &HashSet<Pubkey>,
+    trade_tag: Option<&TradeTag>,
+) -> Vec<WalletTrade> {
+    let Ok(instructions) = LogFilter::parse_instruction(logs, None) else {
+        return vec![];
+    };
+
+    let initiator = match trade_tag {
+        Some(tag) if tag.appears_in(logs) => TradeInitiator::Sdk,
+        Some(_) => TradeInitiator::External,
+        None => TradeInitiator::Unknown,
+    };
+
+    instructions
+        .into_iter()
+        .filter_map(|instruction| match instruction {
+            DexInstruction::UserTrade(trade) if wallets.contains(&trade.user) => Some(trade),
+            _ => None,
+        })
+        .map(|mut trade| {
+            trade.slot = slot;
+            WalletTrade { wallet: trade.user, signature, initiator, trade }
+        })
+        .collect()
+}
+
+/// Bounded set of recently seen signatures
+pub(crate) struct RecentSignatures {
+    capacity: usize,
+    order: VecDeque<Signature>,
+    seen: HashSet<Signature>,
+}
+
+impl RecentSignatures {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { capacity, order: VecDeque::with_capacity(capacity), seen: HashSet::with_capacity(capacity) }
+    }
+
+    /// Records `signature`, returning `false` if it was already seen
+    pub(crate) fn insert(&mut self, signature: Signature) -> bool {
+        if !self.seen.insert(signature) {
+            return false;
+        }
+        self.order.push_back(signature);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+    use super::*;
+    use crate::constants;
+
+    /// Logs of a transaction making one Pump.fun buy for `user`, optionally tagged
+    fn trade_logs(mint: &Pubkey, user: &Pubkey, tag: Option<&TradeTag>) -> Vec<String> {
+        let mut data = vec![0u8; 8];
+        data.extend_from_slice(mint.as_ref());
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&35_000_000u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(user.as_ref());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        for reserves in [30_001_000_000u64, 1_072_965_000_000_000, 1_000_000, 793_064_999_999_999] {
+            data.extend_from_slice(&reserves.to_le_bytes());
+        }
+
+        let program = constants::accounts::PUMPFUN;
+        let mut logs = vec![
+            format!("Program {} invoke [1]", program),
+            "Program log: Instruction: Buy".to_string(),
+            format!("Program data: {}", BASE64.encode(&data)),
+            format!("Program {} success", program),
+        ];
+        if let Some(tag) = tag {
+            logs.push(format!("Program log: Memo (len {}): {:?}", tag.as_str().len(), tag.as_str()));
+        }
+        logs
+    }
+
+    #[test]
+    fn test_wallet_trades_are_labeled() {
+        let (mint, wallet, other) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let wallets = HashSet::from([wallet]);
+        let tag = TradeTag::new("sniper-1").unwrap();
+        let signature = Signature::new_unique();
+
+        let trades = wallet_trades(signature, 42, &trade_logs(&mint, &wallet, Some(&tag)), &wallets, Some(&tag));
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].wallet, wallet);
+        assert_eq!(trades[0].signature, signature);
+        assert_eq!(trades[0].initiator, TradeInitiator::Sdk);
+        assert_eq!((trades[0].trade.mint, trades[0].trade.slot, trades[0].trade.is_buy), (mint, 42, true));
+
+        let manual = wallet_trades(signature, 42, &trade_logs(&mint, &wallet, None), &wallets, Some(&tag));
+        assert_eq!(manual[0].initiator, TradeInitiator::External);
+        let untagged = wallet_trades(signature, 42, &trade_logs(&mint, &wallet, None), &wallets, None);
+        assert_eq!(untagged[0].initiator, TradeInitiator::Unknown);
+
+        assert!(wallet_trades(signature, 42, &trade_logs(&mint, &other, None), &wallets, None).is_empty());
+    }
+
+    #[test]
+    fn test_recent_signatures_forget_oldest() {
+        let mut recent = RecentSignatures::new(2);
+        let (first, second, third) = (Signature::new_unique(), Signature::new_unique(), Signature::new_unique());
+
+        assert!(recent.insert(first));
+        assert!(!recent.insert(first));
+        assert!(recent.insert(second));
+        assert!(recent.insert(third));
+        assert!(recent.insert(first));
+        assert!(!recent.insert(third));
+    }
+}
@@ -0,0 +1,311 @@
+//! Per-mint callbacks over one shared Pump.fun subscription
+//!
+//! A [`MintWatchlist`] keeps a single program-wide subscription, over WebSocket or gRPC, and
+//! routes each event to the callback of the mint it concerns. Mints are added and removed at
+//! runtime without resubscribing: the subscription filters on the Pump.fun program and the
+//! routing happens locally, so no events are lost while the watchlist changes. Removing the
+//! last mint stops the subscription and closes the watchlist.
+//!
+//! The routing table is an immutable snapshot swapped on every change; dispatching an event
+//! only clones the current snapshot's `Arc`, and callbacks may add or remove mints themselves.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+use tokio::task::AbortHandle;
+
+use super::{commitment::EventCommitment, logs_events::PumpfunEvent, logs_subscribe};
+use crate::grpc::YellowstoneGrpc;
+
+/// Receives the events of one watched mint along with the mint
+pub type MintCallback = Arc<dyn Fn(Pubkey, PumpfunEvent) + Send + Sync>;
+
+type Routes = HashMap<Pubkey, MintCallback>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum WatchlistError {
+    #[error("no mints to watch")]
+    Empty,
+    #[error("the watchlist was shut down")]
+    Closed,
+}
+
+/// Where the shared subscription of a watchlist reads events from
+#[derive(Clone)]
+pub enum WatchSource {
+    WebSocket { ws_url: String, commitment: EventCommitment },
+    Grpc(YellowstoneGrpc),
+}
+
+/// Mint to callback routing shared by a watchlist and its subscription task
+struct MintRouter {
+    routes: RwLock<Arc<Routes>>,
+    closed: AtomicBool,
+    subscription: Mutex<Option<AbortHandle>>,
+}
+
+impl MintRouter {
+    fn new(routes: Routes) -> Self {
+        Self { routes: RwLock::new(Arc::new(routes)), closed: AtomicBool::new(false), subscription: Mutex::new(None) }
+    }
+
+    fn snapshot(&self) -> Arc<Routes> {
+        self.routes.read().unwrap().clone()
+    }
+
+    /// Applies `change` to a copy of the routes and publishes it, unless the router is closed;
+    /// leaving no routes closes the router and stops the subscription
+    fn update<T>(&self, change: impl FnOnce(&mut Routes) -> T) -> Result<T, WatchlistError> {
+        let mut routes = self.routes.write().unwrap();
+        if self.closed.load(Ordering::Acquire) {
+            return Err(WatchlistError::Closed);
+        }
+        let mut next = Routes::clone(&routes);
+        let result = change(&mut next);
+        let emptied = next.is_empty();
+        *routes = Arc::new(next);
+        if emptied {
+            self.closed.store(true, Ordering::Release);
+            drop(routes);
+            self.stop_subscription();
+        }
+        Ok(result)
+    }
+
+    /// Passes `event` to the callback of its mint; errors, staleness and watermarks go to every mint
+    fn dispatch(&self, event: PumpfunEvent) {
+        let routes = self.snapshot();
+        let mint = match &event {
+            PumpfunEvent::NewToken(token) => token.mint,
+            PumpfunEvent::NewDevTrade(trade) | PumpfunEvent::NewUserTrade(trade) | PumpfunEvent::NewBotTrade(trade) => trade.mint,
+            PumpfunEvent::OwnTradeSubmitted(own) | PumpfunEvent::OwnTradeConfirmed(own) | PumpfunEvent::OwnTradeFailed(own) => own.mint,
+            PumpfunEvent::WalletTrade(wallet_trade) => wallet_trade.trade.mint,
+            PumpfunEvent::Error(message) => {
+                for (mint, callback) in routes.iter() {
+                    callback(*mint, PumpfunEvent::Error(message.clone()));
+                }
+                return;
+            }
+            PumpfunEvent::Stale(silent_for) => {
+                for (mint, callback) in routes.iter() {
+                    callback(*mint, PumpfunEvent::Stale(*silent_for));
+                }
+                return;
+            }
+            PumpfunEvent::Watermark(watermark) => {
+                for (mint, callback) in routes.iter() {
+                    callback(*mint, PumpfunEvent::Watermark(*watermark));
+                }
+                return;
+            }
+        };
+        if let Some(callback) = routes.get(&mint) {
+            callback(mint, event);
+        }
+    }
+
+    /// Stops the subscription and refuses further mints
+    fn close(&self) {
+        {
+            let mut routes = self.routes.write().unwrap();
+            self.closed.store(true, Ordering::Release);
+            *routes = Arc::new(Routes::new());
+        }
+        self.stop_subscription();
+    }
+
+    fn stop_subscription(&self) {
+        if let Some(subscription) = self.subscription.lock().unwrap().take() {
+            subscription.abort();
+        }
+    }
+}
+
+/// Mints watched over one shared subscription, each routed to its callback
+#[derive(Clone)]
+pub struct MintWatchlist {
+    router: Arc<MintRouter>,
+    callback: MintCallback,
+}
+
+impl MintWatchlist {
+    /// Subscribes through `source` and routes the events of `mints` to `per_mint`
+    ///
+    /// Fails with [`WatchlistError::Empty`] without `mints`, and if the WebSocket provider
+    /// refuses the subscription. A gRPC subscription connects in the background and reports
+    /// connection failures to every mint as `PumpfunEvent::Error`.
+    pub async fn subscribe<F>(source: WatchSource, mints: Vec<Pubkey>, per_mint: F) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        F: Fn(Pubkey, PumpfunEvent) + Send + Sync + 'static,
+    {
+        if mints.is_empty() {
+            return Err(WatchlistError::Empty.into());
+        }
+        let callback: MintCallback = Arc::new(per_mint);
+        let routes = mints.into_iter().map(|mint| (mint, callback.clone())).collect();
+        let router = Arc::new(MintRouter::new(routes));
+
+        let dispatcher = router.clone();
+        let dispatch = move |event: PumpfunEvent| dispatcher.dispatch(event);
+        let subscription = match source {
+            WatchSource::WebSocket { ws_url, commitment } => {
+                logs_subscribe::tokens_subscription(&ws_url, commitment, dispatch, None).await?.task.abort_handle()
+            }
+            WatchSource::Grpc(grpc) => tokio::spawn(async move {
+                let report = dispatch.clone();
+                if let Err(e) = grpc.subscribe_pumpfun(dispatch, None).await {
+                    report(PumpfunEvent::Error(format!("Failed to subscribe: {}", e)));
+                }
+            })
+            .abort_handle(),
+        };
+        *router.subscription.lock().unwrap() = Some(subscription);
+        // a callback may have removed every mint before the handle was stored
+        if router.closed.load(Ordering::Acquire) {
+            router.stop_subscription();
+        }
+
+        Ok(Self { router, callback })
+    }
+
+    /// Starts routing the events of `mint` to the watchlist's callback; `false` if it was
+    /// already watched
+    pub fn add_mint(&self, mint: Pubkey) -> Result<bool, WatchlistError> {
+        self.add_mint_with_callback(mint, self.callback.clone())
+    }
+
+    /// Like [`add_mint`](Self::add_mint), routing the events of `mint` to `callback` instead
+    pub fn add_mint_with<F>(&self, mint: Pubkey, callback: F) -> Result<bool, WatchlistError>
+    where
+        F: Fn(Pubkey, PumpfunEvent) + Send + Sync + 'static,
+    {
+        self.add_mint_with_callback(mint, Arc::new(callback))
+    }
+
+    fn add_mint_with_callback(&self, mint: Pubkey, callback: MintCallback) -> Result<bool, WatchlistError> {
+        let previous = self.router.update(|routes| routes.insert(mint, callback))?;
+        Ok(previous.is_none())
+    }
+
+    /// Stops routing the events of `mint`; `false` if it wasn't watched
+    ///
+    /// Removing the last mint stops the subscription and closes the watchlist.
+    pub fn remove_mint(&self, mint: &Pubkey) -> bool {
+        self.router.update(|routes| routes.remove(mint).is_some()).unwrap_or(false)
+    }
+
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.router.snapshot().contains_key(mint)
+    }
+
+    /// The watched mints, in no particular order
+    pub fn mints(&self) -> Vec<Pubkey> {
+        self.router.snapshot().keys().copied().collect()
+    }
+
+    /// Whether the watchlist was emptied or shut down; a closed watchlist accepts no mints
+    pub fn is_closed(&self) -> bool {
+        self.router.closed.load(Ordering::Acquire)
+    }
+
+    /// Stops the subscription and removes every mint
+    pub fn shutdown(&self) {
+        self.router.close();
+    }
+
+    /// Abort handle of the shared subscription, `None` once closed
+    pub(crate) fn abort_handle(&self) -> Option<AbortHandle> {
+        self.router.subscription.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::logs_data::TradeInfo;
+
+    fn trade(mint: Pubkey) -> PumpfunEvent {
+        PumpfunEvent::NewUserTrade(TradeInfo { mint, ..Default::default() })
+    }
+
+    fn recording(seen: &Arc<Mutex<Vec<(Pubkey, String)>>>) -> MintCallback {
+        let seen = seen.clone();
+        Arc::new(move |mint: Pubkey, event: PumpfunEvent| {
+            let kind = match event {
+                PumpfunEvent::NewUserTrade(_) => "trade".to_string(),
+                PumpfunEvent::Error(message) => message,
+                _ => "other".to_string(),
+            };
+            seen.lock().unwrap().push((mint, kind));
+        })
+    }
+
+    fn watchlist(mints: &[Pubkey], callback: MintCallback) -> MintWatchlist {
+        let routes = mints.iter().map(|mint| (*mint, callback.clone())).collect();
+        MintWatchlist { router: Arc::new(MintRouter::new(routes)), callback }
+    }
+
+    #[test]
+    fn test_events_are_routed_by_mint() {
+        let (watched, added, other) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let seen = Arc::new(Mutex::new(vec![]));
+        let watchlist = watchlist(&[watched], recording(&seen));
+
+        watchlist.router.dispatch(trade(watched));
+        watchlist.router.dispatch(trade(other));
+        assert_eq!(*seen.lock().unwrap(), vec![(watched, "trade".to_string())]);
+
+        let added_seen = Arc::new(Mutex::new(vec![]));
+        let added_callback = recording(&added_seen);
+        assert_eq!(watchlist.add_mint_with(added, move |mint, event| added_callback(mint, event)), Ok(true));
+        assert_eq!(watchlist.add_mint(watched), Ok(false));
+        watchlist.router.dispatch(trade(added));
+        assert_eq!(*added_seen.lock().unwrap(), vec![(added, "trade".to_string())]);
+
+        seen.lock().unwrap().clear();
+        watchlist.router.dispatch(PumpfunEvent::Error("Stream closed".to_string()));
+        assert_eq!(*seen.lock().unwrap(), vec![(watched, "Stream closed".to_string())]);
+        assert_eq!(added_seen.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_removing_the_last_mint_closes_the_watchlist() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let seen = Arc::new(Mutex::new(vec![]));
+        let watchlist = watchlist(&[first, second], recording(&seen));
+
+        assert!(watchlist.remove_mint(&first));
+        assert!(!watchlist.remove_mint(&first));
+        watchlist.router.dispatch(trade(first));
+        assert!(seen.lock().unwrap().is_empty());
+        assert!(!watchlist.is_closed());
+
+        assert!(watchlist.remove_mint(&second));
+        assert!(watchlist.is_closed());
+        assert_eq!(watchlist.add_mint(first), Err(WatchlistError::Closed));
+        assert!(watchlist.mints().is_empty());
+    }
+
+    #[test]
+    fn test_callbacks_can_change_the_watchlist() {
+        let (mint, next) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let holder: Arc<Mutex<Option<MintWatchlist>>> = Arc::new(Mutex::new(None));
+        let inner = holder.clone();
+        let watchlist = watchlist(&[mint], Arc::new(move |mint: Pubkey, _: PumpfunEvent| {
+            let watchlist = inner.lock().unwrap().clone().unwrap();
+            watchlist.add_mint(next).unwrap();
+            watchlist.remove_mint(&mint);
+        }));
+        *holder.lock().unwrap() = Some(watchlist.clone());
+
+        watchlist.router.dispatch(trade(mint));
+        assert_eq!(watchlist.mints(), vec![next]);
+    }
+}
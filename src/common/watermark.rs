@@ -0,0 +1,247 @@
+//! Event-time watermarks and reconnect gaps per source
+//!
+//! A [`Watermarks`] shared by subscriptions tracks, per [`EventSource`], the highest slot
+//! whose events were all delivered: the newest slot seen minus [`Watermarks::lag_slots`], held
+//! back by the first gap. Subscriptions record the slot of every message once its events went
+//! to the callback, so a consumer that receives `Watermark { source, slot }` has already
+//! received every event of `source` up to `slot` and can commit them.
+//!
+//! When a stream reconnects, the slots between the last one seen before and the first one seen
+//! after were possibly missed, both ends included since either may be partial. That range is
+//! recorded as a [`SlotGap`] and the watermark stalls below it until [`Watermarks::heal`] is
+//! called, e.g. by a backfill job that replayed the range from transaction history. The slot a
+//! source started in may be partial too; it is not a gap.
+//!
+//! [`Watermarks::spawn_emitter`] passes `PumpfunEvent::Watermark` to a callback whenever the
+//! watermark of a source advanced.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use super::{event_latency::EventSource, logs_events::PumpfunEvent};
+
+/// Slots a slot is held back after it was first seen, so late events of it are still counted
+pub const DEFAULT_LAG_SLOTS: u64 = 1;
+/// Default interval at which [`Watermarks::spawn_emitter`] checks for advanced watermarks
+pub const DEFAULT_EMIT_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Every event of `source` up to `slot` was delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watermark {
+    pub source: EventSource,
+    pub slot: u64,
+}
+
+/// Slots `first..=last` of `source` were possibly missed while it reconnected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SlotGap {
+    pub source: EventSource,
+    pub first: u64,
+    pub last: u64,
+}
+
+#[derive(Debug, Default)]
+struct SourceState {
+    newest_slot: Option<u64>,
+    /// Newest slot seen before the stream disconnected, until it delivers again
+    disconnected_at: Option<u64>,
+    gaps: BTreeSet<(u64, u64)>,
+    emitted: Option<u64>,
+}
+
+impl SourceState {
+    fn watermark(&self, lag_slots: u64) -> Option<u64> {
+        let newest = self.newest_slot?;
+        let mut watermark = newest.checked_sub(lag_slots)?;
+        if let Some(disconnected_at) = self.disconnected_at {
+            watermark = watermark.min(disconnected_at.checked_sub(1)?);
+        }
+        if let Some((first, _)) = self.gaps.first() {
+            watermark = watermark.min(first.checked_sub(1)?);
+        }
+        Some(watermark)
+    }
+}
+
+/// Watermarks and gaps of the sources sharing it, see the [module docs](self)
+#[derive(Debug)]
+pub struct Watermarks {
+    lag_slots: u64,
+    sources: Mutex<BTreeMap<EventSource, SourceState>>,
+}
+
+impl Default for Watermarks {
+    fn default() -> Self {
+        Self::new(DEFAULT_LAG_SLOTS)
+    }
+}
+
+impl Watermarks {
+    pub fn new(lag_slots: u64) -> Self {
+        Self { lag_slots, sources: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn lag_slots(&self) -> u64 {
+        self.lag_slots
+    }
+
+    /// Records that the events of a message of `source` in `slot` were delivered
+    pub fn observe(&self, source: EventSource, slot: u64) {
+        if slot == 0 {
+            return;
+        }
+        let mut sources = self.sources.lock().unwrap();
+        let state = sources.entry(source).or_default();
+        if let Some(disconnected_at) = state.disconnected_at.take() {
+            state.gaps.insert((disconnected_at, slot.max(disconnected_at)));
+        }
+        state.newest_slot = Some(state.newest_slot.map_or(slot, |newest| newest.max(slot)));
+    }
+
+    /// Records that `source` lost its stream; the slots until it delivers again become a gap
+    pub fn disconnected(&self, source: EventSource) {
+        let mut sources = self.sources.lock().unwrap();
+        let state = sources.entry(source).or_default();
+        if state.disconnected_at.is_none() {
+            state.disconnected_at = state.newest_slot;
+        }
+    }
+
+    /// Highest slot of `source` whose events were all delivered
+    pub fn watermark(&self, source: EventSource) -> Option<u64> {
+        self.sources.lock().unwrap().get(&source).and_then(|state| state.watermark(self.lag_slots))
+    }
+
+    /// Gaps of `source` not healed yet, oldest first
+    pub fn gaps(&self, source: EventSource) -> Vec<SlotGap> {
+        let sources = self.sources.lock().unwrap();
+        let Some(state) = sources.get(&source) else {
+            return vec![];
+        };
+        state.gaps.iter().map(|(first, last)| SlotGap { source, first: *first, last: *last }).collect()
+    }
+
+    /// Gaps of every source not healed yet
+    pub fn all_gaps(&self) -> Vec<SlotGap> {
+        let sources = self.sources.lock().unwrap();
+        sources
+            .iter()
+            .flat_map(|(source, state)| state.gaps.iter().map(|(first, last)| SlotGap { source: *source, first: *first, last: *last }))
+            .collect()
+    }
+
+    /// Marks `gap` as backfilled, letting the watermark move past it; false if it was not open
+    pub fn heal(&self, gap: &SlotGap) -> bool {
+        let mut sources = self.sources.lock().unwrap();
+        sources.get_mut(&gap.source).is_some_and(|state| state.gaps.remove(&(gap.first, gap.last)))
+    }
+
+    /// Watermarks that advanced since the last call
+    pub fn advanced(&self) -> Vec<Watermark> {
+        let mut sources = self.sources.lock().unwrap();
+        let mut advanced = vec![];
+        for (source, state) in sources.iter_mut() {
+            let Some(slot) = state.watermark(self.lag_slots) else {
+                continue;
+            };
+            if state.emitted.map_or(true, |emitted| slot > emitted) {
+                state.emitted = Some(slot);
+                advanced.push(Watermark { source: *source, slot });
+            }
+        }
+        advanced
+    }
+
+    /// Passes `PumpfunEvent::Watermark` to `callback` every `interval` for each source whose
+    /// watermark advanced, until the watermarks are dropped; must be called in a Tokio runtime
+    pub fn spawn_emitter<F>(self: &Arc<Self>, interval: Duration, callback: F) -> JoinHandle<()>
+    where
+        F: Fn(PumpfunEvent) + Send + Sync + 'static,
+    {
+        tokio::spawn(emit_loop(Arc::downgrade(self), interval, callback))
+    }
+}
+
+async fn emit_loop<F>(watermarks: Weak<Watermarks>, interval: Duration, callback: F)
+where
+    F: Fn(PumpfunEvent) + Send + Sync + 'static,
+{
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let Some(watermarks) = watermarks.upgrade() else {
+            return;
+        };
+        for watermark in watermarks.advanced() {
+            callback(PumpfunEvent::Watermark(watermark));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watermark_trails_the_newest_slot() {
+        let watermarks = Watermarks::default();
+        assert_eq!(watermarks.watermark(EventSource::Grpc), None);
+        watermarks.observe(EventSource::Grpc, 100);
+        watermarks.observe(EventSource::Grpc, 101);
+        watermarks.observe(EventSource::Grpc, 100);
+        assert_eq!(watermarks.watermark(EventSource::Grpc), Some(100));
+        assert_eq!(watermarks.watermark(EventSource::WebSocket), None);
+
+        assert_eq!(watermarks.advanced(), vec![Watermark { source: EventSource::Grpc, slot: 100 }]);
+        assert!(watermarks.advanced().is_empty());
+        watermarks.observe(EventSource::Grpc, 105);
+        assert_eq!(watermarks.advanced(), vec![Watermark { source: EventSource::Grpc, slot: 104 }]);
+    }
+
+    #[test]
+    fn test_watermark_stalls_at_a_gap_until_it_is_healed() {
+        let watermarks = Watermarks::default();
+        watermarks.observe(EventSource::WebSocket, 200);
+        watermarks.observe(EventSource::WebSocket, 205);
+        watermarks.disconnected(EventSource::WebSocket);
+        watermarks.disconnected(EventSource::WebSocket);
+        assert_eq!(watermarks.watermark(EventSource::WebSocket), Some(204));
+        assert!(watermarks.gaps(EventSource::WebSocket).is_empty());
+
+        watermarks.observe(EventSource::WebSocket, 230);
+        watermarks.observe(EventSource::WebSocket, 260);
+        let gap = SlotGap { source: EventSource::WebSocket, first: 205, last: 230 };
+        assert_eq!(watermarks.gaps(EventSource::WebSocket), vec![gap]);
+        assert_eq!(watermarks.all_gaps(), vec![gap]);
+        assert_eq!(watermarks.watermark(EventSource::WebSocket), Some(204));
+        assert_eq!(watermarks.advanced(), vec![Watermark { source: EventSource::WebSocket, slot: 204 }]);
+        assert!(watermarks.advanced().is_empty());
+
+        assert!(watermarks.heal(&gap));
+        assert!(!watermarks.heal(&gap));
+        assert_eq!(watermarks.watermark(EventSource::WebSocket), Some(259));
+        assert_eq!(watermarks.advanced(), vec![Watermark { source: EventSource::WebSocket, slot: 259 }]);
+    }
+
+    #[tokio::test]
+    async fn test_emitter_reports_advanced_watermarks() {
+        let watermarks = Arc::new(Watermarks::new(0));
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sink = seen.clone();
+        let emitter = watermarks.spawn_emitter(Duration::from_millis(5), move |event| sink.lock().unwrap().push(event));
+
+        watermarks.observe(EventSource::Grpc, 42);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        emitter.abort();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert!(matches!(seen[0], PumpfunEvent::Watermark(Watermark { source: EventSource::Grpc, slot: 42 })));
+    }
+}
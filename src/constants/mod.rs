@@ -9,6 +9,8 @@
 //!
 //! - `seeds`: Contains seed values used for PDA derivation
 //! - `accounts`: Contains important program account addresses
+//! - `tokens`: Contains token decimals, supply and SOL denominations, with conversions
+//! - `discriminators`: Contains the Anchor discriminators of the program's events
 
 /// Constants used as seeds for deriving PDAs (Program Derived Addresses)
 pub mod seeds {
@@ -23,12 +25,13 @@ pub mod seeds {
 
     /// Seed for metadata PDAs
     pub const METADATA_SEED: &[u8] = b"metadata";
+
+    /// Seed for creator vault PDAs
+    pub const CREATOR_VAULT_SEED: &[u8] = b"creator-vault";
 }
 
 /// Constants related to program accounts and authorities
 pub mod accounts {
-    use std::collections::{HashMap, HashSet};
-
     use solana_sdk::{pubkey, pubkey::Pubkey};
 
     /// Public key for the Pump.fun program
@@ -46,10 +49,16 @@ pub mod accounts {
     /// Token Program ID
     pub const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
 
+    /// Token-2022 Program ID
+    pub const TOKEN_2022_PROGRAM: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
     /// Associated Token Program ID
     pub const ASSOCIATED_TOKEN_PROGRAM: Pubkey =
         pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
 
+    /// SPL Memo Program ID
+    pub const MEMO_PROGRAM: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
     /// Rent Sysvar ID
     pub const RENT: Pubkey = pubkey!("SysvarRent111111111111111111111111111111111");
 
@@ -88,10 +97,83 @@ pub mod accounts {
     pub const AMM_PROGRAM: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
 }
 
+/// Token units and SOL denominations
+///
+/// Amounts on chain are raw: a whole token is `TOKEN_UNIT` raw units and a SOL is
+/// `LAMPORTS_PER_SOL` lamports. Prices and amounts shown to users convert through these.
+pub mod tokens {
+    /// Decimals of every Pump.fun mint
+    pub const DECIMALS: u8 = 6;
+
+    /// Raw units per whole token
+    pub const TOKEN_UNIT: u64 = 10u64.pow(DECIMALS as u32);
+
+    /// Raw supply minted at creation, one billion tokens
+    pub const TOTAL_SUPPLY: u64 = 1_000_000_000 * TOKEN_UNIT;
+
+    /// Lamports per SOL
+    pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+    /// Whole tokens in `amount` raw units
+    #[inline]
+    pub fn tokens_to_ui(amount: u64) -> f64 {
+        amount as f64 / TOKEN_UNIT as f64
+    }
+
+    /// Raw units in `tokens` whole tokens, rounded to the nearest unit with halves away from
+    /// zero; negative amounts and NaN give 0 and amounts too large for a `u64` saturate
+    #[inline]
+    pub fn ui_to_tokens(tokens: f64) -> u64 {
+        let raw = (tokens * TOKEN_UNIT as f64).round();
+        if raw.is_nan() || raw <= 0.0 {
+            return 0;
+        }
+        raw as u64
+    }
+
+    /// SOL per whole token at the given virtual reserves
+    #[inline]
+    pub fn token_price_sol(virtual_sol_reserves: u64, virtual_token_reserves: u64) -> f64 {
+        (virtual_sol_reserves as f64 / LAMPORTS_PER_SOL as f64) / tokens_to_ui(virtual_token_reserves)
+    }
+}
+
+/// The 8 bytes prefixing each event the program emits in a `Program data:` log, the first
+/// bytes of `sha256("event:<EventName>")`
+pub mod discriminators {
+    pub const CREATE_EVENT: [u8; 8] = [27, 114, 169, 77, 222, 235, 99, 118];
+    pub const TRADE_EVENT: [u8; 8] = [189, 219, 127, 211, 78, 230, 97, 238];
+    pub const COMPLETE_EVENT: [u8; 8] = [95, 114, 97, 156, 212, 46, 152, 8];
+    pub const SET_PARAMS_EVENT: [u8; 8] = [223, 195, 159, 246, 62, 48, 143, 131];
+
+    /// Prefix of the instruction the program calls itself with to emit an event through CPI,
+    /// followed by the event's own discriminator
+    pub const EVENT_CPI: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+
+    /// Every known event, by its name in the program IDL
+    pub const EVENTS: [(&str, [u8; 8]); 4] = [
+        ("CreateEvent", CREATE_EVENT),
+        ("TradeEvent", TRADE_EVENT),
+        ("CompleteEvent", COMPLETE_EVENT),
+        ("SetParamsEvent", SET_PARAMS_EVENT),
+    ];
+}
+
 pub mod trade {
     pub const TRADER_TIP_AMOUNT: f64 = 0.0001;
     pub const DEFAULT_SLIPPAGE: u64 = 3000; // 30%
+    /// Compute unit limit of every operation before limits were set per operation
     pub const DEFAULT_COMPUTE_UNIT_LIMIT: u32 = 78000;
+    /// Compute units requested by a buy: creating the payer's token account and buying
+    /// typically consume 55k-65k
+    pub const DEFAULT_BUY_UNIT_LIMIT: u32 = 80_000;
+    /// Compute units requested by a sell: selling and closing the token account typically
+    /// consume 30k-40k
+    pub const DEFAULT_SELL_UNIT_LIMIT: u32 = 50_000;
+    /// Compute units requested by a create and buy, the limit tipped create-and-buys always
+    /// requested; creating the mint, its metadata and the payer's token account and buying
+    /// haven't been simulated against a lower one
+    pub const DEFAULT_CREATE_UNIT_LIMIT: u32 = 600_000;
     pub const DEFAULT_COMPUTE_UNIT_PRICE: u64 = 500000;
     pub const DEFAULT_BUY_TIP_FEE: f64 = 0.0006;
     pub const DEFAULT_SELL_TIP_FEE: f64 = 0.0001;
@@ -102,3 +184,58 @@ pub struct Symbol;
 impl Symbol {
     pub const SOLANA: &'static str = "solana";
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::tokens::*;
+    use crate::{accounts::BondingCurveAccount, pumpfun::{common::get_token_price, mint_safety::{PUMP_TOKEN_DECIMALS, PUMP_TOKEN_SUPPLY}}};
+
+    #[test]
+    fn test_token_conversions_round_trip() {
+        let mut rng = rand::rng();
+        for _ in 0..100_000 {
+            let raw = rng.random_range(0..=TOTAL_SUPPLY);
+            assert_eq!(ui_to_tokens(tokens_to_ui(raw)), raw);
+
+            let ui = rng.random_range(0.0..1_000_000_000.0);
+            assert!((tokens_to_ui(ui_to_tokens(ui)) - ui).abs() <= 0.5 / TOKEN_UNIT as f64 + ui * f64::EPSILON);
+        }
+        assert_eq!(ui_to_tokens(1.5), 1_500_000);
+        assert_eq!((ui_to_tokens(0.000_000_4), ui_to_tokens(0.000_000_6)), (0, 1));
+        assert_eq!(ui_to_tokens(-1.0), 0);
+        assert_eq!(ui_to_tokens(f64::NAN), 0);
+        assert_eq!(ui_to_tokens(f64::INFINITY), u64::MAX);
+        assert_eq!(tokens_to_ui(TOTAL_SUPPLY), 1_000_000_000.0);
+    }
+
+    #[test]
+    fn test_modules_agree_on_units() {
+        assert_eq!((PUMP_TOKEN_DECIMALS, PUMP_TOKEN_SUPPLY), (DECIMALS, TOTAL_SUPPLY));
+        assert_eq!(solana_sdk::native_token::LAMPORTS_PER_SOL, LAMPORTS_PER_SOL);
+
+        let mut rng = rand::rng();
+        for _ in 0..10_000 {
+            let (virtual_sol, virtual_tokens) = (rng.random_range(1..=1_000 * LAMPORTS_PER_SOL), rng.random_range(1..=TOTAL_SUPPLY));
+            let curve = BondingCurveAccount::new(0, virtual_tokens, virtual_sol, 0, 0, TOTAL_SUPPLY, false);
+            let price = token_price_sol(virtual_sol, virtual_tokens);
+            assert_eq!(curve.get_token_price(), price);
+            assert_eq!(get_token_price(virtual_sol, virtual_tokens), price);
+        }
+        // 30 SOL against 1.073 billion tokens, the initial curve
+        assert!((token_price_sol(30 * LAMPORTS_PER_SOL, 1_073_000_000 * TOKEN_UNIT) - 2.795899e-8).abs() < 1e-13);
+    }
+
+    #[test]
+    fn test_event_discriminators_are_anchor_hashes() {
+        for (name, discriminator) in super::discriminators::EVENTS {
+            let hash = solana_sdk::hash::hashv(&[format!("event:{}", name).as_bytes()]);
+            assert_eq!(hash.to_bytes()[..8], discriminator, "{}", name);
+        }
+        // Anchor's tag is the hash read as a big-endian u64, then written little-endian
+        let mut tag: [u8; 8] = solana_sdk::hash::hashv(&[b"anchor:event"]).to_bytes()[..8].try_into().unwrap();
+        tag.reverse();
+        assert_eq!(tag, super::discriminators::EVENT_CPI);
+    }
+}
@@ -16,13 +16,19 @@
 //! - `InsufficientFunds`: Insufficient funds for a transaction.
 //! - `SimulationError`: Transaction simulation failed.
 //! - `RateLimitExceeded`: Rate limit exceeded.
+//!
+//! `PumpFunError` decodes the errors the Pump.fun program itself returns, and `SdkError`
+//! combines both for the trading layer.
+
+use std::string::FromUtf8Error;
 
-use serde_json::Error;
 use solana_client::{
     client_error::ClientError as SolanaClientError, 
     pubsub_client::PubsubClientError
 };
 use solana_sdk::pubkey::ParsePubkeyError;
+use thiserror::Error;
+use yellowstone_grpc_client::{GeyserGrpcBuilderError, GeyserGrpcClientError};
 
 // #[derive(Debug)]
 // #[allow(dead_code)]
@@ -37,161 +43,263 @@ use solana_sdk::pubkey::ParsePubkeyError;
 //     }
 // }
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum ClientError {
     /// Bonding curve account was not found
+    #[error("Bonding curve not found")]
     BondingCurveNotFound,
     /// Error related to bonding curve operations
+    #[error("Bonding curve error: {0}")]
     BondingCurveError(&'static str),
     /// Error deserializing data using Borsh
-    BorshError(std::io::Error),
+    #[error("Borsh serialization error: {0}")]
+    BorshError(#[from] std::io::Error),
     /// Error from Solana RPC client
-    SolanaClientError(solana_client::client_error::ClientError),
+    #[error("Solana client error: {0}")]
+    SolanaClientError(#[from] SolanaClientError),
+    /// Error from the Solana PubSub client
+    #[error("PubSub client error: {0}")]
+    PubsubClientError(#[from] PubsubClientError),
     /// Error uploading metadata
-    UploadMetadataError(Box<dyn std::error::Error>),
+    #[error("Metadata upload error: {0}")]
+    UploadMetadataError(#[source] Box<dyn std::error::Error + Send + Sync>),
     /// Invalid input parameters
+    #[error("Invalid input: {0}")]
     InvalidInput(&'static str),
     /// Insufficient funds for transaction
+    #[error("Insufficient funds for transaction")]
     InsufficientFunds,
     /// Transaction simulation failed
+    #[error("Transaction simulation failed: {0}")]
     SimulationError(String),
     /// Rate limit exceeded
+    #[error("Rate limit exceeded")]
     RateLimitExceeded,
 
+    #[error("Order limit exceeded")]
     OrderLimitExceeded,
 
+    #[error("External service error: {0}")]
     ExternalService(String),
 
+    #[error("Redis error: {0}, details: {1}")]
     Redis(String, String),      
 
+    #[error("Solana error: {0}, details: {1}")]
     Solana(String, String),
     
+    #[error("Parse error: {0}, details: {1}")]
     Parse(String, String),
 
+    /// Event data ends before a field of its layout
+    #[error("Data too short for {field}: need {needed} bytes at offset {offset}, data is {len} bytes")]
+    DataTooShort { field: &'static str, offset: usize, needed: usize, len: usize },
+
+    /// A string field of event data is not valid UTF-8
+    #[error("Invalid UTF-8 in {field} at offset {offset}")]
+    InvalidString { field: &'static str, offset: usize, #[source] source: FromUtf8Error },
+
+    #[error("Base64 decode error: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("Invalid UTF-8: {0}")]
+    Utf8(#[from] FromUtf8Error),
+
+    #[error("JSON serialization error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Pubkey error: {0}, details: {1}")]
     Pubkey(String, String),
 
+    #[error("Invalid pubkey: {0}")]
+    ParsePubkey(#[from] ParsePubkeyError),
+
+    #[error("gRPC transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+
+    #[error("gRPC status: {0}")]
+    Status(#[from] tonic::Status),
+
+    #[error("gRPC client builder error: {0}")]
+    GrpcBuilder(#[from] GeyserGrpcBuilderError),
+
+    #[error("gRPC client error: {0}")]
+    GrpcClient(#[from] GeyserGrpcClientError),
+
+    #[error("Jito error: {0}, details: {1}")]
     Jito(String, String),
 
+    #[error("Task join error: {0}")]
     Join(String),
 
+    #[error("Subscribe error: {0}, details: {1}")]
     Subscribe(String, String),
 
+    #[error("Send error: {0}, details: {1}")]
     Send(String, String),
 
+    #[error("Other error: {0}")]
     Other(String),
 
+    #[error("Anyhow error: {0}")]
     Anyhow(&'static str),
 
+    #[error("Invalid data: {0}")]
     InvalidData(String),
 
+    #[error("PumpFun buy error: {0}")]
     PumpFunBuy(String),
 
+    #[error("PumpFun sell error: {0}")]
     PumpFunSell(String),
 
+    #[error("Operation timed out: {0}, details: {1}")]
     Timeout(String, String),
 
+    #[error("Duplicate event: {0}")]
     Duplicate(String),
 
+    #[error("Invalid event type")]
     InvalidEventType,
 
+    #[error("Channel closed")]
     ChannelClosed,
 }
 
-impl std::fmt::Display for ClientError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::BondingCurveNotFound => write!(f, "Bonding curve not found"),
-            Self::BondingCurveError(msg) => write!(f, "Bonding curve error: {}", msg),
-            Self::BorshError(err) => write!(f, "Borsh serialization error: {}", err),
-            Self::SolanaClientError(err) => write!(f, "Solana client error: {}", err),
-            Self::UploadMetadataError(err) => write!(f, "Metadata upload error: {}", err),
-            Self::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
-            Self::InsufficientFunds => write!(f, "Insufficient funds for transaction"),
-            Self::SimulationError(msg) => write!(f, "Transaction simulation failed: {}", msg),
-            Self::ExternalService(msg) => write!(f, "External service error: {}", msg),
-            Self::RateLimitExceeded => write!(f, "Rate limit exceeded"),
-            Self::OrderLimitExceeded => write!(f, "Order limit exceeded"),
-            Self::Anyhow(msg) => write!(f, "Anyhow error: {}", msg),
-            Self::Solana(msg, details) => write!(f, "Solana error: {}, details: {}", msg, details),
-            Self::Parse(msg, details) => write!(f, "Parse error: {}, details: {}", msg, details),
-            Self::Jito(msg, details) => write!(f, "Jito error: {}, details: {}", msg, details),
-            Self::Redis(msg, details) => write!(f, "Redis error: {}, details: {}", msg, details),
-            Self::Join(msg) => write!(f, "Task join error: {}", msg),
-            Self::Pubkey(msg, details) => write!(f, "Pubkey error: {}, details: {}", msg, details),
-            Self::Subscribe(msg, details) => write!(f, "Subscribe error: {}, details: {}", msg, details),
-            Self::Send(msg, details) => write!(f, "Send error: {}, details: {}", msg, details),
-            Self::Other(msg) => write!(f, "Other error: {}", msg),
-            Self::PumpFunBuy(msg) => write!(f, "PumpFun buy error: {}", msg),
-            Self::PumpFunSell(msg) => write!(f, "PumpFun sell error: {}", msg),
-            Self::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
-            Self::Timeout(msg, details) => write!(f, "Operation timed out: {}, details: {}", msg, details),
-            Self::Duplicate(msg) => write!(f, "Duplicate event: {}", msg),
-            Self::InvalidEventType => write!(f, "Invalid event type"),
-            Self::ChannelClosed => write!(f, "Channel closed"),
-        }
-    }
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// Typed error of the trading layer
+///
+/// Wraps the parsing and subscription layer's [`ClientError`] and the program's
+/// [`PumpFunError`], so `?` works on a [`ClientResult`] inside functions returning
+/// [`SdkResult`]. Errors without a typed variant are carried as `anyhow` errors.
+#[derive(Debug, Error)]
+pub enum SdkError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error(transparent)]
+    Program(#[from] PumpFunError),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
 }
-impl std::error::Error for ClientError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+
+impl SdkError {
+    /// The Pump.fun program error, if the program rejected the transaction
+    pub fn program_error(&self) -> Option<PumpFunError> {
         match self {
-            Self::BorshError(err) => Some(err),
-            Self::SolanaClientError(err) => Some(err),
-            Self::UploadMetadataError(err) => Some(err.as_ref()),
-            Self::ExternalService(_) => None,
-            Self::Redis(_, _) => None,
-            Self::Solana(_, _) => None,
-            Self::Parse(_, _) => None,
-            Self::Jito(_, _) => None,
-            Self::Join(_) => None,
-            Self::Pubkey(_, _) => None,
-            Self::Subscribe(_, _) => None,
-            Self::Send(_, _) => None,
-            Self::Other(_) => None,
-            Self::PumpFunBuy(_) => None,
-            Self::PumpFunSell(_) => None,
-            Self::Timeout(_, _) => None,
-            Self::Duplicate(_) => None,
-            Self::InvalidEventType => None,
-            Self::ChannelClosed => None,
-            _ => None,
+            Self::Program(error) => Some(*error),
+            Self::Other(error) => error.chain().find_map(|cause| cause.downcast_ref::<PumpFunError>().copied()),
+            Self::Client(_) => None,
         }
     }
 }
 
-impl From<SolanaClientError> for ClientError {
-    fn from(error: SolanaClientError) -> Self {
-        ClientError::Solana(
-            "Solana client error".to_string(),
-            error.to_string(),
-        )
-    }
+pub type SdkResult<T> = Result<T, SdkError>;
+
+/// Errors returned by the Pump.fun program, as declared in its IDL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PumpFunError {
+    NotAuthorized,
+    AlreadyInitialized,
+    TooMuchSolRequired,
+    TooLittleSolReceived,
+    MintDoesNotMatchBondingCurve,
+    BondingCurveComplete,
+    BondingCurveNotComplete,
+    NotInitialized,
+    WithdrawTooFrequent,
 }
 
-impl From<PubsubClientError> for ClientError {
-    fn from(error: PubsubClientError) -> Self {
-        ClientError::Solana(
-            "PubSub client error".to_string(),
-            error.to_string(),
-        )
+impl PumpFunError {
+    const ALL: [PumpFunError; 9] = [
+        Self::NotAuthorized,
+        Self::AlreadyInitialized,
+        Self::TooMuchSolRequired,
+        Self::TooLittleSolReceived,
+        Self::MintDoesNotMatchBondingCurve,
+        Self::BondingCurveComplete,
+        Self::BondingCurveNotComplete,
+        Self::NotInitialized,
+        Self::WithdrawTooFrequent,
+    ];
+
+    /// Anchor error code, starting at 6000
+    pub fn code(self) -> u32 {
+        6000 + Self::ALL.iter().position(|error| *error == self).unwrap() as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<Self> {
+        code.checked_sub(6000).and_then(|index| Self::ALL.get(index as usize).copied())
     }
-}
 
-impl From<ParsePubkeyError> for ClientError {
-    fn from(error: ParsePubkeyError) -> Self {
-        ClientError::Pubkey(
-            "Pubkey error".to_string(),
-            error.to_string(),
-        )
+    /// Finds the Pump.fun error a transaction failed with in its log messages
+    pub fn from_logs(logs: &[String]) -> Option<Self> {
+        let prefix = format!("Program {} failed: custom program error: 0x", crate::constants::accounts::PUMPFUN);
+        logs.iter()
+            .filter_map(|log| log.strip_prefix(prefix.as_str()))
+            .find_map(|code| u32::from_str_radix(code.trim(), 16).ok())
+            .and_then(Self::from_code)
     }
 }
 
-impl From<Error> for ClientError {
-    fn from(err: Error) -> Self {
-        ClientError::Parse(
-            "JSON serialization error".to_string(),
-            err.to_string()
-        )
+impl std::fmt::Display for PumpFunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            Self::NotAuthorized => "The given account is not authorized to execute this instruction",
+            Self::AlreadyInitialized => "The program is already initialized",
+            Self::TooMuchSolRequired => "slippage: Too much SOL required to buy the given amount of tokens",
+            Self::TooLittleSolReceived => "slippage: Too little SOL received to sell the given amount of tokens",
+            Self::MintDoesNotMatchBondingCurve => "The mint does not match the bonding curve",
+            Self::BondingCurveComplete => "The bonding curve has completed and liquidity migrated to raydium",
+            Self::BondingCurveNotComplete => "The bonding curve has not completed",
+            Self::NotInitialized => "The program is not initialized",
+            Self::WithdrawTooFrequent => "Withdraw too frequent",
+        };
+        write!(f, "{:?} ({}): {}", self, self.code(), message)
     }
 }
 
-pub type ClientResult<T> = Result<T, ClientError>;
+impl std::error::Error for PumpFunError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pumpfun_error_from_logs() {
+        assert_eq!(PumpFunError::TooMuchSolRequired.code(), 6002);
+        assert_eq!(PumpFunError::from_code(6005), Some(PumpFunError::BondingCurveComplete));
+        assert_eq!(PumpFunError::from_code(42), None);
+
+        let logs = vec![
+            "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P invoke [1]".to_string(),
+            "Program log: AnchorError occurred. Error Code: TooMuchSolRequired. Error Number: 6002.".to_string(),
+            "Program 6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P failed: custom program error: 0x1772".to_string(),
+        ];
+        assert_eq!(PumpFunError::from_logs(&logs), Some(PumpFunError::TooMuchSolRequired));
+
+        let other_program = vec!["Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA failed: custom program error: 0x1".to_string()];
+        assert_eq!(PumpFunError::from_logs(&other_program), None);
+    }
+
+    #[test]
+    fn test_client_error_sources_and_interop() {
+        use std::error::Error as _;
+
+        let err = ClientError::DataTooShort { field: "mint", offset: 8, needed: 32, len: 20 };
+        assert_eq!(err.to_string(), "Data too short for mint: need 32 bytes at offset 8, data is 20 bytes");
+
+        let borsh_err = <u64 as borsh::BorshDeserialize>::try_from_slice(&[1, 2]).unwrap_err();
+        let err = ClientError::from(borsh_err);
+        assert!(err.source().is_some());
+
+        let parse = || -> ClientResult<Vec<u8>> { Ok(base64::Engine::decode(&base64::engine::general_purpose::STANDARD, "not base64!")?) };
+        let err = anyhow::Error::from(parse().unwrap_err()).context("decoding event");
+        assert!(err.chain().any(|cause| cause.downcast_ref::<base64::DecodeError>().is_some()));
+
+        let sdk = || -> SdkResult<()> { Err(anyhow::Error::from(PumpFunError::TooLittleSolReceived).context("sell").into()) };
+        assert_eq!(sdk().unwrap_err().program_error(), Some(PumpFunError::TooLittleSolReceived));
+        let sdk = || -> SdkResult<Vec<u8>> { Ok(parse()?) };
+        assert!(matches!(sdk().unwrap_err(), SdkError::Client(ClientError::Base64(_))));
+    }
+}
@@ -0,0 +1,373 @@
+//! Jito bundle assembly
+//!
+//! Jito only accepts bundles of at most [`MAX_BUNDLE_TRANSACTIONS`] transactions, each within
+//! the packet size limit, sharing one recent blockhash and paying the tip in the last
+//! transaction. [`BundleBuilder`] enforces these rules and produces the transactions for
+//! [`send_bundle_with_confirmation`](super::searcher_client::send_bundle_with_confirmation).
+//! With [`TipPlacement::SeparateTx`] the tip is a transfer of its own at the end of the
+//! bundle, leaving the other transactions, possibly pre-signed, untouched.
+
+use std::sync::Arc;
+
+use solana_hash::Hash;
+use solana_sdk::{
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+    transaction::VersionedTransaction,
+};
+use thiserror::Error;
+
+use crate::pumpfun::common::{check_signers, SignerMismatch};
+
+/// Most transactions Jito accepts in one bundle
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error("bundle has no transactions")]
+    Empty,
+    #[error("bundle has {0} transactions, at most 5 are allowed")]
+    TooManyTransactions(usize),
+    #[error("transaction {index} is {size} bytes, exceeding the 1232-byte packet limit")]
+    TransactionTooLarge { index: usize, size: usize },
+    #[error("failed to compile transaction {index}: {message}")]
+    Compile { index: usize, message: String },
+    #[error("failed to sign transaction {index}: {message}")]
+    Sign { index: usize, message: String },
+    #[error("transaction {index}: {source}")]
+    Signers { index: usize, source: SignerMismatch },
+    #[error("transaction {index} is pre-signed, so the tip can't be added to it; use TipPlacement::SeparateTx")]
+    TipInSignedTransaction { index: usize },
+    #[error("transaction {index} was signed for another blockhash than the bundle's")]
+    BlockhashMismatch { index: usize },
+}
+
+/// Where a bundle pays its tip
+#[derive(Debug, Clone, Default)]
+pub enum TipPlacement {
+    /// A transfer appended to the last transaction, paid by its payer
+    #[default]
+    Inline,
+    /// A single-transfer transaction at the end of the bundle, paid and signed by `payer`; it
+    /// counts towards [`MAX_BUNDLE_TRANSACTIONS`]
+    SeparateTx { payer: Arc<Keypair> },
+}
+
+enum BundleTransaction {
+    Unsigned {
+        payer: Arc<Keypair>,
+        instructions: Vec<Instruction>,
+        signers: Vec<Arc<Keypair>>,
+    },
+    Signed(VersionedTransaction),
+}
+
+/// Builds a Jito bundle from per-transaction instruction sets
+///
+/// # Example
+///
+/// ```ignore
+/// let transactions = BundleBuilder::new()
+///     .add_transaction(creator.clone(), create_instructions, vec![mint.clone()])
+///     .add_transaction(sniper.clone(), buy_instructions, vec![])
+///     .tip(tip_account, 100_000)
+///     .build(recent_blockhash)?;
+/// jito_client.send_bundle_with_confirmation(&transactions).await?;
+/// ```
+#[derive(Default)]
+pub struct BundleBuilder {
+    transactions: Vec<BundleTransaction>,
+    tip: Option<(Pubkey, u64)>,
+    tip_placement: TipPlacement,
+}
+
+impl BundleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a transaction paid by `payer`; `signers` are any additional signers, such as
+    /// a new mint
+    pub fn add_transaction(
+        mut self,
+        payer: Arc<Keypair>,
+        instructions: Vec<Instruction>,
+        signers: Vec<Arc<Keypair>>,
+    ) -> Self {
+        self.transactions.push(BundleTransaction::Unsigned { payer, instructions, signers });
+        self
+    }
+
+    /// Appends a transaction signed elsewhere, sent as is; it must be signed for the
+    /// blockhash the bundle is built with
+    pub fn add_signed_transaction(mut self, transaction: VersionedTransaction) -> Self {
+        self.transactions.push(BundleTransaction::Signed(transaction));
+        self
+    }
+
+    /// Pays `lamports` to `tip_account` as set by [`tip_placement`](Self::tip_placement)
+    pub fn tip(mut self, tip_account: Pubkey, lamports: u64) -> Self {
+        self.tip = Some((tip_account, lamports));
+        self
+    }
+
+    /// Where the tip is paid, by default in the last transaction
+    pub fn tip_placement(mut self, tip_placement: TipPlacement) -> Self {
+        self.tip_placement = tip_placement;
+        self
+    }
+
+    /// Number of transactions in the bundle, counting a separate tip transaction
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.transactions.len() + self.separate_tip().is_some() as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Compiles and signs every transaction against `blockhash`
+    pub fn build(&self, blockhash: Hash) -> Result<Vec<VersionedTransaction>, BundleError> {
+        if self.transactions.is_empty() {
+            return Err(BundleError::Empty);
+        }
+        if self.len() > MAX_BUNDLE_TRANSACTIONS {
+            return Err(BundleError::TooManyTransactions(self.len()));
+        }
+
+        let last = self.transactions.len() - 1;
+        let inline_tip = match self.tip_placement {
+            TipPlacement::Inline => self.tip,
+            TipPlacement::SeparateTx { .. } => None,
+        };
+        let mut transactions = Vec::with_capacity(self.len());
+        for (index, entry) in self.transactions.iter().enumerate() {
+            let transaction = match entry {
+                BundleTransaction::Unsigned { payer, instructions, signers } => {
+                    let mut instructions = instructions.clone();
+                    if index == last {
+                        if let Some((tip_account, lamports)) = inline_tip {
+                            instructions.push(system_instruction::transfer(&payer.pubkey(), &tip_account, lamports));
+                        }
+                    }
+                    sign_transaction(index, payer, &instructions, signers, blockhash)?
+                }
+                BundleTransaction::Signed(transaction) => {
+                    if index == last && inline_tip.is_some() {
+                        return Err(BundleError::TipInSignedTransaction { index });
+                    }
+                    if *transaction.message.recent_blockhash() != blockhash {
+                        return Err(BundleError::BlockhashMismatch { index });
+                    }
+                    transaction.clone()
+                }
+            };
+            check_size(index, &transaction)?;
+            transactions.push(transaction);
+        }
+
+        if let Some((payer, tip_account, lamports)) = self.separate_tip() {
+            let index = transactions.len();
+            let transfer = system_instruction::transfer(&payer.pubkey(), &tip_account, lamports);
+            let transaction = sign_transaction(index, payer, &[transfer], &[], blockhash)?;
+            check_size(index, &transaction)?;
+            transactions.push(transaction);
+        }
+
+        Ok(transactions)
+    }
+
+    fn separate_tip(&self) -> Option<(&Arc<Keypair>, Pubkey, u64)> {
+        match (&self.tip_placement, self.tip) {
+            (TipPlacement::SeparateTx { payer }, Some((tip_account, lamports))) => Some((payer, tip_account, lamports)),
+            _ => None,
+        }
+    }
+}
+
+fn sign_transaction(
+    index: usize,
+    payer: &Arc<Keypair>,
+    instructions: &[Instruction],
+    signers: &[Arc<Keypair>],
+    blockhash: Hash,
+) -> Result<VersionedTransaction, BundleError> {
+    let message = v0::Message::try_compile(&payer.pubkey(), instructions, &[], blockhash)
+        .map_err(|e| BundleError::Compile { index, message: e.to_string() })?;
+    let message = VersionedMessage::V0(message);
+
+    let mut keypairs: Vec<&Keypair> = vec![payer.as_ref()];
+    for signer in signers {
+        if !keypairs.iter().any(|existing| existing.pubkey() == signer.pubkey()) {
+            keypairs.push(signer.as_ref());
+        }
+    }
+    let keys: Vec<Pubkey> = keypairs.iter().map(|keypair| keypair.pubkey()).collect();
+    check_signers(&message, &keys).map_err(|source| BundleError::Signers { index, source })?;
+
+    VersionedTransaction::try_new(message, &keypairs)
+        .map_err(|e| BundleError::Sign { index, message: e.to_string() })
+}
+
+fn check_size(index: usize, transaction: &VersionedTransaction) -> Result<(), BundleError> {
+    let size = bincode::serialized_size(transaction)
+        .map_err(|e| BundleError::Compile { index, message: e.to_string() })? as usize;
+    if size > PACKET_DATA_SIZE {
+        return Err(BundleError::TransactionTooLarge { index, size });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{instruction::AccountMeta, system_program};
+
+    use super::*;
+
+    fn memo(len: usize) -> Instruction {
+        Instruction::new_with_bytes(Pubkey::new_unique(), &vec![1; len], vec![])
+    }
+
+    fn is_transfer_to(transaction: &VersionedTransaction, instruction_index: usize, to: &Pubkey) -> bool {
+        let keys = transaction.message.static_account_keys();
+        let instruction = &transaction.message.instructions()[instruction_index];
+        keys[instruction.program_id_index as usize] == system_program::id()
+            && keys[instruction.accounts[1] as usize] == *to
+    }
+
+    #[test]
+    fn test_tip_is_last_instruction_of_last_transaction() {
+        let first = Arc::new(Keypair::new());
+        let second = Arc::new(Keypair::new());
+        let tip_account = Pubkey::new_unique();
+
+        let transactions = BundleBuilder::new()
+            .add_transaction(first.clone(), vec![memo(8)], vec![])
+            .add_transaction(second.clone(), vec![memo(8), memo(8)], vec![])
+            .tip(tip_account, 10_000)
+            .build(Hash::new_unique())
+            .unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        assert!(!transactions[0].message.static_account_keys().contains(&tip_account));
+
+        let last = &transactions[1];
+        assert_eq!(last.message.instructions().len(), 3);
+        assert!(is_transfer_to(last, 2, &tip_account));
+        assert_eq!(last.message.static_account_keys()[0], second.pubkey());
+    }
+
+    #[test]
+    fn test_transactions_share_blockhash_and_are_signed() {
+        let payer = Arc::new(Keypair::new());
+        let mint = Arc::new(Keypair::new());
+        let blockhash = Hash::new_unique();
+
+        let create = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[0],
+            vec![AccountMeta::new(mint.pubkey(), true)],
+        );
+        let transactions = BundleBuilder::new()
+            .add_transaction(payer.clone(), vec![create], vec![mint.clone(), payer.clone()])
+            .add_transaction(payer.clone(), vec![memo(8)], vec![])
+            .build(blockhash)
+            .unwrap();
+
+        for transaction in &transactions {
+            assert_eq!(*transaction.message.recent_blockhash(), blockhash);
+            assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+        }
+        assert_eq!(transactions[0].signatures.len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_invalid_bundles() {
+        let payer = Arc::new(Keypair::new());
+
+        assert!(matches!(BundleBuilder::new().build(Hash::new_unique()), Err(BundleError::Empty)));
+
+        let mut builder = BundleBuilder::new();
+        for _ in 0..=MAX_BUNDLE_TRANSACTIONS {
+            builder = builder.add_transaction(payer.clone(), vec![memo(8)], vec![]);
+        }
+        assert!(matches!(builder.build(Hash::new_unique()), Err(BundleError::TooManyTransactions(6))));
+
+        let oversized = BundleBuilder::new()
+            .add_transaction(payer, vec![memo(PACKET_DATA_SIZE)], vec![])
+            .build(Hash::new_unique());
+        assert!(matches!(oversized, Err(BundleError::TransactionTooLarge { index: 0, .. })));
+    }
+
+    #[test]
+    fn test_missing_signer_is_named() {
+        let payer = Arc::new(Keypair::new());
+        let mint = Pubkey::new_unique();
+        let create = Instruction::new_with_bytes(Pubkey::new_unique(), &[0], vec![AccountMeta::new(mint, true)]);
+
+        let result = BundleBuilder::new()
+            .add_transaction(payer.clone(), vec![memo(8)], vec![])
+            .add_transaction(payer, vec![create], vec![])
+            .build(Hash::new_unique());
+        match result {
+            Err(BundleError::Signers { index: 1, source: SignerMismatch::MissingSigners(keys) }) => assert_eq!(keys, vec![mint]),
+            other => panic!("unexpected result: {:?}", other.map(|transactions| transactions.len())),
+        }
+    }
+
+    #[test]
+    fn test_separate_tip_transaction_ends_the_bundle() {
+        let trader = Arc::new(Keypair::new());
+        let tipper = Arc::new(Keypair::new());
+        let tip_account = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+
+        let presigned = sign_transaction(0, &Arc::new(Keypair::new()), &[memo(8)], &[], blockhash).unwrap();
+        let transactions = BundleBuilder::new()
+            .add_signed_transaction(presigned.clone())
+            .add_transaction(trader.clone(), vec![memo(8)], vec![])
+            .tip(tip_account, 10_000)
+            .tip_placement(TipPlacement::SeparateTx { payer: tipper.clone() })
+            .build(blockhash)
+            .unwrap();
+
+        assert_eq!(transactions.len(), 3);
+        assert_eq!(transactions[0], presigned);
+        assert_eq!(transactions[1].message.instructions().len(), 1);
+        assert!(!transactions[1].message.static_account_keys().contains(&tip_account));
+
+        let tip = &transactions[2];
+        assert_eq!(tip.message.instructions().len(), 1);
+        assert!(is_transfer_to(tip, 0, &tip_account));
+        assert_eq!(tip.message.static_account_keys()[0], tipper.pubkey());
+        assert_eq!(*tip.message.recent_blockhash(), blockhash);
+        assert!(tip.verify_with_results().iter().all(|ok| *ok));
+    }
+
+    #[test]
+    fn test_separate_tip_counts_towards_the_limit_and_presigned_transactions_are_checked() {
+        let payer = Arc::new(Keypair::new());
+        let blockhash = Hash::new_unique();
+        let separate = TipPlacement::SeparateTx { payer: payer.clone() };
+
+        let mut builder = BundleBuilder::new().tip(Pubkey::new_unique(), 10_000).tip_placement(separate.clone());
+        for _ in 0..MAX_BUNDLE_TRANSACTIONS {
+            builder = builder.add_transaction(payer.clone(), vec![memo(8)], vec![]);
+        }
+        assert_eq!(builder.len(), MAX_BUNDLE_TRANSACTIONS + 1);
+        assert!(matches!(builder.build(blockhash), Err(BundleError::TooManyTransactions(6))));
+
+        let presigned = sign_transaction(0, &payer, &[memo(8)], &[], blockhash).unwrap();
+        let inline = BundleBuilder::new().add_signed_transaction(presigned.clone()).tip(Pubkey::new_unique(), 10_000);
+        assert!(matches!(inline.build(blockhash), Err(BundleError::TipInSignedTransaction { index: 0 })));
+        let stale = BundleBuilder::new().add_signed_transaction(presigned).tip_placement(separate);
+        assert!(matches!(stale.build(Hash::new_unique()), Err(BundleError::BlockhashMismatch { index: 0 })));
+    }
+}
@@ -3,47 +3,62 @@ use serde_json::json;
 use solana_client::rpc_client::SerializableTransaction;
 use solana_sdk::signature::Signature;
 use solana_sdk::transaction::Transaction;
-use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
+use solana_transaction_status::UiTransactionEncoding;
 use std::str::FromStr;
-use std::time::{Duration, Instant};
-use tokio::time::sleep;
+use std::time::Duration;
 use crate::common::types::SolanaRpcClient;
+use crate::trade::confirm::{confirm_signatures, ConfirmLevel};
+pub use crate::trade::confirm::LandingStatus;
 use anyhow::Result;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 use reqwest::Client;
 
+/// Waits up to 15 seconds for `txt_sig` to confirm, failing on timeout
 pub async fn poll_transaction_confirmation(rpc: &SolanaRpcClient, txt_sig: Signature) -> Result<Signature> {
-    // 15 second timeout
-    let timeout: Duration = Duration::from_secs(15);
-    // 5 second retry interval
-    let interval: Duration = Duration::from_secs(5);
-    let start: Instant = Instant::now();
-
-    loop {
-        if start.elapsed() >= timeout {
-            return Err(anyhow::anyhow!("Transaction {}'s confirmation timed out", txt_sig));
-        }
-
-        let status = rpc.get_signature_statuses(&[txt_sig]).await?;
-
-        match status.value[0].clone() {
-            Some(status) => {
-                if status.err.is_none()
-                    && (status.confirmation_status == Some(TransactionConfirmationStatus::Confirmed)
-                        || status.confirmation_status == Some(TransactionConfirmationStatus::Finalized))
-                {
-                    return Ok(txt_sig);
-                }
-                if status.err.is_some() {
-                    return Err(anyhow::anyhow!(status.err.unwrap()));
-                }
-            }
-            None => {
-                sleep(interval).await;
-            }
-        }
+    let timeout = Duration::from_secs(15);
+    let confirmation = confirm_signatures(rpc, &[txt_sig], ConfirmLevel::Confirmed, timeout).await.remove(0);
+    if !confirmation.reached(ConfirmLevel::Confirmed) {
+        confirmation.result?;
+        return Err(anyhow::anyhow!("Transaction {}'s confirmation timed out", txt_sig));
+    }
+    Ok(txt_sig)
+}
+
+/// Replaces every occurrence of `secret` in `text`, so relay responses and errors can be
+/// logged and shared
+pub fn redact(text: &str, secret: &str) -> String {
+    if secret.is_empty() {
+        return text.to_string();
     }
+    text.replace(secret, "<redacted>")
+}
+
+/// How long fee clients wait for a submitted transaction to confirm
+pub const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Waits for `signature` to reach `confirmed`
+///
+/// Fails if the transaction landed but failed, or didn't reach `confirmed` within `timeout`.
+pub async fn wait_for_landing(rpc: &SolanaRpcClient, signature: Signature, timeout: Duration) -> Result<LandingStatus> {
+    Ok(wait_for_landings(rpc, &[signature], timeout).await?.remove(0))
+}
+
+/// [`wait_for_landing`] for several signatures, polling their statuses together
+pub async fn wait_for_landings(rpc: &SolanaRpcClient, signatures: &[Signature], timeout: Duration) -> Result<Vec<LandingStatus>> {
+    let confirmations = confirm_signatures(rpc, signatures, ConfirmLevel::Confirmed, timeout).await;
+    confirmations
+        .into_iter()
+        .zip(signatures)
+        .map(|(confirmation, signature)| {
+            let reached = confirmation.reached(ConfirmLevel::Confirmed);
+            let landing = confirmation.result?;
+            if !reached {
+                return Err(anyhow::anyhow!("Transaction {}'s confirmation timed out", signature));
+            }
+            Ok(landing)
+        })
+        .collect()
 }
 
 pub async fn send_nb_transaction(client: Client, endpoint: &str, auth_token: &str, transaction: &Transaction) -> Result<Signature, anyhow::Error> {
@@ -124,4 +139,19 @@ pub async fn serialize_smart_transaction_and_encode(
         _ => return Err(anyhow::anyhow!("Unsupported encoding")),
     };
     Ok((serialized, *signature))
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_landing_fails_when_not_landed() {
+        let signature = Signature::new_unique();
+
+        let landed = wait_for_landing(&SolanaRpcClient::new_mock("succeeds".to_string()), signature, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(landed.slot, Some(1));
+
+        let missing = wait_for_landings(&SolanaRpcClient::new_mock("sig_not_found".to_string()), &[signature], Duration::from_millis(300)).await;
+        assert!(missing.unwrap_err().to_string().contains("timed out"));
+    }
+}
@@ -0,0 +1,98 @@
+//! Jito block engine client, sending transactions as bundles over gRPC
+
+use std::{sync::Arc, time::Instant};
+
+use anyhow::{anyhow, Result};
+use rand::{rng, seq::IteratorRandom};
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+
+use crate::{
+    common::SolanaRpcClient,
+    constants::accounts::JITO_TIP_ACCOUNTS,
+    trade::transport::{JitoTransport, Transport},
+};
+
+use super::{
+    bundle,
+    common::{wait_for_landing, CONFIRMATION_TIMEOUT},
+    searcher_client::{self, send_bundle_with_confirmation, SearcherConnection},
+    ClientType, FeeClientTrait, RelayReceipt, RelayStatus, SendOutcome, SubmissionOptions,
+};
+
+pub struct JitoClient {
+    pub rpc_client: Arc<SolanaRpcClient>,
+    /// Shared by the clones of the client, see [`SearcherConnection`]
+    pub searcher_client: Arc<SearcherConnection>,
+}
+
+#[async_trait::async_trait]
+impl FeeClientTrait for JitoClient {
+    async fn send_transaction_detailed(&self, transaction: &VersionedTransaction) -> Result<SendOutcome, anyhow::Error> {
+        let start = Instant::now();
+        let receipt = self.submit_transaction_detailed(transaction).await?;
+        let relay_latency = start.elapsed();
+
+        let landing = wait_for_landing(&self.rpc_client, receipt.signature, CONFIRMATION_TIMEOUT).await?;
+        Ok(SendOutcome::new(receipt, landing, relay_latency))
+    }
+
+    async fn send_transactions(&self, transactions: &Vec<VersionedTransaction>) -> Result<Vec<Signature>, anyhow::Error> {
+        self.send_bundle_with_confirmation(transactions).await
+    }
+
+    fn transport(self: Arc<Self>, _options: &SubmissionOptions) -> Arc<dyn Transport> {
+        Arc::new(JitoTransport::new(self))
+    }
+
+    async fn get_tip_account(&self) -> Result<String, anyhow::Error> {
+        if let Some(acc) = JITO_TIP_ACCOUNTS.iter().choose(&mut rng()) {
+            Ok(acc.to_string())
+        } else {
+            Err(anyhow!("no valid tip accounts found"))
+        }
+    }
+
+    async fn get_client_type(&self) -> ClientType {
+        ClientType::Jito
+    }
+}
+
+impl JitoClient {
+    pub async fn new(rpc_url: String, block_engine_url: String) -> Result<Self> {
+        let rpc_client = SolanaRpcClient::new(rpc_url);
+        let searcher_client = SearcherConnection::connect(block_engine_url.as_str()).await?;
+        Ok(Self { rpc_client: Arc::new(rpc_client), searcher_client: Arc::new(searcher_client) })
+    }
+    
+    pub async fn send_bundle_with_confirmation(
+        &self,
+        transactions: &Vec<VersionedTransaction>,
+    ) -> Result<Vec<Signature>, anyhow::Error> {
+        send_bundle_with_confirmation(self.rpc_client.clone(), &transactions, &self.searcher_client).await
+    }
+
+    /// Assembles a bundle with [`bundle::BundleBuilder`] and sends it
+    pub async fn send_bundle(
+        &self,
+        builder: &bundle::BundleBuilder,
+        blockhash: solana_hash::Hash,
+    ) -> Result<Vec<Signature>, anyhow::Error> {
+        let transactions = builder.build(blockhash)?;
+        self.send_bundle_with_confirmation(&transactions).await
+    }
+
+    /// Sends `transaction` as a single-transaction bundle without waiting for it to land; the
+    /// receipt's response is the bundle id
+    pub async fn submit_transaction_detailed(&self, transaction: &VersionedTransaction) -> Result<RelayReceipt, anyhow::Error> {
+        let (signatures, bundle_id) = searcher_client::submit_bundle(&vec![transaction.clone()], &self.searcher_client).await?;
+        let signature = signatures.first().copied().ok_or(anyhow!("Failed to send transaction"))?;
+        Ok(RelayReceipt { signature, status: Some(RelayStatus::Grpc(tonic::Code::Ok)), response: Some(bundle_id) })
+    }
+
+    pub async fn send_bundle_no_wait(
+        &self,
+        transactions: &Vec<VersionedTransaction>,
+    ) -> Result<Vec<Signature>, anyhow::Error> {
+        searcher_client::send_bundle_no_wait(&transactions, &self.searcher_client).await
+    }
+}
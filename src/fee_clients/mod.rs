@@ -0,0 +1,312 @@
+//! Transaction relays, or fee clients, that land trades faster than the RPC node
+//!
+//! Every relay implements [`FeeClientTrait`] and is constructed from a
+//! [`Cluster`](crate::common::Cluster) by [`startup::connect_fee_clients`]. Jito's block engine
+//! client depends on the `jito_protos` gRPC definitions and is only compiled with the `jito`
+//! cargo feature, which is on by default; NextBlock and 0slot are always available.
+
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+use solana_transaction_status::TransactionConfirmationStatus;
+
+use crate::trade::transport::Transport;
+use common::LandingStatus;
+
+pub mod api;
+pub mod bundle;
+pub mod common;
+#[cfg(feature = "jito")]
+pub mod jito;
+pub mod nextblock;
+#[cfg(feature = "jito")]
+#[doc(hidden)]
+pub mod searcher_client;
+pub mod startup;
+pub mod zeroslot;
+
+#[cfg(feature = "jito")]
+pub use jito::JitoClient;
+pub use nextblock::{MyInterceptor, NextBlockClient, NextBlockEndpointStats, NEXTBLOCK_SUBMIT_TIMEOUT};
+pub use zeroslot::ZeroSlotClient;
+
+/// Label of a submission client, used in submission results, latency reports and startup
+/// reports, and to list enabled clients in a [`Cluster`](crate::common::Cluster)
+///
+/// Labels parse case-insensitively; `0slot` is accepted for [`ClientType::ZeroSlot`]. Any
+/// other label names a [`ClientType::Custom`] client.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum ClientType {
+    Jito,
+    NextBlock,
+    ZeroSlot,
+    /// The RPC node's own `sendTransaction`
+    Rpc,
+    /// A client implemented outside the SDK
+    Custom(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid client label {0:?}: labels must be non-empty and contain no whitespace")]
+pub struct ParseClientTypeError(pub String);
+
+impl std::fmt::Display for ClientType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Jito => f.write_str("Jito"),
+            Self::NextBlock => f.write_str("NextBlock"),
+            Self::ZeroSlot => f.write_str("ZeroSlot"),
+            Self::Rpc => f.write_str("Rpc"),
+            Self::Custom(label) => f.write_str(label),
+        }
+    }
+}
+
+impl FromStr for ClientType {
+    type Err = ParseClientTypeError;
+
+    fn from_str(label: &str) -> Result<Self, Self::Err> {
+        if label.is_empty() || label.chars().any(char::is_whitespace) {
+            return Err(ParseClientTypeError(label.to_string()));
+        }
+        Ok(match label.to_ascii_lowercase().as_str() {
+            "jito" => Self::Jito,
+            "nextblock" => Self::NextBlock,
+            "zeroslot" | "0slot" => Self::ZeroSlot,
+            "rpc" => Self::Rpc,
+            _ => Self::Custom(label.to_string()),
+        })
+    }
+}
+
+impl From<ClientType> for String {
+    fn from(client_type: ClientType) -> Self {
+        client_type.to_string()
+    }
+}
+
+impl TryFrom<String> for ClientType {
+    type Error = ParseClientTypeError;
+
+    fn try_from(label: String) -> Result<Self, Self::Error> {
+        label.parse()
+    }
+}
+
+/// NextBlock routing flags sent with each submission
+///
+/// These change routing and pricing on NextBlock's side; the defaults match what the SDK
+/// always sent before they were configurable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NextBlockOptions {
+    pub front_running_protection: bool,
+    pub experimental_front_running_protection: bool,
+    pub snipe_transaction: bool,
+}
+
+impl Default for NextBlockOptions {
+    fn default() -> Self {
+        Self {
+            front_running_protection: true,
+            experimental_front_running_protection: true,
+            snipe_transaction: true,
+        }
+    }
+}
+
+/// Per-trade overrides of the fee clients' submission settings
+///
+/// Unset fields fall back to the settings of each client.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubmissionOptions {
+    pub nextblock: Option<NextBlockOptions>,
+}
+
+/// Status a relay answered a submission with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayStatus {
+    Http(u16),
+    Grpc(tonic::Code),
+}
+
+impl std::fmt::Display for RelayStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http(status) => write!(f, "HTTP {}", status),
+            Self::Grpc(code) => write!(f, "gRPC {:?}", code),
+        }
+    }
+}
+
+/// What a relay answered when it accepted a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayReceipt {
+    pub signature: Signature,
+    pub status: Option<RelayStatus>,
+    /// The relay's raw response, auth material redacted: the JSON body for 0slot, the
+    /// response message for NextBlock, the bundle id for Jito
+    pub response: Option<String>,
+}
+
+impl RelayReceipt {
+    /// Receipt of a transport that doesn't report a response
+    pub fn new(signature: Signature) -> Self {
+        Self { signature, status: None, response: None }
+    }
+}
+
+/// A relay refused a submission, or the request to it failed
+///
+/// Fee clients return it inside their `anyhow::Error`; see [`RelayError::find`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayError {
+    pub client: ClientType,
+    /// `None` if the relay never answered
+    pub status: Option<RelayStatus>,
+    /// The relay's raw response, auth material redacted
+    pub relay_response: Option<String>,
+    pub message: String,
+}
+
+impl RelayError {
+    pub fn new(client: ClientType, status: Option<RelayStatus>, relay_response: Option<String>, message: impl Into<String>) -> Self {
+        Self { client, status, relay_response, message: message.into() }
+    }
+
+    /// The relay error somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&RelayError> {
+        error.chain().find_map(|cause| cause.downcast_ref::<RelayError>())
+    }
+}
+
+impl std::fmt::Display for RelayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} rejected the submission", self.client)?;
+        if let Some(status) = self.status {
+            write!(f, " with {}", status)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for RelayError {}
+
+/// Result of a submission through a fee client
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendOutcome {
+    pub signature: Signature,
+    /// Slot the transaction landed in, `None` if it wasn't seen before the confirmation timeout
+    pub landed_slot: Option<u64>,
+    /// Commitment the transaction reached when confirmation stopped
+    pub commitment: Option<TransactionConfirmationStatus>,
+    /// Time from submission until the relay accepted the transaction
+    pub relay_latency_ms: u64,
+    pub relay_status: Option<RelayStatus>,
+    /// The relay's raw response to the submission, see [`RelayReceipt::response`]
+    pub relay_response: Option<String>,
+}
+
+impl SendOutcome {
+    pub(crate) fn new(receipt: RelayReceipt, landing: LandingStatus, relay_latency: Duration) -> Self {
+        Self {
+            signature: receipt.signature,
+            landed_slot: landing.slot,
+            commitment: landing.commitment,
+            relay_latency_ms: relay_latency.as_millis() as u64,
+            relay_status: receipt.status,
+            relay_response: receipt.response,
+        }
+    }
+
+    /// Whether the transaction reached at least `confirmed`
+    pub fn is_confirmed(&self) -> bool {
+        matches!(
+            self.commitment,
+            Some(TransactionConfirmationStatus::Confirmed) | Some(TransactionConfirmationStatus::Finalized)
+        )
+    }
+}
+
+pub type FeeClient = dyn FeeClientTrait + Send + Sync + 'static;
+
+#[async_trait::async_trait]
+pub trait FeeClientTrait: Send + Sync {
+    /// Sends `transaction` and waits for it to confirm, reporting where it landed
+    async fn send_transaction_detailed(&self, transaction: &VersionedTransaction) -> Result<SendOutcome>;
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature> {
+        Ok(self.send_transaction_detailed(transaction).await?.signature)
+    }
+
+    async fn send_transactions(&self, transactions: &Vec<VersionedTransaction>) -> Result<Vec<Signature>>;
+
+    /// Returns the submission pipeline transport sending through this client with `options`
+    fn transport(self: Arc<Self>, options: &SubmissionOptions) -> Arc<dyn Transport>;
+
+    /// Sends `transaction` with per-trade overrides; clients without overridable settings
+    /// ignore `options`
+    async fn send_transaction_detailed_with_options(&self, transaction: &VersionedTransaction, options: &SubmissionOptions) -> Result<SendOutcome> {
+        let _ = options;
+        self.send_transaction_detailed(transaction).await
+    }
+
+    async fn send_transaction_with_options(&self, transaction: &VersionedTransaction, options: &SubmissionOptions) -> Result<Signature> {
+        Ok(self.send_transaction_detailed_with_options(transaction, options).await?.signature)
+    }
+
+    async fn send_transactions_with_options(&self, transactions: &Vec<VersionedTransaction>, options: &SubmissionOptions) -> Result<Vec<Signature>> {
+        let _ = options;
+        self.send_transactions(transactions).await
+    }
+
+    async fn get_tip_account(&self) -> Result<String>;
+    async fn get_client_type(&self) -> ClientType;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_type_labels() {
+        assert_eq!("JITO".parse::<ClientType>().unwrap(), ClientType::Jito);
+        assert_eq!("0slot".parse::<ClientType>().unwrap(), ClientType::ZeroSlot);
+        assert_eq!("my-relay".parse::<ClientType>().unwrap(), ClientType::Custom("my-relay".to_string()));
+        assert!("two words".parse::<ClientType>().is_err());
+
+        let labels = vec![ClientType::NextBlock, ClientType::Custom("my-relay".to_string())];
+        let json = serde_json::to_string(&labels).unwrap();
+        assert_eq!(json, r#"["NextBlock","my-relay"]"#);
+        assert_eq!(serde_json::from_str::<Vec<ClientType>>(&json).unwrap(), labels);
+    }
+
+    #[test]
+    fn test_send_outcome_from_landing() {
+        let signature = Signature::new_unique();
+        let landing = LandingStatus { slot: Some(42), commitment: Some(TransactionConfirmationStatus::Confirmed) };
+        let receipt = RelayReceipt { signature, status: Some(RelayStatus::Http(200)), response: Some("{}".to_string()) };
+        let outcome = SendOutcome::new(receipt, landing, Duration::from_micros(12_500));
+        assert_eq!(outcome.landed_slot, Some(42));
+        assert_eq!(outcome.relay_latency_ms, 12);
+        assert_eq!(outcome.relay_response.as_deref(), Some("{}"));
+        assert!(outcome.is_confirmed());
+
+        let pending = SendOutcome::new(RelayReceipt::new(signature), LandingStatus::default(), Duration::ZERO);
+        assert_eq!(pending.landed_slot, None);
+        assert!(!pending.is_confirmed());
+    }
+
+    #[test]
+    fn test_relay_error_is_found_in_context() {
+        let error = RelayError::new(ClientType::ZeroSlot, Some(RelayStatus::Http(429)), Some("{\"error\":\"rate limited\"}".to_string()), "rate limited");
+        assert_eq!(error.to_string(), "ZeroSlot rejected the submission with HTTP 429: rate limited");
+
+        let wrapped = anyhow::Error::new(error.clone()).context("buy failed");
+        assert_eq!(RelayError::find(&wrapped), Some(&error));
+        assert!(RelayError::find(&anyhow::anyhow!("timed out")).is_none());
+    }
+}
@@ -0,0 +1,472 @@
+//! NextBlock client submitting over gRPC, failing over between regional endpoints
+
+use std::{
+    str::FromStr,
+    sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use rand::seq::IndexedRandom;
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+use solana_transaction_status::UiTransactionEncoding;
+use tonic::{
+    service::interceptor::InterceptedService,
+    transport::{Channel, ClientTlsConfig, Uri},
+    Status,
+};
+use yellowstone_grpc_client::Interceptor;
+
+use crate::{
+    common::{secret::{redact_api_keys, Secret}, user_agent::ClientHeaderInterceptor, SolanaRpcClient},
+    constants::accounts::NEXTBLOCK_TIP_ACCOUNTS,
+    trade::transport::{NextBlockTransport, Transport},
+};
+
+use super::{
+    api::{self, api_client::ApiClient},
+    common::{redact, serialize_smart_transaction_and_encode, wait_for_landing, wait_for_landings, CONFIRMATION_TIMEOUT},
+    ClientType, FeeClientTrait, NextBlockOptions, RelayError, RelayReceipt, RelayStatus, SendOutcome, SubmissionOptions,
+};
+
+/// Adds the auth token and the SDK's [`user_agent`](crate::common::user_agent) to requests
+#[derive(Clone)]
+pub struct MyInterceptor {
+    auth_token: Secret<String>,
+}
+
+impl MyInterceptor {
+    pub fn new(auth_token: String) -> Self {
+        Self { auth_token: auth_token.into() }
+    }
+}
+
+impl Interceptor for MyInterceptor {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        request.metadata_mut().insert(
+            "authorization", 
+            tonic::metadata::MetadataValue::from_str(self.auth_token.expose())
+                .map_err(|_| Status::invalid_argument("Invalid auth token"))?
+        );
+        ClientHeaderInterceptor.call(request)
+    }
+}
+
+type NextBlockApiClient = ApiClient<InterceptedService<Channel, MyInterceptor>>;
+
+/// How long a single NextBlock endpoint may take to accept a submission before failing over
+pub const NEXTBLOCK_SUBMIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Submission statistics of one NextBlock endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct NextBlockEndpointStats {
+    pub url: String,
+    pub successes: u64,
+    pub failures: u64,
+}
+
+impl NextBlockEndpointStats {
+    /// Share of submissions the endpoint accepted, `None` before the first submission
+    pub fn success_rate(&self) -> Option<f64> {
+        let total = self.successes + self.failures;
+        if total == 0 {
+            None
+        } else {
+            Some(self.successes as f64 / total as f64)
+        }
+    }
+}
+
+struct NextBlockEndpoint {
+    /// With any `api-key` masked; the channel holds the real one
+    url: String,
+    client: NextBlockApiClient,
+    successes: AtomicU64,
+    failures: AtomicU64,
+}
+
+impl NextBlockEndpoint {
+    fn connect_lazy(url: String, auth_token: &str) -> Result<Self> {
+        let uri = url.parse::<Uri>();
+        let url = redact_api_keys(&url);
+        let uri = uri.map_err(|e| anyhow!("Invalid NextBlock endpoint {}: {}", url, e))?;
+        let tls = ClientTlsConfig::new().with_native_roots();
+        let channel = Channel::builder(uri)
+            .tls_config(tls).map_err(|e| anyhow!("Invalid TLS config for NextBlock endpoint {}: {}", url, e))?
+            .tcp_keepalive(Some(Duration::from_secs(60)))
+            .http2_keep_alive_interval(Duration::from_secs(30))
+            .keep_alive_while_idle(true)
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(10))
+            .connect_lazy();
+
+        let client = ApiClient::with_interceptor(channel, MyInterceptor::new(auth_token.to_string()));
+        Ok(Self { url, client, successes: AtomicU64::new(0), failures: AtomicU64::new(0) })
+    }
+}
+
+/// NextBlock client submitting through one or more regional endpoints
+///
+/// Submissions go to the preferred endpoint, which is the last one that accepted a
+/// submission. When it errors or doesn't answer within [`NEXTBLOCK_SUBMIT_TIMEOUT`], the
+/// same transaction is resubmitted to the next endpoint.
+#[derive(Clone)]
+pub struct NextBlockClient {
+    pub rpc_client: Arc<SolanaRpcClient>,
+    endpoints: Arc<Vec<NextBlockEndpoint>>,
+    preferred: Arc<AtomicUsize>,
+    options: NextBlockOptions,
+    /// Kept to redact it from relay responses
+    auth_token: Arc<str>,
+}
+
+#[async_trait::async_trait]
+impl FeeClientTrait for NextBlockClient {
+    async fn send_transaction_detailed(&self, transaction: &VersionedTransaction) -> Result<SendOutcome, anyhow::Error> {
+        self.send_transaction_detailed_with_nextblock_options(transaction, None).await
+    }
+
+    async fn send_transactions(&self, transactions: &Vec<VersionedTransaction>) -> Result<Vec<Signature>, anyhow::Error> {
+        self.send_transactions(transactions).await
+    }
+
+    async fn send_transaction_detailed_with_options(&self, transaction: &VersionedTransaction, options: &SubmissionOptions) -> Result<SendOutcome, anyhow::Error> {
+        self.send_transaction_detailed_with_nextblock_options(transaction, options.nextblock).await
+    }
+
+    fn transport(self: Arc<Self>, options: &SubmissionOptions) -> Arc<dyn Transport> {
+        Arc::new(NextBlockTransport::new(self, options.nextblock))
+    }
+
+    async fn send_transactions_with_options(&self, transactions: &Vec<VersionedTransaction>, options: &SubmissionOptions) -> Result<Vec<Signature>, anyhow::Error> {
+        self.send_transactions_with_nextblock_options(transactions, options.nextblock).await
+    }
+
+    async fn get_tip_account(&self) -> Result<String> {
+        let tip_account = self.get_tip_account().await?;
+        Ok(tip_account)
+    }
+
+    async fn get_client_type(&self) -> ClientType {
+        ClientType::NextBlock
+    }
+}
+
+impl NextBlockClient {
+    pub fn new(rpc_url: String, endpoint: String, auth_token: String) -> Result<Self> {
+        Self::new_multi(rpc_url, vec![endpoint], auth_token)
+    }
+
+    /// Creates a client failing over between `endpoints`, preferring them in the given order
+    pub fn new_multi(rpc_url: String, endpoints: Vec<String>, auth_token: String) -> Result<Self> {
+        crate::common::tls::ensure_crypto_provider()?;
+
+        if endpoints.is_empty() {
+            return Err(anyhow!("At least one NextBlock endpoint is required"));
+        }
+
+        let endpoints = endpoints
+            .into_iter()
+            .map(|url| NextBlockEndpoint::connect_lazy(url, &auth_token))
+            .collect::<Result<Vec<_>>>()?;
+
+        let rpc_client = SolanaRpcClient::new(rpc_url);
+        Ok(Self {
+            rpc_client: Arc::new(rpc_client),
+            endpoints: Arc::new(endpoints),
+            preferred: Arc::new(AtomicUsize::new(0)),
+            options: NextBlockOptions::default(),
+            auth_token: auth_token.into(),
+        })
+    }
+
+    /// Sets the flags used when a submission doesn't override them
+    pub fn with_options(mut self, options: NextBlockOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[inline]
+    pub fn options(&self) -> NextBlockOptions {
+        self.options
+    }
+
+    /// Returns the endpoint the next submission goes to first, with any `api-key` masked
+    pub fn preferred_endpoint(&self) -> &str {
+        &self.endpoints[self.preferred.load(Ordering::Relaxed)].url
+    }
+
+    /// Returns per-endpoint submission statistics, in configuration order
+    pub fn endpoint_stats(&self) -> Vec<NextBlockEndpointStats> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| NextBlockEndpointStats {
+                url: endpoint.url.clone(),
+                successes: endpoint.successes.load(Ordering::Relaxed),
+                failures: endpoint.failures.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    /// Submits through the endpoints in turn; fails with the [`RelayError`] of the last one
+    async fn submit_with_failover<T, F, Fut>(&self, submit: F) -> Result<T>
+    where
+        F: Fn(NextBlockApiClient) -> Fut,
+        Fut: std::future::Future<Output = Result<T, Status>>,
+    {
+        let count = self.endpoints.len();
+        let first = self.preferred.load(Ordering::Relaxed);
+        let mut last_error = RelayError::new(ClientType::NextBlock, None, None, "No NextBlock endpoints configured");
+
+        for offset in 0..count {
+            let index = (first + offset) % count;
+            let endpoint = &self.endpoints[index];
+            match tokio::time::timeout(NEXTBLOCK_SUBMIT_TIMEOUT, submit(endpoint.client.clone())).await {
+                Ok(Ok(response)) => {
+                    endpoint.successes.fetch_add(1, Ordering::Relaxed);
+                    self.preferred.store(index, Ordering::Relaxed);
+                    return Ok(response);
+                }
+                Ok(Err(status)) => {
+                    endpoint.failures.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(endpoint = %endpoint.url, %status, "NextBlock endpoint failed, trying the next one");
+                    last_error = self.status_error(&endpoint.url, &status);
+                }
+                Err(_) => {
+                    endpoint.failures.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(endpoint = %endpoint.url, timeout = ?NEXTBLOCK_SUBMIT_TIMEOUT, "NextBlock endpoint timed out, trying the next one");
+                    let message = format!("endpoint {} timed out after {:?}", endpoint.url, NEXTBLOCK_SUBMIT_TIMEOUT);
+                    last_error = RelayError::new(ClientType::NextBlock, None, None, message);
+                }
+            }
+        }
+
+        Err(last_error.into())
+    }
+
+    fn status_error(&self, url: &str, status: &Status) -> RelayError {
+        let message = redact_api_keys(&redact(status.message(), &self.auth_token));
+        RelayError::new(
+            ClientType::NextBlock,
+            Some(RelayStatus::Grpc(status.code())),
+            Some(message.clone()),
+            format!("endpoint {} failed: {}", url, message),
+        )
+    }
+
+    /// The response message as text, with the auth token redacted
+    fn response_text<T: std::fmt::Debug>(&self, response: &T) -> String {
+        redact(&format!("{:?}", response), &self.auth_token)
+    }
+
+    fn receipt<T: std::fmt::Debug>(&self, signature: Signature, response: &T) -> RelayReceipt {
+        RelayReceipt { signature, status: Some(RelayStatus::Grpc(tonic::Code::Ok)), response: Some(self.response_text(response)) }
+    }
+
+    /// A batch is rejected when any of its transactions wasn't submitted
+    fn batch_error(&self, response: &api::PostSubmitBatchResponse) -> Option<RelayError> {
+        let rejected = response.transactions.iter().find(|entry| !entry.submitted)?;
+        let message = format!("transaction {} not submitted: {}", rejected.signature, redact(&rejected.error, &self.auth_token));
+        Some(RelayError::new(ClientType::NextBlock, Some(RelayStatus::Grpc(tonic::Code::Ok)), Some(self.response_text(response)), message))
+    }
+
+    pub async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+        Ok(self.send_transaction_detailed_with_nextblock_options(transaction, None).await?.signature)
+    }
+
+    /// Sends `transaction`, overriding the client's flags with `options` when given
+    pub async fn send_transaction_with_nextblock_options(&self, transaction: &VersionedTransaction, options: Option<NextBlockOptions>) -> Result<Signature, anyhow::Error> {
+        Ok(self.send_transaction_detailed_with_nextblock_options(transaction, options).await?.signature)
+    }
+
+    /// Sends `transaction` and reports where it landed, overriding the client's flags with
+    /// `options` when given
+    pub async fn send_transaction_detailed_with_nextblock_options(&self, transaction: &VersionedTransaction, options: Option<NextBlockOptions>) -> Result<SendOutcome, anyhow::Error> {
+        let start = Instant::now();
+        let receipt = self.submit_transaction_detailed(transaction, options).await?;
+        let relay_latency = start.elapsed();
+
+        let landing = wait_for_landing(&self.rpc_client, receipt.signature, CONFIRMATION_TIMEOUT).await?;
+        Ok(SendOutcome::new(receipt, landing, relay_latency))
+    }
+
+    /// Hands `transaction` to NextBlock without waiting for confirmation
+    pub async fn submit_transaction(&self, transaction: &VersionedTransaction, options: Option<NextBlockOptions>) -> Result<Signature, anyhow::Error> {
+        Ok(self.submit_transaction_detailed(transaction, options).await?.signature)
+    }
+
+    /// [`NextBlockClient::submit_transaction`], keeping NextBlock's response
+    ///
+    /// Rejections fail with a [`RelayError`] carrying the gRPC status and message.
+    pub async fn submit_transaction_detailed(&self, transaction: &VersionedTransaction, options: Option<NextBlockOptions>) -> Result<RelayReceipt, anyhow::Error> {
+        let (content, signature) = serialize_smart_transaction_and_encode(transaction, UiTransactionEncoding::Base64).await?;
+        let request = submit_request(content, options.unwrap_or(self.options));
+        let response = self.submit_with_failover(|mut client| {
+            let request = request.clone();
+            async move { client.post_submit_v2(request).await }
+        }).await?;
+
+        Ok(self.receipt(signature, response.get_ref()))
+    }
+
+    pub async fn send_transactions(&self, transactions: &Vec<VersionedTransaction>) -> Result<Vec<Signature>, anyhow::Error> {
+        self.send_transactions_with_nextblock_options(transactions, None).await
+    }
+
+    /// Sends `transactions` as a batch, overriding the client's flags with `options` when
+    /// given; batches only carry `front_running_protection`
+    pub async fn send_transactions_with_nextblock_options(&self, transactions: &Vec<VersionedTransaction>, options: Option<NextBlockOptions>) -> Result<Vec<Signature>, anyhow::Error> {
+        let options = options.unwrap_or(self.options);
+        let mut entries = Vec::new();
+        let encoding = UiTransactionEncoding::Base64;
+        
+        let mut signatures = Vec::new();
+        for transaction in transactions {
+            let (content, signature) = serialize_smart_transaction_and_encode(transaction, encoding).await?;
+            entries.push(api::PostSubmitRequestEntry {
+                transaction: Some(api::TransactionMessage {
+                    content,
+                    is_cleanup: false,
+                }),
+                skip_pre_flight: true,
+            });
+            signatures.push(signature);
+        }
+
+        let request = api::PostSubmitBatchRequest {
+            entries,
+            submit_strategy: api::SubmitStrategy::PSubmitAll as i32,
+            use_bundle: Some(true),
+            front_running_protection: Some(options.front_running_protection),
+        };
+        let response = self.submit_with_failover(|mut client| {
+            let request = request.clone();
+            async move { client.post_submit_batch_v2(request).await }
+        }).await?;
+        if let Some(error) = self.batch_error(response.get_ref()) {
+            return Err(error.into());
+        }
+
+        wait_for_landings(&self.rpc_client, &signatures, CONFIRMATION_TIMEOUT).await?;
+
+        Ok(signatures)
+    }
+
+    async fn get_tip_account(&self) -> Result<String> {
+        let tip_account = *NEXTBLOCK_TIP_ACCOUNTS.choose(&mut rand::rng()).or_else(|| NEXTBLOCK_TIP_ACCOUNTS.first()).unwrap();
+        Ok(tip_account.to_string())
+    }
+}
+
+fn submit_request(content: String, options: NextBlockOptions) -> api::PostSubmitRequest {
+    api::PostSubmitRequest {
+        transaction: Some(api::TransactionMessage {
+            content,
+            is_cleanup: false,
+        }),
+        skip_pre_flight: true,
+        front_running_protection: Some(options.front_running_protection),
+        experimental_front_running_protection: Some(options.experimental_front_running_protection),
+        snipe_transaction: Some(options.snipe_transaction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_nextblock_new_multi() {
+        let client = NextBlockClient::new_multi(
+            "http://127.0.0.1:8899".to_string(),
+            vec!["https://fra.nextblock.io".to_string(), "https://ny.nextblock.io".to_string()],
+            "token".to_string(),
+        ).unwrap();
+
+        assert_eq!(client.preferred_endpoint(), "https://fra.nextblock.io");
+        let stats = client.endpoint_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].url, "https://ny.nextblock.io");
+        assert_eq!(stats[0].success_rate(), None);
+
+        assert_eq!(client.options(), NextBlockOptions::default());
+        let options = NextBlockOptions { front_running_protection: false, ..Default::default() };
+        assert_eq!(client.with_options(options).options(), options);
+    }
+
+    #[tokio::test]
+    async fn test_failover_moves_to_the_next_endpoint() {
+        let client = NextBlockClient::new_multi(
+            "http://127.0.0.1:8899".to_string(),
+            vec!["https://fra.nextblock.io".to_string(), "https://ny.nextblock.io".to_string()],
+            "token".to_string(),
+        ).unwrap();
+
+        let calls = AtomicUsize::new(0);
+        let response = client.submit_with_failover(|_| {
+            let call = calls.fetch_add(1, Ordering::Relaxed);
+            async move {
+                if call == 0 {
+                    Err(Status::unavailable("down"))
+                } else {
+                    Ok(call)
+                }
+            }
+        }).await.unwrap();
+
+        assert_eq!(response, 1);
+        assert_eq!(client.preferred_endpoint(), "https://ny.nextblock.io");
+        let stats = client.endpoint_stats();
+        assert_eq!((stats[0].successes, stats[0].failures), (0, 1));
+        assert_eq!((stats[1].successes, stats[1].failures), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_rejections_carry_status_and_redacted_message() {
+        let client = NextBlockClient::new("http://127.0.0.1:8899".to_string(), "https://fra.nextblock.io".to_string(), "token-123".to_string()).unwrap();
+
+        let error = client.status_error("https://fra.nextblock.io", &Status::permission_denied("bad token token-123"));
+        assert_eq!(error.status, Some(RelayStatus::Grpc(tonic::Code::PermissionDenied)));
+        assert_eq!(error.relay_response.as_deref(), Some("bad token <redacted>"));
+
+        let response = api::PostSubmitBatchResponse {
+            transactions: vec![
+                api::PostSubmitBatchResponseEntry { signature: "a".to_string(), error: String::new(), submitted: true },
+                api::PostSubmitBatchResponseEntry { signature: "b".to_string(), error: "tip too low".to_string(), submitted: false },
+            ],
+        };
+        let error = client.batch_error(&response).unwrap();
+        assert!(error.message.contains("b not submitted: tip too low"), "{}", error);
+        assert!(error.relay_response.unwrap().contains("tip too low"));
+        assert!(client.batch_error(&api::PostSubmitBatchResponse { transactions: vec![] }).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_nextblock_rejects_invalid_endpoints() {
+        let rpc_url = "http://127.0.0.1:8899".to_string();
+
+        assert!(NextBlockClient::new_multi(rpc_url.clone(), vec![], "token".to_string()).is_err());
+
+        let err = NextBlockClient::new(rpc_url.clone(), "not a uri".to_string(), "token".to_string()).err().unwrap();
+        assert!(err.to_string().contains("not a uri"), "{}", err);
+
+        let err = NextBlockClient::new(rpc_url, "not a uri/?api-key=abc123".to_string(), "token".to_string()).err().unwrap();
+        assert!(!err.to_string().contains("abc123"), "{}", err);
+    }
+
+    #[test]
+    fn test_endpoint_success_rate() {
+        let stats = NextBlockEndpointStats { url: "https://fra.nextblock.io".to_string(), successes: 3, failures: 1 };
+        assert_eq!(stats.success_rate(), Some(0.75));
+    }
+
+    #[test]
+    fn test_submit_request_carries_options() {
+        let options = NextBlockOptions { snipe_transaction: true, ..Default::default() };
+        let request = submit_request("tx".to_string(), options);
+        assert_eq!(request.transaction.unwrap().content, "tx");
+        assert!(request.skip_pre_flight);
+        assert_eq!(request.front_running_protection, Some(options.front_running_protection));
+        assert_eq!(request.snipe_transaction, Some(true));
+    }
+}
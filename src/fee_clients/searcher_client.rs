@@ -0,0 +1,360 @@
+use std::{
+    error::Error as _,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use jito_protos::{
+    bundle::{
+        Bundle, BundleResult,
+    },
+    convert::proto_packet_from_versioned_tx,
+    searcher::{
+        searcher_service_client::SearcherServiceClient, SendBundleRequest, SendBundleResponse, SubscribeBundleResultsRequest,
+    },
+};
+use solana_sdk::{
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tonic::{
+    service::interceptor::InterceptedService, transport::{self, Channel, Endpoint}, Code, Status
+};
+use yellowstone_grpc_client::ClientTlsConfig;
+
+use crate::fee_clients::{common::{wait_for_landings, CONFIRMATION_TIMEOUT}, ClientType, RelayError, RelayStatus};
+use crate::common::{user_agent::{user_agent, ClientHeaderInterceptor}, SolanaRpcClient};
+
+/// Searcher client tagging every request with the SDK's user agent
+pub type SearcherClient = SearcherServiceClient<InterceptedService<Channel, ClientHeaderInterceptor>>;
+
+/// Wait before the first attempt to rebuild a dead connection; doubled after each failure
+pub const RECONNECT_BACKOFF_INITIAL: Duration = Duration::from_millis(250);
+/// Longest wait between attempts to rebuild a dead connection
+pub const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum BlockEngineConnectionError {
+    #[error("transport error {0}")]
+    TransportError(#[from] transport::Error),
+    #[error("client error {0}")]
+    ClientError(#[from] Status),
+}
+
+#[derive(Debug, Error)]
+pub enum BundleRejectionError {
+    #[error("bundle lost state auction, auction: {0}, tip {1} lamports")]
+    StateAuctionBidRejected(String, u64),
+    #[error("bundle won state auction but failed global auction, auction {0}, tip {1} lamports")]
+    WinningBatchBidRejected(String, u64),
+    #[error("bundle simulation failure on tx {0}, message: {1:?}")]
+    SimulationFailure(String, Option<String>),
+    #[error("internal error {0}")]
+    InternalError(String),
+}
+
+pub type BlockEngineConnectionResult<T> = Result<T, BlockEngineConnectionError>;
+
+pub async fn get_searcher_client_no_auth(
+    block_engine_url: &str,
+) -> BlockEngineConnectionResult<SearcherClient> {
+    let searcher_channel = create_grpc_channel(block_engine_url).await?;
+    let searcher_client = SearcherServiceClient::with_interceptor(searcher_channel, ClientHeaderInterceptor);
+    Ok(searcher_client)
+}
+
+pub async fn create_grpc_channel(url: &str) -> BlockEngineConnectionResult<Channel> {
+    let mut endpoint = Endpoint::from_shared(url.to_string()).expect("invalid url");
+    if url.starts_with("https") {
+        endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+    }
+
+    endpoint = endpoint.tcp_nodelay(true);
+    endpoint = endpoint.tcp_keepalive(Some(Duration::from_secs(10)));
+    endpoint = endpoint.connect_timeout(Duration::from_secs(20));
+    endpoint = endpoint.http2_keep_alive_interval(Duration::from_secs(10));
+    endpoint = endpoint.user_agent(user_agent())?;
+
+    Ok(endpoint.connect().await?)
+}
+
+/// Whether `status` comes from the connection rather than from the block engine
+fn is_connection_error(status: &Status) -> bool {
+    status.code() == Code::Unavailable || status.source().is_some_and(|source| source.is::<transport::Error>())
+}
+
+struct Backoff {
+    delay: Duration,
+    not_before: Option<Instant>,
+}
+
+/// Searcher client shared by a `JitoClient` and the `PumpFun` clones holding it, rebuilt when
+/// its connection dies
+///
+/// Every request clones the client, which only clones a handle to the shared channel, so
+/// bundles are sent concurrently. A bundle failing on the connection rebuilds the channel,
+/// at most once per backoff delay, and is sent once more on the new one.
+pub struct SearcherConnection {
+    /// `None` for a client that is never rebuilt
+    block_engine_url: Option<String>,
+    /// The client and how many times it was rebuilt
+    client: RwLock<(u64, SearcherClient)>,
+    backoff: Mutex<Backoff>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    reconnects: AtomicU64,
+}
+
+impl SearcherConnection {
+    pub async fn connect(block_engine_url: &str) -> BlockEngineConnectionResult<Self> {
+        let client = get_searcher_client_no_auth(block_engine_url).await?;
+        Ok(Self { block_engine_url: Some(block_engine_url.to_string()), ..Self::fixed(client) })
+    }
+
+    /// Wraps `client`, which is kept when its connection dies
+    pub fn fixed(client: SearcherClient) -> Self {
+        Self {
+            block_engine_url: None,
+            client: RwLock::new((0, client)),
+            backoff: Mutex::new(Backoff { delay: RECONNECT_BACKOFF_INITIAL, not_before: None }),
+            initial_backoff: RECONNECT_BACKOFF_INITIAL,
+            max_backoff: RECONNECT_BACKOFF_MAX,
+            reconnects: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits `initial` before the first attempt to rebuild a dead connection, doubling up to
+    /// `max` after each failed attempt
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = Mutex::new(Backoff { delay: initial, not_before: None });
+        (self.initial_backoff, self.max_backoff) = (initial, max);
+        self
+    }
+
+    pub fn client(&self) -> SearcherClient {
+        self.client.read().unwrap().1.clone()
+    }
+
+    /// Times the connection was rebuilt
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+
+    /// Sends `request`, rebuilding the connection and sending once more if it failed on the
+    /// connection
+    pub async fn send_bundle(&self, request: SendBundleRequest) -> Result<SendBundleResponse, Status> {
+        let (generation, mut client) = self.client.read().unwrap().clone();
+        match client.send_bundle(request.clone()).await {
+            Err(status) if is_connection_error(&status) => {
+                if !self.reconnect(generation).await {
+                    return Err(status);
+                }
+                Ok(self.client().send_bundle(request).await?.into_inner())
+            }
+            result => Ok(result?.into_inner()),
+        }
+    }
+
+    /// Replaces the client of `generation` with a new connection, unless another request did
+    /// already; false if no new client is in place
+    async fn reconnect(&self, generation: u64) -> bool {
+        let Some(block_engine_url) = &self.block_engine_url else {
+            return false;
+        };
+        let mut backoff = self.backoff.lock().await;
+        if self.client.read().unwrap().0 != generation {
+            return true;
+        }
+        if backoff.not_before.is_some_and(|not_before| Instant::now() < not_before) {
+            return false;
+        }
+        match get_searcher_client_no_auth(block_engine_url).await {
+            Ok(client) => {
+                let mut current = self.client.write().unwrap();
+                *current = (current.0 + 1, client);
+                *backoff = Backoff { delay: self.initial_backoff, not_before: None };
+                self.reconnects.fetch_add(1, Ordering::Relaxed);
+                tracing::info!(block_engine_url, "reconnected to the block engine");
+                true
+            }
+            Err(e) => {
+                tracing::warn!(block_engine_url, error = %e, retry_in = ?backoff.delay, "failed to reconnect to the block engine");
+                backoff.not_before = Some(Instant::now() + backoff.delay);
+                backoff.delay = (backoff.delay * 2).min(self.max_backoff);
+                false
+            }
+        }
+    }
+}
+
+pub async fn subscribe_bundle_results(
+    searcher_client: &SearcherConnection,
+    request: impl tonic::IntoRequest<SubscribeBundleResultsRequest>,
+) -> std::result::Result<
+    tonic::Response<tonic::codec::Streaming<BundleResult>>,
+    tonic::Status,
+> {
+    searcher_client.client().subscribe_bundle_results(request).await
+}
+
+pub async fn send_bundle_with_confirmation(
+    rpc: Arc<SolanaRpcClient>,
+    transactions: &Vec<VersionedTransaction>,
+    searcher_client: &SearcherConnection,
+) -> Result<Vec<Signature>, anyhow::Error> {
+    let signatures = send_bundle_no_wait(transactions, searcher_client).await?;
+
+    wait_for_landings(&rpc, &signatures, CONFIRMATION_TIMEOUT).await?;
+
+    Ok(signatures)
+}
+
+pub async fn send_bundle_no_wait(
+    transactions: &Vec<VersionedTransaction>,
+    searcher_client: &SearcherConnection,
+) -> Result<Vec<Signature>, anyhow::Error> {
+    Ok(submit_bundle(transactions, searcher_client).await?.0)
+}
+
+/// Sends a bundle without waiting for it to land, returning its signatures and the block
+/// engine's bundle id
+///
+/// Rejections fail with a [`RelayError`] carrying the gRPC status and message.
+pub async fn submit_bundle(
+    transactions: &Vec<VersionedTransaction>,
+    searcher_client: &SearcherConnection,
+) -> Result<(Vec<Signature>, String), anyhow::Error> {
+    let mut packets = vec![];
+    let mut signatures = vec![];
+    for transaction in transactions {
+        let packet = proto_packet_from_versioned_tx(transaction);
+        packets.push(packet);
+        signatures.push(transaction.signatures[0]);
+    }
+
+    let response = searcher_client
+        .send_bundle(SendBundleRequest {
+            bundle: Some(Bundle {
+                header: None,
+                packets,
+            }),
+        })
+        .await
+        .map_err(|status| {
+            let message = status.message().to_string();
+            RelayError::new(ClientType::Jito, Some(RelayStatus::Grpc(status.code())), Some(message.clone()), message)
+        })?;
+
+    Ok((signatures, response.uuid))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        net::SocketAddr,
+        task::{Context, Poll},
+    };
+
+    use tokio::{sync::oneshot, task::JoinHandle};
+    use tonic::{
+        body::BoxBody,
+        codec::ProstCodec,
+        codegen::{http, BoxFuture, Service},
+        server::{Grpc, NamedService, UnaryService},
+        transport::Server,
+    };
+
+    use super::*;
+
+    /// Block engine accepting every bundle
+    #[derive(Clone)]
+    struct MockBlockEngine;
+
+    impl NamedService for MockBlockEngine {
+        const NAME: &'static str = "searcher.SearcherService";
+    }
+
+    struct SendBundle;
+
+    impl UnaryService<SendBundleRequest> for SendBundle {
+        type Response = SendBundleResponse;
+        type Future = BoxFuture<tonic::Response<SendBundleResponse>, Status>;
+
+        fn call(&mut self, _request: tonic::Request<SendBundleRequest>) -> Self::Future {
+            Box::pin(async { Ok(tonic::Response::new(SendBundleResponse { uuid: "bundle".to_string() })) })
+        }
+    }
+
+    impl Service<http::Request<BoxBody>> for MockBlockEngine {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<BoxBody>) -> Self::Future {
+            Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::default());
+                Ok(grpc.unary(SendBundle, request).await)
+            })
+        }
+    }
+
+    /// Serves the mock block engine on `addr` until the returned sender fires
+    fn serve(addr: SocketAddr) -> (oneshot::Sender<()>, JoinHandle<()>) {
+        let (stop, stopped) = oneshot::channel::<()>();
+        let server = tokio::spawn(async move {
+            let shutdown = async {
+                let _ = stopped.await;
+            };
+            Server::builder().add_service(MockBlockEngine).serve_with_shutdown(addr, shutdown).await.unwrap();
+        });
+        (stop, server)
+    }
+
+    async fn send(connection: &SearcherConnection) -> Result<String, Status> {
+        Ok(connection.send_bundle(SendBundleRequest::default()).await?.uuid)
+    }
+
+    #[tokio::test]
+    async fn test_recovers_when_the_block_engine_comes_back() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let url = format!("http://{}", addr);
+        let (stop, server) = serve(addr);
+        let connection = loop {
+            match SearcherConnection::connect(&url).await {
+                Ok(connection) => break connection.with_backoff(Duration::from_millis(10), Duration::from_millis(50)),
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let sends = futures::future::join_all((0..8).map(|_| send(&connection))).await;
+        assert!(sends.iter().all(|sent| matches!(sent.as_deref(), Ok("bundle"))), "{sends:?}");
+
+        stop.send(()).unwrap();
+        server.await.unwrap();
+        let status = send(&connection).await.unwrap_err();
+        assert!(is_connection_error(&status), "{status:?}");
+
+        let (stop, server) = serve(addr);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(send(&connection).await.unwrap(), "bundle");
+
+        stop.send(()).unwrap();
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_only_connection_failures_trigger_a_reconnect() {
+        assert!(is_connection_error(&Status::unavailable("connection refused")));
+        assert!(!is_connection_error(&Status::invalid_argument("bundle contains an expired blockhash")));
+        assert!(!is_connection_error(&Status::resource_exhausted("rate limited")));
+    }
+}
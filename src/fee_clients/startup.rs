@@ -0,0 +1,157 @@
+//! Concurrent construction of the fee clients enabled in a [`Cluster`]
+//!
+//! Every enabled relay is constructed at the same time under its own connect timeout. A relay
+//! that fails or times out is left out and reported in its [`FeeClientStartup`], so one slow
+//! block engine neither delays nor prevents the others.
+
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+
+use crate::common::Cluster;
+
+use super::{ClientType, FeeClient, NextBlockClient, ZeroSlotClient};
+
+/// Time each fee client gets to connect before it is left out
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How starting one fee client went
+#[derive(Debug, Clone)]
+pub struct FeeClientStartup {
+    pub client_type: ClientType,
+    pub elapsed: Duration,
+    /// Why the client was left out, `None` if it started
+    pub error: Option<String>,
+}
+
+impl FeeClientStartup {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Constructs the fee clients enabled in `cluster` concurrently
+///
+/// Returns the clients that started, in the order Jito, ZeroSlot, NextBlock, and one report
+/// per enabled client. Custom labels in `Cluster::clients` are reported as not started.
+pub async fn connect_fee_clients(cluster: &Cluster, connect_timeout: Duration) -> (Vec<Arc<FeeClient>>, Vec<FeeClientStartup>) {
+    let jito = connect_jito(cluster);
+    let zeroslot = async {
+        let client = ZeroSlotClient::new(cluster.read_endpoint().to_string(), cluster.zeroslot_url.clone(), cluster.zeroslot_auth_token.expose().clone());
+        Ok::<_, anyhow::Error>(Arc::new(client) as Arc<FeeClient>)
+    };
+    let nextblock = async {
+        let client = NextBlockClient::new_multi(cluster.read_endpoint().to_string(), cluster.nextblock_urls(), cluster.nextblock_auth_token.expose().clone())?
+            .with_options(cluster.nextblock_options);
+        Ok::<_, anyhow::Error>(Arc::new(client) as Arc<FeeClient>)
+    };
+
+    let (jito, zeroslot, nextblock) = tokio::join!(
+        start(ClientType::Jito, cluster.is_enabled(&ClientType::Jito), connect_timeout, jito),
+        start(ClientType::ZeroSlot, cluster.is_enabled(&ClientType::ZeroSlot), connect_timeout, zeroslot),
+        start(ClientType::NextBlock, cluster.is_enabled(&ClientType::NextBlock), connect_timeout, nextblock),
+    );
+
+    let mut fee_clients = vec![];
+    let mut startup = vec![];
+    for (client, report) in [jito, zeroslot, nextblock].into_iter().flatten() {
+        fee_clients.extend(client);
+        startup.push(report);
+    }
+    startup.extend(cluster.clients.iter().filter_map(unbuilt));
+    (fee_clients, startup)
+}
+
+#[cfg(feature = "jito")]
+async fn connect_jito(cluster: &Cluster) -> Result<Arc<FeeClient>, anyhow::Error> {
+    let client = super::JitoClient::new(cluster.read_endpoint().to_string(), cluster.block_engine_url.clone()).await?;
+    Ok(Arc::new(client))
+}
+
+#[cfg(not(feature = "jito"))]
+async fn connect_jito(_cluster: &Cluster) -> Result<Arc<FeeClient>, anyhow::Error> {
+    Err(anyhow!("the SDK was built without the `jito` feature"))
+}
+
+/// Report for a listed client the SDK can't construct itself
+fn unbuilt(client_type: &ClientType) -> Option<FeeClientStartup> {
+    let error = match client_type {
+        ClientType::Jito | ClientType::NextBlock | ClientType::ZeroSlot => return None,
+        ClientType::Rpc => "the RPC node is not a fee client".to_string(),
+        ClientType::Custom(_) => "custom clients must be added to `PumpFun::fee_clients`".to_string(),
+    };
+    tracing::warn!(client = %client_type, error = %error, "fee client not started");
+    Some(FeeClientStartup { client_type: client_type.clone(), elapsed: Duration::ZERO, error: Some(error) })
+}
+
+/// Runs `connect` under `connect_timeout` if the client is enabled
+async fn start<T, F>(client_type: ClientType, enabled: bool, connect_timeout: Duration, connect: F) -> Option<(Option<T>, FeeClientStartup)>
+where
+    F: Future<Output = Result<T, anyhow::Error>>,
+{
+    if !enabled {
+        return None;
+    }
+
+    let started_at = Instant::now();
+    let result = match tokio::time::timeout(connect_timeout, connect).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!("timed out after {:?}", connect_timeout)),
+    };
+    let elapsed = started_at.elapsed();
+
+    match result {
+        Ok(client) => {
+            tracing::info!(client = %client_type, ?elapsed, "fee client started");
+            Some((Some(client), FeeClientStartup { client_type, elapsed, error: None }))
+        }
+        Err(e) => {
+            tracing::warn!(client = %client_type, ?elapsed, error = %e, "fee client failed to start");
+            Some((None, FeeClientStartup { client_type, elapsed, error: Some(format!("{:#}", e)) }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slow_and_failing_clients_do_not_block_others() {
+        let timeout = Duration::from_millis(50);
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok::<_, anyhow::Error>(1u8)
+        };
+        let failing = async { Err::<u8, _>(anyhow!("connection refused")) };
+        let ready = async { Ok::<_, anyhow::Error>(3u8) };
+
+        let started_at = Instant::now();
+        let (slow, failing, ready, disabled) = tokio::join!(
+            start(ClientType::Jito, true, timeout, slow),
+            start(ClientType::ZeroSlot, true, timeout, failing),
+            start(ClientType::NextBlock, true, timeout, ready),
+            start(ClientType::Jito, false, timeout, async { Ok::<_, anyhow::Error>(4u8) }),
+        );
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+
+        let (client, report) = slow.unwrap();
+        assert!(client.is_none());
+        assert!(report.error.unwrap().contains("timed out"));
+
+        let (client, report) = failing.unwrap();
+        assert!(client.is_none());
+        assert_eq!(report.error.as_deref(), Some("connection refused"));
+
+        let (client, report) = ready.unwrap();
+        assert_eq!(client, Some(3));
+        assert!(report.is_ok());
+
+        assert!(disabled.is_none());
+    }
+}
@@ -0,0 +1,208 @@
+//! 0slot client submitting over its HTTP `sendTransaction` endpoint
+
+use std::{sync::Arc, time::Instant};
+
+use anyhow::Result;
+use rand::seq::IndexedRandom;
+use serde_json::json;
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::{
+    common::{secret::{redact_api_keys, Secret}, user_agent::http_client, SolanaRpcClient},
+    constants::accounts::{NEXTBLOCK_TIP_ACCOUNTS, ZEROSLOT_TIP_ACCOUNTS},
+    trade::transport::{Transport, ZeroSlotTransport},
+};
+
+use super::{
+    common::{redact, serialize_smart_transaction_and_encode, wait_for_landing, CONFIRMATION_TIMEOUT},
+    ClientType, FeeClientTrait, RelayError, RelayReceipt, RelayStatus, SendOutcome, SubmissionOptions,
+};
+
+#[derive(Clone)]
+pub struct ZeroSlotClient {
+    pub endpoint: String,
+    pub auth_token: Secret<String>,
+    pub rpc_client: Arc<SolanaRpcClient>,
+}
+
+#[async_trait::async_trait]
+impl FeeClientTrait for ZeroSlotClient {
+    async fn send_transaction_detailed(&self, transaction: &VersionedTransaction) -> Result<SendOutcome, anyhow::Error> {
+        self.send_transaction_detailed(transaction).await
+    }
+
+    async fn send_transactions(&self, transactions: &Vec<VersionedTransaction>) -> Result<Vec<Signature>, anyhow::Error> {
+        self.send_transactions(transactions).await
+    }
+
+    fn transport(self: Arc<Self>, _options: &SubmissionOptions) -> Arc<dyn Transport> {
+        Arc::new(ZeroSlotTransport::new(self))
+    }
+
+    async fn get_tip_account(&self) -> Result<String> {
+        let tip_account = self.get_tip_account().await?;
+        Ok(tip_account)
+    }
+
+    async fn get_client_type(&self) -> ClientType {
+        ClientType::ZeroSlot
+    }
+}
+
+impl ZeroSlotClient {
+    pub fn new(rpc_url: String, endpoint: String, auth_token: String) -> Self {
+        let rpc_client = SolanaRpcClient::new(rpc_url);
+        Self { rpc_client: Arc::new(rpc_client), endpoint, auth_token: auth_token.into() }
+    }
+
+    pub async fn send_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+        Ok(self.send_transaction_detailed(transaction).await?.signature)
+    }
+
+    /// Sends `transaction` and reports where it landed
+    pub async fn send_transaction_detailed(&self, transaction: &VersionedTransaction) -> Result<SendOutcome, anyhow::Error> {
+        let start = Instant::now();
+        let receipt = self.submit_transaction_detailed(transaction).await?;
+        let relay_latency = start.elapsed();
+
+        let landing = wait_for_landing(&self.rpc_client, receipt.signature, CONFIRMATION_TIMEOUT).await?;
+        Ok(SendOutcome::new(receipt, landing, relay_latency))
+    }
+
+    /// Hands `transaction` to 0slot without waiting for confirmation
+    pub async fn submit_transaction(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+        Ok(self.submit_transaction_detailed(transaction).await?.signature)
+    }
+
+    /// [`ZeroSlotClient::submit_transaction`], keeping 0slot's HTTP status and response body
+    ///
+    /// Rejections fail with a [`RelayError`] carrying the same; the API key is redacted.
+    pub async fn submit_transaction_detailed(&self, transaction: &VersionedTransaction) -> Result<RelayReceipt, anyhow::Error> {
+        let (content, signature) = serialize_smart_transaction_and_encode(transaction, UiTransactionEncoding::Base64).await?;
+        let relay_error = |status: Option<RelayStatus>, response: Option<String>, message: String| {
+            RelayError::new(ClientType::ZeroSlot, status, response, redact_api_keys(&redact(&message, self.auth_token.expose())))
+        };
+
+        let client = http_client();
+        let response = client.post(format!("{}/?api-key={}", self.endpoint, self.auth_token.expose()))
+            .json(&send_transaction_body(&content))
+            .send()
+            .await
+            .map_err(|e| relay_error(None, None, e.without_url().to_string()))?;
+
+        let status = RelayStatus::Http(response.status().as_u16());
+        let accepted = response.status().is_success();
+        let body = response.text().await.map_err(|e| relay_error(Some(status), None, e.without_url().to_string()))?;
+        let body = redact(&body, self.auth_token.expose());
+        Ok(receipt_from_response(signature, status, accepted, body)?)
+    }
+
+    pub async fn send_transactions(&self, transactions: &Vec<VersionedTransaction>) -> Result<Vec<Signature>, anyhow::Error> {
+        let mut signatures = Vec::new();
+        for transaction in transactions {
+            let signature = self.send_transaction(transaction).await?;
+            signatures.push(signature);
+        }
+        Ok(signatures)
+    }
+
+    async fn get_tip_account(&self) -> Result<String> {
+        let tip_account = *ZEROSLOT_TIP_ACCOUNTS.choose(&mut rand::rng()).or_else(|| NEXTBLOCK_TIP_ACCOUNTS.first()).unwrap();
+        Ok(tip_account.to_string())
+    }
+}
+
+/// Reads a `sendTransaction` response; JSON-RPC errors come with HTTP 200
+fn receipt_from_response(signature: Signature, status: RelayStatus, accepted: bool, body: String) -> Result<RelayReceipt, RelayError> {
+    let rejected = |message: String, body: String| RelayError::new(ClientType::ZeroSlot, Some(status), Some(body), message);
+    if !accepted {
+        return Err(rejected(format!("transaction {} was not accepted", signature), body));
+    }
+    let json: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(json) => json,
+        Err(e) => return Err(rejected(format!("invalid response: {}", e), body)),
+    };
+    if let Some(error) = json.get("error") {
+        return Err(rejected(format!("transaction {} rejected: {}", signature, error), body));
+    }
+    Ok(RelayReceipt { signature, status: Some(status), response: Some(body) })
+}
+
+fn send_transaction_body(content: &str) -> serde_json::Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "sendTransaction",
+        "params": [
+            content,
+            {
+                "encoding": "base64",
+                "skipPreflight": true,
+            }
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signer::Signer;
+
+    use super::*;
+
+    #[test]
+    fn test_send_transaction_body() {
+        let body = send_transaction_body("tx");
+        assert_eq!(body["method"], "sendTransaction");
+        assert_eq!(body["params"][0], "tx");
+        assert_eq!(body["params"][1]["skipPreflight"], true);
+    }
+
+    /// Serves `status` and `body` to every request on a local port
+    async fn mock_relay(status: u16, body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let status = axum::http::StatusCode::from_u16(status).unwrap();
+        let router = axum::Router::new().fallback(move || async move { (status, body) });
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        endpoint
+    }
+
+    fn transaction() -> VersionedTransaction {
+        let payer = solana_sdk::signature::Keypair::new();
+        let transfer = solana_sdk::system_instruction::transfer(&payer.pubkey(), &payer.pubkey(), 1);
+        solana_sdk::transaction::Transaction::new_signed_with_payer(&[transfer], Some(&payer.pubkey()), &[&payer], solana_hash::Hash::new_unique()).into()
+    }
+
+    #[tokio::test]
+    async fn test_relay_responses_are_kept_and_redacted() {
+        let transaction = transaction();
+        let accepting = mock_relay(200, r#"{"jsonrpc":"2.0","id":1,"result":"sig"}"#).await;
+        let client = ZeroSlotClient::new("http://127.0.0.1:1".to_string(), accepting, "secret-key".to_string());
+        let receipt = client.submit_transaction_detailed(&transaction).await.unwrap();
+        assert_eq!(receipt.signature, transaction.signatures[0]);
+        assert_eq!(receipt.status, Some(RelayStatus::Http(200)));
+        assert!(receipt.response.unwrap().contains("\"result\""));
+
+        let rejecting = mock_relay(200, r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32002,"message":"blockhash not found"}}"#).await;
+        let client = ZeroSlotClient::new("http://127.0.0.1:1".to_string(), rejecting, "secret-key".to_string());
+        let err = client.submit_transaction_detailed(&transaction).await.unwrap_err();
+        let relay_error = RelayError::find(&err).unwrap();
+        assert_eq!(relay_error.status, Some(RelayStatus::Http(200)));
+        assert!(relay_error.relay_response.as_deref().unwrap().contains("blockhash not found"));
+
+        let unauthorized = mock_relay(401, "invalid api key secret-key").await;
+        let client = ZeroSlotClient::new("http://127.0.0.1:1".to_string(), unauthorized, "secret-key".to_string());
+        let err = client.submit_transaction_detailed(&transaction).await.unwrap_err();
+        let relay_error = RelayError::find(&err).unwrap();
+        assert_eq!(relay_error.status, Some(RelayStatus::Http(401)));
+        assert_eq!(relay_error.relay_response.as_deref(), Some("invalid api key <redacted>"));
+
+        let unreachable = ZeroSlotClient::new("http://127.0.0.1:1".to_string(), "http://127.0.0.1:1".to_string(), "secret-key".to_string());
+        let err = unreachable.submit_transaction_detailed(&transaction).await.unwrap_err();
+        let relay_error = RelayError::find(&err).unwrap();
+        assert_eq!(relay_error.status, None);
+        assert!(!err.to_string().contains("secret-key"), "{}", err);
+        assert_eq!(format!("{} {:?}", unreachable.auth_token, unreachable.auth_token), "*** ***");
+    }
+}
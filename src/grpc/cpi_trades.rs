@@ -0,0 +1,321 @@
+//! Pump.fun trades made by other programs through CPI
+//!
+//! Aggregators and bots call the Pump.fun program from their own, and the `Program data:` log
+//! of such a trade interleaves with the outer program's logs or is dropped once the logs are
+//! truncated. With the full transaction at hand, the gRPC subscription also reads trades from
+//! the inner instructions: each buy or sell calling Pump.fun is matched with the `TradeEvent`
+//! the program emits through CPI right after it, which carries the fill. A trade whose event
+//! is missing is built from the instruction alone, with its bound (`max_sol_cost` or
+//! `min_sol_output`) as the SOL amount and no reserves. Creates made through CPI are not
+//! reported.
+
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{option_serializer::OptionSerializer, EncodedTransactionWithStatusMeta, UiInstruction};
+
+use crate::{
+    common::{
+        logs_data::{DexInstruction, PumpfunEventPayload, TradeInfo},
+        logs_events::try_decode_event,
+        logs_filters::LogFilter,
+    },
+    constants::{accounts::PUMPFUN, discriminators::EVENT_CPI},
+    instruction::decode::{decode_instruction, PumpInstruction},
+};
+
+/// Positions of the mint and the trader among the accounts of a buy or sell
+const MINT_ACCOUNT: usize = 2;
+const USER_ACCOUNT: usize = 6;
+
+/// Trades Pump.fun made for other programs in `transaction`, marked `via_cpi` with the
+/// top-level program that called it; slot and signature are left to the caller
+pub fn cpi_trades(transaction: &EncodedTransactionWithStatusMeta) -> Vec<TradeInfo> {
+    let Some(meta) = transaction.meta.as_ref().filter(|meta| meta.err.is_none()) else {
+        return vec![];
+    };
+    let OptionSerializer::Some(groups) = &meta.inner_instructions else {
+        return vec![];
+    };
+    let Some(decoded) = transaction.transaction.decode() else {
+        return vec![];
+    };
+    let mut keys = decoded.message.static_account_keys().to_vec();
+    if let OptionSerializer::Some(loaded) = &meta.loaded_addresses {
+        keys.extend(loaded.writable.iter().chain(&loaded.readonly).filter_map(|key| key.parse::<Pubkey>().ok()));
+    }
+
+    let mut trades = Vec::new();
+    for group in groups {
+        let outer = decoded.message.instructions().get(group.index as usize).and_then(|instruction| keys.get(instruction.program_id_index as usize));
+        let Some(&outer) = outer.filter(|outer| **outer != PUMPFUN) else {
+            continue;
+        };
+        // trades of this instruction, and whether their event was seen
+        let mut group_trades: Vec<(TradeInfo, bool)> = Vec::new();
+        for instruction in &group.instructions {
+            let UiInstruction::Compiled(instruction) = instruction else {
+                continue;
+            };
+            if keys.get(instruction.program_id_index as usize) != Some(&PUMPFUN) {
+                continue;
+            }
+            let Ok(data) = bs58::decode(&instruction.data).into_vec() else {
+                continue;
+            };
+            if let Some(event) = data.strip_prefix(&EVENT_CPI) {
+                if let Ok(PumpfunEventPayload::Trade(fill)) = try_decode_event(event) {
+                    match group_trades.iter_mut().find(|(trade, filled)| !filled && same_order(trade, &fill)) {
+                        Some((trade, filled)) => (*trade, *filled) = (fill, true),
+                        None => group_trades.push((fill, true)),
+                    }
+                }
+            } else if let Some(trade) = decode_instruction(&data).ok().and_then(|decoded| instruction_trade(decoded, &instruction.accounts, &keys)) {
+                group_trades.push((trade, false));
+            }
+        }
+        trades.extend(group_trades.into_iter().map(|(trade, _)| TradeInfo { via_cpi: true, cpi_program: Some(outer), ..trade }));
+    }
+    trades
+}
+
+/// The trade a buy or sell instruction asks for, bounded by its limit
+fn instruction_trade(instruction: PumpInstruction, accounts: &[u8], keys: &[Pubkey]) -> Option<TradeInfo> {
+    let (is_buy, token_amount, sol_amount) = match instruction {
+        PumpInstruction::Buy { amount, max_sol_cost } => (true, amount, max_sol_cost),
+        PumpInstruction::Sell { amount, min_sol_output } => (false, amount, min_sol_output),
+        PumpInstruction::Create { .. } => return None,
+    };
+    let account = |position: usize| accounts.get(position).and_then(|index| keys.get(*index as usize)).copied();
+    Some(TradeInfo { mint: account(MINT_ACCOUNT)?, user: account(USER_ACCOUNT)?, is_buy, token_amount, sol_amount, ..Default::default() })
+}
+
+/// Whether `a` and `b` are the same order, ignoring the SOL amount only the fill knows
+fn same_order(a: &TradeInfo, b: &TradeInfo) -> bool {
+    a.mint == b.mint && a.user == b.user && a.is_buy == b.is_buy && a.token_amount == b.token_amount
+}
+
+/// Takes the trade of `cpi` that the logs reported as `logged`, marking `logged` as made
+/// through CPI; whatever stays in `cpi` the logs missed
+pub fn claim(cpi: &mut Vec<TradeInfo>, logged: &mut TradeInfo) {
+    if let Some(position) = cpi.iter().position(|trade| same_order(trade, logged)) {
+        let trade = cpi.remove(position);
+        logged.via_cpi = true;
+        logged.cpi_program = trade.cpi_program;
+    }
+}
+
+/// How many trades of a set of transactions the logs and the inner instructions report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpiCoverage {
+    /// Trades parsed from the logs
+    pub logged: usize,
+    /// Trades found among the inner instructions
+    pub via_cpi: usize,
+    /// Trades found among the inner instructions but not in the logs
+    pub missed: usize,
+}
+
+impl CpiCoverage {
+    /// Counts the trades of successful `transactions`, such as those of a block
+    pub fn measure<'a>(transactions: impl IntoIterator<Item = &'a EncodedTransactionWithStatusMeta>) -> Self {
+        let mut coverage = Self::default();
+        for transaction in transactions {
+            let Some(meta) = transaction.meta.as_ref().filter(|meta| meta.err.is_none()) else {
+                continue;
+            };
+            let mut cpi = cpi_trades(transaction);
+            coverage.via_cpi += cpi.len();
+            let logs = match &meta.log_messages {
+                OptionSerializer::Some(logs) => logs.as_slice(),
+                _ => &[],
+            };
+            for instruction in LogFilter::parse_instruction(logs, None).unwrap_or_default() {
+                if let DexInstruction::UserTrade(mut trade) | DexInstruction::BotTrade(mut trade) = instruction {
+                    coverage.logged += 1;
+                    claim(&mut cpi, &mut trade);
+                }
+            }
+            coverage.missed += cpi.len();
+        }
+        coverage
+    }
+
+    /// Share of all trades that only the inner instructions report
+    pub fn missed_fraction(&self) -> f64 {
+        match self.logged + self.missed {
+            0 => 0.0,
+            total => self.missed as f64 / total as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+    use borsh::BorshSerialize;
+    use serde_json::{json, Value};
+    use solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        message::Message,
+        transaction::{Transaction, VersionedTransaction},
+    };
+
+    use super::*;
+    use crate::{
+        constants::{accounts::TOKEN_PROGRAM, discriminators::TRADE_EVENT},
+        instruction::{buy, sell, Buy, Sell},
+    };
+
+    /// A trade in a synthetic transaction; `emits_event` adds the program's event CPI
+    struct Order {
+        fill: TradeInfo,
+        bound: u64,
+        emits_event: bool,
+    }
+
+    fn order(is_buy: bool, sol_amount: u64, emits_event: bool) -> Order {
+        let fill = TradeInfo { mint: Pubkey::new_unique(), user: Pubkey::new_unique(), is_buy, token_amount: 1_000_000, sol_amount, timestamp: 1_700_000_000, ..Default::default() };
+        let bound = if is_buy { sol_amount * 2 } else { sol_amount / 2 };
+        Order { fill, bound, emits_event }
+    }
+
+    fn event(fill: &TradeInfo) -> Vec<u8> {
+        let mut data = TRADE_EVENT.to_vec();
+        fill.serialize(&mut data).unwrap();
+        data
+    }
+
+    fn pump_instruction(order: &Order) -> Instruction {
+        let fill = &order.fill;
+        if fill.is_buy {
+            buy(&fill.user, &fill.mint, &Pubkey::new_unique(), &TOKEN_PROGRAM, Buy::new(fill.token_amount, order.bound).unwrap())
+        } else {
+            sell(&fill.user, &fill.mint, &Pubkey::new_unique(), &TOKEN_PROGRAM, Sell::new(fill.token_amount, order.bound).unwrap())
+        }
+    }
+
+    /// A transaction as `getBlock` returns it in base64, trading `order` directly or through
+    /// `outer`, with its trade logged unless `truncated`
+    fn transaction(order: &Order, outer: Option<Pubkey>, truncated: bool) -> EncodedTransactionWithStatusMeta {
+        let pump = pump_instruction(order);
+        let top_level = match outer {
+            Some(outer) => {
+                let mut accounts = pump.accounts.clone();
+                accounts.push(AccountMeta::new_readonly(PUMPFUN, false));
+                Instruction::new_with_bytes(outer, &[1], accounts)
+            }
+            None => pump.clone(),
+        };
+        let message = Message::new(&[top_level], Some(&order.fill.user));
+        let index = |key: &Pubkey| message.account_keys.iter().position(|account| account == key).unwrap();
+        let compiled = |data: &[u8], accounts: &[Pubkey], stack_height: u32| {
+            json!({
+                "programIdIndex": index(&PUMPFUN),
+                "accounts": accounts.iter().map(index).collect::<Vec<_>>(),
+                "data": bs58::encode(data).into_string(),
+                "stackHeight": stack_height,
+            })
+        };
+
+        let depth = if outer.is_some() { 2 } else { 1 };
+        let mut inner = Vec::new();
+        if outer.is_some() {
+            let accounts: Vec<Pubkey> = pump.accounts.iter().map(|account| account.pubkey).collect();
+            inner.push(compiled(&pump.data, &accounts, depth));
+        }
+        if order.emits_event {
+            let mut data = EVENT_CPI.to_vec();
+            data.extend(event(&order.fill));
+            inner.push(compiled(&data, &[crate::pumpfun::idl::event_authority()], depth + 1));
+        }
+        let mut logs = vec![
+            format!("Program {} invoke [{}]", PUMPFUN, depth),
+            format!("Program log: Instruction: {}", if order.fill.is_buy { "Buy" } else { "Sell" }),
+            format!("Program data: {}", BASE64.encode(event(&order.fill))),
+            format!("Program {} success", PUMPFUN),
+        ];
+        if truncated {
+            logs.truncate(1);
+            logs.push("Log truncated".to_string());
+        }
+
+        let transaction = VersionedTransaction::from(Transaction::new_unsigned(message));
+        let captured: Value = json!({
+            "transaction": [BASE64.encode(bincode::serialize(&transaction).unwrap()), "base64"],
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5_000,
+                "preBalances": [],
+                "postBalances": [],
+                "innerInstructions": if inner.is_empty() { json!([]) } else { json!([{ "index": 0, "instructions": inner }]) },
+                "logMessages": logs,
+                "preTokenBalances": [],
+                "postTokenBalances": [],
+                "rewards": [],
+                "loadedAddresses": { "writable": [], "readonly": [] },
+                "returnData": null,
+                "computeUnitsConsumed": 0,
+            },
+            "version": "legacy",
+        });
+        serde_json::from_value(captured).unwrap()
+    }
+
+    #[test]
+    fn test_cpi_trades_take_the_fill_from_the_event() {
+        let aggregator = Pubkey::new_unique();
+        let filled = order(true, 40_000_000, true);
+        let trades = cpi_trades(&transaction(&filled, Some(aggregator), true));
+        assert_eq!(trades, vec![TradeInfo { via_cpi: true, cpi_program: Some(aggregator), ..filled.fill.clone() }]);
+
+        let unfilled = order(false, 40_000_000, false);
+        let trades = cpi_trades(&transaction(&unfilled, Some(aggregator), true));
+        assert_eq!(trades.len(), 1);
+        let fill = &unfilled.fill;
+        assert_eq!((trades[0].mint, trades[0].user, trades[0].is_buy, trades[0].token_amount), (fill.mint, fill.user, false, fill.token_amount));
+        assert_eq!((trades[0].sol_amount, trades[0].virtual_sol_reserves), (20_000_000, 0), "the instruction's bound");
+
+        assert!(cpi_trades(&transaction(&filled, None, false)).is_empty(), "top-level trades are not CPI");
+    }
+
+    #[test]
+    fn test_coverage_of_a_block() {
+        let aggregator = Pubkey::new_unique();
+        let orders = [order(true, 10_000_000, true), order(true, 20_000_000, true), order(true, 30_000_000, true), order(false, 40_000_000, false)];
+        let block = [
+            transaction(&orders[0], None, false),
+            transaction(&orders[1], Some(aggregator), false),
+            transaction(&orders[2], Some(aggregator), true),
+            transaction(&orders[3], Some(aggregator), true),
+        ];
+
+        let coverage = CpiCoverage::measure(&block);
+        assert_eq!(coverage, CpiCoverage { logged: 2, via_cpi: 3, missed: 2 });
+        assert_eq!(coverage.missed_fraction(), 0.5);
+        assert_eq!(CpiCoverage::default().missed_fraction(), 0.0);
+    }
+
+    /// Measures a real block:
+    /// `PUMPFUN_BLOCK_RPC=<url> PUMPFUN_BLOCK_SLOT=<slot> cargo test -- --ignored --nocapture test_coverage_of_a_captured_block`
+    #[tokio::test]
+    #[ignore]
+    async fn test_coverage_of_a_captured_block() {
+        use solana_client::rpc_config::RpcBlockConfig;
+        use solana_sdk::commitment_config::CommitmentConfig;
+        use solana_transaction_status::{TransactionDetails, UiTransactionEncoding};
+
+        let rpc = crate::common::SolanaRpcClient::new(std::env::var("PUMPFUN_BLOCK_RPC").unwrap());
+        let slot = std::env::var("PUMPFUN_BLOCK_SLOT").unwrap().parse().unwrap();
+        let config = RpcBlockConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            transaction_details: Some(TransactionDetails::Full),
+            rewards: Some(false),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        let block = rpc.get_block_with_config(slot, config).await.unwrap();
+        let coverage = CpiCoverage::measure(block.transactions.iter().flatten());
+        println!("slot {}: {:?}, {:.1}% of the trades were missed by the logs", slot, coverage, coverage.missed_fraction() * 100.0);
+        assert!(coverage.missed <= coverage.via_cpi);
+    }
+}
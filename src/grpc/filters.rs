@@ -0,0 +1,129 @@
+//! Named transaction filters for a single Geyser subscription
+//!
+//! A subscription may carry several transaction filters, each under its own name; Geyser
+//! reports which names a transaction matched, and `subscribe_pumpfun_filtered` passes those
+//! names on with every event so consumers can route them.
+
+use std::collections::HashMap;
+
+use solana_sdk::pubkey::Pubkey;
+use yellowstone_grpc_proto::geyser::SubscribeRequestFilterTransactions;
+
+use super::PUMP_PROGRAM_ID;
+
+pub type TransactionsFilterMap = HashMap<String, SubscribeRequestFilterTransactions>;
+
+/// A transaction filter; successful non-vote transactions unless changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionFilter {
+    filter: SubscribeRequestFilterTransactions,
+}
+
+impl TransactionFilter {
+    pub fn new() -> Self {
+        Self {
+            filter: SubscribeRequestFilterTransactions {
+                vote: Some(false),
+                failed: Some(false),
+                signature: None,
+                account_include: vec![],
+                account_exclude: vec![],
+                account_required: vec![],
+            },
+        }
+    }
+
+    /// Transactions invoking the Pump.fun program
+    pub fn pumpfun() -> Self {
+        Self::new().account_include([PUMP_PROGRAM_ID])
+    }
+
+    /// `Some(true)` for votes only, `None` for both
+    pub fn vote(mut self, vote: Option<bool>) -> Self {
+        self.filter.vote = vote;
+        self
+    }
+
+    /// `Some(true)` for failed transactions only, `None` for both
+    pub fn failed(mut self, failed: Option<bool>) -> Self {
+        self.filter.failed = failed;
+        self
+    }
+
+    pub fn signature(mut self, signature: impl ToString) -> Self {
+        self.filter.signature = Some(signature.to_string());
+        self
+    }
+
+    /// Transactions touching any of `accounts`
+    pub fn account_include(mut self, accounts: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.filter.account_include.extend(accounts.into_iter().map(|account| account.to_string()));
+        self
+    }
+
+    /// Transactions touching none of `accounts`
+    pub fn account_exclude(mut self, accounts: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.filter.account_exclude.extend(accounts.into_iter().map(|account| account.to_string()));
+        self
+    }
+
+    /// Transactions touching all of `accounts`
+    pub fn account_required(mut self, accounts: impl IntoIterator<Item = Pubkey>) -> Self {
+        self.filter.account_required.extend(accounts.into_iter().map(|account| account.to_string()));
+        self
+    }
+}
+
+impl Default for TransactionFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<TransactionFilter> for SubscribeRequestFilterTransactions {
+    fn from(filter: TransactionFilter) -> Self {
+        filter.filter
+    }
+}
+
+/// Accumulates named transaction filters
+#[derive(Debug, Clone, Default)]
+pub struct FilterBuilder {
+    filters: TransactionsFilterMap,
+}
+
+impl FilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `filter` under `name`, replacing any filter of that name
+    pub fn filter(mut self, name: impl Into<String>, filter: impl Into<SubscribeRequestFilterTransactions>) -> Self {
+        self.filters.insert(name.into(), filter.into());
+        self
+    }
+
+    pub fn build(self) -> TransactionsFilterMap {
+        self.filters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_keeps_filters_by_name() {
+        let wallet = Pubkey::new_unique();
+        let filters = FilterBuilder::new()
+            .filter("mine", TransactionFilter::pumpfun().account_required([wallet]))
+            .filter("debug", TransactionFilter::pumpfun().failed(None))
+            .build();
+
+        assert_eq!(filters.len(), 2);
+        assert_eq!(filters["mine"].account_required, vec![wallet.to_string()]);
+        assert_eq!(filters["mine"].failed, Some(false));
+        assert_eq!(filters["debug"].failed, None);
+        assert_eq!(filters["debug"].account_include, vec![PUMP_PROGRAM_ID.to_string()]);
+    }
+}
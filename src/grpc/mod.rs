@@ -1,13 +1,10 @@
-use std::{collections::HashMap, fmt, time::Duration};
+use std::{collections::{HashMap, HashSet}, fmt, sync::Arc, time::{Duration, Instant, SystemTime}};
 
 use futures::{channel::mpsc, sink::Sink, Stream, StreamExt, SinkExt};
-use rustls::crypto::{ring::default_provider, CryptoProvider};
-use tonic::codec::CompressionEncoding;
 use tonic::{transport::channel::ClientTlsConfig, Status};
 use yellowstone_grpc_client::{GeyserGrpcClient, GeyserGrpcClientResult};
-use yellowstone_grpc_proto::geyser::SubscribeUpdateSlot;
 use yellowstone_grpc_proto::geyser::{
-    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterTransactions, SubscribeUpdate,
+    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterTransactions, SubscribeUpdate,
     SubscribeUpdateTransaction, subscribe_update::UpdateOneof, SubscribeRequestPing,
 };
 use log::{error, info};
@@ -17,17 +14,36 @@ use solana_transaction_status::{
     option_serializer::OptionSerializer, EncodedTransactionWithStatusMeta, UiTransactionEncoding,
 };
 
+
+use crate::accounts::BondingCurveAccount;
+use crate::common::cache::PersistentLru;
+use crate::common::commitment::EventCommitment;
+use crate::common::event_latency::{EventLatency, EventSource};
+use crate::common::heartbeat::{Heartbeat, StreamHealth, RECONNECT_DELAY};
 use crate::common::logs_data::DexInstruction;
 use crate::common::logs_events::PumpfunEvent;
 use crate::common::logs_filters::LogFilter;
+use crate::common::wallet_activity::wallet_trades;
+use crate::common::watermark::Watermarks;
+use crate::common::TradeTag;
+use crate::common::user_agent::user_agent;
+use crate::pumpfun::token_age;
 use crate::error::{ClientError, ClientResult};
+use crate::pumpfun::{common::get_bonding_curve_pda, curve_cache::{BondingCurveCache, CurveSource, StreamGuard}};
+use crate::trade::deadline::slot_tracker;
+
+pub mod cpi_trades;
+pub mod filters;
+pub mod options;
 
-type TransactionsFilterMap = HashMap<String, SubscribeRequestFilterTransactions>;
+pub use filters::{FilterBuilder, TransactionFilter, TransactionsFilterMap};
+pub use options::GrpcChannelOptions;
 
 const PUMP_PROGRAM_ID: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
 const CONNECT_TIMEOUT: u64 = 10;
 const REQUEST_TIMEOUT: u64 = 60;
 const CHANNEL_SIZE: usize = 1000;
+const PING_ID: i32 = 1;
 
 #[derive(Clone)]
 pub struct TransactionPretty {
@@ -35,9 +51,19 @@ pub struct TransactionPretty {
     pub signature: Signature,
     pub is_vote: bool,
     pub tx: EncodedTransactionWithStatusMeta,
+    /// Names of the subscription filters the transaction matched
+    pub filters: Vec<String>,
     // pub transaction: Option<Transaction>,
 }
 
+/// An event with the names of the subscription filters its transaction matched
+#[derive(Debug)]
+pub struct FilteredEvent {
+    /// Empty for errors and staleness reports, which belong to no transaction
+    pub filters: Vec<String>,
+    pub event: PumpfunEvent,
+}
+
 impl fmt::Debug for TransactionPretty {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         struct TxWrap<'a>(&'a EncodedTransactionWithStatusMeta);
@@ -52,36 +78,127 @@ impl fmt::Debug for TransactionPretty {
             .field("slot", &self.slot)
             .field("signature", &self.signature)
             .field("is_vote", &self.is_vote)
+            .field("filters", &self.filters)
             .field("tx", &TxWrap(&self.tx))
             .finish()
     }
 }
 
-impl From<SubscribeUpdateTransaction> for TransactionPretty {
-    fn from(SubscribeUpdateTransaction { transaction, slot }: SubscribeUpdateTransaction) -> Self {
-        let tx = transaction.expect("should be defined");
+impl TryFrom<SubscribeUpdateTransaction> for TransactionPretty {
+    type Error = ClientError;
+
+    fn try_from(SubscribeUpdateTransaction { transaction, slot }: SubscribeUpdateTransaction) -> ClientResult<Self> {
+        let tx = transaction.ok_or_else(|| ClientError::Parse("transaction update".to_string(), format!("no transaction in slot {}", slot)))?;
         // let transaction_info = tx.transaction.clone().unwrap();
-        Self {
+        let signature = Signature::try_from(tx.signature.as_slice())
+            .map_err(|e| ClientError::Parse("transaction signature".to_string(), e.to_string()))?;
+        let is_vote = tx.is_vote;
+        let tx = yellowstone_grpc_proto::convert_from::create_tx_with_meta(tx)
+            .map_err(|e| ClientError::Parse(format!("transaction {}", signature), e.to_string()))?
+            .encode(UiTransactionEncoding::Base64, Some(u8::MAX), true)
+            .map_err(|e| ClientError::Parse(format!("encoding transaction {}", signature), e.to_string()))?;
+        Ok(Self {
             slot,
-            signature: Signature::try_from(tx.signature.as_slice()).expect("valid signature"),
-            is_vote: tx.is_vote,
-            tx: yellowstone_grpc_proto::convert_from::create_tx_with_meta(tx)
-                .expect("valid tx with meta")
-                .encode(UiTransactionEncoding::Base64, Some(u8::MAX), true)
-                .expect("failed to encode"),
+            signature,
+            is_vote,
+            tx,
+            filters: vec![],
             // transaction: Some(transaction_info),
+        })
+    }
+}
+
+impl From<EventCommitment> for CommitmentLevel {
+    fn from(commitment: EventCommitment) -> Self {
+        match commitment {
+            EventCommitment::Processed => CommitmentLevel::Processed,
+            EventCommitment::Confirmed => CommitmentLevel::Confirmed,
+            EventCommitment::Finalized => CommitmentLevel::Finalized,
         }
     }
 }
 
+/// Why a forwarded gRPC stream stopped
+#[derive(Debug)]
+enum StreamEnd {
+    /// Nothing, not even a pong, arrived for this long
+    Stale(Duration),
+    Failed(String),
+    Closed,
+}
+
 #[derive(Clone)]
 pub struct YellowstoneGrpc {
     endpoint: String,
+    channel_options: GrpcChannelOptions,
+    health: StreamHealth,
+    commitment: EventCommitment,
+    /// Stamped on every message any stream of this client receives
+    heartbeat: Heartbeat,
+    /// Creator of every mint whose creation was streamed, so the creator's later trades are
+    /// reported as dev trades
+    dev_addresses: Option<PersistentLru<Pubkey, Pubkey>>,
+    event_latency: Option<Arc<EventLatency>>,
+    watermarks: Option<Arc<Watermarks>>,
+    curve_cache: Option<Arc<BondingCurveCache>>,
 }
 
 impl YellowstoneGrpc {
     pub fn new(endpoint: String) -> Self {
-        Self { endpoint }
+        Self { endpoint, channel_options: GrpcChannelOptions::default(), health: StreamHealth::default(), commitment: EventCommitment::Processed, heartbeat: Heartbeat::new(), dev_addresses: None, event_latency: None, watermarks: None, curve_cache: None }
+    }
+
+    /// Remembers the creator of each streamed mint in `dev_addresses`, reporting the creator's
+    /// trades after the creation transaction as `NewDevTrade` too; persist the cache with
+    /// [`PersistentLru::open`] to keep the creators across restarts
+    pub fn with_dev_addresses(mut self, dev_addresses: PersistentLru<Pubkey, Pubkey>) -> Self {
+        self.dev_addresses = Some(dev_addresses);
+        self
+    }
+
+    /// Records the delivery latency of every Pump.fun event in `latency`, streaming block
+    /// metadata along with the transactions to learn the block times
+    pub fn with_event_latency(mut self, latency: Arc<EventLatency>) -> Self {
+        self.event_latency = Some(latency);
+        self
+    }
+
+    /// Tracks the slots `subscribe_pumpfun` delivered in `watermarks`, recording a gap whenever
+    /// the stream reconnects
+    pub fn with_watermarks(mut self, watermarks: Arc<Watermarks>) -> Self {
+        self.watermarks = Some(watermarks);
+        self
+    }
+
+    /// Writes the updates of bonding curve subscriptions into `cache`, e.g.
+    /// `bonding_curve_cache(&pumpfun.rpc)`
+    pub fn with_curve_cache(mut self, cache: Arc<BondingCurveCache>) -> Self {
+        self.curve_cache = Some(cache);
+        self
+    }
+
+    /// Sets the compression, keepalive and message size settings of new connections
+    pub fn with_channel_options(mut self, channel_options: GrpcChannelOptions) -> Self {
+        self.channel_options = channel_options;
+        self
+    }
+
+    /// Sets the ping interval and staleness threshold of `subscribe_pumpfun`
+    pub fn with_stream_health(mut self, health: StreamHealth) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Sets the commitment of every subscription, `Processed` by default
+    pub fn with_commitment(mut self, commitment: EventCommitment) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    /// When a stream of this client last received a message, for external health checks
+    #[inline]
+    pub fn last_message_at(&self) -> SystemTime {
+        self.heartbeat.last_message_at()
     }
 
     pub async fn connect(
@@ -93,50 +210,96 @@ impl YellowstoneGrpc {
             impl Stream<Item = Result<SubscribeUpdate, Status>>,
         )>
     > {
-        if CryptoProvider::get_default().is_none() {
-            default_provider()
-                .install_default()
-                .map_err(|e| ClientError::Other(format!("Failed to install crypto provider: {:?}", e)))?;
-        }
-
-        let mut client = GeyserGrpcClient::build_from_shared(self.endpoint.clone())
-            .map_err(|e| ClientError::Other(format!("Failed to build client: {:?}", e)))?
-            .tls_config(ClientTlsConfig::new().with_native_roots())
-            .map_err(|e| ClientError::Other(format!("Failed to build client: {:?}", e)))?
-            .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT))
-            .timeout(Duration::from_secs(REQUEST_TIMEOUT))
-            .connect()
-            .await
-            .map_err(|e| ClientError::Other(format!("Failed to connect: {:?}", e)))?;
-
-        let subscribe_request = SubscribeRequest {
+        let mut subscribe_request = SubscribeRequest {
             transactions,
-            commitment: Some(CommitmentLevel::Processed.into()),
+            commitment: Some(CommitmentLevel::from(self.commitment).into()),
             ..Default::default()
         };
+        if self.event_latency.is_some() {
+            subscribe_request.blocks_meta.insert("block_times".to_string(), SubscribeRequestFilterBlocksMeta {});
+        }
+        self.connect_with_request(subscribe_request).await
+    }
+
+    pub async fn connect_with_request(
+        &self,
+        subscribe_request: SubscribeRequest,
+    ) -> ClientResult<
+        GeyserGrpcClientResult<(
+            impl Sink<SubscribeRequest, Error = mpsc::SendError>,
+            impl Stream<Item = Result<SubscribeUpdate, Status>>,
+        )>
+    > {
+        crate::common::tls::ensure_crypto_provider()
+            .map_err(|e| ClientError::Other(e.to_string()))?;
+
+        let builder = GeyserGrpcClient::build_from_shared(self.endpoint.clone())?
+            .tls_config(ClientTlsConfig::new().with_native_roots())?
+            // The client's own interceptor only carries the x-token, so the SDK is named in the user agent
+            .user_agent(user_agent())?
+            .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT))
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT));
+        let mut client = self.channel_options.apply(builder).connect().await?;
 
         Ok(client.subscribe_with_request(Some(subscribe_request)).await)
     }
 
+    /// Connects and waits for the server to answer a ping, returning the round trip
+    pub async fn ping(&self, timeout: Duration) -> ClientResult<Duration> {
+        let start = Instant::now();
+        let request = SubscribeRequest { ping: Some(SubscribeRequestPing { id: PING_ID }), ..Default::default() };
+        let (_subscribe_tx, mut stream) = self.connect_with_request(request).await??;
+
+        let reply = async {
+            while let Some(update) = stream.next().await {
+                if matches!(update?.update_oneof, Some(UpdateOneof::Pong(_) | UpdateOneof::Ping(_))) {
+                    return Ok(());
+                }
+            }
+            Err(ClientError::Other("stream closed before the server answered the ping".to_string()))
+        };
+        tokio::time::timeout(timeout, reply)
+            .await
+            .map_err(|_| ClientError::Other(format!("no answer to the ping within {:?}", timeout)))??;
+        Ok(start.elapsed())
+    }
+
+    /// A single filter named "client" for successful non-vote transactions; use
+    /// [`FilterBuilder`] for several named filters or other settings
     pub fn get_subscribe_request_filter(
         &self,
         account_include: Vec<String>,
         account_exclude: Vec<String>,
         account_required: Vec<String>,
     ) -> TransactionsFilterMap {
-        let mut transactions = HashMap::new();
-        transactions.insert(
-            "client".to_string(),
-            SubscribeRequestFilterTransactions {
-                vote: Some(false),
-                failed: Some(false),
-                signature: None,
-                account_include,
-                account_exclude,
-                account_required,
-            },
-        );
-        transactions
+        let filter = SubscribeRequestFilterTransactions {
+            account_include,
+            account_exclude,
+            account_required,
+            ..SubscribeRequestFilterTransactions::from(TransactionFilter::new())
+        };
+        FilterBuilder::new().filter("client", filter).build()
+    }
+
+    /// Filters matching successful transactions signed or touched by any of `wallets`
+    ///
+    /// `account_required` must hold every listed account, so each wallet gets its own filter;
+    /// a transaction matching any filter is delivered once.
+    pub fn get_wallet_activity_request_filter(&self, wallets: &HashSet<Pubkey>) -> TransactionsFilterMap {
+        wallets
+            .iter()
+            .map(|wallet| {
+                let filter = SubscribeRequestFilterTransactions {
+                    vote: Some(false),
+                    failed: Some(false),
+                    signature: None,
+                    account_include: vec![],
+                    account_exclude: vec![],
+                    account_required: vec![wallet.to_string()],
+                };
+                (format!("wallet-{}", wallet), filter)
+            })
+            .collect()
     }
 
     // pub fn get_subscribe_account_updater_request_filter(
@@ -180,11 +343,20 @@ impl YellowstoneGrpc {
         msg: SubscribeUpdate,
         tx: &mut mpsc::Sender<TransactionPretty>,
         subscribe_tx: &mut (impl Sink<SubscribeRequest, Error = mpsc::SendError> + Unpin),
+        latency: Option<&EventLatency>,
     ) -> ClientResult<()> {
         match msg.update_oneof {
             Some(UpdateOneof::Transaction(sut)) => {
-                let transaction_pretty = TransactionPretty::from(sut);
-                tx.try_send(transaction_pretty).map_err(|e| ClientError::Other(format!("Send error: {:?}", e)))?;
+                slot_tracker().observe(sut.slot);
+                let mut transaction_pretty = match TransactionPretty::try_from(sut) {
+                    Ok(transaction_pretty) => transaction_pretty,
+                    Err(e) => {
+                        error!("Skipping transaction: {}", e);
+                        return Ok(());
+                    }
+                };
+                transaction_pretty.filters = msg.filters;
+                tx.try_send(transaction_pretty).map_err(|e| ClientError::Send("Transaction channel".to_string(), e.to_string()))?;
             }
             Some(UpdateOneof::Ping(_)) => {
                 subscribe_tx
@@ -193,48 +365,160 @@ impl YellowstoneGrpc {
                         ..Default::default()
                     })
                     .await
-                    .map_err(|e| ClientError::Other(format!("Ping error: {:?}", e)))?;
+                    .map_err(|e| ClientError::Send("Ping error".to_string(), e.to_string()))?;
                 info!("service is ping: {}", Local::now());
             }
             Some(UpdateOneof::Pong(_)) => {
                 info!("service is pong: {}", Local::now());
             }
+            Some(UpdateOneof::BlockMeta(block)) => {
+                if let (Some(latency), Some(block_time)) = (latency, block.block_time) {
+                    latency.observe_block_time(block.slot, block_time.timestamp);
+                }
+            }
             _ => {}
         }
         Ok(())
     }
 
-    // pub async fn subscribe_account_updater<F>(&self, callback: F, bot_wallet: Option<Pubkey>) -> ClientResult<()> 
-    // where
-    //     F: Fn(PumpfunEvent) + Send + Sync + 'static,
-    // {
-    //     let addrs = vec![PUMP_PROGRAM_ID.to_string()];
-    //     let transactions = self.get_subscribe_request_filter(addrs, vec![], vec![]);
-    //     let (mut subscribe_tx, mut stream) = self.connect(transactions).await?
-    //     .map_err(|e| ClientError::Other(format!("Failed to subscribe: {:?}", e)))?;
-    //     let (mut tx, mut rx) = mpsc::channel::<TransactionPretty>(CHANNEL_SIZE);
-
-    //     let callback = Box::new(callback);
-        
-    // }
+    /// Forwards transactions from `stream` into `tx`, pinging the server every
+    /// `health.ping_interval`, until the stream fails, closes or stays silent for
+    /// `health.stale_after`
+    async fn forward_stream(
+        mut stream: impl Stream<Item = Result<SubscribeUpdate, Status>> + Unpin,
+        mut subscribe_tx: impl Sink<SubscribeRequest, Error = mpsc::SendError> + Unpin,
+        mut tx: mpsc::Sender<TransactionPretty>,
+        heartbeat: Heartbeat,
+        health: StreamHealth,
+        latency: Option<Arc<EventLatency>>,
+    ) -> StreamEnd {
+        let mut ping = tokio::time::interval(health.ping_interval);
+        ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut last_message = tokio::time::Instant::now();
 
+        loop {
+            tokio::select! {
+                message = stream.next() => match message {
+                    Some(Ok(msg)) => {
+                        last_message = tokio::time::Instant::now();
+                        heartbeat.beat();
+                        if let Err(e) = Self::handle_stream_message(msg, &mut tx, &mut subscribe_tx, latency.as_deref()).await {
+                            return StreamEnd::Failed(format!("{:?}", e));
+                        }
+                    }
+                    Some(Err(status)) => return StreamEnd::Failed(status.to_string()),
+                    None => return StreamEnd::Closed,
+                },
+                _ = ping.tick() => {
+                    let request = SubscribeRequest { ping: Some(SubscribeRequestPing { id: PING_ID }), ..Default::default() };
+                    if let Err(e) = subscribe_tx.send(request).await {
+                        return StreamEnd::Failed(format!("Ping error: {}", e));
+                    }
+                }
+                _ = tokio::time::sleep_until(last_message + health.stale_after) => {
+                    return StreamEnd::Stale(last_message.elapsed());
+                }
+            }
+        }
+    }
+
+    /// Subscribe to Pump.fun events, reconnecting whenever the stream fails or goes stale
+    ///
+    /// Fails only if the first connection can't be made; later interruptions are reported to
+    /// `callback` as `PumpfunEvent::Error` or `PumpfunEvent::Stale`.
     pub async fn subscribe_pumpfun<F>(&self, callback: F, bot_wallet: Option<Pubkey>) -> ClientResult<()> 
     where
         F: Fn(PumpfunEvent) + Send + Sync + 'static,
     {
         let addrs = vec![PUMP_PROGRAM_ID.to_string()];
         let transactions = self.get_subscribe_request_filter(addrs, vec![], vec![]);
-        let (mut subscribe_tx, mut stream) = self.connect(transactions).await?
-        .map_err(|e| ClientError::Other(format!("Failed to subscribe: {:?}", e)))?;
-        let (mut tx, mut rx) = mpsc::channel::<TransactionPretty>(CHANNEL_SIZE);
+        self.subscribe_pumpfun_filtered(transactions, move |filtered| callback(filtered.event), bot_wallet).await
+    }
 
-        let callback = Box::new(callback);
+    /// Like [`subscribe_pumpfun`](Self::subscribe_pumpfun) with the filters of `transactions`,
+    /// e.g. built with [`FilterBuilder`], passing each event with the filters it matched
+    pub async fn subscribe_pumpfun_filtered<F>(&self, transactions: TransactionsFilterMap, callback: F, bot_wallet: Option<Pubkey>) -> ClientResult<()>
+    where
+        F: Fn(FilteredEvent) + Send + Sync + 'static,
+    {
+        if transactions.is_empty() {
+            return Err(ClientError::Other("No transaction filters".to_string()));
+        }
+        let mut connection = Some(self.connect(transactions.clone()).await??);
+
+        let report = |event: PumpfunEvent| callback(FilteredEvent { filters: vec![], event });
+
+        loop {
+            let (subscribe_tx, stream) = match connection.take() {
+                Some(connection) => connection,
+                None => match self.connect(transactions.clone()).await {
+                    Ok(Ok(connection)) => connection,
+                    Ok(Err(e)) => {
+                        report(PumpfunEvent::Error(format!("Failed to resubscribe: {:?}", e)));
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                    Err(e) => {
+                        report(PumpfunEvent::Error(format!("Failed to reconnect: {:?}", e)));
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                },
+            };
+
+            let (tx, mut rx) = mpsc::channel::<TransactionPretty>(CHANNEL_SIZE);
+            let forwarder = tokio::spawn(Self::forward_stream(stream, subscribe_tx, tx, self.heartbeat.clone(), self.health, self.event_latency.clone()));
+
+            while let Some(transaction_pretty) = rx.next().await {
+                let filters = transaction_pretty.filters.clone();
+                let tagged = |event: PumpfunEvent| {
+                    if let Some(latency) = &self.event_latency {
+                        latency.record(EventSource::Grpc, &event);
+                    }
+                    callback(FilteredEvent { filters: filters.clone(), event })
+                };
+                let slot = transaction_pretty.slot;
+                if let Err(e) = Self::process_pumpfun_transaction(transaction_pretty, &tagged, bot_wallet, self.dev_addresses.as_ref()).await {
+                    error!("Error processing transaction: {:?}", e);
+                }
+                if let Some(watermarks) = &self.watermarks {
+                    watermarks.observe(EventSource::Grpc, slot);
+                }
+            }
+            if let Some(watermarks) = &self.watermarks {
+                watermarks.disconnected(EventSource::Grpc);
+            }
+
+            match forwarder.await {
+                Ok(StreamEnd::Stale(silent_for)) => report(PumpfunEvent::Stale(silent_for)),
+                Ok(StreamEnd::Failed(e)) => report(PumpfunEvent::Error(format!("Stream error: {}", e))),
+                Ok(StreamEnd::Closed) => report(PumpfunEvent::Error("Stream closed".to_string())),
+                Err(e) => report(PumpfunEvent::Error(format!("Stream task failed: {}", e))),
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Subscribe to the Pump.fun trades of `wallets`, labeling trades carrying `trade_tag` as
+    /// initiated by the SDK; trades are passed to `callback` as `PumpfunEvent::WalletTrade`
+    pub async fn subscribe_wallet_activity<F>(&self, wallets: HashSet<Pubkey>, trade_tag: Option<TradeTag>, callback: F) -> ClientResult<()>
+    where
+        F: Fn(PumpfunEvent) + Send + Sync + 'static,
+    {
+        if wallets.is_empty() {
+            return Err(ClientError::Other("No wallets to watch".to_string()));
+        }
+
+        let transactions = self.get_wallet_activity_request_filter(&wallets);
+        let (mut subscribe_tx, mut stream) = self.connect(transactions).await??;
+        let (mut tx, mut rx) = mpsc::channel::<TransactionPretty>(CHANNEL_SIZE);
+        let latency = self.event_latency.clone();
 
         tokio::spawn(async move {
             while let Some(message) = stream.next().await {
                 match message {
                     Ok(msg) => {
-                        if let Err(e) = Self::handle_stream_message(msg, &mut tx, &mut subscribe_tx).await {
+                        if let Err(e) = Self::handle_stream_message(msg, &mut tx, &mut subscribe_tx, latency.as_deref()).await {
                             error!("Error handling message: {:?}", e);
                             break;
                         }
@@ -248,18 +532,118 @@ impl YellowstoneGrpc {
         });
 
         while let Some(transaction_pretty) = rx.next().await {
-            if let Err(e) = Self::process_pumpfun_transaction(transaction_pretty, &*callback, bot_wallet).await {
-                error!("Error processing transaction: {:?}", e);
+            let Some(meta) = transaction_pretty.tx.meta.as_ref() else {
+                continue;
+            };
+            if meta.err.is_some() {
+                continue;
+            }
+            let OptionSerializer::Some(logs) = &meta.log_messages else {
+                continue;
+            };
+
+            for trade in wallet_trades(transaction_pretty.signature, transaction_pretty.slot, logs, &wallets, trade_tag.as_ref()) {
+                callback(PumpfunEvent::WalletTrade(trade));
+            }
+        }
+        Ok(())
+    }
+
+    /// Streams the bonding curves of `mints` into the cache set with
+    /// [`with_curve_cache`](Self::with_curve_cache)
+    ///
+    /// While this runs, `get_bonding_curve_account` serves these mints from streamed state
+    /// without RPC calls. Their entries are dropped from the cache when the stream ends or
+    /// this future is dropped.
+    pub async fn subscribe_bonding_curves(&self, mints: Vec<Pubkey>) -> ClientResult<()> {
+        if self.curve_cache.is_none() {
+            return Err(ClientError::Other("No bonding curve cache to stream into, see with_curve_cache".to_string()));
+        }
+        self.subscribe_bonding_curves_with(mints, |_, _, _| {}).await
+    }
+
+    /// Like [`subscribe_bonding_curves`](Self::subscribe_bonding_curves), also passing each
+    /// update to `on_update` with its mint and slot
+    ///
+    /// Returns once the stream fails or closes; updates sent meanwhile are missed.
+    pub async fn subscribe_bonding_curves_with<F>(&self, mints: Vec<Pubkey>, on_update: F) -> ClientResult<()>
+    where
+        F: Fn(Pubkey, u64, &BondingCurveAccount),
+    {
+        let curves: HashMap<Pubkey, Pubkey> = mints
+            .iter()
+            .filter_map(|mint| Some((get_bonding_curve_pda(mint)?, *mint)))
+            .collect();
+        if curves.is_empty() {
+            return Err(ClientError::Other("No bonding curves to watch".to_string()));
+        }
+
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            "bonding_curves".to_string(),
+            SubscribeRequestFilterAccounts {
+                account: curves.keys().map(|curve| curve.to_string()).collect(),
+                ..Default::default()
+            },
+        );
+        let subscribe_request = SubscribeRequest {
+            accounts,
+            commitment: Some(CommitmentLevel::from(self.commitment).into()),
+            ..Default::default()
+        };
+        let (mut subscribe_tx, mut stream) = self.connect_with_request(subscribe_request).await??;
+
+        let _guard = self.curve_cache.clone().map(|cache| StreamGuard::new(cache, mints.clone()));
+        while let Some(message) = stream.next().await {
+            let msg = match message {
+                Ok(msg) => msg,
+                Err(error) => {
+                    error!("Stream error: {error:?}");
+                    break;
+                }
+            };
+
+            match msg.update_oneof {
+                Some(UpdateOneof::Account(update)) => {
+                    slot_tracker().observe(update.slot);
+                    let Some(account) = update.account else { continue };
+                    let Some(mint) = Pubkey::try_from(account.pubkey.as_slice()).ok().and_then(|curve| curves.get(&curve)) else {
+                        continue;
+                    };
+                    match BondingCurveAccount::from_account_data(&account.data) {
+                        Ok(curve) => {
+                            on_update(*mint, update.slot, &curve);
+                            if let Some(cache) = &self.curve_cache {
+                                cache.insert(*mint, update.slot, Arc::new(curve), CurveSource::Stream);
+                            }
+                        }
+                        Err(e) => error!("Invalid bonding curve of {}: {:?}", mint, e),
+                    }
+                }
+                Some(UpdateOneof::Ping(_)) => {
+                    let ping = SubscribeRequest { ping: Some(SubscribeRequestPing { id: 1 }), ..Default::default() };
+                    if let Err(e) = subscribe_tx.send(ping).await {
+                        error!("Ping error: {:?}", e);
+                        break;
+                    }
+                }
+                _ => {}
             }
         }
+
         Ok(())
     }
 
-    async fn process_pumpfun_transaction<F>(transaction_pretty: TransactionPretty, callback: &F, bot_wallet: Option<Pubkey>) -> ClientResult<()> 
+    async fn process_pumpfun_transaction<F>(
+        transaction_pretty: TransactionPretty,
+        callback: &F,
+        bot_wallet: Option<Pubkey>,
+        dev_addresses: Option<&PersistentLru<Pubkey, Pubkey>>,
+    ) -> ClientResult<()> 
     where
         F: Fn(PumpfunEvent) + Send + Sync,
     {
-        let slot = transaction_pretty.slot;
+        let (slot, signature) = (transaction_pretty.slot, transaction_pretty.signature);
         let trade_raw = transaction_pretty.tx;
         let meta = trade_raw.meta.as_ref()
             .ok_or_else(|| ClientError::Other("Missing transaction metadata".to_string()))?;
@@ -275,17 +659,25 @@ impl YellowstoneGrpc {
         };
 
         let mut dev_address: Option<Pubkey> = None;
-        let instructions = LogFilter::parse_instruction(logs, bot_wallet).unwrap();
+        let mut cpi_trades = cpi_trades::cpi_trades(&trade_raw);
+        let instructions = LogFilter::parse_instruction(logs, bot_wallet)?;
         for instruction in instructions {
             match instruction {
                 DexInstruction::CreateToken(mut token_info) => {
                     token_info.slot = slot;
+                    token_age::record_create_event(&token_info);
                     dev_address = Some(token_info.user);
+                    if let Some(dev_addresses) = dev_addresses {
+                        dev_addresses.insert(token_info.mint, token_info.user);
+                    }
                     callback(PumpfunEvent::NewToken(token_info));
                 }
                 DexInstruction::UserTrade(mut trade_info) => {
                     trade_info.slot = slot;
-                    if Some(trade_info.user) == dev_address {
+                    trade_info.signature = signature;
+                    cpi_trades::claim(&mut cpi_trades, &mut trade_info);
+                    let creator = dev_address.or_else(|| dev_addresses.and_then(|dev_addresses| dev_addresses.get(&trade_info.mint)));
+                    if Some(trade_info.user) == creator {
                         callback(PumpfunEvent::NewDevTrade(trade_info));
                     } else {
                         callback(PumpfunEvent::NewUserTrade(trade_info));
@@ -293,12 +685,66 @@ impl YellowstoneGrpc {
                 }
                 DexInstruction::BotTrade(mut trade_info) => {
                     trade_info.slot = slot;
+                    trade_info.signature = signature;
+                    cpi_trades::claim(&mut cpi_trades, &mut trade_info);
                     callback(PumpfunEvent::NewBotTrade(trade_info));
                 }
                 _ => {}
             }
         }
 
+        // Trades made through CPI whose logs were interleaved or truncated
+        for mut trade_info in cpi_trades {
+            trade_info.slot = slot;
+            trade_info.signature = signature;
+            trade_info.is_initial_buy = dev_address.is_some();
+            let creator = dev_address.or_else(|| dev_addresses.and_then(|dev_addresses| dev_addresses.get(&trade_info.mint)));
+            if bot_wallet == Some(trade_info.user) {
+                callback(PumpfunEvent::NewBotTrade(trade_info));
+            } else if Some(trade_info.user) == creator {
+                callback(PumpfunEvent::NewDevTrade(trade_info));
+            } else {
+                callback(PumpfunEvent::NewUserTrade(trade_info));
+            }
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+    use tonic::codec::CompressionEncoding;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connect_applies_channel_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let options = GrpcChannelOptions {
+            accept_compressed: Some(CompressionEncoding::Zstd),
+            send_compressed: Some(CompressionEncoding::Gzip),
+            http2_keepalive_interval: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let client = YellowstoneGrpc::new(endpoint).with_channel_options(options);
+
+        let connecting = tokio::spawn(async move {
+            let filters = FilterBuilder::new().filter("pump", TransactionFilter::pumpfun()).build();
+            let _ = client.connect(filters).await;
+        });
+        let accepted = tokio::time::timeout(Duration::from_secs(CONNECT_TIMEOUT), listener.accept()).await;
+        connecting.abort();
+        assert!(matches!(accepted, Ok(Ok(_))), "the client never reached the server");
+    }
+
+    #[test]
+    fn test_commitment_matches_websocket_levels() {
+        assert_eq!(YellowstoneGrpc::new(String::new()).commitment, EventCommitment::Processed);
+        assert_eq!(CommitmentLevel::from(EventCommitment::Processed), CommitmentLevel::Processed);
+        assert_eq!(CommitmentLevel::from(EventCommitment::Confirmed), CommitmentLevel::Confirmed);
+        assert_eq!(CommitmentLevel::from(EventCommitment::Finalized), CommitmentLevel::Finalized);
+    }
+}
@@ -0,0 +1,59 @@
+//! Transport settings of the Geyser channel
+//!
+//! Compression trades CPU for bandwidth: on full-firehose filters zstd or gzip cut traffic
+//! several times over, at the cost of decompressing every message, which adds latency on a
+//! loaded client. Narrow filters gain little from it. HTTP/2 keepalive pings keep
+//! intermediaries from dropping a subscription that is quiet for a while; a short interval
+//! also detects dead connections sooner, with a few bytes of overhead per ping.
+
+use std::time::Duration;
+
+use tonic::codec::CompressionEncoding;
+use yellowstone_grpc_client::GeyserGrpcBuilder;
+
+/// Largest message decoded by default; blocks with many transactions exceed tonic's 4 MiB
+pub const DEFAULT_MAX_DECODING_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct GrpcChannelOptions {
+    /// Compression the server may use for messages it sends
+    pub accept_compressed: Option<CompressionEncoding>,
+    /// Compression of the requests sent to the server
+    pub send_compressed: Option<CompressionEncoding>,
+    pub http2_keepalive_interval: Option<Duration>,
+    /// How long a keepalive ping may go unanswered before the connection is closed
+    pub keepalive_timeout: Option<Duration>,
+    pub tcp_nodelay: bool,
+    pub max_decoding_message_size: usize,
+}
+
+impl Default for GrpcChannelOptions {
+    fn default() -> Self {
+        Self {
+            accept_compressed: None,
+            send_compressed: None,
+            http2_keepalive_interval: Some(Duration::from_secs(15)),
+            keepalive_timeout: Some(Duration::from_secs(10)),
+            tcp_nodelay: true,
+            max_decoding_message_size: DEFAULT_MAX_DECODING_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl GrpcChannelOptions {
+    pub(crate) fn apply(&self, mut builder: GeyserGrpcBuilder) -> GeyserGrpcBuilder {
+        if let Some(encoding) = self.accept_compressed {
+            builder = builder.accept_compressed(encoding);
+        }
+        if let Some(encoding) = self.send_compressed {
+            builder = builder.send_compressed(encoding);
+        }
+        if let Some(interval) = self.http2_keepalive_interval {
+            builder = builder.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.keepalive_timeout {
+            builder = builder.keep_alive_timeout(timeout);
+        }
+        builder.tcp_nodelay(self.tcp_nodelay).max_decoding_message_size(self.max_decoding_message_size)
+    }
+}
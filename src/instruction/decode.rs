@@ -0,0 +1,104 @@
+//! Decoding of Pump.fun instruction data
+//!
+//! The inverse of the [`Create`](super::Create), [`Buy`](super::Buy) and [`Sell`](super::Sell)
+//! builders, for inspecting instructions before they are sent or after they landed.
+
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::{ClientError, ClientResult};
+
+use super::{BUY_DISCRIMINATOR, CREATE_DISCRIMINATOR, SELL_DISCRIMINATOR};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "instruction", rename_all = "snake_case")]
+pub enum PumpInstruction {
+    Create { name: String, symbol: String, uri: String, creator: String },
+    Buy { amount: u64, max_sol_cost: u64 },
+    Sell { amount: u64, min_sol_output: u64 },
+}
+
+/// Decodes the data of an instruction to the Pump.fun program
+pub fn decode_instruction(data: &[u8]) -> ClientResult<PumpInstruction> {
+    let mut reader = Reader { data, offset: 8 };
+    let discriminator: [u8; 8] = data
+        .get(..8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ClientError::DataTooShort { field: "discriminator", offset: 0, needed: 8, len: data.len() })?;
+
+    match discriminator {
+        CREATE_DISCRIMINATOR => {
+            let name = reader.string("name")?;
+            let symbol = reader.string("symbol")?;
+            let uri = reader.string("uri")?;
+            // The SDK appends the creator as a string; the program's own layout uses raw bytes
+            let creator = match reader.remaining() {
+                32 => Pubkey::new_from_array(reader.bytes("creator", 32)?.try_into().unwrap_or_default()).to_string(),
+                _ => reader.string("creator")?,
+            };
+            Ok(PumpInstruction::Create { name, symbol, uri, creator })
+        }
+        BUY_DISCRIMINATOR => Ok(PumpInstruction::Buy { amount: reader.u64("amount")?, max_sol_cost: reader.u64("max_sol_cost")? }),
+        SELL_DISCRIMINATOR => Ok(PumpInstruction::Sell { amount: reader.u64("amount")?, min_sol_output: reader.u64("min_sol_output")? }),
+        _ => Err(ClientError::Parse("Unknown Pump.fun instruction".to_string(), format!("discriminator {:?}", discriminator))),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.offset)
+    }
+
+    fn bytes(&mut self, field: &'static str, needed: usize) -> ClientResult<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + needed)
+            .ok_or(ClientError::DataTooShort { field, offset: self.offset, needed, len: self.data.len() })?;
+        self.offset += needed;
+        Ok(bytes)
+    }
+
+    fn u64(&mut self, field: &'static str) -> ClientResult<u64> {
+        let mut value = [0u8; 8];
+        value.copy_from_slice(self.bytes(field, 8)?);
+        Ok(u64::from_le_bytes(value))
+    }
+
+    fn string(&mut self, field: &'static str) -> ClientResult<String> {
+        let mut len = [0u8; 4];
+        len.copy_from_slice(self.bytes(field, 4)?);
+        let offset = self.offset;
+        let bytes = self.bytes(field, u32::from_le_bytes(len) as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|source| ClientError::InvalidString { field, offset, source })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::{Buy, Create, Sell};
+
+    #[test]
+    fn test_decodes_what_the_builders_encode() {
+        let buy = Buy { amount: 1_000_000, max_sol_cost: 10_000_000 }.data();
+        assert_eq!(decode_instruction(&buy).unwrap(), PumpInstruction::Buy { amount: 1_000_000, max_sol_cost: 10_000_000 });
+
+        let sell = Sell { amount: 5, min_sol_output: 7 }.data();
+        assert_eq!(decode_instruction(&sell).unwrap(), PumpInstruction::Sell { amount: 5, min_sol_output: 7 });
+
+        let creator = Pubkey::new_unique();
+        let create = Create { _name: "Token".to_string(), _symbol: "TKN".to_string(), _uri: "ipfs://uri".to_string(), payer_pubkey: creator }.data();
+        assert_eq!(
+            decode_instruction(&create).unwrap(),
+            PumpInstruction::Create { name: "Token".to_string(), symbol: "TKN".to_string(), uri: "ipfs://uri".to_string(), creator: creator.to_string() }
+        );
+
+        assert!(matches!(decode_instruction(&buy[..20]), Err(ClientError::DataTooShort { field: "max_sol_cost", .. })));
+        assert!(decode_instruction(&[0; 24]).is_err());
+    }
+}
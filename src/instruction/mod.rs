@@ -10,14 +10,18 @@
 //! - `buy`: Instruction to buy tokens from a bonding curve by providing SOL.
 //! - `sell`: Instruction to sell tokens back to the bonding curve in exchange for SOL.
 
+pub mod decode;
+
 use std::sync::Arc;
 
-use spl_associated_token_account::instruction::create_associated_token_account;
-use spl_token::instruction::close_account;
 use crate::common::SolanaRpcClient;
-use crate::constants::trade::DEFAULT_SLIPPAGE;
 use crate::ipfs::TokenMetadataIPFS;
-use crate::pumpfun::common::{calculate_with_slippage_buy, calculate_with_slippage_sell, get_bonding_curve_account, get_buy_amount_with_slippage, get_global_account, get_initial_buy_price, get_token_balance, get_token_balance_and_ata};
+use crate::pumpfun::buy::build_buy_instructions_offline;
+use crate::pumpfun::create::build_create_and_buy_instructions_offline;
+use crate::pumpfun::curve_cache::CurveRead;
+use crate::pumpfun::idl;
+use crate::pumpfun::sell::{build_sell_instructions_offline, Proceeds, SellAuthority};
+use crate::pumpfun::common::{get_ata, get_bonding_curve_account_with, get_buy_accounts, get_global_account, get_token_program, PartialFillGuard, PostTradeChecks, SellFloor, SlippageMode};
 use crate::{
     constants, 
     pumpfun::common::{
@@ -28,12 +32,22 @@ use spl_associated_token_account::get_associated_token_address;
 
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
     signature::Keypair,
     signer::Signer,
 };
 
 use anyhow::{anyhow, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Anchor discriminator of the `create` instruction
+pub const CREATE_DISCRIMINATOR: [u8; 8] = [24, 30, 200, 40, 5, 28, 7, 119];
+/// Anchor discriminator of the `buy` instruction
+pub const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+/// Anchor discriminator of the `sell` instruction
+pub const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+
 pub struct Create {
     pub _name: String,
     pub _symbol: String,
@@ -56,7 +70,7 @@ impl Create {
         let mut data = Vec::with_capacity(capacity);
 
         // Append discriminator
-        data.extend_from_slice(&[24, 30, 200, 40, 5, 28, 7, 119]); // Correct discriminator for create
+        data.extend_from_slice(&CREATE_DISCRIMINATOR);
 
         // Append name string length and content
         data.extend_from_slice(&(self._name.len() as u32).to_le_bytes());
@@ -80,36 +94,119 @@ impl Create {
     }
 }
 
+/// Arguments of the `buy` instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
 pub struct Buy {
-    pub _amount: u64,
-    pub _max_sol_cost: u64,
+    /// Tokens to buy
+    pub amount: u64,
+    /// Most lamports the buy may cost, fee included
+    pub max_sol_cost: u64,
 }
 
 impl Buy {
+    /// Buy arguments, rejecting a zero `amount` or a `max_sol_cost` no buy can fit under
+    pub fn new(amount: u64, max_sol_cost: u64) -> Result<Self> {
+        if amount == 0 {
+            return Err(InvalidInstructionArgs::ZeroAmount.into());
+        }
+        if max_sol_cost == 0 {
+            return Err(InvalidInstructionArgs::ZeroMaxSolCost.into());
+        }
+        Ok(Self { amount, max_sol_cost })
+    }
+
     pub fn data(&self) -> Vec<u8> {
         let mut data = Vec::with_capacity(8 + 8 + 8);
-        data.extend_from_slice(&[102, 6, 61, 18, 1, 218, 235, 234]); // discriminator
-        data.extend_from_slice(&self._amount.to_le_bytes());
-        data.extend_from_slice(&self._max_sol_cost.to_le_bytes());
+        data.extend_from_slice(&BUY_DISCRIMINATOR);
+        data.extend_from_slice(&self.amount.to_le_bytes());
+        data.extend_from_slice(&self.max_sol_cost.to_le_bytes());
         data
     }
 }
 
+/// Arguments of the `sell` instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
 pub struct Sell {
-    pub _amount: u64,
-    pub _min_sol_output: u64,
+    /// Tokens to sell
+    pub amount: u64,
+    /// Fewest lamports the sell must return, after the fee
+    pub min_sol_output: u64,
 }
 
 impl Sell {
+    /// Sell arguments, rejecting a zero `amount` or a `min_sol_output` above all the SOL there is
+    pub fn new(amount: u64, min_sol_output: u64) -> Result<Self> {
+        if amount == 0 {
+            return Err(InvalidInstructionArgs::ZeroAmount.into());
+        }
+        if min_sol_output > MAX_LAMPORTS {
+            return Err(InvalidInstructionArgs::MinSolOutputTooLarge(min_sol_output).into());
+        }
+        Ok(Self { amount, min_sol_output })
+    }
+
     pub fn data(&self) -> Vec<u8> {
         let mut data = Vec::with_capacity(8 + 8 + 8);
-        data.extend_from_slice(&[51, 230, 133, 164, 1, 127, 131, 173]); // discriminator
-        data.extend_from_slice(&self._amount.to_le_bytes());
-        data.extend_from_slice(&self._min_sol_output.to_le_bytes());
+        data.extend_from_slice(&SELL_DISCRIMINATOR);
+        data.extend_from_slice(&self.amount.to_le_bytes());
+        data.extend_from_slice(&self.min_sol_output.to_le_bytes());
         data
     }
 }
 
+/// More lamports than will ever exist, so no sell can return them
+const MAX_LAMPORTS: u64 = 1_000_000_000 * LAMPORTS_PER_SOL;
+
+/// Arguments [`Buy::new`] or [`Sell::new`] refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidInstructionArgs {
+    #[error("Token amount cannot be zero")]
+    ZeroAmount,
+    #[error("Maximum SOL cost cannot be zero")]
+    ZeroMaxSolCost,
+    #[error("Minimum SOL output of {0} lamports can never be met")]
+    MinSolOutputTooLarge(u64),
+}
+
+impl InvalidInstructionArgs {
+    pub fn find(error: &anyhow::Error) -> Option<&InvalidInstructionArgs> {
+        error.chain().find_map(|cause| cause.downcast_ref::<InvalidInstructionArgs>())
+    }
+}
+
+/// The 2.x argument structs, whose fields were underscore-prefixed
+///
+/// [`buy`], [`sell`] and [`sell_from`] still accept them for this release.
+#[deprecated(since = "3.0.0", note = "use `Buy` and `Sell`, whose fields drop the underscore")]
+pub mod legacy {
+    #[deprecated(since = "3.0.0", note = "use `instruction::Buy`")]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Buy {
+        pub _amount: u64,
+        pub _max_sol_cost: u64,
+    }
+
+    #[deprecated(since = "3.0.0", note = "use `instruction::Sell`")]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Sell {
+        pub _amount: u64,
+        pub _min_sol_output: u64,
+    }
+
+    #[allow(deprecated)]
+    impl From<Buy> for super::Buy {
+        fn from(args: Buy) -> Self {
+            Self { amount: args._amount, max_sol_cost: args._max_sol_cost }
+        }
+    }
+
+    #[allow(deprecated)]
+    impl From<Sell> for super::Sell {
+        fn from(args: Sell) -> Self {
+            Self { amount: args._amount, min_sol_output: args._min_sol_output }
+        }
+    }
+}
 
 /// Creates an instruction to create a new token with bonding curve
 ///
@@ -117,35 +214,35 @@ impl Sell {
 ///
 /// # Arguments
 ///
-/// * `payer` - Keypair that will pay for account creation and transaction fees
-/// * `mint` - Keypair for the new token mint account that will be created
+/// * `payer` - Account that will pay for account creation and transaction fees
+/// * `mint` - The new token mint account that will be created, which signs the transaction
 /// * `args` - Create instruction data containing token name, symbol and metadata URI
 ///
 /// # Returns
 ///
 /// Returns a Solana instruction that when executed will create the token and its accounts
-pub fn create(payer: &Keypair, mint: &Keypair, args: Create) -> Instruction {
-    let bonding_curve: Pubkey = get_bonding_curve_pda(&mint.pubkey()).unwrap();
+pub fn create(payer: &Pubkey, mint: &Pubkey, args: Create) -> Instruction {
+    let bonding_curve: Pubkey = get_bonding_curve_pda(mint).unwrap();
     Instruction::new_with_bytes(
         constants::accounts::PUMPFUN,
         &args.data(),
         vec![
-            AccountMeta::new(mint.pubkey(), true),
+            AccountMeta::new(*mint, true),
             AccountMeta::new(get_mint_authority_pda(), false),
             AccountMeta::new(bonding_curve, false),
             AccountMeta::new(
-                get_associated_token_address(&bonding_curve, &mint.pubkey()),
+                get_associated_token_address(&bonding_curve, mint),
                 false,
             ),
             AccountMeta::new_readonly(get_global_pda(), false),
             AccountMeta::new_readonly(constants::accounts::MPL_TOKEN_METADATA, false),
-            AccountMeta::new(get_metadata_pda(&mint.pubkey()), false),
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_metadata_pda(mint), false),
+            AccountMeta::new(*payer, true),
             AccountMeta::new_readonly(constants::accounts::SYSTEM_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::TOKEN_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::ASSOCIATED_TOKEN_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::RENT, false),
-            AccountMeta::new_readonly(constants::accounts::EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(idl::event_authority(), false),
             AccountMeta::new_readonly(constants::accounts::PUMPFUN, false),
         ],
     )
@@ -159,20 +256,23 @@ pub fn create(payer: &Keypair, mint: &Keypair, args: Create) -> Instruction {
 ///
 /// # Arguments
 ///
-/// * `payer` - Keypair that will provide the SOL to buy tokens
+/// * `payer` - Account that will provide the SOL to buy tokens and sign the transaction
 /// * `mint` - Public key of the token mint to buy
 /// * `fee_recipient` - Public key of the account that will receive the transaction fee
+/// * `token_program` - Token program owning the mint (legacy SPL Token or Token-2022)
 /// * `args` - Buy instruction data containing the SOL amount and maximum acceptable token price
 ///
 /// # Returns
 ///
 /// Returns a Solana instruction that when executed will buy tokens from the bonding curve
 pub fn buy(
-    payer: &Keypair,
+    payer: &Pubkey,
     mint: &Pubkey,
     fee_recipient: &Pubkey,
-    args: Buy,
+    token_program: &Pubkey,
+    args: impl Into<Buy>,
 ) -> Instruction {
+    let args = args.into();
     let bonding_curve: Pubkey = get_bonding_curve_pda(mint).unwrap();
     Instruction::new_with_bytes(
         constants::accounts::PUMPFUN,
@@ -182,13 +282,13 @@ pub fn buy(
             AccountMeta::new(*fee_recipient, false),
             AccountMeta::new_readonly(*mint, false),
             AccountMeta::new(bonding_curve, false),
-            AccountMeta::new(get_associated_token_address(&bonding_curve, mint), false),
-            AccountMeta::new(get_associated_token_address(&payer.pubkey(), mint), false),
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_ata(&bonding_curve, mint, token_program), false),
+            AccountMeta::new(get_ata(payer, mint, token_program), false),
+            AccountMeta::new(*payer, true),
             AccountMeta::new_readonly(constants::accounts::SYSTEM_PROGRAM, false),
-            AccountMeta::new_readonly(constants::accounts::TOKEN_PROGRAM, false),
+            AccountMeta::new_readonly(*token_program, false),
             AccountMeta::new_readonly(constants::accounts::RENT, false),
-            AccountMeta::new_readonly(constants::accounts::EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(idl::event_authority(), false),
             AccountMeta::new_readonly(constants::accounts::PUMPFUN, false),
         ],
     )
@@ -202,20 +302,36 @@ pub fn buy(
 ///
 /// # Arguments
 ///
-/// * `payer` - Keypair that owns the tokens to sell
+/// * `payer` - Account that owns the tokens to sell and signs the transaction
 /// * `mint` - Public key of the token mint to sell
 /// * `fee_recipient` - Public key of the account that will receive the transaction fee
+/// * `token_program` - Token program owning the mint (legacy SPL Token or Token-2022)
 /// * `args` - Sell instruction data containing token amount and minimum acceptable SOL output
 ///
 /// # Returns
 ///
 /// Returns a Solana instruction that when executed will sell tokens to the bonding curve
 pub fn sell(
-    payer: &Keypair,
+    payer: &Pubkey,
     mint: &Pubkey,
     fee_recipient: &Pubkey,
-    args: Sell,
+    token_program: &Pubkey,
+    args: impl Into<Sell>,
 ) -> Instruction {
+    sell_from(payer, mint, &get_ata(payer, mint, token_program), fee_recipient, token_program, args)
+}
+
+/// Creates an instruction to sell tokens from `token_account`, which the payer either owns
+/// or is an approved delegate of; the SOL goes to the payer
+pub fn sell_from(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    fee_recipient: &Pubkey,
+    token_program: &Pubkey,
+    args: impl Into<Sell>,
+) -> Instruction {
+    let args = args.into();
     let bonding_curve: Pubkey = get_bonding_curve_pda(mint).unwrap();
     Instruction::new_with_bytes(
         constants::accounts::PUMPFUN,
@@ -225,13 +341,13 @@ pub fn sell(
             AccountMeta::new(*fee_recipient, false),
             AccountMeta::new_readonly(*mint, false),
             AccountMeta::new(bonding_curve, false),
-            AccountMeta::new(get_associated_token_address(&bonding_curve, mint), false),
-            AccountMeta::new(get_associated_token_address(&payer.pubkey(), mint), false),
-            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(get_ata(&bonding_curve, mint, token_program), false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(*payer, true),
             AccountMeta::new_readonly(constants::accounts::SYSTEM_PROGRAM, false),
             AccountMeta::new_readonly(constants::accounts::ASSOCIATED_TOKEN_PROGRAM, false),
-            AccountMeta::new_readonly(constants::accounts::TOKEN_PROGRAM, false),
-            AccountMeta::new_readonly(constants::accounts::EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(idl::event_authority(), false),
             AccountMeta::new_readonly(constants::accounts::PUMPFUN, false),
         ],
     )
@@ -249,101 +365,45 @@ pub async fn build_create_and_buy_instructions(
         return Err(anyhow!("build_create_and_buy_instructions: Amount cannot be zero"));
     }
 
-    let rpc = rpc.as_ref();
     let global_account = get_global_account(&rpc).await?;
-    let buy_amount = global_account.get_initial_buy_price(amount_sol);
-    let buy_amount_with_slippage =
-        get_buy_amount_with_slippage(amount_sol, slippage_basis_points);
-
-    let mut instructions = vec![];
-
-    instructions.push(create(
-        payer.as_ref(),
-        mint.as_ref(),
-        Create {
-            _name: ipfs.metadata.name.clone(),
-            _symbol: ipfs.metadata.symbol.clone(),
-            _uri: ipfs.metadata_uri.clone(),
-            payer_pubkey: payer.pubkey(),
-        },
-    ));
-
-    let ata = get_associated_token_address(&payer.pubkey(), &mint.pubkey());
-    instructions.push(create_associated_token_account(
-        &payer.pubkey(),
-        &payer.pubkey(),
-        &mint.pubkey(),
-        &constants::accounts::TOKEN_PROGRAM,
-    ));
-    
-    instructions.push(buy(
-        payer.as_ref(),
-        &mint.pubkey(),
-        &global_account.fee_recipient,
-        Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
-        },
-    ));
-
-    Ok(instructions)
+    build_create_and_buy_instructions_offline(&payer.pubkey(), &mint.pubkey(), &ipfs, amount_sol, slippage_basis_points, &global_account)
 }
 
+/// Builds the ATA creation and buy instructions for spending `amount_sol`, fee included
+///
+/// `post_trade_checks` optionally bounds what the buy may spend and must return; see
+/// [`PostTradeChecks`] for what the program enforces. The token account is created
+/// idempotently, and not at all if `ata_exists`, e.g. after `precreate_atas`. A buy from a
+/// nearly exhausted curve fails unless `partial_fill` allows it.
 pub async fn build_buy_instructions(
     rpc: Arc<SolanaRpcClient>,
     payer: Arc<Keypair>,
     mint: Arc<Pubkey>,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    post_trade_checks: Option<PostTradeChecks>,
+    ata_exists: bool,
+    partial_fill: PartialFillGuard,
 ) -> Result<Vec<Instruction>, anyhow::Error> {
     if amount_sol == 0 {
         return Err(anyhow!("build_buy_instructions:Amount cannot be zero"));
     }
 
-    let global_account = get_global_account(&rpc).await?;
-    let buy_amount = match get_bonding_curve_account(&rpc, mint.as_ref()).await {
-        Ok(account) => {
-            account.get_buy_price(amount_sol).map_err(|e| anyhow!(e))?
-        },
-        Err(_e) => {
-            let initial_buy_amount = get_initial_buy_price(&global_account, amount_sol).await?;
-            initial_buy_amount * 80 / 100
-        }
-    };
-
-    let buy_amount_with_slippage = calculate_with_slippage_buy(amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
-    let mut instructions = vec![];
-    // let ata = get_associated_token_address(&payer.pubkey(), &mint);
-    // match rpc.get_account(&ata).await {
-    //     Ok(_) => {},
-    //     Err(_) => {
-    //         instructions.push(create_associated_token_account(
-    //             &payer.pubkey(),
-    //             &payer.pubkey(),
-    //             &mint,
-    //             &constants::accounts::TOKEN_PROGRAM,
-    //         ));
-    //     }
-    // }
-
-    instructions.push(create_associated_token_account(
-        &payer.pubkey(),
+    let (global_account, curve, token_program) = get_buy_accounts(&rpc, mint.as_ref()).await?;
+    build_buy_instructions_offline(
         &payer.pubkey(),
-        &mint,
-        &constants::accounts::TOKEN_PROGRAM,
-    ));
-
-    instructions.push(buy(
-        payer.as_ref(),
-        &mint,
-        &global_account.fee_recipient,
-        Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
-        },
-    ));
-
-    Ok(instructions)
+        mint.as_ref(),
+        amount_sol,
+        slippage_basis_points,
+        slippage_mode,
+        post_trade_checks,
+        ata_exists,
+        partial_fill,
+        &global_account,
+        curve.as_deref().ok(),
+        &token_program,
+    )
 }
 
 pub async fn build_sell_instructions(
@@ -352,42 +412,74 @@ pub async fn build_sell_instructions(
     mint: Arc<Pubkey>,
     amount_token: u64,
     slippage_basis_points: Option<u64>,
+    proceeds: Proceeds,
+    sell_floor: SellFloor,
 ) -> Result<Vec<Instruction>, anyhow::Error> {
     if amount_token == 0 {
         return Err(anyhow!("build_sell_instructions: Amount cannot be zero"));
     }
 
-    let ata = get_associated_token_address(&payer.pubkey(), mint.as_ref());
-    let global_account = get_global_account(&rpc).await?;
-    let bonding_curve_account = get_bonding_curve_account(&rpc, mint.as_ref()).await?;
-    let min_sol_output = bonding_curve_account
-        .get_sell_price(amount_token, global_account.fee_basis_points)
-        .map_err(|e| anyhow!(e))?;
-    let min_sol_output_with_slippage = calculate_with_slippage_sell(
-        min_sol_output,
-        slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-    );
-
-    let mut instructions = vec![];
-
-    instructions.push(sell(
-        payer.as_ref(),
-        &mint,
-        &global_account.fee_recipient,
-        Sell {
-            _amount: amount_token,
-            _min_sol_output: min_sol_output_with_slippage,
-        },
-    ));
-
-    instructions.push(close_account(
-        &spl_token::ID,
-        &ata,
-        &payer.pubkey(),
+    let (token_program, global_account, bonding_curve_account) = tokio::try_join!(
+        get_token_program(&rpc, mint.as_ref()),
+        get_global_account(&rpc),
+        get_bonding_curve_account_with(&rpc, mint.as_ref(), CurveRead::Fresh),
+    )?;
+    let ata = get_ata(&payer.pubkey(), mint.as_ref(), &token_program);
+    build_sell_instructions_offline(
         &payer.pubkey(),
-        &[&payer.pubkey()],
-    )?);
-
-    Ok(instructions)
+        mint.as_ref(),
+        &ata,
+        SellAuthority::Owner,
+        amount_token,
+        slippage_basis_points,
+        proceeds,
+        sell_floor,
+        &global_account,
+        &bonding_curve_account,
+        &token_program,
+    )
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_are_validated() {
+        assert_eq!(Buy::new(1_000, 10).unwrap(), Buy { amount: 1_000, max_sol_cost: 10 });
+        let error = Buy::new(0, 10).unwrap_err();
+        assert_eq!(InvalidInstructionArgs::find(&error), Some(&InvalidInstructionArgs::ZeroAmount));
+        let error = Buy::new(1_000, 0).unwrap_err();
+        assert_eq!(InvalidInstructionArgs::find(&error), Some(&InvalidInstructionArgs::ZeroMaxSolCost));
+
+        assert_eq!(Sell::new(5, 0).unwrap(), Sell { amount: 5, min_sol_output: 0 });
+        assert_eq!(InvalidInstructionArgs::find(&Sell::new(0, 7).unwrap_err()), Some(&InvalidInstructionArgs::ZeroAmount));
+        let error = Sell::new(5, u64::MAX).unwrap_err();
+        assert_eq!(InvalidInstructionArgs::find(&error), Some(&InvalidInstructionArgs::MinSolOutputTooLarge(u64::MAX)));
+    }
+
+    #[test]
+    fn test_borsh_layout_is_the_instruction_data() {
+        let buy = Buy { amount: 1_000_000, max_sol_cost: 10_000_000 };
+        assert_eq!(borsh::to_vec(&buy).unwrap(), buy.data()[8..]);
+        assert_eq!(Buy::try_from_slice(&buy.data()[8..]).unwrap(), buy);
+        let sell = Sell { amount: 5, min_sol_output: 7 };
+        assert_eq!(borsh::to_vec(&sell).unwrap(), sell.data()[8..]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_legacy_args_build_the_same_instructions() {
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let fee_recipient = Pubkey::new_unique();
+        let token_program = constants::accounts::TOKEN_PROGRAM;
+        assert_eq!(
+            buy(&payer, &mint, &fee_recipient, &token_program, legacy::Buy { _amount: 3, _max_sol_cost: 4 }),
+            buy(&payer, &mint, &fee_recipient, &token_program, Buy { amount: 3, max_sol_cost: 4 }),
+        );
+        assert_eq!(
+            sell(&payer, &mint, &fee_recipient, &token_program, legacy::Sell { _amount: 3, _min_sol_output: 4 }),
+            sell(&payer, &mint, &fee_recipient, &token_program, Sell { amount: 3, min_sol_output: 4 }),
+        );
+    }
+}
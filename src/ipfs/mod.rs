@@ -1,9 +1,11 @@
+pub mod prefetch;
+
 use std::time::Duration;
 
 use serde_json::Value;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
-use reqwest::Client;
+use crate::common::user_agent::{http_client, http_client_builder};
 use reqwest::multipart::{Form, Part};
 use base64::{Engine as _, engine::general_purpose};
 use serde::{Deserialize, Serialize};
@@ -17,12 +19,15 @@ pub struct TokenMetadata {
     /// Token symbol (e.g. "BTC")
     pub symbol: String,
     /// Description of the token
+    #[serde(default)]
     pub description: String,
     /// IPFS URL of the token's image
     pub image: String,
     /// Whether to display the token's name
+    #[serde(default)]
     pub show_name: bool,
     /// Creation timestamp/source
+    #[serde(default)]
     pub created_on: String,
     /// Twitter handle
     pub twitter: Option<String>,
@@ -103,7 +108,7 @@ pub async fn create_token_metadata(metadata: CreateTokenMetadata, api_key: &str)
         };  
         Ok(token_metadata_ipfs)
     } else {
-        let client = Client::new();
+        let client = http_client();
         let response = client
             .post("https://api.pinata.cloud/pinning/pinJSONToIPFS")
             .header("Content-Type", "application/json")
@@ -129,10 +134,38 @@ pub async fn create_token_metadata(metadata: CreateTokenMetadata, api_key: &str)
     }
 }
 
+/// Checks `api_key` against Pinata's authentication test endpoint
+pub async fn test_authentication(api_key: &str) -> Result<(), anyhow::Error> {
+    let client = http_client_builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+
+    let response = client
+        .get("https://api.pinata.cloud/data/testAuthentication")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Pinata rejected the API key: {}", response.status()))
+    }
+}
+
+/// Download the token metadata JSON stored at `metadata_uri`
+pub async fn fetch_token_metadata(metadata_uri: &str) -> Result<TokenMetadata, anyhow::Error> {
+    let client = http_client_builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+
+    let response = client.get(metadata_uri).send().await?.error_for_status()?;
+    Ok(response.json::<TokenMetadata>().await?)
+}
+
 pub async fn upload_base64_file(base64_string: &str, api_key: &str) -> Result<String, anyhow::Error> {
     let decoded_bytes = general_purpose::STANDARD.decode(base64_string)?;
 
-    let client = Client::builder()
+    let client = http_client_builder()
         .timeout(Duration::from_secs(120))  // 增加超时时间到120秒
         .pool_max_idle_per_host(0) // 禁用连接池
         .pool_idle_timeout(None) // 禁用空闲超时
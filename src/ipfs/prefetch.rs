@@ -0,0 +1,248 @@
+//! Throttle-aware prefetching of token metadata
+//!
+//! Filters that look at a new token's metadata put an HTTP fetch on the critical path, and
+//! IPFS gateways throttle during launch storms. A [`MetadataPrefetcher`] starts fetching as
+//! soon as a token is announced, so a filter evaluated a little later usually finds the
+//! metadata ready. Fetches run on a bounded pool of workers, each gateway host is sent at most
+//! its configured requests per second and backs off when it answers `429 Too Many Requests`,
+//! and fetched metadata is kept in a small LRU cache keyed by uri. Concurrent requests for the
+//! same uri share one fetch.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use reqwest::StatusCode;
+use thiserror::Error;
+use tokio::{sync::Semaphore, time::Instant};
+
+use super::TokenMetadata;
+use crate::common::{cache::PersistentLru, logs_events::PumpfunEvent, user_agent::http_client_builder};
+
+/// Backoff of a gateway that throttled without saying for how long
+const DEFAULT_THROTTLE_BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct PrefetchConfig {
+    /// Fetches running at once
+    pub workers: usize,
+    /// Fetches queued or running at once; further prefetches are dropped
+    pub max_pending: usize,
+    /// Requests per second sent to a gateway host without an entry in `gateway_limits`
+    pub requests_per_second: u32,
+    /// Requests per second by gateway host, e.g. `ipfs.io`
+    pub gateway_limits: HashMap<String, u32>,
+    /// Metadata documents kept, by uri
+    pub cache_capacity: usize,
+    /// Timeout of each HTTP request
+    pub request_timeout: Duration,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            workers: 16,
+            max_pending: 256,
+            requests_per_second: 10,
+            gateway_limits: HashMap::new(),
+            cache_capacity: 1_024,
+            request_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PrefetchError {
+    #[error("metadata was not fetched within {0:?}")]
+    Deadline(Duration),
+    #[error("too many metadata fetches pending")]
+    Busy,
+    #[error("gateway {0} is throttling requests")]
+    Throttled(String),
+    #[error("fetching metadata failed: {0}")]
+    Fetch(String),
+}
+
+type PendingFetch = Shared<BoxFuture<'static, Result<TokenMetadata, PrefetchError>>>;
+
+/// Spaces the requests sent to each gateway host
+#[derive(Debug, Default)]
+struct GatewaySchedule {
+    /// Earliest time the next request to each host may start
+    next: HashMap<String, Instant>,
+}
+
+impl GatewaySchedule {
+    /// Reserves the next request slot of `host` at `rate` requests per second, returning when it
+    /// starts
+    fn reserve(&mut self, host: &str, rate: u32, now: Instant) -> Instant {
+        let interval = Duration::from_secs(1) / rate.max(1);
+        let slot = self.next.get(host).map_or(now, |next| (*next).max(now));
+        self.next.insert(host.to_string(), slot + interval);
+        slot
+    }
+
+    /// Holds back requests to `host` for `backoff` after it throttled
+    fn throttled(&mut self, host: &str, backoff: Duration, now: Instant) {
+        let next = self.next.entry(host.to_string()).or_insert(now);
+        *next = (*next).max(now + backoff);
+    }
+}
+
+struct Inner {
+    config: PrefetchConfig,
+    client: reqwest::Client,
+    workers: Semaphore,
+    schedule: Mutex<GatewaySchedule>,
+    pending: Mutex<HashMap<String, PendingFetch>>,
+    cache: PersistentLru<String, TokenMetadata>,
+}
+
+/// Fetches token metadata ahead of the filters needing it; clones share the pool and cache
+#[derive(Clone)]
+pub struct MetadataPrefetcher {
+    inner: Arc<Inner>,
+}
+
+impl MetadataPrefetcher {
+    pub fn new(config: PrefetchConfig) -> Result<Self, anyhow::Error> {
+        let client = http_client_builder().timeout(config.request_timeout).build()?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                workers: Semaphore::new(config.workers.max(1)),
+                cache: PersistentLru::in_memory(config.cache_capacity),
+                schedule: Mutex::new(GatewaySchedule::default()),
+                pending: Mutex::new(HashMap::new()),
+                client,
+                config,
+            }),
+        })
+    }
+
+    /// Starts fetching the metadata of every `NewToken` event; pass it every event of a
+    /// subscription
+    pub fn on_event(&self, event: &PumpfunEvent) {
+        if let PumpfunEvent::NewToken(token) = event {
+            self.prefetch(&token.uri);
+        }
+    }
+
+    /// Starts fetching `uri` in the background unless it is cached or already being fetched;
+    /// `false` if too many fetches are pending; must be called within a tokio runtime
+    pub fn prefetch(&self, uri: &str) -> bool {
+        self.pending_fetch(uri).is_ok()
+    }
+
+    /// The cached metadata of `uri`, without fetching it
+    pub fn cached(&self, uri: &str) -> Option<TokenMetadata> {
+        self.inner.cache.get(&uri.to_string())
+    }
+
+    /// The metadata of `uri`, fetching it unless it is cached or already being fetched, within
+    /// `deadline`; must be called within a tokio runtime
+    ///
+    /// A fetch still running at the deadline keeps running and fills the cache for later
+    /// calls.
+    pub async fn get(&self, uri: &str, deadline: Duration) -> Result<TokenMetadata, PrefetchError> {
+        let fetch = match self.pending_fetch(uri)? {
+            Some(fetch) => fetch,
+            None => return self.cached(uri).ok_or_else(|| PrefetchError::Fetch("cached metadata was evicted".to_string())),
+        };
+        tokio::time::timeout(deadline, fetch).await.map_err(|_| PrefetchError::Deadline(deadline))?
+    }
+
+    /// The fetch of `uri` in flight, started if needed; `None` if `uri` is cached
+    fn pending_fetch(&self, uri: &str) -> Result<Option<PendingFetch>, PrefetchError> {
+        let mut pending = self.inner.pending.lock().unwrap();
+        // a finished fetch fills the cache before leaving `pending`, so checking in this order
+        // never misses one
+        if let Some(fetch) = pending.get(uri) {
+            return Ok(Some(fetch.clone()));
+        }
+        if self.cached(uri).is_some() {
+            return Ok(None);
+        }
+        if pending.len() >= self.inner.config.max_pending {
+            return Err(PrefetchError::Busy);
+        }
+
+        let (inner, owned_uri) = (self.inner.clone(), uri.to_string());
+        let task = tokio::spawn(async move {
+            let result = inner.fetch(&owned_uri).await;
+            if let Ok(metadata) = &result {
+                inner.cache.insert(owned_uri.clone(), metadata.clone());
+            }
+            inner.pending.lock().unwrap().remove(&owned_uri);
+            result
+        });
+        let fetch = async move { task.await.unwrap_or_else(|e| Err(PrefetchError::Fetch(e.to_string()))) }.boxed().shared();
+        pending.insert(uri.to_string(), fetch.clone());
+        Ok(Some(fetch))
+    }
+}
+
+impl Inner {
+    async fn fetch(&self, uri: &str) -> Result<TokenMetadata, PrefetchError> {
+        let host = reqwest::Url::parse(uri)
+            .map_err(|e| PrefetchError::Fetch(format!("invalid uri {}: {}", uri, e)))?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+        let rate = self.config.gateway_limits.get(&host).copied().unwrap_or(self.config.requests_per_second);
+        let start = self.schedule.lock().unwrap().reserve(&host, rate, Instant::now());
+        tokio::time::sleep_until(start).await;
+
+        let _worker = self.workers.acquire().await.map_err(|e| PrefetchError::Fetch(e.to_string()))?;
+        let response = self.client.get(uri).send().await.map_err(|e| PrefetchError::Fetch(e.to_string()))?;
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let backoff = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok()?.parse().ok())
+                .map_or(DEFAULT_THROTTLE_BACKOFF, Duration::from_secs);
+            self.schedule.lock().unwrap().throttled(&host, backoff, Instant::now());
+            return Err(PrefetchError::Throttled(host));
+        }
+        let response = response.error_for_status().map_err(|e| PrefetchError::Fetch(e.to_string()))?;
+        response.json::<TokenMetadata>().await.map_err(|e| PrefetchError::Fetch(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gateway_requests_are_spaced_and_throttled_gateways_back_off() {
+        let mut schedule = GatewaySchedule::default();
+        let now = Instant::now();
+
+        assert_eq!(schedule.reserve("ipfs.io", 4, now), now);
+        assert_eq!(schedule.reserve("ipfs.io", 4, now), now + Duration::from_millis(250));
+        assert_eq!(schedule.reserve("cf-ipfs.com", 4, now), now);
+
+        schedule.throttled("ipfs.io", Duration::from_secs(2), now);
+        assert_eq!(schedule.reserve("ipfs.io", 4, now), now + Duration::from_secs(2));
+        // a slot already later than the backoff is kept
+        schedule.throttled("ipfs.io", Duration::from_secs(1), now);
+        assert_eq!(schedule.reserve("ipfs.io", 4, now), now + Duration::from_millis(2_250));
+    }
+
+    #[tokio::test]
+    async fn test_cached_metadata_is_served_without_fetching() {
+        let prefetcher = MetadataPrefetcher::new(PrefetchConfig { max_pending: 0, ..Default::default() }).unwrap();
+        let uri = "https://ipfs.io/ipfs/cached";
+        let metadata: TokenMetadata = serde_json::from_value(serde_json::json!({ "name": "a", "symbol": "A", "image": "https://ipfs.io/ipfs/image" })).unwrap();
+        prefetcher.inner.cache.insert(uri.to_string(), metadata);
+
+        assert_eq!(prefetcher.get(uri, Duration::from_millis(10)).await.unwrap().name, "a");
+        assert!(prefetcher.prefetch(uri));
+        assert_eq!(prefetcher.get("https://ipfs.io/ipfs/other", Duration::from_millis(10)).await.unwrap_err(), PrefetchError::Busy);
+    }
+}
@@ -1,6 +1,7 @@
-#![allow(warnings)]
-
 pub mod accounts;
+pub mod addresses;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod constants;
 pub mod error;
 pub mod instruction;
@@ -8,29 +9,66 @@ pub mod grpc;
 pub mod common;
 pub mod ipfs;
 pub mod trade;
-pub mod jito;
+pub mod fee_clients;
+/// Former home of the fee clients, kept until 4.0
+#[deprecated(note = "moved to `fee_clients`")]
+pub mod jito {
+    pub use crate::fee_clients::*;
+}
+pub mod prelude;
 pub mod pumpfun;
 
-use std::sync::Arc;
+use std::{collections::HashSet, future::Future, sync::{Arc, RwLock}, time::Duration};
 
-use jito::{FeeClient, JitoClient, NextBlockClient, ZeroSlotClient};
-use rustls::crypto::{ring::default_provider, CryptoProvider};
+use fee_clients::{startup::{connect_fee_clients, FeeClientStartup, DEFAULT_CONNECT_TIMEOUT}, FeeClient, SubmissionOptions};
+use solana_hash::Hash;
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
+    instruction::Instruction,
     pubkey::Pubkey,
     signature::{Keypair, Signer, Signature},
 };
 
-use common::{logs_data::TradeInfo, logs_events::PumpfunEvent, logs_subscribe, Cluster, PriorityFee, SolanaRpcClient};
-use common::logs_subscribe::SubscriptionHandle;
+use accounts::InitialBuyQuote;
+use addresses::DerivedAddresses;
+use common::{latency::LatencyReport, logs_data::TradeInfo, logs_events::PumpfunEvent, logs_subscribe, Cluster, PriorityFee, RpcClients, SolanaRpcClient, TradeTag};
+use common::{commitment::EventCommitment, logs_subscribe::SubscriptionHandle};
+use common::balance_watch::{BalanceChange, DEFAULT_BALANCE_THRESHOLD};
+use common::event_latency::EventLatency;
+use common::watermark::Watermarks;
+use common::watchlist::{MintWatchlist, WatchSource};
+use common::cache_stats::{CacheCounters, CacheStats};
+use common::self_test::{SelfTestOptions, SelfTestReport};
+use common::tasks::{TaskRegistry, DEFAULT_SHUTDOWN_TIMEOUT};
 use ipfs::TokenMetadataIPFS;
+use tracing::Instrument;
+use trade::{cancel::{CancellationToken, Cancelled}, drain::{Drain, DrainTimedOut, DEFAULT_DRAIN_TIMEOUT}, context::{ExecutionContext, TradeOverrides}, mint_guard::MintOnceGuard, mint_lock::{self, MintBusy, MintLocks, DEFAULT_MINT_LOCK_TIMEOUT}, params::{CreateParams, TradeAction, TradeParams}, position::PositionTracker, reconcile::ReconciliationDiff, tip_spend::TipSpendReport};
+use pumpfun::{ata::PrecreatedAta, balance::InsufficientSolBalance, common::BuyTooSmall, create::CreateResult, creator::CreatorHistory, fee_recipient::retry_on_rotation, recovery::CreateOutcome, explain::ExplainedTransaction, idl::CompatibilityReport, metadata::MetadataPolicy, mint_safety::{MintSafetyError, MintSafetyReport}, snapshot::{SnapshotOptions, TokenSnapshot}, split::{SplitBuyReport, SplitMode}, token_age::TokenAge};
 
 pub struct PumpFun {
     pub payer: Arc<Keypair>,
+    /// Client of reads, quotes and blockhashes, on the cluster's read endpoint
     pub rpc: Arc<SolanaRpcClient>,
+    /// Client transactions are sent and confirmed through over RPC, on the cluster's send
+    /// endpoint; the same client as `rpc` unless the endpoints differ
+    pub send_rpc: Arc<SolanaRpcClient>,
     pub fee_clients: Vec<Arc<FeeClient>>,
+    fee_client_startup: Arc<Vec<FeeClientStartup>>,
     pub priority_fee: PriorityFee,
     pub cluster: Cluster,
+    /// Memo appended to every buy, sell and create transaction
+    pub trade_tag: Option<TradeTag>,
+    /// Background tasks and subscriptions owned by this client and its clones
+    tasks: Arc<TaskRegistry>,
+    /// Mints whose payer token account is known to exist, so buys skip creating it
+    known_atas: Arc<RwLock<HashSet<Pubkey>>>,
+    known_ata_counters: Arc<CacheCounters>,
+    /// Mints already bought, checked before every buy
+    mint_guard: Option<MintOnceGuard>,
+    /// Trades in flight, refusing new ones once a shutdown began
+    drain: Arc<Drain>,
+    /// Lets one trade per mint build and send at a time
+    mint_locks: MintLocks,
+    mint_lock_timeout: Duration,
 }
 
 impl Clone for PumpFun {
@@ -38,172 +76,601 @@ impl Clone for PumpFun {
         Self {
             payer: self.payer.clone(),
             rpc: self.rpc.clone(),
+            send_rpc: self.send_rpc.clone(),
             fee_clients: self.fee_clients.clone(),
+            fee_client_startup: self.fee_client_startup.clone(),
             priority_fee: self.priority_fee.clone(),
             cluster: self.cluster.clone(),
+            trade_tag: self.trade_tag.clone(),
+            tasks: self.tasks.clone(),
+            known_atas: self.known_atas.clone(),
+            known_ata_counters: self.known_ata_counters.clone(),
+            mint_guard: self.mint_guard.clone(),
+            drain: self.drain.clone(),
+            mint_locks: self.mint_locks.clone(),
+            mint_lock_timeout: self.mint_lock_timeout,
         }
     }
 }
 
 impl PumpFun {
+    /// Creates the client, panicking if the TLS crypto provider can't be installed
+    ///
+    /// Unlike [`PumpFun::try_new`], this never checks the RPC node's cluster.
+    #[deprecated(note = "use `PumpFun::try_new`, which returns an error instead of panicking")]
     #[inline]
     pub async fn new(
         payer: Arc<Keypair>,
         cluster: &Cluster,
     ) -> Self {
-        if CryptoProvider::get_default().is_none() {
-            let _ = default_provider()
-                .install_default()
-                .map_err(|e| anyhow::anyhow!("Failed to install crypto provider: {:?}", e));
-        }
-
-        let rpc = SolanaRpcClient::new_with_commitment(
-            cluster.clone().rpc_url,
-            cluster.clone().commitment
-        );   
+        Self::connect(payer, cluster, false).await.expect("Failed to create PumpFun client")
+    }
 
-        let mut fee_clients: Vec<Arc<FeeClient>> = vec![];
-        if cluster.clone().use_jito {
-            let jito_client = JitoClient::new(
-                cluster.clone().rpc_url, 
-                cluster.clone().block_engine_url
-            ).await.expect("Failed to create Jito client");
+    /// Creates the client, connecting the enabled fee clients concurrently
+    ///
+    /// Each fee client gets [`DEFAULT_CONNECT_TIMEOUT`]; one that fails or times out is left
+    /// out rather than failing the client, and its error is kept in
+    /// [`PumpFun::fee_client_startup`]. When `Cluster::cluster_kind` is set and
+    /// `Cluster::skip_genesis_check` isn't, the RPC node must serve that cluster or the client
+    /// fails with a [`ClusterMismatch`](common::genesis::ClusterMismatch). With
+    /// `Cluster::verify_program_on_startup` set, the deployed program is checked with
+    /// [`PumpFun::verify_program_compatibility`] and any drift is logged as a warning.
+    pub async fn try_new(
+        payer: Arc<Keypair>,
+        cluster: &Cluster,
+    ) -> Result<Self, anyhow::Error> {
+        Self::connect(payer, cluster, !cluster.skip_genesis_check).await
+    }
 
-            fee_clients.push(Arc::new(jito_client));
+    async fn connect(payer: Arc<Keypair>, cluster: &Cluster, check_genesis: bool) -> Result<Self, anyhow::Error> {
+        common::tls::ensure_crypto_provider()?;
+        if cluster.app_name.is_some() {
+            common::user_agent::set_app_name(cluster.app_name.clone());
         }
 
-        if cluster.clone().use_zeroslot {
-            let zeroslot_client = ZeroSlotClient::new(
-                cluster.clone().rpc_url, 
-                cluster.clone().zeroslot_url,
-                cluster.clone().zeroslot_auth_token
-            );
+        let rpc = Arc::new(SolanaRpcClient::new_with_commitment(cluster.read_endpoint().to_string(), cluster.commitment));
+        let send_rpc = match cluster.send_endpoint() == cluster.read_endpoint() {
+            true => rpc.clone(),
+            false => Arc::new(SolanaRpcClient::new_with_commitment(cluster.send_endpoint().to_string(), cluster.commitment)),
+        };
 
-            fee_clients.push(Arc::new(zeroslot_client));
+        if let Some(cluster_kind) = cluster.cluster_kind.filter(|_| check_genesis) {
+            common::genesis::check_cluster(&rpc, cluster_kind).await?;
+            if !Arc::ptr_eq(&rpc, &send_rpc) {
+                common::genesis::check_cluster(&send_rpc, cluster_kind).await?;
+            }
         }
 
-        if cluster.clone().use_nextblock {
-            let nextblock_client = NextBlockClient::new(
-                cluster.clone().rpc_url,
-                cluster.clone().nextblock_url,
-                cluster.clone().nextblock_auth_token
-            );
+        let (fee_clients, fee_client_startup) = connect_fee_clients(cluster, DEFAULT_CONNECT_TIMEOUT).await;
 
-            fee_clients.push(Arc::new(nextblock_client));
+        if cluster.verify_program_on_startup {
+            match pumpfun::idl::verify_program_compatibility(&rpc, &constants::accounts::PUMPFUN).await {
+                Ok(report) if !report.idl_found => tracing::warn!(program = %report.program_id, "program publishes no IDL, compatibility not verified"),
+                Ok(report) => {
+                    for mismatch in &report.mismatches {
+                        tracing::warn!(program = %report.program_id, %mismatch, "program drifted from the SDK");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "failed to verify program compatibility"),
+            }
         }
 
-        Self {
+        Ok(Self {
             payer,
-            rpc: Arc::new(rpc),
+            rpc,
+            send_rpc,
             fee_clients,
+            fee_client_startup: Arc::new(fee_client_startup),
             priority_fee: cluster.clone().priority_fee,
             cluster: cluster.clone(),
+            trade_tag: None,
+            tasks: Arc::new(TaskRegistry::new()),
+            known_atas: Arc::new(RwLock::new(HashSet::new())),
+            known_ata_counters: Arc::new(CacheCounters::new()),
+            mint_guard: None,
+            drain: Arc::new(Drain::new()),
+            mint_locks: MintLocks::new(),
+            mint_lock_timeout: DEFAULT_MINT_LOCK_TIMEOUT,
+        })
+    }
+
+    /// How starting each enabled fee client went
+    #[inline]
+    pub fn fee_client_startup(&self) -> &[FeeClientStartup] {
+        &self.fee_client_startup
+    }
+
+    /// Tag every subsequent trade of this client with `trade_tag`
+    pub fn set_trade_tag(&mut self, trade_tag: Option<TradeTag>) {
+        self.trade_tag = trade_tag;
+    }
+
+    /// Returns a client sharing this one's connections and tasks but tagging its trades with
+    /// `trade_tag`, for overriding the tag on a single call
+    pub fn with_trade_tag(&self, trade_tag: Option<TradeTag>) -> Self {
+        let mut client = self.clone();
+        client.trade_tag = trade_tag;
+        client
+    }
+
+    /// Returns a client sharing this one's connections and tasks but buying each mint at most
+    /// once per `mint_guard`; share one guard between the clients of all event sources
+    pub fn with_mint_guard(&self, mint_guard: Option<MintOnceGuard>) -> Self {
+        let mut client = self.clone();
+        client.mint_guard = mint_guard;
+        client
+    }
+
+    /// Returns a client sharing this one's connections, tasks and mint locks but waiting up to
+    /// `timeout` for another trade of the same mint to send before failing with [`MintBusy`]
+    pub fn with_mint_lock_timeout(&self, timeout: Duration) -> Self {
+        let mut client = self.clone();
+        client.mint_lock_timeout = timeout;
+        client
+    }
+
+    /// Runs `trade` holding the payer's lock of `mint` until it has sent its transactions,
+    /// see [`trade::mint_lock`]
+    async fn locked<T>(&self, mint: &Pubkey, trade: impl Future<Output = Result<T, anyhow::Error>>) -> Result<T, anyhow::Error> {
+        let lock = self.mint_locks.acquire(self.payer.pubkey(), *mint, self.mint_lock_timeout).await?;
+        mint_lock::hold(lock, trade).await
+    }
+
+    /// The read and send clients, for the trades sent over plain RPC
+    fn rpc_clients(&self) -> RpcClients {
+        RpcClients::new(self.rpc.clone(), self.send_rpc.clone())
+    }
+
+    /// Claims `mint` in the mint guard, failing if it was bought before
+    fn claim_mint(&self, mint: &Pubkey) -> Result<(), anyhow::Error> {
+        match &self.mint_guard {
+            Some(guard) if !guard.try_acquire(*mint) => Err(anyhow::anyhow!("Buy of mint {} was already attempted", mint)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Lets `mint` be bought again if its buy was cancelled or unaffordable before anything was sent
+    fn release_unsent(&self, mint: &Pubkey, result: &Result<LatencyReport, anyhow::Error>) {
+        if let (Some(guard), Err(e)) = (&self.mint_guard, result) {
+            if Cancelled::find(e) == Some(&Cancelled::BeforeSubmission) || InsufficientSolBalance::find(e).is_some() || BuyTooSmall::find(e).is_some() || MintBusy::find(e).is_some() {
+                guard.release(mint);
+            }
         }
     }
 
+    /// Adds the trade tag memo, if any, after the caller's extra instructions
+    fn tagged(&self, extra_instructions: Option<Vec<Instruction>>) -> Option<Vec<Instruction>> {
+        match &self.trade_tag {
+            Some(tag) => {
+                let mut instructions = extra_instructions.unwrap_or_default();
+                instructions.push(tag.instruction());
+                Some(instructions)
+            }
+            None => extra_instructions,
+        }
+    }
+
+    /// Stops accepting trades on this client and its clones; see [`trade::drain`]
+    ///
+    /// Buys, sells and creates called afterwards fail with
+    /// [`ShuttingDown`](trade::drain::ShuttingDown). Trades already running keep sending and
+    /// confirming their transactions.
+    pub fn begin_drain(&self) {
+        self.drain.begin();
+    }
+
+    /// Waits up to [`DEFAULT_DRAIN_TIMEOUT`] for the trades in flight to finish, failing
+    /// with the signatures still unresolved after it
+    pub async fn drain_complete(&self) -> Result<(), DrainTimedOut> {
+        self.drain_complete_with_timeout(DEFAULT_DRAIN_TIMEOUT).await
+    }
+
+    pub async fn drain_complete_with_timeout(&self, timeout: std::time::Duration) -> Result<(), DrainTimedOut> {
+        self.drain.wait(timeout).await
+    }
+
+    /// Stop background tasks and subscriptions started through this client
+    ///
+    /// Drains first: new trades are refused and the trades in flight are awaited for up to
+    /// [`DEFAULT_DRAIN_TIMEOUT`]; call [`PumpFun::drain_complete_with_timeout`] before for
+    /// another limit. Then aborts every tracked task, including subscriptions started with
+    /// `tokens_subscription`, and waits for them to finish. A drain that timed out fails
+    /// with [`DrainTimedOut`] once the tasks are stopped. The fee clients' connections are
+    /// closed once the last clone of this client is dropped. When the last clone is dropped
+    /// without calling this, the tasks are still aborted, but without waiting for them.
+    pub async fn shutdown(self) -> Result<(), anyhow::Error> {
+        self.shutdown_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT).await
+    }
+
+    pub async fn shutdown_with_timeout(self, timeout: std::time::Duration) -> Result<(), anyhow::Error> {
+        let result = self.drain.shutdown(&self.tasks, DEFAULT_DRAIN_TIMEOUT, timeout).await;
+        drop(self.fee_clients);
+        result
+    }
+
     /// Create a new token
     pub async fn create(
         &self,
         mint: Keypair,
         ipfs: TokenMetadataIPFS,
-    ) -> Result<(), anyhow::Error> {
+        metadata_policy: MetadataPolicy,
+    ) -> Result<CreateResult, anyhow::Error> {
+        let _in_flight = self.drain.admit()?;
         pumpfun::create::create(
-            self.rpc.clone(),
+            self.rpc_clients(),
             self.payer.clone(),
             mint,
             ipfs,
             self.priority_fee.clone(),
+            self.cluster.rpc_send_options,
+            metadata_policy,
+            self.tagged(None),
         ).await 
     }
 
-    pub async fn create_and_buy(
+    /// Create a new token with a mint keypair the SDK generates, ending with `vanity_suffix`
+    /// if given; the keypair is returned in the result
+    pub async fn create_with_generated_mint(
         &self,
-        mint: Keypair,
+        vanity_suffix: Option<&str>,
         ipfs: TokenMetadataIPFS,
-        amount_sol: u64,
-        slippage_basis_points: Option<u64>,
-    ) -> Result<(), anyhow::Error> {
-        pumpfun::create::create_and_buy(
-            self.rpc.clone(),
+        metadata_policy: MetadataPolicy,
+    ) -> Result<CreateResult, anyhow::Error> {
+        let _in_flight = self.drain.admit()?;
+        pumpfun::create::create_with_generated_mint(
+            self.rpc_clients(),
             self.payer.clone(),
-            mint,
+            vanity_suffix,
             ipfs,
-            amount_sol,
-            slippage_basis_points,
             self.priority_fee.clone(),
+            self.cluster.rpc_send_options,
+            metadata_policy,
+            self.tagged(None),
         ).await
     }
 
-    pub async fn create_and_buy_with_tip(
+    /// Create a token and buy it in one transaction per `params`, see [`CreateParams`]
+    pub async fn create_and_buy(&self, mut params: CreateParams) -> Result<CreateResult, anyhow::Error> {
+        let _in_flight = self.drain.admit()?;
+        let context = self.execution_context(std::mem::take(&mut params.overrides));
+        context.buy_floor.check(params.amount_sol)?;
+        let extra_instructions = params.take_extra_instructions();
+        pumpfun::create::create_and_buy(
+            self.rpc_clients(),
+            context.payer.clone(),
+            params.mint,
+            params.ipfs,
+            params.amount_sol,
+            params.slippage_basis_points,
+            context.priority_fee,
+            context.rpc_send_options,
+            params.metadata_policy,
+            params.mint_check,
+            params.preflight,
+            Some(context.tagged(extra_instructions)),
+            params.launch_budget,
+        ).await
+    }
+
+    /// Like [`PumpFun::create_and_buy`], paid by `payer` and sent through the fee clients
+    pub async fn create_and_buy_with_tip(&self, payer: Arc<Keypair>, mut params: CreateParams) -> Result<CreateResult, anyhow::Error> {
+        let _in_flight = self.drain.admit()?;
+        let context = self.execution_context(std::mem::take(&mut params.overrides));
+        context.buy_floor.check(params.amount_sol)?;
+        let extra_instructions = params.take_extra_instructions();
+        pumpfun::create::create_and_buy_with_tip(
+            self.rpc_clients(),
+            context.fee_clients.clone(),
+            payer,
+            params.mint,
+            params.ipfs,
+            params.amount_sol,
+            params.slippage_basis_points,
+            context.priority_fee,
+            context.rpc_send_options,
+            params.metadata_policy,
+            params.mint_check,
+            params.preflight,
+            Some(context.tagged(extra_instructions)),
+            params.launch_budget,
+        ).await
+    }
+
+    /// Whether the payer's create of `mint`, sent as `signature` with `recent_blockhash`, landed
+    /// after its confirmation timed out; see [`pumpfun::recovery`]
+    pub async fn check_create_outcome(&self, mint: &Pubkey, signature: Signature, recent_blockhash: Hash) -> Result<CreateOutcome, anyhow::Error> {
+        pumpfun::recovery::check_create_outcome(&self.rpc, &self.payer.pubkey(), mint, signature, recent_blockhash).await
+    }
+
+    /// Buy `amount_sol` of `mint` in its own transaction after a `create_and_buy` whose create
+    /// landed without the buy; fails without buying if the create didn't land or the payer
+    /// already holds the tokens
+    pub async fn resume_create_and_buy(
         &self,
-        payer: Arc<Keypair>, 
-        mint: Keypair,
-        ipfs: TokenMetadataIPFS,
+        mint: Pubkey,
         amount_sol: u64,
         slippage_basis_points: Option<u64>,
-    ) -> Result<(Signature, Pubkey), anyhow::Error> {
-        pumpfun::create::create_and_buy_with_tip(
-            self.rpc.clone(),
-            self.fee_clients.clone(),
-            payer,
+        extra_instructions: Option<Vec<Instruction>>,
+    ) -> Result<LatencyReport, anyhow::Error> {
+        let _in_flight = self.drain.admit()?;
+        self.locked(&mint, pumpfun::recovery::resume_create_and_buy(
+            self.rpc_clients(),
+            self.payer.clone(),
             mint,
-            ipfs,
             amount_sol,
             slippage_basis_points,
+            self.cluster.slippage_mode,
             self.priority_fee.clone(),
-        ).await
+            self.cluster.rpc_send_options,
+            self.tagged(extra_instructions),
+        )).await
     }
     
     /// Buy tokens
+    ///
+    /// Cancelling `cancel` stops the buy, see [`trade::cancel`]. Unless the cluster skips the
+    /// balance check, a buy the payer can't afford fails with [`InsufficientSolBalance`]. A
+    /// buy cancelled or failing the check before sending anything can be attempted again
+    /// despite the mint guard. With the cluster's `retry_fee_recipient_rotation`, a buy
+    /// rejected for a rotated fee recipient is rebuilt and sent once more. A buy below the
+    /// cluster's `buy_floor` fails with [`BuyTooSmall`](pumpfun::common::BuyTooSmall).
     pub async fn buy(
         &self,
         mint: Pubkey,
         amount_sol: u64,
         slippage_basis_points: Option<u64>,
-    ) -> Result<(), anyhow::Error> {
-        pumpfun::buy::buy(
+        extra_instructions: Option<Vec<Instruction>>,
+        cancel: Option<CancellationToken>,
+    ) -> Result<LatencyReport, anyhow::Error> {
+        let params = TradeParams { slippage_basis_points, extra_instructions: extra_instructions.unwrap_or_default(), ..TradeParams::buy(mint, amount_sol) };
+        self.execute(params.overrides(TradeOverrides { cancel, ..Default::default() })).await
+    }
+
+    /// Buy tokens using Jito
+    ///
+    /// With `land_by_slot`, the buy is not sent once the current slot is past it, and the
+    /// report's `deadline` tells whether it landed in time; see [`trade::deadline`].
+    pub async fn buy_with_tip(
+        &self,
+        mint: Pubkey,
+        amount_sol: u64,
+        slippage_basis_points: Option<u64>,
+        submission_options: Option<SubmissionOptions>,
+        extra_instructions: Option<Vec<Instruction>>,
+        cancel: Option<CancellationToken>,
+        land_by_slot: Option<u64>,
+    ) -> Result<LatencyReport, anyhow::Error> {
+        let params = TradeParams { slippage_basis_points, extra_instructions: extra_instructions.unwrap_or_default(), ..TradeParams::buy(mint, amount_sol) };
+        self.execute(params.with_tip().overrides(TradeOverrides { submission_options, cancel, land_by_slot, ..Default::default() })).await
+    }
+
+    /// Buy or sell per `params`, see [`trade::params`]
+    ///
+    /// Buys go through the mint guard and the buy floor like [`PumpFun::buy`]; sells forget a
+    /// pre-created token account like [`PumpFun::sell`]. Trades of the same mint take turns.
+    pub async fn execute(&self, mut params: TradeParams) -> Result<LatencyReport, anyhow::Error> {
+        let _in_flight = self.drain.admit()?;
+        let (mint, slippage_basis_points) = (params.mint, params.slippage_basis_points);
+        let extra_instructions = params.take_extra_instructions();
+        let context = self.execution_context(params.overrides);
+        match params.action {
+            TradeAction::Buy { amount_sol } => {
+                context.buy_floor.check(amount_sol)?;
+                self.claim_mint(&mint)?;
+                let result = if params.with_tip {
+                    let context = context.with_span(tracing::info_span!("buy", %mint, amount_sol));
+                    self.locked(&mint, pumpfun::buy::buy_with_tip(&context, mint, amount_sol, slippage_basis_points, self.ata_exists(&mint), extra_instructions)).await
+                } else {
+                    self.locked(&mint, retry_on_rotation(context.retry_fee_recipient_rotation, || pumpfun::buy::buy(
+                        self.rpc_clients(),
+                        context.payer.clone(),
+                        mint,
+                        amount_sol,
+                        slippage_basis_points,
+                        context.slippage_mode,
+                        context.partial_fill,
+                        context.priority_fee,
+                        context.rpc_send_options,
+                        self.ata_exists(&mint),
+                        context.skip_balance_check,
+                        Some(context.tagged(extra_instructions.clone())),
+                        context.cancel.clone(),
+                    ))).await
+                };
+                self.release_unsent(&mint, &result);
+                result
+            }
+            TradeAction::Sell { amount_token } => {
+                self.forget_ata(&mint);
+                if params.with_tip {
+                    let context = context.with_span(tracing::info_span!("sell", %mint, ?amount_token));
+                    self.locked(&mint, pumpfun::sell::sell_with_tip(&context, mint, amount_token, slippage_basis_points, extra_instructions)).await
+                } else {
+                    self.locked(&mint, retry_on_rotation(context.retry_fee_recipient_rotation, || pumpfun::sell::sell(
+                        self.rpc_clients(),
+                        context.payer.clone(),
+                        mint,
+                        amount_token,
+                        slippage_basis_points,
+                        context.sell_floor,
+                        context.priority_fee,
+                        context.rpc_send_options,
+                        Some(context.tagged(extra_instructions.clone())),
+                        context.cancel.clone(),
+                    ))).await
+                }
+            }
+            TradeAction::SellPercent { percent } => {
+                self.forget_ata(&mint);
+                if params.with_tip {
+                    let context = context.with_span(tracing::info_span!("sell", %mint, percent));
+                    self.locked(&mint, pumpfun::sell::sell_by_percent_with_tip(&context, mint, percent, slippage_basis_points, extra_instructions)).await
+                } else {
+                    self.locked(&mint, retry_on_rotation(context.retry_fee_recipient_rotation, || pumpfun::sell::sell_by_percent(
+                        self.rpc_clients(),
+                        context.payer.clone(),
+                        mint,
+                        percent,
+                        slippage_basis_points,
+                        context.sell_floor,
+                        context.priority_fee,
+                        context.rpc_send_options,
+                        Some(context.tagged(extra_instructions.clone())),
+                        context.cancel.clone(),
+                    ))).await
+                }
+            }
+        }
+    }
+
+    /// Largest `amount_sol` the payer can currently buy `mint` with, its fees, the token
+    /// account rent and, `with_tip`, the cluster's buy tip included
+    pub async fn max_buyable_sol(&self, mint: &Pubkey, slippage_basis_points: Option<u64>, with_tip: bool) -> Result<u64, anyhow::Error> {
+        let tip = if with_tip { self.priority_fee.buy_tip_fee.lamports() } else { 0 };
+        pumpfun::balance::max_buyable_sol(
+            self.rpc.clone(),
+            self.payer.clone(),
+            *mint,
+            slippage_basis_points.unwrap_or(constants::trade::DEFAULT_SLIPPAGE),
+            self.cluster.slippage_mode,
+            &self.priority_fee,
+            tip,
+        ).await
+    }
+
+    /// Buy `total_sol` lamports of `mint` in tranches of at most `max_impact_bps` price impact,
+    /// one after the other or as a single Jito bundle per `mode`
+    pub async fn buy_split(
+        &self,
+        mint: Pubkey,
+        total_sol: u64,
+        max_impact_bps: u64,
+        mode: SplitMode,
+        slippage_basis_points: Option<u64>,
+    ) -> Result<SplitBuyReport, anyhow::Error> {
+        let _in_flight = self.drain.admit()?;
+        self.claim_mint(&mint)?;
+        let context = self.execution_context(TradeOverrides::default())
+            .with_span(tracing::info_span!("buy_split", %mint, total_sol));
+        let report = self.locked(&mint, pumpfun::split::buy_split(
+            &context,
+            mint,
+            total_sol,
+            max_impact_bps,
+            mode,
+            slippage_basis_points,
+            self.ata_exists(&mint),
+            None,
+        ).instrument(context.span.clone())).await?;
+        if report.filled().any(|tranche| tranche.wallet == self.payer.pubkey()) {
+            self.known_atas.write().unwrap().insert(mint);
+        }
+        Ok(report)
+    }
+
+    /// Describe the transaction `buy` would send, without sending it
+    pub async fn explain_buy(&self, mint: Pubkey, amount_sol: u64, slippage_basis_points: Option<u64>) -> Result<ExplainedTransaction, anyhow::Error> {
+        pumpfun::explain::explain_buy(
             self.rpc.clone(),
             self.payer.clone(),
             mint,
             amount_sol,
             slippage_basis_points,
-            self.priority_fee.clone(),
+            self.cluster.slippage_mode,
+            self.cluster.partial_fill,
+            &self.priority_fee,
+            self.ata_exists(&mint),
+            self.tagged(None),
         ).await
     }
 
-    /// Buy tokens using Jito
-    pub async fn buy_with_tip(
+    /// Describe the transaction `sell` would send, without sending it
+    pub async fn explain_sell(&self, mint: Pubkey, amount_token: Option<u64>, slippage_basis_points: Option<u64>) -> Result<ExplainedTransaction, anyhow::Error> {
+        pumpfun::explain::explain_sell(
+            self.rpc.clone(),
+            self.payer.clone(),
+            mint,
+            amount_token,
+            slippage_basis_points,
+            self.cluster.sell_floor,
+            &self.priority_fee,
+            self.tagged(None),
+        ).await
+    }
+
+    /// Describe the transaction `create_and_buy` would send, without sending it
+    pub async fn explain_create_and_buy(
         &self,
-        mint: Pubkey,
+        mint: &Keypair,
+        ipfs: TokenMetadataIPFS,
         amount_sol: u64,
         slippage_basis_points: Option<u64>,
-    ) -> Result<(), anyhow::Error> {
-        pumpfun::buy::buy_with_tip(
+        metadata_policy: MetadataPolicy,
+    ) -> Result<ExplainedTransaction, anyhow::Error> {
+        pumpfun::explain::explain_create_and_buy(
             self.rpc.clone(),
-            self.fee_clients.clone(),
             self.payer.clone(),
-            mint,
+            Arc::new(mint.insecure_clone()),
+            ipfs,
             amount_sol,
             slippage_basis_points,
-            self.priority_fee.clone(),
+            &self.priority_fee,
+            metadata_policy,
+            self.tagged(None),
         ).await
     }
 
+    /// Settings a trade through this client runs with, after applying `overrides`
+    ///
+    /// The `*_with_tip` methods build one per call. Pass one to `pumpfun::buy::buy_with_tip`
+    /// or `pumpfun::sell::sell_with_tip` directly to override more than the submission options.
+    pub fn execution_context(&self, overrides: TradeOverrides) -> ExecutionContext {
+        let mut context = ExecutionContext::new(self.payer.clone(), self.rpc.clone(), self.fee_clients.clone(), self.priority_fee).with_send_rpc(self.send_rpc.clone());
+        context.trade_tag = self.trade_tag.clone();
+        context.rpc_send_options = self.cluster.rpc_send_options;
+        context.slippage_mode = self.cluster.slippage_mode;
+        context.skip_balance_check = self.cluster.skip_balance_check;
+        context.sell_floor = self.cluster.sell_floor;
+        context.partial_fill = self.cluster.partial_fill;
+        context.buy_floor = self.cluster.buy_floor;
+        context.retry_fee_recipient_rotation = self.cluster.retry_fee_recipient_rotation;
+        context.drain = Some(self.drain.clone());
+        context.with_overrides(overrides)
+    }
+
+    /// Create the payer's token accounts for `mints` ahead of trading them
+    ///
+    /// Accounts that already exist are left alone; missing ones are created in as few
+    /// transactions as fit. Later buys of these mints through this client, and its clones,
+    /// leave out the token account creation. Selling closes the account, so a mint has to be
+    /// pre-created again after it was sold.
+    pub async fn precreate_atas(&self, mints: &[Pubkey]) -> Result<Vec<PrecreatedAta>, anyhow::Error> {
+        let atas = pumpfun::ata::precreate_atas(self.rpc_clients(), self.payer.clone(), mints, self.priority_fee, self.cluster.rpc_send_options).await?;
+        self.known_atas.write().unwrap().extend(atas.iter().filter(|ata| ata.exists()).map(|ata| ata.mint));
+        Ok(atas)
+    }
+
+    fn ata_exists(&self, mint: &Pubkey) -> bool {
+        let known = self.known_atas.read().unwrap().contains(mint);
+        self.known_ata_counters.record(known.then_some(())).is_some()
+    }
+
+    /// Forgets a pre-created token account, which the sell about to be sent closes
+    fn forget_ata(&self, mint: &Pubkey) {
+        self.known_atas.write().unwrap().remove(mint);
+    }
+
     /// Sell tokens
     pub async fn sell(
         &self,
         mint: Pubkey,
         amount_token: Option<u64>,
         slippage_basis_points: Option<u64>,
-    ) -> Result<(), anyhow::Error> {
-        pumpfun::sell::sell(
-            self.rpc.clone(),
-            self.payer.clone(),
-            mint.clone(),
-            amount_token,
-            slippage_basis_points,
-            self.priority_fee.clone(),
-        ).await
+        extra_instructions: Option<Vec<Instruction>>,
+        cancel: Option<CancellationToken>,
+    ) -> Result<LatencyReport, anyhow::Error> {
+        let params = TradeParams { slippage_basis_points, extra_instructions: extra_instructions.unwrap_or_default(), ..TradeParams::new(mint, TradeAction::Sell { amount_token }) };
+        self.execute(params.overrides(TradeOverrides { cancel, ..Default::default() })).await
     }
 
     /// Sell tokens by percentage
@@ -212,15 +679,10 @@ impl PumpFun {
         mint: Pubkey,
         percent: u64,
         slippage_basis_points: Option<u64>,
-    ) -> Result<(), anyhow::Error> {
-        pumpfun::sell::sell_by_percent(
-            self.rpc.clone(),
-            self.payer.clone(),
-            mint.clone(),
-            percent,
-            slippage_basis_points,
-            self.priority_fee.clone(),
-        ).await
+        extra_instructions: Option<Vec<Instruction>>,
+    ) -> Result<LatencyReport, anyhow::Error> {
+        let params = TradeParams { slippage_basis_points, extra_instructions: extra_instructions.unwrap_or_default(), ..TradeParams::sell_percent(mint, percent) };
+        self.execute(params).await
     }
 
     pub async fn sell_by_percent_with_tip(
@@ -228,16 +690,11 @@ impl PumpFun {
         mint: Pubkey,
         percent: u64,
         slippage_basis_points: Option<u64>,
-    ) -> Result<(), anyhow::Error> {
-        pumpfun::sell::sell_by_percent_with_tip(
-            self.rpc.clone(),
-            self.fee_clients.clone(),
-            self.payer.clone(),
-            mint,
-            percent,
-            slippage_basis_points,
-            self.priority_fee.clone(),
-        ).await
+        submission_options: Option<SubmissionOptions>,
+        extra_instructions: Option<Vec<Instruction>>,
+    ) -> Result<LatencyReport, anyhow::Error> {
+        let params = TradeParams { slippage_basis_points, extra_instructions: extra_instructions.unwrap_or_default(), ..TradeParams::sell_percent(mint, percent) };
+        self.execute(params.with_tip().overrides(TradeOverrides { submission_options, ..Default::default() })).await
     }
 
     /// Sell tokens using Jito
@@ -246,30 +703,171 @@ impl PumpFun {
         mint: Pubkey,
         amount_token: Option<u64>,
         slippage_basis_points: Option<u64>,
-    ) -> Result<(), anyhow::Error> {
-        pumpfun::sell::sell_with_tip(
-            self.rpc.clone(),
-            self.fee_clients.clone(),
-            self.payer.clone(),
-            mint,
-            amount_token,
-            slippage_basis_points,
-            self.priority_fee.clone(),
-        ).await
+        submission_options: Option<SubmissionOptions>,
+        extra_instructions: Option<Vec<Instruction>>,
+        cancel: Option<CancellationToken>,
+    ) -> Result<LatencyReport, anyhow::Error> {
+        let params = TradeParams { slippage_basis_points, extra_instructions: extra_instructions.unwrap_or_default(), ..TradeParams::new(mint, TradeAction::Sell { amount_token }) };
+        self.execute(params.with_tip().overrides(TradeOverrides { submission_options, cancel, ..Default::default() })).await
     }
 
+    /// Subscribe to Pump.fun events over WebSocket at `commitment`, an [`EventCommitment`] or
+    /// `CommitmentConfig`; fails with `SubscriptionRejected` if the provider refuses it
     #[inline]
     pub async fn tokens_subscription<F>(
         &self,
         ws_url: &str,
-        commitment: CommitmentConfig,
+        commitment: impl Into<EventCommitment>,
+        callback: F,
+        bot_wallet: Option<Pubkey>,
+    ) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+    where
+        F: Fn(PumpfunEvent) + Send + Sync + 'static,
+    {
+        let handle = logs_subscribe::tokens_subscription(ws_url, commitment, callback, bot_wallet).await?;
+        self.tasks.track_subscription(handle.task.abort_handle());
+        Ok(handle)
+    }
+
+    /// Like [`PumpFun::tokens_subscription`], recording the delivery latency of every event in
+    /// `latency`, see [`common::event_latency`]
+    pub async fn tokens_subscription_with_latency<F>(
+        &self,
+        ws_url: &str,
+        commitment: impl Into<EventCommitment>,
         callback: F,
         bot_wallet: Option<Pubkey>,
+        latency: Arc<EventLatency>,
+    ) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+    where
+        F: Fn(PumpfunEvent) + Send + Sync + 'static,
+    {
+        let handle = logs_subscribe::tokens_subscription_with_latency(ws_url, commitment, callback, bot_wallet, latency).await?;
+        self.tasks.track_subscription(handle.task.abort_handle());
+        Ok(handle)
+    }
+
+    /// Like [`PumpFun::tokens_subscription`], tracking the delivered slots in `watermarks`, see
+    /// [`common::watermark`]
+    pub async fn tokens_subscription_with_watermarks<F>(
+        &self,
+        ws_url: &str,
+        commitment: impl Into<EventCommitment>,
+        callback: F,
+        bot_wallet: Option<Pubkey>,
+        watermarks: Arc<Watermarks>,
+    ) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+    where
+        F: Fn(PumpfunEvent) + Send + Sync + 'static,
+    {
+        let handle = logs_subscribe::tokens_subscription_with_watermarks(ws_url, commitment, callback, bot_wallet, watermarks).await?;
+        self.tasks.track_subscription(handle.task.abort_handle());
+        Ok(handle)
+    }
+
+    /// A latency recorder looking block times up on this client's RPC, to share between the
+    /// subscriptions being compared
+    pub fn event_latency(&self) -> Arc<EventLatency> {
+        EventLatency::new(self.rpc.clone())
+    }
+
+    /// Subscribe over WebSocket to the Pump.fun trades of `wallets`, including trades made
+    /// outside the SDK; trades carrying this client's trade tag are labeled as SDK initiated
+    ///
+    /// With Geyser access, `YellowstoneGrpc::subscribe_wallet_activity` delivers the same
+    /// events with a single subscription.
+    pub async fn subscribe_wallet_activity<F>(
+        &self,
+        ws_url: &str,
+        commitment: impl Into<EventCommitment>,
+        wallets: HashSet<Pubkey>,
+        callback: F,
     ) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
     where
         F: Fn(PumpfunEvent) + Send + Sync + 'static,
     {
-        logs_subscribe::tokens_subscription(ws_url, commitment, callback, bot_wallet).await
+        let handle = logs_subscribe::wallet_activity_subscription(ws_url, commitment, wallets, self.trade_tag.clone(), callback).await?;
+        self.tasks.track_subscription(handle.task.abort_handle());
+        Ok(handle)
+    }
+
+    /// Subscribe over the cluster's `ws_url` to the SOL balance changes of `wallets` of at
+    /// least [`DEFAULT_BALANCE_THRESHOLD`] lamports; see [`common::balance_watch`]
+    pub async fn subscribe_balance_changes<F>(&self, wallets: HashSet<Pubkey>, callback: F) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+    where
+        F: Fn(BalanceChange) + Send + Sync + 'static,
+    {
+        self.subscribe_balance_changes_with_threshold(wallets, DEFAULT_BALANCE_THRESHOLD, callback).await
+    }
+
+    /// Like [`PumpFun::subscribe_balance_changes`], reporting changes of at least `threshold`
+    /// lamports
+    pub async fn subscribe_balance_changes_with_threshold<F>(&self, wallets: HashSet<Pubkey>, threshold: u64, callback: F) -> Result<SubscriptionHandle, Box<dyn std::error::Error>>
+    where
+        F: Fn(BalanceChange) + Send + Sync + 'static,
+    {
+        if self.cluster.ws_url.is_empty() {
+            return Err("subscribe_balance_changes needs `ws_url` on the cluster".into());
+        }
+        let handle = common::balance_watch::balance_changes_subscription(&self.cluster.ws_url, self.rpc.clone(), self.cluster.commitment, wallets, threshold, callback).await?;
+        self.tasks.track_subscription(handle.task.abort_handle());
+        Ok(handle)
+    }
+
+    /// Route the Pump.fun events of `mints` to `per_mint` over one shared subscription, through
+    /// the cluster's `grpc_url` when set and its `ws_url` otherwise
+    ///
+    /// Mints can be added and removed on the returned watchlist; removing the last one stops
+    /// the subscription.
+    pub async fn watch_mints<F>(&self, mints: Vec<Pubkey>, per_mint: F) -> Result<MintWatchlist, Box<dyn std::error::Error>>
+    where
+        F: Fn(Pubkey, PumpfunEvent) + Send + Sync + 'static,
+    {
+        let commitment = EventCommitment::from(self.cluster.commitment);
+        let source = if !self.cluster.grpc_url.is_empty() {
+            WatchSource::Grpc(grpc::YellowstoneGrpc::new(self.cluster.grpc_url.clone()).with_commitment(commitment))
+        } else if !self.cluster.ws_url.is_empty() {
+            WatchSource::WebSocket { ws_url: self.cluster.ws_url.clone(), commitment }
+        } else {
+            return Err("watch_mints needs `grpc_url` or `ws_url` on the cluster".into());
+        };
+        let watchlist = MintWatchlist::subscribe(source, mints, per_mint).await?;
+        if let Some(subscription) = watchlist.abort_handle() {
+            self.tasks.track_subscription(subscription);
+        }
+        Ok(watchlist)
+    }
+
+    /// Compare the balances `tracker` expects the payer to hold with the chain, in a few
+    /// batched account reads, and return the mismatches; with `auto_correct` the tracker is
+    /// set to the balances read
+    pub async fn reconcile_positions(&self, tracker: &PositionTracker, auto_correct: bool) -> Result<Vec<ReconciliationDiff>, anyhow::Error> {
+        trade::reconcile::reconcile_positions(&self.rpc, &self.payer.pubkey(), tracker, auto_correct).await
+    }
+
+    /// Reconcile `tracker` in the background every `interval`, jittered by up to a tenth,
+    /// passing non-empty mismatches to `on_diffs`
+    ///
+    /// Failed reads are logged and retried on the next run. The task stops on `shutdown` or
+    /// when the returned handle is aborted.
+    pub fn spawn_position_reconciler<F>(&self, tracker: PositionTracker, interval: std::time::Duration, auto_correct: bool, on_diffs: F) -> tokio::task::AbortHandle
+    where
+        F: Fn(Vec<ReconciliationDiff>) + Send + Sync + 'static,
+    {
+        let (rpc, owner) = (self.rpc.clone(), self.payer.pubkey());
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(trade::reconcile::jittered(interval)).await;
+                match trade::reconcile::reconcile_positions(&rpc, &owner, &tracker, auto_correct).await {
+                    Ok(diffs) if !diffs.is_empty() => on_diffs(diffs),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!(error = %e, "position reconciliation failed"),
+                }
+            }
+        });
+        let handle = task.abort_handle();
+        self.tasks.track(task);
+        handle
     }
 
     #[inline]
@@ -277,6 +875,95 @@ impl PumpFun {
         subscription_handle.shutdown().await;
     }
 
+    /// Load the bonding curve, metadata and, per `options`, holders and recent trades of `mint`
+    /// concurrently; components that fail to load are listed in `TokenSnapshot::errors`
+    pub async fn get_token_snapshot(&self, mint: &Pubkey, options: SnapshotOptions) -> TokenSnapshot {
+        pumpfun::snapshot::get_token_snapshot(&self.rpc, mint, options).await
+    }
+
+    /// Tokens created by `creator` within their latest `limit` transactions; cached for a few
+    /// minutes
+    pub async fn get_creator_history(&self, creator: &Pubkey, limit: usize) -> Result<CreatorHistory, anyhow::Error> {
+        pumpfun::creator::get_creator_history(&self.rpc, creator, limit).await
+    }
+
+    /// Creation slot and age in slots of `mint`, from the create event if a subscription saw
+    /// it and from the bonding curve's transaction history otherwise; see
+    /// [`pumpfun::token_age`]
+    pub async fn get_token_age(&self, mint: &Pubkey) -> Result<TokenAge, anyhow::Error> {
+        pumpfun::token_age::get_token_age(&self.rpc, mint).await
+    }
+
+    /// Whether `creator` launched at least `threshold` tokens recently
+    pub async fn is_serial_deployer(&self, creator: &Pubkey, threshold: usize) -> Result<bool, anyhow::Error> {
+        pumpfun::creator::is_serial_deployer(&self.rpc, creator, threshold).await
+    }
+
+    /// Hits, misses, entries and the oldest entry's age of each cache: the global account,
+    /// token program and creator history caches, the bonding curve cache of this client's
+    /// RPC, and this client's known token accounts
+    pub async fn cache_stats(&self) -> Vec<CacheStats> {
+        let mut stats = pumpfun::common::account_cache_stats().await;
+        stats.push(pumpfun::curve_cache::bonding_curve_cache(&self.rpc).stats());
+        stats.push(pumpfun::creator::creator_history_cache_stats());
+        stats.push(pumpfun::token_age::token_age_cache_stats());
+        stats.push(self.known_ata_counters.stats("known_atas", self.known_atas.read().unwrap().len(), None));
+        stats
+    }
+
+    /// Empty every cache listed by [`PumpFun::cache_stats`], e.g. after switching RPC
+    /// endpoints or when the program was upgraded
+    ///
+    /// Safe while trades are in flight: transactions are built from fresh reads, cached
+    /// values already handed out stay valid for their caller, and buys of mints whose token
+    /// account is forgotten create it idempotently. The once-per-mint buy guard is not a
+    /// cache and is kept.
+    pub async fn clear_caches(&self) {
+        pumpfun::common::clear_account_caches().await;
+        pumpfun::curve_cache::bonding_curve_cache(&self.rpc).clear();
+        pumpfun::creator::clear_creator_history_cache();
+        pumpfun::token_age::clear_token_age_cache();
+        self.known_atas.write().unwrap().clear();
+    }
+
+    /// Relay tips paid by landed transactions on the UTC days in `days`, per transport, trade
+    /// tag and send mode, with the tips of duplicate landings reported as wasted
+    ///
+    /// The ledger is process-wide, so it includes the trades of every client.
+    pub fn tip_spend_report(&self, days: impl std::ops::RangeBounds<chrono::NaiveDate>) -> TipSpendReport {
+        trade::tip_spend::tip_ledger().report(days)
+    }
+
+    /// Check the mint of `mint` for a foreign mint authority, a freeze authority and
+    /// non-standard decimals or supply
+    pub async fn check_mint_safety(&self, mint: &Pubkey) -> Result<MintSafetyReport, anyhow::Error> {
+        pumpfun::mint_safety::check_mint_safety(&self.rpc, mint).await
+    }
+
+    /// [`PumpFun::check_mint_safety`] for many mints, batched; missing or invalid mints are
+    /// reported per entry
+    pub async fn check_mints_safety(&self, mints: &[Pubkey]) -> Result<Vec<Result<MintSafetyReport, MintSafetyError>>, anyhow::Error> {
+        pumpfun::mint_safety::check_mints_safety(&self.rpc, mints).await
+    }
+
+    /// Compare the deployed Pump.fun program's IDL with the discriminators and account sizes
+    /// the SDK assumes
+    pub async fn verify_program_compatibility(&self) -> Result<CompatibilityReport, anyhow::Error> {
+        pumpfun::idl::verify_program_compatibility(&self.rpc, &constants::accounts::PUMPFUN).await
+    }
+
+    /// Checks the RPC node, every fee client and the optional gRPC and IPFS endpoints with one
+    /// cheap real call each; fee clients that failed to start are reported as failures
+    pub async fn self_test(&self, options: &SelfTestOptions) -> SelfTestReport {
+        common::self_test::self_test(&self.rpc, &self.payer, &self.fee_clients, &self.fee_client_startup, self.priority_fee, options).await
+    }
+
+    /// Bonding curve of `mint` read at fixed offsets of its raw data, see [`accounts::view`]
+    #[inline]
+    pub async fn get_bonding_curve_view(&self, mint: &Pubkey) -> Result<Arc<accounts::BondingCurveView>, anyhow::Error> {
+        pumpfun::common::get_bonding_curve_view(&self.rpc, mint).await
+    }
+
     #[inline]
     pub async fn get_sol_balance(&self, payer: &Pubkey) -> Result<u64, anyhow::Error> {
         pumpfun::common::get_sol_balance(&self.rpc, payer).await
@@ -308,6 +995,24 @@ impl PumpFun {
         self.payer.as_ref()
     }
 
+    /// Derive the bonding curve, metadata and token account addresses of `mint` for the payer,
+    /// resolving the mint's token program
+    pub async fn derive(&self, mint: &Pubkey) -> Result<DerivedAddresses, anyhow::Error> {
+        let token_program = pumpfun::common::get_token_program(&self.rpc, mint).await?;
+        DerivedAddresses::new(mint, &self.payer.pubkey(), &token_program).ok_or(anyhow::anyhow!("Bonding curve not found"))
+    }
+
+    /// Quote a dev buy of `amount_sol` lamports for a token that hasn't been created yet
+    ///
+    /// Returns the quote and the `max_sol_cost` a create-and-buy with `slippage_basis_points`
+    /// would send, computed exactly as `create_and_buy` does.
+    pub async fn get_initial_buy_quote(&self, amount_sol: u64, slippage_basis_points: Option<u64>) -> Result<(InitialBuyQuote, u64), anyhow::Error> {
+        let global_account = pumpfun::common::get_global_account(&self.rpc).await?;
+        let quote = global_account.initial_buy_quote(amount_sol).map_err(|e| anyhow::anyhow!(e))?;
+        let slippage_basis_points = slippage_basis_points.unwrap_or(constants::trade::DEFAULT_SLIPPAGE);
+        Ok((quote, quote.max_sol_cost_with(slippage_basis_points)))
+    }
+
     #[inline]
     pub fn get_token_price(&self,virtual_sol_reserves: u64, virtual_token_reserves: u64) -> f64 {
         pumpfun::common::get_token_price(virtual_sol_reserves, virtual_token_reserves)
@@ -320,6 +1025,6 @@ impl PumpFun {
 
     #[inline]
     pub async fn transfer_sol(&self, payer: &Keypair, receive_wallet: &Pubkey, amount: u64) -> Result<(), anyhow::Error> {
-        pumpfun::common::transfer_sol(&self.rpc, payer, receive_wallet, amount).await
+        pumpfun::common::transfer_sol(&self.send_rpc, payer, receive_wallet, amount).await
     }
 }
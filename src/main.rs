@@ -1,52 +1,490 @@
-use pumpfun_sdk::common::{
-    logs_events::PumpfunEvent,
-    logs_subscribe::{tokens_subscription, stop_subscription}
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Parser, Subcommand};
+use pumpfun_sdk::{
+    common::{
+        latency::LatencyReport,
+        logs_data::{CreateTokenInfo, TradeInfo},
+        logs_events::PumpfunEvent,
+        commitment::EventCommitment,
+        logs_subscribe::{stop_subscription, tokens_subscription},
+        secret::Secret,
+        self_test::SelfTestOptions,
+        Cluster,
+    },
+    constants::tokens::tokens_to_ui,
+    ipfs::{create_token_metadata, CreateTokenMetadata},
+    pumpfun::{
+        common::{get_buy_quote, get_global_account},
+        create::MintCheck,
+        metadata::MetadataPolicy,
+    },
+    trade::{own_events::OwnTradeEvent, params::CreateParams},
+    PumpFun,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_sdk::{
+    native_token::{lamports_to_sol, sol_to_lamports},
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair},
 };
-use solana_sdk::commitment_config::CommitmentConfig;
+
+/// Prefix of the environment variables the cluster settings are read from
+const ENV_PREFIX: &str = "PUMPFUN";
+
+#[derive(Parser)]
+#[command(name = "pumpfun", version, about = "Watch and trade Pump.fun tokens")]
+struct Cli {
+    /// Cluster config file; `PUMPFUN_*` environment variables override its values
+    #[arg(long, global = true, env = "PUMPFUN_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Keypair file of the wallet that pays for and signs trades
+    #[arg(long, global = true, env = "PUMPFUN_KEYPAIR")]
+    keypair: Option<PathBuf>,
+
+    /// Print machine-readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Stream token creations and trades
+    Watch(WatchArgs),
+    /// Buy a token with SOL
+    Buy {
+        mint: Pubkey,
+        /// SOL to spend
+        #[arg(value_parser = parse_sol)]
+        sol: u64,
+        #[command(flatten)]
+        slippage: SlippageArgs,
+    },
+    /// Sell a share of the wallet's token balance
+    Sell {
+        mint: Pubkey,
+        /// Share of the balance to sell
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..=100))]
+        percent: u64,
+        #[command(flatten)]
+        slippage: SlippageArgs,
+    },
+    /// Create a token, optionally buying it in the same transaction
+    Create {
+        /// JSON file with `name`, `symbol`, `description` and optional `twitter`, `telegram`, `website`
+        #[arg(long)]
+        metadata: PathBuf,
+        /// Token image uploaded to IPFS
+        #[arg(long)]
+        image: PathBuf,
+        /// SOL to buy the new token with
+        #[arg(long, value_parser = parse_sol)]
+        buy: Option<u64>,
+        #[command(flatten)]
+        slippage: SlippageArgs,
+        /// Make the token metadata immutable
+        #[arg(long)]
+        immutable: bool,
+        #[arg(long, env = "PUMPFUN_IPFS_API_KEY", hide_env_values = true)]
+        ipfs_api_key: String,
+    },
+    /// Quote the tokens a buy would return, without sending anything
+    Quote {
+        mint: Pubkey,
+        /// SOL to spend
+        #[arg(value_parser = parse_sol)]
+        sol: u64,
+        #[command(flatten)]
+        slippage: SlippageArgs,
+    },
+    /// Show the wallet's SOL balance, and its token balance with `--mint`
+    Balance {
+        #[arg(long)]
+        mint: Option<Pubkey>,
+    },
+    /// Check the RPC node, fee clients, gRPC endpoint and IPFS key with one real call each
+    SelfTest {
+        /// Yellowstone gRPC endpoint to ping
+        #[arg(long, env = "PUMPFUN_GRPC_URL")]
+        grpc_url: Option<String>,
+        #[arg(long, env = "PUMPFUN_IPFS_API_KEY", hide_env_values = true)]
+        ipfs_api_key: Option<String>,
+        /// Land a zero-value self-transfer through each fee client, paying its tip
+        #[arg(long)]
+        spend_allowed: bool,
+    },
+}
+
+#[derive(Args)]
+struct WatchArgs {
+    #[arg(long, env = "PUMPFUN_WS_URL", default_value = "wss://api.mainnet-beta.solana.com")]
+    ws_url: String,
+    #[arg(long, default_value = "confirmed")]
+    commitment: EventCommitment,
+    /// Only show events of these mints; may be repeated
+    #[arg(long = "mint")]
+    mints: Vec<Pubkey>,
+    /// Only show token creations
+    #[arg(long, conflicts_with = "trades_only")]
+    creates_only: bool,
+    /// Only show trades
+    #[arg(long)]
+    trades_only: bool,
+    /// Only show trades of at least this much SOL
+    #[arg(long, value_parser = parse_sol)]
+    min_sol: Option<u64>,
+    /// Stop after this many seconds instead of on Ctrl-C
+    #[arg(long)]
+    duration: Option<u64>,
+}
+
+#[derive(Args)]
+struct SlippageArgs {
+    /// Allowed slippage in basis points
+    #[arg(long, value_parser = clap::value_parser!(u64).range(0..=10_000))]
+    slippage_bps: Option<u64>,
+}
+
+/// Token metadata file read by `create`
+#[derive(Deserialize)]
+struct MetadataFile {
+    name: String,
+    symbol: String,
+    description: String,
+    twitter: Option<String>,
+    telegram: Option<String>,
+    website: Option<String>,
+}
+
+fn parse_sol(value: &str) -> Result<u64, String> {
+    let sol: f64 = value.parse().map_err(|_| format!("`{}` is not a SOL amount", value))?;
+    if !sol.is_finite() || sol <= 0.0 {
+        return Err("the SOL amount must be greater than zero".to_string());
+    }
+    Ok(sol_to_lamports(sol))
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Starting token subscription\n");
-
-    let ws_url = "wss://api.mainnet-beta.solana.com";
-            
-    // Set commitment
-    let commitment = CommitmentConfig::confirmed();
-    
-    // Define callback function
-    let callback = |event: PumpfunEvent| {
-        match event {
-            PumpfunEvent::NewDevTrade(trade_info) => {
-                println!("Received new dev trade event: {:?}", trade_info);
-            },
-            PumpfunEvent::NewToken(token_info) => {
-                println!("Received new token event: {:?}", token_info);
-            },
-            PumpfunEvent::NewUserTrade(trade_info) => {
-                println!("Received new trade event: {:?}", trade_info);
-            },
-            PumpfunEvent::NewBotTrade(trade_info) => {
-                println!("Received new bot trade event: {:?}", trade_info);
-            },
-            PumpfunEvent::Error(err) => {
-                println!("Received error: {}", err);
+async fn main() {
+    let cli = Cli::parse();
+    let json = cli.json;
+    if let Err(e) = run(cli).await {
+        if json {
+            println!("{}", json!({ "error": format!("{:#}", e) }));
+        } else {
+            eprintln!("Error: {:#}", e);
+        }
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), anyhow::Error> {
+    let json = cli.json;
+    match cli.command {
+        Command::Watch(args) => watch(args, json).await,
+        Command::Quote { mint, sol, slippage } => {
+            let cluster = load_cluster(&cli.config)?;
+            let rpc = pumpfun_sdk::common::SolanaRpcClient::new_with_commitment(cluster.rpc_url.clone(), cluster.commitment);
+            let global = get_global_account(&rpc).await?;
+            let quote = get_buy_quote(&rpc, &global, &mint, sol, slippage.slippage_bps, cluster.slippage_mode, None).await?;
+            print_output(
+                json,
+                json!({
+                    "mint": mint.to_string(),
+                    "sol_in": sol,
+                    "token_amount": quote.token_amount,
+                    "sol_cost": quote.sol_cost,
+                    "max_sol_cost": quote.max_sol_cost,
+                    "fill_ratio_bps": quote.fill_ratio_bps,
+                }),
+                format!(
+                    "{} SOL buys {} tokens of {}\ncost {} SOL, at most {} SOL with slippage\nfills {:.2}% of the spot amount",
+                    lamports_to_sol(sol),
+                    tokens_to_ui(quote.token_amount),
+                    mint,
+                    lamports_to_sol(quote.sol_cost),
+                    lamports_to_sol(quote.max_sol_cost),
+                    quote.fill_ratio() * 100.0
+                ),
+            );
+            Ok(())
+        }
+        command => {
+            let client = connect(&cli.config, &cli.keypair).await?;
+            let result = trade(&client, command, json).await;
+            client.shutdown().await?;
+            result
+        }
+    }
+}
+
+/// Runs the subcommands that need a wallet
+async fn trade(client: &PumpFun, command: Command, json: bool) -> Result<(), anyhow::Error> {
+    match command {
+        Command::Buy { mint, sol, slippage } => {
+            let latency = client.buy(mint, sol, slippage.slippage_bps, None, None).await?;
+            print_trade(json, "buy", &mint, &latency);
+        }
+        Command::Sell { mint, percent, slippage } => {
+            let latency = client.sell_by_percent(mint, percent, slippage.slippage_bps, None).await?;
+            print_trade(json, "sell", &mint, &latency);
+        }
+        Command::Create { metadata, image, buy, slippage, immutable, ipfs_api_key } => {
+            let file: MetadataFile = serde_json::from_str(
+                &std::fs::read_to_string(&metadata).with_context(|| format!("reading {}", metadata.display()))?,
+            )
+            .with_context(|| format!("parsing {}", metadata.display()))?;
+            if !image.is_file() {
+                bail!("image {} does not exist", image.display());
+            }
+
+            let ipfs = create_token_metadata(
+                CreateTokenMetadata {
+                    name: file.name,
+                    symbol: file.symbol,
+                    description: file.description,
+                    file: image.to_string_lossy().into_owned(),
+                    twitter: file.twitter,
+                    telegram: file.telegram,
+                    website: file.website,
+                    metadata_uri: None,
+                },
+                &ipfs_api_key,
+            )
+            .await?;
+            let policy = if immutable { MetadataPolicy::Immutable } else { MetadataPolicy::Mutable };
+
+            let mint = Keypair::new();
+            let created = match buy {
+                Some(sol) => {
+                    let mut params = CreateParams::new(mint, ipfs, sol).metadata_policy(policy).mint_check(MintCheck::Regenerate);
+                    params.slippage_basis_points = slippage.slippage_bps;
+                    client.create_and_buy(params).await?
+                }
+                None => client.create(mint, ipfs, policy).await?,
+            };
+            print_output(
+                json,
+                json!({
+                    "action": "create",
+                    "mint": created.mint.to_string(),
+                    "bonding_curve": created.bonding_curve.to_string(),
+                    "signature": created.signature.to_string(),
+                    "metadata_uri": created.metadata_uri,
+                    "latency": created.latency,
+                }),
+                format!("created {} ({})\n{}\n{}", created.mint, created.metadata_uri, created.signature, latency_summary(&created.latency)),
+            );
+        }
+        Command::Balance { mint } => {
+            let wallet = client.get_payer_pubkey();
+            let sol = client.get_payer_sol_balance().await?;
+            let tokens = match mint {
+                Some(mint) => Some(client.get_payer_token_balance(&mint).await?),
+                None => None,
+            };
+            let mut text = format!("{}: {} SOL", wallet, lamports_to_sol(sol));
+            if let (Some(mint), Some(tokens)) = (mint, tokens) {
+                text.push_str(&format!("\n{}: {} tokens", mint, tokens_to_ui(tokens)));
             }
+            print_output(
+                json,
+                json!({ "wallet": wallet.to_string(), "lamports": sol, "mint": mint.map(|mint| mint.to_string()), "token_amount": tokens }),
+                text,
+            );
         }
+        Command::SelfTest { grpc_url, ipfs_api_key, spend_allowed } => {
+            let options = SelfTestOptions { grpc_endpoint: grpc_url, ipfs_api_key: ipfs_api_key.map(Secret::from), spend_allowed, ..Default::default() };
+            let report = client.self_test(&options).await;
+            print_output(json, serde_json::to_value(&report)?, report.to_string());
+            if !report.passed() {
+                bail!("{} of {} checks failed", report.failures().count(), report.checks.len());
+            }
+        }
+        Command::Watch(_) | Command::Quote { .. } => unreachable!("handled without a wallet"),
+    }
+    Ok(())
+}
+
+async fn watch(args: WatchArgs, json: bool) -> Result<(), anyhow::Error> {
+    let WatchArgs { ws_url, commitment, mints, creates_only, trades_only, min_sol, duration } = args;
+    let callback = move |event: PumpfunEvent| {
+        let shown = match &event {
+            PumpfunEvent::NewToken(token) => !trades_only && (mints.is_empty() || mints.contains(&token.mint)),
+            PumpfunEvent::NewDevTrade(trade) | PumpfunEvent::NewUserTrade(trade) | PumpfunEvent::NewBotTrade(trade) => {
+                !creates_only
+                    && (mints.is_empty() || mints.contains(&trade.mint))
+                    && min_sol.map_or(true, |min_sol| trade.sol_amount >= min_sol)
+            }
+            PumpfunEvent::OwnTradeSubmitted(_) | PumpfunEvent::OwnTradeConfirmed(_) | PumpfunEvent::OwnTradeFailed(_) | PumpfunEvent::WalletTrade(_) => false,
+            PumpfunEvent::Error(_) | PumpfunEvent::Stale(_) | PumpfunEvent::Watermark(_) => true,
+        };
+        if shown {
+            print_event(json, &event);
+        }
+    };
+
+    let subscription = tokens_subscription(&ws_url, commitment, callback, None)
+        .await
+        .map_err(|e| anyhow!("failed to subscribe to {}: {}", ws_url, e))?;
+    match duration {
+        Some(seconds) => tokio::time::sleep(Duration::from_secs(seconds)).await,
+        None => tokio::signal::ctrl_c().await?,
+    }
+    stop_subscription(subscription).await;
+    Ok(())
+}
+
+fn load_cluster(config: &Option<PathBuf>) -> Result<Cluster, anyhow::Error> {
+    match config {
+        Some(path) => Cluster::load(path, ENV_PREFIX).with_context(|| format!("loading {}", path.display())),
+        None => Cluster::from_env(ENV_PREFIX).context("loading the cluster from PUMPFUN_* environment variables"),
+    }
+}
+
+async fn connect(config: &Option<PathBuf>, keypair: &Option<PathBuf>) -> Result<PumpFun, anyhow::Error> {
+    let cluster = load_cluster(config)?;
+    let path = match keypair {
+        Some(path) => path.clone(),
+        None => default_keypair_path().ok_or_else(|| anyhow!("no keypair given; pass --keypair or set PUMPFUN_KEYPAIR"))?,
     };
+    let payer = read_keypair_file(&path).map_err(|e| anyhow!("reading keypair {}: {}", path.display(), e))?;
+    PumpFun::try_new(Arc::new(payer), &cluster).await
+}
 
-    // Start subscription
-    let subscription = tokens_subscription(
-        ws_url,
-        commitment,
-        callback,
-        None
-    ).await.unwrap();
+/// The Solana CLI's default keypair, `~/.config/solana/id.json`
+fn default_keypair_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".config/solana/id.json"))
+}
 
-    // Wait for a while to receive events
-    tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+fn print_output(json: bool, value: Value, text: String) {
+    if json {
+        println!("{}", value);
+    } else {
+        println!("{}", text);
+    }
+}
 
-    // Stop subscription
-    stop_subscription(subscription).await;
+fn print_trade(json: bool, action: &str, mint: &Pubkey, latency: &LatencyReport) {
+    print_output(
+        json,
+        json!({ "action": action, "mint": mint.to_string(), "latency": latency }),
+        format!("{} {} sent\n{}", action, mint, latency_summary(latency)),
+    );
+}
+
+fn latency_summary(latency: &LatencyReport) -> String {
+    serde_json::to_string_pretty(latency).unwrap_or_default()
+}
+
+/// Prints `event`, as JSON stamped with when it was received so `ReplaySource` can replay it
+fn print_event(json: bool, event: &PumpfunEvent) {
+    let (mut value, text) = match event {
+        PumpfunEvent::NewToken(token) => (token_json(token), format!("new token {} ({}) {} by {}", token.name, token.symbol, token.mint, token.user)),
+        PumpfunEvent::NewDevTrade(trade) => (trade_json("dev_trade", trade), trade_text("dev", trade)),
+        PumpfunEvent::NewUserTrade(trade) => (trade_json("user_trade", trade), trade_text("user", trade)),
+        PumpfunEvent::NewBotTrade(trade) => (trade_json("bot_trade", trade), trade_text("bot", trade)),
+        PumpfunEvent::WalletTrade(wallet_trade) => (trade_json("wallet_trade", &wallet_trade.trade), trade_text("wallet", &wallet_trade.trade)),
+        PumpfunEvent::Error(error) => (json!({ "event": "error", "error": error }), format!("error: {}", error)),
+        PumpfunEvent::Stale(silent_for) => (
+            json!({ "event": "stale", "silent_ms": silent_for.as_millis() as u64 }),
+            format!("no messages for {:?}, reconnecting", silent_for),
+        ),
+        PumpfunEvent::Watermark(watermark) => (
+            json!({ "event": "watermark", "source": watermark.source, "slot": watermark.slot }),
+            format!("{:?} delivered up to slot {}", watermark.source, watermark.slot),
+        ),
+        PumpfunEvent::OwnTradeSubmitted(own) => (own_trade_json("own_trade_submitted", own), format!("submitted {} through {}", own.signature, own.client_type)),
+        PumpfunEvent::OwnTradeConfirmed(own) => (own_trade_json("own_trade_confirmed", own), format!("confirmed {} in slot {}", own.signature, own.landed_slot.unwrap_or_default())),
+        PumpfunEvent::OwnTradeFailed(own) => (
+            own_trade_json("own_trade_failed", own),
+            format!("failed {}: {}", own.signature, own.error.as_deref().unwrap_or("unknown error")),
+        ),
+    };
+    let received_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    value["received_at_ms"] = json!(received_at.as_millis() as u64);
+    print_output(json, value, text);
+}
+
+fn own_trade_json(event: &str, own: &OwnTradeEvent) -> Value {
+    let mut value = json!(own);
+    value["event"] = json!(event);
+    value
+}
+
+fn token_json(token: &CreateTokenInfo) -> Value {
+    json!({
+        "event": "new_token",
+        "slot": token.slot,
+        "name": token.name,
+        "symbol": token.symbol,
+        "uri": token.uri,
+        "mint": token.mint.to_string(),
+        "bonding_curve": token.bonding_curve.to_string(),
+        "user": token.user.to_string(),
+    })
+}
+
+fn trade_json(event: &str, trade: &TradeInfo) -> Value {
+    json!({
+        "event": event,
+        "slot": trade.slot,
+        "mint": trade.mint.to_string(),
+        "is_buy": trade.is_buy,
+        "is_initial_buy": trade.is_initial_buy,
+        "via_cpi": trade.via_cpi,
+        "sol_amount": trade.sol_amount,
+        "token_amount": trade.token_amount,
+        "user": trade.user.to_string(),
+        "timestamp": trade.timestamp,
+        "virtual_sol_reserves": trade.virtual_sol_reserves,
+        "virtual_token_reserves": trade.virtual_token_reserves,
+        "real_sol_reserves": trade.real_sol_reserves,
+        "real_token_reserves": trade.real_token_reserves,
+        "signature": trade.signature.to_string(),
+    })
+}
+
+fn trade_text(kind: &str, trade: &TradeInfo) -> String {
+    format!(
+        "{} {} {} {} tokens of {} for {} SOL",
+        kind,
+        trade.user,
+        if trade.is_buy { "bought" } else { "sold" },
+        tokens_to_ui(trade.token_amount),
+        trade.mint,
+        lamports_to_sol(trade.sol_amount)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_cli_validates_arguments() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["pumpfun", "--json", "sell", &Pubkey::new_unique().to_string(), "--percent", "50"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(cli.command, Command::Sell { percent: 50, .. }));
+
+        let mint = Pubkey::new_unique().to_string();
+        assert!(Cli::try_parse_from(["pumpfun", "sell", &mint, "--percent", "0"]).is_err());
+        assert!(Cli::try_parse_from(["pumpfun", "buy", &mint, "-1"]).is_err());
+        assert!(Cli::try_parse_from(["pumpfun", "buy", "not-a-mint", "0.1"]).is_err());
+        assert!(Cli::try_parse_from(["pumpfun", "watch", "--creates-only", "--trades-only"]).is_err());
 
-    Ok(())  
-}
\ No newline at end of file
+        let cli = Cli::try_parse_from(["pumpfun", "buy", &mint, "0.5"]).unwrap();
+        assert!(matches!(cli.command, Command::Buy { sol: 500_000_000, .. }));
+    }
+}
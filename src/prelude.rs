@@ -0,0 +1,18 @@
+//! The types most programs need, importable at once with `use pumpfun_sdk::prelude::*`
+
+pub use crate::{
+    common::{
+        commitment::EventCommitment,
+        logs_data::{CreateTokenInfo, TradeInfo},
+        logs_events::PumpfunEvent,
+        logs_subscribe::SubscriptionHandle,
+        watchlist::{MintWatchlist, WatchSource},
+        Cluster, PriorityFee, TipAmount, TradeTag,
+    },
+    error::{SdkError, SdkResult},
+    fee_clients::ClientType,
+    grpc::YellowstoneGrpc,
+    ipfs::TokenMetadataIPFS,
+    trade::{cancel::CancellationToken, context::TradeOverrides, params::{CreateParams, TradeParams}},
+    PumpFun,
+};
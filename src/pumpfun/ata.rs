@@ -0,0 +1,163 @@
+//! Pre-creation of associated token accounts ahead of a trading session
+//!
+//! A buy normally creates the payer's token account in the same transaction, paying its rent
+//! and compute when latency matters most. [`precreate_atas`] creates the token accounts of a
+//! watchlist up front, packing idempotent create instructions into as few transactions as fit
+//! in a packet. Buys of those mints can then skip the create instruction by passing
+//! `ata_exists = true` to `build_buy_instructions`.
+
+use std::sync::Arc;
+
+use futures::future::join_all;
+use solana_hash::Hash;
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer, transaction::VersionedTransaction
+};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+
+use crate::{
+    common::{latency::StageTimer, tx_packing::{pack_groups, InstructionGroup, PackingLimits}, PriorityFee, RpcClients},
+    trade::RpcSendOptions,
+};
+
+use super::common::{get_ata, get_token_program, send_and_confirm_with_rpc_options};
+
+/// Compute units budgeted per token account creation; Token-2022 mints need the most
+pub const CREATE_ATA_COMPUTE_UNITS: u32 = 35_000;
+
+/// Accounts per `getMultipleAccounts` request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtaStatus {
+    /// The account already existed and nothing was sent
+    Existed,
+    /// The account was created, or appeared concurrently, in `signature`
+    Created { signature: Signature },
+    /// The transaction meant to create the account failed
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecreatedAta {
+    pub mint: Pubkey,
+    pub ata: Pubkey,
+    pub token_program: Pubkey,
+    pub status: AtaStatus,
+}
+
+impl PrecreatedAta {
+    /// Whether the account exists after pre-creation
+    #[inline]
+    pub fn exists(&self) -> bool {
+        !matches!(self.status, AtaStatus::Failed { .. })
+    }
+}
+
+/// Creates the payer's token accounts for `mints` that don't exist yet
+///
+/// Missing accounts are created with idempotent instructions, batched into as few
+/// transactions as fit, which are sent and confirmed concurrently over RPC. Returns one
+/// entry per mint, in order. Fails only if the accounts can't be looked up or the
+/// transactions can't be built; failed transactions are reported per account.
+pub async fn precreate_atas(
+    rpc: impl Into<RpcClients>,
+    payer: Arc<Keypair>,
+    mints: &[Pubkey],
+    priority_fee: PriorityFee,
+    rpc_send_options: RpcSendOptions,
+) -> Result<Vec<PrecreatedAta>, anyhow::Error> {
+    let RpcClients { read: rpc, send } = rpc.into();
+    let mut atas = Vec::with_capacity(mints.len());
+    for mint in mints {
+        let token_program = get_token_program(&rpc, mint).await?;
+        let ata = get_ata(&payer.pubkey(), mint, &token_program);
+        atas.push(PrecreatedAta { mint: *mint, ata, token_program, status: AtaStatus::Existed });
+    }
+
+    let addresses: Vec<Pubkey> = atas.iter().map(|ata| ata.ata).collect();
+    let mut missing = vec![];
+    for (chunk_index, chunk) in addresses.chunks(MAX_MULTIPLE_ACCOUNTS).enumerate() {
+        let accounts = rpc.get_multiple_accounts(chunk).await?;
+        for (offset, account) in accounts.iter().enumerate() {
+            if account.is_none() {
+                missing.push(chunk_index * MAX_MULTIPLE_ACCOUNTS + offset);
+            }
+        }
+    }
+    if missing.is_empty() {
+        return Ok(atas);
+    }
+
+    let instructions = missing
+        .iter()
+        .map(|&index| create_associated_token_account_idempotent(&payer.pubkey(), &payer.pubkey(), &atas[index].mint, &atas[index].token_program))
+        .collect();
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let batches = batch_create_instructions(&payer, &priority_fee, instructions, recent_blockhash)?;
+
+    let results = join_all(batches.iter().map(|(transaction, _)| {
+        let send = send.clone();
+        async move { send_and_confirm_with_rpc_options(&mut StageTimer::new(), &send, None, transaction, rpc_send_options).await }
+    }))
+    .await;
+
+    let mut missing = missing.into_iter();
+    for ((_, count), result) in batches.iter().zip(results) {
+        let status = match result {
+            Ok(signature) => AtaStatus::Created { signature },
+            Err(e) => AtaStatus::Failed { error: e.to_string() },
+        };
+        for index in missing.by_ref().take(*count) {
+            atas[index].status = status.clone();
+        }
+    }
+    Ok(atas)
+}
+
+/// Packs `instructions` greedily into signed transactions that fit in a packet, returning
+/// each transaction with the number of instructions it carries
+fn batch_create_instructions(
+    payer: &Keypair,
+    priority_fee: &PriorityFee,
+    instructions: Vec<Instruction>,
+    blockhash: Hash,
+) -> Result<Vec<(VersionedTransaction, usize)>, anyhow::Error> {
+    let groups = instructions.into_iter().map(|instruction| InstructionGroup::from(instruction).with_compute_units(CREATE_ATA_COMPUTE_UNITS));
+    let limits = PackingLimits { compute_unit_price: Some(priority_fee.unit_price), ..Default::default() };
+    let packed = pack_groups(groups, &[payer], blockhash, &limits)?;
+    Ok(packed.into_iter().map(|packed| (packed.transaction, packed.groups.len())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common::replay::ReplaySource, constants::accounts::TOKEN_PROGRAM};
+
+    #[test]
+    fn test_batches_fill_packets() {
+        let payer = Keypair::new();
+        let instructions: Vec<Instruction> = (0..40)
+            .map(|_| create_associated_token_account_idempotent(&payer.pubkey(), &payer.pubkey(), &Pubkey::new_unique(), &TOKEN_PROGRAM))
+            .collect();
+
+        let batches = batch_create_instructions(&payer, &PriorityFee::default(), instructions, Hash::default()).unwrap();
+        assert!(batches.len() > 1);
+        assert_eq!(batches.iter().map(|(_, count)| count).sum::<usize>(), 40);
+        for (transaction, count) in &batches {
+            assert_eq!(transaction.message.instructions().len(), count + 2);
+        }
+        assert!(batches[0].1 >= batches.last().unwrap().1);
+    }
+
+    #[tokio::test]
+    async fn test_reads_and_sends_use_their_own_clients() {
+        let (read, send) = (ReplaySource::from_reader(&b""[..]).unwrap(), ReplaySource::from_reader(&b""[..]).unwrap());
+        let rpc = RpcClients::new(read.rpc_client(), send.rpc_client());
+        let atas = precreate_atas(rpc, Arc::new(Keypair::new()), &[Pubkey::new_unique()], PriorityFee::default(), RpcSendOptions::default()).await.unwrap();
+
+        assert!(matches!(atas[0].status, AtaStatus::Created { .. }), "{:?}", atas[0].status);
+        assert!(read.sent_transactions().is_empty());
+        assert_eq!(send.sent_transactions().len(), 1);
+    }
+}
@@ -0,0 +1,285 @@
+//! Checking the payer can afford a buy before sending it
+//!
+//! A buy spending the payer's whole balance fails on chain: besides what the buy instruction
+//! debits, its transaction pays the network and priority fees, the relay tip and, when the
+//! payer's token account doesn't exist yet, its rent, and the payer has to stay rent exempt.
+//! [`check_buy_balance`] adds these up from the built instructions and fails with
+//! [`InsufficientSolBalance`] before anything is sent. [`max_buyable_sol`] finds the largest
+//! buy that passes.
+
+use std::sync::Arc;
+
+use solana_sdk::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey, rent::Rent, signature::Keypair, signer::Signer};
+use spl_token_2022::{extension::ExtensionType, state::Account as TokenAccount};
+use thiserror::Error;
+
+use crate::{
+    common::{PriorityFee, SolanaRpcClient},
+    constants::accounts::{ASSOCIATED_TOKEN_PROGRAM, PUMPFUN, TOKEN_2022_PROGRAM},
+    instruction::decode::{decode_instruction, PumpInstruction},
+};
+
+use super::{
+    buy::build_buy_instructions,
+    common::{get_ata, get_token_program, PartialFillGuard, SlippageMode},
+};
+
+/// Network fee of each transaction signature
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Attempts of [`max_buyable_sol`] to shrink an estimate until its quote fits
+const MAX_SIZING_ATTEMPTS: usize = 5;
+
+/// Lamports a buy transaction may spend, by purpose
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BuyCost {
+    /// Most the buy instruction may debit, protocol fee and slippage included
+    pub max_sol_cost: u64,
+    /// Signature fee
+    pub network_fee: u64,
+    /// Compute unit limit times price
+    pub priority_fee: u64,
+    pub tip: u64,
+    /// Rent of the payer's token account, zero if it exists
+    pub ata_rent: u64,
+    /// Rent-exempt minimum the payer has to keep
+    pub payer_reserve: u64,
+}
+
+impl BuyCost {
+    pub fn total(&self) -> u64 {
+        self.max_sol_cost.saturating_add(self.overhead())
+    }
+
+    /// Everything but the buy instruction itself
+    pub fn overhead(&self) -> u64 {
+        self.network_fee + self.priority_fee + self.tip + self.ata_rent + self.payer_reserve
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Insufficient SOL balance: the buy needs up to {required} lamports, the payer holds {available}")]
+pub struct InsufficientSolBalance {
+    pub required: u64,
+    pub available: u64,
+    pub breakdown: BuyCost,
+}
+
+impl InsufficientSolBalance {
+    /// The balance failure somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&InsufficientSolBalance> {
+        error.chain().find_map(|cause| cause.downcast_ref::<InsufficientSolBalance>())
+    }
+}
+
+/// Priority fee paid for the compute budget of a buy with `priority_fee`, rounded up to whole
+/// lamports
+pub fn priority_fee_lamports(priority_fee: &PriorityFee) -> u64 {
+    ((priority_fee.buy_limit() as u128 * priority_fee.unit_price as u128).div_ceil(1_000_000)) as u64
+}
+
+/// Rent of a token account of `token_program` as the associated token program creates it
+pub fn token_account_rent(token_program: &Pubkey) -> u64 {
+    let len = if *token_program == TOKEN_2022_PROGRAM {
+        ExtensionType::try_calculate_account_len::<TokenAccount>(&[ExtensionType::ImmutableOwner]).unwrap_or(TokenAccount::LEN)
+    } else {
+        TokenAccount::LEN
+    };
+    Rent::default().minimum_balance(len)
+}
+
+/// What a transaction of the buy `instructions`, tipping `tip` lamports, may spend
+///
+/// `ata_exists` tells whether the token account the instructions create, if any, already
+/// exists, in which case its idempotent creation costs nothing.
+pub fn buy_cost(instructions: &[Instruction], priority_fee: &PriorityFee, tip: u64, ata_exists: bool) -> BuyCost {
+    let max_sol_cost = instructions
+        .iter()
+        .filter(|instruction| instruction.program_id == PUMPFUN)
+        .filter_map(|instruction| match decode_instruction(&instruction.data) {
+            Ok(PumpInstruction::Buy { max_sol_cost, .. }) => Some(max_sol_cost),
+            _ => None,
+        })
+        .sum();
+    let ata_rent = match created_ata(instructions) {
+        Some((_, token_program)) if !ata_exists => token_account_rent(&token_program),
+        _ => 0,
+    };
+    BuyCost {
+        max_sol_cost,
+        network_fee: LAMPORTS_PER_SIGNATURE,
+        priority_fee: priority_fee_lamports(priority_fee),
+        tip,
+        ata_rent,
+        payer_reserve: Rent::default().minimum_balance(0),
+    }
+}
+
+/// Fails unless `available` lamports cover `cost`
+pub fn ensure_affordable(cost: BuyCost, available: u64) -> Result<(), InsufficientSolBalance> {
+    if cost.total() > available {
+        return Err(InsufficientSolBalance { required: cost.total(), available, breakdown: cost });
+    }
+    Ok(())
+}
+
+/// Fails with [`InsufficientSolBalance`] unless `payer` can afford a transaction of the buy
+/// `instructions` tipping `tip` lamports, returning what it may spend
+pub async fn check_buy_balance(
+    rpc: &SolanaRpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    priority_fee: &PriorityFee,
+    tip: u64,
+) -> Result<BuyCost, anyhow::Error> {
+    let ata = created_ata(instructions).map(|(ata, _)| ata);
+    let accounts = rpc.get_multiple_accounts(&std::iter::once(*payer).chain(ata).collect::<Vec<_>>()).await?;
+    let available = accounts[0].as_ref().map_or(0, |account| account.lamports);
+    let ata_exists = accounts.get(1).map_or(true, Option::is_some);
+
+    let cost = buy_cost(instructions, priority_fee, tip, ata_exists);
+    ensure_affordable(cost, available)?;
+    Ok(cost)
+}
+
+/// Largest `amount_sol` whose buy fits in `budget` lamports once slippage is applied per `mode`
+pub fn max_buy_amount(budget: u64, slippage_basis_points: u64, mode: SlippageMode) -> u64 {
+    match mode {
+        SlippageMode::MinTokensOut => budget,
+        SlippageMode::MaxSolCost | SlippageMode::Both => (budget as u128 * 10_000 / (10_000 + slippage_basis_points as u128)) as u64,
+    }
+}
+
+/// Largest `amount_sol` `payer` can buy `mint` with, tipping `tip` lamports
+///
+/// The estimate from the payer's balance is quoted against the live curve and shrunk until
+/// [`check_buy_balance`] would pass. Returns zero if not even the overhead is covered.
+pub async fn max_buyable_sol(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    mint: Pubkey,
+    slippage_basis_points: u64,
+    slippage_mode: SlippageMode,
+    priority_fee: &PriorityFee,
+    tip: u64,
+) -> Result<u64, anyhow::Error> {
+    let token_program = get_token_program(&rpc, &mint).await?;
+    let accounts = rpc.get_multiple_accounts(&[payer.pubkey(), get_ata(&payer.pubkey(), &mint, &token_program)]).await?;
+    let available = accounts[0].as_ref().map_or(0, |account| account.lamports);
+    let ata_exists = accounts[1].is_some();
+
+    let overhead = BuyCost {
+        ata_rent: if ata_exists { 0 } else { token_account_rent(&token_program) },
+        ..buy_cost(&[], priority_fee, tip, true)
+    }
+    .overhead();
+    let mut amount_sol = max_buy_amount(available.saturating_sub(overhead), slippage_basis_points, slippage_mode);
+
+    for _ in 0..MAX_SIZING_ATTEMPTS {
+        if amount_sol == 0 {
+            break;
+        }
+        let instructions = build_buy_instructions(rpc.clone(), payer.clone(), Arc::new(mint), amount_sol, Some(slippage_basis_points), slippage_mode, None, ata_exists, PartialFillGuard::default()).await?;
+        let total = buy_cost(&instructions, priority_fee, tip, ata_exists).total();
+        if total <= available {
+            return Ok(amount_sol);
+        }
+        amount_sol = amount_sol.saturating_sub(total - available);
+    }
+    Ok(0)
+}
+
+/// Token account created by `instructions`, if any, and its token program
+fn created_ata(instructions: &[Instruction]) -> Option<(Pubkey, Pubkey)> {
+    let create = instructions.iter().find(|instruction| instruction.program_id == ASSOCIATED_TOKEN_PROGRAM)?;
+    Some((create.accounts.get(1)?.pubkey, create.accounts.get(5)?.pubkey))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_hash::Hash;
+    use solana_sdk::{
+        compute_budget::{self, ComputeBudgetInstruction},
+        system_instruction::SystemInstruction,
+        system_program,
+    };
+
+    use super::*;
+    use crate::{
+        accounts::BondingCurveAccount,
+        common::TipAmount,
+        constants::accounts::TOKEN_PROGRAM,
+        pumpfun::{
+            buy::{build_buy_transaction_with_tip, buy_quote_instructions},
+            common::quote_buy_with_mode,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_breakdown_matches_the_built_transaction() {
+        let payer = Arc::new(Keypair::new());
+        let curve = BondingCurveAccount::new(0, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000, false);
+        let quote = quote_buy_with_mode(&curve, 1_000_000_000, 100, 500, SlippageMode::MaxSolCost).unwrap();
+        let instructions = buy_quote_instructions(&payer.pubkey(), &Pubkey::new_unique(), &Pubkey::new_unique(), &TOKEN_PROGRAM, &quote, false);
+        let priority_fee = PriorityFee { buy_unit_limit: Some(78_000), unit_price: 500_000, buy_tip_fee: TipAmount::Lamports(600_000), ..Default::default() };
+
+        let cost = buy_cost(&instructions, &priority_fee, priority_fee.buy_tip_fee.lamports(), false);
+        let transaction = build_buy_transaction_with_tip(Arc::new(Pubkey::new_unique()), payer, priority_fee, instructions, vec![], Hash::default()).await.unwrap();
+
+        // What the transaction spends, read back from its instructions
+        let keys = transaction.message.static_account_keys();
+        let (mut unit_limit, mut unit_price, mut tip, mut max_sol_cost, mut creates_ata) = (0u128, 0u128, 0, 0, false);
+        for instruction in transaction.message.instructions() {
+            let program = keys[instruction.program_id_index as usize];
+            if program == compute_budget::id() {
+                match borsh::from_slice(&instruction.data) {
+                    Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => unit_limit = limit as u128,
+                    Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => unit_price = price as u128,
+                    _ => {}
+                }
+            } else if program == system_program::id() {
+                if let Ok(SystemInstruction::Transfer { lamports }) = bincode::deserialize(&instruction.data) {
+                    tip += lamports;
+                }
+            } else if program == PUMPFUN {
+                if let Ok(PumpInstruction::Buy { max_sol_cost: cost, .. }) = decode_instruction(&instruction.data) {
+                    max_sol_cost += cost;
+                }
+            } else if program == ASSOCIATED_TOKEN_PROGRAM {
+                creates_ata = true;
+            }
+        }
+
+        assert_eq!(cost.max_sol_cost, max_sol_cost);
+        assert_eq!(cost.max_sol_cost, quote.max_sol_cost);
+        assert_eq!(cost.network_fee, LAMPORTS_PER_SIGNATURE * transaction.signatures.len() as u64);
+        assert_eq!(cost.priority_fee as u128, (unit_limit * unit_price).div_ceil(1_000_000));
+        assert_eq!(cost.priority_fee, 39_000);
+        assert_eq!(cost.tip, tip);
+        assert!(creates_ata);
+        assert_eq!(cost.ata_rent, 2_039_280);
+        assert_eq!(cost.payer_reserve, 890_880);
+
+        let without_ata = buy_quote_instructions(&Pubkey::new_unique(), &Pubkey::new_unique(), &Pubkey::new_unique(), &TOKEN_PROGRAM, &quote, true);
+        assert_eq!(buy_cost(&without_ata, &priority_fee, tip, false).ata_rent, 0);
+    }
+
+    #[test]
+    fn test_insufficient_balance_is_reported_with_its_breakdown() {
+        let cost = BuyCost { max_sol_cost: 1_050_000_000, network_fee: 5_000, priority_fee: 39_000, tip: 600_000, ata_rent: 2_039_280, payer_reserve: 890_880 };
+        assert!(ensure_affordable(cost, cost.total()).is_ok());
+
+        let error = ensure_affordable(cost, 1_000_000_000).unwrap_err();
+        assert_eq!(error, InsufficientSolBalance { required: 1_053_574_160, available: 1_000_000_000, breakdown: cost });
+        assert!(InsufficientSolBalance::find(&anyhow::Error::from(error)).is_some());
+        assert_eq!(token_account_rent(&TOKEN_2022_PROGRAM), Rent::default().minimum_balance(170));
+    }
+
+    #[test]
+    fn test_max_buy_amount_leaves_room_for_slippage() {
+        let budget = 1_050_000_000;
+        assert_eq!(max_buy_amount(budget, 500, SlippageMode::MaxSolCost), 1_000_000_000);
+        assert_eq!(max_buy_amount(budget, 500, SlippageMode::Both), 1_000_000_000);
+        assert_eq!(max_buy_amount(budget, 500, SlippageMode::MinTokensOut), budget);
+    }
+}
@@ -1,82 +1,132 @@
 use anyhow::anyhow;
 use solana_sdk::{
-    compute_budget::ComputeBudgetInstruction, instruction::Instruction, message::{v0, VersionedMessage}, native_token::sol_to_lamports, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
 };
 use solana_hash::Hash;
-use spl_associated_token_account::instruction::create_associated_token_account;
-use tokio::task::JoinHandle;
-use std::{str::FromStr, time::Instant, sync::Arc};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use std::{str::FromStr, sync::Arc};
 
-use crate::{common::{PriorityFee, SolanaRpcClient}, constants::{self, trade::DEFAULT_SLIPPAGE}, instruction, jito::FeeClient};
+use crate::{common::{latency::{LatencyReport, Stage, StageTimer}, PriorityFee, RpcClients, SolanaRpcClient}, instruction, trade::{cancel::{self, CancellationToken}, context::ExecutionContext, journal::{memo_text, record_intent, TradeIntent, TradeSide}, RpcSendOptions}};
 
 const MAX_LOADED_ACCOUNTS_DATA_SIZE_LIMIT: u32 = 250000;
 
-use super::common::{calculate_with_slippage_buy, get_bonding_curve_account, get_global_account, get_initial_buy_price};
+use super::balance::check_buy_balance;
+use super::fee_recipient::{retry_on_rotation, FeeRecipientUsed};
+use crate::accounts::{BondingCurveAccount, GlobalAccount};
+use super::common::{append_extra_instructions, buy_quote_from, BuyQuote, SlippageMode, dedup_compute_budget_instructions, get_buy_accounts, send_and_confirm_cancellable, sign_versioned_transaction, visible_curve, PartialFillGuard, PostTradeChecks};
 
+/// Buy tokens over RPC, quoting through the read client of `rpc` and sending through its
+/// send client
+///
+/// Unless `skip_balance_check`, fails with
+/// [`InsufficientSolBalance`](super::balance::InsufficientSolBalance) before sending when the
+/// payer can't afford the buy, its fees and the token account rent.
 pub async fn buy(
-    rpc: Arc<SolanaRpcClient>,
+    rpc: impl Into<RpcClients>,
     payer: Arc<Keypair>,
     mint: Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    partial_fill: PartialFillGuard,
     priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
-    let transaction = build_buy_transaction(rpc.clone(), payer.clone(), mint.clone(), amount_sol, slippage_basis_points, priority_fee.clone()).await?;
-    rpc.send_and_confirm_transaction(&transaction).await?;
-    Ok(())
+    rpc_send_options: RpcSendOptions,
+    ata_exists: bool,
+    skip_balance_check: bool,
+    extra_instructions: Option<Vec<Instruction>>,
+    cancel: Option<CancellationToken>,
+) -> Result<LatencyReport, anyhow::Error> {
+    cancel::check(cancel.as_ref())?;
+    let RpcClients { read: rpc, send } = rpc.into();
+    let trade_id = record_intent(TradeIntent {
+        side: TradeSide::Buy,
+        mint,
+        amount_sol: Some(amount_sol),
+        amount_token: None,
+        slippage_basis_points,
+        trade_tag: extra_instructions.as_deref().and_then(memo_text),
+        idempotency_key: None,
+    });
+    let mut timer = StageTimer::new();
+    let build_instructions = async {
+        let instructions = build_buy_instructions(rpc.clone(), payer.clone(), Arc::new(mint), amount_sol, slippage_basis_points, slippage_mode, None, ata_exists, partial_fill).await?;
+        if !skip_balance_check {
+            check_buy_balance(&rpc, &payer.pubkey(), &instructions, &priority_fee, 0).await?;
+        }
+        Ok::<_, anyhow::Error>(instructions)
+    };
+    let instructions = timer.time(Stage::Quote, cancel::before_submission(cancel.as_ref(), build_instructions)).await?;
+    let fee_recipient = FeeRecipientUsed::from_instructions(&instructions).await;
+    let recent_blockhash = timer.time(Stage::Blockhash, cancel::before_submission(cancel.as_ref(), rpc.get_latest_blockhash())).await?;
+    let transaction = timer.time_sync(Stage::BuildSign, || {
+        sign_buy_transaction(&payer, &priority_fee, instructions, extra_instructions.unwrap_or_default(), recent_blockhash)
+    })?;
+    send_and_confirm_cancellable(&mut timer, &send, Some(trade_id), &transaction, rpc_send_options, cancel).await?;
+    Ok(timer.finish().with_fee_recipient(fee_recipient))
 }
 
-/// Buy tokens using Jito
+/// Buy tokens through the fee clients of `context`, tipping each
+///
+/// Checks the payer can afford the buy and one tip like [`buy`], unless the context skips it.
+/// Retries once on a rotated fee recipient if the context says so, see
+/// [`fee_recipient`](super::fee_recipient). A buy below the context's `buy_floor` fails with
+/// [`BuyTooSmall`](super::common::BuyTooSmall).
 pub async fn buy_with_tip(
-    rpc: Arc<SolanaRpcClient>,
-    fee_clients: Vec<Arc<FeeClient>>,
-    payer: Arc<Keypair>,
+    context: &ExecutionContext,
     mint: Pubkey,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
-    let start_time = Instant::now();
+    ata_exists: bool,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<LatencyReport, anyhow::Error> {
+    context.buy_floor.check(amount_sol)?;
+    retry_on_rotation(context.retry_fee_recipient_rotation, || {
+        buy_with_tip_once(context, mint, amount_sol, slippage_basis_points, ata_exists, extra_instructions.clone())
+    }).await
+}
+
+async fn buy_with_tip_once(
+    context: &ExecutionContext,
+    mint: Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    ata_exists: bool,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<LatencyReport, anyhow::Error> {
+    let trade_id = context.record_intent(TradeSide::Buy, mint, Some(amount_sol), None, slippage_basis_points);
+    let mut timer = StageTimer::new();
+    let extra_instructions = context.tagged(extra_instructions);
 
     let mint = Arc::new(mint.clone());
-    let instructions = build_buy_instructions(rpc.clone(), payer.clone(), mint.clone(), amount_sol, slippage_basis_points).await?;
-
-    let mut transactions = vec![];
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
-    for fee_client in fee_clients.clone() {
-        let payer = payer.clone();
-        let priority_fee = priority_fee.clone();
-        let tip_account = fee_client.get_tip_account().await.map_err(|e| anyhow!(e.to_string()))?;
-        let tip_account = Arc::new(Pubkey::from_str(&tip_account).map_err(|e| anyhow!(e))?);
-
-        let transaction = build_buy_transaction_with_tip(tip_account, payer, priority_fee, instructions.clone(), recent_blockhash).await?;
-        transactions.push(transaction);
-    }
+    let build_instructions = async {
+        let instructions = build_buy_instructions(context.rpc.clone(), context.payer.clone(), mint.clone(), amount_sol, slippage_basis_points, context.slippage_mode, None, ata_exists, context.partial_fill).await?;
+        if !context.skip_balance_check {
+            let tip = context.priority_fee.buy_tip_fee.lamports();
+            check_buy_balance(&context.rpc, &context.payer.pubkey(), &instructions, &context.priority_fee, tip).await?;
+        }
+        Ok::<_, anyhow::Error>(instructions)
+    };
+    let instructions = timer.time(Stage::Quote, context.before_submission(build_instructions)).await?;
+    let fee_recipient = FeeRecipientUsed::from_instructions(&instructions).await;
+    let recent_blockhash = timer.time(Stage::Blockhash, context.before_submission(context.rpc.get_latest_blockhash())).await?;
 
-    let mut handles: Vec<JoinHandle<Result<(), anyhow::Error>>> = vec![];
-    for i in 0..fee_clients.len() {
-        let fee_client = fee_clients[i].clone();
-        let transactions = transactions.clone();
-        let start_time = start_time.clone();
-        let transaction = transactions[i].clone();
-        let handle = tokio::spawn(async move {
-           fee_client.send_transaction(&transaction).await?;
-            println!("index: {}, Total Jito buy operation time: {:?}ms", i, start_time.elapsed().as_millis());
-            Ok::<(), anyhow::Error>(())
-        });
-
-        handles.push(handle);        
-    }
+    let transactions = timer.time(Stage::BuildSign, context.before_submission(async {
+        let mut transactions = vec![];
+        for fee_client in context.fee_clients.clone() {
+            let payer = context.payer.clone();
+            let priority_fee = context.priority_fee.clone();
+            let tip_account = fee_client.get_tip_account().await.map_err(|e| anyhow!(e.to_string()))?;
+            let tip_account = Arc::new(Pubkey::from_str(&tip_account).map_err(|e| anyhow!(e))?);
 
-    for handle in handles {
-        match handle.await {
-            Ok(Ok(_)) => (),
-            Ok(Err(e)) => println!("Error in task: {}", e),
-            Err(e) => println!("Task join error: {}", e),
+            let transaction = build_buy_transaction_with_tip(tip_account, payer, priority_fee, instructions.clone(), extra_instructions.clone(), recent_blockhash).await?;
+            transactions.push(transaction);
         }
-    }
+        Ok::<_, anyhow::Error>(transactions)
+    })).await?;
+
+    context.submit(&mut timer, trade_id, transactions).await?;
 
-    Ok(())
+    Ok(timer.finish().with_fee_recipient(fee_recipient))
 }
 
 pub async fn build_buy_transaction(
@@ -86,25 +136,43 @@ pub async fn build_buy_transaction(
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: PriorityFee,
-) -> Result<Transaction, anyhow::Error> {
-    let mut instructions = vec![
-        ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(MAX_LOADED_ACCOUNTS_DATA_SIZE_LIMIT),
-        ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price),
-        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.unit_limit),
-    ];
-
-    let build_instructions = build_buy_instructions(rpc.clone(), payer.clone(), Arc::new(mint), amount_sol, slippage_basis_points).await?;
-    instructions.extend(build_instructions);
+    extra_instructions: Vec<Instruction>,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let (build_instructions, recent_blockhash) = tokio::try_join!(
+        build_buy_instructions(rpc.clone(), payer.clone(), Arc::new(mint), amount_sol, slippage_basis_points, SlippageMode::MaxSolCost, None, false, PartialFillGuard::default()),
+        async { Ok::<_, anyhow::Error>(rpc.get_latest_blockhash().await?) },
+    )?;
+    sign_buy_transaction(&payer, &priority_fee, build_instructions, extra_instructions, recent_blockhash)
+}
 
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );
+#[deprecated(note = "use `build_buy_transaction`, which returns a `VersionedTransaction`")]
+#[allow(deprecated)]
+pub async fn build_buy_transaction_legacy(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    mint: Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: PriorityFee,
+    extra_instructions: Vec<Instruction>,
+) -> Result<Transaction, anyhow::Error> {
+    let (build_instructions, recent_blockhash) = tokio::try_join!(
+        build_buy_instructions(rpc.clone(), payer.clone(), Arc::new(mint), amount_sol, slippage_basis_points, SlippageMode::MaxSolCost, None, false, PartialFillGuard::default()),
+        async { Ok::<_, anyhow::Error>(rpc.get_latest_blockhash().await?) },
+    )?;
+    let instructions = buy_transaction_instructions(&priority_fee, None, build_instructions, extra_instructions);
+    super::common::sign_legacy_transaction(&instructions, &[payer.as_ref()], recent_blockhash)
+}
 
-    Ok(transaction)
+fn sign_buy_transaction(
+    payer: &Keypair,
+    priority_fee: &PriorityFee,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+    blockhash: Hash,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let instructions = buy_transaction_instructions(priority_fee, None, build_instructions, extra_instructions);
+    sign_versioned_transaction(&instructions, &[payer], blockhash)
 }
 
 pub async fn build_buy_transaction_with_tip(
@@ -112,68 +180,288 @@ pub async fn build_buy_transaction_with_tip(
     payer: Arc<Keypair>,
     priority_fee: PriorityFee,  
     build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
     blockhash: Hash,
 ) -> Result<VersionedTransaction, anyhow::Error> {
+    let tip = system_instruction::transfer(
+        &payer.pubkey(),
+        &tip_account,
+        priority_fee.buy_tip_fee.lamports(),
+    );
+    let instructions = buy_transaction_instructions(&priority_fee, Some(tip), build_instructions, extra_instructions);
+    sign_versioned_transaction(&instructions, &[payer.as_ref()], blockhash)
+}
+
+/// Compute budget, optional tip, trade and extra instructions of a buy transaction
+pub(crate) fn buy_transaction_instructions(
+    priority_fee: &PriorityFee,
+    tip: Option<Instruction>,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+) -> Vec<Instruction> {
     let mut instructions = vec![
         ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(MAX_LOADED_ACCOUNTS_DATA_SIZE_LIMIT),
         ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price),
-        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.unit_limit),
-        system_instruction::transfer(
-            &payer.pubkey(),
-            &tip_account,
-            sol_to_lamports(priority_fee.buy_tip_fee),
-        ),
+        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.buy_limit()),
     ];
-
-    instructions.extend(build_instructions);
-
-    let v0_message: v0::Message =
-        v0::Message::try_compile(&payer.pubkey(), &instructions, &[], blockhash)?;
-    let versioned_message: VersionedMessage = VersionedMessage::V0(v0_message);
-    let transaction = VersionedTransaction::try_new(versioned_message, &[&payer])?;
-
-    Ok(transaction)
+    instructions.extend(tip);
+    instructions.extend(append_extra_instructions(build_instructions, extra_instructions));
+    dedup_compute_budget_instructions(instructions)
 }
 
+/// Builds the buy instructions, preceded by an idempotent creation of the payer's token
+/// account unless `ata_exists` says it was created beforehand, e.g. with `precreate_atas`
+///
+/// `slippage_mode` picks whether the tolerance pads the SOL cost, trims the token amount,
+/// or both; see [`SlippageMode`]. A buy from a nearly exhausted curve fails with
+/// [`CurveNearlyExhausted`](super::common::CurveNearlyExhausted) unless `partial_fill`
+/// allows it.
 pub async fn build_buy_instructions(
     rpc: Arc<SolanaRpcClient>,
     payer: Arc<Keypair>,
     mint: Arc<Pubkey>,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    post_trade_checks: Option<PostTradeChecks>,
+    ata_exists: bool,
+    partial_fill: PartialFillGuard,
 ) -> Result<Vec<Instruction>, anyhow::Error> {
     if amount_sol == 0 {
         return Err(anyhow!("Amount cannot be zero"));
     }
 
-    let rpc = rpc.as_ref();
-    let global_account = get_global_account(rpc).await?;
-    let buy_amount = match get_bonding_curve_account(rpc, mint.as_ref()).await {
-        Ok(account) => account.get_buy_price(amount_sol).map_err(|e| anyhow!(e))?,
-        Err(_e) => {
-            println!("Bonding curve account not found, using initial buy price: {}", _e);
-            let initial_buy_amount = get_initial_buy_price(&global_account, amount_sol).await?;
-            initial_buy_amount * 80 / 100
-        }
-    };
-    let buy_amount_with_slippage = calculate_with_slippage_buy(amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE));
-    let mut instructions = vec![];
-    instructions.push(create_associated_token_account(
+    let (global_account, curve, token_program) = get_buy_accounts(rpc.as_ref(), mint.as_ref()).await?;
+    build_buy_instructions_offline(
         &payer.pubkey(),
-        &payer.pubkey(),
-        &mint,
-        &constants::accounts::TOKEN_PROGRAM,
-    ));
+        mint.as_ref(),
+        amount_sol,
+        slippage_basis_points,
+        slippage_mode,
+        post_trade_checks,
+        ata_exists,
+        partial_fill,
+        &global_account,
+        visible_curve(&curve),
+        &token_program,
+    )
+}
+
+/// [`build_buy_instructions`] from chain state the caller supplies, without any RPC call
+///
+/// `curve` is the live bonding curve of `mint`, `None` if it is not visible yet, which quotes
+/// from the initial curve like [`get_buy_quote`](super::common::get_buy_quote);
+/// `token_program` owns the mint.
+#[allow(clippy::too_many_arguments)]
+pub fn build_buy_instructions_offline(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    post_trade_checks: Option<PostTradeChecks>,
+    ata_exists: bool,
+    partial_fill: PartialFillGuard,
+    global: &GlobalAccount,
+    curve: Option<&BondingCurveAccount>,
+    token_program: &Pubkey,
+) -> Result<Vec<Instruction>, anyhow::Error> {
+    if amount_sol == 0 {
+        return Err(anyhow!("Amount cannot be zero"));
+    }
+
+    let quote = buy_quote_from(curve, global, amount_sol, slippage_basis_points, slippage_mode, post_trade_checks)?;
+    if let Some(curve) = curve {
+        partial_fill.check(curve.real_token_reserves, amount_sol, &quote)?;
+    }
+    if quote.token_amount == 0 {
+        return Err(anyhow!("Amount buys no tokens"));
+    }
+    Ok(buy_quote_instructions(payer, mint, &global.fee_recipient, token_program, &quote, ata_exists))
+}
+
+/// Buy instructions for an already computed `quote`, preceded by an idempotent creation of
+/// the payer's token account unless `ata_exists`
+pub fn buy_quote_instructions(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    fee_recipient: &Pubkey,
+    token_program: &Pubkey,
+    quote: &BuyQuote,
+    ata_exists: bool,
+) -> Vec<Instruction> {
+    let mut instructions = vec![];
+    if !ata_exists {
+        instructions.push(create_associated_token_account_idempotent(
+            payer,
+            payer,
+            mint,
+            token_program,
+        ));
+    }
 
     instructions.push(instruction::buy(
-        payer.as_ref(),
-        &mint,
-        &global_account.fee_recipient,
+        payer,
+        mint,
+        fee_recipient,
+        token_program,
         instruction::Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
+            amount: quote.token_amount,
+            max_sol_cost: quote.max_sol_cost,
         },
     ));
+    instructions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::{Duration, Instant}};
+
+    use serde_json::{json, Value};
+    use solana_account_decoder::{UiAccount, UiAccountEncoding};
+    use solana_rpc_client::{rpc_client::RpcClientConfig, rpc_sender::{RpcSender, RpcTransportStats}};
+    use solana_rpc_client_api::{client_error::Result as ClientResult, request::RpcRequest};
+    use solana_sdk::{account::Account, commitment_config::CommitmentConfig, system_instruction::SystemInstruction, system_program};
+
+    use super::*;
+    use crate::{common::TipAmount, constants, pumpfun::common::{calculate_with_slippage_buy, get_bonding_curve_pda, get_buy_sol_after_fee, get_global_pda, quote_buy_with_mode, CurveNearlyExhausted}};
+
+    /// RPC node answering `getAccountInfo` from `accounts` after each address's delay;
+    /// addresses not listed don't exist
+    struct DelayedRpc {
+        accounts: HashMap<Pubkey, (Duration, Option<Account>)>,
+    }
 
-    Ok(instructions)
-}
\ No newline at end of file
+    #[async_trait::async_trait]
+    impl RpcSender for DelayedRpc {
+        async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+            assert_eq!(request, RpcRequest::GetAccountInfo);
+            let address = Pubkey::from_str(params[0].as_str().unwrap()).unwrap();
+            let (delay, account) = self.accounts.get(&address).cloned().unwrap_or_default();
+            tokio::time::sleep(delay).await;
+            let value = account.map(|account| UiAccount::encode(&address, &account, UiAccountEncoding::Base64, None, None));
+            Ok(json!({ "context": { "slot": 1 }, "value": value }))
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "delayed".to_string()
+        }
+    }
+
+    /// `amount` and `max_sol_cost` of the buy instruction built for `quote`
+    fn buy_fields(quote: &BuyQuote) -> (u64, u64) {
+        let instructions = buy_quote_instructions(&Pubkey::new_unique(), &Pubkey::new_unique(), &Pubkey::new_unique(), &constants::accounts::TOKEN_PROGRAM, quote, true);
+        let data = &instructions[0].data;
+        (u64::from_le_bytes(data[8..16].try_into().unwrap()), u64::from_le_bytes(data[16..24].try_into().unwrap()))
+    }
+
+    #[test]
+    fn test_slippage_modes_set_buy_fields() {
+        let curve = BondingCurveAccount::new(0, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000, false);
+        let (amount_sol, fee_basis_points, slippage) = (1_000_000_000, 100, 500);
+        let quoted_tokens = curve.get_buy_price(get_buy_sol_after_fee(amount_sol, fee_basis_points)).unwrap();
+        let reduced_tokens = quoted_tokens - quoted_tokens * slippage / 10_000;
+
+        let quote = |mode| quote_buy_with_mode(&curve, amount_sol, fee_basis_points, slippage, mode).unwrap();
+        let max_sol_cost = quote(SlippageMode::MaxSolCost);
+        assert_eq!(buy_fields(&max_sol_cost), (quoted_tokens, calculate_with_slippage_buy(max_sol_cost.sol_cost, slippage)));
+        assert!(max_sol_cost.max_sol_cost > amount_sol);
+
+        let min_tokens_out = quote(SlippageMode::MinTokensOut);
+        assert_eq!(buy_fields(&min_tokens_out), (reduced_tokens, amount_sol));
+        assert!(min_tokens_out.sol_cost < amount_sol);
+
+        let both = quote(SlippageMode::Both);
+        assert_eq!(buy_fields(&both), (reduced_tokens, calculate_with_slippage_buy(amount_sol, slippage)));
+    }
+
+    fn global(fee_recipient: Pubkey) -> GlobalAccount {
+        GlobalAccount::new(0, true, Pubkey::new_unique(), fee_recipient, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100)
+    }
+
+    #[test]
+    fn test_offline_buy_uses_only_the_supplied_state() {
+        let (payer, mint, fee_recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let global = global(fee_recipient);
+        let curve = BondingCurveAccount::new(0, 900_000_000_000_000, 35_000_000_000, 620_000_000_000_000, 5_000_000_000, 1_000_000_000_000_000, false);
+        let token_program = constants::accounts::TOKEN_2022_PROGRAM;
+        let build = |curve: Option<&BondingCurveAccount>, ata_exists, post_trade_checks, partial_fill| {
+            build_buy_instructions_offline(&payer, &mint, 1_000_000_000, Some(500), SlippageMode::MaxSolCost, post_trade_checks, ata_exists, partial_fill, &global, curve, &token_program)
+        };
+
+        let instructions = build(Some(&curve), false, None, PartialFillGuard::default()).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0], create_associated_token_account_idempotent(&payer, &payer, &mint, &token_program));
+        let quote = quote_buy_with_mode(&curve, 1_000_000_000, 100, 500, SlippageMode::MaxSolCost).unwrap();
+        assert_eq!(instructions[1], buy_quote_instructions(&payer, &mint, &fee_recipient, &token_program, &quote, true)[0]);
+        assert_eq!(instructions[1].accounts[1].pubkey, fee_recipient);
+        assert!(instructions[1].accounts.iter().any(|meta| meta.pubkey == payer && meta.is_signer));
+        assert_eq!(build(Some(&curve), true, None, PartialFillGuard::default()).unwrap(), instructions[1..]);
+
+        // A curve not visible yet is quoted from the initial curve, at 80% of its tokens
+        let fallback = build(None, true, None, PartialFillGuard::default()).unwrap();
+        let initial = build(Some(&global.initial_bonding_curve()), true, None, PartialFillGuard::default()).unwrap();
+        let tokens = |instructions: &[Instruction]| u64::from_le_bytes(instructions[0].data[8..16].try_into().unwrap());
+        assert_eq!(tokens(&fallback), buy_quote_from(None, &global, 1_000_000_000, Some(500), SlippageMode::MaxSolCost, None).unwrap().token_amount);
+        assert!(tokens(&fallback) < tokens(&initial));
+
+        let checks = PostTradeChecks { max_sol_spend: 2_000_000_000, min_tokens_out: quote.token_amount + 1 };
+        assert!(build(Some(&curve), true, Some(checks), PartialFillGuard::default()).unwrap_err().to_string().contains("Post-trade check failed"));
+        assert_eq!(build_buy_instructions_offline(&payer, &mint, 0, None, SlippageMode::MaxSolCost, None, true, PartialFillGuard::default(), &global, Some(&curve), &token_program).unwrap_err().to_string(), "Amount cannot be zero");
+    }
+
+    #[test]
+    fn test_offline_buy_refuses_exhausted_curves_unless_allowed() {
+        let global = global(Pubkey::new_unique());
+        let curve = BondingCurveAccount::new(0, 280_000_000_000_000, 114_900_000_000, 1_000_000_000, 84_900_000_000, 1_000_000_000_000_000, false);
+        let build = |partial_fill| {
+            build_buy_instructions_offline(&Pubkey::new_unique(), &Pubkey::new_unique(), 1_000_000_000, None, SlippageMode::MaxSolCost, None, true, partial_fill, &global, Some(&curve), &constants::accounts::TOKEN_PROGRAM)
+        };
+
+        let error = build(PartialFillGuard::default()).unwrap_err();
+        assert_eq!(CurveNearlyExhausted::find(&error).unwrap().remaining_tokens, 1_000_000_000);
+        assert_eq!(build(PartialFillGuard { allow_partial_fill: true, ..Default::default() }).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quote_accounts_are_fetched_concurrently() {
+        let mint = Pubkey::new_unique();
+        let global = GlobalAccount::new(0, true, Pubkey::new_unique(), Pubkey::new_unique(), 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100);
+        let curve = BondingCurveAccount::new(0, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000, false);
+        let account = |data: Vec<u8>| Account { lamports: 1_000_000, data, owner: constants::accounts::PUMPFUN, ..Default::default() };
+        let sender = DelayedRpc {
+            accounts: HashMap::from([
+                (get_global_pda(), (Duration::from_millis(100), Some(account(bincode::serialize(&global).unwrap())))),
+                (get_bonding_curve_pda(&mint).unwrap(), (Duration::from_millis(300), Some(account(borsh::to_vec(&curve).unwrap())))),
+                // A mint that can't be read resolves to the legacy token program
+                (mint, (Duration::from_millis(200), None)),
+            ]),
+        };
+        let rpc = Arc::new(SolanaRpcClient::new_sender(sender, RpcClientConfig::with_commitment(CommitmentConfig::confirmed())));
+
+        let started = Instant::now();
+        let instructions = build_buy_instructions(rpc, Arc::new(Keypair::new()), Arc::new(mint), 1_000_000_000, None, SlippageMode::MaxSolCost, None, true, PartialFillGuard::default()).await.unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(instructions.len(), 1);
+        // The slowest fetch, not the 600ms the three take one after the other
+        assert!(elapsed >= Duration::from_millis(300) && elapsed < Duration::from_millis(450), "took {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_tip_is_the_configured_lamports() {
+        let payer = Arc::new(Keypair::new());
+        let priority_fee = PriorityFee { buy_tip_fee: TipAmount::Lamports(1_000_000), ..Default::default() };
+        let transaction = build_buy_transaction_with_tip(Arc::new(Pubkey::new_unique()), payer, priority_fee, vec![], vec![], Hash::default()).await.unwrap();
+
+        let keys = transaction.message.static_account_keys();
+        let tip = transaction.message.instructions().iter()
+            .find(|ix| keys[ix.program_id_index as usize] == system_program::id())
+            .unwrap();
+        assert_eq!(bincode::deserialize::<SystemInstruction>(&tip.data).unwrap(), SystemInstruction::Transfer { lamports: 1_000_000 });
+    }
+}
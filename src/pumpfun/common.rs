@@ -1,18 +1,29 @@
 use anyhow::anyhow;
 use spl_token::state::Account;
 use tokio::sync::RwLock;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::{Duration, Instant}};
 use solana_sdk::{
-    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, instruction::Instruction, program_pack::Pack, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::Transaction
+    account::Account as SolanaAccount, compute_budget::{self, ComputeBudgetInstruction}, instruction::Instruction, message::{v0, VersionedMessage}, packet::PACKET_DATA_SIZE, program_pack::Pack, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
 };
-use spl_associated_token_account::get_associated_token_address;
-use crate::{accounts, common::{logs_data::TradeInfo, PriorityFee, SolanaRpcClient}, constants::{self, trade::DEFAULT_SLIPPAGE}};
-use borsh::BorshDeserialize;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_hash::Hash;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use crate::{accounts, common::{cache_stats::{CacheCounters, CacheStats}, latency::{Stage, StageTimer}, logs_data::TradeInfo, PriorityFee, SolanaRpcClient}, constants::{self, trade::DEFAULT_SLIPPAGE}, error::PumpFunError, fee_clients::ClientType, trade::{cancel::{CancellationToken, Cancelled}, confirm::ConfirmLevel, journal::{self, JournalEntry, TradeId}, transport::RpcTransport, RpcSendOptions, SendMode, SubmitOptions, Submitter}};
+
+use super::curve_cache::{bonding_curve_cache, CurveRead, CurveSource};
 
 lazy_static::lazy_static! {
-    static ref ACCOUNT_CACHE: RwLock<HashMap<Pubkey, Arc<accounts::GlobalAccount>>> = RwLock::new(HashMap::new());
+    /// Global account, when it was fetched and the slot it was read at
+    static ref ACCOUNT_CACHE: RwLock<HashMap<Pubkey, (Arc<accounts::GlobalAccount>, Instant, u64)>> = RwLock::new(HashMap::new());
+    static ref TOKEN_PROGRAM_CACHE: RwLock<HashMap<Pubkey, (Pubkey, Instant)>> = RwLock::new(HashMap::new());
 }
 
+/// Most accounts a single `getMultipleAccounts` call returns
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+static GLOBAL_ACCOUNT_COUNTERS: CacheCounters = CacheCounters::new();
+static TOKEN_PROGRAM_COUNTERS: CacheCounters = CacheCounters::new();
+
 pub async fn transfer_sol(rpc: &SolanaRpcClient, payer: &Keypair, receive_wallet: &Pubkey, amount: u64) -> Result<(), anyhow::Error> {
     if amount == 0 {
         return Err(anyhow!("transfer_sol: Amount cannot be zero"));
@@ -29,32 +40,256 @@ pub async fn transfer_sol(rpc: &SolanaRpcClient, payer: &Keypair, receive_wallet
         amount,
     );
 
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    send_and_journal(rpc, &[transfer_instruction], payer).await?;
+    Ok(())
+}
 
-    let transaction = Transaction::new_signed_with_payer(
-        &[transfer_instruction],
-        Some(&payer.pubkey()),
-        &[payer],
-        recent_blockhash,
-    );  
+/// Approves `delegate` to move up to `amount` of the owner's tokens of `mint`, e.g. for a
+/// custodial executor selling with `SellAuthority::Delegate`
+pub async fn approve_delegate(rpc: &SolanaRpcClient, owner: &Keypair, mint: &Pubkey, delegate: &Pubkey, amount: u64) -> Result<Signature, anyhow::Error> {
+    let token_program = get_token_program(rpc, mint).await?;
+    let ata = get_ata(&owner.pubkey(), mint, &token_program);
+    let approve = spl_token_2022::instruction::approve(&token_program, &ata, delegate, &owner.pubkey(), &[], amount)?;
+    send_and_journal(rpc, &[approve], owner).await
+}
 
-    rpc.send_and_confirm_transaction(&transaction).await?;
+/// Revokes whatever delegate the owner's token account of `mint` has
+pub async fn revoke_delegate(rpc: &SolanaRpcClient, owner: &Keypair, mint: &Pubkey) -> Result<Signature, anyhow::Error> {
+    let token_program = get_token_program(rpc, mint).await?;
+    let ata = get_ata(&owner.pubkey(), mint, &token_program);
+    let revoke = spl_token_2022::instruction::revoke(&token_program, &ata, &owner.pubkey(), &[])?;
+    send_and_journal(rpc, &[revoke], owner).await
+}
 
-    Ok(())
+/// Signs `instructions` with `payer` alone and sends them over RPC, journaling the outcome
+async fn send_and_journal(rpc: &SolanaRpcClient, instructions: &[Instruction], payer: &Keypair) -> Result<Signature, anyhow::Error> {
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let transaction = sign_versioned_transaction(instructions, &[payer], recent_blockhash)?;
+
+    let signature = transaction.signatures[0];
+    let result = rpc.send_and_confirm_transaction(&transaction).await;
+    journal::record(None, JournalEntry::Submitted {
+        signature,
+        transport: ClientType::Rpc,
+        tip_lamports: None,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+    if result.is_ok() {
+        journal::record(None, JournalEntry::Resolved { signature, transport: ClientType::Rpc, confirmed: true, landed_slot: None, error: None });
+    }
+    result?;
+
+    Ok(signature)
 }
 
+/// Compute budget instructions requesting `unit_limit` compute units at the price of
+/// `priority_fee`
+///
+/// Only the transaction builders (`build_*_transaction*`) inject compute budget instructions;
+/// the instruction builders (`build_*_instructions`) never do. Instruction lists passed to a
+/// transaction builder may carry their own compute budget instructions, which replace the
+/// injected ones of the same kind (see [`dedup_compute_budget_instructions`]).
 #[inline]
-pub fn create_priority_fee_instructions(priority_fee: PriorityFee) -> Vec<Instruction> {
+pub fn create_priority_fee_instructions(priority_fee: PriorityFee, unit_limit: u32) -> Vec<Instruction> {
     let mut instructions = Vec::with_capacity(2);
-    instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.unit_limit));
+    instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(unit_limit));
     instructions.push(ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price));
     
     instructions
 }
 
+/// Keeps only the last compute budget instruction of each kind, preserving the order of
+/// everything else
+///
+/// The runtime rejects transactions that repeat a compute budget instruction, so every
+/// transaction builder runs its final instruction list through this.
+pub fn dedup_compute_budget_instructions(instructions: Vec<Instruction>) -> Vec<Instruction> {
+    let mut seen = HashSet::new();
+    let mut deduped: Vec<Instruction> = instructions
+        .into_iter()
+        .rev()
+        .filter(|instruction| {
+            instruction.program_id != compute_budget::id()
+                || seen.insert(instruction.data.first().copied())
+        })
+        .collect();
+    deduped.reverse();
+    deduped
+}
+
+/// Appends `extra_instructions` after the trade instructions but before any trailing
+/// close-account instructions, so user instructions still see the token account
+pub fn append_extra_instructions(mut instructions: Vec<Instruction>, extra_instructions: Vec<Instruction>) -> Vec<Instruction> {
+    let closing = instructions.iter().rev().take_while(|instruction| is_close_account(instruction)).count();
+    let at = instructions.len() - closing;
+    instructions.splice(at..at, extra_instructions);
+    instructions
+}
+
+#[inline]
+fn is_close_account(instruction: &Instruction) -> bool {
+    const CLOSE_ACCOUNT_TAG: u8 = 9;
+    (instruction.program_id == constants::accounts::TOKEN_PROGRAM
+        || instruction.program_id == constants::accounts::TOKEN_2022_PROGRAM)
+        && instruction.data.first() == Some(&CLOSE_ACCOUNT_TAG)
+}
+
+/// Compiles `instructions` into a v0 message paid by the first of `signers`, signs it and
+/// checks that it fits in a packet
+///
+/// Fails with a [`SignerMismatch`] naming the keys when `signers` are not exactly the
+/// signers the instructions require.
+pub fn sign_versioned_transaction(
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+    blockhash: Hash,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let signer_keys: Vec<Pubkey> = signers.iter().map(|signer| signer.pubkey()).collect();
+    let message = compile_unsigned_message(instructions, &signer_keys, blockhash)?;
+    let transaction = VersionedTransaction::try_new(message, signers)?;
+    check_transaction_size(&transaction)?;
+    Ok(transaction)
+}
+
+/// Legacy counterpart of [`sign_versioned_transaction`], backing the deprecated `*_legacy`
+/// builders
+#[deprecated(note = "use `sign_versioned_transaction`")]
+pub fn sign_legacy_transaction(
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+    blockhash: Hash,
+) -> Result<Transaction, anyhow::Error> {
+    let payer = signers.first().ok_or(anyhow!("A transaction needs at least one signer"))?;
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    transaction.try_sign(signers, blockhash)?;
+    check_transaction_size(&transaction)?;
+    Ok(transaction)
+}
+
+/// Compiles `instructions` into a v0 message paid by the first of `signers`, to be signed
+/// elsewhere by exactly `signers`
+///
+/// Fails with a [`SignerMismatch`] when the message requires other signers.
+pub fn compile_unsigned_message(instructions: &[Instruction], signers: &[Pubkey], blockhash: Hash) -> Result<VersionedMessage, anyhow::Error> {
+    let payer = signers.first().ok_or(anyhow!("A transaction needs at least one signer"))?;
+    let message = VersionedMessage::V0(v0::Message::try_compile(payer, instructions, &[], blockhash)?);
+    check_signers(&message, signers)?;
+    Ok(message)
+}
+
+/// Keys that must sign `message`, the fee payer first
+#[inline]
+pub fn required_signers(message: &VersionedMessage) -> &[Pubkey] {
+    let keys = message.static_account_keys();
+    &keys[..(message.header().num_required_signatures as usize).min(keys.len())]
+}
+
+/// Checks that `signers` are exactly the keys that must sign `message`, reporting missing
+/// signers before unexpected ones
+pub fn check_signers(message: &VersionedMessage, signers: &[Pubkey]) -> Result<(), SignerMismatch> {
+    let required = required_signers(message);
+    let mut missing: Vec<Pubkey> = required.iter().filter(|key| !signers.contains(key)).copied().collect();
+    missing.dedup();
+    if !missing.is_empty() {
+        return Err(SignerMismatch::MissingSigners(missing));
+    }
+    let mut unexpected: Vec<Pubkey> = vec![];
+    for key in signers.iter().filter(|key| !required.contains(key)) {
+        if !unexpected.contains(key) {
+            unexpected.push(*key);
+        }
+    }
+    if !unexpected.is_empty() {
+        return Err(SignerMismatch::UnexpectedSigners(unexpected));
+    }
+    Ok(())
+}
+
+/// The keys given to sign a message differ from the signers its accounts require
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SignerMismatch {
+    /// Required signers no keypair was given for
+    #[error("Transaction is missing signatures of {}", join_keys(.0))]
+    MissingSigners(Vec<Pubkey>),
+    /// Keypairs given for keys the message does not need signatures of
+    #[error("Transaction does not need the signatures of {}", join_keys(.0))]
+    UnexpectedSigners(Vec<Pubkey>),
+}
+
+impl SignerMismatch {
+    /// The signer mismatch somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&SignerMismatch> {
+        error.chain().find_map(|cause| cause.downcast_ref::<SignerMismatch>())
+    }
+}
+
+fn join_keys(keys: &[Pubkey]) -> String {
+    keys.iter().map(Pubkey::to_string).collect::<Vec<_>>().join(", ")
+}
+
+/// Rejects transactions that don't fit in a single packet
+pub fn check_transaction_size<T: serde::Serialize>(transaction: &T) -> Result<(), anyhow::Error> {
+    let size = bincode::serialized_size(transaction)? as usize;
+    if size > PACKET_DATA_SIZE {
+        return Err(anyhow!("Transaction is {} bytes, exceeding the {}-byte packet limit", size, PACKET_DATA_SIZE));
+    }
+    Ok(())
+}
+
+/// Returns the token program owning a mint account, rejecting anything that is not
+/// the legacy SPL Token or the Token-2022 program.
+#[inline]
+pub fn token_program_for_account(mint_account: &SolanaAccount) -> Result<Pubkey, anyhow::Error> {
+    let owner = mint_account.owner;
+    if owner == constants::accounts::TOKEN_PROGRAM || owner == constants::accounts::TOKEN_2022_PROGRAM {
+        Ok(owner)
+    } else {
+        Err(anyhow!("Mint is owned by {}, which is not a token program", owner))
+    }
+}
+
+/// Resolves the token program of `mint` from the owner of its mint account.
+///
+/// Results are cached per mint. A mint that does not exist yet (e.g. one that is
+/// about to be created by Pump.fun) resolves to the legacy SPL Token program; RPC errors
+/// are returned, so a Token-2022 mint is never traded under the wrong program.
+pub async fn get_token_program(rpc: &SolanaRpcClient, mint: &Pubkey) -> Result<Pubkey, anyhow::Error> {
+    if let Some((program, _)) = TOKEN_PROGRAM_COUNTERS.record(TOKEN_PROGRAM_CACHE.read().await.get(mint).copied()) {
+        return Ok(program);
+    }
+
+    let Some(account) = rpc.get_account_with_commitment(mint, rpc.commitment()).await?.value else {
+        return Ok(constants::accounts::TOKEN_PROGRAM);
+    };
+
+    let program = token_program_for_account(&account)?;
+    TOKEN_PROGRAM_CACHE.write().await.insert(*mint, (program, Instant::now()));
+    Ok(program)
+}
+
+/// Derives the associated token account of `owner` for `mint` under `token_program`
+///
+/// # Example
+///
+/// ```
+/// use std::str::FromStr;
+/// use solana_sdk::pubkey::Pubkey;
+/// use pumpfun_sdk::{constants, pumpfun::common::get_ata};
+///
+/// let owner = Pubkey::from_str("CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM").unwrap();
+/// let mint = Pubkey::from_str("9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump").unwrap();
+/// let ata = get_ata(&owner, &mint, &constants::accounts::TOKEN_PROGRAM);
+/// assert_eq!(ata.to_string(), "Hef95Xg4LAHNT7kwmfQi1pZRFXhGQkXynSWY6FqSRBqh");
+/// ```
+#[inline]
+pub fn get_ata(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(owner, mint, token_program)
+}
+
 // #[inline]
 pub async fn get_token_balance(rpc: &SolanaRpcClient, payer: &Pubkey, mint: &Pubkey) -> Result<u64, anyhow::Error> {
-    let ata = get_associated_token_address(payer, mint);
+    let token_program = get_token_program(rpc, mint).await?;
+    let ata = get_ata(payer, mint, &token_program);
     // let account_data = rpc.get_account_data(&ata).await?;
     // let token_account = Account::unpack(&account_data.as_slice())?;
 
@@ -69,7 +304,8 @@ pub async fn get_token_balance(rpc: &SolanaRpcClient, payer: &Pubkey, mint: &Pub
 
 #[inline]
 pub async fn get_token_balance_and_ata(rpc: &SolanaRpcClient, payer: &Keypair, mint: &Pubkey) -> Result<(u64, Pubkey), anyhow::Error> {
-    let ata = get_associated_token_address(&payer.pubkey(), mint);
+    let token_program = get_token_program(rpc, mint).await?;
+    let ata = get_ata(&payer.pubkey(), mint, &token_program);
     // let account_data = rpc.get_account_data(&ata).await?;
     // let token_account = Account::unpack(&account_data)?;
 
@@ -86,6 +322,53 @@ pub async fn get_token_balance_and_ata(rpc: &SolanaRpcClient, payer: &Keypair, m
     Ok((balance_u64, ata))
 }
 
+/// Amount held by a token account; Token-2022 accounts start with the same layout
+pub fn token_account_amount(account: &SolanaAccount) -> u64 {
+    account
+        .data
+        .get(..Account::LEN)
+        .and_then(|data| Account::unpack(data).ok())
+        .map_or(0, |token_account| token_account.amount)
+}
+
+/// SOL balance of a wallet and its token balances
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalletBalances {
+    pub lamports: u64,
+    /// Balance of each requested mint, zero if its token account doesn't exist
+    pub tokens: HashMap<Pubkey, u64>,
+}
+
+/// SOL balance of `owner` and its balances of `mints`, batched into `getMultipleAccounts`
+/// calls of up to 100 accounts: one pass over the wallet and the mints, one over the token
+/// accounts
+pub async fn get_wallet_balances(rpc: &SolanaRpcClient, owner: &Pubkey, mints: &[Pubkey]) -> Result<WalletBalances, anyhow::Error> {
+    let addresses: Vec<Pubkey> = std::iter::once(*owner).chain(mints.iter().copied()).collect();
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(rpc.get_multiple_accounts(chunk).await?);
+    }
+    let lamports = accounts[0].as_ref().map_or(0, |account| account.lamports);
+
+    // a mint that doesn't exist has no token account either; the legacy program is assumed
+    let atas: Vec<Pubkey> = mints
+        .iter()
+        .zip(&accounts[1..])
+        .map(|(mint, account)| {
+            let token_program = account.as_ref().and_then(|account| token_program_for_account(account).ok()).unwrap_or(constants::accounts::TOKEN_PROGRAM);
+            get_ata(owner, mint, &token_program)
+        })
+        .collect();
+    let mut tokens = HashMap::with_capacity(mints.len());
+    for (chunk_index, chunk) in atas.chunks(MAX_MULTIPLE_ACCOUNTS).enumerate() {
+        for (offset, account) in rpc.get_multiple_accounts(chunk).await?.iter().enumerate() {
+            let mint = mints[chunk_index * MAX_MULTIPLE_ACCOUNTS + offset];
+            tokens.insert(mint, account.as_ref().map_or(0, token_account_amount));
+        }
+    }
+    Ok(WalletBalances { lamports, tokens })
+}
+
 #[inline]
 pub async fn get_sol_balance(rpc: &SolanaRpcClient, account: &Pubkey) -> Result<u64, anyhow::Error> {
     println!("get_sol_balance account: {}", account);
@@ -94,6 +377,15 @@ pub async fn get_sol_balance(rpc: &SolanaRpcClient, account: &Pubkey) -> Result<
     Ok(balance)
 }
 
+/// Returns the Pump.fun global state PDA
+///
+/// # Example
+///
+/// ```
+/// use pumpfun_sdk::pumpfun::common::get_global_pda;
+///
+/// assert_eq!(get_global_pda().to_string(), "4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf");
+/// ```
 #[inline]
 pub fn get_global_pda() -> Pubkey {
     static GLOBAL_PDA: once_cell::sync::Lazy<Pubkey> = once_cell::sync::Lazy::new(|| {
@@ -102,6 +394,15 @@ pub fn get_global_pda() -> Pubkey {
     *GLOBAL_PDA
 }
 
+/// Returns the PDA that Pump.fun uses as mint authority for every token it creates
+///
+/// # Example
+///
+/// ```
+/// use pumpfun_sdk::pumpfun::common::get_mint_authority_pda;
+///
+/// assert_eq!(get_mint_authority_pda().to_string(), "TSLvdd1pWpHVjahSpsvCXUbgwsL3JAcvokwaKt1eokM");
+/// ```
 #[inline]
 pub fn get_mint_authority_pda() -> Pubkey {
     static MINT_AUTHORITY_PDA: once_cell::sync::Lazy<Pubkey> = once_cell::sync::Lazy::new(|| {
@@ -110,6 +411,19 @@ pub fn get_mint_authority_pda() -> Pubkey {
     *MINT_AUTHORITY_PDA
 }
 
+/// Returns the bonding curve PDA of `mint`
+///
+/// # Example
+///
+/// ```
+/// use std::str::FromStr;
+/// use solana_sdk::pubkey::Pubkey;
+/// use pumpfun_sdk::pumpfun::common::get_bonding_curve_pda;
+///
+/// let mint = Pubkey::from_str("9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump").unwrap();
+/// let bonding_curve = get_bonding_curve_pda(&mint).unwrap();
+/// assert_eq!(bonding_curve.to_string(), "TBHe5tJnuT4CQbHorJ1uVdfUoaYGPKgfCpiv2jgesVN");
+/// ```
 #[inline]
 pub fn get_bonding_curve_pda(mint: &Pubkey) -> Option<Pubkey> {
     let seeds: &[&[u8]; 2] = &[constants::seeds::BONDING_CURVE_SEED, mint.as_ref()];
@@ -118,6 +432,18 @@ pub fn get_bonding_curve_pda(mint: &Pubkey) -> Option<Pubkey> {
     pda.map(|pubkey| pubkey.0)
 }
 
+/// Returns the Metaplex metadata PDA of `mint`
+///
+/// # Example
+///
+/// ```
+/// use std::str::FromStr;
+/// use solana_sdk::pubkey::Pubkey;
+/// use pumpfun_sdk::pumpfun::common::get_metadata_pda;
+///
+/// let mint = Pubkey::from_str("9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump").unwrap();
+/// assert_eq!(get_metadata_pda(&mint).to_string(), "9XQ1xyaxP4ALeRbzj9Rm9KByCuwcLjf5yJ4nbyKLxG5u");
+/// ```
 #[inline]
 pub fn get_metadata_pda(mint: &Pubkey) -> Pubkey {
     Pubkey::find_program_address(
@@ -133,41 +459,121 @@ pub fn get_metadata_pda(mint: &Pubkey) -> Pubkey {
 #[inline]
 pub async fn get_global_account(rpc: &SolanaRpcClient) -> Result<Arc<accounts::GlobalAccount>, anyhow::Error> {
     let global = get_global_pda();
-    if let Some(account) = ACCOUNT_CACHE.read().await.get(&global) {
-        return Ok(account.clone());
+    if let Some((account, _, _)) = GLOBAL_ACCOUNT_COUNTERS.record(ACCOUNT_CACHE.read().await.get(&global).cloned()) {
+        return Ok(account);
     }
 
-    let account = rpc.get_account(&global).await?;
+    let response = rpc.get_account_with_commitment(&global, rpc.commitment()).await?;
+    let account = response.value.ok_or(anyhow!("Global account {} not found", global))?;
     let global_account = bincode::deserialize::<accounts::GlobalAccount>(&account.data)?;
     let global_account = Arc::new(global_account);
 
-    ACCOUNT_CACHE.write().await.insert(global, global_account.clone());
+    ACCOUNT_CACHE.write().await.insert(global, (global_account.clone(), Instant::now(), response.context.slot));
     Ok(global_account)
 }
 
+/// When the cached global account was fetched and the slot it was read at, if it is cached
+pub async fn global_account_fetched() -> Option<(Instant, u64)> {
+    ACCOUNT_CACHE.read().await.get(&get_global_pda()).map(|(_, fetched_at, slot)| (*fetched_at, *slot))
+}
+
+/// Drops the cached global account, so the next trade refetches it
+pub async fn invalidate_global_account() {
+    ACCOUNT_CACHE.write().await.remove(&get_global_pda());
+}
+
+/// Stats of the global account and token program caches
+pub async fn account_cache_stats() -> Vec<CacheStats> {
+    let global = ACCOUNT_CACHE.read().await;
+    let token_programs = TOKEN_PROGRAM_CACHE.read().await;
+    vec![
+        GLOBAL_ACCOUNT_COUNTERS.stats("global_account", global.len(), global.values().map(|(_, at, _)| at.elapsed()).max()),
+        TOKEN_PROGRAM_COUNTERS.stats("token_program", token_programs.len(), token_programs.values().map(|(_, at)| at.elapsed()).max()),
+    ]
+}
+
+/// Empties the global account and token program caches; readers refetch on their next use
+pub async fn clear_account_caches() {
+    ACCOUNT_CACHE.write().await.clear();
+    TOKEN_PROGRAM_CACHE.write().await.clear();
+}
+
 #[inline]
 pub async fn get_initial_buy_price(global_account: &Arc<accounts::GlobalAccount>, amount_sol: u64) -> Result<u64, anyhow::Error> {
     let buy_amount = global_account.get_initial_buy_price(amount_sol);
     Ok(buy_amount)
 }
 
+/// Global account, live bonding curve and token program of `mint`, fetched concurrently
+///
+/// A buy can be quoted without the curve, see [`buy_quote_from_curve`], so a failed curve
+/// fetch is returned rather than failing the call.
+pub async fn get_buy_accounts(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+) -> Result<(Arc<accounts::GlobalAccount>, Result<Arc<accounts::BondingCurveAccount>, anyhow::Error>, Pubkey), anyhow::Error> {
+    tokio::try_join!(
+        get_global_account(rpc),
+        async { Ok::<_, anyhow::Error>(get_bonding_curve_account_with(rpc, mint, CurveRead::Fresh).await) },
+        get_token_program(rpc, mint),
+    )
+}
+
+/// Reads the bonding curve of `mint`, from the bonding curve cache when it holds a valid entry
 #[inline]
 pub async fn get_bonding_curve_account(
     rpc: &SolanaRpcClient,
     mint: &Pubkey,
 ) -> Result<Arc<accounts::BondingCurveAccount>, anyhow::Error> {
+    get_bonding_curve_account_with(rpc, mint, CurveRead::Cached).await
+}
+
+pub async fn get_bonding_curve_account_with(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+    read: CurveRead,
+) -> Result<Arc<accounts::BondingCurveAccount>, anyhow::Error> {
+    let cache = bonding_curve_cache(rpc);
+    if read == CurveRead::Cached {
+        if let Some(entry) = cache.get(mint) {
+            return Ok(entry.account);
+        }
+    }
+
     let bonding_curve_pda = get_bonding_curve_pda(mint)
         .ok_or(anyhow!("Bonding curve not found"))?;
 
-    let account = rpc.get_account(&bonding_curve_pda).await?;
-    if account.data.is_empty() {
-        return Err(anyhow!("Bonding curve not found"));
-    }
+    let response = rpc.get_account_with_commitment(&bonding_curve_pda, rpc.commitment()).await?;
+    let account = match response.value {
+        Some(account) if !account.data.is_empty() => account,
+        _ => return Err(anyhow!("Bonding curve not found")),
+    };
 
-    let bonding_curve = Arc::new(accounts::BondingCurveAccount::try_from_slice(&account.data)?);
+    let bonding_curve = Arc::new(accounts::BondingCurveAccount::from_account_data(&account.data)?);
+    cache.insert(*mint, response.context.slot, bonding_curve.clone(), CurveSource::Rpc);
     Ok(bonding_curve)
 }
 
+/// Fetches the bonding curve of `mint` as a [`BondingCurveView`](accounts::BondingCurveView),
+/// reading fields from the raw data without decoding it
+///
+/// Always reads the account from RPC; share the returned view between threads quoting from it.
+pub async fn get_bonding_curve_view(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+) -> Result<Arc<accounts::BondingCurveView>, anyhow::Error> {
+    let bonding_curve_pda = get_bonding_curve_pda(mint)
+        .ok_or(anyhow!("Bonding curve not found"))?;
+
+    let response = rpc.get_account_with_commitment(&bonding_curve_pda, rpc.commitment()).await?;
+    let account = match response.value {
+        Some(account) if !account.data.is_empty() => account,
+        _ => return Err(anyhow!("Bonding curve not found")),
+    };
+
+    Ok(Arc::new(accounts::BondingCurveView::new(response.context.slot, account.data)?))
+}
+
 #[inline]
 pub fn get_buy_amount_with_slippage(amount_sol: u64, slippage_basis_points: Option<u64>) -> u64 {
     let slippage = slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE);
@@ -176,9 +582,7 @@ pub fn get_buy_amount_with_slippage(amount_sol: u64, slippage_basis_points: Opti
 
 #[inline]
 pub fn get_token_price(virtual_sol_reserves: u64, virtual_token_reserves: u64) -> f64 {
-    let v_sol = virtual_sol_reserves as f64 / 100_000_000.0;
-    let v_tokens = virtual_token_reserves as f64 / 100_000.0;
-    v_sol / v_tokens
+    constants::tokens::token_price_sol(virtual_sol_reserves, virtual_token_reserves)
 }
 
 #[inline]
@@ -205,3 +609,1014 @@ pub fn calculate_with_slippage_buy(amount: u64, basis_points: u64) -> u64 {
 pub fn calculate_with_slippage_sell(amount: u64, basis_points: u64) -> u64 {
     amount - (amount * basis_points) / 10000
 }
+
+/// How long `send_and_confirm_with_rpc` waits for confirmation
+pub const RPC_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Sends `transaction` over RPC, recording `Submit` and `Confirm` stages on `timer` and
+/// journaling under `trade_id`
+///
+/// Waits up to [`RPC_CONFIRMATION_TIMEOUT`] for the RPC client's commitment.
+pub async fn send_and_confirm_with_rpc(
+    timer: &mut StageTimer,
+    rpc: &Arc<SolanaRpcClient>,
+    trade_id: Option<TradeId>,
+    transaction: &VersionedTransaction,
+) -> Result<Signature, anyhow::Error> {
+    send_and_confirm_with_rpc_options(timer, rpc, trade_id, transaction, RpcSendOptions::default()).await
+}
+
+/// [`send_and_confirm_with_rpc`] with the preflight and retry settings of `rpc_send_options`
+pub async fn send_and_confirm_with_rpc_options(
+    timer: &mut StageTimer,
+    rpc: &Arc<SolanaRpcClient>,
+    trade_id: Option<TradeId>,
+    transaction: &VersionedTransaction,
+    rpc_send_options: RpcSendOptions,
+) -> Result<Signature, anyhow::Error> {
+    send_and_confirm_cancellable(timer, rpc, trade_id, transaction, rpc_send_options, None).await
+}
+
+/// [`send_and_confirm_with_rpc_options`], stopping once `cancel` is cancelled
+///
+/// Fails with [`Cancelled::AfterSubmission`] if cancelled while waiting for confirmation.
+pub async fn send_and_confirm_cancellable(
+    timer: &mut StageTimer,
+    rpc: &Arc<SolanaRpcClient>,
+    trade_id: Option<TradeId>,
+    transaction: &VersionedTransaction,
+    rpc_send_options: RpcSendOptions,
+    cancel: Option<CancellationToken>,
+) -> Result<Signature, anyhow::Error> {
+    let submitter = Submitter::new(vec![Arc::new(RpcTransport::new(rpc.clone()))], rpc.clone())
+        .with_trade_id(trade_id)
+        .with_cancellation(cancel);
+    let options = SubmitOptions {
+        mode: SendMode::Fallback,
+        timeout: RPC_CONFIRMATION_TIMEOUT,
+        confirm_level: ConfirmLevel::from(rpc.commitment().commitment),
+        rpc: rpc_send_options,
+    };
+
+    let result = submitter.submit(vec![transaction.clone()], options).await.remove(0);
+    for timing in result.stage_timings(timer.origin()) {
+        timer.push(timing);
+    }
+
+    let outcome = result.result?;
+    if result.cancelled {
+        return Err(Cancelled::AfterSubmission { signatures: vec![outcome.signature] }.into());
+    }
+    if !result.confirmed {
+        let recent_blockhash = *transaction.message.recent_blockhash();
+        return Err(ConfirmationTimeout { signature: outcome.signature, recent_blockhash, timeout: RPC_CONFIRMATION_TIMEOUT }.into());
+    }
+    Ok(outcome.signature)
+}
+
+/// A sent transaction was not confirmed in time; it may still land until its blockhash expires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationTimeout {
+    pub signature: Signature,
+    pub recent_blockhash: Hash,
+    pub timeout: Duration,
+}
+
+impl ConfirmationTimeout {
+    /// The confirmation timeout somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&ConfirmationTimeout> {
+        error.chain().find_map(|cause| cause.downcast_ref::<ConfirmationTimeout>())
+    }
+}
+
+impl std::fmt::Display for ConfirmationTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Transaction {} was not confirmed within {:?}", self.signature, self.timeout)
+    }
+}
+
+impl std::error::Error for ConfirmationTimeout {}
+
+/// Simulation of a transaction that passed preflight
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PreflightReport {
+    /// Compute units the simulation consumed
+    pub units_consumed: Option<u64>,
+    pub logs: Vec<String>,
+}
+
+/// A transaction failed preflight simulation and was not sent
+#[derive(Debug)]
+pub struct PreflightError {
+    /// Transaction error reported by the simulation
+    pub error: String,
+    /// Pump.fun error decoded from the logs, if the program failed
+    pub program_error: Option<PumpFunError>,
+    pub logs: Vec<String>,
+}
+
+impl std::fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.program_error {
+            Some(program_error) => write!(f, "Preflight simulation failed: {} ({})", program_error, self.error)?,
+            None => write!(f, "Preflight simulation failed: {}", self.error)?,
+        }
+        for log in &self.logs {
+            write!(f, "\n  {}", log)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PreflightError {}
+
+/// Simulates `transaction` without signature verification against the latest blockhash,
+/// recording a `Preflight` stage on `timer`
+///
+/// A failed simulation returns a [`PreflightError`] holding the simulation logs.
+pub async fn preflight_transaction(
+    timer: &mut StageTimer,
+    rpc: &SolanaRpcClient,
+    transaction: &VersionedTransaction,
+) -> Result<PreflightReport, anyhow::Error> {
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(rpc.commitment()),
+        ..Default::default()
+    };
+    timer.time(Stage::Preflight, async {
+        let simulation = rpc.simulate_transaction_with_config(transaction, config).await?.value;
+        preflight_result(simulation.err.map(|err| err.to_string()), simulation.logs.unwrap_or_default(), simulation.units_consumed)
+    }).await
+}
+
+fn preflight_result(error: Option<String>, logs: Vec<String>, units_consumed: Option<u64>) -> Result<PreflightReport, anyhow::Error> {
+    match error {
+        Some(error) => Err(PreflightError { error, program_error: PumpFunError::from_logs(&logs), logs }.into()),
+        None => Ok(PreflightReport { units_consumed, logs }),
+    }
+}
+
+/// Token amount and SOL bounds for a buy instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuyQuote {
+    /// Tokens requested from the program (`Buy::amount`)
+    pub token_amount: u64,
+    /// SOL the program charges for `token_amount` at the quoted curve state, fee included
+    pub sol_cost: u64,
+    /// Upper bound passed as `Buy::max_sol_cost`
+    pub max_sol_cost: u64,
+    /// Tokens the curve returns for the SOL spent, in basis points of what that SOL buys at
+    /// the spot price; price impact lowers it gradually, an exhausted curve abruptly
+    pub fill_ratio_bps: u64,
+}
+
+impl BuyQuote {
+    /// [`fill_ratio_bps`](Self::fill_ratio_bps) as a fraction
+    pub fn fill_ratio(&self) -> f64 {
+        self.fill_ratio_bps as f64 / FULL_FILL_BPS as f64
+    }
+}
+
+/// Fill ratio of quotes for an exact token amount
+pub const FULL_FILL_BPS: u64 = 10_000;
+
+/// Share of the spot amount `tokens` is for `net_sol` spent on `bonding_curve`, in basis points
+pub fn fill_ratio_bps(bonding_curve: &accounts::BondingCurveAccount, net_sol: u64, tokens: u64) -> u64 {
+    let spot_tokens = net_sol as u128 * bonding_curve.virtual_token_reserves as u128 / (bonding_curve.virtual_sol_reserves as u128).max(1);
+    if spot_tokens == 0 {
+        return FULL_FILL_BPS;
+    }
+    (tokens as u128 * FULL_FILL_BPS as u128 / spot_tokens).min(FULL_FILL_BPS as u128) as u64
+}
+
+/// Lowest fill ratio buys are sent with by default, see [`PartialFillGuard`]
+pub const DEFAULT_MIN_FILL_RATIO_BPS: u64 = 5_000;
+
+/// When a buy from a nearly exhausted curve is refused
+///
+/// Near graduation the curve's real token reserves run out and a buy is clamped to what is
+/// left, returning dust for its SOL or failing on chain. A buy whose quote fills less than
+/// `min_fill_ratio_bps` of its spot amount, or from a curve with fewer than
+/// `min_real_token_reserves` tokens left, fails with [`CurveNearlyExhausted`] before it is
+/// sent, unless `allow_partial_fill` sends it anyway with a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialFillGuard {
+    pub min_fill_ratio_bps: u64,
+    /// Zero only checks the fill ratio
+    pub min_real_token_reserves: u64,
+    pub allow_partial_fill: bool,
+}
+
+impl Default for PartialFillGuard {
+    fn default() -> Self {
+        Self { min_fill_ratio_bps: DEFAULT_MIN_FILL_RATIO_BPS, min_real_token_reserves: 0, allow_partial_fill: false }
+    }
+}
+
+impl PartialFillGuard {
+    /// Fails if `quote` for `requested_sol` fills too little from a curve with
+    /// `remaining_tokens` left and the caller didn't allow it
+    pub fn check(&self, remaining_tokens: u64, requested_sol: u64, quote: &BuyQuote) -> Result<(), CurveNearlyExhausted> {
+        if quote.fill_ratio_bps >= self.min_fill_ratio_bps && remaining_tokens >= self.min_real_token_reserves {
+            return Ok(());
+        }
+        if self.allow_partial_fill {
+            tracing::warn!(remaining_tokens, requested_sol, fill_ratio_bps = quote.fill_ratio_bps, "sending a partial fill from a nearly exhausted curve");
+            return Ok(());
+        }
+        Err(CurveNearlyExhausted { remaining_tokens, requested_sol, fill_ratio_bps: quote.fill_ratio_bps })
+    }
+}
+
+/// A buy would fill only part of its SOL from a curve running out of tokens, see
+/// [`PartialFillGuard`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurveNearlyExhausted {
+    /// Real token reserves left on the curve
+    pub remaining_tokens: u64,
+    pub requested_sol: u64,
+    pub fill_ratio_bps: u64,
+}
+
+impl CurveNearlyExhausted {
+    /// The exhaustion somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&CurveNearlyExhausted> {
+        error.chain().find_map(|cause| cause.downcast_ref::<CurveNearlyExhausted>())
+    }
+}
+
+impl std::fmt::Display for CurveNearlyExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Bonding curve is nearly exhausted: {} tokens left, a buy of {} lamports would fill {}.{:02}% of its spot amount",
+            self.remaining_tokens, self.requested_sol, self.fill_ratio_bps / 100, self.fill_ratio_bps % 100
+        )
+    }
+}
+
+impl std::error::Error for CurveNearlyExhausted {}
+
+/// How a buy applies its slippage tolerance
+///
+/// The program credits exactly `Buy::amount` tokens and fails if they cost more than
+/// `Buy::max_sol_cost`, so the tolerance can loosen either field:
+///
+/// - `MaxSolCost` asks for the quoted tokens and pays up to the tolerance more than their
+///   quoted cost. The token amount is never reduced, so once the price moves by more than
+///   the tolerance the buy fails instead of returning fewer tokens.
+/// - `MinTokensOut` asks for the tolerance fewer tokens than quoted and never pays more
+///   than `amount_sol`.
+/// - `Both` asks for the tolerance fewer tokens and pays up to the tolerance more than
+///   `amount_sol`.
+///
+/// With the 80% fallback of [`get_buy_quote`], the tolerance applies on top of the 80%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlippageMode {
+    #[default]
+    MaxSolCost,
+    MinTokensOut,
+    Both,
+}
+
+/// Optional guard on what a buy may spend and must return
+///
+/// What the Pump.fun program enforces on-chain, and therefore what these checks can
+/// guarantee:
+///
+/// - the lamports debited by the buy instruction itself (curve cost plus protocol fee) never
+///   exceed `Buy::max_sol_cost`, which is clamped to `max_sol_spend`
+/// - exactly `Buy::amount` tokens are credited to the buyer's ATA, or the instruction fails,
+///   so `min_tokens_out` holds whenever the transaction lands
+///
+/// What is not enforceable without a custom program, because no standard program can assert
+/// a wallet's balance delta:
+///
+/// - ATA rent, network and priority fees, and tips paid elsewhere in the transaction
+/// - fees the program may charge on top of the global `fee_basis_points`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostTradeChecks {
+    /// Most lamports the buy instruction may debit, fee included
+    pub max_sol_spend: u64,
+    /// Fewest tokens the buy must credit
+    pub min_tokens_out: u64,
+}
+
+impl PostTradeChecks {
+    /// Clamps `quote` to the checks, failing if the quote can't satisfy them
+    pub fn apply(&self, quote: BuyQuote) -> Result<BuyQuote, anyhow::Error> {
+        if quote.token_amount < self.min_tokens_out {
+            return Err(anyhow!(
+                "Post-trade check failed: quote returns {} tokens, at least {} required",
+                quote.token_amount, self.min_tokens_out
+            ));
+        }
+        if quote.sol_cost > self.max_sol_spend {
+            return Err(anyhow!(
+                "Post-trade check failed: quote costs {} lamports, at most {} allowed",
+                quote.sol_cost, self.max_sol_spend
+            ));
+        }
+
+        Ok(BuyQuote { max_sol_cost: quote.max_sol_cost.min(self.max_sol_spend), ..quote })
+    }
+}
+
+/// Returns the part of `amount_sol` left for the curve once the protocol fee is paid
+#[inline]
+pub fn get_buy_sol_after_fee(amount_sol: u64, fee_basis_points: u64) -> u64 {
+    ((amount_sol as u128) * 10_000 / (10_000 + fee_basis_points as u128)) as u64
+}
+
+/// Returns the bonding curve a mint starts with, as configured by the global account
+pub fn get_initial_bonding_curve(global_account: &accounts::GlobalAccount) -> accounts::BondingCurveAccount {
+    global_account.initial_bonding_curve()
+}
+
+/// Quotes the dev buy of a create-and-buy, with slippage applied to its fee-inclusive cost
+pub fn quote_initial_buy(
+    global_account: &accounts::GlobalAccount,
+    amount_sol: u64,
+    slippage_basis_points: u64,
+) -> Result<BuyQuote, anyhow::Error> {
+    let quote = global_account.initial_buy_quote(amount_sol).map_err(|e| anyhow!(e))?;
+    Ok(BuyQuote {
+        token_amount: quote.tokens,
+        sol_cost: quote.sol_cost,
+        max_sol_cost: quote.max_sol_cost_with(slippage_basis_points),
+        fill_ratio_bps: FULL_FILL_BPS,
+    })
+}
+
+/// Quotes buying an exact token amount, with slippage applied to its fee-inclusive cost
+pub fn quote_buy_tokens(
+    bonding_curve: &accounts::BondingCurveAccount,
+    token_amount: u64,
+    fee_basis_points: u64,
+    slippage_basis_points: u64,
+) -> Result<BuyQuote, anyhow::Error> {
+    let sol_cost = bonding_curve.get_buy_sol_cost(token_amount, fee_basis_points).map_err(|e| anyhow!(e))?;
+    Ok(BuyQuote {
+        token_amount,
+        sol_cost,
+        max_sol_cost: calculate_with_slippage_buy(sol_cost, slippage_basis_points),
+        fill_ratio_bps: FULL_FILL_BPS,
+    })
+}
+
+/// Quotes buying up to `token_amount` tokens with `amount_sol`, applying the slippage
+/// tolerance per `mode`
+///
+/// `MinTokensOut` caps the cost at `amount_sol`, or at the quoted cost when rounding puts it
+/// a few lamports above.
+pub fn quote_buy_tokens_with_mode(
+    bonding_curve: &accounts::BondingCurveAccount,
+    token_amount: u64,
+    amount_sol: u64,
+    fee_basis_points: u64,
+    slippage_basis_points: u64,
+    mode: SlippageMode,
+) -> Result<BuyQuote, anyhow::Error> {
+    let token_amount = match mode {
+        SlippageMode::MaxSolCost => token_amount,
+        SlippageMode::MinTokensOut | SlippageMode::Both => calculate_with_slippage_sell(token_amount, slippage_basis_points),
+    };
+    let sol_cost = bonding_curve.get_buy_sol_cost(token_amount, fee_basis_points).map_err(|e| anyhow!(e))?;
+    let max_sol_cost = match mode {
+        SlippageMode::MaxSolCost => calculate_with_slippage_buy(sol_cost, slippage_basis_points),
+        SlippageMode::MinTokensOut => amount_sol.max(sol_cost),
+        SlippageMode::Both => calculate_with_slippage_buy(amount_sol.max(sol_cost), slippage_basis_points),
+    };
+    Ok(BuyQuote { token_amount, sol_cost, max_sol_cost, fill_ratio_bps: FULL_FILL_BPS })
+}
+
+/// Quotes a buy whose total cost, protocol fee included, stays within `amount_sol`
+///
+/// The token amount is computed from `amount_sol` net of the fee, so at the quoted curve
+/// state the buy spends at most `amount_sol` plus a few lamports of rounding, and never more
+/// than the slippage allowance above that once the curve moves.
+pub fn quote_buy(
+    bonding_curve: &accounts::BondingCurveAccount,
+    amount_sol: u64,
+    fee_basis_points: u64,
+    slippage_basis_points: u64,
+) -> Result<BuyQuote, anyhow::Error> {
+    quote_buy_with_mode(bonding_curve, amount_sol, fee_basis_points, slippage_basis_points, SlippageMode::MaxSolCost)
+}
+
+/// [`quote_buy`] with the slippage tolerance applied per `mode`
+pub fn quote_buy_with_mode(
+    bonding_curve: &accounts::BondingCurveAccount,
+    amount_sol: u64,
+    fee_basis_points: u64,
+    slippage_basis_points: u64,
+    mode: SlippageMode,
+) -> Result<BuyQuote, anyhow::Error> {
+    let net_sol = get_buy_sol_after_fee(amount_sol, fee_basis_points);
+    let token_amount = bonding_curve.get_buy_price(net_sol).map_err(|e| anyhow!(e))?;
+    let quote = quote_buy_tokens_with_mode(bonding_curve, token_amount, amount_sol, fee_basis_points, slippage_basis_points, mode)?;
+    Ok(BuyQuote { fill_ratio_bps: fill_ratio_bps(bonding_curve, net_sol, token_amount), ..quote })
+}
+
+/// Quotes a buy of `mint` from its live bonding curve
+///
+/// When the curve can't be fetched yet (e.g. buying in the same slot as the create), the
+/// quote falls back to the initial curve and asks for 80% of the tokens it would return.
+pub async fn get_buy_quote(
+    rpc: &SolanaRpcClient,
+    global_account: &accounts::GlobalAccount,
+    mint: &Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    post_trade_checks: Option<PostTradeChecks>,
+) -> Result<BuyQuote, anyhow::Error> {
+    let curve = get_bonding_curve_account_with(rpc, mint, CurveRead::Fresh).await;
+    buy_quote_from_curve(curve, global_account, amount_sol, slippage_basis_points, slippage_mode, post_trade_checks)
+}
+
+/// Quotes a buy from the result of fetching the bonding curve, falling back to the initial
+/// curve as [`get_buy_quote`] does when the fetch failed
+pub fn buy_quote_from_curve(
+    curve: Result<Arc<accounts::BondingCurveAccount>, anyhow::Error>,
+    global_account: &accounts::GlobalAccount,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    post_trade_checks: Option<PostTradeChecks>,
+) -> Result<BuyQuote, anyhow::Error> {
+    buy_quote_from(visible_curve(&curve), global_account, amount_sol, slippage_basis_points, slippage_mode, post_trade_checks)
+}
+
+/// The fetched bonding curve, or `None` to quote from the initial curve when the fetch failed
+pub(crate) fn visible_curve(curve: &Result<Arc<accounts::BondingCurveAccount>, anyhow::Error>) -> Option<&accounts::BondingCurveAccount> {
+    if let Err(e) = curve {
+        tracing::debug!(error = %e, "bonding curve account not found, using the initial buy price");
+    }
+    curve.as_deref().ok()
+}
+
+/// Quotes a buy from `curve`, or from the initial curve as [`get_buy_quote`] does when the
+/// curve is not visible yet
+pub fn buy_quote_from(
+    curve: Option<&accounts::BondingCurveAccount>,
+    global_account: &accounts::GlobalAccount,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    post_trade_checks: Option<PostTradeChecks>,
+) -> Result<BuyQuote, anyhow::Error> {
+    let slippage = slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE);
+    let fee_basis_points = global_account.fee_basis_points;
+
+    let quote = match curve {
+        Some(account) => quote_buy_with_mode(account, amount_sol, fee_basis_points, slippage, slippage_mode)?,
+        None => {
+            let initial_curve = get_initial_bonding_curve(global_account);
+            let token_amount = quote_buy(&initial_curve, amount_sol, fee_basis_points, slippage)?.token_amount * 80 / 100;
+            quote_buy_tokens_with_mode(&initial_curve, token_amount, amount_sol, fee_basis_points, slippage, slippage_mode)?
+        }
+    };
+
+    match post_trade_checks {
+        Some(checks) => checks.apply(quote),
+        None => Ok(quote),
+    }
+}
+
+/// Fewest lamports a sell asks for by default, see [`SellFloor`]
+pub const DEFAULT_MIN_ACCEPTABLE_LAMPORTS: u64 = 5_000;
+
+/// Lowest minimum output a sell is sent with
+///
+/// A tiny sell's minimum output rounds down to a few lamports or zero, leaving it without
+/// slippage protection. Below `min_acceptable_lamports` a sell fails with [`SellTooSmall`],
+/// unless `allow_below` sends it anyway with a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SellFloor {
+    pub min_acceptable_lamports: u64,
+    pub allow_below: bool,
+}
+
+impl Default for SellFloor {
+    fn default() -> Self {
+        Self { min_acceptable_lamports: DEFAULT_MIN_ACCEPTABLE_LAMPORTS, allow_below: false }
+    }
+}
+
+impl SellFloor {
+    /// Fails if `min_sol_output` is below the floor and the caller didn't allow it
+    pub fn check(&self, min_sol_output: u64) -> Result<(), SellTooSmall> {
+        if min_sol_output >= self.min_acceptable_lamports {
+            return Ok(());
+        }
+        if self.allow_below {
+            tracing::warn!(min_sol_output, min_acceptable_lamports = self.min_acceptable_lamports, "sending a sell below the minimum output floor");
+            return Ok(());
+        }
+        Err(SellTooSmall { min_sol_output, min_acceptable_lamports: self.min_acceptable_lamports })
+    }
+}
+
+/// A sell's minimum output is below its [`SellFloor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SellTooSmall {
+    pub min_sol_output: u64,
+    pub min_acceptable_lamports: u64,
+}
+
+impl SellTooSmall {
+    /// The floor violation somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&SellTooSmall> {
+        error.chain().find_map(|cause| cause.downcast_ref::<SellTooSmall>())
+    }
+}
+
+impl std::fmt::Display for SellTooSmall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Sell would accept as little as {} lamports, below the floor of {}",
+            self.min_sol_output, self.min_acceptable_lamports
+        )
+    }
+}
+
+impl std::error::Error for SellTooSmall {}
+
+/// Fewest lamports a buy spends by default, see [`BuyFloor`]
+pub const DEFAULT_MIN_BUY_LAMPORTS: u64 = 1_000_000;
+
+/// Smallest buy that is sent
+///
+/// A dust buy pays full priority fees and tips, then fails on chain or fills for no tokens
+/// after rounding. A buy of fewer than `min_buy_lamports` fails with [`BuyTooSmall`] before
+/// anything is fetched, unless `allow_below` sends it anyway with a warning, e.g. for
+/// intentional dust tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuyFloor {
+    pub min_buy_lamports: u64,
+    pub allow_below: bool,
+}
+
+impl Default for BuyFloor {
+    fn default() -> Self {
+        Self { min_buy_lamports: DEFAULT_MIN_BUY_LAMPORTS, allow_below: false }
+    }
+}
+
+impl BuyFloor {
+    /// Fails if `amount_sol` is below the floor and the caller didn't allow it
+    pub fn check(&self, amount_sol: u64) -> Result<(), BuyTooSmall> {
+        if amount_sol >= self.min_buy_lamports {
+            return Ok(());
+        }
+        if self.allow_below {
+            tracing::warn!(amount_sol, min_buy_lamports = self.min_buy_lamports, "sending a buy below the minimum amount");
+            return Ok(());
+        }
+        Err(BuyTooSmall { amount: amount_sol, minimum: self.min_buy_lamports })
+    }
+}
+
+/// A buy's amount is below its [`BuyFloor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuyTooSmall {
+    pub amount: u64,
+    pub minimum: u64,
+}
+
+impl BuyTooSmall {
+    /// The floor violation somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&BuyTooSmall> {
+        error.chain().find_map(|cause| cause.downcast_ref::<BuyTooSmall>())
+    }
+}
+
+impl std::fmt::Display for BuyTooSmall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Buy of {} lamports is below the minimum of {}", self.amount, self.minimum)
+    }
+}
+
+impl std::error::Error for BuyTooSmall {}
+
+/// Least SOL a sell of `token_amount` must return, checked against `floor`
+///
+/// The protocol fee comes off the curve's output first and the slippage tolerance applies to
+/// what is left, since the program compares the output net of its fee with `min_sol_output`.
+pub fn quote_sell_min_output(
+    bonding_curve: &accounts::BondingCurveAccount,
+    token_amount: u64,
+    fee_basis_points: u64,
+    slippage_basis_points: u64,
+    floor: SellFloor,
+) -> Result<u64, anyhow::Error> {
+    let sol_after_fee = bonding_curve.get_sell_price(token_amount, fee_basis_points).map_err(|e| anyhow!(e))?;
+    let min_sol_output = calculate_with_slippage_sell(sol_after_fee, slippage_basis_points);
+    floor.check(min_sol_output)?;
+    Ok(min_sol_output)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_rpc_client_api::request::RpcRequest;
+
+    use super::*;
+    use crate::pumpfun::{buy::build_buy_transaction_with_tip, sell::build_sell_transaction_with_tip};
+
+    fn token_account(amount: u64, extra: usize) -> SolanaAccount {
+        let mut data = vec![0; Account::LEN];
+        let state = Account { amount, state: spl_token::state::AccountState::Initialized, ..Default::default() };
+        Account::pack(state, &mut data).unwrap();
+        data.extend(vec![0; extra]);
+        SolanaAccount { lamports: 1, data, owner: constants::accounts::TOKEN_PROGRAM, executable: false, rent_epoch: 0 }
+    }
+
+    #[test]
+    fn test_token_account_amount_reads_token_2022_accounts() {
+        assert_eq!(token_account_amount(&token_account(42, 0)), 42);
+        assert_eq!(token_account_amount(&token_account(7, 83)), 7);
+        assert_eq!(token_account_amount(&SolanaAccount::default()), 0);
+    }
+
+    fn memo(text: &str) -> Instruction {
+        Instruction::new_with_bytes(Pubkey::new_unique(), text.as_bytes(), vec![])
+    }
+
+    fn compute_budget_data(message: &VersionedMessage) -> Vec<Vec<u8>> {
+        let keys = message.static_account_keys();
+        message
+            .instructions()
+            .iter()
+            .filter(|instruction| keys[instruction.program_id_index as usize] == compute_budget::id())
+            .map(|instruction| instruction.data.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_dedup_compute_budget_keeps_last_of_each_kind() {
+        let user_memo = memo("order-1");
+        let instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_price(1),
+            ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            user_memo.clone(),
+            ComputeBudgetInstruction::set_compute_unit_price(2),
+        ];
+
+        let deduped = dedup_compute_budget_instructions(instructions);
+        assert_eq!(deduped, vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            user_memo,
+            ComputeBudgetInstruction::set_compute_unit_price(2),
+        ]);
+    }
+
+    #[test]
+    fn test_extra_instructions_go_before_close_account() {
+        let owner = Pubkey::new_unique();
+        let ata = Pubkey::new_unique();
+        let trade = memo("trade");
+        let close = spl_token::instruction::close_account(&spl_token::id(), &ata, &owner, &owner, &[]).unwrap();
+        let extra = crate::common::memo_instruction("order-1");
+
+        let instructions = append_extra_instructions(vec![trade.clone(), close.clone()], vec![extra.clone()]);
+        assert_eq!(instructions, vec![trade.clone(), extra.clone(), close]);
+
+        let instructions = append_extra_instructions(vec![trade.clone()], vec![extra.clone()]);
+        assert_eq!(instructions, vec![trade, extra]);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_sign_versioned_transaction() {
+        let payer = Keypair::new();
+        let blockhash = Hash::new_unique();
+        let instructions = vec![memo("create")];
+
+        let transaction = sign_versioned_transaction(&instructions, &[&payer], blockhash).unwrap();
+        assert!(matches!(transaction.message, VersionedMessage::V0(_)));
+        assert_eq!(transaction.message.static_account_keys()[0], payer.pubkey());
+        assert_eq!(*transaction.message.recent_blockhash(), blockhash);
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+
+        let legacy = sign_legacy_transaction(&instructions, &[&payer], blockhash).unwrap();
+        assert!(legacy.is_signed());
+
+        assert!(sign_versioned_transaction(&instructions, &[], blockhash).is_err());
+    }
+
+    #[test]
+    fn test_signer_mismatch_names_the_keys() {
+        let (payer, mint, trader, stranger) = (Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new());
+        let blockhash = Hash::new_unique();
+        let create = system_instruction::create_account(&payer.pubkey(), &mint.pubkey(), 1, 82, &Pubkey::new_unique());
+        let trade = system_instruction::transfer(&trader.pubkey(), &Pubkey::new_unique(), 1);
+
+        let error = sign_versioned_transaction(&[create.clone()], &[&payer], blockhash).unwrap_err();
+        assert_eq!(SignerMismatch::find(&error), Some(&SignerMismatch::MissingSigners(vec![mint.pubkey()])));
+        assert!(error.to_string().contains(&mint.pubkey().to_string()));
+
+        let error = sign_versioned_transaction(&[trade.clone()], &[&payer], blockhash).unwrap_err();
+        assert_eq!(SignerMismatch::find(&error), Some(&SignerMismatch::MissingSigners(vec![trader.pubkey()])));
+
+        let error = sign_versioned_transaction(&[create.clone()], &[&payer, &mint, &stranger, &stranger], blockhash).unwrap_err();
+        assert_eq!(SignerMismatch::find(&error), Some(&SignerMismatch::UnexpectedSigners(vec![stranger.pubkey()])));
+
+        let transaction = sign_versioned_transaction(&[create.clone()], &[&payer, &mint], blockhash).unwrap();
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+        let transaction = sign_versioned_transaction(&[create.clone(), trade.clone()], &[&payer, &trader, &mint], blockhash).unwrap();
+        assert_eq!(required_signers(&transaction.message).len(), 3);
+        assert_eq!(required_signers(&transaction.message)[0], payer.pubkey());
+
+        let message = compile_unsigned_message(&[create, trade], &[payer.pubkey(), mint.pubkey(), trader.pubkey()], blockhash).unwrap();
+        assert_eq!(check_signers(&message, &[trader.pubkey(), mint.pubkey(), payer.pubkey()]), Ok(()));
+        assert_eq!(check_signers(&message, &[payer.pubkey()]), Err(SignerMismatch::MissingSigners(vec![mint.pubkey(), trader.pubkey()])));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_extra_instructions_are_rejected() {
+        let result = build_buy_transaction_with_tip(
+            Arc::new(Pubkey::new_unique()),
+            Arc::new(Keypair::new()),
+            PriorityFee::default(),
+            vec![memo("trade")],
+            vec![crate::common::memo_instruction(&"x".repeat(PACKET_DATA_SIZE))],
+            Hash::new_unique(),
+        ).await;
+        assert!(result.unwrap_err().to_string().contains("packet limit"));
+    }
+
+    #[tokio::test]
+    async fn test_transaction_builders_dedup_user_compute_budget() {
+        let payer = Arc::new(Keypair::new());
+        let priority_fee = PriorityFee { unit_price: 1_000, unit_limit: Some(80_000), ..Default::default() };
+        let user_instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_price(5_000),
+            memo("order-1"),
+        ];
+
+        let buy = build_buy_transaction_with_tip(
+            Arc::new(Pubkey::new_unique()),
+            payer.clone(),
+            priority_fee.clone(),
+            user_instructions.clone(),
+            vec![],
+            Hash::new_unique(),
+        ).await.unwrap();
+        let sell = build_sell_transaction_with_tip(
+            Arc::new(Pubkey::new_unique()),
+            payer,
+            priority_fee,
+            user_instructions,
+            vec![],
+            Hash::new_unique(),
+        ).await.unwrap();
+
+        for transaction in [buy, sell] {
+            let data = compute_budget_data(&transaction.message);
+            let price = ComputeBudgetInstruction::set_compute_unit_price(5_000).data;
+            let limit = ComputeBudgetInstruction::set_compute_unit_limit(80_000).data;
+            assert_eq!(data.iter().filter(|d| d[0] == price[0]).collect::<Vec<_>>(), vec![&price]);
+            assert_eq!(data.iter().filter(|d| d[0] == limit[0]).collect::<Vec<_>>(), vec![&limit]);
+        }
+    }
+
+    #[test]
+    fn test_each_operation_requests_its_compute_unit_limit() {
+        use crate::{
+            constants::trade::{DEFAULT_BUY_UNIT_LIMIT, DEFAULT_CREATE_UNIT_LIMIT, DEFAULT_SELL_UNIT_LIMIT},
+            pumpfun::{buy::buy_transaction_instructions, create::create_and_buy_transaction_instructions, sell::sell_transaction_instructions},
+        };
+
+        let limit_of = |instructions: Vec<Instruction>| {
+            instructions
+                .iter()
+                .filter(|instruction| instruction.program_id == compute_budget::id())
+                .find_map(|instruction| match borsh::from_slice(&instruction.data) {
+                    Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => Some(limit),
+                    _ => None,
+                })
+        };
+        let limits = |priority_fee: &PriorityFee| {
+            (
+                limit_of(buy_transaction_instructions(priority_fee, None, vec![memo("buy")], vec![])),
+                limit_of(sell_transaction_instructions(priority_fee, None, vec![memo("sell")], vec![])),
+                limit_of(create_and_buy_transaction_instructions(priority_fee, None, vec![memo("create")], vec![])),
+            )
+        };
+
+        let defaults = (Some(DEFAULT_BUY_UNIT_LIMIT), Some(DEFAULT_SELL_UNIT_LIMIT), Some(DEFAULT_CREATE_UNIT_LIMIT));
+        assert_eq!(limits(&PriorityFee::default()), defaults);
+        assert!(DEFAULT_SELL_UNIT_LIMIT < DEFAULT_BUY_UNIT_LIMIT && DEFAULT_BUY_UNIT_LIMIT < DEFAULT_CREATE_UNIT_LIMIT);
+
+        // `unit_limit` stands in for the operations without their own limit
+        let priority_fee = PriorityFee { unit_limit: Some(120_000), sell_unit_limit: Some(45_000), ..Default::default() };
+        assert_eq!(limits(&priority_fee), (Some(120_000), Some(45_000), Some(120_000)));
+        let priority_fee = PriorityFee { buy_unit_limit: Some(70_000), create_unit_limit: Some(300_000), ..Default::default() };
+        assert_eq!(limits(&priority_fee), (Some(70_000), Some(DEFAULT_SELL_UNIT_LIMIT), Some(300_000)));
+    }
+
+    #[tokio::test]
+    async fn test_token_program_falls_back_only_for_missing_mints() {
+        let missing = HashMap::from([(RpcRequest::GetAccountInfo, serde_json::json!({ "context": { "slot": 1 }, "value": null }))]);
+        let rpc = SolanaRpcClient::new_mock_with_mocks("succeeds".to_string(), missing);
+        assert_eq!(get_token_program(&rpc, &Pubkey::new_unique()).await.unwrap(), constants::accounts::TOKEN_PROGRAM);
+
+        let rpc = SolanaRpcClient::new_mock("fails".to_string());
+        assert!(get_token_program(&rpc, &Pubkey::new_unique()).await.is_err());
+    }
+
+    fn mint_account(owner: Pubkey) -> SolanaAccount {
+        SolanaAccount {
+            lamports: 1_461_600,
+            data: vec![0; spl_token::state::Mint::LEN],
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn mainnet_initial_curve() -> accounts::BondingCurveAccount {
+        accounts::BondingCurveAccount::new(
+            0,
+            1_073_000_000_000_000,
+            30_000_000_000,
+            793_100_000_000_000,
+            0,
+            1_000_000_000_000_000,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_sell_min_output_takes_the_fee_before_slippage() {
+        let curve = mainnet_initial_curve();
+        let (fee_basis_points, slippage_basis_points) = (100, 1_000);
+        let amount = 10_000_000_000;
+        let floor = SellFloor { min_acceptable_lamports: 0, allow_below: false };
+
+        let gross = curve.get_sell_price(amount, 0).unwrap();
+        let after_fee = gross - gross * fee_basis_points / 10_000;
+        let min_sol_output = quote_sell_min_output(&curve, amount, fee_basis_points, slippage_basis_points, floor).unwrap();
+        assert_eq!(min_sol_output, calculate_with_slippage_sell(after_fee, slippage_basis_points));
+        assert!(min_sol_output < after_fee);
+    }
+
+    #[test]
+    fn test_tiny_sells_stop_at_the_floor() {
+        let curve = mainnet_initial_curve();
+        let err = quote_sell_min_output(&curve, 10, 100, 1_000, SellFloor::default()).unwrap_err();
+        assert_eq!(SellTooSmall::find(&err), Some(&SellTooSmall { min_sol_output: 0, min_acceptable_lamports: DEFAULT_MIN_ACCEPTABLE_LAMPORTS }));
+
+        let allowed = SellFloor { allow_below: true, ..Default::default() };
+        assert_eq!(quote_sell_min_output(&curve, 10, 100, 1_000, allowed).unwrap(), 0);
+        assert!(quote_sell_min_output(&curve, 1_000_000_000_000, 100, 1_000, SellFloor::default()).unwrap() >= DEFAULT_MIN_ACCEPTABLE_LAMPORTS);
+    }
+
+    #[test]
+    fn test_dust_buys_stop_at_the_floor() {
+        let err = anyhow::Error::from(BuyFloor::default().check(5_000).unwrap_err());
+        assert_eq!(BuyTooSmall::find(&err), Some(&BuyTooSmall { amount: 5_000, minimum: DEFAULT_MIN_BUY_LAMPORTS }));
+        assert!(BuyFloor { allow_below: true, ..Default::default() }.check(5_000).is_ok());
+        assert!(BuyFloor::default().check(DEFAULT_MIN_BUY_LAMPORTS).is_ok());
+    }
+
+    #[test]
+    fn test_preflight_failure_keeps_logs_and_program_error() {
+        let logs = vec![format!("Program {} failed: custom program error: 0x1772", constants::accounts::PUMPFUN)];
+        let err = preflight_result(Some("Error processing Instruction 3".to_string()), logs.clone(), None).unwrap_err();
+        let preflight = err.downcast_ref::<PreflightError>().unwrap();
+        assert_eq!(preflight.program_error, Some(PumpFunError::TooMuchSolRequired));
+        assert_eq!(preflight.logs, logs);
+        assert!(err.to_string().contains("TooMuchSolRequired"));
+
+        let report = preflight_result(None, logs.clone(), Some(52_000)).unwrap();
+        assert_eq!(report, PreflightReport { units_consumed: Some(52_000), logs });
+    }
+
+    #[tokio::test]
+    async fn test_preflight_transaction_records_stage() {
+        let rpc = SolanaRpcClient::new_mock("succeeds".to_string());
+        let payer = Keypair::new();
+        let transaction = sign_versioned_transaction(&[memo("preflight")], &[&payer], Hash::new_unique()).unwrap();
+
+        let mut timer = StageTimer::new();
+        assert!(preflight_transaction(&mut timer, &rpc, &transaction).await.is_ok());
+        let report = timer.finish();
+        assert_eq!(report.stages[0].stage, Stage::Preflight);
+        assert!(report.stages[0].succeeded);
+    }
+
+    #[test]
+    fn test_initial_buy_quote_matches_initial_curve_quote() {
+        let global = accounts::GlobalAccount::new(
+            1, true, Pubkey::new_unique(), Pubkey::new_unique(),
+            1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100,
+        );
+        for amount_sol in [1, 10_000_000, 2_000_000_000, 85_000_000_000] {
+            let planned = quote_initial_buy(&global, amount_sol, 500).unwrap();
+            let executed = quote_buy(&get_initial_bonding_curve(&global), amount_sol, global.fee_basis_points, 500).unwrap();
+            assert_eq!(planned, executed);
+        }
+    }
+
+    #[test]
+    fn test_quote_buy_spends_at_most_amount_sol() {
+        let curve = mainnet_initial_curve();
+
+        for amount_sol in [1_000_000, 100_000_000, 1_000_000_000, 10_000_000_000] {
+            let quote = quote_buy(&curve, amount_sol, 100, 1_000).unwrap();
+
+            // Fee included, the quoted cost is within 3 lamports of the requested spend
+            assert!(quote.sol_cost <= amount_sol + 3, "{} > {}", quote.sol_cost, amount_sol);
+            assert!(quote.sol_cost + amount_sol / 1_000 >= amount_sol);
+            assert_eq!(quote.max_sol_cost, calculate_with_slippage_buy(quote.sol_cost, 1_000));
+        }
+    }
+
+    #[test]
+    fn test_quote_buy_fixes_fee_overpay() {
+        let curve = mainnet_initial_curve();
+        let amount_sol = 1_000_000_000;
+
+        // Computing tokens from the gross amount, as before, costs the full fee on top
+        let gross_tokens = curve.get_buy_price(amount_sol).unwrap();
+        let gross_cost = curve.get_buy_sol_cost(gross_tokens, 100).unwrap();
+        assert!(gross_cost >= amount_sol + amount_sol / 100);
+
+        let quote = quote_buy(&curve, amount_sol, 100, 0).unwrap();
+        assert!(quote.token_amount < gross_tokens);
+        assert!(quote.sol_cost <= amount_sol + 3);
+    }
+
+    #[test]
+    fn test_post_trade_checks() {
+        let quote = BuyQuote { token_amount: 1_000, sol_cost: 500, max_sol_cost: 550, fill_ratio_bps: FULL_FILL_BPS };
+
+        let clamped = PostTradeChecks { max_sol_spend: 520, min_tokens_out: 1_000 }.apply(quote).unwrap();
+        assert_eq!(clamped.max_sol_cost, 520);
+
+        let unclamped = PostTradeChecks { max_sol_spend: 600, min_tokens_out: 0 }.apply(quote).unwrap();
+        assert_eq!(unclamped, quote);
+
+        assert!(PostTradeChecks { max_sol_spend: 499, min_tokens_out: 0 }.apply(quote).is_err());
+        assert!(PostTradeChecks { max_sol_spend: 600, min_tokens_out: 1_001 }.apply(quote).is_err());
+    }
+
+    #[test]
+    fn test_nearly_exhausted_curves_are_refused() {
+        let fresh = quote_buy(&mainnet_initial_curve(), 1_000_000_000, 100, 500).unwrap();
+        assert!(fresh.fill_ratio_bps > 9_500 && fresh.fill_ratio_bps < FULL_FILL_BPS);
+
+        let remaining_tokens = 1_000_000_000;
+        let curve = accounts::BondingCurveAccount::new(0, 280_000_000_000_000, 114_900_000_000, remaining_tokens, 84_900_000_000, 1_000_000_000_000_000, false);
+        let quote = quote_buy(&curve, 1_000_000_000, 100, 500).unwrap();
+        assert_eq!(quote.token_amount, remaining_tokens);
+        assert!(quote.fill_ratio() < 0.01);
+
+        let guard = PartialFillGuard::default();
+        assert!(guard.check(remaining_tokens, 1_000_000_000, &fresh).is_ok());
+        let error = guard.check(remaining_tokens, 1_000_000_000, &quote).unwrap_err();
+        assert_eq!(error, CurveNearlyExhausted { remaining_tokens, requested_sol: 1_000_000_000, fill_ratio_bps: quote.fill_ratio_bps });
+        assert!(CurveNearlyExhausted::find(&anyhow::Error::from(error)).is_some());
+        assert!(PartialFillGuard { allow_partial_fill: true, ..guard }.check(remaining_tokens, 1_000_000_000, &quote).is_ok());
+        assert!(PartialFillGuard { min_real_token_reserves: 2_000_000_000, ..guard }.check(remaining_tokens, 1, &fresh).is_err());
+    }
+
+    #[test]
+    fn test_token_program_for_legacy_mint() {
+        let account = mint_account(constants::accounts::TOKEN_PROGRAM);
+        assert_eq!(token_program_for_account(&account).unwrap(), constants::accounts::TOKEN_PROGRAM);
+    }
+
+    #[test]
+    fn test_token_program_for_token_2022_mint() {
+        let account = mint_account(constants::accounts::TOKEN_2022_PROGRAM);
+        assert_eq!(token_program_for_account(&account).unwrap(), constants::accounts::TOKEN_2022_PROGRAM);
+    }
+
+    #[test]
+    fn test_token_program_for_foreign_owner() {
+        let account = mint_account(constants::accounts::SYSTEM_PROGRAM);
+        assert!(token_program_for_account(&account).is_err());
+    }
+
+    #[test]
+    fn test_ata_depends_on_token_program() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let legacy = get_ata(&owner, &mint, &constants::accounts::TOKEN_PROGRAM);
+        let token_2022 = get_ata(&owner, &mint, &constants::accounts::TOKEN_2022_PROGRAM);
+
+        assert_eq!(legacy, spl_associated_token_account::get_associated_token_address(&owner, &mint));
+        assert_ne!(legacy, token_2022);
+    }
+}
@@ -1,38 +1,117 @@
-use std::{str::FromStr, time::Instant, sync::Arc};
+use std::{str::FromStr, sync::Arc};
 
 use anyhow::anyhow;
-use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use thiserror::Error;
 use solana_sdk::{
-    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, instruction::Instruction, message::{v0, VersionedMessage}, native_token::sol_to_lamports, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
+    account::Account, compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
 };
+use solana_hash::Hash;
 use spl_associated_token_account::{
     instruction::create_associated_token_account,
 };
 
 use crate::{
-    common::{PriorityFee, SolanaRpcClient}, constants, instruction, 
-    ipfs::TokenMetadataIPFS,  jito::FeeClient,
-    pumpfun::buy::build_buy_transaction_with_tip
+    accounts::GlobalAccount,
+    common::{latency::{LatencyReport, Stage, StageTimer}, PriorityFee, RpcClients, SolanaRpcClient}, constants::{self, trade::DEFAULT_SLIPPAGE}, instruction, 
+    ipfs::TokenMetadataIPFS,  fee_clients::FeeClient,
+    trade::{journal::{memo_text, record_intent, TradeId, TradeIntent, TradeSide}, RpcSendOptions},
 };
 
+use crate::pumpfun::metadata::{metadata_policy_instructions, program_update_authority, verify_metadata_policy, MetadataPolicy};
 use crate::pumpfun::common::{
-    create_priority_fee_instructions, 
-    get_buy_amount_with_slippage, get_global_account
+    append_extra_instructions, create_priority_fee_instructions, dedup_compute_budget_instructions,
+    get_bonding_curve_pda, get_global_account, preflight_transaction, quote_initial_buy, send_and_confirm_with_rpc_options,
+    sign_versioned_transaction, ConfirmationTimeout, PreflightReport,
 };
+use crate::pumpfun::launch_budget::{LaunchBudget, LaunchCost};
+use crate::pumpfun::recovery::recover_create_timeout;
+
+/// Longest vanity suffix [`generate_mint`] grinds for; each character multiplies the expected
+/// attempts by 58
+pub const MAX_VANITY_SUFFIX_LEN: usize = 4;
+
+/// What a create flow created
+#[derive(Debug, Clone)]
+pub struct CreateResult {
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub signature: Signature,
+    pub metadata_uri: String,
+    pub latency: LatencyReport,
+    /// Simulation of the transaction, if it was preflighted
+    pub preflight: Option<PreflightReport>,
+    /// The mint keypair, if the SDK generated it, including a replacement picked by
+    /// [`MintCheck::Regenerate`]; archive it to prove the mint's origin later
+    pub mint_keypair: Option<Arc<Keypair>>,
+    /// Most the create-and-buy transaction may spend, by purpose; `None` for a plain create
+    pub cost: Option<LaunchCost>,
+}
+
+impl CreateResult {
+    fn new(mint: Pubkey, signature: Signature, metadata_uri: String, latency: LatencyReport, preflight: Option<PreflightReport>) -> Result<Self, anyhow::Error> {
+        let bonding_curve = get_bonding_curve_pda(&mint).ok_or(anyhow!("Bonding curve not found"))?;
+        Ok(Self { mint, bonding_curve, signature, metadata_uri, latency, preflight, mint_keypair: None, cost: None })
+    }
+}
+
+/// Generates a mint keypair whose address ends with `vanity_suffix`, e.g. `"pump"`
+///
+/// Grinding runs on a blocking thread and takes about 58 to the power of the suffix length
+/// attempts. Fails if the suffix is longer than [`MAX_VANITY_SUFFIX_LEN`] or not base58.
+pub async fn generate_mint(vanity_suffix: Option<&str>) -> Result<Keypair, anyhow::Error> {
+    let Some(suffix) = vanity_suffix.filter(|suffix| !suffix.is_empty()) else {
+        return Ok(Keypair::new());
+    };
+    if suffix.len() > MAX_VANITY_SUFFIX_LEN {
+        return Err(anyhow!("Vanity suffix {:?} is longer than {} characters", suffix, MAX_VANITY_SUFFIX_LEN));
+    }
+    if bs58::decode(suffix).into_vec().is_err() {
+        return Err(anyhow!("Vanity suffix {:?} is not base58", suffix));
+    }
+
+    let suffix = suffix.to_string();
+    Ok(tokio::task::spawn_blocking(move || loop {
+        let mint = Keypair::new();
+        if mint.pubkey().to_string().ends_with(&suffix) {
+            return mint;
+        }
+    })
+    .await?)
+}
 
 /// Create a new token
+///
+/// With [`MetadataPolicy::Immutable`], fails before sending anything if the metadata can't be
+/// made immutable, and checks the created metadata otherwise.
 pub async fn create(
-    rpc: Arc<SolanaRpcClient>,
+    rpc: impl Into<RpcClients>,
     payer: Arc<Keypair>,
     mint: Keypair,
     ipfs: TokenMetadataIPFS,
     priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
-    let mut instructions = create_priority_fee_instructions(priority_fee);
+    rpc_send_options: RpcSendOptions,
+    metadata_policy: MetadataPolicy,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<CreateResult, anyhow::Error> {
+    let RpcClients { read: rpc, send } = rpc.into();
+    let policy_instructions = metadata_policy_instructions(metadata_policy, &program_update_authority(), &payer.pubkey(), &mint.pubkey())?;
+    let trade_id = record_intent(TradeIntent {
+        side: TradeSide::Create,
+        mint: mint.pubkey(),
+        amount_sol: None,
+        amount_token: None,
+        slippage_basis_points: None,
+        trade_tag: extra_instructions.as_deref().and_then(memo_text),
+        idempotency_key: None,
+    });
+    let mut timer = StageTimer::new();
+    let metadata_uri = ipfs.metadata_uri.clone();
+    let unit_limit = priority_fee.create_limit();
+    let mut instructions = create_priority_fee_instructions(priority_fee, unit_limit);
 
     instructions.push(instruction::create(
-        payer.as_ref(),
-        &mint,
+        &payer.pubkey(),
+        &mint.pubkey(),
         instruction::Create {
             _name: ipfs.metadata.name,
             _symbol: ipfs.metadata.symbol,
@@ -41,42 +120,112 @@ pub async fn create(
         },
     ));
 
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer.as_ref(), &mint],
-        recent_blockhash,
-    );
+    instructions.extend(policy_instructions);
+    instructions.extend(extra_instructions.unwrap_or_default());
+    let instructions = dedup_compute_budget_instructions(instructions);
+
+    let recent_blockhash = timer.time(Stage::Blockhash, rpc.get_latest_blockhash()).await?;
+    let transaction = timer.time_sync(Stage::BuildSign, || {
+        sign_versioned_transaction(&instructions, &[payer.as_ref(), &mint], recent_blockhash)
+    })?;
+
+    let signature = send_and_confirm_with_rpc_options(&mut timer, &send, Some(trade_id), &transaction, rpc_send_options).await?;
+    if metadata_policy == MetadataPolicy::Immutable {
+        verify_metadata_policy(&rpc, metadata_policy, &mint.pubkey()).await?;
+    }
 
-    rpc.send_and_confirm_transaction(&transaction).await?;
+    CreateResult::new(mint.pubkey(), signature, metadata_uri, timer.finish(), None)
+}
 
-    Ok(())
+/// Create a new token with a mint keypair generated by [`generate_mint`], returned in the
+/// result
+pub async fn create_with_generated_mint(
+    rpc: impl Into<RpcClients>,
+    payer: Arc<Keypair>,
+    vanity_suffix: Option<&str>,
+    ipfs: TokenMetadataIPFS,
+    priority_fee: PriorityFee,
+    rpc_send_options: RpcSendOptions,
+    metadata_policy: MetadataPolicy,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<CreateResult, anyhow::Error> {
+    let mint = generate_mint(vanity_suffix).await?;
+    let mint_keypair = Arc::new(mint.insecure_clone());
+    let result = create(rpc, payer, mint, ipfs, priority_fee, rpc_send_options, metadata_policy, extra_instructions).await?;
+    Ok(CreateResult { mint_keypair: Some(mint_keypair), ..result })
 }
 
 /// Create and buy tokens in one transaction
+///
+/// With `preflight`, the signed transaction is simulated first and nothing is sent if the
+/// simulation fails; the simulation is returned for auditing. `metadata_policy` applies as
+/// in [`create`]. `mint_check` decides what happens if `mint` already exists; the result
+/// holds the mint actually created, and its keypair if it replaced `mint`.
+///
+/// If confirmation times out, the chain is checked: a create that landed with its buy is
+/// returned as usual, anything else fails with a
+/// [`RecoveryError::Unconfirmed`](super::recovery::RecoveryError) holding the outcome.
+///
+/// With a `launch_budget`, nothing is sent if the transaction may spend more than it allows,
+/// see [`launch_budget`](super::launch_budget). The result holds the transaction's cost.
 pub async fn create_and_buy(
-    rpc: Arc<SolanaRpcClient>,
+    rpc: impl Into<RpcClients>,
     payer: Arc<Keypair>,
     mint: Keypair,
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
+    rpc_send_options: RpcSendOptions,
+    metadata_policy: MetadataPolicy,
+    mint_check: MintCheck,
+    preflight: bool,
+    extra_instructions: Option<Vec<Instruction>>,
+    launch_budget: Option<LaunchBudget>,
+) -> Result<CreateResult, anyhow::Error> {
     if amount_sol == 0 {
         return Err(anyhow!("Amount cannot be zero"));
     }
-
+    let RpcClients { read: rpc, send } = rpc.into();
+    let requested_mint = mint.pubkey();
+    let mint = checked_mint(&rpc, mint, mint_check).await?;
+    let trade_id = create_and_buy_intent(&mint, amount_sol, slippage_basis_points, extra_instructions.as_deref());
+    let extra_instructions = with_metadata_policy(metadata_policy, &payer, &mint, extra_instructions)?;
+
+    let mut timer = StageTimer::new();
+    let metadata_uri = ipfs.metadata_uri.clone();
     let mint = Arc::new(mint);
-    let transaction = build_create_and_buy_transaction(rpc.clone(), payer.clone(), mint.clone(), ipfs, amount_sol, slippage_basis_points, priority_fee.clone()).await?;
-    rpc.send_and_confirm_transaction(&transaction).await?;
+    let build_instructions = timer.time(Stage::Quote, build_create_and_buy_instructions(rpc.clone(), payer.clone(), mint.clone(), ipfs, amount_sol, slippage_basis_points, priority_fee.clone())).await?;
+    let recent_blockhash = timer.time(Stage::Blockhash, rpc.get_latest_blockhash()).await?;
+    let transaction = timer.time_sync(Stage::BuildSign, || {
+        sign_create_and_buy_transaction(&payer, &mint, &priority_fee, build_instructions, extra_instructions, recent_blockhash)
+    })?;
+    let cost = check_launch_budget(launch_budget, &transaction, &[])?;
+    let preflight = match preflight {
+        true => Some(preflight_transaction(&mut timer, &rpc, &transaction).await?),
+        false => None,
+    };
+    let signature = match send_and_confirm_with_rpc_options(&mut timer, &send, Some(trade_id), &transaction, rpc_send_options).await {
+        Ok(signature) => signature,
+        Err(e) => recover_create_timeout(&rpc, &payer.pubkey(), &mint.pubkey(), e).await?,
+    };
+    if metadata_policy == MetadataPolicy::Immutable {
+        verify_metadata_policy(&rpc, metadata_policy, &mint.pubkey()).await?;
+    }
 
-    Ok(())
+    let result = CreateResult::new(mint.pubkey(), signature, metadata_uri, timer.finish(), preflight)?;
+    let mint_keypair = (mint.pubkey() != requested_mint).then_some(mint);
+    Ok(CreateResult { cost: Some(cost), mint_keypair, ..result })
 }
 
+/// Create and buy tokens in one transaction, tipping the first fee client
+///
+/// With `preflight`, the signed transaction is simulated first and nothing is sent if the
+/// simulation fails; the simulation is returned for auditing. `metadata_policy`, `mint_check`
+/// and the recovery of a timed out confirmation apply as in [`create_and_buy`], and so does
+/// `launch_budget`, the tip included.
 pub async fn create_and_buy_with_tip(
-    rpc: Arc<SolanaRpcClient>,
+    rpc: impl Into<RpcClients>,
     fee_clients: Vec<Arc<FeeClient>>,
     payer: Arc<Keypair>,
     mint: Keypair,
@@ -84,12 +233,24 @@ pub async fn create_and_buy_with_tip(
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: PriorityFee,
-) -> Result<(Signature, Pubkey), anyhow::Error> {
-    let start_time = Instant::now();
+    rpc_send_options: RpcSendOptions,
+    metadata_policy: MetadataPolicy,
+    mint_check: MintCheck,
+    preflight: bool,
+    extra_instructions: Option<Vec<Instruction>>,
+    launch_budget: Option<LaunchBudget>,
+) -> Result<CreateResult, anyhow::Error> {
+    let RpcClients { read: rpc, send } = rpc.into();
+    let requested_mint = mint.pubkey();
+    let mint = checked_mint(&rpc, mint, mint_check).await?;
+    let trade_id = create_and_buy_intent(&mint, amount_sol, slippage_basis_points, extra_instructions.as_deref());
+    let extra_instructions = with_metadata_policy(metadata_policy, &payer, &mint, extra_instructions)?;
+    let mut timer = StageTimer::new();
+    let metadata_uri = ipfs.metadata_uri.clone();
     let mint_keypair = mint;
     let mint_pubkey = mint_keypair.pubkey();
     let mint = Arc::new(mint_keypair);
-    let build_instructions = build_create_and_buy_instructions(rpc.clone(), payer.clone(), mint.clone(), ipfs, amount_sol, slippage_basis_points, priority_fee.clone()).await?;
+    let build_instructions = timer.time(Stage::Quote, build_create_and_buy_instructions(rpc.clone(), payer.clone(), mint.clone(), ipfs, amount_sol, slippage_basis_points, priority_fee.clone())).await?;
     
     let tip_account = if let Some(first_client) = fee_clients.first() {
         match first_client.get_tip_account().await {
@@ -110,44 +271,124 @@ pub async fn create_and_buy_with_tip(
         None
     };
 
-    let transaction = build_create_and_buy_transaction_with_tip(
-        rpc.clone(),
-        tip_account,
-        payer.clone(),
-        mint.clone(),
-        priority_fee.clone(),
-        build_instructions
-    ).await?;
-
-    println!("Transaction built. Submitting and awaiting confirmation...");
+    let tip_accounts: Vec<Pubkey> = tip_account.iter().map(|account| **account).collect();
+    let recent_blockhash = timer.time(Stage::Blockhash, rpc.get_latest_blockhash()).await?;
+    let transaction = timer.time_sync(Stage::BuildSign, || {
+        sign_create_and_buy_transaction_with_tip(tip_account, &payer, &mint, &priority_fee, build_instructions, extra_instructions, recent_blockhash)
+    })?;
+    let cost = check_launch_budget(launch_budget, &transaction, &tip_accounts)?;
 
     let signature = transaction.signatures[0];
     println!("Transaction signature: {}", signature);
 
-    let confirmation_result = rpc.send_and_confirm_transaction_with_spinner(&transaction).await;
-
-    match confirmation_result {
-        Ok(confirmed_signature) => {
-            if confirmed_signature != signature {
-                 println!("Warning: Confirmed signature {} differs from initial signature {}", confirmed_signature, signature);
-                 println!("Total create, buy, and confirm operation time: {:?}ms", start_time.elapsed().as_millis());
-                 Ok((confirmed_signature, mint_pubkey))
-            } else {
-                 println!("Transaction confirmed successfully!");
-                 println!("Total create, buy, and confirm operation time: {:?}ms", start_time.elapsed().as_millis());
-                 Ok((signature, mint_pubkey))
-            }
-        }
+    let preflight = match preflight {
+        true => Some(preflight_transaction(&mut timer, &rpc, &transaction).await?),
+        false => None,
+    };
+
+    let confirmed_signature = match send_and_confirm_with_rpc_options(&mut timer, &send, Some(trade_id), &transaction, rpc_send_options).await {
+        Ok(confirmed_signature) => confirmed_signature,
+        Err(e) if ConfirmationTimeout::find(&e).is_some() => recover_create_timeout(&rpc, &payer.pubkey(), &mint_pubkey, e).await?,
         Err(e) => {
             println!("Error sending/confirming transaction: {}", e);
-             if let Some(tx_error) = e.get_transaction_error() {
-                 println!("Transaction error details: {:?}", tx_error);
-             }
-             Err(anyhow!("Failed to send or confirm transaction: {}", e))
+            return Err(anyhow!("Failed to send or confirm transaction: {}", e));
+        }
+    };
+    if metadata_policy == MetadataPolicy::Immutable {
+        verify_metadata_policy(&rpc, metadata_policy, &mint_pubkey).await?;
+    }
+
+    let result = CreateResult::new(mint_pubkey, confirmed_signature, metadata_uri, timer.finish(), preflight)?;
+    let mint_keypair = (mint_pubkey != requested_mint).then_some(mint);
+    Ok(CreateResult { cost: Some(cost), mint_keypair, ..result })
+}
+
+/// Cost of the create-and-buy `transaction`, failing if it may spend more than `launch_budget`
+fn check_launch_budget(launch_budget: Option<LaunchBudget>, transaction: &VersionedTransaction, tip_accounts: &[Pubkey]) -> Result<LaunchCost, anyhow::Error> {
+    let cost = LaunchCost::of_transactions(std::slice::from_ref(transaction), tip_accounts);
+    match launch_budget {
+        Some(budget) => Ok(budget.check(cost)?),
+        None => Ok(cost),
+    }
+}
+
+fn create_and_buy_intent(mint: &Keypair, amount_sol: u64, slippage_basis_points: Option<u64>, extra_instructions: Option<&[Instruction]>) -> TradeId {
+    record_intent(TradeIntent {
+        side: TradeSide::CreateAndBuy,
+        mint: mint.pubkey(),
+        amount_sol: Some(amount_sol),
+        amount_token: None,
+        slippage_basis_points,
+        trade_tag: extra_instructions.and_then(memo_text),
+        idempotency_key: None,
+    })
+}
+
+/// What `create_and_buy` does about a mint that already exists on-chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MintCheck {
+    /// Don't check, saving an RPC round trip; an existing mint fails on-chain after broadcasting
+    Skip,
+    /// Fail with [`CreateError::MintAlreadyExists`] before building anything
+    #[default]
+    Fail,
+    /// Replace an existing mint with a fresh keypair, checking that one once
+    Regenerate,
+}
+
+#[derive(Debug, Error)]
+pub enum CreateError {
+    #[error("mint {mint} or its bonding curve already exists")]
+    MintAlreadyExists { mint: Pubkey },
+}
+
+/// Applies `mint_check` to `mint`, returning the keypair of the mint to create
+async fn checked_mint(rpc: &SolanaRpcClient, mint: Keypair, mint_check: MintCheck) -> Result<Keypair, anyhow::Error> {
+    match mint_check {
+        MintCheck::Skip => Ok(mint),
+        MintCheck::Fail => {
+            check_mint_is_new(rpc, &mint.pubkey()).await?;
+            Ok(mint)
         }
+        MintCheck::Regenerate => match check_mint_is_new(rpc, &mint.pubkey()).await {
+            Err(e) if e.downcast_ref::<CreateError>().is_some() => {
+                let fresh = Keypair::new();
+                tracing::warn!(existing = %mint.pubkey(), replacement = %fresh.pubkey(), "mint already exists, retrying with a fresh keypair");
+                check_mint_is_new(rpc, &fresh.pubkey()).await?;
+                Ok(fresh)
+            }
+            result => result.map(|_| mint),
+        },
+    }
+}
+
+/// Fetches `mint` and its bonding curve in one request, failing if either exists
+async fn check_mint_is_new(rpc: &SolanaRpcClient, mint: &Pubkey) -> Result<(), anyhow::Error> {
+    let mut addresses = vec![*mint];
+    addresses.extend(get_bonding_curve_pda(mint));
+    let accounts = rpc.get_multiple_accounts(&addresses).await?;
+    Ok(ensure_accounts_missing(mint, &accounts)?)
+}
+
+fn ensure_accounts_missing(mint: &Pubkey, accounts: &[Option<Account>]) -> Result<(), CreateError> {
+    match accounts.iter().flatten().any(|account| account.lamports > 0) {
+        true => Err(CreateError::MintAlreadyExists { mint: *mint }),
+        false => Ok(()),
     }
 }
 
+/// Puts the instructions applying `metadata_policy` ahead of `extra_instructions`
+pub(crate) fn with_metadata_policy(
+    metadata_policy: MetadataPolicy,
+    payer: &Keypair,
+    mint: &Keypair,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<Vec<Instruction>, anyhow::Error> {
+    let mut instructions = metadata_policy_instructions(metadata_policy, &program_update_authority(), &payer.pubkey(), &mint.pubkey())?;
+    instructions.extend(extra_instructions.unwrap_or_default());
+    Ok(instructions)
+}
+
 pub async fn build_create_and_buy_transaction(
     rpc: Arc<SolanaRpcClient>,
     payer: Arc<Keypair>,
@@ -156,24 +397,45 @@ pub async fn build_create_and_buy_transaction(
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
     priority_fee: PriorityFee,
-) -> Result<Transaction, anyhow::Error> {
-    let mut instructions = vec![
-        ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price),
-        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.unit_limit),
-    ];
-
-    let build_instructions = build_create_and_buy_instructions(rpc.clone(), payer.clone(), mint.clone(), ipfs, amount_sol, slippage_basis_points, priority_fee.clone()).await?;
-    instructions.extend(build_instructions);
+    extra_instructions: Vec<Instruction>,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let (build_instructions, recent_blockhash) = tokio::try_join!(
+        build_create_and_buy_instructions(rpc.clone(), payer.clone(), mint.clone(), ipfs, amount_sol, slippage_basis_points, priority_fee.clone()),
+        async { Ok::<_, anyhow::Error>(rpc.get_latest_blockhash().await?) },
+    )?;
+    sign_create_and_buy_transaction(&payer, &mint, &priority_fee, build_instructions, extra_instructions, recent_blockhash)
+}
 
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer.as_ref(), mint.as_ref()],
-        recent_blockhash,
-    );
+#[deprecated(note = "use `build_create_and_buy_transaction`, which returns a `VersionedTransaction`")]
+#[allow(deprecated)]
+pub async fn build_create_and_buy_transaction_legacy(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    mint: Arc<Keypair>,
+    ipfs: TokenMetadataIPFS,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: PriorityFee,
+    extra_instructions: Vec<Instruction>,
+) -> Result<Transaction, anyhow::Error> {
+    let (build_instructions, recent_blockhash) = tokio::try_join!(
+        build_create_and_buy_instructions(rpc.clone(), payer.clone(), mint.clone(), ipfs, amount_sol, slippage_basis_points, priority_fee.clone()),
+        async { Ok::<_, anyhow::Error>(rpc.get_latest_blockhash().await?) },
+    )?;
+    let instructions = create_and_buy_transaction_instructions(&priority_fee, None, build_instructions, extra_instructions);
+    crate::pumpfun::common::sign_legacy_transaction(&instructions, &[payer.as_ref(), mint.as_ref()], recent_blockhash)
+}
 
-    Ok(transaction)
+fn sign_create_and_buy_transaction(
+    payer: &Keypair,
+    mint: &Keypair,
+    priority_fee: &PriorityFee,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+    blockhash: Hash,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let instructions = create_and_buy_transaction_instructions(priority_fee, None, build_instructions, extra_instructions);
+    sign_versioned_transaction(&instructions, &[payer, mint], blockhash)
 }
 
 pub async fn build_create_and_buy_transaction_with_tip(
@@ -183,40 +445,60 @@ pub async fn build_create_and_buy_transaction_with_tip(
     mint: Arc<Keypair>,
     priority_fee: PriorityFee,
     build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
 ) -> Result<VersionedTransaction, anyhow::Error> {
-    const INCREASED_COMPUTE_LIMIT: u32 = 600_000; // Increased CU Limit
-
-    let mut instructions = vec![
-        ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price),
-        ComputeBudgetInstruction::set_compute_unit_limit(INCREASED_COMPUTE_LIMIT), 
-    ];
-
-    if let Some(tip_acc) = tip_account {
-         instructions.push(
-             system_instruction::transfer(
-                 &payer.pubkey(),
-                 &tip_acc,
-                 sol_to_lamports(priority_fee.buy_tip_fee),
-             )
-         );
-         println!("Added tip instruction for account: {}", tip_acc);
-    } else {
-         println!("No tip account provided, skipping tip instruction.");
-    }
-
-    instructions.extend(build_instructions);
-
     let recent_blockhash = rpc.get_latest_blockhash().await?;
-    let v0_message: v0::Message =
-        v0::Message::try_compile(&payer.pubkey(), &instructions, &[], recent_blockhash)?;
+    sign_create_and_buy_transaction_with_tip(tip_account, &payer, &mint, &priority_fee, build_instructions, extra_instructions, recent_blockhash)
+}
+
+fn sign_create_and_buy_transaction_with_tip(
+    tip_account: Option<Arc<Pubkey>>,
+    payer: &Keypair,
+    mint: &Keypair,
+    priority_fee: &PriorityFee,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+    blockhash: Hash,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let tip = match tip_account {
+        Some(tip_acc) => {
+            println!("Added tip instruction for account: {}", tip_acc);
+            Some(system_instruction::transfer(
+                &payer.pubkey(),
+                &tip_acc,
+                priority_fee.buy_tip_fee.lamports(),
+            ))
+        }
+        None => {
+            println!("No tip account provided, skipping tip instruction.");
+            None
+        }
+    };
 
-    let versioned_message: VersionedMessage = VersionedMessage::V0(v0_message);
-    let transaction = VersionedTransaction::try_new(versioned_message, &[payer.as_ref(), mint.as_ref()])?;
+    let instructions = create_and_buy_transaction_instructions(priority_fee, tip, build_instructions, extra_instructions);
+    let transaction = sign_versioned_transaction(&instructions, &[payer, mint], blockhash)?;
     println!("Transaction built and signed by payer {} and mint {}", payer.pubkey(), mint.pubkey());
 
     Ok(transaction)
 }
 
+/// Compute budget, optional tip, create-and-buy and extra instructions of a create-and-buy
+/// transaction
+pub(crate) fn create_and_buy_transaction_instructions(
+    priority_fee: &PriorityFee,
+    tip: Option<Instruction>,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+) -> Vec<Instruction> {
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price),
+        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.create_limit()),
+    ];
+    instructions.extend(tip);
+    instructions.extend(append_extra_instructions(build_instructions, extra_instructions));
+    dedup_compute_budget_instructions(instructions)
+}
+
 pub async fn build_create_and_buy_instructions(
     rpc: Arc<SolanaRpcClient>,
     payer: Arc<Keypair>,
@@ -224,53 +506,126 @@ pub async fn build_create_and_buy_instructions(
     ipfs: TokenMetadataIPFS,
     amount_sol: u64,
     slippage_basis_points: Option<u64>,
-    priority_fee: PriorityFee,
+    _priority_fee: PriorityFee,
 ) -> Result<Vec<Instruction>, anyhow::Error> {
     if amount_sol == 0 {
         return Err(anyhow!("Amount cannot be zero"));
     }
 
-    let rpc = rpc.as_ref();
-    let global_account = get_global_account(rpc).await?;
-    let buy_amount = global_account.get_initial_buy_price(amount_sol);
-    let buy_amount_with_slippage =
-        get_buy_amount_with_slippage(amount_sol, slippage_basis_points);
-
-    let mut instructions = vec![];
+    let global_account = get_global_account(rpc.as_ref()).await?;
 
     println!("SDK creating token with name='{}', symbol='{}', uri='{}'", 
              ipfs.metadata.name, ipfs.metadata.symbol, ipfs.metadata_uri);
     
-    let original_name = ipfs.metadata.name.clone();
-    let original_symbol = ipfs.metadata.symbol.clone();
-    
-    instructions.push(instruction::create(
-        payer.as_ref(),
-        mint.as_ref(),
-        instruction::Create {
-            _name: original_name,
-            _symbol: original_symbol,
+    build_create_and_buy_instructions_offline(&payer.pubkey(), &mint.pubkey(), &ipfs, amount_sol, slippage_basis_points, &global_account)
+}
+
+/// [`build_create_and_buy_instructions`] from the global account the caller supplies, without
+/// any RPC call; the dev buy is quoted from the initial curve
+pub fn build_create_and_buy_instructions_offline(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    ipfs: &TokenMetadataIPFS,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    global: &GlobalAccount,
+) -> Result<Vec<Instruction>, anyhow::Error> {
+    if amount_sol == 0 {
+        return Err(anyhow!("Amount cannot be zero"));
+    }
+
+    let quote = quote_initial_buy(global, amount_sol, slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE))?;
+    if quote.token_amount == 0 {
+        return Err(anyhow!("Amount buys no tokens"));
+    }
+
+    Ok(vec![
+        instruction::create(
+            payer,
+            mint,
+            instruction::Create {
+                _name: ipfs.metadata.name.clone(),
+                _symbol: ipfs.metadata.symbol.clone(),
+                _uri: ipfs.metadata_uri.clone(),
+                payer_pubkey: *payer,
+            },
+        ),
+        create_associated_token_account(payer, payer, mint, &constants::accounts::TOKEN_PROGRAM),
+        instruction::buy(
+            payer,
+            mint,
+            &global.fee_recipient,
+            &constants::accounts::TOKEN_PROGRAM,
+            instruction::Buy::new(quote.token_amount, quote.max_sol_cost)?,
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_generated_mints_end_with_the_vanity_suffix() {
+        let mint = generate_mint(Some("p")).await.unwrap();
+        assert!(mint.pubkey().to_string().ends_with('p'));
+        assert!(generate_mint(None).await.is_ok());
+
+        assert!(generate_mint(Some("pumpf")).await.unwrap_err().to_string().contains("longer than 4"));
+        assert!(generate_mint(Some("p0")).await.unwrap_err().to_string().contains("not base58"));
+    }
+
+    #[test]
+    fn test_offline_create_and_buy_uses_only_the_supplied_state() {
+        let (payer, mint, fee_recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let global = GlobalAccount::new(0, true, Pubkey::new_unique(), fee_recipient, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100);
+        let metadata = serde_json::from_value(serde_json::json!({ "name": "Fixture", "symbol": "FIX", "image": "https://ipfs.io/ipfs/image" })).unwrap();
+        let ipfs = TokenMetadataIPFS { metadata, metadata_uri: "https://ipfs.io/ipfs/metadata".to_string() };
+
+        let instructions = build_create_and_buy_instructions_offline(&payer, &mint, &ipfs, 1_000_000_000, Some(500), &global).unwrap();
+        assert_eq!(instructions.len(), 3);
+        let create = instruction::create(&payer, &mint, instruction::Create {
+            _name: "Fixture".to_string(),
+            _symbol: "FIX".to_string(),
             _uri: ipfs.metadata_uri.clone(),
-            payer_pubkey: payer.pubkey(),
-        },
-    ));
+            payer_pubkey: payer,
+        });
+        assert_eq!(instructions[0], create);
+        assert_eq!(instructions[1], create_associated_token_account(&payer, &payer, &mint, &constants::accounts::TOKEN_PROGRAM));
 
-    instructions.push(create_associated_token_account(
-        &payer.pubkey(),
-        &payer.pubkey(),
-        &mint.pubkey(),
-        &constants::accounts::TOKEN_PROGRAM,
-    ));
+        let quote = quote_initial_buy(&global, 1_000_000_000, 500).unwrap();
+        let buy = instruction::buy(&payer, &mint, &fee_recipient, &constants::accounts::TOKEN_PROGRAM, instruction::Buy { amount: quote.token_amount, max_sol_cost: quote.max_sol_cost });
+        assert_eq!(instructions[2], buy);
 
-    instructions.push(instruction::buy(
-        payer.as_ref(),
-        &mint.pubkey(),
-        &global_account.fee_recipient,
-        instruction::Buy {
-            _amount: buy_amount,
-            _max_sol_cost: buy_amount_with_slippage,
-        },
-    ));
+        let error = build_create_and_buy_instructions_offline(&payer, &mint, &ipfs, 0, None, &global).unwrap_err();
+        assert_eq!(error.to_string(), "Amount cannot be zero");
+    }
 
-    Ok(instructions)
+    #[test]
+    fn test_existing_mint_or_bonding_curve_is_rejected() {
+        let mint = Pubkey::new_unique();
+        let account = Account { lamports: 1_461_600, ..Default::default() };
+
+        assert!(ensure_accounts_missing(&mint, &[None, None]).is_ok());
+        assert!(ensure_accounts_missing(&mint, &[Some(Account::default()), None]).is_ok());
+        for accounts in [[Some(account.clone()), None], [None, Some(account.clone())]] {
+            let err = ensure_accounts_missing(&mint, &accounts).unwrap_err();
+            assert!(matches!(err, CreateError::MintAlreadyExists { mint: existing } if existing == mint));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mint_check_modes() {
+        let rpc = SolanaRpcClient::new_mock("fails".to_string());
+        let mint = Keypair::new();
+        let pubkey = mint.pubkey();
+
+        // Skipping makes no request, so the failing RPC is never hit
+        assert_eq!(checked_mint(&rpc, mint, MintCheck::Skip).await.unwrap().pubkey(), pubkey);
+
+        // RPC errors are not mistaken for an existing mint
+        let err = checked_mint(&rpc, Keypair::new(), MintCheck::Regenerate).await.unwrap_err();
+        assert!(err.downcast_ref::<CreateError>().is_none());
+        assert!(checked_mint(&rpc, Keypair::new(), MintCheck::Fail).await.is_err());
+    }
 }
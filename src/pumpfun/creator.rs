@@ -0,0 +1,255 @@
+//! Launch history of token creators
+//!
+//! [`get_creator_history`] pages through a creator's transaction history and collects the
+//! tokens they created. Every transaction costs an RPC call, so the scan is capped at
+//! [`MAX_SCANNED_TRANSACTIONS`], fetches run with bounded concurrency, and rate-limited calls
+//! are retried with backoff. Histories are cached for [`CREATOR_HISTORY_TTL`], which lets
+//! [`is_serial_deployer_cached`] answer from memory inside latency-sensitive filters.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use futures::{stream, StreamExt};
+use solana_client::{client_error::ClientError, rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+
+use crate::common::{cache_stats::{CacheCounters, CacheStats}, logs_data::DexInstruction, logs_filters::LogFilter, SolanaRpcClient};
+
+/// Most transactions scanned for one history, whatever the requested limit
+pub const MAX_SCANNED_TRANSACTIONS: usize = 1_000;
+/// Transactions scanned by [`is_serial_deployer`]
+pub const DEFAULT_HISTORY_LIMIT: usize = 200;
+pub const CREATOR_HISTORY_TTL: Duration = Duration::from_secs(300);
+
+const SIGNATURES_PER_PAGE: usize = 1_000;
+const CONCURRENT_FETCHES: usize = 8;
+const MAX_RETRIES: u32 = 3;
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_CACHED_CREATORS: usize = 10_000;
+
+lazy_static::lazy_static! {
+    static ref CREATOR_HISTORY_CACHE: RwLock<HashMap<Pubkey, CachedHistory>> = RwLock::new(HashMap::new());
+}
+
+static CREATOR_HISTORY_COUNTERS: CacheCounters = CacheCounters::new();
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatorHistory {
+    pub creator: Pubkey,
+    /// Tokens created within the scanned transactions
+    pub total_launches: usize,
+    /// Created mints with their slots, newest first
+    pub recent_launches: Vec<(Pubkey, u64)>,
+    pub scanned_transactions: usize,
+    /// The scan reached the creator's first transaction, so `total_launches` is exact
+    pub complete: bool,
+}
+
+struct CachedHistory {
+    history: CreatorHistory,
+    limit: usize,
+    fetched_at: Instant,
+}
+
+/// Tokens created by `creator` within their latest `limit` successful transactions, capped
+/// at [`MAX_SCANNED_TRANSACTIONS`]
+///
+/// Served from the cache when a fresh history of at least `limit` transactions is there.
+pub async fn get_creator_history(rpc: &SolanaRpcClient, creator: &Pubkey, limit: usize) -> Result<CreatorHistory, anyhow::Error> {
+    let limit = limit.min(MAX_SCANNED_TRANSACTIONS);
+    if let Some(history) = cached(creator, limit) {
+        return Ok(history);
+    }
+
+    let (signatures, complete) = fetch_signatures(rpc, creator, limit).await?;
+    let transactions: Vec<_> = stream::iter(signatures.iter())
+        .map(|signature| fetch_transaction(rpc, signature))
+        .buffered(CONCURRENT_FETCHES)
+        .collect()
+        .await;
+
+    let mut recent_launches = vec![];
+    for transaction in transactions {
+        let transaction = transaction?;
+        let Some(OptionSerializer::Some(logs)) = transaction.transaction.meta.map(|meta| meta.log_messages) else {
+            continue;
+        };
+        recent_launches.extend(launches_in_logs(creator, transaction.slot, &logs));
+    }
+
+    let history = CreatorHistory {
+        creator: *creator,
+        total_launches: recent_launches.len(),
+        recent_launches,
+        scanned_transactions: signatures.len(),
+        complete,
+    };
+    store(history.clone(), limit);
+    Ok(history)
+}
+
+/// Whether `creator` launched at least `threshold` tokens within their latest
+/// [`DEFAULT_HISTORY_LIMIT`] transactions
+pub async fn is_serial_deployer(rpc: &SolanaRpcClient, creator: &Pubkey, threshold: usize) -> Result<bool, anyhow::Error> {
+    if let Some(serial) = cached_serial_deployer(creator, threshold) {
+        return Ok(serial);
+    }
+    Ok(get_creator_history(rpc, creator, DEFAULT_HISTORY_LIMIT).await?.total_launches >= threshold)
+}
+
+/// Answers from a fresh cached history only, without RPC calls; `None` if there is none
+pub fn is_serial_deployer_cached(creator: &Pubkey, threshold: usize) -> Option<bool> {
+    CREATOR_HISTORY_COUNTERS.record(cached_serial_deployer(creator, threshold))
+}
+
+/// Stats of the creator history cache
+pub fn creator_history_cache_stats() -> CacheStats {
+    let cache = CREATOR_HISTORY_CACHE.read().unwrap();
+    let oldest = cache.values().map(|cached| cached.fetched_at.elapsed()).max();
+    CREATOR_HISTORY_COUNTERS.stats("creator_history", cache.len(), oldest)
+}
+
+pub fn clear_creator_history_cache() {
+    CREATOR_HISTORY_CACHE.write().unwrap().clear();
+}
+
+fn cached_serial_deployer(creator: &Pubkey, threshold: usize) -> Option<bool> {
+    let cache = CREATOR_HISTORY_CACHE.read().unwrap();
+    let cached = cache.get(creator).filter(|cached| cached.fetched_at.elapsed() < CREATOR_HISTORY_TTL)?;
+    // Launches only grow with a longer scan, so reaching the threshold is final
+    (cached.history.total_launches >= threshold || cached.history.complete).then_some(cached.history.total_launches >= threshold)
+}
+
+fn cached(creator: &Pubkey, limit: usize) -> Option<CreatorHistory> {
+    let cache = CREATOR_HISTORY_CACHE.read().unwrap();
+    let history = cache
+        .get(creator)
+        .filter(|cached| cached.fetched_at.elapsed() < CREATOR_HISTORY_TTL && (cached.limit >= limit || cached.history.complete))
+        .map(|cached| cached.history.clone());
+    CREATOR_HISTORY_COUNTERS.record(history)
+}
+
+fn store(history: CreatorHistory, limit: usize) {
+    let mut cache = CREATOR_HISTORY_CACHE.write().unwrap();
+    if cache.len() >= MAX_CACHED_CREATORS {
+        cache.retain(|_, cached| cached.fetched_at.elapsed() < CREATOR_HISTORY_TTL);
+    }
+    if cache.len() >= MAX_CACHED_CREATORS {
+        if let Some(oldest) = cache.iter().min_by_key(|(_, cached)| cached.fetched_at).map(|(creator, _)| *creator) {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(history.creator, CachedHistory { history, limit, fetched_at: Instant::now() });
+}
+
+/// Signatures of the latest `limit` successful transactions of `creator`, and whether they
+/// are all of them
+async fn fetch_signatures(rpc: &SolanaRpcClient, creator: &Pubkey, limit: usize) -> Result<(Vec<Signature>, bool), anyhow::Error> {
+    let mut signatures = vec![];
+    if limit == 0 {
+        return Ok((signatures, false));
+    }
+    let mut before = None;
+    loop {
+        let requested = SIGNATURES_PER_PAGE.min(limit - signatures.len());
+        let page = with_retries(move || {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(requested),
+                commitment: Some(CommitmentConfig::confirmed()),
+            };
+            rpc.get_signatures_for_address_with_config(creator, config)
+        })
+        .await?;
+        let Some(last) = page.last() else {
+            return Ok((signatures, true));
+        };
+        before = Signature::from_str(&last.signature).ok();
+        let exhausted = page.len() < requested;
+
+        signatures.extend(
+            page.iter()
+                .filter(|status| status.err.is_none())
+                .filter_map(|status| Signature::from_str(&status.signature).ok())
+                .take(limit - signatures.len()),
+        );
+        if exhausted {
+            return Ok((signatures, true));
+        }
+        if signatures.len() >= limit || before.is_none() {
+            return Ok((signatures, false));
+        }
+    }
+}
+
+async fn fetch_transaction(rpc: &SolanaRpcClient, signature: &Signature) -> Result<EncodedConfirmedTransactionWithStatusMeta, anyhow::Error> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    Ok(with_retries(move || rpc.get_transaction_with_config(signature, config.clone())).await?)
+}
+
+/// Runs `call`, retrying with a growing delay while the RPC node rate-limits it
+async fn with_retries<T, F, Fut>(mut call: F) -> Result<T, ClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T, ClientError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match call().await {
+            Err(e) if attempt < MAX_RETRIES && is_rate_limited(&e) => {
+                attempt += 1;
+                tokio::time::sleep(RATE_LIMIT_BACKOFF * attempt).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_rate_limited(error: &ClientError) -> bool {
+    let message = error.to_string();
+    message.contains("429") || message.contains("Too Many Requests") || message.contains("rate limit")
+}
+
+fn launches_in_logs(creator: &Pubkey, slot: u64, logs: &[String]) -> Vec<(Pubkey, u64)> {
+    let Ok(instructions) = LogFilter::parse_instruction(logs, None) else {
+        return vec![];
+    };
+    instructions
+        .into_iter()
+        .filter_map(|instruction| match instruction {
+            DexInstruction::CreateToken(token) if token.user == *creator => Some((token.mint, slot)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_answer_needs_a_conclusive_history() {
+        let creator = Pubkey::new_unique();
+        let launches = vec![(Pubkey::new_unique(), 2), (Pubkey::new_unique(), 1)];
+        store(
+            CreatorHistory { creator, total_launches: 2, recent_launches: launches, scanned_transactions: 200, complete: false },
+            DEFAULT_HISTORY_LIMIT,
+        );
+
+        assert_eq!(is_serial_deployer_cached(&creator, 2), Some(true));
+        // More launches may hide beyond the scanned transactions
+        assert_eq!(is_serial_deployer_cached(&creator, 3), None);
+        assert!(cached(&creator, MAX_SCANNED_TRANSACTIONS).is_none());
+        assert_eq!(is_serial_deployer_cached(&Pubkey::new_unique(), 1), None);
+    }
+}
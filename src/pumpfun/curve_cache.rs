@@ -0,0 +1,330 @@
+//! Short-lived cache of bonding curve accounts
+//!
+//! Quoting a hot token reads its bonding curve on every tick. With a TTL set through
+//! [`set_bonding_curve_cache_ttl`], [`get_bonding_curve_account`](super::common::get_bonding_curve_account)
+//! serves reads from this cache for that long after each fetch. Entries are stamped with the
+//! slot they were read at, and an entry never replaces one from a later slot.
+//!
+//! Each RPC URL has its own cache, see [`bonding_curve_cache`], so clients of different
+//! clusters or nodes never serve each other's curves.
+//!
+//! A bonding curve subscription such as `YellowstoneGrpc::subscribe_bonding_curves` writes
+//! every update through into a cache. Streamed entries are served until the subscription ends
+//! or for [`DEFAULT_STREAM_STALENESS`] after their last update, so quotes are served from
+//! streamed state without RPC calls. Expired entries are swept out as new ones are stored.
+//!
+//! Transaction builders read with [`CurveRead::Fresh`], which always goes to the RPC.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    accounts::BondingCurveAccount,
+    common::{cache_stats::{CacheCounters, CacheStats}, SolanaRpcClient},
+};
+
+/// Suggested TTL for quoting loops
+pub const DEFAULT_CURVE_CACHE_TTL: Duration = Duration::from_millis(200);
+
+/// How long a streamed entry is served after its last update
+pub const DEFAULT_STREAM_STALENESS: Duration = Duration::from_secs(30);
+
+/// Least time between two sweeps of expired entries
+const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+lazy_static::lazy_static! {
+    static ref BONDING_CURVE_CACHES: RwLock<HashMap<String, Arc<BondingCurveCache>>> = RwLock::new(HashMap::new());
+    static ref BONDING_CURVE_CACHE_TTL: RwLock<Option<Duration>> = RwLock::new(None);
+}
+
+/// Cache `get_bonding_curve_account` uses for reads through `rpc`
+#[inline]
+pub fn bonding_curve_cache(rpc: &SolanaRpcClient) -> Arc<BondingCurveCache> {
+    bonding_curve_cache_for(&rpc.url())
+}
+
+/// Cache of the bonding curves read through the RPC at `rpc_url`
+pub fn bonding_curve_cache_for(rpc_url: &str) -> Arc<BondingCurveCache> {
+    if let Some(cache) = BONDING_CURVE_CACHES.read().unwrap().get(rpc_url) {
+        return cache.clone();
+    }
+    let ttl = *BONDING_CURVE_CACHE_TTL.read().unwrap();
+    BONDING_CURVE_CACHES
+        .write()
+        .unwrap()
+        .entry(rpc_url.to_string())
+        .or_insert_with(|| Arc::new(BondingCurveCache::new(ttl)))
+        .clone()
+}
+
+/// Enables caching of RPC reads for `ttl`, or disables it with `None`, in every cache
+pub fn set_bonding_curve_cache_ttl(ttl: Option<Duration>) {
+    *BONDING_CURVE_CACHE_TTL.write().unwrap() = ttl;
+    for cache in BONDING_CURVE_CACHES.read().unwrap().values() {
+        cache.set_ttl(ttl);
+    }
+}
+
+/// How a bonding curve read may use the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurveRead {
+    /// Serve a cached entry if it is still valid
+    #[default]
+    Cached,
+    /// Always fetch from the RPC, e.g. right before building a transaction
+    Fresh,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveSource {
+    Rpc,
+    Stream,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedCurve {
+    pub account: Arc<BondingCurveAccount>,
+    pub slot: u64,
+    pub source: CurveSource,
+    pub cached_at: Instant,
+}
+
+pub struct BondingCurveCache {
+    ttl: RwLock<Option<Duration>>,
+    stream_staleness: RwLock<Duration>,
+    entries: RwLock<HashMap<Pubkey, CachedCurve>>,
+    last_sweep: Mutex<Instant>,
+    counters: CacheCounters,
+}
+
+impl BondingCurveCache {
+    pub fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            ttl: RwLock::new(ttl),
+            stream_staleness: RwLock::new(DEFAULT_STREAM_STALENESS),
+            entries: RwLock::new(HashMap::new()),
+            last_sweep: Mutex::new(Instant::now()),
+            counters: CacheCounters::new(),
+        }
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        *self.ttl.read().unwrap()
+    }
+
+    pub fn set_ttl(&self, ttl: Option<Duration>) {
+        *self.ttl.write().unwrap() = ttl;
+    }
+
+    pub fn stream_staleness(&self) -> Duration {
+        *self.stream_staleness.read().unwrap()
+    }
+
+    /// Sets how long a streamed entry is served after its last update
+    pub fn set_stream_staleness(&self, staleness: Duration) {
+        *self.stream_staleness.write().unwrap() = staleness;
+    }
+
+    /// Returns the entry of `mint` if it was streamed or fetched recently enough
+    pub fn get(&self, mint: &Pubkey) -> Option<CachedCurve> {
+        self.counters.record(self.lookup(mint))
+    }
+
+    fn lookup(&self, mint: &Pubkey) -> Option<CachedCurve> {
+        let entry = self.entries.read().unwrap().get(mint).cloned()?;
+        self.is_valid(&entry).then_some(entry)
+    }
+
+    fn is_valid(&self, entry: &CachedCurve) -> bool {
+        let max_age = match entry.source {
+            CurveSource::Stream => Some(self.stream_staleness()),
+            CurveSource::Rpc => self.ttl(),
+        };
+        max_age.is_some_and(|max_age| entry.cached_at.elapsed() < max_age)
+    }
+
+    /// Stores `account` as read at `slot`, unless a valid entry from a later slot is cached
+    ///
+    /// RPC reads are only stored while a TTL is set. Returns whether the entry was stored.
+    pub fn insert(&self, mint: Pubkey, slot: u64, account: Arc<BondingCurveAccount>, source: CurveSource) -> bool {
+        if source == CurveSource::Rpc && self.ttl().is_none() {
+            return false;
+        }
+        self.sweep_if_due();
+
+        let mut entries = self.entries.write().unwrap();
+        if entries.get(&mint).is_some_and(|current| current.slot > slot && self.is_valid(current)) {
+            return false;
+        }
+        entries.insert(mint, CachedCurve { account, slot, source, cached_at: Instant::now() });
+        true
+    }
+
+    /// Drops every expired entry, returning how many were dropped
+    pub fn evict_expired(&self) -> usize {
+        let mut entries = self.entries.write().unwrap();
+        let before = entries.len();
+        entries.retain(|_, entry| self.is_valid(entry));
+        before - entries.len()
+    }
+
+    fn sweep_if_due(&self) {
+        {
+            let mut last_sweep = self.last_sweep.lock().unwrap();
+            if last_sweep.elapsed() < SWEEP_INTERVAL {
+                return;
+            }
+            *last_sweep = Instant::now();
+        }
+        self.evict_expired();
+    }
+
+    /// Drops streamed entries of `mints`, e.g. when their subscription ends
+    pub fn forget_streamed(&self, mints: &[Pubkey]) {
+        let mut entries = self.entries.write().unwrap();
+        for mint in mints {
+            if entries.get(mint).is_some_and(|entry| entry.source == CurveSource::Stream) {
+                entries.remove(mint);
+            }
+        }
+    }
+
+    pub fn invalidate(&self, mint: &Pubkey) {
+        self.entries.write().unwrap().remove(mint);
+    }
+
+    /// Drops every entry; a running subscription refills its mints with their next update
+    pub fn clear(&self) {
+        self.entries.write().unwrap().clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let entries = self.entries.read().unwrap();
+        let oldest = entries.values().map(|entry| entry.cached_at.elapsed()).max();
+        self.counters.stats("bonding_curve", entries.len(), oldest)
+    }
+}
+
+/// Drops the streamed entries of its mints from the cache when dropped, so a subscription
+/// that is aborted doesn't leave them behind
+pub struct StreamGuard {
+    cache: Arc<BondingCurveCache>,
+    mints: Vec<Pubkey>,
+}
+
+impl StreamGuard {
+    pub fn new(cache: Arc<BondingCurveCache>, mints: Vec<Pubkey>) -> Self {
+        Self { cache, mints }
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.cache.forget_streamed(&self.mints);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(real_token_reserves: u64) -> Arc<BondingCurveAccount> {
+        Arc::new(BondingCurveAccount::new(0, 0, 0, real_token_reserves, 0, 0, false))
+    }
+
+    #[test]
+    fn test_rpc_entries_expire_and_require_ttl() {
+        let cache = BondingCurveCache::new(None);
+        let mint = Pubkey::new_unique();
+        assert!(!cache.insert(mint, 10, curve(1), CurveSource::Rpc));
+        assert!(cache.get(&mint).is_none());
+
+        cache.set_ttl(Some(Duration::from_millis(20)));
+        assert!(cache.insert(mint, 10, curve(1), CurveSource::Rpc));
+        assert_eq!(cache.get(&mint).unwrap().slot, 10);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get(&mint).is_none());
+    }
+
+    #[test]
+    fn test_streamed_entries_win_until_forgotten() {
+        let cache = BondingCurveCache::new(Some(Duration::from_millis(200)));
+        let mint = Pubkey::new_unique();
+
+        assert!(cache.insert(mint, 12, curve(2), CurveSource::Stream));
+        // A slower RPC read from an earlier slot doesn't overwrite streamed state
+        assert!(!cache.insert(mint, 11, curve(1), CurveSource::Rpc));
+        let entry = cache.get(&mint).unwrap();
+        assert_eq!((entry.slot, entry.source, entry.account.real_token_reserves), (12, CurveSource::Stream, 2));
+
+        cache.set_ttl(None);
+        assert!(cache.get(&mint).is_some());
+
+        cache.forget_streamed(&[mint]);
+        assert!(cache.get(&mint).is_none());
+    }
+
+    #[test]
+    fn test_streamed_entries_go_stale_and_expired_entries_are_evicted() {
+        let cache = BondingCurveCache::new(Some(Duration::from_millis(20)));
+        cache.set_stream_staleness(Duration::from_millis(40));
+        let (streamed, fetched) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(cache.insert(streamed, 12, curve(2), CurveSource::Stream));
+        assert!(cache.insert(fetched, 12, curve(1), CurveSource::Rpc));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get(&streamed).is_some());
+        assert_eq!(cache.evict_expired(), 1);
+        assert!(cache.get(&fetched).is_none());
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(cache.get(&streamed).is_none());
+        // A stale entry from a later slot no longer blocks a fresh read
+        assert!(cache.insert(streamed, 11, curve(3), CurveSource::Rpc));
+        assert_eq!(cache.evict_expired(), 0);
+    }
+
+    #[test]
+    fn test_stream_guard_forgets_on_drop() {
+        let cache = Arc::new(BondingCurveCache::new(None));
+        let mint = Pubkey::new_unique();
+        let guard = StreamGuard::new(cache.clone(), vec![mint]);
+        assert!(cache.insert(mint, 1, curve(1), CurveSource::Stream));
+        drop(guard);
+        assert!(cache.get(&mint).is_none());
+    }
+
+    #[test]
+    fn test_caches_are_scoped_by_rpc_url() {
+        let mainnet = bonding_curve_cache_for("https://mainnet.example");
+        let devnet = bonding_curve_cache_for("https://devnet.example");
+        let mint = Pubkey::new_unique();
+        assert!(mainnet.insert(mint, 1, curve(1), CurveSource::Stream));
+        assert!(devnet.get(&mint).is_none());
+        assert!(bonding_curve_cache_for("https://mainnet.example").get(&mint).is_some());
+    }
+
+    #[test]
+    fn test_stats_survive_clear() {
+        let cache = BondingCurveCache::new(Some(Duration::from_secs(1)));
+        let mint = Pubkey::new_unique();
+        cache.get(&mint);
+        cache.insert(mint, 1, curve(1), CurveSource::Rpc);
+        cache.get(&mint);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+        assert!(stats.oldest_entry_age.is_some());
+
+        cache.clear();
+        assert!(cache.get(&mint).is_none());
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries, stats.oldest_entry_age), (1, 2, 0, None));
+    }
+}
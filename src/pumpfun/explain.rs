@@ -0,0 +1,399 @@
+//! Human-readable breakdowns of the transactions the SDK would send
+//!
+//! The `explain_*` functions build the instructions of a trade exactly as the RPC trade
+//! functions do, without signing or sending them, and describe each one: its program, its
+//! accounts with their writable and signer flags, and its decoded arguments. Pump.fun
+//! instructions are decoded with [`decode_instruction`]; system, compute budget, token
+//! account and memo instructions get a one-line summary. Instructions of other programs are
+//! listed with their raw data.
+//!
+//! An [`ExplainedTransaction`] prints as an indented listing and serializes to JSON, so it can
+//! be diffed before a strategy is enabled.
+
+use std::{fmt, sync::Arc};
+
+use base64::Engine;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use solana_hash::Hash;
+use solana_sdk::{
+    compute_budget,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    native_token::lamports_to_sol,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_instruction::SystemInstruction,
+    transaction::VersionedTransaction,
+};
+
+use crate::{
+    common::{PriorityFee, SolanaRpcClient},
+    constants::accounts::{
+        ASSOCIATED_TOKEN_PROGRAM, EVENT_AUTHORITY, MEMO_PROGRAM, MPL_TOKEN_METADATA, PUMPFUN, RENT, SYSTEM_PROGRAM, TOKEN_2022_PROGRAM, TOKEN_PROGRAM,
+    },
+    instruction::decode::{decode_instruction, PumpInstruction},
+    ipfs::TokenMetadataIPFS,
+};
+
+use super::{
+    buy::{build_buy_instructions, buy_transaction_instructions},
+    common::{get_global_pda, PartialFillGuard, SellFloor, SlippageMode},
+    create::{build_create_and_buy_instructions, create_and_buy_transaction_instructions, with_metadata_policy},
+    fee_recipient::FeeRecipientUsed,
+    metadata::MetadataPolicy,
+    sell::{build_sell_instructions, sell_transaction_instructions, Proceeds},
+};
+
+/// Lamports charged per signature
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// Compute units granted per instruction when the transaction sets no limit
+const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u32 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExplainedAccount {
+    #[serde(serialize_with = "as_string")]
+    pub pubkey: Pubkey,
+    pub writable: bool,
+    pub signer: bool,
+    /// Name of a well-known program or account
+    pub label: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExplainedInstruction {
+    #[serde(serialize_with = "as_string")]
+    pub program: Pubkey,
+    pub program_name: Option<&'static str>,
+    pub accounts: Vec<ExplainedAccount>,
+    pub summary: String,
+    /// Decoded arguments, `null` for instructions of unknown programs
+    pub decoded_args: Value,
+    /// Raw instruction data, base64
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExplainedTransaction {
+    #[serde(serialize_with = "as_string")]
+    pub payer: Pubkey,
+    pub instructions: Vec<ExplainedInstruction>,
+    pub signatures: usize,
+    pub compute_unit_limit: u32,
+    /// Micro-lamports per compute unit
+    pub compute_unit_price: u64,
+    /// Signature and priority fees in lamports; tips are listed as transfers
+    pub estimated_fee: u64,
+    /// Serialized size of the signed transaction in bytes
+    pub estimated_size: usize,
+    /// Fee recipient a buy or sell would pay, and the global account snapshot it came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_recipient: Option<FeeRecipientUsed>,
+}
+
+impl ExplainedTransaction {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl fmt::Display for ExplainedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} instructions paid by {}, {} bytes, estimated fee {} lamports",
+            self.instructions.len(),
+            self.payer,
+            self.estimated_size,
+            self.estimated_fee
+        )?;
+        if let Some(used) = &self.fee_recipient {
+            writeln!(
+                f,
+                "fee recipient {} from the global account read at slot {}, cached {} ms",
+                used.fee_recipient, used.global_account_slot, used.global_account_age_ms
+            )?;
+        }
+        for (index, instruction) in self.instructions.iter().enumerate() {
+            let program = instruction.program_name.map(str::to_string).unwrap_or_else(|| instruction.program.to_string());
+            writeln!(f, "#{} {}: {}", index + 1, program, instruction.summary)?;
+            for account in &instruction.accounts {
+                let flags = format!("{}{}", if account.writable { "w" } else { "-" }, if account.signer { "s" } else { "-" });
+                match account.label {
+                    Some(label) => writeln!(f, "    [{}] {} ({})", flags, account.pubkey, label)?,
+                    None => writeln!(f, "    [{}] {}", flags, account.pubkey)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes `instructions` as one transaction paid by `payer`
+pub fn explain_instructions(payer: &Pubkey, instructions: &[Instruction]) -> Result<ExplainedTransaction, anyhow::Error> {
+    let message = v0::Message::try_compile(payer, instructions, &[], Hash::default())?;
+    let signatures = message.header.num_required_signatures as usize;
+    let transaction = VersionedTransaction { signatures: vec![Signature::default(); signatures], message: VersionedMessage::V0(message) };
+    let estimated_size = bincode::serialized_size(&transaction)? as usize;
+
+    let mut compute_unit_limit = None;
+    let mut compute_unit_price = 0;
+    for instruction in instructions.iter().filter(|instruction| instruction.program_id == compute_budget::id()) {
+        match instruction.data.split_first() {
+            Some((2, value)) => compute_unit_limit = read_u32(value),
+            Some((3, value)) => compute_unit_price = read_u64(value).unwrap_or_default(),
+            _ => {}
+        }
+    }
+    let budgeted = instructions.iter().filter(|instruction| instruction.program_id != compute_budget::id()).count() as u32;
+    let compute_unit_limit = compute_unit_limit
+        .unwrap_or(budgeted.saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNITS))
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let priority_fee = ((compute_unit_limit as u128 * compute_unit_price as u128 + 999_999) / 1_000_000) as u64;
+
+    Ok(ExplainedTransaction {
+        payer: *payer,
+        instructions: instructions.iter().map(explain_instruction).collect(),
+        signatures,
+        compute_unit_limit,
+        compute_unit_price,
+        estimated_fee: LAMPORTS_PER_SIGNATURE * signatures as u64 + priority_fee,
+        estimated_size,
+        fee_recipient: None,
+    })
+}
+
+/// Explains the transaction `buy::buy` would send
+pub async fn explain_buy(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    mint: Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    partial_fill: PartialFillGuard,
+    priority_fee: &PriorityFee,
+    ata_exists: bool,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<ExplainedTransaction, anyhow::Error> {
+    let instructions = build_buy_instructions(rpc, payer.clone(), Arc::new(mint), amount_sol, slippage_basis_points, slippage_mode, None, ata_exists, partial_fill).await?;
+    let fee_recipient = FeeRecipientUsed::from_instructions(&instructions).await;
+    let instructions = buy_transaction_instructions(priority_fee, None, instructions, extra_instructions.unwrap_or_default());
+    Ok(ExplainedTransaction { fee_recipient, ..explain_instructions(&payer.pubkey(), &instructions)? })
+}
+
+/// Explains the transaction `sell::sell` would send; `None` sells the whole balance
+pub async fn explain_sell(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    mint: Pubkey,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    sell_floor: SellFloor,
+    priority_fee: &PriorityFee,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<ExplainedTransaction, anyhow::Error> {
+    let instructions = build_sell_instructions(rpc, payer.clone(), mint, amount_token, slippage_basis_points, Proceeds::Native, sell_floor).await?;
+    let fee_recipient = FeeRecipientUsed::from_instructions(&instructions).await;
+    let instructions = sell_transaction_instructions(priority_fee, None, instructions, extra_instructions.unwrap_or_default());
+    Ok(ExplainedTransaction { fee_recipient, ..explain_instructions(&payer.pubkey(), &instructions)? })
+}
+
+/// Explains the transaction `create::create_and_buy` would send
+pub async fn explain_create_and_buy(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    mint: Arc<Keypair>,
+    ipfs: TokenMetadataIPFS,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    priority_fee: &PriorityFee,
+    metadata_policy: MetadataPolicy,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<ExplainedTransaction, anyhow::Error> {
+    let extra_instructions = with_metadata_policy(metadata_policy, &payer, &mint, extra_instructions)?;
+    let instructions = build_create_and_buy_instructions(rpc, payer.clone(), mint, ipfs, amount_sol, slippage_basis_points, *priority_fee).await?;
+    let instructions = create_and_buy_transaction_instructions(priority_fee, None, instructions, extra_instructions);
+    explain_instructions(&payer.pubkey(), &instructions)
+}
+
+fn explain_instruction(instruction: &Instruction) -> ExplainedInstruction {
+    let (summary, decoded_args) = summarize(instruction);
+    ExplainedInstruction {
+        program: instruction.program_id,
+        program_name: known_name(&instruction.program_id),
+        accounts: instruction
+            .accounts
+            .iter()
+            .map(|meta| ExplainedAccount { pubkey: meta.pubkey, writable: meta.is_writable, signer: meta.is_signer, label: known_name(&meta.pubkey) })
+            .collect(),
+        summary,
+        decoded_args,
+        data: base64::engine::general_purpose::STANDARD.encode(&instruction.data),
+    }
+}
+
+fn summarize(instruction: &Instruction) -> (String, Value) {
+    let data = instruction.data.as_slice();
+    let account = |index: usize| instruction.accounts.get(index).map(|meta| meta.pubkey.to_string()).unwrap_or_else(|| "?".to_string());
+    let program = instruction.program_id;
+
+    if program == PUMPFUN {
+        return match decode_instruction(data) {
+            Ok(decoded) => {
+                let summary = match &decoded {
+                    PumpInstruction::Create { name, symbol, uri, .. } => format!("create token {} ({}) with metadata {}", name, symbol, uri),
+                    PumpInstruction::Buy { amount, max_sol_cost } => format!("buy {} tokens for at most {} SOL", amount, lamports_to_sol(*max_sol_cost)),
+                    PumpInstruction::Sell { amount, min_sol_output } => format!("sell {} tokens for at least {} SOL", amount, lamports_to_sol(*min_sol_output)),
+                };
+                (summary, serde_json::to_value(&decoded).unwrap_or(Value::Null))
+            }
+            Err(e) => (format!("undecodable Pump.fun instruction: {}", e), Value::Null),
+        };
+    }
+    if program == compute_budget::id() {
+        return match data.split_first() {
+            Some((1, value)) => (format!("request a heap frame of {} bytes", read_u32(value).unwrap_or_default()), json!({ "request_heap_frame": read_u32(value) })),
+            Some((2, value)) => (format!("set compute unit limit to {}", read_u32(value).unwrap_or_default()), json!({ "set_compute_unit_limit": read_u32(value) })),
+            Some((3, value)) => (format!("set compute unit price to {} micro-lamports", read_u64(value).unwrap_or_default()), json!({ "set_compute_unit_price": read_u64(value) })),
+            Some((4, value)) => (format!("limit loaded account data to {} bytes", read_u32(value).unwrap_or_default()), json!({ "set_loaded_accounts_data_size_limit": read_u32(value) })),
+            _ => ("unknown compute budget instruction".to_string(), Value::Null),
+        };
+    }
+    if program == SYSTEM_PROGRAM {
+        return match bincode::deserialize::<SystemInstruction>(data) {
+            Ok(SystemInstruction::Transfer { lamports }) => {
+                (format!("transfer {} SOL to {}", lamports_to_sol(lamports), account(1)), json!({ "transfer": { "lamports": lamports } }))
+            }
+            Ok(decoded) => (format!("{:?}", decoded), serde_json::to_value(&decoded).unwrap_or(Value::Null)),
+            Err(_) => ("undecodable system instruction".to_string(), Value::Null),
+        };
+    }
+    if program == ASSOCIATED_TOKEN_PROGRAM {
+        let idempotent = data.first() == Some(&1);
+        let summary = format!(
+            "create{} token account {} for {}, mint {}",
+            if idempotent { " (if missing)" } else { "" },
+            account(1),
+            account(2),
+            account(3)
+        );
+        return (summary, json!({ "create": { "idempotent": idempotent } }));
+    }
+    if program == TOKEN_PROGRAM || program == TOKEN_2022_PROGRAM {
+        const CLOSE_ACCOUNT_TAG: u8 = 9;
+        return match data.first() {
+            Some(&CLOSE_ACCOUNT_TAG) => (format!("close token account {}, rent to {}", account(0), account(1)), json!({ "close_account": {} })),
+            Some(tag) => (format!("token instruction {}", tag), json!({ "tag": tag })),
+            None => ("empty token instruction".to_string(), Value::Null),
+        };
+    }
+    if program == MEMO_PROGRAM {
+        let memo = String::from_utf8_lossy(data);
+        return (format!("memo {:?}", memo), json!({ "memo": memo }));
+    }
+    (format!("unknown instruction, {} bytes of data", data.len()), Value::Null)
+}
+
+fn known_name(pubkey: &Pubkey) -> Option<&'static str> {
+    match *pubkey {
+        PUMPFUN => Some("Pump.fun"),
+        SYSTEM_PROGRAM => Some("System Program"),
+        TOKEN_PROGRAM => Some("Token Program"),
+        TOKEN_2022_PROGRAM => Some("Token-2022 Program"),
+        ASSOCIATED_TOKEN_PROGRAM => Some("Associated Token Program"),
+        MEMO_PROGRAM => Some("Memo Program"),
+        MPL_TOKEN_METADATA => Some("Token Metadata Program"),
+        RENT => Some("Rent Sysvar"),
+        EVENT_AUTHORITY => Some("Pump.fun event authority"),
+        _ if *pubkey == compute_budget::id() => Some("Compute Budget Program"),
+        _ if *pubkey == get_global_pda() => Some("Pump.fun global"),
+        _ => None,
+    }
+}
+
+fn read_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(..4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(..8)?.try_into().ok()?))
+}
+
+fn as_string<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{compute_budget::ComputeBudgetInstruction, instruction::AccountMeta, system_instruction};
+
+    use super::*;
+    use crate::instruction::Buy;
+
+    const PAYER: Pubkey = Pubkey::new_from_array([1; 32]);
+    const TIP: Pubkey = Pubkey::new_from_array([2; 32]);
+    const MINT: Pubkey = Pubkey::new_from_array([3; 32]);
+
+    fn instructions() -> Vec<Instruction> {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            ComputeBudgetInstruction::set_compute_unit_price(500_000),
+            system_instruction::transfer(&PAYER, &TIP, 1_000),
+            Instruction::new_with_bytes(
+                PUMPFUN,
+                &Buy { amount: 1_000_000, max_sol_cost: 10_000_000 }.data(),
+                vec![AccountMeta::new_readonly(MINT, false), AccountMeta::new(PAYER, true), AccountMeta::new_readonly(SYSTEM_PROGRAM, false)],
+            ),
+            Instruction::new_with_bytes(MEMO_PROGRAM, b"sdk-tag", vec![]),
+        ]
+    }
+
+    #[test]
+    fn test_display_snapshot() {
+        let explained = explain_instructions(&PAYER, &instructions()).unwrap();
+        let expected = "\
+5 instructions paid by 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi, 405 bytes, estimated fee 55000 lamports
+#1 Compute Budget Program: set compute unit limit to 100000
+#2 Compute Budget Program: set compute unit price to 500000 micro-lamports
+#3 System Program: transfer 0.000001 SOL to 8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR
+    [ws] 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi
+    [w-] 8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR
+#4 Pump.fun: buy 1000000 tokens for at most 0.01 SOL
+    [--] CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8
+    [ws] 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi
+    [--] 11111111111111111111111111111111 (System Program)
+#5 Memo Program: memo \"sdk-tag\"
+";
+        assert_eq!(explained.to_string(), expected);
+    }
+
+    #[test]
+    fn test_json_snapshot() {
+        let explained = explain_instructions(&PAYER, &instructions()).unwrap();
+        let json: Value = serde_json::from_str(&explained.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["payer"], PAYER.to_string());
+        assert_eq!(json["estimated_size"], 405);
+        assert_eq!(json["estimated_fee"], 55_000);
+        assert_eq!(json["compute_unit_limit"], 100_000);
+        assert_eq!(json["instructions"][2]["decoded_args"], json!({ "transfer": { "lamports": 1_000 } }));
+        assert_eq!(
+            json["instructions"][3],
+            json!({
+                "program": PUMPFUN.to_string(),
+                "program_name": "Pump.fun",
+                "accounts": [
+                    { "pubkey": MINT.to_string(), "writable": false, "signer": false, "label": null },
+                    { "pubkey": PAYER.to_string(), "writable": true, "signer": true, "label": null },
+                    { "pubkey": SYSTEM_PROGRAM.to_string(), "writable": false, "signer": false, "label": "System Program" },
+                ],
+                "summary": "buy 1000000 tokens for at most 0.01 SOL",
+                "decoded_args": { "instruction": "buy", "amount": 1_000_000, "max_sol_cost": 10_000_000 },
+                "data": base64::engine::general_purpose::STANDARD.encode(Buy { amount: 1_000_000, max_sol_cost: 10_000_000 }.data()),
+            })
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Which fee recipient a trade paid, and retrying trades built before a rotation
+//!
+//! Buys and sells pay the `fee_recipient` of the cached global account. Pump.fun rotates it,
+//! and a trade built from a stale snapshot is rejected by the program on its
+//! `fee_recipient` account. [`FeeRecipientUsed`] records the recipient a trade paid and the
+//! snapshot it came from. With `retry_fee_recipient_rotation` on the cluster or in
+//! `TradeOverrides`, a rejected trade drops the cached global account, refetches it, and is
+//! rebuilt and sent once more.
+//!
+//! A rejection is recognised from the failed transaction's logs, which only a preflight
+//! simulation returns: trades sent with `skip_preflight` or through relays fail as before.
+
+use std::{future::Future, time::Instant};
+
+use serde::{Deserialize, Serialize};
+use solana_rpc_client_api::{
+    client_error::{Error as ClientError, ErrorKind},
+    request::{RpcError, RpcResponseErrorData},
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::constants;
+
+use super::common::{get_global_pda, global_account_fetched, invalidate_global_account, PreflightError};
+
+/// Fee recipient a trade paid and the global account snapshot it was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeRecipientUsed {
+    #[serde(with = "as_string")]
+    pub fee_recipient: Pubkey,
+    /// Slot the global account was read at
+    pub global_account_slot: u64,
+    /// How long the global account had been cached when the trade was built
+    pub global_account_age_ms: u64,
+}
+
+impl FeeRecipientUsed {
+    /// The fee recipient of the first buy or sell in `instructions`, with the cached global
+    /// account it was built from
+    pub async fn from_instructions(instructions: &[Instruction]) -> Option<Self> {
+        let fee_recipient = fee_recipient_of(instructions)?;
+        let (fetched_at, slot) = global_account_fetched().await?;
+        Some(Self::new(fee_recipient, fetched_at, slot))
+    }
+
+    fn new(fee_recipient: Pubkey, fetched_at: Instant, global_account_slot: u64) -> Self {
+        Self { fee_recipient, global_account_slot, global_account_age_ms: fetched_at.elapsed().as_millis() as u64 }
+    }
+}
+
+/// The `fee_recipient` account of the first Pump.fun buy or sell in `instructions`
+fn fee_recipient_of(instructions: &[Instruction]) -> Option<Pubkey> {
+    let global = get_global_pda();
+    instructions
+        .iter()
+        .filter(|instruction| instruction.program_id == constants::accounts::PUMPFUN)
+        .find(|instruction| instruction.accounts.first().is_some_and(|account| account.pubkey == global))
+        .and_then(|instruction| instruction.accounts.get(1))
+        .map(|account| account.pubkey)
+}
+
+/// Whether the program rejected the `fee_recipient` account in `logs`
+pub fn rejects_fee_recipient(logs: &[String]) -> bool {
+    logs.iter().any(|log| log.starts_with("Program log: AnchorError caused by account: fee_recipient."))
+}
+
+/// Whether `error` is a trade failing because the fee recipient was rotated
+pub fn is_fee_recipient_rejection(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(preflight) = cause.downcast_ref::<PreflightError>() {
+            return rejects_fee_recipient(&preflight.logs);
+        }
+        match cause.downcast_ref::<ClientError>().map(ClientError::kind) {
+            Some(ErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(simulation),
+                ..
+            })) => simulation.logs.as_deref().is_some_and(rejects_fee_recipient),
+            _ => false,
+        }
+    })
+}
+
+/// Runs `attempt`, and once more after refreshing the global account if `enabled` and it
+/// failed on a rotated fee recipient
+pub async fn retry_on_rotation<T, F, Fut>(enabled: bool, mut attempt: F) -> Result<T, anyhow::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, anyhow::Error>>,
+{
+    match attempt().await {
+        Err(e) if enabled && is_fee_recipient_rejection(&e) => {
+            tracing::warn!(error = %format!("{:#}", e), "fee recipient rejected, refreshing the global account and retrying");
+            invalidate_global_account().await;
+            attempt().await
+        }
+        result => result,
+    }
+}
+
+mod as_string {
+    use std::str::FromStr;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use solana_sdk::pubkey::Pubkey;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        Pubkey::from_str(&String::deserialize(deserializer)?).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::instruction::{self, Buy};
+
+    fn rejection() -> anyhow::Error {
+        let logs = vec![
+            format!("Program {} invoke [1]", constants::accounts::PUMPFUN),
+            "Program log: Instruction: Buy".to_string(),
+            "Program log: AnchorError caused by account: fee_recipient. Error Code: ConstraintAddress. Error Number: 2012. Error Message: An address constraint was violated.".to_string(),
+        ];
+        anyhow::Error::from(PreflightError { error: "custom program error: 0x7dc".to_string(), program_error: None, logs }).context("buy")
+    }
+
+    #[test]
+    fn test_fee_recipient_is_read_from_the_trade_instruction() {
+        let fee_recipient = Pubkey::new_unique();
+        let buy = instruction::buy(&Pubkey::new_unique(), &Pubkey::new_unique(), &fee_recipient, &constants::accounts::TOKEN_PROGRAM, Buy { amount: 1, max_sol_cost: 2 });
+        let memo = Instruction::new_with_bytes(Pubkey::new_unique(), &[1], vec![]);
+        assert_eq!(fee_recipient_of(&[memo.clone(), buy]), Some(fee_recipient));
+        assert_eq!(fee_recipient_of(&[memo]), None);
+
+        let used = FeeRecipientUsed::new(fee_recipient, Instant::now(), 42);
+        let json = serde_json::to_value(used).unwrap();
+        assert_eq!(json["fee_recipient"], fee_recipient.to_string());
+        assert_eq!(serde_json::from_value::<FeeRecipientUsed>(json).unwrap(), used);
+    }
+
+    #[tokio::test]
+    async fn test_rotation_is_retried_once_when_enabled() {
+        assert!(is_fee_recipient_rejection(&rejection()));
+        assert!(!is_fee_recipient_rejection(&anyhow!("Blockhash not found")));
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let attempt = || {
+            let attempts = attempts.clone();
+            async move {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(rejection()),
+                    n => Ok(n),
+                }
+            }
+        };
+        assert_eq!(retry_on_rotation(true, attempt).await.unwrap(), 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        attempts.store(0, Ordering::SeqCst);
+        assert!(retry_on_rotation(false, attempt).await.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let always_rejected = || async { Err::<(), _>(rejection()) };
+        assert!(retry_on_rotation(true, always_rejected).await.is_err());
+    }
+}
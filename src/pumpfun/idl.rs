@@ -0,0 +1,362 @@
+//! Detection of drift between the SDK and the deployed Pump.fun program
+//!
+//! Program upgrades can change instruction discriminators and account layouts, after which
+//! the SDK fails in confusing ways. [`verify_program_compatibility`] reads the program's
+//! Anchor IDL account and compares it with what the SDK assumes. A program that doesn't
+//! publish an IDL can't be checked, which the report states instead of failing.
+//!
+//! Both IDL formats are understood: the current one lists discriminators, the legacy one
+//! derives them from the instruction names.
+//!
+//! The event authority the instruction builders pass is read from the IDL when one was
+//! checked, so a moved authority does not break trading; see [`event_authority`].
+
+use std::{io::Read, str::FromStr, sync::RwLock};
+
+use flate2::read::ZlibDecoder;
+use serde_json::Value;
+use solana_sdk::{hash::hashv, pubkey::Pubkey};
+use thiserror::Error;
+
+use crate::{
+    common::SolanaRpcClient,
+    constants,
+    instruction::{BUY_DISCRIMINATOR, CREATE_DISCRIMINATOR, SELL_DISCRIMINATOR},
+};
+
+/// Seed of the Anchor IDL account, derived from the program's base address
+pub const IDL_SEED: &str = "anchor:idl";
+
+/// Instructions the SDK sends and their discriminators
+pub const EXPECTED_INSTRUCTIONS: [(&str, [u8; 8]); 3] = [
+    ("create", CREATE_DISCRIMINATOR),
+    ("buy", BUY_DISCRIMINATOR),
+    ("sell", SELL_DISCRIMINATOR),
+];
+
+/// Accounts the SDK decodes and their sizes, without the 8 byte discriminator
+pub const EXPECTED_ACCOUNTS: [(&str, usize); 2] = [
+    // initialized, authority, fee_recipient, 4 reserves and supply fields, fee_basis_points
+    ("Global", 1 + 32 + 32 + 5 * 8),
+    // 4 reserves fields, token_total_supply, complete
+    ("BondingCurve", 5 * 8 + 1),
+];
+
+/// Anchor IDL account header: discriminator, authority and data length
+const IDL_HEADER_LEN: usize = 8 + 32 + 4;
+
+#[derive(Debug, Error)]
+pub enum IdlError {
+    #[error("IDL account is too short: {len} bytes")]
+    TooShort { len: usize },
+    #[error("failed to decompress IDL: {0}")]
+    Decompress(#[source] std::io::Error),
+    #[error("invalid IDL JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    MissingInstruction { name: &'static str },
+    InstructionDiscriminator { name: &'static str, expected: [u8; 8], actual: [u8; 8] },
+    MissingAccount { name: &'static str },
+    AccountSize { name: &'static str, expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingInstruction { name } => write!(f, "instruction `{}` is not in the IDL", name),
+            Self::InstructionDiscriminator { name, expected, actual } => {
+                write!(f, "instruction `{}` has discriminator {:?}, the SDK sends {:?}", name, actual, expected)
+            }
+            Self::MissingAccount { name } => write!(f, "account `{}` is not in the IDL", name),
+            Self::AccountSize { name, expected, actual } => {
+                write!(f, "account `{}` is {} bytes, the SDK decodes {} bytes", name, actual, expected)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    pub program_id: Pubkey,
+    /// Whether the program publishes an IDL; without one nothing was checked
+    pub idl_found: bool,
+    /// Accounts whose IDL layout has variable-size or unknown fields, so their size wasn't checked
+    pub unchecked_accounts: Vec<&'static str>,
+    pub mismatches: Vec<Mismatch>,
+    /// Event authority the IDL's instructions take, if it names one
+    pub event_authority: Option<Pubkey>,
+}
+
+impl CompatibilityReport {
+    /// No mismatches were found; also true when there was no IDL to check against
+    #[inline]
+    pub fn is_compatible(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Address of the Anchor IDL account of `program_id`
+pub fn idl_address(program_id: &Pubkey) -> Pubkey {
+    let (base, _) = Pubkey::find_program_address(&[], program_id);
+    Pubkey::create_with_seed(&base, IDL_SEED, program_id).expect("IDL seed is valid")
+}
+
+/// Decompresses the IDL JSON stored in an IDL account
+pub fn decode_idl_account(data: &[u8]) -> Result<Value, IdlError> {
+    if data.len() < IDL_HEADER_LEN {
+        return Err(IdlError::TooShort { len: data.len() });
+    }
+    let len = u32::from_le_bytes(data[IDL_HEADER_LEN - 4..IDL_HEADER_LEN].try_into().unwrap()) as usize;
+    let compressed = data.get(IDL_HEADER_LEN..IDL_HEADER_LEN + len).ok_or(IdlError::TooShort { len: data.len() })?;
+
+    let mut json = Vec::new();
+    ZlibDecoder::new(compressed).read_to_end(&mut json).map_err(IdlError::Decompress)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Compares an IDL with the instructions and accounts the SDK assumes
+pub fn check_idl(program_id: Pubkey, idl: &Value) -> CompatibilityReport {
+    let mut report = CompatibilityReport { program_id, idl_found: true, unchecked_accounts: vec![], mismatches: vec![], event_authority: event_authority_from_idl(&program_id, idl) };
+
+    for (name, expected) in EXPECTED_INSTRUCTIONS {
+        match find_named(idl, "instructions", name).map(|instruction| instruction_discriminator(instruction, name)) {
+            None => report.mismatches.push(Mismatch::MissingInstruction { name }),
+            Some(actual) if actual != expected => report.mismatches.push(Mismatch::InstructionDiscriminator { name, expected, actual }),
+            Some(_) => {}
+        }
+    }
+
+    for (name, expected) in EXPECTED_ACCOUNTS {
+        if find_named(idl, "accounts", name).is_none() {
+            report.mismatches.push(Mismatch::MissingAccount { name });
+            continue;
+        }
+        match account_size(idl, name) {
+            None => report.unchecked_accounts.push(name),
+            Some(actual) if actual != expected => report.mismatches.push(Mismatch::AccountSize { name, expected, actual }),
+            Some(_) => {}
+        }
+    }
+
+    report
+}
+
+/// Fetches the IDL of `program_id` and checks it with [`check_idl`]
+///
+/// The event authority of the Pump.fun program's IDL is used by the instruction builders
+/// from then on.
+pub async fn verify_program_compatibility(rpc: &SolanaRpcClient, program_id: &Pubkey) -> Result<CompatibilityReport, anyhow::Error> {
+    let response = rpc.get_account_with_commitment(&idl_address(program_id), rpc.commitment()).await?;
+    let Some(account) = response.value else {
+        return Ok(CompatibilityReport { program_id: *program_id, idl_found: false, unchecked_accounts: vec![], mismatches: vec![], event_authority: None });
+    };
+    let report = check_idl(*program_id, &decode_idl_account(&account.data)?);
+    if *program_id == constants::accounts::PUMPFUN {
+        if let Some(authority) = report.event_authority {
+            set_event_authority(Some(authority));
+        }
+    }
+    Ok(report)
+}
+
+static EVENT_AUTHORITY: RwLock<Option<Pubkey>> = RwLock::new(None);
+
+/// Event authority of the Pump.fun program: the one its IDL names once
+/// [`verify_program_compatibility`] read it, else [`constants::accounts::EVENT_AUTHORITY`]
+pub fn event_authority() -> Pubkey {
+    EVENT_AUTHORITY.read().unwrap().unwrap_or(constants::accounts::EVENT_AUTHORITY)
+}
+
+/// Overrides the event authority the instruction builders pass; `None` restores the constant
+pub fn set_event_authority(authority: Option<Pubkey>) {
+    if let Some(authority) = authority.filter(|authority| *authority != constants::accounts::EVENT_AUTHORITY) {
+        tracing::warn!(%authority, "the program's event authority differs from the SDK's, using the program's");
+    }
+    *EVENT_AUTHORITY.write().unwrap() = authority;
+}
+
+/// The `event_authority` account of the IDL's instructions, by its address or its seeds
+pub fn event_authority_from_idl(program_id: &Pubkey, idl: &Value) -> Option<Pubkey> {
+    let account = idl
+        .get("instructions")?
+        .as_array()?
+        .iter()
+        .filter_map(|instruction| instruction.get("accounts")?.as_array())
+        .flatten()
+        .find(|account| matches!(account.get("name").and_then(Value::as_str), Some("event_authority" | "eventAuthority")))?;
+    if let Some(address) = account.get("address").and_then(Value::as_str) {
+        return Pubkey::from_str(address).ok();
+    }
+    let seeds = account
+        .get("pda")?
+        .get("seeds")?
+        .as_array()?
+        .iter()
+        .map(|seed| {
+            if seed.get("kind").and_then(Value::as_str) != Some("const") {
+                return None;
+            }
+            seed.get("value")?.as_array()?.iter().map(|byte| byte.as_u64().map(|byte| byte as u8)).collect::<Option<Vec<u8>>>()
+        })
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+    let seeds: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+    Some(Pubkey::find_program_address(&seeds, program_id).0)
+}
+
+fn find_named<'a>(idl: &'a Value, section: &str, name: &str) -> Option<&'a Value> {
+    idl.get(section)?.as_array()?.iter().find(|item| item.get("name").and_then(Value::as_str) == Some(name))
+}
+
+/// Listed discriminator, or the Anchor one derived from the name in legacy IDLs
+fn instruction_discriminator(instruction: &Value, name: &str) -> [u8; 8] {
+    let listed = instruction
+        .get("discriminator")
+        .and_then(Value::as_array)
+        .and_then(|bytes| bytes.iter().map(|b| b.as_u64().map(|b| b as u8)).collect::<Option<Vec<u8>>>())
+        .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok());
+    listed.unwrap_or_else(|| {
+        let hash = hashv(&[b"global:", name.as_bytes()]);
+        hash.to_bytes()[..8].try_into().unwrap()
+    })
+}
+
+/// Size of a fixed-size account layout, looked up in `accounts` (legacy) or `types` (current)
+fn account_size(idl: &Value, name: &str) -> Option<usize> {
+    let layout = find_named(idl, "accounts", name)
+        .and_then(|account| account.get("type"))
+        .or_else(|| find_named(idl, "types", name).and_then(|ty| ty.get("type")))?;
+    layout.get("fields")?.as_array()?.iter().map(|field| type_size(field.get("type")?)).sum()
+}
+
+fn type_size(ty: &Value) -> Option<usize> {
+    if let Some(array) = ty.get("array").and_then(Value::as_array) {
+        let count = array.get(1)?.as_u64()? as usize;
+        return Some(type_size(array.first()?)? * count);
+    }
+    match ty.as_str()? {
+        "bool" | "u8" | "i8" => Some(1),
+        "u16" | "i16" => Some(2),
+        "u32" | "i32" | "f32" => Some(4),
+        "u64" | "i64" | "f64" => Some(8),
+        "u128" | "i128" => Some(16),
+        "publicKey" | "pubkey" => Some(32),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+    use serde_json::json;
+
+    use super::*;
+    use crate::constants::accounts::PUMPFUN;
+
+    fn global_fields() -> Value {
+        json!([
+            { "name": "initialized", "type": "bool" },
+            { "name": "authority", "type": "publicKey" },
+            { "name": "feeRecipient", "type": "publicKey" },
+            { "name": "initialVirtualTokenReserves", "type": "u64" },
+            { "name": "initialVirtualSolReserves", "type": "u64" },
+            { "name": "initialRealTokenReserves", "type": "u64" },
+            { "name": "tokenTotalSupply", "type": "u64" },
+            { "name": "feeBasisPoints", "type": "u64" }
+        ])
+    }
+
+    fn curve_fields(extra: Option<Value>) -> Value {
+        let mut fields = vec![
+            json!({ "name": "virtualTokenReserves", "type": "u64" }),
+            json!({ "name": "virtualSolReserves", "type": "u64" }),
+            json!({ "name": "realTokenReserves", "type": "u64" }),
+            json!({ "name": "realSolReserves", "type": "u64" }),
+            json!({ "name": "tokenTotalSupply", "type": "u64" }),
+            json!({ "name": "complete", "type": "bool" }),
+        ];
+        fields.extend(extra);
+        Value::Array(fields)
+    }
+
+    #[test]
+    fn test_legacy_idl_matches_sdk() {
+        let idl = json!({
+            "instructions": [{ "name": "create" }, { "name": "buy" }, { "name": "sell" }],
+            "accounts": [
+                { "name": "Global", "type": { "kind": "struct", "fields": global_fields() } },
+                { "name": "BondingCurve", "type": { "kind": "struct", "fields": curve_fields(None) } }
+            ]
+        });
+
+        let report = check_idl(PUMPFUN, &idl);
+        assert!(report.is_compatible(), "{:?}", report.mismatches);
+        assert!(report.unchecked_accounts.is_empty());
+    }
+
+    #[test]
+    fn test_upgraded_idl_reports_mismatches() {
+        let idl = json!({
+            "instructions": [
+                { "name": "create", "discriminator": CREATE_DISCRIMINATOR },
+                { "name": "buy", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }
+            ],
+            "accounts": [
+                { "name": "Global", "discriminator": [0, 0, 0, 0, 0, 0, 0, 0] },
+                { "name": "BondingCurve", "discriminator": [0, 0, 0, 0, 0, 0, 0, 0] }
+            ],
+            "types": [
+                { "name": "Global", "type": { "kind": "struct", "fields": [{ "name": "feeRecipients", "type": { "vec": "pubkey" } }] } },
+                { "name": "BondingCurve", "type": { "kind": "struct", "fields": curve_fields(Some(json!({ "name": "creator", "type": "pubkey" }))) } }
+            ]
+        });
+
+        let report = check_idl(PUMPFUN, &idl);
+        assert_eq!(
+            report.mismatches,
+            vec![
+                Mismatch::InstructionDiscriminator { name: "buy", expected: BUY_DISCRIMINATOR, actual: [1, 2, 3, 4, 5, 6, 7, 8] },
+                Mismatch::MissingInstruction { name: "sell" },
+                Mismatch::AccountSize { name: "BondingCurve", expected: 41, actual: 73 },
+            ]
+        );
+        assert_eq!(report.unchecked_accounts, vec!["Global"]);
+    }
+
+    #[test]
+    fn test_event_authority_is_read_from_the_idl() {
+        let seeds = json!([{ "kind": "const", "value": b"__event_authority" }]);
+        let current = json!({ "instructions": [{ "name": "buy", "accounts": [{ "name": "event_authority", "pda": { "seeds": seeds } }] }] });
+        assert_eq!(event_authority_from_idl(&PUMPFUN, &current), Some(constants::accounts::EVENT_AUTHORITY));
+
+        let moved = Pubkey::new_unique();
+        let legacy = json!({ "instructions": [{ "name": "buy", "accounts": [{ "name": "eventAuthority", "address": moved.to_string() }] }] });
+        assert_eq!(check_idl(PUMPFUN, &legacy).event_authority, Some(moved));
+        assert_eq!(event_authority_from_idl(&PUMPFUN, &json!({ "instructions": [] })), None);
+
+        set_event_authority(Some(moved));
+        assert_eq!(event_authority(), moved);
+        set_event_authority(None);
+        assert_eq!(event_authority(), constants::accounts::EVENT_AUTHORITY);
+    }
+
+    #[test]
+    fn test_decode_idl_account() {
+        let idl = json!({ "instructions": [] });
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(idl.to_string().as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut data = vec![0u8; 8 + 32];
+        data.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        data.extend_from_slice(&compressed);
+        data.resize(data.len() + 64, 0);
+
+        assert_eq!(decode_idl_account(&data).unwrap(), idl);
+        assert!(matches!(decode_idl_account(&data[..20]), Err(IdlError::TooShort { len: 20 })));
+    }
+}
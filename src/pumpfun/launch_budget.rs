@@ -0,0 +1,277 @@
+//! Capping what a token launch may spend
+//!
+//! A launch pays rent for the accounts the create makes, the dev buy's `max_sol_cost`, tips,
+//! transfers and the network and priority fees of each transaction. [`LaunchCost::of_transactions`]
+//! adds these up from the signed transactions, and [`LaunchBudget::check`] fails with an
+//! itemized [`BudgetExceeded`] before anything is sent when they go over
+//! [`LaunchBudget::max_total_lamports`]. The create flows return the breakdown on success too,
+//! so planned spend can be reconciled with the parsed fills.
+//!
+//! The breakdown is an upper bound: buys usually cost less than their `max_sol_cost`, and the
+//! bonding curve's rent assumes the largest layout the SDK knows. Accounts a v0 transaction
+//! loads from address lookup tables can't be resolved from the transaction alone; costs
+//! depending on them are counted at their largest and the references are counted in
+//! [`LaunchCost::unresolved_accounts`].
+
+use solana_sdk::{
+    compute_budget::{self, ComputeBudgetInstruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::VersionedTransaction,
+};
+use spl_token::state::{Account as TokenAccount, Mint};
+use thiserror::Error;
+
+use crate::{
+    accounts::BondingCurveVersion,
+    constants::accounts::{ASSOCIATED_TOKEN_PROGRAM, PUMPFUN, TOKEN_2022_PROGRAM},
+    instruction::decode::{decode_instruction, PumpInstruction},
+};
+
+use super::balance::{token_account_rent, LAMPORTS_PER_SIGNATURE};
+
+/// Bytes of a Metaplex metadata account, as the create allocates it
+pub const METADATA_ACCOUNT_LEN: usize = 679;
+
+/// Compute units a transaction gets per instruction when it sets no limit
+const DEFAULT_UNITS_PER_INSTRUCTION: u64 = 200_000;
+const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+
+/// Most lamports a launch may spend, fees included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LaunchBudget {
+    pub max_total_lamports: u64,
+}
+
+impl LaunchBudget {
+    pub fn new(max_total_lamports: u64) -> Self {
+        Self { max_total_lamports }
+    }
+
+    /// Fails unless `cost` fits in the budget
+    pub fn check(&self, cost: LaunchCost) -> Result<LaunchCost, BudgetExceeded> {
+        if cost.total() > self.max_total_lamports {
+            return Err(BudgetExceeded { max_total_lamports: self.max_total_lamports, cost });
+        }
+        Ok(cost)
+    }
+}
+
+/// Lamports the transactions of a launch may spend, by purpose
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LaunchCost {
+    /// Rent of the mint, bonding curve, its token account and the metadata of each create
+    pub create_rent: u64,
+    /// Rent of the token accounts the associated token program creates
+    pub ata_rent: u64,
+    /// Lamports funding accounts created through the system program
+    pub account_funding: u64,
+    /// Most the buy instructions may debit, protocol fee and slippage included
+    pub max_sol_cost: u64,
+    /// Transfers to the tip accounts
+    pub tips: u64,
+    /// Other system transfers
+    pub transfers: u64,
+    /// Signature fees
+    pub network_fee: u64,
+    /// Compute unit limit times price of each transaction
+    pub priority_fee: u64,
+    /// References to accounts loaded from address lookup tables, which the breakdown couldn't
+    /// resolve; not lamports
+    pub unresolved_accounts: u64,
+}
+
+impl LaunchCost {
+    /// What `transactions` may spend, counting transfers to `tip_accounts` as tips
+    ///
+    /// Token accounts created idempotently are counted as new, as they are for a new mint.
+    pub fn of_transactions(transactions: &[VersionedTransaction], tip_accounts: &[Pubkey]) -> Self {
+        let mut cost = Self::default();
+        for transaction in transactions {
+            cost.add_transaction(transaction, tip_accounts);
+        }
+        cost
+    }
+
+    fn add_transaction(&mut self, transaction: &VersionedTransaction, tip_accounts: &[Pubkey]) {
+        let message = &transaction.message;
+        let keys = message.static_account_keys();
+        let (mut unit_limit, mut unit_price, mut instructions) = (None, 0u64, 0u64);
+
+        for instruction in message.instructions() {
+            let Some(&program) = keys.get(instruction.program_id_index as usize) else {
+                self.unresolved_accounts += 1;
+                continue;
+            };
+            let account = |index: usize| instruction.accounts.get(index).and_then(|key| keys.get(*key as usize)).copied();
+            let unresolved = instruction.accounts.iter().filter(|key| keys.get(**key as usize).is_none()).count() as u64;
+            self.unresolved_accounts += unresolved;
+            if program == compute_budget::id() {
+                match borsh::from_slice(&instruction.data) {
+                    Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => unit_limit = Some(limit as u64),
+                    Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => unit_price = price,
+                    _ => {}
+                }
+                continue;
+            }
+            instructions += 1;
+
+            if program == system_program::id() {
+                match bincode::deserialize(&instruction.data) {
+                    Ok(SystemInstruction::Transfer { lamports }) => self.add_transfer(account(1), lamports, tip_accounts),
+                    Ok(SystemInstruction::TransferWithSeed { lamports, .. }) => self.add_transfer(account(2), lamports, tip_accounts),
+                    Ok(SystemInstruction::CreateAccount { lamports, .. }) | Ok(SystemInstruction::CreateAccountWithSeed { lamports, .. }) => {
+                        self.account_funding += lamports;
+                    }
+                    _ => {}
+                }
+            } else if program == PUMPFUN {
+                match decode_instruction(&instruction.data) {
+                    Ok(PumpInstruction::Create { .. }) => self.create_rent += create_rent(),
+                    Ok(PumpInstruction::Buy { max_sol_cost, .. }) => self.max_sol_cost += max_sol_cost,
+                    _ => {}
+                }
+            } else if program == ASSOCIATED_TOKEN_PROGRAM {
+                // Token-2022 accounts are the larger, so an unresolved token program counts as one
+                self.ata_rent += token_account_rent(&account(5).unwrap_or(TOKEN_2022_PROGRAM));
+            }
+        }
+
+        let unit_limit = unit_limit.unwrap_or((instructions * DEFAULT_UNITS_PER_INSTRUCTION).min(MAX_COMPUTE_UNITS));
+        self.priority_fee += ((unit_limit as u128 * unit_price as u128).div_ceil(1_000_000)) as u64;
+        self.network_fee += LAMPORTS_PER_SIGNATURE * message.header().num_required_signatures as u64;
+    }
+
+    fn add_transfer(&mut self, to: Option<Pubkey>, lamports: u64, tip_accounts: &[Pubkey]) {
+        match to.is_some_and(|to| tip_accounts.contains(&to)) {
+            true => self.tips += lamports,
+            false => self.transfers += lamports,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        [
+            self.create_rent,
+            self.ata_rent,
+            self.account_funding,
+            self.max_sol_cost,
+            self.tips,
+            self.transfers,
+            self.network_fee,
+            self.priority_fee,
+        ]
+        .into_iter()
+        .fold(0, u64::saturating_add)
+    }
+}
+
+/// Rent of the accounts a Pump.fun create allocates
+pub fn create_rent() -> u64 {
+    let rent = Rent::default();
+    let largest_curve = BondingCurveVersion::ALL.iter().map(|version| version.size()).max().unwrap_or_default();
+    [Mint::LEN, largest_curve, TokenAccount::LEN, METADATA_ACCOUNT_LEN].into_iter().map(|len| rent.minimum_balance(len)).sum()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Launch may spend up to {} lamports, over its budget of {max_total_lamports}", .cost.total())]
+pub struct BudgetExceeded {
+    pub max_total_lamports: u64,
+    pub cost: LaunchCost,
+}
+
+impl BudgetExceeded {
+    /// The budget failure somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&BudgetExceeded> {
+        error.chain().find_map(|cause| cause.downcast_ref::<BudgetExceeded>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_hash::Hash;
+    use solana_sdk::{message::VersionedMessage, signature::Keypair, signer::Signer, system_instruction};
+
+    use super::*;
+    use crate::{
+        accounts::GlobalAccount,
+        common::{PriorityFee, TipAmount},
+        ipfs::TokenMetadataIPFS,
+        pumpfun::{
+            common::{quote_initial_buy, sign_versioned_transaction},
+            create::{build_create_and_buy_instructions_offline, create_and_buy_transaction_instructions},
+        },
+    };
+
+    fn create_and_buy(payer: &Keypair, mint: &Keypair, tip_account: &Pubkey, global: &GlobalAccount) -> VersionedTransaction {
+        let metadata = serde_json::from_value(serde_json::json!({ "name": "Fixture", "symbol": "FIX", "image": "https://ipfs.io/ipfs/image" })).unwrap();
+        let ipfs = TokenMetadataIPFS { metadata, metadata_uri: "https://ipfs.io/ipfs/metadata".to_string() };
+        let priority_fee = PriorityFee { unit_price: 500_000, buy_tip_fee: TipAmount::Lamports(600_000), ..Default::default() };
+
+        let build_instructions = build_create_and_buy_instructions_offline(&payer.pubkey(), &mint.pubkey(), &ipfs, 1_000_000_000, Some(500), global).unwrap();
+        let tip = system_instruction::transfer(&payer.pubkey(), tip_account, priority_fee.buy_tip_fee.lamports());
+        let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+        let instructions = create_and_buy_transaction_instructions(&priority_fee, Some(tip), build_instructions, vec![transfer]);
+        sign_versioned_transaction(&instructions, &[payer, mint], Hash::new_unique()).unwrap()
+    }
+
+    #[test]
+    fn test_launch_cost_is_itemized() {
+        let (payer, mint, tip_account) = (Keypair::new(), Keypair::new(), Pubkey::new_unique());
+        let global = GlobalAccount::new(0, true, Pubkey::new_unique(), Pubkey::new_unique(), 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100);
+        let transaction = create_and_buy(&payer, &mint, &tip_account, &global);
+
+        let cost = LaunchCost::of_transactions(std::slice::from_ref(&transaction), &[tip_account]);
+        let quote = quote_initial_buy(&global, 1_000_000_000, 500).unwrap();
+        let priority_fee = PriorityFee::default();
+        assert_eq!(cost, LaunchCost {
+            create_rent: create_rent(),
+            ata_rent: 2_039_280,
+            account_funding: 0,
+            max_sol_cost: quote.max_sol_cost,
+            tips: 600_000,
+            transfers: 1_000,
+            network_fee: 2 * LAMPORTS_PER_SIGNATURE,
+            priority_fee: (priority_fee.create_limit() as u64 * 500_000).div_ceil(1_000_000),
+            unresolved_accounts: 0,
+        });
+
+        let twice = LaunchCost::of_transactions(&[transaction.clone(), transaction], &[]);
+        assert_eq!((twice.tips, twice.transfers), (0, 2 * 601_000));
+        assert_eq!(twice.total(), 2 * cost.total());
+    }
+
+    #[test]
+    fn test_lookup_table_accounts_are_flagged_not_indexed() {
+        let (payer, mint, tip_account) = (Keypair::new(), Keypair::new(), Pubkey::new_unique());
+        let global = GlobalAccount::new(0, true, Pubkey::new_unique(), Pubkey::new_unique(), 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100);
+        let mut transaction = create_and_buy(&payer, &mint, &tip_account, &global);
+        let resolved = LaunchCost::of_transactions(std::slice::from_ref(&transaction), &[tip_account]);
+
+        // Point the tip transfer at an account past the static keys, as a lookup table would
+        let VersionedMessage::V0(message) = &mut transaction.message else { panic!("v0 message expected") };
+        let tip_index = message.account_keys.iter().position(|key| *key == tip_account).unwrap() as u8;
+        let lookup_index = message.account_keys.len() as u8;
+        for instruction in &mut message.instructions {
+            instruction.accounts.iter_mut().filter(|key| **key == tip_index).for_each(|key| *key = lookup_index);
+        }
+
+        let cost = LaunchCost::of_transactions(std::slice::from_ref(&transaction), &[tip_account]);
+        assert_eq!(cost.unresolved_accounts, 1);
+        assert_eq!((cost.tips, cost.transfers), (0, resolved.tips + resolved.transfers));
+        assert_eq!(cost.total(), resolved.total());
+    }
+
+    #[test]
+    fn test_budget_exceeded_carries_the_breakdown() {
+        let cost = LaunchCost { create_rent: 20_000_000, max_sol_cost: 1_050_000_000, tips: 600_000, network_fee: 10_000, ..Default::default() };
+        assert_eq!(LaunchBudget::new(cost.total()).check(cost), Ok(cost));
+
+        let error = LaunchBudget::new(1_000_000_000).check(cost).unwrap_err();
+        assert_eq!(error, BudgetExceeded { max_total_lamports: 1_000_000_000, cost });
+        assert_eq!(error.to_string(), "Launch may spend up to 1070610000 lamports, over its budget of 1000000000");
+        assert!(BudgetExceeded::find(&anyhow::Error::from(error)).is_some());
+    }
+}
@@ -0,0 +1,230 @@
+//! Metadata mutability of newly created tokens
+//!
+//! Buyers screen for immutable metadata. Making it immutable takes a Metaplex
+//! `UpdateMetadataAccountV2` instruction signed by the metadata's update authority, so
+//! [`metadata_policy_instructions`] only produces it when the creator holds that authority.
+//! The Pump.fun program makes its own mint authority PDA the update authority, in which case
+//! [`MetadataPolicy::Immutable`] fails with [`MetadataPolicyError::UnsupportedByProgram`]
+//! before anything is sent.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use thiserror::Error;
+
+use crate::{common::SolanaRpcClient, constants};
+
+use super::common::{get_metadata_pda, get_mint_authority_pda};
+
+/// Metaplex `UpdateMetadataAccountV2` instruction discriminator
+const UPDATE_METADATA_ACCOUNT_V2: u8 = 15;
+
+/// Whether a token's metadata may change after creation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataPolicy {
+    /// Leave the metadata as the program creates it
+    #[default]
+    Mutable,
+    /// Make the metadata immutable in the create transaction
+    Immutable,
+}
+
+#[derive(Debug, Error)]
+pub enum MetadataPolicyError {
+    #[error("metadata update authority {update_authority} is owned by the Pump.fun program, so the metadata can't be made immutable")]
+    UnsupportedByProgram { update_authority: Pubkey },
+    #[error("metadata of {mint} is still mutable after creation")]
+    NotApplied { mint: Pubkey },
+    #[error("invalid metadata account of {mint}: {message}")]
+    InvalidMetadata { mint: Pubkey, message: String },
+}
+
+#[derive(BorshSerialize, BorshDeserialize)]
+struct Creator {
+    address: Pubkey,
+    verified: bool,
+    share: u8,
+}
+
+/// Leading fields of a Metaplex metadata account, up to `is_mutable`
+#[derive(BorshSerialize, BorshDeserialize)]
+struct MetadataHeader {
+    key: u8,
+    update_authority: Pubkey,
+    mint: Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    creators: Option<Vec<Creator>>,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+}
+
+/// Update authority and mutability of a metadata account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetadataAuthority {
+    pub update_authority: Pubkey,
+    pub is_mutable: bool,
+}
+
+impl MetadataAuthority {
+    pub fn from_account_data(mint: &Pubkey, data: &[u8]) -> Result<Self, MetadataPolicyError> {
+        let header = MetadataHeader::from_account_data(mint, data)?;
+        Ok(Self { update_authority: header.update_authority, is_mutable: header.is_mutable })
+    }
+}
+
+/// Token name, symbol and URI as stored in its metadata account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OnchainMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub update_authority: Pubkey,
+    pub is_mutable: bool,
+}
+
+impl OnchainMetadata {
+    pub fn from_account_data(mint: &Pubkey, data: &[u8]) -> Result<Self, MetadataPolicyError> {
+        let header = MetadataHeader::from_account_data(mint, data)?;
+        // Metaplex pads the strings with NUL bytes to their maximum length
+        let trim = |value: String| value.trim_end_matches('\0').to_string();
+        Ok(Self {
+            name: trim(header.name),
+            symbol: trim(header.symbol),
+            uri: trim(header.uri),
+            update_authority: header.update_authority,
+            is_mutable: header.is_mutable,
+        })
+    }
+}
+
+impl MetadataHeader {
+    fn from_account_data(mint: &Pubkey, data: &[u8]) -> Result<Self, MetadataPolicyError> {
+        let header = Self::deserialize(&mut &data[..])
+            .map_err(|e| MetadataPolicyError::InvalidMetadata { mint: *mint, message: e.to_string() })?;
+        if header.mint != *mint {
+            return Err(MetadataPolicyError::InvalidMetadata { mint: *mint, message: format!("account belongs to mint {}", header.mint) });
+        }
+        Ok(header)
+    }
+}
+
+/// Update authority the Pump.fun `create` instruction assigns to new metadata
+#[inline]
+pub fn program_update_authority() -> Pubkey {
+    get_mint_authority_pda()
+}
+
+/// Instructions applying `policy` to the metadata of `mint` created with `update_authority`
+///
+/// Fails with [`MetadataPolicyError::UnsupportedByProgram`] when the metadata must become
+/// immutable but `creator` can't sign for `update_authority`.
+pub fn metadata_policy_instructions(
+    policy: MetadataPolicy,
+    update_authority: &Pubkey,
+    creator: &Pubkey,
+    mint: &Pubkey,
+) -> Result<Vec<Instruction>, MetadataPolicyError> {
+    match policy {
+        MetadataPolicy::Mutable => Ok(vec![]),
+        MetadataPolicy::Immutable if update_authority == creator => Ok(vec![make_immutable(mint, creator)]),
+        MetadataPolicy::Immutable => Err(MetadataPolicyError::UnsupportedByProgram { update_authority: *update_authority }),
+    }
+}
+
+/// Metaplex instruction setting `is_mutable = false` on the metadata of `mint`
+pub fn make_immutable(mint: &Pubkey, update_authority: &Pubkey) -> Instruction {
+    // data: None, new_update_authority: None, primary_sale_happened: None, is_mutable: Some(false)
+    let data = vec![UPDATE_METADATA_ACCOUNT_V2, 0, 0, 0, 1, 0];
+    Instruction::new_with_bytes(
+        constants::accounts::MPL_TOKEN_METADATA,
+        &data,
+        vec![
+            AccountMeta::new(get_metadata_pda(mint), false),
+            AccountMeta::new_readonly(*update_authority, true),
+        ],
+    )
+}
+
+/// Checks that the metadata of `mint` satisfies `policy`
+pub fn check_metadata_policy(policy: MetadataPolicy, mint: &Pubkey, data: &[u8]) -> Result<MetadataAuthority, MetadataPolicyError> {
+    let authority = MetadataAuthority::from_account_data(mint, data)?;
+    if policy == MetadataPolicy::Immutable && authority.is_mutable {
+        return Err(MetadataPolicyError::NotApplied { mint: *mint });
+    }
+    Ok(authority)
+}
+
+/// Fetches the metadata of `mint` and checks that it satisfies `policy`
+pub async fn verify_metadata_policy(rpc: &SolanaRpcClient, policy: MetadataPolicy, mint: &Pubkey) -> Result<MetadataAuthority, anyhow::Error> {
+    let account = rpc.get_account(&get_metadata_pda(mint)).await?;
+    Ok(check_metadata_policy(policy, mint, &account.data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Metadata account as the Pump.fun program creates it, padded like on-chain accounts
+    fn metadata_fixture(mint: &Pubkey, update_authority: &Pubkey, is_mutable: bool) -> Vec<u8> {
+        let header = MetadataHeader {
+            key: 4,
+            update_authority: *update_authority,
+            mint: *mint,
+            name: format!("{:\0<32}", "Pump"),
+            symbol: format!("{:\0<10}", "PUMP"),
+            uri: "https://ipfs.io/ipfs/Qm".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+            primary_sale_happened: false,
+            is_mutable,
+        };
+        let mut data = borsh::to_vec(&header).unwrap();
+        data.resize(679, 0);
+        data
+    }
+
+    #[test]
+    fn test_immutable_is_unsupported_with_program_authority() {
+        let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let err = metadata_policy_instructions(MetadataPolicy::Immutable, &program_update_authority(), &creator, &mint).unwrap_err();
+        assert!(matches!(err, MetadataPolicyError::UnsupportedByProgram { update_authority } if update_authority == program_update_authority()));
+
+        let data = metadata_fixture(&mint, &program_update_authority(), true);
+        assert!(matches!(check_metadata_policy(MetadataPolicy::Immutable, &mint, &data), Err(MetadataPolicyError::NotApplied { .. })));
+        assert!(metadata_policy_instructions(MetadataPolicy::Mutable, &program_update_authority(), &creator, &mint).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_immutable_with_creator_authority() {
+        let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let instructions = metadata_policy_instructions(MetadataPolicy::Immutable, &creator, &creator, &mint).unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].program_id, constants::accounts::MPL_TOKEN_METADATA);
+        assert_eq!(instructions[0].data, vec![UPDATE_METADATA_ACCOUNT_V2, 0, 0, 0, 1, 0]);
+        assert_eq!(instructions[0].accounts[0].pubkey, get_metadata_pda(&mint));
+        assert!(instructions[0].accounts[1].is_signer);
+
+        let data = metadata_fixture(&mint, &creator, false);
+        let authority = check_metadata_policy(MetadataPolicy::Immutable, &mint, &data).unwrap();
+        assert_eq!(authority, MetadataAuthority { update_authority: creator, is_mutable: false });
+
+        assert!(matches!(
+            check_metadata_policy(MetadataPolicy::Immutable, &Pubkey::new_unique(), &data),
+            Err(MetadataPolicyError::InvalidMetadata { .. })
+        ));
+    }
+
+    #[test]
+    fn test_onchain_metadata_strips_padding() {
+        let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let metadata = OnchainMetadata::from_account_data(&mint, &metadata_fixture(&mint, &creator, true)).unwrap();
+        assert_eq!((metadata.name.as_str(), metadata.symbol.as_str()), ("Pump", "PUMP"));
+        assert_eq!(metadata.uri, "https://ipfs.io/ipfs/Qm");
+        assert_eq!(metadata.update_authority, creator);
+    }
+}
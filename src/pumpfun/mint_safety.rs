@@ -0,0 +1,168 @@
+//! Rug checks on the SPL mint of a token
+//!
+//! A Pump.fun mint is created with 6 decimals and a fixed supply of one billion tokens, its
+//! mint authority revoked or held by the program's mint authority PDA, and no freeze
+//! authority. [`check_mint_safety`] reads the mint account and lists every way it deviates
+//! from that, so a holder can't be frozen and the supply can't be inflated by anyone but the
+//! program. [`check_mints_safety`] screens many mints with batched `getMultipleAccounts`.
+
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use spl_token_2022::{extension::StateWithExtensions, state::Mint};
+use thiserror::Error;
+
+use crate::{common::SolanaRpcClient, constants::tokens};
+
+use super::common::{get_mint_authority_pda, token_program_for_account};
+
+/// Decimals of every Pump.fun mint
+pub const PUMP_TOKEN_DECIMALS: u8 = tokens::DECIMALS;
+/// Raw supply minted to the bonding curve at creation, one billion tokens
+pub const PUMP_TOKEN_SUPPLY: u64 = tokens::TOTAL_SUPPLY;
+
+/// Accounts per `getMultipleAccounts` request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// A way a mint deviates from a standard Pump.fun mint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MintAnomaly {
+    /// Someone other than the Pump.fun program can mint more tokens
+    MintAuthority(Pubkey),
+    /// Token accounts of this mint can be frozen
+    FreezeAuthority(Pubkey),
+    Decimals(u8),
+    /// More tokens exist than Pump.fun mints; burns can only lower the supply
+    SupplyInflated(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintSafetyReport {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub mint_authority: Option<Pubkey>,
+    pub freeze_authority: Option<Pubkey>,
+    pub decimals: u8,
+    /// Raw supply
+    pub supply: u64,
+    /// Whether the mint has no anomalies
+    pub is_pump_standard: bool,
+    pub anomalies: Vec<MintAnomaly>,
+}
+
+#[derive(Debug, Error)]
+pub enum MintSafetyError {
+    #[error("mint {mint} does not exist")]
+    NotFound { mint: Pubkey },
+    #[error("account {mint} is not a token mint: {reason}")]
+    NotAMint { mint: Pubkey, reason: String },
+}
+
+/// Reads `mint` and checks it against a standard Pump.fun mint
+pub async fn check_mint_safety(rpc: &SolanaRpcClient, mint: &Pubkey) -> Result<MintSafetyReport, anyhow::Error> {
+    let account = rpc.get_account_with_commitment(mint, rpc.commitment()).await?.value;
+    let account = account.ok_or(MintSafetyError::NotFound { mint: *mint })?;
+    Ok(inspect_mint(mint, &account)?)
+}
+
+/// [`check_mint_safety`] for each of `mints`, in order
+///
+/// Fails only if the accounts can't be fetched; mints that are missing or aren't token mints
+/// are reported per entry.
+pub async fn check_mints_safety(
+    rpc: &SolanaRpcClient,
+    mints: &[Pubkey],
+) -> Result<Vec<Result<MintSafetyReport, MintSafetyError>>, anyhow::Error> {
+    let mut reports = Vec::with_capacity(mints.len());
+    for chunk in mints.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc.get_multiple_accounts(chunk).await?;
+        reports.extend(chunk.iter().zip(accounts).map(|(mint, account)| match account {
+            Some(account) => inspect_mint(mint, &account),
+            None => Err(MintSafetyError::NotFound { mint: *mint }),
+        }));
+    }
+    Ok(reports)
+}
+
+/// Unpacks the mint account `account` of `mint` and lists its anomalies
+pub fn inspect_mint(mint: &Pubkey, account: &Account) -> Result<MintSafetyReport, MintSafetyError> {
+    let not_a_mint = |reason: String| MintSafetyError::NotAMint { mint: *mint, reason };
+    let token_program = token_program_for_account(account).map_err(|e| not_a_mint(e.to_string()))?;
+    let state = StateWithExtensions::<Mint>::unpack(&account.data).map_err(|e| not_a_mint(e.to_string()))?;
+
+    let mint_authority: Option<Pubkey> = state.base.mint_authority.into();
+    let freeze_authority: Option<Pubkey> = state.base.freeze_authority.into();
+    let mut anomalies = vec![];
+    if let Some(authority) = mint_authority.filter(|authority| *authority != get_mint_authority_pda()) {
+        anomalies.push(MintAnomaly::MintAuthority(authority));
+    }
+    if let Some(authority) = freeze_authority {
+        anomalies.push(MintAnomaly::FreezeAuthority(authority));
+    }
+    if state.base.decimals != PUMP_TOKEN_DECIMALS {
+        anomalies.push(MintAnomaly::Decimals(state.base.decimals));
+    }
+    if state.base.supply > PUMP_TOKEN_SUPPLY {
+        anomalies.push(MintAnomaly::SupplyInflated(state.base.supply));
+    }
+
+    Ok(MintSafetyReport {
+        mint: *mint,
+        token_program,
+        mint_authority,
+        freeze_authority,
+        decimals: state.base.decimals,
+        supply: state.base.supply,
+        is_pump_standard: anomalies.is_empty(),
+        anomalies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::program_pack::Pack;
+
+    use super::*;
+    use crate::constants;
+
+    fn mint_account(mint_authority: Option<Pubkey>, freeze_authority: Option<Pubkey>, decimals: u8, supply: u64) -> Account {
+        let state = Mint {
+            mint_authority: mint_authority.into(),
+            supply,
+            decimals,
+            is_initialized: true,
+            freeze_authority: freeze_authority.into(),
+        };
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(state, &mut data).unwrap();
+        Account { lamports: 1_461_600, data, owner: constants::accounts::TOKEN_PROGRAM, executable: false, rent_epoch: 0 }
+    }
+
+    #[test]
+    fn test_pump_mints_are_standard() {
+        let mint = Pubkey::new_unique();
+        for mint_authority in [None, Some(get_mint_authority_pda())] {
+            let report = inspect_mint(&mint, &mint_account(mint_authority, None, 6, PUMP_TOKEN_SUPPLY)).unwrap();
+            assert!(report.is_pump_standard, "{:?}", report.anomalies);
+        }
+        // Burns lower the supply
+        assert!(inspect_mint(&mint, &mint_account(None, None, 6, PUMP_TOKEN_SUPPLY / 2)).unwrap().is_pump_standard);
+    }
+
+    #[test]
+    fn test_anomalies_are_listed() {
+        let mint = Pubkey::new_unique();
+        let (dev, freezer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let report = inspect_mint(&mint, &mint_account(Some(dev), Some(freezer), 9, PUMP_TOKEN_SUPPLY + 1)).unwrap();
+
+        assert!(!report.is_pump_standard);
+        assert_eq!(report.anomalies, vec![
+            MintAnomaly::MintAuthority(dev),
+            MintAnomaly::FreezeAuthority(freezer),
+            MintAnomaly::Decimals(9),
+            MintAnomaly::SupplyInflated(PUMP_TOKEN_SUPPLY + 1),
+        ]);
+
+        let mut account = mint_account(None, None, 6, PUMP_TOKEN_SUPPLY);
+        account.owner = Pubkey::new_unique();
+        assert!(matches!(inspect_mint(&mint, &account), Err(MintSafetyError::NotAMint { .. })));
+    }
+}
@@ -1,4 +1,19 @@
 pub mod buy;
 pub mod create;
 pub mod sell;
-pub mod common;
\ No newline at end of file
+pub mod common;
+pub mod metadata;
+pub mod snapshot;
+pub mod curve_cache;
+pub mod idl;
+pub mod ata;
+pub mod creator;
+pub mod split;
+pub mod explain;
+pub mod mint_safety;
+pub mod balance;
+pub mod launch_budget;
+pub mod fee_recipient;
+pub mod recovery;
+pub mod reserve_history;
+pub mod token_age;
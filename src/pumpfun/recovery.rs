@@ -0,0 +1,214 @@
+//! Recovering a `create_and_buy` whose confirmation timed out
+//!
+//! A create that wasn't confirmed in time may still land until its blockhash expires, so
+//! retrying it blindly either fails on the existing mint or buys twice. The create flows check
+//! the chain instead: the mint and bonding curve existing means the create landed, and the
+//! payer's token balance tells whether the buy did. [`resume_create_and_buy`] sends only the
+//! missing buy, as a second transaction.
+
+use std::sync::Arc;
+
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer,
+    transaction::TransactionError,
+};
+use solana_hash::Hash;
+use thiserror::Error;
+
+use crate::{
+    common::{latency::LatencyReport, PriorityFee, RpcClients, SolanaRpcClient},
+    trade::RpcSendOptions,
+};
+
+use super::{
+    buy,
+    common::{get_ata, get_bonding_curve_pda, token_account_amount, ConfirmationTimeout, PartialFillGuard, SlippageMode},
+};
+
+/// Whether a create whose confirmation timed out landed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreateOutcome {
+    /// The mint and its bonding curve exist
+    Landed {
+        mint: Pubkey,
+        bonding_curve: Pubkey,
+        signature: Signature,
+        /// Tokens held by the payer's token account; zero if the buy didn't land
+        token_balance: u64,
+    },
+    /// The transaction failed or its blockhash expired and the mint doesn't exist; creating
+    /// again is safe
+    NotLanded { mint: Pubkey, signature: Signature },
+    /// The transaction may still land
+    Unknown { mint: Pubkey, signature: Signature, guidance: String },
+}
+
+impl CreateOutcome {
+    pub fn mint(&self) -> Pubkey {
+        match self {
+            CreateOutcome::Landed { mint, .. } | CreateOutcome::NotLanded { mint, .. } | CreateOutcome::Unknown { mint, .. } => *mint,
+        }
+    }
+
+    /// Whether the create landed with the payer holding tokens
+    pub fn buy_landed(&self) -> bool {
+        matches!(self, CreateOutcome::Landed { token_balance, .. } if *token_balance > 0)
+    }
+}
+
+impl std::fmt::Display for CreateOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CreateOutcome::Landed { mint, token_balance: 0, .. } => {
+                write!(f, "mint {} was created but the buy didn't land; call resume_create_and_buy to send it", mint)
+            }
+            CreateOutcome::Landed { mint, token_balance, .. } => write!(f, "mint {} was created and {} tokens bought", mint, token_balance),
+            CreateOutcome::NotLanded { mint, .. } => write!(f, "mint {} was not created; it is safe to create it again", mint),
+            CreateOutcome::Unknown { guidance, .. } => f.write_str(guidance),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RecoveryError {
+    /// The create's confirmation timed out and it didn't land with its buy
+    #[error("create was not confirmed: {outcome}")]
+    Unconfirmed { outcome: CreateOutcome },
+    #[error("mint {mint} or its bonding curve doesn't exist; nothing to resume")]
+    CreateNotLanded { mint: Pubkey },
+    #[error("the payer already holds {token_balance} tokens of {mint}; the buy landed")]
+    BuyAlreadyLanded { mint: Pubkey, token_balance: u64 },
+}
+
+impl RecoveryError {
+    /// The recovery error somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&RecoveryError> {
+        error.chain().find_map(|cause| cause.downcast_ref::<RecoveryError>())
+    }
+}
+
+/// Looks up on-chain whether the create of `mint` sent as `signature` with `recent_blockhash`
+/// landed, and whether `payer` holds tokens of it
+pub async fn check_create_outcome(
+    rpc: &SolanaRpcClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    signature: Signature,
+    recent_blockhash: Hash,
+) -> Result<CreateOutcome, anyhow::Error> {
+    if let Some((bonding_curve, token_balance)) = created_state(rpc, payer, mint).await? {
+        return Ok(CreateOutcome::Landed { mint: *mint, bonding_curve, signature, token_balance });
+    }
+    let status = rpc.get_signature_statuses(&[signature]).await?.value.remove(0).map(|status| status.err);
+    let blockhash_valid = rpc.is_blockhash_valid(&recent_blockhash, rpc.commitment()).await?;
+    Ok(pending_outcome(*mint, signature, status, blockhash_valid))
+}
+
+/// The bonding curve of `mint` and the tokens `payer` holds, if the mint and curve exist
+async fn created_state(rpc: &SolanaRpcClient, payer: &Pubkey, mint: &Pubkey) -> Result<Option<(Pubkey, u64)>, anyhow::Error> {
+    let Some(bonding_curve) = get_bonding_curve_pda(mint) else {
+        return Ok(None);
+    };
+    let accounts = rpc.get_multiple_accounts(&[*mint, bonding_curve]).await?;
+    let (Some(Some(mint_account)), Some(Some(_))) = (accounts.first(), accounts.get(1)) else {
+        return Ok(None);
+    };
+
+    // the mint's owner is the token program its token accounts belong to
+    let ata = get_ata(payer, mint, &mint_account.owner);
+    let ata_account = rpc.get_account_with_commitment(&ata, rpc.commitment()).await?.value;
+    let token_balance = ata_account.as_ref().map_or(0, token_account_amount);
+    Ok(Some((bonding_curve, token_balance)))
+}
+
+/// The outcome of a create whose mint doesn't exist yet, from its signature status and whether
+/// its blockhash is still valid
+fn pending_outcome(mint: Pubkey, signature: Signature, status: Option<Option<TransactionError>>, blockhash_valid: bool) -> CreateOutcome {
+    match status {
+        Some(Some(_)) => CreateOutcome::NotLanded { mint, signature },
+        Some(None) => CreateOutcome::Unknown {
+            mint,
+            signature,
+            guidance: format!("transaction {} succeeded but mint {} isn't visible yet; check again shortly", signature, mint),
+        },
+        None if !blockhash_valid => CreateOutcome::NotLanded { mint, signature },
+        None => CreateOutcome::Unknown {
+            mint,
+            signature,
+            guidance: format!(
+                "transaction {} may still land; check again once its blockhash expires (about a minute) before creating {} again",
+                signature, mint
+            ),
+        },
+    }
+}
+
+/// Turns the confirmation timeout of a `create_and_buy` into its signature if the create
+/// landed with the buy, and into a [`RecoveryError::Unconfirmed`] otherwise; other errors are
+/// returned unchanged
+pub(crate) async fn recover_create_timeout(rpc: &SolanaRpcClient, payer: &Pubkey, mint: &Pubkey, error: anyhow::Error) -> Result<Signature, anyhow::Error> {
+    let Some(timeout) = ConfirmationTimeout::find(&error).cloned() else {
+        return Err(error);
+    };
+    let outcome = match check_create_outcome(rpc, payer, mint, timeout.signature, timeout.recent_blockhash).await {
+        Ok(outcome) => outcome,
+        Err(e) => return Err(error.context(format!("checking whether the create landed failed: {:#}", e))),
+    };
+    match outcome {
+        outcome if outcome.buy_landed() => {
+            tracing::info!(%mint, signature = %timeout.signature, "create_and_buy landed after its confirmation timed out");
+            Ok(timeout.signature)
+        }
+        outcome => Err(RecoveryError::Unconfirmed { outcome }.into()),
+    }
+}
+
+/// Buys `amount_sol` of `mint` after its create landed without the buy, in a transaction of
+/// its own
+///
+/// Fails with [`RecoveryError::CreateNotLanded`] if the mint doesn't exist and with
+/// [`RecoveryError::BuyAlreadyLanded`] if the payer already holds its tokens, so a repeated
+/// resume never buys twice.
+pub async fn resume_create_and_buy(
+    rpc: impl Into<RpcClients>,
+    payer: Arc<Keypair>,
+    mint: Pubkey,
+    amount_sol: u64,
+    slippage_basis_points: Option<u64>,
+    slippage_mode: SlippageMode,
+    priority_fee: PriorityFee,
+    rpc_send_options: RpcSendOptions,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<LatencyReport, anyhow::Error> {
+    let rpc = rpc.into();
+    match created_state(&rpc.read, &payer.pubkey(), &mint).await? {
+        None => Err(RecoveryError::CreateNotLanded { mint }.into()),
+        Some((_, token_balance)) if token_balance > 0 => Err(RecoveryError::BuyAlreadyLanded { mint, token_balance }.into()),
+        Some(_) => {
+            buy::buy(rpc, payer, mint, amount_sol, slippage_basis_points, slippage_mode, PartialFillGuard::default(), priority_fee, rpc_send_options, false, false, extra_instructions, None).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::instruction::InstructionError;
+
+    use super::*;
+
+    #[test]
+    fn test_pending_outcome() {
+        let (mint, signature) = (Pubkey::new_unique(), Signature::new_unique());
+        let failed = Some(Some(TransactionError::InstructionError(2, InstructionError::Custom(6002))));
+
+        assert_eq!(pending_outcome(mint, signature, failed, true), CreateOutcome::NotLanded { mint, signature });
+        assert_eq!(pending_outcome(mint, signature, None, false), CreateOutcome::NotLanded { mint, signature });
+        assert!(matches!(pending_outcome(mint, signature, None, true), CreateOutcome::Unknown { .. }));
+        assert!(matches!(pending_outcome(mint, signature, Some(None), false), CreateOutcome::Unknown { .. }));
+
+        let landed = |token_balance| CreateOutcome::Landed { mint, bonding_curve: Pubkey::new_unique(), signature, token_balance };
+        assert!(landed(1).buy_landed());
+        assert!(!landed(0).buy_landed());
+        assert!(landed(0).to_string().contains("resume_create_and_buy"));
+    }
+}
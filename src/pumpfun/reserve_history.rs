@@ -0,0 +1,363 @@
+//! Bonding curve reserve history for charting
+//!
+//! A [`ReserveRecorder`] keeps, per tracked mint, the reserves of its bonding curve at the
+//! slots they were observed. [`run_grpc_reserve_recorder`] feeds it from a bonding curve
+//! account subscription and [`poll_reserve_history`] from periodic RPC reads, for setups
+//! without Geyser access. Both pass every stored sample to a callback, e.g. to export it.
+//!
+//! Memory is bounded: each mint keeps at most `capacity` samples in a ring buffer, samples
+//! older than `downsample_after` slots are thinned to one per `downsample_every` slots, and
+//! at most `max_mints` mints are kept, evicting the one whose newest sample is oldest.
+//!
+//! Samples go missing while a stream reconnects or a poll fails. The first sample after such a
+//! gap has [`ReserveSample::after_gap`] set, so charts don't interpolate across it.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::RangeBounds,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    accounts::BondingCurveAccount,
+    common::{heartbeat::RECONNECT_DELAY, SolanaRpcClient},
+    grpc::YellowstoneGrpc,
+};
+
+use super::common::get_bonding_curve_pda;
+
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct ReserveRecorderConfig {
+    /// Samples kept per mint
+    pub capacity: usize,
+    pub max_mints: usize,
+    /// Age in slots after which samples are thinned, about an hour by default
+    pub downsample_after: u64,
+    /// Thinned samples keep the first of every this many slots
+    pub downsample_every: u64,
+}
+
+impl Default for ReserveRecorderConfig {
+    fn default() -> Self {
+        Self { capacity: 4_096, max_mints: 1_000, downsample_after: 9_000, downsample_every: 150 }
+    }
+}
+
+/// Reserves of a bonding curve as of a slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReserveSample {
+    pub slot: u64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    /// Samples before this one may be missing, e.g. after a reconnect
+    pub after_gap: bool,
+}
+
+impl ReserveSample {
+    pub fn new(slot: u64, curve: &BondingCurveAccount) -> Self {
+        Self {
+            slot,
+            virtual_sol_reserves: curve.virtual_sol_reserves,
+            virtual_token_reserves: curve.virtual_token_reserves,
+            real_sol_reserves: curve.real_sol_reserves,
+            real_token_reserves: curve.real_token_reserves,
+            after_gap: false,
+        }
+    }
+
+    fn same_reserves(&self, other: &ReserveSample) -> bool {
+        (self.virtual_sol_reserves, self.virtual_token_reserves, self.real_sol_reserves, self.real_token_reserves)
+            == (other.virtual_sol_reserves, other.virtual_token_reserves, other.real_sol_reserves, other.real_token_reserves)
+    }
+}
+
+/// Samples of one mint: thinned older ones, then every recent one
+#[derive(Debug, Default)]
+struct MintHistory {
+    thinned: VecDeque<ReserveSample>,
+    recent: VecDeque<ReserveSample>,
+    /// The next sample follows a gap
+    gap: bool,
+}
+
+impl MintHistory {
+    fn newest(&self) -> Option<&ReserveSample> {
+        self.recent.back().or(self.thinned.back())
+    }
+
+    fn newest_slot(&self) -> u64 {
+        self.newest().map_or(0, |sample| sample.slot)
+    }
+
+    fn len(&self) -> usize {
+        self.thinned.len() + self.recent.len()
+    }
+
+    fn record(&mut self, mut sample: ReserveSample, config: &ReserveRecorderConfig) -> Option<ReserveSample> {
+        if let Some(newest) = self.newest() {
+            if sample.slot < newest.slot || (sample.same_reserves(newest) && !self.gap) {
+                return None;
+            }
+            if sample.slot == newest.slot {
+                // a later write in the same slot replaces the earlier one
+                let newest = self.recent.back_mut().or(self.thinned.back_mut())?;
+                sample.after_gap = newest.after_gap;
+                *newest = sample;
+                return Some(sample);
+            }
+        }
+
+        sample.after_gap = std::mem::take(&mut self.gap);
+        self.recent.push_back(sample);
+        self.thin(sample.slot, config);
+        while self.len() > config.capacity.max(1) {
+            if self.thinned.pop_front().is_none() {
+                self.recent.pop_front();
+            }
+        }
+        Some(sample)
+    }
+
+    /// Moves samples older than `downsample_after` slots before `newest_slot` to the thinned
+    /// part, keeping the first of each `downsample_every` slots and every one after a gap
+    fn thin(&mut self, newest_slot: u64, config: &ReserveRecorderConfig) {
+        let cutoff = newest_slot.saturating_sub(config.downsample_after);
+        let every = config.downsample_every.max(1);
+        while self.recent.front().is_some_and(|sample| sample.slot < cutoff) {
+            let Some(sample) = self.recent.pop_front() else { break };
+            let same_bucket = self.thinned.back().is_some_and(|kept| kept.slot / every == sample.slot / every);
+            if !same_bucket || sample.after_gap {
+                self.thinned.push_back(sample);
+            }
+        }
+    }
+
+    fn range(&self, slots: &impl RangeBounds<u64>) -> Vec<ReserveSample> {
+        self.thinned.iter().chain(self.recent.iter()).filter(|sample| slots.contains(&sample.slot)).copied().collect()
+    }
+}
+
+pub struct ReserveRecorder {
+    config: ReserveRecorderConfig,
+    mints: HashMap<Pubkey, MintHistory>,
+}
+
+impl ReserveRecorder {
+    pub fn new(config: ReserveRecorderConfig) -> Self {
+        Self { config, mints: HashMap::new() }
+    }
+
+    /// Starts recording `mint`, evicting the mint whose newest sample is oldest when
+    /// `max_mints` are tracked
+    pub fn track(&mut self, mint: Pubkey) {
+        if self.mints.contains_key(&mint) {
+            return;
+        }
+        if self.mints.len() >= self.config.max_mints.max(1) {
+            let least_recent = self.mints.iter().min_by_key(|(_, history)| history.newest_slot()).map(|(mint, _)| *mint);
+            if let Some(least_recent) = least_recent {
+                self.mints.remove(&least_recent);
+            }
+        }
+        self.mints.insert(mint, MintHistory::default());
+    }
+
+    /// Stops recording `mint` and drops its history
+    pub fn untrack(&mut self, mint: &Pubkey) {
+        self.mints.remove(mint);
+    }
+
+    /// Stores the reserves of `curve` as of `slot` for a tracked mint
+    ///
+    /// Returns the stored sample; `None` for untracked mints, samples older than the newest one
+    /// and unchanged reserves.
+    pub fn record(&mut self, mint: &Pubkey, slot: u64, curve: &BondingCurveAccount) -> Option<ReserveSample> {
+        let config = &self.config;
+        self.mints.get_mut(mint)?.record(ReserveSample::new(slot, curve), config)
+    }
+
+    /// Marks the next sample of `mint` as following a gap
+    pub fn mark_gap(&mut self, mint: &Pubkey) {
+        if let Some(history) = self.mints.get_mut(mint) {
+            history.gap = true;
+        }
+    }
+
+    /// Marks the next sample of every tracked mint as following a gap
+    pub fn mark_gaps(&mut self) {
+        self.mints.values_mut().for_each(|history| history.gap = true);
+    }
+
+    /// The samples of `mint` whose slot is in `slots`, oldest first
+    pub fn get_reserve_history(&self, mint: &Pubkey, slots: impl RangeBounds<u64>) -> Vec<ReserveSample> {
+        self.mints.get(mint).map(|history| history.range(&slots)).unwrap_or_default()
+    }
+
+    pub fn mints(&self) -> Vec<Pubkey> {
+        self.mints.keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.mints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mints.is_empty()
+    }
+}
+
+/// Records the bonding curves of `mints` from a gRPC account subscription until the task is
+/// aborted, passing every stored sample to `on_sample`; returns at once without mints
+///
+/// The subscription is reopened whenever it fails or closes, and the next sample of every mint
+/// is marked as following a gap.
+pub async fn run_grpc_reserve_recorder<F>(recorder: Arc<Mutex<ReserveRecorder>>, grpc: YellowstoneGrpc, mints: Vec<Pubkey>, on_sample: F)
+where
+    F: Fn(Pubkey, ReserveSample),
+{
+    if mints.is_empty() {
+        return;
+    }
+    {
+        let mut recorder = recorder.lock().unwrap();
+        mints.iter().for_each(|mint| recorder.track(*mint));
+    }
+
+    loop {
+        let result = grpc
+            .subscribe_bonding_curves_with(mints.clone(), |mint, slot, curve| {
+                let sample = recorder.lock().unwrap().record(&mint, slot, curve);
+                if let Some(sample) = sample {
+                    on_sample(mint, sample);
+                }
+            })
+            .await;
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, "bonding curve subscription failed");
+        }
+        recorder.lock().unwrap().mark_gaps();
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Records the bonding curves of the tracked mints by reading them from the RPC every
+/// `interval` until the task is aborted, passing every stored sample to `on_sample`
+///
+/// A failed read marks a gap in the mints it covered.
+pub async fn poll_reserve_history<F>(recorder: Arc<Mutex<ReserveRecorder>>, rpc: Arc<SolanaRpcClient>, interval: Duration, on_sample: F)
+where
+    F: Fn(Pubkey, ReserveSample),
+{
+    let mut tick = tokio::time::interval(interval);
+    tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tick.tick().await;
+        let mints = recorder.lock().unwrap().mints();
+        for chunk in mints.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            match read_curves(&rpc, chunk).await {
+                Ok((slot, curves)) => {
+                    for (mint, curve) in curves {
+                        let sample = recorder.lock().unwrap().record(&mint, slot, &curve);
+                        if let Some(sample) = sample {
+                            on_sample(mint, sample);
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, mints = chunk.len(), "reading bonding curves failed");
+                    let mut recorder = recorder.lock().unwrap();
+                    chunk.iter().for_each(|mint| recorder.mark_gap(mint));
+                }
+            }
+        }
+    }
+}
+
+/// The bonding curves of `mints` that exist, with the slot they were read at
+async fn read_curves(rpc: &SolanaRpcClient, mints: &[Pubkey]) -> Result<(u64, Vec<(Pubkey, BondingCurveAccount)>), anyhow::Error> {
+    let (mints, curves): (Vec<Pubkey>, Vec<Pubkey>) = mints.iter().filter_map(|mint| Some((*mint, get_bonding_curve_pda(mint)?))).unzip();
+    let response = rpc.get_multiple_accounts_with_commitment(&curves, rpc.commitment()).await?;
+    let read = mints
+        .into_iter()
+        .zip(response.value)
+        .filter_map(|(mint, account)| Some((mint, BondingCurveAccount::from_account_data(&account?.data).ok()?)))
+        .collect();
+    Ok((response.context.slot, read))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(virtual_sol_reserves: u64) -> BondingCurveAccount {
+        BondingCurveAccount::new(0, 1_000_000, virtual_sol_reserves, 800_000, virtual_sol_reserves / 2, 1_000_000, false)
+    }
+
+    #[test]
+    fn test_old_samples_are_thinned_and_capacity_is_kept() {
+        let config = ReserveRecorderConfig { capacity: 200, max_mints: 10, downsample_after: 100, downsample_every: 10 };
+        let mut recorder = ReserveRecorder::new(config);
+        let mint = Pubkey::new_unique();
+        recorder.track(mint);
+        for slot in 0..=300 {
+            recorder.record(&mint, slot, &curve(slot + 1));
+        }
+
+        let thinned = recorder.get_reserve_history(&mint, ..200);
+        assert_eq!(thinned.len(), 20);
+        assert!(thinned.iter().all(|sample| sample.slot % 10 == 0));
+        assert_eq!(recorder.get_reserve_history(&mint, 250..=260).len(), 11);
+
+        let config = ReserveRecorderConfig { capacity: 5, ..Default::default() };
+        let mut recorder = ReserveRecorder::new(config);
+        recorder.track(mint);
+        for slot in 0..10 {
+            recorder.record(&mint, slot, &curve(slot + 1));
+        }
+        let kept: Vec<u64> = recorder.get_reserve_history(&mint, ..).iter().map(|sample| sample.slot).collect();
+        assert_eq!(kept, vec![5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_gaps_are_marked_on_the_next_sample() {
+        let mut recorder = ReserveRecorder::new(ReserveRecorderConfig::default());
+        let (mint, untracked) = (Pubkey::new_unique(), Pubkey::new_unique());
+        recorder.track(mint);
+
+        assert!(recorder.record(&untracked, 10, &curve(1)).is_none());
+        assert!(!recorder.record(&mint, 10, &curve(1)).unwrap().after_gap);
+        recorder.mark_gaps();
+        assert!(recorder.record(&mint, 9, &curve(2)).is_none());
+        let after_gap = recorder.record(&mint, 12, &curve(1)).unwrap();
+        assert!(after_gap.after_gap);
+        assert!(recorder.record(&mint, 13, &curve(1)).is_none());
+        assert!(!recorder.record(&mint, 14, &curve(3)).unwrap().after_gap);
+
+        // a later write in the same slot replaces the sample
+        assert_eq!(recorder.record(&mint, 14, &curve(4)).unwrap().virtual_sol_reserves, 4);
+        assert_eq!(recorder.get_reserve_history(&mint, ..).len(), 3);
+    }
+
+    #[test]
+    fn test_least_recently_updated_mint_is_evicted() {
+        let mut recorder = ReserveRecorder::new(ReserveRecorderConfig { max_mints: 2, ..Default::default() });
+        let mints: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        recorder.track(mints[0]);
+        recorder.track(mints[1]);
+        recorder.record(&mints[0], 5, &curve(1));
+        recorder.record(&mints[1], 9, &curve(1));
+
+        recorder.track(mints[2]);
+        assert_eq!(recorder.len(), 2);
+        assert!(recorder.get_reserve_history(&mints[0], ..).is_empty());
+        assert_eq!(recorder.get_reserve_history(&mints[1], ..).len(), 1);
+    }
+}
@@ -1,22 +1,63 @@
 use anyhow::anyhow;
-use solana_client::rpc_config::RpcSimulateTransactionConfig;
 use solana_sdk::{
-    commitment_config::CommitmentConfig, compute_budget::ComputeBudgetInstruction, instruction::Instruction, message::{v0, VersionedMessage}, native_token::sol_to_lamports, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::{Transaction, VersionedTransaction}
 };
 use solana_hash::Hash;
-use spl_associated_token_account::get_associated_token_address;
-use spl_token::instruction::close_account;
-use tokio::task::JoinHandle;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_token_2022::{extension::StateWithExtensions, instruction::{close_account, sync_native}, state::Account as TokenAccount};
+use std::{str::FromStr, sync::Arc};
 
-use std::{str::FromStr, time::Instant, sync::Arc};
+use crate::{common::{latency::{LatencyReport, Stage, StageTimer}, PriorityFee, RpcClients, SolanaRpcClient}, constants::{accounts::TOKEN_PROGRAM, trade::DEFAULT_SLIPPAGE}, instruction, trade::{cancel::{self, CancellationToken}, context::ExecutionContext, journal::{memo_text, record_intent, TradeIntent, TradeSide}, RpcSendOptions}};
 
-use crate::{common::{PriorityFee, SolanaRpcClient}, constants::trade::{DEFAULT_COMPUTE_UNIT_PRICE, DEFAULT_SLIPPAGE}, instruction, jito::FeeClient};
+use crate::accounts::{BondingCurveAccount, GlobalAccount};
+use super::curve_cache::CurveRead;
+use super::fee_recipient::{retry_on_rotation, FeeRecipientUsed};
+use super::common::{append_extra_instructions, dedup_compute_budget_instructions, get_ata, get_bonding_curve_account_with, get_global_account, get_token_program, quote_sell_min_output, send_and_confirm_cancellable, sign_versioned_transaction, SellFloor};
 
-use super::common::{calculate_with_slippage_sell, get_bonding_curve_account, get_global_account};
+/// Who signs for the token account a sell draws from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SellAuthority {
+    /// The payer owns the token account, which is closed after the sell
+    #[default]
+    Owner,
+    /// The payer is an approved delegate of the token account, e.g. a custodial executor; the
+    /// account is left open for its owner
+    Delegate,
+}
+
+/// Where the SOL a sell receives ends up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Proceeds {
+    /// Lamports in the payer's account
+    #[default]
+    Native,
+    /// Wrapped SOL in the payer's wSOL account, left open for a following instruction, e.g.
+    /// an aggregator swap passed as an extra instruction
+    Wsol,
+}
+
+/// Routes the SOL of `instructions`, a sell and the closing of its token account, per `proceeds`
+///
+/// For `Wsol`, the payer's wSOL account is created if missing before the sell, and after it
+/// `min_sol_output` lamports, the least the sell pays, are moved into the account and synced.
+/// Anything the sell pays above the minimum stays native.
+pub fn route_proceeds(payer: &Pubkey, instructions: Vec<Instruction>, proceeds: Proceeds, min_sol_output: u64) -> Result<Vec<Instruction>, anyhow::Error> {
+    match proceeds {
+        Proceeds::Native => Ok(instructions),
+        Proceeds::Wsol => {
+            let wsol_account = get_ata(payer, &spl_token::native_mint::ID, &TOKEN_PROGRAM);
+            let mut routed = Vec::with_capacity(instructions.len() + 3);
+            routed.push(create_associated_token_account_idempotent(payer, payer, &spl_token::native_mint::ID, &TOKEN_PROGRAM));
+            routed.extend(instructions);
+            routed.push(system_instruction::transfer(payer, &wsol_account, min_sol_output));
+            routed.push(sync_native(&TOKEN_PROGRAM, &wsol_account)?);
+            Ok(routed)
+        }
+    }
+}
 
-async fn get_token_balance(rpc: &SolanaRpcClient, payer: &Keypair, mint: &Pubkey) -> Result<(u64, Pubkey), anyhow::Error> {
-    let ata = get_associated_token_address(&payer.pubkey(), mint);
-    let balance = rpc.get_token_account_balance(&ata).await?;
+async fn get_token_balance(rpc: &SolanaRpcClient, token_account: &Pubkey) -> Result<u64, anyhow::Error> {
+    let balance = rpc.get_token_account_balance(token_account).await?;
     let balance_u64 = balance.amount.parse::<u64>()
         .map_err(|_| anyhow!("Failed to parse token balance"))?;
     
@@ -24,133 +65,197 @@ async fn get_token_balance(rpc: &SolanaRpcClient, payer: &Keypair, mint: &Pubkey
         return Err(anyhow!("Balance is 0"));
     }
 
-    Ok((balance_u64, ata))
+    Ok(balance_u64)
+}
+
+/// Tokens `delegate` sells from `token_account`: `amount_token`, or all it may move, failing
+/// unless the account holds `mint` and its approval covers the amount
+fn check_delegation(token_account: &TokenAccount, mint: &Pubkey, delegate: &Pubkey, amount_token: Option<u64>) -> Result<u64, anyhow::Error> {
+    if token_account.mint != *mint {
+        return Err(anyhow!("Token account holds mint {}, not {}", token_account.mint, mint));
+    }
+    if token_account.delegate != Some(*delegate).into() {
+        return Err(anyhow!("{} is not the delegate of the token account", delegate));
+    }
+
+    let available = token_account.delegated_amount.min(token_account.amount);
+    let amount = amount_token.unwrap_or(available);
+    if amount > available {
+        return Err(anyhow!(
+            "Delegate may sell {} tokens (approved {}, balance {}), {} requested",
+            available, token_account.delegated_amount, token_account.amount, amount
+        ));
+    }
+    Ok(amount)
 }
 
+/// Sell tokens over RPC, quoting through the read client of `rpc` and sending through its
+/// send client
 pub async fn sell(
-    rpc: Arc<SolanaRpcClient>,
+    rpc: impl Into<RpcClients>,
     payer: Arc<Keypair>,
     mint: Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
+    sell_floor: SellFloor,
     priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
-    let instructions = build_sell_instructions(rpc.clone(), payer.clone(), mint.clone(), amount_token, slippage_basis_points).await?;
-    let transaction = build_sell_transaction(rpc.clone(), payer.clone(), priority_fee, instructions).await?;
-    rpc.send_and_confirm_transaction(&transaction).await?;
+    rpc_send_options: RpcSendOptions,
+    extra_instructions: Option<Vec<Instruction>>,
+    cancel: Option<CancellationToken>,
+) -> Result<LatencyReport, anyhow::Error> {
+    cancel::check(cancel.as_ref())?;
+    let RpcClients { read: rpc, send } = rpc.into();
+    let trade_id = record_intent(TradeIntent {
+        side: TradeSide::Sell,
+        mint,
+        amount_sol: None,
+        amount_token,
+        slippage_basis_points,
+        trade_tag: extra_instructions.as_deref().and_then(memo_text),
+        idempotency_key: None,
+    });
+    let mut timer = StageTimer::new();
+    let build_instructions = build_sell_instructions(rpc.clone(), payer.clone(), mint.clone(), amount_token, slippage_basis_points, Proceeds::Native, sell_floor);
+    let instructions = timer.time(Stage::Quote, cancel::before_submission(cancel.as_ref(), build_instructions)).await?;
+    let fee_recipient = FeeRecipientUsed::from_instructions(&instructions).await;
+    let recent_blockhash = timer.time(Stage::Blockhash, cancel::before_submission(cancel.as_ref(), rpc.get_latest_blockhash())).await?;
+    let transaction = timer.time_sync(Stage::BuildSign, || {
+        sign_sell_transaction(&payer, &priority_fee, instructions, extra_instructions.unwrap_or_default(), recent_blockhash)
+    })?;
+    send_and_confirm_cancellable(&mut timer, &send, Some(trade_id), &transaction, rpc_send_options, cancel).await?;
 
-    Ok(())
+    Ok(timer.finish().with_fee_recipient(fee_recipient))
 }
 
-/// Sell tokens by percentage
+/// Sell tokens by percentage, stopping before submission once `cancel` fires
 pub async fn sell_by_percent(
-    rpc: Arc<SolanaRpcClient>,
+    rpc: impl Into<RpcClients>,
     payer: Arc<Keypair>,
     mint: Pubkey,
     percent: u64,
     slippage_basis_points: Option<u64>,
+    sell_floor: SellFloor,
     priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
+    rpc_send_options: RpcSendOptions,
+    extra_instructions: Option<Vec<Instruction>>,
+    cancel: Option<CancellationToken>,
+) -> Result<LatencyReport, anyhow::Error> {
     if percent == 0 || percent > 100 {
         return Err(anyhow!("Percentage must be between 1 and 100"));
     }
 
-    let (balance_u64, _) = get_token_balance(rpc.as_ref(), payer.as_ref(), &mint).await?;
+    let rpc = rpc.into();
+    let balance = async {
+        let token_program = get_token_program(rpc.read.as_ref(), &mint).await?;
+        get_token_balance(rpc.read.as_ref(), &get_ata(&payer.pubkey(), &mint, &token_program)).await
+    };
+    let balance_u64 = cancel::before_submission(cancel.as_ref(), balance).await?;
     let amount = balance_u64 * percent / 100;
-    sell(rpc, payer, mint, Some(amount), slippage_basis_points, priority_fee).await
+    sell(rpc, payer, mint, Some(amount), slippage_basis_points, sell_floor, priority_fee, rpc_send_options, extra_instructions, cancel).await
 }
 
 pub async fn sell_by_percent_with_tip(
-    rpc: Arc<SolanaRpcClient>,
-    fee_clients: Vec<Arc<FeeClient>>,
-    payer: Arc<Keypair>,
+    context: &ExecutionContext,
     mint: Pubkey,
     percent: u64,
     slippage_basis_points: Option<u64>,
-    priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<LatencyReport, anyhow::Error> {
     if percent == 0 || percent > 100 {
         return Err(anyhow!("Percentage must be between 1 and 100"));
     }
 
-    let (balance_u64, _) = get_token_balance(rpc.as_ref(), payer.as_ref(), &mint).await?;
+    let token_program = get_token_program(context.rpc.as_ref(), &mint).await?;
+    let balance_u64 = get_token_balance(context.rpc.as_ref(), &get_ata(&context.payer.pubkey(), &mint, &token_program)).await?;
     let amount = balance_u64 * percent / 100;
-    sell_with_tip(rpc, fee_clients, payer, mint, Some(amount), slippage_basis_points, priority_fee).await
+    sell_with_tip(context, mint, Some(amount), slippage_basis_points, extra_instructions).await
 }
 
-/// Sell tokens using Jito
+/// Sell tokens through the fee clients of `context`, tipping each
+///
+/// Retries once on a rotated fee recipient if the context says so, see
+/// [`fee_recipient`](super::fee_recipient).
 pub async fn sell_with_tip(
-    rpc: Arc<SolanaRpcClient>,
-    fee_clients: Vec<Arc<FeeClient>>,
-    payer: Arc<Keypair>,
+    context: &ExecutionContext,
     mint: Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
-    priority_fee: PriorityFee,
-) -> Result<(), anyhow::Error> {
-    let start_time = Instant::now();
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<LatencyReport, anyhow::Error> {
+    retry_on_rotation(context.retry_fee_recipient_rotation, || {
+        sell_with_tip_once(context, mint, amount_token, slippage_basis_points, extra_instructions.clone())
+    }).await
+}
 
-    let mut transactions = vec![];
-    let instructions = build_sell_instructions(rpc.clone(), payer.clone(), mint.clone(), amount_token, slippage_basis_points).await?;
+async fn sell_with_tip_once(
+    context: &ExecutionContext,
+    mint: Pubkey,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<LatencyReport, anyhow::Error> {
+    let trade_id = context.record_intent(TradeSide::Sell, mint, None, amount_token, slippage_basis_points);
+    let mut timer = StageTimer::new();
+    let extra_instructions = context.tagged(extra_instructions);
 
-    let recent_blockhash = rpc.get_latest_blockhash().await?;
-    for fee_client in fee_clients.clone() {
-        let payer = payer.clone();
-        let priority_fee = priority_fee.clone();
-        let tip_account = fee_client.get_tip_account().await.map_err(|e| anyhow!(e.to_string()))?;
-        let tip_account = Arc::new(Pubkey::from_str(&tip_account).map_err(|e| anyhow!(e))?);
-
-        let transaction = build_sell_transaction_with_tip(tip_account, payer, priority_fee, instructions.clone(), recent_blockhash).await?;
-        transactions.push(transaction);
-    }
+    let build_instructions = build_sell_instructions(context.rpc.clone(), context.payer.clone(), mint.clone(), amount_token, slippage_basis_points, context.proceeds, context.sell_floor);
+    let instructions = timer.time(Stage::Quote, context.before_submission(build_instructions)).await?;
+    let fee_recipient = FeeRecipientUsed::from_instructions(&instructions).await;
+    let recent_blockhash = timer.time(Stage::Blockhash, context.before_submission(context.rpc.get_latest_blockhash())).await?;
 
-    let mut handles = vec![];
-    for i in 0..fee_clients.len() {
-        let fee_client = fee_clients[i].clone();
-        let transaction = transactions[i].clone();
-        let handle: JoinHandle<Result<(), anyhow::Error>> = tokio::spawn(async move {
-            fee_client.send_transaction(&transaction).await?;
-            println!("index: {}, Total Jito sell operation time: {:?}ms", i, start_time.elapsed().as_millis());
-            Ok(())
-        });
-
-        handles.push(handle);
-    }
+    let transactions = timer.time(Stage::BuildSign, context.before_submission(async {
+        let mut transactions = vec![];
+        for fee_client in context.fee_clients.clone() {
+            let payer = context.payer.clone();
+            let priority_fee = context.priority_fee.clone();
+            let tip_account = fee_client.get_tip_account().await.map_err(|e| anyhow!(e.to_string()))?;
+            let tip_account = Arc::new(Pubkey::from_str(&tip_account).map_err(|e| anyhow!(e))?);
 
-    for handle in handles {
-        match handle.await {
-            Ok(Ok(_)) => (),
-            Ok(Err(e)) => println!("Error in task: {}", e),
-            Err(e) => println!("Task join error: {}", e),
+            let transaction = build_sell_transaction_with_tip(tip_account, payer, priority_fee, instructions.clone(), extra_instructions.clone(), recent_blockhash).await?;
+            transactions.push(transaction);
         }
-    }
+        Ok::<_, anyhow::Error>(transactions)
+    })).await?;
+
+    context.submit(&mut timer, trade_id, transactions).await?;
 
-    println!("Total Jito sell operation time: {:?}ms", start_time.elapsed().as_millis());
-    Ok(())
+    Ok(timer.finish().with_fee_recipient(fee_recipient))
 }
 
 pub async fn build_sell_transaction(
     rpc: Arc<SolanaRpcClient>,
     payer: Arc<Keypair>,
     priority_fee: PriorityFee,
-    build_instructions: Vec<Instruction>
-) -> Result<Transaction, anyhow::Error> {
-    let mut instructions = vec![
-        ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price),
-        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.unit_limit),
-    ];
-
-    instructions.extend(build_instructions);
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    sign_sell_transaction(&payer, &priority_fee, build_instructions, extra_instructions, recent_blockhash)
+}
 
+#[deprecated(note = "use `build_sell_transaction`, which returns a `VersionedTransaction`")]
+#[allow(deprecated)]
+pub async fn build_sell_transaction_legacy(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    priority_fee: PriorityFee,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+) -> Result<Transaction, anyhow::Error> {
     let recent_blockhash = rpc.get_latest_blockhash().await?;
-    let transaction = Transaction::new_signed_with_payer(
-        &instructions,
-        Some(&payer.pubkey()),
-        &[payer.as_ref()],
-        recent_blockhash,
-    );
+    let instructions = sell_transaction_instructions(&priority_fee, None, build_instructions, extra_instructions);
+    super::common::sign_legacy_transaction(&instructions, &[payer.as_ref()], recent_blockhash)
+}
 
-    Ok(transaction)
+fn sign_sell_transaction(
+    payer: &Keypair,
+    priority_fee: &PriorityFee,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+    blockhash: Hash,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let instructions = sell_transaction_instructions(priority_fee, None, build_instructions, extra_instructions);
+    sign_versioned_transaction(&instructions, &[payer], blockhash)
 }
 
 pub async fn build_sell_transaction_with_tip(
@@ -158,72 +263,272 @@ pub async fn build_sell_transaction_with_tip(
     payer: Arc<Keypair>,
     priority_fee: PriorityFee,
     build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
     blockhash: Hash,
 ) -> Result<VersionedTransaction, anyhow::Error> {
+    let tip = system_instruction::transfer(
+        &payer.pubkey(),
+        &tip_account,
+        priority_fee.sell_tip_fee.lamports(),
+    );
+    let instructions = sell_transaction_instructions(&priority_fee, Some(tip), build_instructions, extra_instructions);
+    sign_versioned_transaction(&instructions, &[payer.as_ref()], blockhash)
+}
+
+/// Compute budget, optional tip, trade and extra instructions of a sell transaction
+pub(crate) fn sell_transaction_instructions(
+    priority_fee: &PriorityFee,
+    tip: Option<Instruction>,
+    build_instructions: Vec<Instruction>,
+    extra_instructions: Vec<Instruction>,
+) -> Vec<Instruction> {
     let mut instructions = vec![
         ComputeBudgetInstruction::set_compute_unit_price(priority_fee.unit_price),
-        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.unit_limit),
-        system_instruction::transfer(
-            &payer.pubkey(),
-            &tip_account,
-            sol_to_lamports(priority_fee.sell_tip_fee),
-        ),
+        ComputeBudgetInstruction::set_compute_unit_limit(priority_fee.sell_limit()),
     ];
-
-    instructions.extend(build_instructions);
-
-    let v0_message: v0::Message =
-        v0::Message::try_compile(&payer.pubkey(), &instructions, &[], blockhash)?;
-    let versioned_message: VersionedMessage = VersionedMessage::V0(v0_message);
-
-    let transaction = VersionedTransaction::try_new(versioned_message, &[&payer])?;
-
-    Ok(transaction)
+    instructions.extend(tip);
+    instructions.extend(append_extra_instructions(build_instructions, extra_instructions));
+    dedup_compute_budget_instructions(instructions)
 }
 
+/// Builds the sell of the payer's tokens of `mint`, closing the payer's token account after,
+/// with the SOL paid out per `proceeds`; fails with `SellTooSmall` if the minimum output is
+/// below `sell_floor`
 pub async fn build_sell_instructions(
     rpc: Arc<SolanaRpcClient>,
     payer: Arc<Keypair>,
     mint: Pubkey,
     amount_token: Option<u64>,
     slippage_basis_points: Option<u64>,
+    proceeds: Proceeds,
+    sell_floor: SellFloor,
+) -> Result<Vec<Instruction>, anyhow::Error> {
+    let token_program = get_token_program(rpc.as_ref(), &mint).await?;
+    let token_account = get_ata(&payer.pubkey(), &mint, &token_program);
+    build_sell_instructions_from(rpc, payer, mint, token_account, SellAuthority::Owner, amount_token, slippage_basis_points, proceeds, sell_floor).await
+}
+
+/// Builds a sell from `token_account`, signed by the payer as its owner or its delegate
+///
+/// `None` sells the whole balance, or for a delegate everything it is approved to move. A
+/// delegate sell is validated against the account's approval before building, and leaves the
+/// account open; the SOL goes to the payer either way, as lamports or wSOL per `proceeds`.
+pub async fn build_sell_instructions_from(
+    rpc: Arc<SolanaRpcClient>,
+    payer: Arc<Keypair>,
+    mint: Pubkey,
+    token_account: Pubkey,
+    authority: SellAuthority,
+    amount_token: Option<u64>,
+    slippage_basis_points: Option<u64>,
+    proceeds: Proceeds,
+    sell_floor: SellFloor,
+) -> Result<Vec<Instruction>, anyhow::Error> {
+    let amount = async {
+        match authority {
+            SellAuthority::Owner => match amount_token {
+                Some(amount) => Ok(amount),
+                None => get_token_balance(rpc.as_ref(), &token_account).await,
+            },
+            SellAuthority::Delegate => {
+                let account = rpc.get_account(&token_account).await?;
+                let state = StateWithExtensions::<TokenAccount>::unpack(&account.data)?;
+                check_delegation(&state.base, &mint, &payer.pubkey(), amount_token)
+            }
+        }
+    };
+    let (token_program, amount, global_account, bonding_curve_account) = tokio::try_join!(
+        get_token_program(rpc.as_ref(), &mint),
+        amount,
+        get_global_account(rpc.as_ref()),
+        get_bonding_curve_account_with(rpc.as_ref(), &mint, CurveRead::Fresh),
+    )?;
+
+    build_sell_instructions_offline(
+        &payer.pubkey(),
+        &mint,
+        &token_account,
+        authority,
+        amount,
+        slippage_basis_points,
+        proceeds,
+        sell_floor,
+        &global_account,
+        &bonding_curve_account,
+        &token_program,
+    )
+}
+
+/// [`build_sell_instructions_from`] from chain state the caller supplies, without any RPC call
+///
+/// `amount` is the tokens to sell out of `token_account`, for an owner usually the
+/// [`get_ata`] of the payer under `token_program`; a delegate must be approved for it.
+/// `curve` is the live bonding curve of `mint` and `token_program` owns the mint.
+#[allow(clippy::too_many_arguments)]
+pub fn build_sell_instructions_offline(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    authority: SellAuthority,
+    amount: u64,
+    slippage_basis_points: Option<u64>,
+    proceeds: Proceeds,
+    sell_floor: SellFloor,
+    global: &GlobalAccount,
+    curve: &BondingCurveAccount,
+    token_program: &Pubkey,
 ) -> Result<Vec<Instruction>, anyhow::Error> {
-    let (balance_u64, ata) = get_token_balance(rpc.as_ref(), payer.as_ref(), &mint).await?;
-    let amount = amount_token.unwrap_or(balance_u64);
-    
     if amount == 0 {
         return Err(anyhow!("Amount cannot be zero"));
     }
-    
-    let global_account = get_global_account(rpc.as_ref()).await?;
-    let bonding_curve_account = get_bonding_curve_account(rpc.as_ref(), &mint).await?;
-    let min_sol_output = bonding_curve_account
-        .get_sell_price(amount, global_account.fee_basis_points)
-        .map_err(|e| anyhow!(e))?;
-    let min_sol_output_with_slippage = calculate_with_slippage_sell(
-        min_sol_output,
+
+    let min_sol_output_with_slippage = quote_sell_min_output(
+        curve,
+        amount,
+        global.fee_basis_points,
         slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-    );
+        sell_floor,
+    )?;
 
-    let instructions = vec![
-        instruction::sell(
-            payer.as_ref(),
-            &mint,
-            &global_account.fee_recipient,
-            instruction::Sell {
-                _amount: amount,
-                _min_sol_output: min_sol_output_with_slippage,
-            },
+    let mut instructions = vec![
+        instruction::sell_from(
+            payer,
+            mint,
+            token_account,
+            &global.fee_recipient,
+            token_program,
+            instruction::Sell::new(amount, min_sol_output_with_slippage)?,
         ),
-    
-        close_account(
-            &spl_token::ID,
-            &ata,
-            &payer.pubkey(),
-            &payer.pubkey(),
-            &[&payer.pubkey()],
-        )?
     ];
+    if authority == SellAuthority::Owner {
+        instructions.push(close_account(
+            token_program,
+            token_account,
+            payer,
+            payer,
+            &[payer],
+        )?);
+    }
+
+    route_proceeds(payer, instructions, proceeds, min_sol_output_with_slippage)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{system_instruction::SystemInstruction, system_program};
+
+    use super::*;
+    use crate::{common::TipAmount, pumpfun::common::{append_extra_instructions, SellTooSmall}};
+
+    #[test]
+    fn test_offline_sell_uses_only_the_supplied_state() {
+        let (payer, mint, fee_recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let global = GlobalAccount::new(0, true, Pubkey::new_unique(), fee_recipient, 1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 1_000_000_000_000_000, 100);
+        let curve = BondingCurveAccount::new(0, 900_000_000_000_000, 35_000_000_000, 620_000_000_000_000, 5_000_000_000, 1_000_000_000_000_000, false);
+        let token_program = crate::constants::accounts::TOKEN_2022_PROGRAM;
+        let token_account = get_ata(&payer, &mint, &token_program);
+        let build = |token_account: &Pubkey, authority, amount, proceeds, sell_floor| {
+            build_sell_instructions_offline(&payer, &mint, token_account, authority, amount, Some(500), proceeds, sell_floor, &global, &curve, &token_program)
+        };
+
+        let amount = 10_000_000_000_000;
+        let min_sol_output = quote_sell_min_output(&curve, amount, 100, 500, SellFloor::default()).unwrap();
+        let sell = instruction::sell_from(&payer, &mint, &token_account, &fee_recipient, &token_program, instruction::Sell { amount: amount, min_sol_output: min_sol_output });
+        let close = close_account(&token_program, &token_account, &payer, &payer, &[&payer]).unwrap();
+        assert_eq!(build(&token_account, SellAuthority::Owner, amount, Proceeds::Native, SellFloor::default()).unwrap(), vec![sell.clone(), close.clone()]);
+        assert_eq!(
+            build(&token_account, SellAuthority::Owner, amount, Proceeds::Wsol, SellFloor::default()).unwrap(),
+            route_proceeds(&payer, vec![sell, close], Proceeds::Wsol, min_sol_output).unwrap()
+        );
+
+        // A delegate sells from someone else's account and leaves it open
+        let owned = Pubkey::new_unique();
+        let delegated = build(&owned, SellAuthority::Delegate, amount, Proceeds::Native, SellFloor::default()).unwrap();
+        assert_eq!(delegated.len(), 1);
+        assert_eq!(delegated[0].accounts[5].pubkey, owned);
+
+        let dust = build(&token_account, SellAuthority::Owner, 1, Proceeds::Native, SellFloor::default()).unwrap_err();
+        assert!(SellTooSmall::find(&dust).is_some());
+        assert!(build(&token_account, SellAuthority::Owner, 1, Proceeds::Native, SellFloor { allow_below: true, ..Default::default() }).is_ok());
+        assert_eq!(build(&token_account, SellAuthority::Owner, 0, Proceeds::Native, SellFloor::default()).unwrap_err().to_string(), "Amount cannot be zero");
+    }
+
+    #[tokio::test]
+    async fn test_tip_is_the_configured_lamports() {
+        let payer = Arc::new(Keypair::new());
+        let priority_fee = PriorityFee { sell_tip_fee: TipAmount::Lamports(1_000_000), ..Default::default() };
+        let transaction = build_sell_transaction_with_tip(Arc::new(Pubkey::new_unique()), payer, priority_fee, vec![], vec![], Hash::default()).await.unwrap();
 
-    Ok(instructions)
+        let keys = transaction.message.static_account_keys();
+        let tip = transaction.message.instructions().iter()
+            .find(|ix| keys[ix.program_id_index as usize] == system_program::id())
+            .unwrap();
+        assert_eq!(bincode::deserialize::<SystemInstruction>(&tip.data).unwrap(), SystemInstruction::Transfer { lamports: 1_000_000 });
+    }
+
+    #[test]
+    fn test_wsol_proceeds_wrap_the_minimum_output() {
+        let payer = Pubkey::new_unique();
+        let sell = Instruction::new_with_bytes(crate::constants::accounts::PUMPFUN, &[51], vec![]);
+        let close = close_account(&TOKEN_PROGRAM, &Pubkey::new_unique(), &payer, &payer, &[&payer]).unwrap();
+        let swap = Instruction::new_with_bytes(Pubkey::new_unique(), &[7], vec![]);
+
+        let native = route_proceeds(&payer, vec![sell.clone(), close.clone()], Proceeds::Native, 950_000_000).unwrap();
+        assert_eq!(native, vec![sell.clone(), close.clone()]);
+
+        let routed = route_proceeds(&payer, vec![sell.clone(), close.clone()], Proceeds::Wsol, 950_000_000).unwrap();
+        let wsol_account = get_ata(&payer, &spl_token::native_mint::ID, &TOKEN_PROGRAM);
+        assert_eq!(routed.len(), 5);
+        assert_eq!(routed[0], create_associated_token_account_idempotent(&payer, &payer, &spl_token::native_mint::ID, &TOKEN_PROGRAM));
+        assert_eq!(routed[0].data, vec![1], "idempotent create");
+        assert_eq!(routed[1], sell);
+        assert_eq!(routed[2], close);
+        assert_eq!(routed[3].program_id, system_program::id());
+        assert_eq!(routed[3].accounts[1].pubkey, wsol_account);
+        assert_eq!(bincode::deserialize::<SystemInstruction>(&routed[3].data).unwrap(), SystemInstruction::Transfer { lamports: 950_000_000 });
+        assert_eq!(routed[4].program_id, TOKEN_PROGRAM);
+        assert_eq!(routed[4].accounts[0].pubkey, wsol_account);
+        assert_eq!(routed[4].data, vec![17], "sync native");
+
+        // The wSOL account stays open, so a following instruction can spend it
+        let with_swap = append_extra_instructions(routed.clone(), vec![swap.clone()]);
+        assert_eq!(with_swap[..5], routed[..]);
+        assert_eq!(with_swap[5], swap);
+    }
+
+    #[test]
+    fn test_delegate_sells_within_its_approval() {
+        let (mint, delegate) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let account = TokenAccount {
+            mint,
+            owner: Pubkey::new_unique(),
+            amount: 1_000,
+            delegate: Some(delegate).into(),
+            delegated_amount: 400,
+            ..Default::default()
+        };
+
+        assert_eq!(check_delegation(&account, &mint, &delegate, None).unwrap(), 400);
+        assert_eq!(check_delegation(&account, &mint, &delegate, Some(250)).unwrap(), 250);
+        assert!(check_delegation(&account, &mint, &delegate, Some(401)).is_err());
+        assert!(check_delegation(&account, &mint, &Pubkey::new_unique(), None).is_err());
+        assert!(check_delegation(&account, &Pubkey::new_unique(), &delegate, None).is_err());
+
+        // An approval larger than the balance is capped by the balance
+        let drained = TokenAccount { amount: 100, ..account };
+        assert_eq!(check_delegation(&drained, &mint, &delegate, None).unwrap(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_sell_by_percent_stops_when_cancelled() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let rpc = Arc::new(SolanaRpcClient::new_mock("succeeds".to_string()));
+        let payer = Arc::new(Keypair::new());
+
+        let error = sell_by_percent(rpc, payer, Pubkey::new_unique(), 50, None, SellFloor::default(), PriorityFee::default(), RpcSendOptions::default(), None, Some(token))
+            .await
+            .unwrap_err();
+        assert_eq!(cancel::Cancelled::find(&error), Some(&cancel::Cancelled::BeforeSubmission));
+    }
 }
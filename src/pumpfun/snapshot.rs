@@ -0,0 +1,355 @@
+//! Point-in-time view of a token
+//!
+//! [`get_token_snapshot`] loads the bonding curve, metadata, holders and recent trades of a
+//! mint concurrently. Every on-chain component carries the slot it was read at, so consumers
+//! can tell how far apart the reads were. A component that fails to load is left `None` and
+//! the failure is recorded in [`TokenSnapshot::errors`] instead of failing the whole snapshot.
+//!
+//! Tokens whose curve completed report the final curve state; their AMM pool is not read.
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use futures::future::join_all;
+use solana_client::{rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+
+use crate::{
+    accounts::BondingCurveAccount,
+    common::{
+        logs_data::{DexInstruction, TradeInfo},
+        logs_filters::LogFilter,
+        SolanaRpcClient,
+    },
+    constants,
+    ipfs::{self, TokenMetadata},
+};
+
+use super::{
+    common::{get_ata, get_bonding_curve_pda, get_metadata_pda},
+    metadata::OnchainMetadata,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SnapshotOptions {
+    /// Download the metadata JSON the on-chain metadata points to
+    pub ipfs_metadata: bool,
+    /// Load the largest token accounts
+    pub holders: bool,
+    /// Number of recent bonding curve transactions to scan for trades; 0 skips them
+    pub recent_trades: usize,
+}
+
+/// A component of a snapshot and the slot it was read at
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtSlot<T> {
+    pub slot: u64,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotComponent {
+    BondingCurve,
+    Metadata,
+    IpfsMetadata,
+    Holders,
+    RecentTrades,
+}
+
+/// Why a component of a snapshot is missing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotError {
+    pub component: SnapshotComponent,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenHolder {
+    pub token_account: Pubkey,
+    pub amount: u64,
+    /// Share of the total supply, in basis points
+    pub share_bps: u64,
+    /// Whether this is the bonding curve's token account, holding the unsold supply
+    pub is_bonding_curve: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotTrade {
+    pub signature: Signature,
+    pub trade: TradeInfo,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenSnapshot {
+    pub mint: Pubkey,
+    pub bonding_curve: Option<AtSlot<BondingCurveAccount>>,
+    pub metadata: Option<AtSlot<OnchainMetadata>>,
+    /// Off-chain metadata, which has no slot
+    pub ipfs_metadata: Option<TokenMetadata>,
+    /// Largest token accounts, largest first
+    pub holders: Option<AtSlot<Vec<TokenHolder>>>,
+    /// Trades newest first, at the slot of the newest scanned transaction
+    pub recent_trades: Option<AtSlot<Vec<SnapshotTrade>>>,
+    pub errors: Vec<SnapshotError>,
+}
+
+impl TokenSnapshot {
+    fn new(mint: Pubkey) -> Self {
+        Self {
+            mint,
+            bonding_curve: None,
+            metadata: None,
+            ipfs_metadata: None,
+            holders: None,
+            recent_trades: None,
+            errors: vec![],
+        }
+    }
+
+    /// Slots between the oldest and newest account read, or `None` with fewer than two reads
+    ///
+    /// Recent trades are history rather than state, so they are not counted.
+    pub fn slot_spread(&self) -> Option<u64> {
+        let slots = [
+            self.bonding_curve.as_ref().map(|c| c.slot),
+            self.metadata.as_ref().map(|c| c.slot),
+            self.holders.as_ref().map(|c| c.slot),
+        ];
+        let slots: Vec<u64> = slots.into_iter().flatten().collect();
+        if slots.len() < 2 {
+            return None;
+        }
+        Some(slots.iter().max()? - slots.iter().min()?)
+    }
+
+    pub fn error(&self, component: SnapshotComponent) -> Option<&SnapshotError> {
+        self.errors.iter().find(|error| error.component == component)
+    }
+
+    fn note<T>(&mut self, component: SnapshotComponent, result: Result<T, anyhow::Error>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.errors.push(SnapshotError { component, message: format!("{:#}", e) });
+                None
+            }
+        }
+    }
+}
+
+/// Loads a snapshot of `mint` at the client's commitment
+pub async fn get_token_snapshot(rpc: &SolanaRpcClient, mint: &Pubkey, options: SnapshotOptions) -> TokenSnapshot {
+    let commitment = rpc.commitment();
+    let (bonding_curve, (metadata, ipfs_metadata), holders, recent_trades) = tokio::join!(
+        fetch_bonding_curve(rpc, mint, commitment),
+        fetch_metadata(rpc, mint, commitment, options.ipfs_metadata),
+        async {
+            match options.holders {
+                true => Some(fetch_holders(rpc, mint, commitment).await),
+                false => None,
+            }
+        },
+        async {
+            match options.recent_trades {
+                0 => None,
+                limit => Some(fetch_recent_trades(rpc, mint, commitment, limit).await),
+            }
+        },
+    );
+
+    let mut snapshot = TokenSnapshot::new(*mint);
+    snapshot.bonding_curve = snapshot.note(SnapshotComponent::BondingCurve, bonding_curve);
+    snapshot.metadata = snapshot.note(SnapshotComponent::Metadata, metadata);
+    if let Some(ipfs_metadata) = ipfs_metadata {
+        snapshot.ipfs_metadata = snapshot.note(SnapshotComponent::IpfsMetadata, ipfs_metadata);
+    }
+    if let Some(holders) = holders {
+        snapshot.holders = snapshot.note(SnapshotComponent::Holders, holders);
+    }
+    if let Some(recent_trades) = recent_trades {
+        snapshot.recent_trades = snapshot.note(SnapshotComponent::RecentTrades, recent_trades);
+    }
+    snapshot
+}
+
+async fn fetch_bonding_curve(rpc: &SolanaRpcClient, mint: &Pubkey, commitment: CommitmentConfig) -> Result<AtSlot<BondingCurveAccount>, anyhow::Error> {
+    let bonding_curve_pda = get_bonding_curve_pda(mint).ok_or(anyhow!("Bonding curve not found"))?;
+    let response = rpc.get_account_with_commitment(&bonding_curve_pda, commitment).await?;
+    let account = response.value.ok_or(anyhow!("Bonding curve not found"))?;
+    let bonding_curve = BondingCurveAccount::from_account_data(&account.data)?;
+    Ok(AtSlot { slot: response.context.slot, value: bonding_curve })
+}
+
+/// Loads the on-chain metadata and, if requested, the off-chain metadata it points to
+async fn fetch_metadata(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+    commitment: CommitmentConfig,
+    ipfs_metadata: bool,
+) -> (Result<AtSlot<OnchainMetadata>, anyhow::Error>, Option<Result<TokenMetadata, anyhow::Error>>) {
+    let metadata = async {
+        let response = rpc.get_account_with_commitment(&get_metadata_pda(mint), commitment).await?;
+        let account = response.value.ok_or(anyhow!("Metadata account not found"))?;
+        let metadata = OnchainMetadata::from_account_data(mint, &account.data)?;
+        Ok::<_, anyhow::Error>(AtSlot { slot: response.context.slot, value: metadata })
+    }
+    .await;
+
+    let ipfs_metadata = match (&metadata, ipfs_metadata) {
+        (Ok(metadata), true) => Some(ipfs::fetch_token_metadata(&metadata.value.uri).await),
+        (Err(_), true) => Some(Err(anyhow!("On-chain metadata unavailable"))),
+        (_, false) => None,
+    };
+    (metadata, ipfs_metadata)
+}
+
+async fn fetch_holders(rpc: &SolanaRpcClient, mint: &Pubkey, commitment: CommitmentConfig) -> Result<AtSlot<Vec<TokenHolder>>, anyhow::Error> {
+    let (largest, supply) = tokio::join!(
+        rpc.get_token_largest_accounts_with_commitment(mint, commitment),
+        rpc.get_token_supply_with_commitment(mint, commitment),
+    );
+    let (largest, supply) = (largest?, supply?);
+    let supply = supply.value.amount.parse::<u64>()?;
+
+    let balances = largest
+        .value
+        .iter()
+        .map(|balance| Ok((Pubkey::from_str(&balance.address)?, balance.amount.amount.parse::<u64>()?)))
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    let bonding_curve_pda = get_bonding_curve_pda(mint).ok_or(anyhow!("Bonding curve not found"))?;
+    let bonding_curve_accounts = [
+        get_ata(&bonding_curve_pda, mint, &constants::accounts::TOKEN_PROGRAM),
+        get_ata(&bonding_curve_pda, mint, &constants::accounts::TOKEN_2022_PROGRAM),
+    ];
+
+    Ok(AtSlot { slot: largest.context.slot, value: token_holders(&balances, supply, &bonding_curve_accounts) })
+}
+
+fn token_holders(balances: &[(Pubkey, u64)], supply: u64, bonding_curve_accounts: &[Pubkey]) -> Vec<TokenHolder> {
+    balances
+        .iter()
+        .map(|&(token_account, amount)| TokenHolder {
+            token_account,
+            amount,
+            share_bps: match supply {
+                0 => 0,
+                supply => (amount as u128 * 10_000 / supply as u128) as u64,
+            },
+            is_bonding_curve: bonding_curve_accounts.contains(&token_account),
+        })
+        .collect()
+}
+
+async fn fetch_recent_trades(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+    commitment: CommitmentConfig,
+    limit: usize,
+) -> Result<AtSlot<Vec<SnapshotTrade>>, anyhow::Error> {
+    let bonding_curve_pda = get_bonding_curve_pda(mint).ok_or(anyhow!("Bonding curve not found"))?;
+    // Transaction history isn't available at processed commitment
+    let commitment = if commitment.is_at_least_confirmed() { commitment } else { CommitmentConfig::confirmed() };
+
+    let config = GetConfirmedSignaturesForAddress2Config { before: None, until: None, limit: Some(limit), commitment: Some(commitment) };
+    let statuses = rpc.get_signatures_for_address_with_config(&bonding_curve_pda, config).await?;
+    let slot = statuses.first().map(|status| status.slot).unwrap_or_default();
+    let signatures: Vec<Signature> = statuses
+        .iter()
+        .filter(|status| status.err.is_none())
+        .filter_map(|status| Signature::from_str(&status.signature).ok())
+        .collect();
+
+    let transaction_config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    };
+    let transactions = join_all(signatures.iter().map(|signature| rpc.get_transaction_with_config(signature, transaction_config.clone()))).await;
+
+    let mut trades = vec![];
+    let mut first_error = None;
+    for (signature, transaction) in signatures.iter().zip(transactions) {
+        let transaction = match transaction {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        let logs = match transaction.transaction.meta.map(|meta| meta.log_messages) {
+            Some(OptionSerializer::Some(logs)) => logs,
+            _ => continue,
+        };
+        trades.extend(trades_from_logs(*signature, transaction.slot, &logs));
+    }
+
+    // Only fail when no transaction could be loaded at all
+    match first_error {
+        Some(e) if trades.is_empty() => Err(e.into()),
+        _ => Ok(AtSlot { slot, value: trades }),
+    }
+}
+
+fn trades_from_logs(signature: Signature, slot: u64, logs: &[String]) -> Vec<SnapshotTrade> {
+    let Ok(instructions) = LogFilter::parse_instruction(logs, None) else {
+        return vec![];
+    };
+    instructions
+        .into_iter()
+        .filter_map(|instruction| match instruction {
+            DexInstruction::UserTrade(trade) | DexInstruction::BotTrade(trade) => Some(trade),
+            _ => None,
+        })
+        .map(|mut trade| {
+            trade.slot = slot;
+            SnapshotTrade { signature, trade }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_holders_shares() {
+        let (curve, holder) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let supply = 1_000_000_000_000_000;
+        let holders = token_holders(&[(curve, 800_000_000_000_000), (holder, 35_000_000_000_000)], supply, &[curve]);
+
+        assert_eq!(holders[0], TokenHolder { token_account: curve, amount: 800_000_000_000_000, share_bps: 8_000, is_bonding_curve: true });
+        assert_eq!(holders[1].share_bps, 350);
+        assert!(!holders[1].is_bonding_curve);
+        assert_eq!(token_holders(&[(holder, 1)], 0, &[])[0].share_bps, 0);
+    }
+
+    #[test]
+    fn test_partial_failures_are_noted() {
+        let mut snapshot = TokenSnapshot::new(Pubkey::new_unique());
+        snapshot.bonding_curve = snapshot.note(
+            SnapshotComponent::BondingCurve,
+            Ok(AtSlot { slot: 100, value: BondingCurveAccount::new(0, 0, 0, 0, 0, 0, false) }),
+        );
+        snapshot.holders = snapshot.note(SnapshotComponent::Holders, Err(anyhow!("rate limited")));
+        assert_eq!(snapshot.slot_spread(), None);
+
+        snapshot.metadata = snapshot.note(
+            SnapshotComponent::Metadata,
+            Ok(AtSlot {
+                slot: 103,
+                value: OnchainMetadata {
+                    name: "Pump".to_string(),
+                    symbol: "PUMP".to_string(),
+                    uri: String::new(),
+                    update_authority: Pubkey::new_unique(),
+                    is_mutable: true,
+                },
+            }),
+        );
+        assert_eq!(snapshot.slot_spread(), Some(3));
+        assert!(snapshot.holders.is_none());
+        assert_eq!(snapshot.error(SnapshotComponent::Holders).unwrap().message, "rate limited");
+        assert!(snapshot.error(SnapshotComponent::BondingCurve).is_none());
+    }
+}
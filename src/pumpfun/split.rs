@@ -0,0 +1,455 @@
+//! Buys split into tranches that each stay under a price-impact cap
+//!
+//! [`plan_split`] sizes the tranches on a local simulation of the bonding curve
+//! ([`simulate_buy`]): every tranche is the largest amount whose price impact, measured on the
+//! curve left by the tranches before it, stays within the cap. [`buy_split`] then executes
+//! them either one at a time from the payer, re-quoting from a fresh curve before each fill,
+//! or all at once as a Jito bundle spread over several wallets.
+//!
+//! Token amounts are exact, since the program credits exactly the quoted amount or fails. SOL
+//! costs are the quoted ones; the program may charge a little less when the curve moved in
+//! the buyer's favour.
+
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::anyhow;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::{Keypair, Signature}, signer::Signer};
+
+use crate::{
+    accounts::BondingCurveAccount,
+    common::latency::StageTimer,
+    constants::trade::DEFAULT_SLIPPAGE,
+    fee_clients::{bundle::{BundleBuilder, MAX_BUNDLE_TRANSACTIONS}, ClientType},
+    trade::{context::ExecutionContext, journal::TradeSide},
+};
+
+use super::{
+    buy::{buy_quote_instructions, buy_transaction_instructions},
+    common::{get_bonding_curve_account_with, get_global_account, get_token_program, quote_buy, quote_buy_with_mode, send_and_confirm_with_rpc_options, sign_versioned_transaction},
+    curve_cache::CurveRead,
+};
+
+/// Most tranches of one split buy in [`SplitMode::Sequential`]
+pub const MAX_TRANCHES: usize = 20;
+
+/// Outcome of a buy on a local copy of the bonding curve
+#[derive(Debug, Clone)]
+pub struct SimulatedBuy {
+    pub token_amount: u64,
+    /// SOL charged, protocol fee included
+    pub sol_cost: u64,
+    /// Rise of the spot price caused by the buy
+    pub price_impact_bps: u64,
+    pub curve_after: BondingCurveAccount,
+}
+
+/// Simulates buying with `amount_sol` lamports, fee included, the way `quote_buy` quotes it
+pub fn simulate_buy(curve: &BondingCurveAccount, amount_sol: u64, fee_basis_points: u64) -> Result<SimulatedBuy, anyhow::Error> {
+    let quote = quote_buy(curve, amount_sol, fee_basis_points, 0)?;
+    let token_amount = quote.token_amount;
+    let curve_sol = match token_amount {
+        0 => 0,
+        _ => ((token_amount as u128) * (curve.virtual_sol_reserves as u128) / ((curve.virtual_token_reserves - token_amount) as u128) + 1) as u64,
+    };
+
+    let mut curve_after = curve.clone();
+    curve_after.virtual_sol_reserves += curve_sol;
+    curve_after.real_sol_reserves += curve_sol;
+    curve_after.virtual_token_reserves -= token_amount;
+    curve_after.real_token_reserves = curve_after.real_token_reserves.saturating_sub(token_amount);
+    curve_after.complete = curve_after.real_token_reserves == 0;
+
+    Ok(SimulatedBuy {
+        token_amount,
+        sol_cost: quote.sol_cost,
+        price_impact_bps: price_ratio_bps(curve, &curve_after).saturating_sub(10_000),
+        curve_after,
+    })
+}
+
+/// Spot price of `to` relative to `from`, in basis points of `from`
+fn price_ratio_bps(from: &BondingCurveAccount, to: &BondingCurveAccount) -> u64 {
+    let numerator = (to.virtual_sol_reserves as u128) * (from.virtual_token_reserves as u128) * 10_000;
+    let denominator = (from.virtual_sol_reserves as u128) * (to.virtual_token_reserves as u128);
+    if denominator == 0 {
+        return u64::MAX;
+    }
+    (numerator / denominator).min(u64::MAX as u128) as u64
+}
+
+/// How far the spot price of `actual` is from that of `expected`, in either direction
+fn price_move_bps(expected: &BondingCurveAccount, actual: &BondingCurveAccount) -> u64 {
+    price_ratio_bps(expected, actual).abs_diff(10_000)
+}
+
+/// Largest amount up to `max_sol` whose price impact on `curve` is at most `max_impact_bps`
+pub fn largest_tranche(curve: &BondingCurveAccount, max_sol: u64, max_impact_bps: u64, fee_basis_points: u64) -> Result<u64, anyhow::Error> {
+    if simulate_buy(curve, max_sol, fee_basis_points)?.price_impact_bps <= max_impact_bps {
+        return Ok(max_sol);
+    }
+    // The impact grows with the amount, so the largest amount under the cap is a boundary
+    let (mut low, mut high) = (0, max_sol);
+    while low < high {
+        let middle = low + (high - low + 1) / 2;
+        if simulate_buy(curve, middle, fee_basis_points)?.price_impact_bps <= max_impact_bps {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Tranche amounts, in order, buying `total_sol` on `curve` without any tranche exceeding
+/// `max_impact_bps`
+pub fn plan_split(
+    curve: &BondingCurveAccount,
+    total_sol: u64,
+    max_impact_bps: u64,
+    fee_basis_points: u64,
+    max_tranches: usize,
+) -> Result<Vec<u64>, anyhow::Error> {
+    if total_sol == 0 {
+        return Err(anyhow!("Amount cannot be zero"));
+    }
+
+    let mut curve = curve.clone();
+    let mut remaining = total_sol;
+    let mut tranches = vec![];
+    while remaining > 0 {
+        if tranches.len() == max_tranches {
+            return Err(anyhow!("Buying {} lamports takes more than {} tranches of at most {} bps impact", total_sol, max_tranches, max_impact_bps));
+        }
+        let amount = largest_tranche(&curve, remaining, max_impact_bps, fee_basis_points)?;
+        if amount == 0 {
+            return Err(anyhow!("No amount stays within {} bps of price impact", max_impact_bps));
+        }
+        let simulated = simulate_buy(&curve, amount, fee_basis_points)?;
+        remaining -= amount;
+        if simulated.curve_after.complete && remaining > 0 {
+            return Err(anyhow!("The bonding curve completes before {} lamports are bought", total_sol));
+        }
+        curve = simulated.curve_after;
+        tranches.push(amount);
+    }
+    Ok(tranches)
+}
+
+#[derive(Clone)]
+pub enum SplitMode {
+    /// Tranches bought one at a time by the payer, each quoted on a fresh curve; stops when
+    /// the curve has moved more than `max_price_move_bps` from where the previous fill left it
+    Sequential { max_price_move_bps: Option<u64> },
+    /// All tranches in one Jito bundle, tranche `i` bought by `wallets[i % wallets.len()]`
+    Bundle { wallets: Vec<Arc<Keypair>> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrancheResult {
+    pub wallet: Pubkey,
+    /// Lamports budgeted for the tranche, fee included
+    pub amount_sol: u64,
+    pub token_amount: u64,
+    /// Quoted SOL cost, fee included
+    pub sol_cost: u64,
+    /// Set when the tranche landed
+    pub signature: Option<Signature>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitBuyReport {
+    /// Tranches the buy was planned with before executing
+    pub planned_tranches: usize,
+    /// Tranches attempted, in order
+    pub tranches: Vec<TrancheResult>,
+    /// Why a sequential buy stopped before buying the whole amount
+    pub stopped: Option<String>,
+}
+
+impl SplitBuyReport {
+    pub fn filled(&self) -> impl Iterator<Item = &TrancheResult> {
+        self.tranches.iter().filter(|tranche| tranche.signature.is_some())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.stopped.is_none() && self.tranches.iter().all(|tranche| tranche.signature.is_some())
+    }
+
+    pub fn tokens_bought(&self) -> u64 {
+        self.filled().map(|tranche| tranche.token_amount).sum()
+    }
+
+    pub fn sol_spent(&self) -> u64 {
+        self.filled().map(|tranche| tranche.sol_cost).sum()
+    }
+
+    /// Blended lamports paid per raw token unit over the filled tranches
+    pub fn average_price(&self) -> Option<f64> {
+        match self.tokens_bought() {
+            0 => None,
+            tokens => Some(self.sol_spent() as f64 / tokens as f64),
+        }
+    }
+}
+
+/// Buys `total_sol` lamports of `mint` in tranches of at most `max_impact_bps` price impact
+///
+/// Fails before sending anything when the amount can't be split within the limits; once
+/// tranches are sent, failures are reported in the returned [`SplitBuyReport`].
+pub async fn buy_split(
+    context: &ExecutionContext,
+    mint: Pubkey,
+    total_sol: u64,
+    max_impact_bps: u64,
+    mode: SplitMode,
+    slippage_basis_points: Option<u64>,
+    ata_exists: bool,
+    extra_instructions: Option<Vec<Instruction>>,
+) -> Result<SplitBuyReport, anyhow::Error> {
+    let rpc = context.rpc.as_ref();
+    let (global_account, curve, token_program) = tokio::try_join!(
+        get_global_account(rpc),
+        get_bonding_curve_account_with(rpc, &mint, CurveRead::Fresh),
+        get_token_program(rpc, &mint),
+    )?;
+    let fee_basis_points = global_account.fee_basis_points;
+    let max_tranches = match mode {
+        SplitMode::Sequential { .. } => MAX_TRANCHES,
+        SplitMode::Bundle { .. } => MAX_BUNDLE_TRANSACTIONS,
+    };
+    let plan = plan_split(&curve, total_sol, max_impact_bps, fee_basis_points, max_tranches)?;
+
+    let split = Split {
+        context,
+        mint,
+        fee_recipient: global_account.fee_recipient,
+        token_program,
+        fee_basis_points,
+        slippage_basis_points: slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+        extra_instructions: context.tagged(extra_instructions),
+    };
+    match mode {
+        SplitMode::Sequential { max_price_move_bps } => Ok(split.sequential(curve.as_ref().clone(), total_sol, max_impact_bps, plan.len(), max_price_move_bps, ata_exists).await),
+        SplitMode::Bundle { wallets } => split.bundle(curve.as_ref().clone(), plan, &wallets).await,
+    }
+}
+
+struct Split<'a> {
+    context: &'a ExecutionContext,
+    mint: Pubkey,
+    fee_recipient: Pubkey,
+    token_program: Pubkey,
+    fee_basis_points: u64,
+    slippage_basis_points: u64,
+    extra_instructions: Vec<Instruction>,
+}
+
+impl Split<'_> {
+    async fn sequential(
+        &self,
+        mut curve: BondingCurveAccount,
+        total_sol: u64,
+        max_impact_bps: u64,
+        planned_tranches: usize,
+        max_price_move_bps: Option<u64>,
+        mut ata_exists: bool,
+    ) -> SplitBuyReport {
+        let context = self.context;
+        let mut report = SplitBuyReport { planned_tranches, tranches: vec![], stopped: None };
+        let mut remaining = total_sol;
+        let mut expected: Option<BondingCurveAccount> = None;
+
+        while remaining > 0 {
+            if report.tranches.len() == MAX_TRANCHES {
+                report.stopped = Some(format!("Reached {} tranches with {} lamports left", MAX_TRANCHES, remaining));
+                break;
+            }
+            if let Some(expected) = &expected {
+                curve = match get_bonding_curve_account_with(&context.rpc, &self.mint, CurveRead::Fresh).await {
+                    Ok(curve) => curve.as_ref().clone(),
+                    Err(e) => {
+                        report.stopped = Some(format!("Failed to re-quote: {}", e));
+                        break;
+                    }
+                };
+                let moved = price_move_bps(expected, &curve);
+                if max_price_move_bps.is_some_and(|max_move| moved > max_move) {
+                    report.stopped = Some(format!("Price moved {} bps from the previous fill", moved));
+                    break;
+                }
+            }
+
+            let simulated = largest_tranche(&curve, remaining, max_impact_bps, self.fee_basis_points)
+                .and_then(|amount| match amount {
+                    0 => Err(anyhow!("No amount stays within {} bps of price impact", max_impact_bps)),
+                    amount => Ok((amount, simulate_buy(&curve, amount, self.fee_basis_points)?)),
+                });
+            let (amount, simulated) = match simulated {
+                Ok(simulated) => simulated,
+                Err(e) => {
+                    report.stopped = Some(e.to_string());
+                    break;
+                }
+            };
+
+            let result = self.send_tranche(&curve, amount, ata_exists).await;
+            let failed = result.is_err();
+            report.tranches.push(TrancheResult {
+                wallet: context.payer.pubkey(),
+                amount_sol: amount,
+                token_amount: simulated.token_amount,
+                sol_cost: simulated.sol_cost,
+                signature: result.as_ref().ok().copied(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            });
+            if failed {
+                report.stopped = Some(format!("Tranche {} failed", report.tranches.len()));
+                break;
+            }
+            remaining -= amount;
+            ata_exists = true;
+            expected = Some(simulated.curve_after);
+        }
+        report
+    }
+
+    async fn send_tranche(&self, curve: &BondingCurveAccount, amount: u64, ata_exists: bool) -> Result<Signature, anyhow::Error> {
+        let context = self.context;
+        let trade_id = context.record_intent(TradeSide::Buy, self.mint, Some(amount), None, Some(self.slippage_basis_points));
+        let quote = quote_buy_with_mode(curve, amount, self.fee_basis_points, self.slippage_basis_points, context.slippage_mode)?;
+        let instructions = buy_quote_instructions(&context.payer.pubkey(), &self.mint, &self.fee_recipient, &self.token_program, &quote, ata_exists);
+        let instructions = buy_transaction_instructions(&context.priority_fee, None, instructions, self.extra_instructions.clone());
+        let recent_blockhash = context.rpc.get_latest_blockhash().await?;
+        let transaction = sign_versioned_transaction(&instructions, &[context.payer.as_ref()], recent_blockhash)?;
+        send_and_confirm_with_rpc_options(&mut StageTimer::new(), &context.send_rpc, Some(trade_id), &transaction, context.rpc_send_options).await
+    }
+
+    async fn bundle(&self, mut curve: BondingCurveAccount, plan: Vec<u64>, wallets: &[Arc<Keypair>]) -> Result<SplitBuyReport, anyhow::Error> {
+        if wallets.is_empty() {
+            return Err(anyhow!("Bundle mode needs at least one wallet"));
+        }
+        let mut jito = None;
+        for fee_client in &self.context.fee_clients {
+            if fee_client.get_client_type().await == ClientType::Jito {
+                jito = Some(fee_client.clone());
+                break;
+            }
+        }
+        let jito = jito.ok_or(anyhow!("Bundle mode needs a Jito fee client"))?;
+
+        let mut builder = BundleBuilder::new();
+        let mut tranches = vec![];
+        for (index, amount) in plan.iter().copied().enumerate() {
+            let wallet = &wallets[index % wallets.len()];
+            self.context.record_intent(TradeSide::Buy, self.mint, Some(amount), None, Some(self.slippage_basis_points));
+            let quote = quote_buy_with_mode(&curve, amount, self.fee_basis_points, self.slippage_basis_points, self.context.slippage_mode)?;
+            let instructions = buy_quote_instructions(&wallet.pubkey(), &self.mint, &self.fee_recipient, &self.token_program, &quote, false);
+            builder = builder.add_transaction(
+                wallet.clone(),
+                buy_transaction_instructions(&self.context.priority_fee, None, instructions, self.extra_instructions.clone()),
+                vec![],
+            );
+
+            let simulated = simulate_buy(&curve, amount, self.fee_basis_points)?;
+            tranches.push(TrancheResult {
+                wallet: wallet.pubkey(),
+                amount_sol: amount,
+                token_amount: simulated.token_amount,
+                sol_cost: simulated.sol_cost,
+                signature: None,
+                error: None,
+            });
+            curve = simulated.curve_after;
+        }
+
+        let tip_account = Pubkey::from_str(&jito.get_tip_account().await?)?;
+        let recent_blockhash = self.context.rpc.get_latest_blockhash().await?;
+        let transactions = builder
+            .tip(tip_account, self.context.priority_fee.buy_tip_fee.lamports())
+            .build(recent_blockhash)?;
+
+        // A bundle lands whole or not at all
+        let stopped = match jito.send_transactions(&transactions).await {
+            Ok(_) => {
+                for (tranche, transaction) in tranches.iter_mut().zip(&transactions) {
+                    tranche.signature = transaction.signatures.first().copied();
+                }
+                None
+            }
+            Err(e) => {
+                tranches.iter_mut().for_each(|tranche| tranche.error = Some(e.to_string()));
+                Some(format!("Bundle failed: {}", e))
+            }
+        };
+        Ok(SplitBuyReport { planned_tranches: plan.len(), tranches, stopped })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::tokens::LAMPORTS_PER_SOL;
+
+    const FEE_BASIS_POINTS: u64 = 100;
+
+    fn initial_curve() -> BondingCurveAccount {
+        BondingCurveAccount::new(0, 1_073_000_000_000_000, 30 * LAMPORTS_PER_SOL, 793_100_000_000_000, 0, 1_000_000_000_000_000, false)
+    }
+
+    #[test]
+    fn test_impact_grows_with_amount() {
+        let curve = initial_curve();
+        let small = simulate_buy(&curve, LAMPORTS_PER_SOL / 10, FEE_BASIS_POINTS).unwrap();
+        let large = simulate_buy(&curve, 5 * LAMPORTS_PER_SOL, FEE_BASIS_POINTS).unwrap();
+
+        assert!(small.price_impact_bps < large.price_impact_bps);
+        // The spot price rises by about (35 / 30)^2 on a 5 SOL buy
+        assert!((3_500..3_700).contains(&large.price_impact_bps), "{}", large.price_impact_bps);
+        assert_eq!(large.curve_after.virtual_token_reserves, curve.virtual_token_reserves - large.token_amount);
+    }
+
+    #[test]
+    fn test_every_tranche_stays_under_the_cap() {
+        let mut curve = initial_curve();
+        let total = 5 * LAMPORTS_PER_SOL;
+        let plan = plan_split(&curve, total, 500, FEE_BASIS_POINTS, MAX_TRANCHES).unwrap();
+
+        assert!(plan.len() > 1);
+        assert_eq!(plan.iter().sum::<u64>(), total);
+        for amount in plan {
+            let simulated = simulate_buy(&curve, amount, FEE_BASIS_POINTS).unwrap();
+            assert!(simulated.price_impact_bps <= 500);
+            curve = simulated.curve_after;
+        }
+    }
+
+    #[test]
+    fn test_plan_fails_when_limits_are_too_tight() {
+        let curve = initial_curve();
+        assert!(plan_split(&curve, 5 * LAMPORTS_PER_SOL, 500, FEE_BASIS_POINTS, MAX_BUNDLE_TRANSACTIONS).is_err());
+        assert!(plan_split(&curve, 5 * LAMPORTS_PER_SOL, 0, FEE_BASIS_POINTS, MAX_TRANCHES).is_err());
+        assert_eq!(plan_split(&curve, LAMPORTS_PER_SOL / 10, 500, FEE_BASIS_POINTS, MAX_TRANCHES).unwrap(), vec![LAMPORTS_PER_SOL / 10]);
+    }
+
+    #[test]
+    fn test_report_blends_filled_tranches() {
+        let tranche = |token_amount, sol_cost, landed: bool| TrancheResult {
+            wallet: Pubkey::new_unique(),
+            amount_sol: sol_cost,
+            token_amount,
+            sol_cost,
+            signature: landed.then(Signature::new_unique),
+            error: (!landed).then(|| "failed".to_string()),
+        };
+        let report = SplitBuyReport {
+            planned_tranches: 3,
+            tranches: vec![tranche(100, 1_000, true), tranche(50, 1_000, true), tranche(40, 1_000, false)],
+            stopped: Some("Tranche 3 failed".to_string()),
+        };
+
+        assert!(!report.is_complete());
+        assert_eq!(report.tokens_bought(), 150);
+        assert_eq!(report.average_price(), Some(2_000.0 / 150.0));
+    }
+}
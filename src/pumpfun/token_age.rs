@@ -0,0 +1,247 @@
+//! Creation slot and age of tokens
+//!
+//! A token's creation slot is taken from its create event when a subscription saw it, and
+//! otherwise from the oldest transaction on its bonding curve, found by paging back through
+//! the curve's signatures up to [`MAX_SIGNATURE_PAGES`] pages. Creation never changes, so it
+//! is cached per mint without expiry. The current slot comes from the running subscriptions
+//! when they are fresh, like deadlines do, and from `getSlot` otherwise.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::RwLock,
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_sdk::{clock::UnixTimestamp, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    common::{cache_stats::{CacheCounters, CacheStats}, logs_data::CreateTokenInfo, SolanaRpcClient},
+    trade::deadline::{slot_tracker, SlotSource, MAX_TRACKED_SLOT_AGE},
+};
+
+use super::common::get_bonding_curve_pda;
+
+/// Most pages of 1,000 signatures walked back to a bonding curve's first transaction
+pub const MAX_SIGNATURE_PAGES: usize = 10;
+
+const SIGNATURES_PER_PAGE: usize = 1_000;
+const MAX_CACHED_MINTS: usize = 100_000;
+
+lazy_static::lazy_static! {
+    static ref CREATION_CACHE: RwLock<HashMap<Pubkey, Creation>> = RwLock::new(HashMap::new());
+}
+
+static CREATION_COUNTERS: CacheCounters = CacheCounters::new();
+
+/// When a token was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Creation {
+    pub slot: u64,
+    /// Block time of the create, `None` when it was seen live or the node doesn't know it
+    pub time: Option<UnixTimestamp>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenAge {
+    pub creation_slot: u64,
+    pub creation_time: Option<UnixTimestamp>,
+    pub current_slot: u64,
+    /// Slots since creation
+    pub age_slots: u64,
+}
+
+impl TokenAge {
+    pub fn new(creation: Creation, current_slot: u64) -> Self {
+        Self {
+            creation_slot: creation.slot,
+            creation_time: creation.time,
+            current_slot,
+            age_slots: current_slot.saturating_sub(creation.slot),
+        }
+    }
+}
+
+/// The bonding curve has more transactions than the walk covers, so its first one was not found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Bonding curve of {mint} has more than {scanned} transactions, the oldest seen is from slot {oldest_slot}")]
+pub struct HistoryTooLong {
+    pub mint: Pubkey,
+    pub scanned: usize,
+    pub oldest_slot: u64,
+}
+
+/// Remembers the creation of the token announced by `token`, whose `slot` must be set
+pub fn record_create_event(token: &CreateTokenInfo) {
+    if token.slot > 0 {
+        store(token.mint, Creation { slot: token.slot, time: None });
+    }
+}
+
+/// Creation of `mint` if it is cached
+pub fn cached_creation(mint: &Pubkey) -> Option<Creation> {
+    CREATION_COUNTERS.record(CREATION_CACHE.read().unwrap().get(mint).copied())
+}
+
+pub fn token_age_cache_stats() -> CacheStats {
+    CREATION_COUNTERS.stats("token_creation", CREATION_CACHE.read().unwrap().len(), None)
+}
+
+pub fn clear_token_age_cache() {
+    CREATION_CACHE.write().unwrap().clear();
+}
+
+/// Age of `mint` in slots, see the [module docs](self)
+pub async fn get_token_age(rpc: &SolanaRpcClient, mint: &Pubkey) -> Result<TokenAge, anyhow::Error> {
+    let creation = get_creation(rpc, mint).await?;
+    let current_slot = match slot_tracker().latest(MAX_TRACKED_SLOT_AGE) {
+        Some(slot) => slot,
+        None => rpc.current_slot().await?,
+    };
+    Ok(TokenAge::new(creation, current_slot))
+}
+
+/// Like [`get_token_age`], reading the current slot from `slots`
+pub async fn get_token_age_with(rpc: &SolanaRpcClient, mint: &Pubkey, slots: &dyn SlotSource) -> Result<TokenAge, anyhow::Error> {
+    let creation = get_creation(rpc, mint).await?;
+    Ok(TokenAge::new(creation, slots.current_slot().await?))
+}
+
+/// Creation of `mint`, from the cache or else its bonding curve's history
+pub async fn get_creation(rpc: &SolanaRpcClient, mint: &Pubkey) -> Result<Creation, anyhow::Error> {
+    if let Some(creation) = cached_creation(mint) {
+        return Ok(creation);
+    }
+    let creation = fetch_creation(rpc, mint).await?;
+    store(*mint, creation);
+    Ok(creation)
+}
+
+/// Slot and time of the oldest successful transaction on the bonding curve of `mint`
+async fn fetch_creation(rpc: &SolanaRpcClient, mint: &Pubkey) -> Result<Creation, anyhow::Error> {
+    let bonding_curve = get_bonding_curve_pda(mint).ok_or(anyhow!("Bonding curve not found"))?;
+    let mut before = None;
+    let mut oldest = None;
+    let mut scanned = 0;
+    for _ in 0..MAX_SIGNATURE_PAGES {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            before,
+            until: None,
+            limit: Some(SIGNATURES_PER_PAGE),
+            commitment: Some(CommitmentConfig::confirmed()),
+        };
+        let page = rpc.get_signatures_for_address_with_config(&bonding_curve, config).await?;
+        scanned += page.len();
+        oldest = page.iter().rev().find(|status| status.err.is_none()).map(|status| Creation { slot: status.slot, time: status.block_time }).or(oldest);
+        if page.len() < SIGNATURES_PER_PAGE {
+            return oldest.ok_or_else(|| anyhow!("Bonding curve of {} has no transactions", mint));
+        }
+        before = page.last().and_then(|status| Signature::from_str(&status.signature).ok());
+    }
+    Err(HistoryTooLong { mint: *mint, scanned, oldest_slot: oldest.map_or(0, |creation| creation.slot) }.into())
+}
+
+fn store(mint: Pubkey, creation: Creation) {
+    let mut cache = CREATION_CACHE.write().unwrap();
+    if cache.len() >= MAX_CACHED_MINTS && !cache.contains_key(&mint) {
+        // the oldest tokens are the least likely to be filtered again
+        if let Some(oldest) = cache.iter().min_by_key(|(_, creation)| creation.slot).map(|(mint, _)| *mint) {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(mint, creation);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use serde_json::{json, Value};
+    use solana_rpc_client::{rpc_client::RpcClientConfig, rpc_sender::{RpcSender, RpcTransportStats}};
+    use solana_rpc_client_api::{client_error::Result as ClientResult, request::RpcRequest};
+
+    use super::*;
+
+    /// Serves `pages` to `getSignaturesForAddress`, newest first, and slot 5,000 to `getSlot`
+    struct History {
+        pages: Vec<Vec<(u64, bool)>>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl RpcSender for History {
+        async fn send(&self, request: RpcRequest, _params: Value) -> ClientResult<Value> {
+            match request {
+                RpcRequest::GetSlot => Ok(json!(5_000)),
+                RpcRequest::GetSignaturesForAddress => {
+                    let page = &self.pages[self.requests.fetch_add(1, Ordering::Relaxed)];
+                    Ok(page
+                        .iter()
+                        .map(|(slot, failed)| {
+                            let err = failed.then(|| json!({ "InstructionError": [0, "InvalidArgument"] }));
+                            json!({ "signature": Signature::new_unique().to_string(), "slot": slot, "err": err, "memo": null, "blockTime": *slot as i64 * 10, "confirmationStatus": "finalized" })
+                        })
+                        .collect())
+                }
+                request => panic!("unexpected {}", request),
+            }
+        }
+
+        fn get_transport_stats(&self) -> RpcTransportStats {
+            RpcTransportStats::default()
+        }
+
+        fn url(&self) -> String {
+            "history".to_string()
+        }
+    }
+
+    fn rpc(pages: Vec<Vec<(u64, bool)>>) -> (SolanaRpcClient, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let sender = History { pages, requests: requests.clone() };
+        (SolanaRpcClient::new_sender(sender, RpcClientConfig::with_commitment(CommitmentConfig::confirmed())), requests)
+    }
+
+    #[tokio::test]
+    async fn test_creation_is_the_oldest_transaction_and_cached() {
+        let mint = Pubkey::new_unique();
+        let full_page: Vec<(u64, bool)> = (0..SIGNATURES_PER_PAGE as u64).map(|i| (4_000 - i, false)).collect();
+        let (rpc, requests) = rpc(vec![full_page, vec![(2_500, false), (2_400, false), (2_300, true)]]);
+
+        let age = get_token_age(&rpc, &mint).await.unwrap();
+        assert_eq!(age, TokenAge { creation_slot: 2_400, creation_time: Some(24_000), current_slot: 5_000, age_slots: 2_600 });
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+
+        assert_eq!(get_token_age(&rpc, &mint).await.unwrap().creation_slot, 2_400);
+        assert_eq!(requests.load(Ordering::Relaxed), 2, "creation is served from the cache");
+    }
+
+    #[tokio::test]
+    async fn test_walk_stops_at_the_page_cap() {
+        let mint = Pubkey::new_unique();
+        let pages = (0..MAX_SIGNATURE_PAGES as u64).map(|page| (0..SIGNATURES_PER_PAGE as u64).map(|i| (100_000 - page * 1_000 - i, false)).collect()).collect();
+        let (rpc, requests) = rpc(pages);
+
+        let error = get_token_age(&rpc, &mint).await.unwrap_err();
+        let too_long = error.downcast_ref::<HistoryTooLong>().unwrap();
+        assert_eq!((too_long.scanned, too_long.oldest_slot), (MAX_SIGNATURE_PAGES * SIGNATURES_PER_PAGE, 90_001));
+        assert_eq!(requests.load(Ordering::Relaxed), MAX_SIGNATURE_PAGES);
+        assert_eq!(cached_creation(&mint), None);
+    }
+
+    #[tokio::test]
+    async fn test_create_events_are_used_without_rpc() {
+        let token = CreateTokenInfo { slot: 4_990, mint: Pubkey::new_unique(), ..Default::default() };
+        record_create_event(&token);
+        let (rpc, requests) = rpc(vec![]);
+
+        let age = get_token_age(&rpc, &token.mint).await.unwrap();
+        assert_eq!((age.creation_slot, age.creation_time, age.age_slots), (4_990, None, 10));
+        assert_eq!(requests.load(Ordering::Relaxed), 0);
+    }
+}
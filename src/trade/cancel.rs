@@ -0,0 +1,69 @@
+//! Cancelling trades in flight
+//!
+//! A trade given a [`CancellationToken`] checks it between its steps. Cancelled before
+//! anything was sent, it fails with [`Cancelled::BeforeSubmission`]. Cancelled later, the
+//! [`Submitter`](super::Submitter) sends through no further transports, stops polling for
+//! confirmation, and the trade fails with [`Cancelled::AfterSubmission`] listing the
+//! signatures whose status is unknown, so the caller can look them up later. Transactions
+//! already handed to a relay can't be recalled and may still land.
+
+use std::future::Future;
+
+use solana_sdk::signature::Signature;
+pub use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Cancelled {
+    #[error("Trade cancelled before submission")]
+    BeforeSubmission,
+    /// The transactions were sent; whether they landed is unknown
+    #[error("Trade cancelled after submitting {signatures:?}, their status is unknown")]
+    AfterSubmission { signatures: Vec<Signature> },
+}
+
+impl Cancelled {
+    /// The cancellation somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&Cancelled> {
+        error.chain().find_map(|cause| cause.downcast_ref::<Cancelled>())
+    }
+}
+
+/// Fails with [`Cancelled::BeforeSubmission`] if `cancel` is cancelled
+pub fn check(cancel: Option<&CancellationToken>) -> Result<(), Cancelled> {
+    match cancel {
+        Some(cancel) if cancel.is_cancelled() => Err(Cancelled::BeforeSubmission),
+        _ => Ok(()),
+    }
+}
+
+/// Runs a step preceding the submission, failing with [`Cancelled::BeforeSubmission`] as
+/// soon as `cancel` is cancelled
+pub async fn before_submission<T, E: Into<anyhow::Error>>(
+    cancel: Option<&CancellationToken>,
+    step: impl Future<Output = Result<T, E>>,
+) -> Result<T, anyhow::Error> {
+    let Some(cancel) = cancel else { return step.await.map_err(Into::into) };
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => Err(Cancelled::BeforeSubmission.into()),
+        result = step => result.map_err(Into::into),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_steps_stop_when_cancelled() {
+        let cancel = CancellationToken::new();
+        assert_eq!(before_submission(Some(&cancel), async { Ok::<_, anyhow::Error>(1) }).await.unwrap(), 1);
+        assert!(check(Some(&cancel)).is_ok());
+
+        cancel.cancel();
+        let error = before_submission(Some(&cancel), futures::future::pending::<Result<(), anyhow::Error>>()).await.unwrap_err();
+        assert_eq!(Cancelled::find(&error), Some(&Cancelled::BeforeSubmission));
+        assert_eq!(check(Some(&cancel)), Err(Cancelled::BeforeSubmission));
+        assert!(check(None).is_ok());
+    }
+}
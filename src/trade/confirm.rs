@@ -0,0 +1,233 @@
+//! Shared confirmation engine
+//!
+//! Every submission path waits for its signatures here: one loop batching
+//! `get_signature_statuses` for all pending signatures, polling every
+//! [`CONFIRMATION_POLL_INTERVAL`] until each reaches the requested [`ConfirmLevel`], fails,
+//! or the timeout elapses. [`confirm_signatures_until`] also stops once its cancellation
+//! token is cancelled.
+
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use anyhow::anyhow;
+use solana_sdk::{commitment_config::CommitmentLevel, signature::Signature};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+use tokio::time::sleep;
+
+use crate::common::SolanaRpcClient;
+
+use super::cancel::CancellationToken;
+
+pub const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Commitment a submission waits for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ConfirmLevel {
+    /// Don't wait; the submission finishes once the transport accepted the transaction
+    None,
+    Processed,
+    #[default]
+    Confirmed,
+    Finalized,
+}
+
+impl ConfirmLevel {
+    /// Whether a transaction at `status` has reached this level
+    pub fn is_reached_by(self, status: Option<&TransactionConfirmationStatus>) -> bool {
+        let reached = match status {
+            None => ConfirmLevel::None,
+            Some(TransactionConfirmationStatus::Processed) => ConfirmLevel::Processed,
+            Some(TransactionConfirmationStatus::Confirmed) => ConfirmLevel::Confirmed,
+            Some(TransactionConfirmationStatus::Finalized) => ConfirmLevel::Finalized,
+        };
+        reached >= self
+    }
+}
+
+impl From<CommitmentLevel> for ConfirmLevel {
+    fn from(level: CommitmentLevel) -> Self {
+        match level {
+            CommitmentLevel::Processed => ConfirmLevel::Processed,
+            CommitmentLevel::Confirmed => ConfirmLevel::Confirmed,
+            CommitmentLevel::Finalized => ConfirmLevel::Finalized,
+        }
+    }
+}
+
+/// Slot and commitment a transaction reached
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LandingStatus {
+    pub slot: Option<u64>,
+    pub commitment: Option<TransactionConfirmationStatus>,
+}
+
+/// Where the engine reads signature statuses from
+#[async_trait::async_trait]
+pub trait SignatureStatuses: Send + Sync {
+    async fn signature_statuses(&self, signatures: &[Signature]) -> Result<Vec<Option<TransactionStatus>>, anyhow::Error>;
+}
+
+#[async_trait::async_trait]
+impl SignatureStatuses for SolanaRpcClient {
+    async fn signature_statuses(&self, signatures: &[Signature]) -> Result<Vec<Option<TransactionStatus>>, anyhow::Error> {
+        Ok(self.get_signature_statuses(signatures).await?.value)
+    }
+}
+
+/// Outcome of waiting for one signature
+#[derive(Debug)]
+pub struct Confirmation {
+    /// Last status seen, which is empty when the transaction never showed up, or an error
+    /// if the transaction landed but failed
+    pub result: Result<LandingStatus, anyhow::Error>,
+    /// When waiting for this signature stopped
+    pub finished_at: Instant,
+    /// Whether waiting was cancelled before the signature reached the level or failed, which
+    /// leaves its status unknown
+    pub cancelled: bool,
+}
+
+impl Confirmation {
+    /// Whether the transaction reached `level`
+    pub fn reached(&self, level: ConfirmLevel) -> bool {
+        matches!(&self.result, Ok(landing) if level.is_reached_by(landing.commitment.as_ref()))
+    }
+}
+
+/// Waits until every signature reaches `level`, fails, or `timeout` elapses
+///
+/// Returns one [`Confirmation`] per signature, in order. Transient errors reading statuses
+/// are retried until the timeout.
+pub async fn confirm_signatures(
+    statuses: &dyn SignatureStatuses,
+    signatures: &[Signature],
+    level: ConfirmLevel,
+    timeout: Duration,
+) -> Vec<Confirmation> {
+    confirm_signatures_until(statuses, signatures, level, timeout, &CancellationToken::new()).await
+}
+
+/// [`confirm_signatures`], stopping early once `cancel` is cancelled
+pub async fn confirm_signatures_until(
+    statuses: &dyn SignatureStatuses,
+    signatures: &[Signature],
+    level: ConfirmLevel,
+    timeout: Duration,
+    cancel: &CancellationToken,
+) -> Vec<Confirmation> {
+    let start = Instant::now();
+    let mut landings = vec![LandingStatus::default(); signatures.len()];
+    let mut done: HashMap<usize, Confirmation> = HashMap::new();
+    let mut cancelled = cancel.is_cancelled();
+
+    if level == ConfirmLevel::None {
+        return landings.into_iter().map(|landing| Confirmation { result: Ok(landing), finished_at: start, cancelled: false }).collect();
+    }
+
+    while !cancelled {
+        let pending: Vec<usize> = (0..signatures.len()).filter(|index| !done.contains_key(index)).collect();
+        if pending.is_empty() {
+            break;
+        }
+
+        let batch: Vec<Signature> = pending.iter().map(|index| signatures[*index]).collect();
+        if let Ok(batch_statuses) = statuses.signature_statuses(&batch).await {
+            for (index, status) in pending.iter().zip(batch_statuses) {
+                let Some(status) = status else { continue };
+                if let Some(err) = status.err {
+                    let result = Err(anyhow!("Transaction {} failed: {}", signatures[*index], err));
+                    done.insert(*index, Confirmation { result, finished_at: Instant::now(), cancelled: false });
+                    continue;
+                }
+
+                landings[*index] = LandingStatus { slot: Some(status.slot), commitment: status.confirmation_status.clone() };
+                if level.is_reached_by(status.confirmation_status.as_ref()) {
+                    done.insert(*index, Confirmation { result: Ok(landings[*index].clone()), finished_at: Instant::now(), cancelled: false });
+                }
+            }
+        }
+
+        if done.len() == signatures.len() || start.elapsed() >= timeout {
+            break;
+        }
+        tokio::select! {
+            _ = cancel.cancelled() => cancelled = true,
+            _ = sleep(CONFIRMATION_POLL_INTERVAL) => {}
+        }
+    }
+
+    let finished_at = Instant::now();
+    landings
+        .into_iter()
+        .enumerate()
+        .map(|(index, landing)| done.remove(&index).unwrap_or(Confirmation { result: Ok(landing), finished_at, cancelled }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_rpc(url: &str) -> SolanaRpcClient {
+        SolanaRpcClient::new_mock(url.to_string())
+    }
+
+    #[test]
+    fn test_confirm_level_ordering() {
+        let confirmed = TransactionConfirmationStatus::Confirmed;
+        assert!(ConfirmLevel::Processed.is_reached_by(Some(&confirmed)));
+        assert!(ConfirmLevel::Confirmed.is_reached_by(Some(&confirmed)));
+        assert!(!ConfirmLevel::Finalized.is_reached_by(Some(&confirmed)));
+        assert!(!ConfirmLevel::Processed.is_reached_by(None));
+        assert!(ConfirmLevel::None.is_reached_by(None));
+        assert_eq!(ConfirmLevel::from(CommitmentLevel::Finalized), ConfirmLevel::Finalized);
+    }
+
+    #[tokio::test]
+    async fn test_confirms_landed_signatures() {
+        let rpc = mock_rpc("succeeds");
+        let signatures = [Signature::new_unique(), Signature::new_unique()];
+
+        let confirmations = confirm_signatures(&rpc, &signatures, ConfirmLevel::Confirmed, Duration::from_secs(1)).await;
+        assert_eq!(confirmations.len(), 2);
+        for confirmation in &confirmations {
+            assert!(confirmation.reached(ConfirmLevel::Finalized));
+            assert_eq!(confirmation.result.as_ref().unwrap().slot, Some(1));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_failed_and_missing_signatures() {
+        let signature = Signature::new_unique();
+
+        let failed = confirm_signatures(&mock_rpc("instruction_error"), &[signature], ConfirmLevel::Confirmed, Duration::from_secs(1)).await;
+        assert!(failed[0].result.as_ref().unwrap_err().to_string().contains(&signature.to_string()));
+
+        let started = Instant::now();
+        let missing = confirm_signatures(&mock_rpc("sig_not_found"), &[signature], ConfirmLevel::Confirmed, Duration::from_millis(500)).await;
+        assert_eq!(missing[0].result.as_ref().unwrap(), &LandingStatus::default());
+        assert!(!missing[0].reached(ConfirmLevel::Processed));
+        assert!(started.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_polling() {
+        let cancel = CancellationToken::new();
+        let stopper = cancel.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(100)).await;
+            stopper.cancel();
+        });
+
+        let started = Instant::now();
+        let confirmations = confirm_signatures_until(&mock_rpc("sig_not_found"), &[Signature::new_unique()], ConfirmLevel::Confirmed, Duration::from_secs(10), &cancel).await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(confirmations[0].cancelled);
+        assert!(!confirmations[0].reached(ConfirmLevel::Processed));
+    }
+
+    #[tokio::test]
+    async fn test_level_none_does_not_poll() {
+        let confirmations = confirm_signatures(&mock_rpc("fails"), &[Signature::new_unique()], ConfirmLevel::None, Duration::from_secs(10)).await;
+        assert!(confirmations[0].reached(ConfirmLevel::None));
+    }
+}
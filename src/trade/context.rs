@@ -0,0 +1,298 @@
+//! Per-trade execution settings
+//!
+//! An [`ExecutionContext`] holds everything a trade needs to build, sign and submit its
+//! transactions. `PumpFun` builds one per call from its own state, with the call's
+//! [`TradeOverrides`] applied on top, and the `*_with_tip` trade functions consume it.
+
+use std::{future::Future, sync::Arc};
+
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::{Keypair, Signature}, transaction::VersionedTransaction};
+use tracing::Instrument;
+
+use crate::{
+    common::{latency::StageTimer, PriorityFee, SolanaRpcClient, TradeTag},
+    fee_clients::{FeeClient, SubmissionOptions},
+    pumpfun::{common::{BuyFloor, PartialFillGuard, SellFloor, SlippageMode}, sell::Proceeds},
+};
+
+use super::{
+    cancel::{self, CancellationToken, Cancelled},
+    deadline::{DeadlineMissed, DeadlineOutcome, SlotSource, TrackedSlots},
+    drain::Drain,
+    journal::{self, TradeId, TradeIntent, TradeSide},
+    NotConfirmed, RpcSendOptions, SendMode, SubmissionResult, SubmitOptions, Submitter,
+};
+
+/// Per-call replacements for a client's settings; `None` keeps the client's value
+#[derive(Clone, Default)]
+pub struct TradeOverrides {
+    pub priority_fee: Option<PriorityFee>,
+    /// Fee clients to submit through instead of all of the client's
+    pub fee_clients: Option<Vec<Arc<FeeClient>>>,
+    pub submission_options: Option<SubmissionOptions>,
+    pub send_mode: Option<SendMode>,
+    pub rpc_send_options: Option<RpcSendOptions>,
+    pub slippage_mode: Option<SlippageMode>,
+    pub skip_balance_check: Option<bool>,
+    pub retry_fee_recipient_rotation: Option<bool>,
+    pub proceeds: Option<Proceeds>,
+    pub sell_floor: Option<SellFloor>,
+    pub partial_fill: Option<PartialFillGuard>,
+    pub buy_floor: Option<BuyFloor>,
+    /// `Some(None)` sends the trade untagged
+    pub trade_tag: Option<Option<TradeTag>>,
+    pub idempotency_key: Option<String>,
+    pub cancel: Option<CancellationToken>,
+    /// Last slot the trade is worth landing in, see [`deadline`](super::deadline)
+    pub land_by_slot: Option<u64>,
+}
+
+/// Settings a single trade is executed with
+#[derive(Clone)]
+pub struct ExecutionContext {
+    pub payer: Arc<Keypair>,
+    /// Signers besides the payer, e.g. the mint keypair of a create
+    pub signers: Vec<Arc<Keypair>>,
+    /// Client of quotes, account reads and blockhashes
+    pub rpc: Arc<SolanaRpcClient>,
+    /// Client the trade's transactions are sent and confirmed through over RPC
+    pub send_rpc: Arc<SolanaRpcClient>,
+    pub fee_clients: Vec<Arc<FeeClient>>,
+    /// How the transactions are spread over `fee_clients`
+    pub send_mode: SendMode,
+    pub submission_options: SubmissionOptions,
+    /// Preflight and retry settings of transactions sent over plain RPC
+    pub rpc_send_options: RpcSendOptions,
+    /// How buys apply their slippage tolerance
+    pub slippage_mode: SlippageMode,
+    /// Send buys without checking the payer can afford them
+    pub skip_balance_check: bool,
+    /// Rebuild and send a trade once more when it failed on a rotated fee recipient, see
+    /// [`fee_recipient`](crate::pumpfun::fee_recipient)
+    pub retry_fee_recipient_rotation: bool,
+    /// Where sells pay out their SOL
+    pub proceeds: Proceeds,
+    /// Lowest minimum output sells are sent with
+    pub sell_floor: SellFloor,
+    /// When buys from a nearly exhausted curve are refused
+    pub partial_fill: PartialFillGuard,
+    /// Smallest buy that is sent
+    pub buy_floor: BuyFloor,
+    pub priority_fee: PriorityFee,
+    pub trade_tag: Option<TradeTag>,
+    /// Caller's key for the trade, recorded in the trade journal
+    pub idempotency_key: Option<String>,
+    /// Span the submission is recorded in
+    pub span: tracing::Span,
+    /// Stops the trade when cancelled, see [`cancel`](super::cancel)
+    pub cancel: Option<CancellationToken>,
+    /// Last slot the trade is worth landing in, see [`deadline`](super::deadline)
+    pub land_by_slot: Option<u64>,
+    /// Where the current slot is read from for `land_by_slot`
+    pub slot_source: Arc<dyn SlotSource>,
+    /// Where the submissions are registered for a graceful shutdown, see [`drain`](super::drain)
+    pub drain: Option<Arc<Drain>>,
+}
+
+impl ExecutionContext {
+    pub fn new(payer: Arc<Keypair>, rpc: Arc<SolanaRpcClient>, fee_clients: Vec<Arc<FeeClient>>, priority_fee: PriorityFee) -> Self {
+        Self {
+            payer,
+            signers: vec![],
+            rpc: rpc.clone(),
+            send_rpc: rpc.clone(),
+            fee_clients,
+            send_mode: SendMode::default(),
+            submission_options: SubmissionOptions::default(),
+            rpc_send_options: RpcSendOptions::default(),
+            slippage_mode: SlippageMode::default(),
+            skip_balance_check: false,
+            retry_fee_recipient_rotation: false,
+            proceeds: Proceeds::default(),
+            sell_floor: SellFloor::default(),
+            partial_fill: PartialFillGuard::default(),
+            buy_floor: BuyFloor::default(),
+            priority_fee,
+            trade_tag: None,
+            idempotency_key: None,
+            span: tracing::Span::none(),
+            cancel: None,
+            land_by_slot: None,
+            slot_source: Arc::new(TrackedSlots::new(rpc)),
+            drain: None,
+        }
+    }
+
+    /// Applies the fields set in `overrides`
+    pub fn with_overrides(mut self, overrides: TradeOverrides) -> Self {
+        if let Some(priority_fee) = overrides.priority_fee {
+            self.priority_fee = priority_fee;
+        }
+        if let Some(fee_clients) = overrides.fee_clients {
+            self.fee_clients = fee_clients;
+        }
+        if let Some(submission_options) = overrides.submission_options {
+            self.submission_options = submission_options;
+        }
+        if let Some(send_mode) = overrides.send_mode {
+            self.send_mode = send_mode;
+        }
+        if let Some(rpc_send_options) = overrides.rpc_send_options {
+            self.rpc_send_options = rpc_send_options;
+        }
+        if let Some(slippage_mode) = overrides.slippage_mode {
+            self.slippage_mode = slippage_mode;
+        }
+        if let Some(skip_balance_check) = overrides.skip_balance_check {
+            self.skip_balance_check = skip_balance_check;
+        }
+        if let Some(retry_fee_recipient_rotation) = overrides.retry_fee_recipient_rotation {
+            self.retry_fee_recipient_rotation = retry_fee_recipient_rotation;
+        }
+        if let Some(proceeds) = overrides.proceeds {
+            self.proceeds = proceeds;
+        }
+        if let Some(sell_floor) = overrides.sell_floor {
+            self.sell_floor = sell_floor;
+        }
+        if let Some(partial_fill) = overrides.partial_fill {
+            self.partial_fill = partial_fill;
+        }
+        if let Some(buy_floor) = overrides.buy_floor {
+            self.buy_floor = buy_floor;
+        }
+        if let Some(trade_tag) = overrides.trade_tag {
+            self.trade_tag = trade_tag;
+        }
+        if let Some(cancel) = overrides.cancel {
+            self.cancel = Some(cancel);
+        }
+        if let Some(idempotency_key) = overrides.idempotency_key {
+            self.idempotency_key = Some(idempotency_key);
+        }
+        if let Some(land_by_slot) = overrides.land_by_slot {
+            self.land_by_slot = Some(land_by_slot);
+        }
+        self
+    }
+
+    /// Sends and confirms the trade's transactions through `send_rpc` instead of `rpc`
+    pub fn with_send_rpc(mut self, send_rpc: Arc<SolanaRpcClient>) -> Self {
+        self.send_rpc = send_rpc;
+        self
+    }
+
+    pub fn with_span(mut self, span: tracing::Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Adds the trade tag memo, if any, after `extra_instructions`
+    pub fn tagged(&self, extra_instructions: Option<Vec<Instruction>>) -> Vec<Instruction> {
+        let mut instructions = extra_instructions.unwrap_or_default();
+        instructions.extend(self.trade_tag.as_ref().map(TradeTag::instruction));
+        instructions
+    }
+
+    /// Records the intent of a trade in the trade journal, filling in the tag and idempotency key
+    pub fn record_intent(&self, side: TradeSide, mint: Pubkey, amount_sol: Option<u64>, amount_token: Option<u64>, slippage_basis_points: Option<u64>) -> TradeId {
+        journal::record_intent(TradeIntent {
+            side,
+            mint,
+            amount_sol,
+            amount_token,
+            slippage_basis_points,
+            trade_tag: self.trade_tag.as_ref().map(|tag| tag.as_str().to_string()),
+            idempotency_key: self.idempotency_key.clone(),
+        })
+    }
+
+    /// Runs a step of the trade preceding its submission, unless the trade is cancelled first
+    pub async fn before_submission<T, E: Into<anyhow::Error>>(&self, step: impl Future<Output = Result<T, E>>) -> Result<T, anyhow::Error> {
+        cancel::before_submission(self.cancel.as_ref(), step).await
+    }
+
+    /// Sends `transactions[i]` through `fee_clients[i]` per `send_mode`, recording `Submit`
+    /// and `Confirm` stages per client on `timer` and journaling under `trade_id`
+    ///
+    /// Failures of individual clients are logged; the fastest client to land wins. Fails if
+    /// the trade is cancelled before any transaction is confirmed, if its `land_by_slot` passed
+    /// before sending, or with [`NotConfirmed`] naming each client's failure if no transaction
+    /// was confirmed; whether it landed in time is recorded on `timer`.
+    pub async fn submit(&self, timer: &mut StageTimer, trade_id: TradeId, transactions: Vec<VersionedTransaction>) -> Result<(), anyhow::Error> {
+        cancel::check(self.cancel.as_ref())?;
+        let transports = self.fee_clients.iter().map(|fee_client| fee_client.clone().transport(&self.submission_options)).collect();
+        let submitter = Submitter::new(transports, self.send_rpc.clone())
+            .with_trade_id(Some(trade_id))
+            .with_strategy(self.trade_tag.as_ref().map(|tag| tag.as_str().to_string()))
+            .with_cancellation(self.cancel.clone())
+            .with_slot_source(self.slot_source.clone())
+            .with_drain(self.drain.clone());
+        let options = SubmitOptions { mode: self.send_mode, rpc: self.rpc_send_options, land_by_slot: self.land_by_slot, ..Default::default() };
+        let results = submitter.submit(transactions, options).instrument(self.span.clone()).await;
+        if let Some(missed) = results.iter().find_map(|result| result.result.as_ref().err().and_then(DeadlineMissed::find)) {
+            return Err((*missed).into());
+        }
+        let deadlines = results.iter().filter_map(|result| result.deadline);
+        if let Some(deadline) = deadlines.reduce(|earliest, deadline| DeadlineOutcome { landed_slot: earliest_slot(earliest.landed_slot, deadline.landed_slot), ..earliest }) {
+            timer.set_deadline(deadline);
+        }
+
+        let _entered = self.span.enter();
+        let origin = timer.origin();
+        let confirmed = results.iter().any(|result| result.confirmed);
+        let not_confirmed = NotConfirmed::from_results(&results);
+        let unknown: Vec<Signature> = results.iter().filter(|result| result.cancelled).filter_map(SubmissionResult::signature).collect();
+        for result in results {
+            let relay_status = result.relay_status.map(|status| status.to_string());
+            match &result.result {
+                Ok(outcome) => tracing::debug!(
+                    client = %result.transport,
+                    signature = %outcome.signature,
+                    confirmed = result.confirmed,
+                    relay_status,
+                    relay_response = result.relay_response.as_deref(),
+                    "submitted"
+                ),
+                Err(e) => tracing::warn!(client = %result.transport, error = %e, relay_status, relay_response = result.relay_response.as_deref(), "submission failed"),
+            }
+            for timing in result.stage_timings(origin) {
+                timer.push(timing);
+            }
+        }
+
+        if !confirmed && !unknown.is_empty() {
+            return Err(Cancelled::AfterSubmission { signatures: unknown }.into());
+        }
+        match not_confirmed {
+            Some(not_confirmed) => Err(not_confirmed.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+fn earliest_slot(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_replace_only_set_fields() {
+        let rpc = Arc::new(SolanaRpcClient::new_mock("succeeds".to_string()));
+        let context = ExecutionContext::new(Arc::new(Keypair::new()), rpc, vec![], PriorityFee::default())
+            .with_overrides(TradeOverrides { send_mode: Some(SendMode::Fallback), trade_tag: Some(Some(TradeTag::new("bot-7").unwrap())), ..Default::default() });
+
+        assert_eq!(context.send_mode, SendMode::Fallback);
+        assert_eq!(context.priority_fee, PriorityFee::default());
+        assert_eq!(context.tagged(None).len(), 1);
+
+        let untagged = context.with_overrides(TradeOverrides { trade_tag: Some(None), ..Default::default() });
+        assert!(untagged.tagged(None).is_empty());
+    }
+}
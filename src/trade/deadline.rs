@@ -0,0 +1,159 @@
+//! Land-by-slot deadlines for snipes
+//!
+//! A trade given `land_by_slot` is only worth landing up to that slot. Before sending, the
+//! [`Submitter`](super::Submitter) reads the current slot from its [`SlotSource`] and fails
+//! with [`DeadlineMissed`] if the deadline already passed. Otherwise it sends without RPC
+//! rebroadcasts and waits for confirmation only as long as the remaining slots and a short
+//! grace allow. The trade's [`LatencyReport`](crate::common::latency::LatencyReport) then
+//! carries a [`DeadlineOutcome`] telling whether it landed in time.
+//!
+//! The current slot comes from the [`SlotTracker`], fed by the gRPC subscriptions with the
+//! slot of every update they receive, and from `getSlot` when nothing was received recently.
+
+use std::{
+    sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::common::SolanaRpcClient;
+
+/// Target duration of a slot
+pub const SLOT_DURATION: Duration = Duration::from_millis(400);
+
+/// How long confirmation is awaited past the deadline slot, for a transaction landed at the
+/// deadline to be confirmed
+pub const DEADLINE_CONFIRMATION_GRACE: Duration = Duration::from_millis(1_500);
+
+/// Age after which the slot of the last subscription update is no longer used
+pub const MAX_TRACKED_SLOT_AGE: Duration = Duration::from_millis(800);
+
+/// Where the current slot is read from
+#[async_trait::async_trait]
+pub trait SlotSource: Send + Sync {
+    async fn current_slot(&self) -> Result<u64, anyhow::Error>;
+}
+
+#[async_trait::async_trait]
+impl SlotSource for SolanaRpcClient {
+    async fn current_slot(&self) -> Result<u64, anyhow::Error> {
+        Ok(self.get_slot_with_commitment(CommitmentConfig::processed()).await?)
+    }
+}
+
+/// Latest slot seen by the subscriptions
+#[derive(Debug, Default)]
+pub struct SlotTracker {
+    slot: AtomicU64,
+    observed_at: Mutex<Option<Instant>>,
+}
+
+impl SlotTracker {
+    /// Records that an update of `slot` was received
+    pub fn observe(&self, slot: u64) {
+        if self.slot.fetch_max(slot, Ordering::Relaxed) <= slot {
+            *self.observed_at.lock().unwrap() = Some(Instant::now());
+        }
+    }
+
+    /// The latest slot seen, if it was seen within `max_age`
+    pub fn latest(&self, max_age: Duration) -> Option<u64> {
+        let observed_at = (*self.observed_at.lock().unwrap())?;
+        (observed_at.elapsed() <= max_age).then(|| self.slot.load(Ordering::Relaxed))
+    }
+}
+
+/// The tracker the gRPC subscriptions feed
+pub fn slot_tracker() -> &'static SlotTracker {
+    static TRACKER: OnceLock<SlotTracker> = OnceLock::new();
+    TRACKER.get_or_init(SlotTracker::default)
+}
+
+/// The [`slot_tracker`]'s slot while subscriptions keep it fresh, else `getSlot` on `rpc`
+pub struct TrackedSlots {
+    rpc: Arc<SolanaRpcClient>,
+}
+
+impl TrackedSlots {
+    pub fn new(rpc: Arc<SolanaRpcClient>) -> Self {
+        Self { rpc }
+    }
+}
+
+#[async_trait::async_trait]
+impl SlotSource for TrackedSlots {
+    async fn current_slot(&self) -> Result<u64, anyhow::Error> {
+        match slot_tracker().latest(MAX_TRACKED_SLOT_AGE) {
+            Some(slot) => Ok(slot),
+            None => self.rpc.current_slot().await,
+        }
+    }
+}
+
+/// The deadline passed before the trade was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Slot {current_slot} is past the deadline slot {land_by_slot}, not submitting")]
+pub struct DeadlineMissed {
+    pub land_by_slot: u64,
+    pub current_slot: u64,
+}
+
+impl DeadlineMissed {
+    /// The missed deadline somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&DeadlineMissed> {
+        error.chain().find_map(|cause| cause.downcast_ref::<DeadlineMissed>())
+    }
+}
+
+/// Whether a trade with a deadline landed in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadlineOutcome {
+    pub land_by_slot: u64,
+    /// Slot when the transactions were sent
+    pub submitted_at_slot: u64,
+    /// Earliest slot a transaction of the trade landed in, `None` if none was seen in time
+    pub landed_slot: Option<u64>,
+}
+
+impl DeadlineOutcome {
+    #[inline]
+    pub fn landed_in_time(&self) -> bool {
+        self.landed_slot.is_some_and(|slot| slot <= self.land_by_slot)
+    }
+}
+
+/// How long to wait for confirmation of transactions sent at `current_slot` that must land
+/// by `land_by_slot`
+pub fn confirmation_timeout(current_slot: u64, land_by_slot: u64) -> Duration {
+    let slots = land_by_slot.saturating_sub(current_slot) + 1;
+    SLOT_DURATION * slots as u32 + DEADLINE_CONFIRMATION_GRACE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracked_slot_expires_and_only_advances() {
+        let tracker = SlotTracker::default();
+        assert_eq!(tracker.latest(MAX_TRACKED_SLOT_AGE), None);
+
+        tracker.observe(100);
+        tracker.observe(98);
+        assert_eq!(tracker.latest(MAX_TRACKED_SLOT_AGE), Some(100));
+        assert_eq!(tracker.latest(Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_deadline_outcome_and_timeout() {
+        let outcome = DeadlineOutcome { land_by_slot: 102, submitted_at_slot: 100, landed_slot: Some(102) };
+        assert!(outcome.landed_in_time());
+        assert!(!DeadlineOutcome { landed_slot: Some(103), ..outcome }.landed_in_time());
+        assert!(!DeadlineOutcome { landed_slot: None, ..outcome }.landed_in_time());
+
+        assert_eq!(confirmation_timeout(100, 102), SLOT_DURATION * 3 + DEADLINE_CONFIRMATION_GRACE);
+        assert_eq!(confirmation_timeout(105, 102), SLOT_DURATION + DEADLINE_CONFIRMATION_GRACE);
+    }
+}
@@ -0,0 +1,156 @@
+//! Graceful shutdown of in-flight trades
+//!
+//! Once a [`Drain`] has begun, `PumpFun`'s buy, sell and create calls fail with
+//! [`ShuttingDown`], while trades admitted earlier keep sending and waiting for confirmation.
+//! The [`Submitter`](super::Submitter) registers the signatures it sends, so a drain that
+//! times out reports which transactions were left unresolved. `PumpFun::shutdown` drains
+//! before stopping subscriptions and background tasks.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use solana_sdk::signature::Signature;
+use tokio::sync::Notify;
+
+use crate::common::tasks::TaskRegistry;
+
+/// Default time `PumpFun::drain_complete` and `PumpFun::shutdown` wait for in-flight trades
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The client is draining and accepts no new trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Client is shutting down, not accepting new trades")]
+pub struct ShuttingDown;
+
+impl ShuttingDown {
+    /// Whether `error` was caused by a drain
+    pub fn find(error: &anyhow::Error) -> Option<&ShuttingDown> {
+        error.chain().find_map(|cause| cause.downcast_ref::<ShuttingDown>())
+    }
+}
+
+/// Trades were still in flight when the drain timed out
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Timed out after {timeout:?} with {trades} trades in flight and {} transactions unresolved", unresolved.len())]
+pub struct DrainTimedOut {
+    pub timeout: Duration,
+    /// Trades and submissions still running
+    pub trades: usize,
+    /// Signatures sent whose confirmation was still awaited
+    pub unresolved: Vec<Signature>,
+}
+
+#[derive(Default)]
+struct State {
+    draining: bool,
+    next_id: u64,
+    in_flight: HashMap<u64, Vec<Signature>>,
+}
+
+/// In-flight trades of a client and its clones
+#[derive(Default)]
+pub struct Drain {
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+impl Drain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops admitting trades; those in flight carry on
+    pub fn begin(&self) {
+        self.state.lock().unwrap().draining = true;
+        self.changed.notify_waiters();
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.state.lock().unwrap().draining
+    }
+
+    /// Admits a new trade, which counts as in flight until the guard is dropped
+    pub fn admit(self: &Arc<Self>) -> Result<InFlight, ShuttingDown> {
+        let mut state = self.state.lock().unwrap();
+        if state.draining {
+            return Err(ShuttingDown);
+        }
+        Ok(self.register(&mut state, vec![]))
+    }
+
+    /// Registers a submission of `signatures` until the guard is dropped; submissions of
+    /// admitted trades go on while draining
+    pub fn track(self: &Arc<Self>, signatures: Vec<Signature>) -> InFlight {
+        let mut state = self.state.lock().unwrap();
+        self.register(&mut state, signatures)
+    }
+
+    fn register(self: &Arc<Self>, state: &mut State, signatures: Vec<Signature>) -> InFlight {
+        let id = state.next_id;
+        state.next_id += 1;
+        state.in_flight.insert(id, signatures);
+        InFlight { drain: self.clone(), id }
+    }
+
+    /// Number of trades and submissions in flight
+    pub fn in_flight(&self) -> usize {
+        self.state.lock().unwrap().in_flight.len()
+    }
+
+    /// Waits until nothing is in flight, or fails after `timeout` with the signatures still
+    /// unresolved
+    pub async fn wait(&self, timeout: Duration) -> Result<(), DrainTimedOut> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            {
+                let state = self.state.lock().unwrap();
+                if state.in_flight.is_empty() {
+                    return Ok(());
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    let mut unresolved: Vec<Signature> = state.in_flight.values().flatten().copied().collect();
+                    unresolved.sort();
+                    unresolved.dedup();
+                    return Err(DrainTimedOut { timeout, trades: state.in_flight.len(), unresolved });
+                }
+            }
+            let _ = tokio::time::timeout_at(deadline, changed).await;
+        }
+    }
+
+    /// Begins draining, waits for the trades in flight, then stops `tasks`
+    ///
+    /// The tasks are stopped even if the drain timed out, in which case its error is returned.
+    pub(crate) async fn shutdown(&self, tasks: &TaskRegistry, drain_timeout: Duration, timeout: Duration) -> Result<(), anyhow::Error> {
+        self.begin();
+        let drained = self.wait(drain_timeout).await;
+        let stopped = tasks.shutdown(timeout).await;
+
+        drained?;
+        if !stopped {
+            return Err(anyhow::anyhow!("Timed out after {:?} waiting for background tasks to stop", timeout));
+        }
+        Ok(())
+    }
+}
+
+/// A trade or submission in flight, resolved when dropped
+pub struct InFlight {
+    drain: Arc<Drain>,
+    id: u64,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.drain.state.lock() {
+            state.in_flight.remove(&self.id);
+        }
+        self.drain.changed.notify_waiters();
+    }
+}
@@ -0,0 +1,357 @@
+//! Staged filters deciding whether to buy a new token
+//!
+//! A sniper runs each announced token through a [`FilterPipeline`]: cheap on-chain checks
+//! first, then stages that wait on the network such as a [`MetadataFilter`]. Every stage runs
+//! within a budget and falls back to a fixed decision when it overruns, so a slow gateway
+//! delays a buy by at most the budget. An [`AgeFilter`] skips tokens by their age in slots.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    common::{logs_data::CreateTokenInfo, SolanaRpcClient},
+    ipfs::{
+        prefetch::{MetadataPrefetcher, PrefetchError},
+        TokenMetadata,
+    },
+    pumpfun::token_age::{self, TokenAge},
+};
+
+use super::deadline::SlotSource;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    Pass,
+    /// Skip the token, with the reason
+    Reject(String),
+}
+
+impl FilterDecision {
+    #[inline]
+    pub fn passed(&self) -> bool {
+        matches!(self, FilterDecision::Pass)
+    }
+}
+
+/// One check of a new token
+#[async_trait::async_trait]
+pub trait FilterStage: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn evaluate(&self, token: &CreateTokenInfo) -> FilterDecision;
+}
+
+/// A stage checking only the announced token, e.g. its name or creator
+pub struct FnStage<F> {
+    name: String,
+    check: F,
+}
+
+impl<F> FnStage<F>
+where
+    F: Fn(&CreateTokenInfo) -> FilterDecision + Send + Sync,
+{
+    pub fn new(name: impl Into<String>, check: F) -> Self {
+        Self { name: name.into(), check }
+    }
+}
+
+#[async_trait::async_trait]
+impl<F> FilterStage for FnStage<F>
+where
+    F: Fn(&CreateTokenInfo) -> FilterDecision + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn evaluate(&self, token: &CreateTokenInfo) -> FilterDecision {
+        (self.check)(token)
+    }
+}
+
+/// What to do with a token whose metadata is not available within the deadline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataFallback {
+    /// Skip the token
+    #[default]
+    Skip,
+    /// Judge the token by the stages that did run
+    BuyBlind,
+}
+
+impl MetadataFallback {
+    fn decide(self, error: &PrefetchError) -> FilterDecision {
+        match self {
+            MetadataFallback::Skip => FilterDecision::Reject(format!("metadata unavailable: {}", error)),
+            MetadataFallback::BuyBlind => FilterDecision::Pass,
+        }
+    }
+}
+
+/// Checks a token's metadata, fetched through a [`MetadataPrefetcher`] within a deadline
+pub struct MetadataFilter<F> {
+    prefetcher: MetadataPrefetcher,
+    deadline: Duration,
+    fallback: MetadataFallback,
+    check: F,
+}
+
+impl<F> MetadataFilter<F>
+where
+    F: Fn(&CreateTokenInfo, &TokenMetadata) -> FilterDecision + Send + Sync,
+{
+    pub fn new(prefetcher: MetadataPrefetcher, deadline: Duration, check: F) -> Self {
+        Self { prefetcher, deadline, fallback: MetadataFallback::default(), check }
+    }
+
+    pub fn with_fallback(mut self, fallback: MetadataFallback) -> Self {
+        self.fallback = fallback;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<F> FilterStage for MetadataFilter<F>
+where
+    F: Fn(&CreateTokenInfo, &TokenMetadata) -> FilterDecision + Send + Sync,
+{
+    fn name(&self) -> &str {
+        "metadata"
+    }
+
+    async fn evaluate(&self, token: &CreateTokenInfo) -> FilterDecision {
+        match self.prefetcher.get(&token.uri, self.deadline).await {
+            Ok(metadata) => (self.check)(token, &metadata),
+            Err(e) => self.fallback.decide(&e),
+        }
+    }
+}
+
+/// Checks a token's age, read with [`token_age::get_token_age`]
+///
+/// The token's own slot, when the subscription set it, is recorded as its creation first, so
+/// live tokens are judged without reading their history. Tokens whose age can't be read are
+/// skipped.
+pub struct AgeFilter<F> {
+    rpc: Arc<SolanaRpcClient>,
+    slots: Option<Arc<dyn SlotSource>>,
+    check: F,
+}
+
+impl<F> AgeFilter<F>
+where
+    F: Fn(&CreateTokenInfo, &TokenAge) -> FilterDecision + Send + Sync,
+{
+    pub fn new(rpc: Arc<SolanaRpcClient>, check: F) -> Self {
+        Self { rpc, slots: None, check }
+    }
+
+    /// Reads the current slot from `slots` instead of the subscriptions and `rpc`
+    pub fn with_slot_source(mut self, slots: Arc<dyn SlotSource>) -> Self {
+        self.slots = Some(slots);
+        self
+    }
+}
+
+/// An [`AgeFilter`] passing tokens created at most `max_slots` slots ago
+pub fn younger_than(rpc: Arc<SolanaRpcClient>, max_slots: u64) -> AgeFilter<impl Fn(&CreateTokenInfo, &TokenAge) -> FilterDecision + Send + Sync> {
+    AgeFilter::new(rpc, move |_: &CreateTokenInfo, age: &TokenAge| match age.age_slots <= max_slots {
+        true => FilterDecision::Pass,
+        false => FilterDecision::Reject(format!("{} slots old", age.age_slots)),
+    })
+}
+
+#[async_trait::async_trait]
+impl<F> FilterStage for AgeFilter<F>
+where
+    F: Fn(&CreateTokenInfo, &TokenAge) -> FilterDecision + Send + Sync,
+{
+    fn name(&self) -> &str {
+        "age"
+    }
+
+    async fn evaluate(&self, token: &CreateTokenInfo) -> FilterDecision {
+        token_age::record_create_event(token);
+        let age = match &self.slots {
+            Some(slots) => token_age::get_token_age_with(&self.rpc, &token.mint, slots.as_ref()).await,
+            None => token_age::get_token_age(&self.rpc, &token.mint).await,
+        };
+        match age {
+            Ok(age) => (self.check)(token, &age),
+            Err(e) => FilterDecision::Reject(format!("token age unavailable: {}", e)),
+        }
+    }
+}
+
+/// How one stage of a pipeline went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageOutcome {
+    pub stage: String,
+    pub decision: FilterDecision,
+    pub elapsed: Duration,
+    /// Whether the stage overran its budget and `decision` is the stage's timeout decision
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterReport {
+    pub decision: FilterDecision,
+    /// The stages that ran, in order; stages after a rejection do not run
+    pub stages: Vec<StageOutcome>,
+}
+
+struct Budgeted {
+    stage: Arc<dyn FilterStage>,
+    budget: Option<Duration>,
+    on_timeout: FilterDecision,
+}
+
+/// Runs its stages in order until one rejects the token
+#[derive(Default)]
+pub struct FilterPipeline {
+    stages: Vec<Budgeted>,
+}
+
+impl FilterPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a stage without a budget
+    pub fn stage(mut self, stage: impl FilterStage + 'static) -> Self {
+        self.stages.push(Budgeted { stage: Arc::new(stage), budget: None, on_timeout: FilterDecision::Pass });
+        self
+    }
+
+    /// Adds a stage that decides `on_timeout` if it does not finish within `budget`
+    pub fn stage_with_budget(mut self, stage: impl FilterStage + 'static, budget: Duration, on_timeout: FilterDecision) -> Self {
+        self.stages.push(Budgeted { stage: Arc::new(stage), budget: Some(budget), on_timeout });
+        self
+    }
+
+    /// Runs the stages on `token`, stopping at the first rejection
+    pub async fn run(&self, token: &CreateTokenInfo) -> FilterReport {
+        let mut stages = Vec::with_capacity(self.stages.len());
+        for budgeted in &self.stages {
+            let started = Instant::now();
+            let (decision, timed_out) = match budgeted.budget {
+                Some(budget) => match tokio::time::timeout(budget, budgeted.stage.evaluate(token)).await {
+                    Ok(decision) => (decision, false),
+                    Err(_) => (budgeted.on_timeout.clone(), true),
+                },
+                None => (budgeted.stage.evaluate(token).await, false),
+            };
+            let rejected = !decision.passed();
+            stages.push(StageOutcome { stage: budgeted.stage.name().to_string(), decision, elapsed: started.elapsed(), timed_out });
+            if rejected {
+                break;
+            }
+        }
+        let decision = stages.last().map_or(FilterDecision::Pass, |outcome| outcome.decision.clone());
+        FilterReport { decision, stages }
+    }
+}
+
+#[async_trait::async_trait]
+impl FilterStage for FilterPipeline {
+    fn name(&self) -> &str {
+        "pipeline"
+    }
+
+    async fn evaluate(&self, token: &CreateTokenInfo) -> FilterDecision {
+        self.run(token).await.decision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct Slow {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl FilterStage for Slow {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        async fn evaluate(&self, _token: &CreateTokenInfo) -> FilterDecision {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            FilterDecision::Pass
+        }
+    }
+
+    fn token(name: &str) -> CreateTokenInfo {
+        CreateTokenInfo { name: name.to_string(), uri: "https://ipfs.io/ipfs/token".to_string(), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_stops_at_rejection_and_applies_timeout_decisions() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let named = |token: &CreateTokenInfo| match token.name.is_empty() {
+            true => FilterDecision::Reject("no name".to_string()),
+            false => FilterDecision::Pass,
+        };
+        let pipeline = FilterPipeline::new()
+            .stage(FnStage::new("name", named))
+            .stage_with_budget(Slow { calls: calls.clone() }, Duration::from_millis(20), FilterDecision::Reject("too slow".to_string()));
+
+        let report = pipeline.run(&token("")).await;
+        assert_eq!(report.decision, FilterDecision::Reject("no name".to_string()));
+        assert_eq!(report.stages.len(), 1);
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+        let report = pipeline.run(&token("pepe")).await;
+        assert_eq!(report.decision, FilterDecision::Reject("too slow".to_string()));
+        assert!(report.stages[1].timed_out && report.stages[1].elapsed < Duration::from_secs(5));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_metadata_falls_back() {
+        use crate::ipfs::prefetch::PrefetchConfig;
+
+        // a prefetcher that can start no fetch finds no metadata
+        let prefetcher = MetadataPrefetcher::new(PrefetchConfig { max_pending: 0, ..Default::default() }).unwrap();
+        let has_image = |_: &CreateTokenInfo, metadata: &TokenMetadata| match metadata.image.is_empty() {
+            true => FilterDecision::Reject("no image".to_string()),
+            false => FilterDecision::Pass,
+        };
+        let skip = MetadataFilter::new(prefetcher.clone(), Duration::from_millis(10), has_image);
+        assert!(!skip.evaluate(&token("pepe")).await.passed());
+        let blind = MetadataFilter::new(prefetcher, Duration::from_millis(10), has_image).with_fallback(MetadataFallback::BuyBlind);
+        assert!(blind.evaluate(&token("pepe")).await.passed());
+    }
+
+    struct FixedSlot(u64);
+
+    #[async_trait::async_trait]
+    impl SlotSource for FixedSlot {
+        async fn current_slot(&self) -> Result<u64, anyhow::Error> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_age_filter_judges_live_tokens_by_their_slot() {
+        let rpc = Arc::new(SolanaRpcClient::new_mock("fails".to_string()));
+        let young = younger_than(rpc.clone(), 20).with_slot_source(Arc::new(FixedSlot(1_010)));
+        let live = CreateTokenInfo { slot: 1_000, mint: solana_sdk::pubkey::Pubkey::new_unique(), ..token("pepe") };
+        assert!(young.evaluate(&live).await.passed());
+
+        let old = younger_than(rpc.clone(), 5).with_slot_source(Arc::new(FixedSlot(1_010)));
+        assert_eq!(old.evaluate(&live).await, FilterDecision::Reject("10 slots old".to_string()));
+
+        // unseen and its history unreadable
+        let unknown = CreateTokenInfo { mint: solana_sdk::pubkey::Pubkey::new_unique(), ..token("pepe") };
+        assert!(!young.evaluate(&unknown).await.passed());
+    }
+}
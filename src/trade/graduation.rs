@@ -0,0 +1,324 @@
+//! Selling positions before their bonding curve completes
+//!
+//! Once a curve completes, its liquidity migrates and the bonding curve sell path stops
+//! working. A [`GraduationGuard`] watches trades of tracked mints and sells the whole
+//! position through a [`PositionSeller`] once curve progress crosses the configured
+//! threshold. A mint whose curve completes before it is sold is marked graduated; selling it
+//! then needs the AMM, which the SDK does not support yet.
+//!
+//! Sells are made under a claim in the shared [`PositionLocks`], so other automated sellers
+//! using the same locks never sell the position at the same time. Outcomes of trades fed
+//! through [`GraduationGuard::on_event`] are logged and passed to the callback set with
+//! [`GraduationGuard::with_outcome_callback`].
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    accounts::{BondingCurveAccount, GlobalAccount},
+    common::{logs_data::TradeInfo, logs_events::PumpfunEvent},
+    PumpFun,
+};
+
+use super::position::PositionLocks;
+
+/// Default progress at which positions are sold, 95% of the curve
+pub const DEFAULT_SELL_AT_PROGRESS_BPS: u64 = 9_500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraduationConfig {
+    /// Curve progress, in basis points of the initial real token reserves sold, at which the
+    /// position is sold; `None` only reports graduation
+    pub sell_at_progress_bps: Option<u64>,
+}
+
+impl Default for GraduationConfig {
+    fn default() -> Self {
+        Self { sell_at_progress_bps: Some(DEFAULT_SELL_AT_PROGRESS_BPS) }
+    }
+}
+
+/// Share of the initial real token reserves sold, in basis points
+pub fn curve_progress_bps(real_token_reserves: u64, initial_real_token_reserves: u64) -> u64 {
+    if initial_real_token_reserves == 0 {
+        return 10_000;
+    }
+    let sold = initial_real_token_reserves.saturating_sub(real_token_reserves);
+    ((sold as u128) * 10_000 / initial_real_token_reserves as u128) as u64
+}
+
+/// Sells a whole position
+#[async_trait::async_trait]
+pub trait PositionSeller: Send + Sync {
+    async fn sell_all(&self, mint: Pubkey) -> Result<(), anyhow::Error>;
+}
+
+#[async_trait::async_trait]
+impl PositionSeller for PumpFun {
+    async fn sell_all(&self, mint: Pubkey) -> Result<(), anyhow::Error> {
+        let report = self.sell_by_percent_with_tip(mint, 100, None, None, None).await?;
+        if !report.confirmed() {
+            return Err(anyhow::anyhow!("Sell of mint {} was not confirmed", mint));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintStatus {
+    Tracking,
+    Sold,
+    Graduated,
+}
+
+#[derive(Debug)]
+pub enum GraduationOutcome {
+    /// The position was sold as progress crossed the threshold
+    Sold { mint: Pubkey, progress_bps: u64 },
+    /// Selling failed; the mint stays tracked and is retried on its next trade
+    SellFailed { mint: Pubkey, progress_bps: u64, error: anyhow::Error },
+    /// Another seller holds the position
+    Locked { mint: Pubkey },
+    /// The curve completed before the position was sold
+    Graduated { mint: Pubkey },
+}
+
+/// Watches tracked mints and sells them before their curve completes
+pub struct GraduationGuard {
+    config: GraduationConfig,
+    initial_real_token_reserves: u64,
+    seller: Arc<dyn PositionSeller>,
+    locks: PositionLocks,
+    mints: Mutex<HashMap<Pubkey, MintStatus>>,
+    on_outcome: Option<Box<dyn Fn(GraduationOutcome) + Send + Sync>>,
+}
+
+impl GraduationGuard {
+    pub fn new(config: GraduationConfig, global_account: &GlobalAccount, seller: Arc<dyn PositionSeller>, locks: PositionLocks) -> Self {
+        Self {
+            config,
+            initial_real_token_reserves: global_account.initial_real_token_reserves,
+            seller,
+            locks,
+            mints: Mutex::new(HashMap::new()),
+            on_outcome: None,
+        }
+    }
+
+    /// Passes the outcomes of trades fed through [`on_event`](Self::on_event) to `callback`
+    pub fn with_outcome_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(GraduationOutcome) + Send + Sync + 'static,
+    {
+        self.on_outcome = Some(Box::new(callback));
+        self
+    }
+
+    /// Starts watching `mint`; mints already sold or graduated keep their status
+    pub fn track(&self, mint: Pubkey) {
+        self.mints.lock().unwrap().entry(mint).or_insert(MintStatus::Tracking);
+    }
+
+    pub fn untrack(&self, mint: &Pubkey) {
+        self.mints.lock().unwrap().remove(mint);
+    }
+
+    pub fn status(&self, mint: &Pubkey) -> Option<MintStatus> {
+        self.mints.lock().unwrap().get(mint).copied()
+    }
+
+    /// Checks a trade of a tracked mint, selling the position if it crossed the threshold
+    pub async fn on_trade(&self, trade: &TradeInfo) -> Option<GraduationOutcome> {
+        self.evaluate(trade.mint, trade.real_token_reserves, false).await
+    }
+
+    /// Checks the bonding curve account of a tracked mint, for mints without recent trades
+    pub async fn on_curve(&self, mint: Pubkey, curve: &BondingCurveAccount) -> Option<GraduationOutcome> {
+        self.evaluate(mint, curve.real_token_reserves, curve.complete).await
+    }
+
+    /// Feeds trade events from a subscription callback, checking each in its own task and
+    /// reporting outcomes to the outcome callback
+    pub fn on_event(self: &Arc<Self>, event: &PumpfunEvent) {
+        let trade = match event {
+            PumpfunEvent::NewDevTrade(trade) | PumpfunEvent::NewUserTrade(trade) | PumpfunEvent::NewBotTrade(trade) => trade.clone(),
+            _ => return,
+        };
+        if self.status(&trade.mint) != Some(MintStatus::Tracking) {
+            return;
+        }
+
+        let guard = self.clone();
+        tokio::spawn(async move {
+            if let Some(outcome) = guard.on_trade(&trade).await {
+                guard.report(outcome);
+            }
+        });
+    }
+
+    async fn evaluate(&self, mint: Pubkey, real_token_reserves: u64, complete: bool) -> Option<GraduationOutcome> {
+        if self.status(&mint)? != MintStatus::Tracking {
+            return None;
+        }
+        if complete || real_token_reserves == 0 {
+            self.set_status(mint, MintStatus::Graduated);
+            return Some(GraduationOutcome::Graduated { mint });
+        }
+
+        let progress_bps = curve_progress_bps(real_token_reserves, self.initial_real_token_reserves);
+        if progress_bps < self.config.sell_at_progress_bps? {
+            return None;
+        }
+
+        let Some(_claim) = self.locks.try_claim(mint) else {
+            return Some(GraduationOutcome::Locked { mint });
+        };
+        // Another trade of this mint may have sold it before the claim was taken
+        if self.status(&mint)? != MintStatus::Tracking {
+            return None;
+        }
+
+        match self.seller.sell_all(mint).await {
+            Ok(()) => {
+                self.set_status(mint, MintStatus::Sold);
+                Some(GraduationOutcome::Sold { mint, progress_bps })
+            }
+            Err(error) => Some(GraduationOutcome::SellFailed { mint, progress_bps, error }),
+        }
+    }
+
+    fn report(&self, outcome: GraduationOutcome) {
+        match &outcome {
+            GraduationOutcome::SellFailed { mint, progress_bps, error } => tracing::warn!(%mint, progress_bps, %error, "graduation sell failed"),
+            outcome => tracing::info!(?outcome, "graduation guard"),
+        }
+        if let Some(on_outcome) = &self.on_outcome {
+            on_outcome(outcome);
+        }
+    }
+
+    fn set_status(&self, mint: Pubkey, status: MintStatus) {
+        if let Some(current) = self.mints.lock().unwrap().get_mut(&mint) {
+            *current = status;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use super::*;
+
+    const INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
+
+    #[derive(Default)]
+    struct MockSeller {
+        sold: AtomicUsize,
+        fails: AtomicBool,
+    }
+
+    #[async_trait::async_trait]
+    impl PositionSeller for MockSeller {
+        async fn sell_all(&self, _mint: Pubkey) -> Result<(), anyhow::Error> {
+            if self.fails.load(Ordering::Relaxed) {
+                return Err(anyhow::anyhow!("sell failed"));
+            }
+            self.sold.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn guard(seller: Arc<MockSeller>, locks: PositionLocks) -> GraduationGuard {
+        let global = GlobalAccount::new(
+            1, true, Pubkey::new_unique(), Pubkey::new_unique(),
+            1_073_000_000_000_000, 30_000_000_000, INITIAL_REAL_TOKEN_RESERVES, 1_000_000_000_000_000, 100,
+        );
+        GraduationGuard::new(GraduationConfig::default(), &global, seller, locks)
+    }
+
+    fn trade(mint: Pubkey, progress_bps: u64) -> TradeInfo {
+        let sold = (INITIAL_REAL_TOKEN_RESERVES as u128 * progress_bps as u128 / 10_000) as u64;
+        TradeInfo { mint, real_token_reserves: INITIAL_REAL_TOKEN_RESERVES - sold, ..Default::default() }
+    }
+
+    #[test]
+    fn test_curve_progress() {
+        assert_eq!(curve_progress_bps(INITIAL_REAL_TOKEN_RESERVES, INITIAL_REAL_TOKEN_RESERVES), 0);
+        assert_eq!(curve_progress_bps(INITIAL_REAL_TOKEN_RESERVES / 2, INITIAL_REAL_TOKEN_RESERVES), 5_000);
+        assert_eq!(curve_progress_bps(0, INITIAL_REAL_TOKEN_RESERVES), 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_sells_tracked_mint_once_past_threshold() {
+        let seller = Arc::new(MockSeller::default());
+        let guard = guard(seller.clone(), PositionLocks::new());
+        let (mint, untracked) = (Pubkey::new_unique(), Pubkey::new_unique());
+        guard.track(mint);
+
+        assert!(guard.on_trade(&trade(mint, 9_000)).await.is_none());
+        assert!(guard.on_trade(&trade(untracked, 9_900)).await.is_none());
+
+        let outcome = guard.on_trade(&trade(mint, 9_600)).await.unwrap();
+        assert!(matches!(outcome, GraduationOutcome::Sold { progress_bps: 9_600, .. }));
+        assert_eq!(guard.status(&mint), Some(MintStatus::Sold));
+
+        assert!(guard.on_trade(&trade(mint, 9_800)).await.is_none());
+        assert_eq!(seller.sold.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_sell_position_claimed_elsewhere() {
+        let seller = Arc::new(MockSeller::default());
+        let locks = PositionLocks::new();
+        let guard = guard(seller.clone(), locks.clone());
+        let mint = Pubkey::new_unique();
+        guard.track(mint);
+
+        // e.g. a take-profit watcher selling the same position
+        let claim = locks.try_claim(mint).unwrap();
+        assert!(matches!(guard.on_trade(&trade(mint, 9_600)).await, Some(GraduationOutcome::Locked { .. })));
+        assert_eq!(seller.sold.load(Ordering::Relaxed), 0);
+
+        drop(claim);
+        assert!(matches!(guard.on_trade(&trade(mint, 9_600)).await, Some(GraduationOutcome::Sold { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_failed_sell_is_retried_and_completion_graduates() {
+        let seller = Arc::new(MockSeller::default());
+        seller.fails.store(true, Ordering::Relaxed);
+        let guard = guard(seller.clone(), PositionLocks::new());
+        let mint = Pubkey::new_unique();
+        guard.track(mint);
+
+        assert!(matches!(guard.on_trade(&trade(mint, 9_700)).await, Some(GraduationOutcome::SellFailed { .. })));
+        assert_eq!(guard.status(&mint), Some(MintStatus::Tracking));
+
+        let curve = BondingCurveAccount::new(0, 0, 0, 0, 0, 0, true);
+        assert!(matches!(guard.on_curve(mint, &curve).await, Some(GraduationOutcome::Graduated { .. })));
+        assert_eq!(guard.status(&mint), Some(MintStatus::Graduated));
+        assert!(guard.on_trade(&trade(mint, 9_900)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_event_outcomes_reach_the_callback() {
+        let seller = Arc::new(MockSeller::default());
+        seller.fails.store(true, Ordering::Relaxed);
+        let (sender, mut outcomes) = tokio::sync::mpsc::unbounded_channel();
+        let guard = Arc::new(guard(seller, PositionLocks::new()).with_outcome_callback(move |outcome| {
+            let _ = sender.send(outcome);
+        }));
+        let mint = Pubkey::new_unique();
+        guard.track(mint);
+
+        guard.on_event(&PumpfunEvent::NewUserTrade(trade(mint, 9_700)));
+        let outcome = tokio::time::timeout(std::time::Duration::from_secs(1), outcomes.recv()).await.unwrap().unwrap();
+        assert!(matches!(outcome, GraduationOutcome::SellFailed { mint: failed, .. } if failed == mint));
+        assert_eq!(guard.status(&mint), Some(MintStatus::Tracking));
+    }
+}
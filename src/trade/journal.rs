@@ -0,0 +1,316 @@
+//! Durable record of every transaction the SDK sends
+//!
+//! With a journal installed through [`set_trade_journal`], each trade records its intent when
+//! it is built, and the [`Submitter`](super::Submitter) records every send attempt and its
+//! resolution. All SDK trades, RPC or fee client, go through the submitter, so none of them
+//! bypass the journal. Transactions sent directly through a `FeeClientTrait` are not recorded.
+//!
+//! Records are handed to the journal without waiting; [`JsonlJournal`] writes them to a file,
+//! one JSON object per line, from a background task.
+
+use std::{
+    io,
+    path::Path,
+    sync::{atomic::{AtomicU64, Ordering}, Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signature, system_program, transaction::VersionedTransaction};
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncWriteExt, BufWriter},
+    sync::{mpsc, oneshot},
+};
+
+use crate::{
+    common::user_agent::SDK_VERSION,
+    constants::accounts::{JITO_TIP_ACCOUNTS, MEMO_PROGRAM, NEXTBLOCK_TIP_ACCOUNTS, ZEROSLOT_TIP_ACCOUNTS},
+    fee_clients::ClientType,
+};
+
+lazy_static::lazy_static! {
+    static ref TRADE_JOURNAL: RwLock<Option<Arc<dyn TradeJournal>>> = RwLock::new(None);
+    static ref TRADE_ID_PREFIX: String = format!("{:x}", now_ms());
+}
+
+static NEXT_TRADE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Receives journal records; implementations must not block the caller
+pub trait TradeJournal: Send + Sync {
+    fn record(&self, record: JournalRecord);
+}
+
+/// Installs `journal` for all clients, or removes it with `None`
+pub fn set_trade_journal(journal: Option<Arc<dyn TradeJournal>>) {
+    *TRADE_JOURNAL.write().unwrap() = journal;
+}
+
+pub fn trade_journal() -> Option<Arc<dyn TradeJournal>> {
+    TRADE_JOURNAL.read().unwrap().clone()
+}
+
+/// Identifies the records of one trade; unique within the process and across restarts
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TradeId(pub String);
+
+impl TradeId {
+    pub fn new() -> Self {
+        Self(format!("{}-{}", *TRADE_ID_PREFIX, NEXT_TRADE_ID.fetch_add(1, Ordering::Relaxed)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+    Create,
+    CreateAndBuy,
+}
+
+/// What a trade was built to do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TradeIntent {
+    pub side: TradeSide,
+    #[serde(with = "as_string")]
+    pub mint: Pubkey,
+    pub amount_sol: Option<u64>,
+    /// Tokens to sell, `None` for the whole balance
+    pub amount_token: Option<u64>,
+    pub slippage_basis_points: Option<u64>,
+    pub trade_tag: Option<String>,
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Intent(TradeIntent),
+    /// A transport was handed the transaction; `error` is set if it refused it
+    Submitted {
+        #[serde(with = "as_string")]
+        signature: Signature,
+        transport: ClientType,
+        tip_lamports: Option<u64>,
+        error: Option<String>,
+    },
+    /// Confirmation of an accepted transaction finished
+    Resolved {
+        #[serde(with = "as_string")]
+        signature: Signature,
+        transport: ClientType,
+        confirmed: bool,
+        landed_slot: Option<u64>,
+        error: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalRecord {
+    /// Unix time in milliseconds
+    pub at_ms: u64,
+    /// `None` for transactions sent outside a trade, e.g. token account pre-creation
+    pub trade_id: Option<TradeId>,
+    /// Version of the SDK that wrote the record, empty in records of versions before it was kept
+    #[serde(default)]
+    pub sdk_version: String,
+    #[serde(flatten)]
+    pub entry: JournalEntry,
+}
+
+/// Records `entry` in the installed journal, if any
+pub fn record(trade_id: Option<&TradeId>, entry: JournalEntry) {
+    if let Some(journal) = trade_journal() {
+        journal.record(JournalRecord { at_ms: now_ms(), trade_id: trade_id.cloned(), sdk_version: SDK_VERSION.to_string(), entry });
+    }
+}
+
+/// Records the intent of a new trade and returns its id
+pub fn record_intent(intent: TradeIntent) -> TradeId {
+    let trade_id = TradeId::new();
+    super::own_events::track_intent(&trade_id, intent.mint, intent.side);
+    record(Some(&trade_id), JournalEntry::Intent(intent));
+    trade_id
+}
+
+/// Text of the last memo among `instructions`, which is where the trade tag is appended
+pub fn memo_text(instructions: &[Instruction]) -> Option<String> {
+    instructions
+        .iter()
+        .rev()
+        .find(|instruction| instruction.program_id == MEMO_PROGRAM)
+        .and_then(|instruction| String::from_utf8(instruction.data.clone()).ok())
+}
+
+/// Lamports `transaction` transfers to a Jito, NextBlock or 0slot tip account
+pub fn tip_lamports(transaction: &VersionedTransaction) -> Option<u64> {
+    const TRANSFER_TAG: [u8; 4] = 2u32.to_le_bytes();
+
+    let keys = transaction.message.static_account_keys();
+    let tips: u64 = transaction
+        .message
+        .instructions()
+        .iter()
+        .filter(|instruction| keys.get(instruction.program_id_index as usize) == Some(&system_program::id()))
+        .filter(|instruction| instruction.data.len() == 12 && instruction.data[..4] == TRANSFER_TAG)
+        .filter(|instruction| {
+            let destination = instruction.accounts.get(1).and_then(|&index| keys.get(index as usize));
+            destination.is_some_and(is_tip_account)
+        })
+        .map(|instruction| u64::from_le_bytes(instruction.data[4..12].try_into().unwrap()))
+        .sum();
+    (tips > 0).then_some(tips)
+}
+
+fn is_tip_account(account: &Pubkey) -> bool {
+    let account = account.to_string();
+    JITO_TIP_ACCOUNTS.iter().chain(NEXTBLOCK_TIP_ACCOUNTS).chain(ZEROSLOT_TIP_ACCOUNTS).any(|tip| *tip == account)
+}
+
+/// Base58 strings instead of byte arrays for keys and signatures
+mod as_string {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?.parse().map_err(Error::custom)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_millis() as u64).unwrap_or_default()
+}
+
+enum Command {
+    Write(JournalRecord),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Appends records to a JSONL file from a background task
+///
+/// Records are buffered and flushed whenever the queue runs empty. Write errors are logged
+/// and the record is dropped; the task ends once the journal is dropped.
+pub struct JsonlJournal {
+    sender: mpsc::UnboundedSender<Command>,
+}
+
+impl JsonlJournal {
+    /// Opens `path` for appending, creating it if needed; must be called within a tokio runtime
+    pub async fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path).await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(write_records(BufWriter::new(file), receiver));
+        Ok(Self { sender })
+    }
+
+    /// Waits until every record handed over so far is written to the file
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        if self.sender.send(Command::Flush(done)).is_ok() {
+            let _ = flushed.await;
+        }
+    }
+}
+
+impl TradeJournal for JsonlJournal {
+    fn record(&self, record: JournalRecord) {
+        let _ = self.sender.send(Command::Write(record));
+    }
+}
+
+async fn write_records(mut writer: BufWriter<tokio::fs::File>, mut receiver: mpsc::UnboundedReceiver<Command>) {
+    let mut waiting = vec![];
+    while let Some(command) = receiver.recv().await {
+        let mut next = Some(command);
+        while let Some(command) = next {
+            match command {
+                Command::Write(record) => {
+                    let mut line = serde_json::to_vec(&record).expect("journal records serialize");
+                    line.push(b'\n');
+                    if let Err(e) = writer.write_all(&line).await {
+                        tracing::warn!(error = %e, "failed to write trade journal record");
+                    }
+                }
+                Command::Flush(done) => waiting.push(done),
+            }
+            next = receiver.try_recv().ok();
+        }
+
+        if let Err(e) = writer.flush().await {
+            tracing::warn!(error = %e, "failed to flush trade journal");
+        }
+        for done in waiting.drain(..) {
+            let _ = done.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_hash::Hash;
+    use solana_sdk::{signature::Keypair, signer::Signer, system_instruction, transaction::Transaction};
+
+    use super::*;
+
+    #[test]
+    fn test_tip_is_read_from_transfers_to_tip_accounts() {
+        let payer = Keypair::new();
+        let tip_account: Pubkey = JITO_TIP_ACCOUNTS[0].parse().unwrap();
+        let instructions = [
+            system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 5),
+            system_instruction::transfer(&payer.pubkey(), &tip_account, 1_000),
+        ];
+        let transaction: VersionedTransaction = Transaction::new_signed_with_payer(&instructions, Some(&payer.pubkey()), &[&payer], Hash::default()).into();
+        assert_eq!(tip_lamports(&transaction), Some(1_000));
+
+        let untipped: VersionedTransaction = Transaction::new_signed_with_payer(&instructions[..1], Some(&payer.pubkey()), &[&payer], Hash::default()).into();
+        assert_eq!(tip_lamports(&untipped), None);
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_journal_appends_records() {
+        let path = std::env::temp_dir().join(format!("pumpfun-journal-{}.jsonl", TradeId::new().0));
+        let journal = JsonlJournal::open(&path).await.unwrap();
+
+        let trade_id = TradeId::new();
+        let intent = TradeIntent {
+            side: TradeSide::Buy,
+            mint: Pubkey::new_unique(),
+            amount_sol: Some(10_000),
+            amount_token: None,
+            slippage_basis_points: Some(500),
+            trade_tag: Some("bot-7".to_string()),
+            idempotency_key: Some("order-42".to_string()),
+        };
+        journal.record(JournalRecord { at_ms: 1, trade_id: Some(trade_id.clone()), sdk_version: SDK_VERSION.to_string(), entry: JournalEntry::Intent(intent.clone()) });
+        journal.record(JournalRecord {
+            at_ms: 2,
+            trade_id: Some(trade_id.clone()),
+            sdk_version: String::new(),
+            entry: JournalEntry::Submitted { signature: Signature::default(), transport: ClientType::Jito, tip_lamports: Some(1_000), error: None },
+        });
+        journal.flush().await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<JournalRecord> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].entry, JournalEntry::Intent(intent));
+        assert_eq!(records[0].sdk_version, SDK_VERSION);
+        assert!(content.lines().nth(1).unwrap().contains(r#""event":"submitted""#));
+        assert!(content.contains(r#""transport":"Jito""#));
+        assert!(content.contains(&format!(r#""signature":"{}""#, Signature::default())));
+    }
+}
@@ -0,0 +1,263 @@
+//! At most one buy attempt per mint
+//!
+//! The same token can reach a bot through several event sources milliseconds apart, and
+//! per-source dedup misses it when the copies carry different keys. A [`MintOnceGuard`]
+//! remembers every mint a buy was attempted for; `PumpFun::buy` and `buy_with_tip` claim the
+//! mint in the client's guard, set with `PumpFun::with_mint_guard`, before building anything.
+//! The check and the claim happen under one lock, so concurrent deliveries can't both pass.
+//!
+//! With a [`MintGuardStore`] the attempted mints survive restarts; [`FileMintStore`] keeps
+//! them in a file, one mint per line. Changes are handed to the store on a background thread,
+//! so claiming a mint never waits for the disk.
+
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use solana_sdk::pubkey::Pubkey;
+
+/// Persists the attempted mints of a [`MintOnceGuard`]; `insert` and `remove` are called from
+/// a background thread, in the order of the changes
+pub trait MintGuardStore: Send + Sync {
+    fn load(&self) -> Result<HashSet<Pubkey>, anyhow::Error>;
+    fn insert(&self, mint: &Pubkey) -> Result<(), anyhow::Error>;
+    fn remove(&self, mint: &Pubkey) -> Result<(), anyhow::Error>;
+}
+
+enum StoreOp {
+    Insert(Pubkey),
+    Remove(Pubkey),
+    Flush(mpsc::Sender<()>),
+}
+
+/// Mints a bot has attempted to buy, shared by every clone
+#[derive(Clone, Default)]
+pub struct MintOnceGuard {
+    attempted: Arc<Mutex<HashSet<Pubkey>>>,
+    writer: Option<mpsc::Sender<StoreOp>>,
+}
+
+impl MintOnceGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A guard starting with the mints in `store`, persisting every change to it from a
+    /// background thread that ends once the guard and its clones are dropped
+    pub fn with_store(store: Arc<dyn MintGuardStore>) -> Result<Self, anyhow::Error> {
+        let attempted = store.load()?;
+        let (writer, receiver) = mpsc::channel();
+        thread::Builder::new().name("mint-guard-store".to_string()).spawn(move || write_changes(store, receiver))?;
+        Ok(Self { attempted: Arc::new(Mutex::new(attempted)), writer: Some(writer) })
+    }
+
+    /// Claims `mint` for a buy attempt; `false` if it was attempted before
+    ///
+    /// The claim is persisted in the background. A mint that fails to persist stays claimed
+    /// in memory, since a second buy costs more than a lost record.
+    pub fn try_acquire(&self, mint: Pubkey) -> bool {
+        let mut attempted = self.attempted.lock().unwrap();
+        if !attempted.insert(mint) {
+            return false;
+        }
+        if let Some(writer) = &self.writer {
+            let _ = writer.send(StoreOp::Insert(mint));
+        }
+        true
+    }
+
+    /// Forgets `mint`, allowing another buy attempt
+    ///
+    /// The store forgets it in the background; [`flush`](Self::flush) waits for that.
+    pub fn release(&self, mint: &Pubkey) {
+        let mut attempted = self.attempted.lock().unwrap();
+        if attempted.remove(mint) {
+            if let Some(writer) = &self.writer {
+                let _ = writer.send(StoreOp::Remove(*mint));
+            }
+        }
+    }
+
+    /// Waits until every change made so far is handed to the store, blocking the thread; meant
+    /// for shutdown rather than async trading paths
+    pub fn flush(&self) {
+        if let Some(writer) = &self.writer {
+            let (done, flushed) = mpsc::channel();
+            if writer.send(StoreOp::Flush(done)).is_ok() {
+                let _ = flushed.recv();
+            }
+        }
+    }
+
+    pub fn is_attempted(&self, mint: &Pubkey) -> bool {
+        self.attempted.lock().unwrap().contains(mint)
+    }
+
+    pub fn len(&self) -> usize {
+        self.attempted.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+fn write_changes(store: Arc<dyn MintGuardStore>, receiver: mpsc::Receiver<StoreOp>) {
+    for op in receiver {
+        match op {
+            StoreOp::Insert(mint) => {
+                if let Err(e) = store.insert(&mint) {
+                    tracing::warn!("Failed to persist attempted mint {}: {}", mint, e);
+                }
+            }
+            StoreOp::Remove(mint) => {
+                if let Err(e) = store.remove(&mint) {
+                    tracing::warn!("Failed to forget released mint {}: {}", mint, e);
+                }
+            }
+            StoreOp::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// Stores the mints in a file, one per line; releasing a mint rewrites the file
+#[derive(Debug, Clone)]
+pub struct FileMintStore {
+    path: PathBuf,
+}
+
+impl FileMintStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self { path: path.as_ref().to_path_buf() }
+    }
+}
+
+impl MintGuardStore for FileMintStore {
+    fn load(&self) -> Result<HashSet<Pubkey>, anyhow::Error> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(e) => return Err(e.into()),
+        };
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| Pubkey::from_str(line).map_err(|e| anyhow::anyhow!("Invalid mint {:?} in {}: {}", line, self.path.display(), e)))
+            .collect()
+    }
+
+    fn insert(&self, mint: &Pubkey) -> Result<(), anyhow::Error> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", mint)?;
+        Ok(())
+    }
+
+    fn remove(&self, mint: &Pubkey) -> Result<(), anyhow::Error> {
+        let mut mints = self.load()?;
+        mints.remove(mint);
+        let contents: String = mints.iter().map(|mint| format!("{}\n", mint)).collect();
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, contents)?;
+        fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Barrier, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_duplicate_deliveries_from_two_sources_buy_once() {
+        let guard = MintOnceGuard::new();
+        for _ in 0..100 {
+            let mint = Pubkey::new_unique();
+            let barrier = Arc::new(Barrier::new(2));
+            let deliveries: Vec<_> = ["shredstream", "grpc"]
+                .into_iter()
+                .map(|_| {
+                    let (guard, barrier) = (guard.clone(), barrier.clone());
+                    thread::spawn(move || {
+                        // Both deliveries arrive within microseconds of each other
+                        barrier.wait();
+                        guard.try_acquire(mint)
+                    })
+                })
+                .collect();
+            let acquired = deliveries.into_iter().map(|delivery| delivery.join().unwrap()).filter(|acquired| *acquired).count();
+            assert_eq!(acquired, 1);
+        }
+    }
+
+    #[test]
+    fn test_release_allows_another_attempt() {
+        let guard = MintOnceGuard::new();
+        let mint = Pubkey::new_unique();
+
+        assert!(guard.try_acquire(mint));
+        assert!(!guard.clone().try_acquire(mint));
+        guard.release(&mint);
+        assert!(guard.try_acquire(mint));
+    }
+
+    /// Store whose inserts wait until the test lets them through
+    struct SlowStore(Mutex<mpsc::Receiver<()>>);
+
+    impl MintGuardStore for SlowStore {
+        fn load(&self) -> Result<HashSet<Pubkey>, anyhow::Error> {
+            Ok(HashSet::new())
+        }
+
+        fn insert(&self, _: &Pubkey) -> Result<(), anyhow::Error> {
+            self.0.lock().unwrap().recv()?;
+            Ok(())
+        }
+
+        fn remove(&self, _: &Pubkey) -> Result<(), anyhow::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_acquire_does_not_wait_for_the_store() {
+        let (proceed, wait) = mpsc::channel();
+        let guard = MintOnceGuard::with_store(Arc::new(SlowStore(Mutex::new(wait)))).unwrap();
+
+        let mint = Pubkey::new_unique();
+        assert!(guard.try_acquire(mint));
+        assert!(!guard.try_acquire(mint));
+        assert!(guard.try_acquire(Pubkey::new_unique()));
+
+        proceed.send(()).unwrap();
+        proceed.send(()).unwrap();
+        guard.flush();
+    }
+
+    #[test]
+    fn test_file_store_survives_restarts() {
+        let path = std::env::temp_dir().join(format!("pumpfun-mints-{}.txt", Pubkey::new_unique()));
+        let (bought, released) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let guard = MintOnceGuard::with_store(Arc::new(FileMintStore::new(&path))).unwrap();
+        assert!(guard.try_acquire(bought));
+        assert!(guard.try_acquire(released));
+        guard.release(&released);
+        guard.flush();
+
+        let restarted = MintOnceGuard::with_store(Arc::new(FileMintStore::new(&path))).unwrap();
+        assert!(!restarted.try_acquire(bought));
+        assert!(restarted.try_acquire(released));
+        fs::remove_file(&path).unwrap();
+    }
+}
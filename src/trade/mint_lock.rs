@@ -0,0 +1,180 @@
+//! One trade at a time per payer and mint
+//!
+//! Two sells of the same mint built at once read the same balance, so one of them fails, or
+//! the first closes the token account under the second. `PumpFun`'s buys and sells take the
+//! lock of their payer and mint in the client's [`MintLocks`], shared by its clones, before
+//! quoting. The lock is held while the trade builds and sends its transactions and is
+//! released by the [`Submitter`](super::Submitter) once they are sent, so the next trade
+//! builds while the previous one is being confirmed. A trade sending several transactions
+//! one after the other, such as a split buy or a retry on a rotated fee recipient, only
+//! holds it until the first is sent.
+//!
+//! A trade that can't take the lock within the client's timeout fails with [`MintBusy`]
+//! without sending anything. Locks nobody holds or waits for are dropped from the registry.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use solana_sdk::pubkey::Pubkey;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Default time a trade waits for another trade of the same mint to send
+pub const DEFAULT_MINT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Registry size below which unused locks are not swept
+const MIN_SWEEP_AT: usize = 256;
+
+tokio::task_local! {
+    static HELD: RefCell<Option<MintLockGuard>>;
+}
+
+/// Another trade of the same payer and mint kept the lock for the whole timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Another trade of {mint} by {payer} is still sending after {waited:?}")]
+pub struct MintBusy {
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub waited: Duration,
+}
+
+impl MintBusy {
+    /// Whether `error` was caused by a busy mint
+    pub fn find(error: &anyhow::Error) -> Option<&MintBusy> {
+        error.chain().find_map(|cause| cause.downcast_ref::<MintBusy>())
+    }
+}
+
+struct Registry {
+    locks: HashMap<(Pubkey, Pubkey), Weak<AsyncMutex<()>>>,
+    sweep_at: usize,
+}
+
+/// Trade locks per payer and mint, shared by every clone
+#[derive(Clone)]
+pub struct MintLocks {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Default for MintLocks {
+    fn default() -> Self {
+        Self { registry: Arc::new(Mutex::new(Registry { locks: HashMap::new(), sweep_at: MIN_SWEEP_AT })) }
+    }
+}
+
+impl MintLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the lock of `payer` and `mint`, failing with [`MintBusy`] after `timeout`
+    pub async fn acquire(&self, payer: Pubkey, mint: Pubkey, timeout: Duration) -> Result<MintLockGuard, MintBusy> {
+        let lock = self.lock_for(payer, mint);
+        match tokio::time::timeout(timeout, lock.lock_owned()).await {
+            Ok(guard) => Ok(MintLockGuard { _guard: guard }),
+            Err(_) => Err(MintBusy { payer, mint, waited: timeout }),
+        }
+    }
+
+    fn lock_for(&self, payer: Pubkey, mint: Pubkey) -> Arc<AsyncMutex<()>> {
+        let mut registry = self.registry.lock().unwrap();
+        if let Some(lock) = registry.locks.get(&(payer, mint)).and_then(Weak::upgrade) {
+            return lock;
+        }
+        if registry.locks.len() >= registry.sweep_at {
+            registry.locks.retain(|_, lock| lock.strong_count() > 0);
+            registry.sweep_at = (registry.locks.len() * 2).max(MIN_SWEEP_AT);
+        }
+        let lock = Arc::new(AsyncMutex::new(()));
+        registry.locks.insert((payer, mint), Arc::downgrade(&lock));
+        lock
+    }
+
+    /// Locks held or waited for
+    pub fn active(&self) -> usize {
+        self.registry.lock().unwrap().locks.values().filter(|lock| lock.strong_count() > 0).count()
+    }
+
+    /// Locks in the registry, including unused ones not swept yet
+    #[cfg(test)]
+    fn tracked(&self) -> usize {
+        self.registry.lock().unwrap().locks.len()
+    }
+}
+
+/// A held mint lock, released when dropped
+pub struct MintLockGuard {
+    _guard: OwnedMutexGuard<()>,
+}
+
+/// Runs `trade` holding `lock` until it calls [`release_held`] or finishes
+pub async fn hold<F: Future>(lock: MintLockGuard, trade: F) -> F::Output {
+    HELD.scope(RefCell::new(Some(lock)), trade).await
+}
+
+/// Releases the lock the current trade holds, if any; called once its transactions are sent
+pub fn release_held() {
+    let _ = HELD.try_with(|held| held.borrow_mut().take());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn test_second_trade_of_a_mint_times_out() {
+        let locks = MintLocks::new();
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let held = locks.acquire(payer, mint, SHORT).await.unwrap();
+
+        let busy = locks.clone().acquire(payer, mint, SHORT).await.err().unwrap();
+        assert_eq!(busy, MintBusy { payer, mint, waited: SHORT });
+        assert!(MintBusy::find(&anyhow::Error::from(busy)).is_some());
+        assert!(locks.acquire(payer, Pubkey::new_unique(), SHORT).await.is_ok(), "other mints are free");
+        assert!(locks.acquire(Pubkey::new_unique(), mint, SHORT).await.is_ok(), "other payers are free");
+
+        let waiter = tokio::spawn({
+            let locks = locks.clone();
+            async move { locks.acquire(payer, mint, Duration::from_secs(5)).await.is_ok() }
+        });
+        tokio::time::sleep(SHORT).await;
+        drop(held);
+        assert!(waiter.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_released_once_sent() {
+        let locks = MintLocks::new();
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let lock = locks.acquire(payer, mint, SHORT).await.unwrap();
+
+        let trade = hold(lock, async {
+            assert!(locks.acquire(payer, mint, SHORT).await.is_err());
+            release_held();
+            // still confirming, but the next trade may build
+            locks.acquire(payer, mint, SHORT).await.is_ok()
+        });
+        assert!(trade.await);
+        // outside a trade there is nothing to release
+        release_held();
+    }
+
+    #[tokio::test]
+    async fn test_unused_locks_are_swept() {
+        let locks = MintLocks::new();
+        let payer = Pubkey::new_unique();
+        let held = locks.acquire(payer, Pubkey::new_unique(), SHORT).await.unwrap();
+        for _ in 0..10 * MIN_SWEEP_AT {
+            drop(locks.acquire(payer, Pubkey::new_unique(), SHORT).await.unwrap());
+        }
+        assert!(locks.tracked() <= MIN_SWEEP_AT);
+        assert_eq!(locks.active(), 1);
+        drop(held);
+    }
+}
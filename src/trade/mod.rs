@@ -0,0 +1,751 @@
+//! Transaction submission pipeline
+//!
+//! A [`Submitter`] sends transactions through one or more [`Transport`]s and waits for
+//! them in the shared [`confirm`] engine, so RPC, Jito, NextBlock and 0slot submissions
+//! share their timeouts, confirmation rules and error handling.
+//!
+//! Each trade is described by a [`params::TradeParams`] and submitted with the settings of its
+//! [`context::ExecutionContext`].
+//!
+//! Automated sellers built on top of it, such as the [`graduation`] guard, coordinate
+//! through [`position`] locks. Bots buying from several event sources guard against buying a
+//! mint twice with a [`mint_guard::MintOnceGuard`], and trades of the same mint take turns
+//! through [`mint_lock`]. The balances a [`position::PositionTracker`] expects are checked
+//! against the chain by [`reconcile`].
+//!
+//! Tips of landed transactions are tallied in the [`tip_spend`] ledger. Trades in flight
+//! can be stopped through a [`cancel::CancellationToken`]. New tokens are screened before
+//! buying by a [`filter::FilterPipeline`]. Subscriptions can receive the SDK's own trades
+//! alongside market events through [`own_events`]. Snipes that are only worth landing by a
+//! given slot set a [`deadline`]. On shutdown, trades in flight are waited for by a [`drain`].
+
+pub mod cancel;
+pub mod confirm;
+pub mod context;
+pub mod deadline;
+pub mod drain;
+pub mod filter;
+pub mod graduation;
+pub mod journal;
+pub mod mint_guard;
+pub mod mint_lock;
+pub mod own_events;
+pub mod params;
+pub mod position;
+pub mod reconcile;
+pub mod tip_spend;
+pub mod transport;
+
+use std::{collections::HashSet, sync::Arc, time::{Duration, Instant}};
+
+use anyhow::anyhow;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{commitment_config::CommitmentLevel, signature::Signature, transaction::VersionedTransaction};
+
+use crate::{
+    common::latency::{Stage, StageTiming},
+    fee_clients::{common::CONFIRMATION_TIMEOUT, ClientType, RelayError, RelayReceipt, RelayStatus, SendOutcome},
+};
+use cancel::{CancellationToken, Cancelled};
+use confirm::{confirm_signatures_until, Confirmation, ConfirmLevel, SignatureStatuses};
+use deadline::{DeadlineMissed, DeadlineOutcome, SlotSource};
+use drain::Drain;
+use journal::{JournalEntry, TradeId};
+use crate::common::logs_events::PumpfunEvent;
+use transport::Transport;
+
+/// How transactions are spread over the transports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SendMode {
+    /// Send through every transport at once
+    #[default]
+    Parallel,
+    /// Try the transports in order until one accepts its transaction
+    Fallback,
+}
+
+/// `sendTransaction` settings of the plain RPC path; the defaults run preflight at the RPC
+/// client's commitment and leave retries to the node
+///
+/// Skipping preflight saves a simulation round trip and avoids rejections by simulations
+/// against stale state during hot launches, at the cost of paying fees for failing trades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RpcSendOptions {
+    pub skip_preflight: bool,
+    /// Commitment the preflight simulation runs at, `None` for the RPC client's commitment
+    pub preflight_commitment: Option<CommitmentLevel>,
+    /// Times the node rebroadcasts the transaction, `None` for the node's default
+    pub max_retries: Option<usize>,
+    /// Minimum slot the node must have reached to accept the transaction
+    pub min_context_slot: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubmitOptions {
+    pub mode: SendMode,
+    /// How long to wait for confirmation once the transactions are sent
+    pub timeout: Duration,
+    pub confirm_level: ConfirmLevel,
+    /// Used by the RPC transport; other transports ignore it
+    pub rpc: RpcSendOptions,
+    /// Last slot the transactions are worth landing in, see [`deadline`]
+    pub land_by_slot: Option<u64>,
+}
+
+impl Default for SubmitOptions {
+    fn default() -> Self {
+        Self {
+            mode: SendMode::Parallel,
+            timeout: CONFIRMATION_TIMEOUT,
+            confirm_level: ConfirmLevel::Confirmed,
+            rpc: RpcSendOptions::default(),
+            land_by_slot: None,
+        }
+    }
+}
+
+/// Result of submitting one transaction through one transport
+#[derive(Debug)]
+pub struct SubmissionResult {
+    pub transport: ClientType,
+    pub started_at: Instant,
+    /// When the transport accepted the transaction, `None` if sending failed
+    pub sent_at: Option<Instant>,
+    /// When sending failed or confirmation finished
+    pub finished_at: Instant,
+    /// Whether the transaction reached the requested [`ConfirmLevel`]
+    pub confirmed: bool,
+    /// Whether the submission was cancelled before confirmation finished, which leaves the
+    /// status of the sent transaction unknown
+    pub cancelled: bool,
+    /// Status the relay answered with, also kept when it rejected the transaction
+    pub relay_status: Option<RelayStatus>,
+    /// Raw relay response with credentials redacted, also kept when it rejected the transaction
+    pub relay_response: Option<String>,
+    /// Whether the transaction landed by [`SubmitOptions::land_by_slot`], when one was set
+    pub deadline: Option<DeadlineOutcome>,
+    pub result: Result<SendOutcome, anyhow::Error>,
+}
+
+impl SubmissionResult {
+    #[inline]
+    pub fn signature(&self) -> Option<Signature> {
+        self.result.as_ref().ok().map(|outcome| outcome.signature)
+    }
+
+    /// `Submit` timing of the send and, if it succeeded, `Confirm` timing of the wait
+    pub fn stage_timings(&self, origin: Instant) -> Vec<StageTiming> {
+        let client = Some(self.transport.to_string());
+        match self.sent_at {
+            Some(sent_at) => vec![
+                StageTiming::between(Stage::Submit, client.clone(), origin, self.started_at, sent_at, true),
+                StageTiming::between(Stage::Confirm, client, origin, sent_at, self.finished_at, self.confirmed),
+            ],
+            None => vec![StageTiming::between(Stage::Submit, client, origin, self.started_at, self.finished_at, false)],
+        }
+    }
+}
+
+/// None of a trade's transactions was confirmed; holds what happened on each transport
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("No transport confirmed the trade: {}", .failures.iter().map(|(transport, failure)| format!("{}: {}", transport, failure)).collect::<Vec<_>>().join("; "))]
+pub struct NotConfirmed {
+    pub failures: Vec<(ClientType, String)>,
+}
+
+impl NotConfirmed {
+    /// The failures of `results`, `None` if any transaction was confirmed
+    pub fn from_results(results: &[SubmissionResult]) -> Option<Self> {
+        if results.iter().any(|result| result.confirmed) {
+            return None;
+        }
+        let failures = results
+            .iter()
+            .map(|result| {
+                let failure = match &result.result {
+                    Ok(outcome) => format!("sent as {} but not confirmed", outcome.signature),
+                    Err(e) => e.to_string(),
+                };
+                (result.transport.clone(), failure)
+            })
+            .collect();
+        Some(Self { failures })
+    }
+
+    /// The missing confirmation somewhere in the chain of `error`
+    pub fn find(error: &anyhow::Error) -> Option<&NotConfirmed> {
+        error.chain().find_map(|cause| cause.downcast_ref::<NotConfirmed>())
+    }
+}
+
+struct Sent {
+    transport: ClientType,
+    started_at: Instant,
+    finished_at: Instant,
+    tip_lamports: Option<u64>,
+    result: Result<RelayReceipt, anyhow::Error>,
+}
+
+/// Sends transactions through its transports and confirms them
+///
+/// Every send attempt and its resolution is recorded in the installed [`journal`], and the
+/// tips of landed transactions in the [`tip_spend`] ledger.
+pub struct Submitter {
+    transports: Vec<Arc<dyn Transport>>,
+    statuses: Arc<dyn SignatureStatuses>,
+    trade_id: Option<TradeId>,
+    strategy: Option<String>,
+    cancel: CancellationToken,
+    slots: Option<Arc<dyn SlotSource>>,
+    drain: Option<Arc<Drain>>,
+}
+
+impl Submitter {
+    pub fn new(transports: Vec<Arc<dyn Transport>>, statuses: Arc<dyn SignatureStatuses>) -> Self {
+        Self { transports, statuses, trade_id: None, strategy: None, cancel: CancellationToken::new(), slots: None, drain: None }
+    }
+
+    /// Stops submitting once `cancel` is cancelled: before sending, every transport fails
+    /// with [`Cancelled::BeforeSubmission`]; after, fallback sends stop and confirmation
+    /// stops waiting, marking the results [`SubmissionResult::cancelled`]
+    pub fn with_cancellation(mut self, cancel: Option<CancellationToken>) -> Self {
+        self.cancel = cancel.unwrap_or_default();
+        self
+    }
+
+    /// Bills the tips of the submissions to `strategy`, usually the trade tag
+    pub fn with_strategy(mut self, strategy: Option<String>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Journals the submissions under `trade_id`
+    pub fn with_trade_id(mut self, trade_id: Option<TradeId>) -> Self {
+        self.trade_id = trade_id;
+        self
+    }
+
+    /// Reads the current slot for [`SubmitOptions::land_by_slot`] from `slots`
+    pub fn with_slot_source(mut self, slots: Arc<dyn SlotSource>) -> Self {
+        self.slots = Some(slots);
+        self
+    }
+
+    /// Registers the sent transactions in `drain` until their confirmation finishes
+    pub fn with_drain(mut self, drain: Option<Arc<Drain>>) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Sends `transactions` and waits for them according to `options`
+    ///
+    /// `transactions` holds either one transaction per transport, in transport order, or a
+    /// single transaction sent through every transport. Returns one result per transport
+    /// that was tried.
+    ///
+    /// With a `land_by_slot`, nothing is sent once the current slot is past it; otherwise
+    /// RPC rebroadcasts are turned off and confirmation is awaited only until shortly after
+    /// the deadline.
+    pub async fn submit(&self, transactions: Vec<VersionedTransaction>, mut options: SubmitOptions) -> Vec<SubmissionResult> {
+        if transactions.len() != 1 && transactions.len() != self.transports.len() {
+            return self.unsent(|| anyhow!("Expected 1 or {} transactions, got {}", self.transports.len(), transactions.len()));
+        }
+        if self.cancel.is_cancelled() {
+            return self.unsent(|| Cancelled::BeforeSubmission.into());
+        }
+        let mut submitted_at_slot = None;
+        if let Some(land_by_slot) = options.land_by_slot {
+            let current_slot = match &self.slots {
+                Some(slots) => slots.current_slot().await,
+                None => Err(anyhow!("No slot source to check the deadline against")),
+            };
+            let current_slot = match current_slot {
+                Ok(current_slot) => current_slot,
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    return self.unsent(|| anyhow!("Failed to read the current slot: {}", message));
+                }
+            };
+            if current_slot > land_by_slot {
+                return self.unsent(|| DeadlineMissed { land_by_slot, current_slot }.into());
+            }
+            options.rpc.max_retries = Some(0);
+            options.timeout = options.timeout.min(deadline::confirmation_timeout(current_slot, land_by_slot));
+            submitted_at_slot = Some(current_slot);
+        }
+
+        let _in_flight = self.drain.as_ref().map(|drain| drain.track(transactions.iter().filter_map(|transaction| transaction.signatures.first().copied()).collect()));
+
+        let pairs: Vec<(Arc<dyn Transport>, VersionedTransaction)> = self.transports.iter().cloned().enumerate()
+            .map(|(index, transport)| (transport, transactions[index.min(transactions.len() - 1)].clone()))
+            .collect();
+
+        let sent = match options.mode {
+            SendMode::Parallel => join_all(pairs.iter().map(|(transport, transaction)| send(transport.as_ref(), transaction, &options, self.trade_id.as_ref()))).await,
+            SendMode::Fallback => {
+                let mut sent = Vec::new();
+                for (transport, transaction) in &pairs {
+                    if self.cancel.is_cancelled() {
+                        break;
+                    }
+                    let attempt = send(transport.as_ref(), transaction, &options, self.trade_id.as_ref()).await;
+                    let accepted = attempt.result.is_ok();
+                    sent.push(attempt);
+                    if accepted {
+                        break;
+                    }
+                }
+                sent
+            }
+        };
+
+        // the next trade of the mint may build while these are confirmed
+        mint_lock::release_held();
+
+        let signatures: Vec<Signature> = sent.iter().filter_map(|attempt| attempt.result.as_ref().ok().map(|receipt| receipt.signature)).collect();
+        let mut confirmations = confirm_signatures_until(self.statuses.as_ref(), &signatures, options.confirm_level, options.timeout, &self.cancel).await.into_iter();
+        let confirmed: Vec<(Sent, Option<Confirmation>)> = sent.into_iter()
+            .map(|attempt| {
+                let confirmation = attempt.result.is_ok().then(|| confirmations.next().expect("one confirmation per sent signature"));
+                (attempt, confirmation)
+            })
+            .collect();
+        self.record_tips(&confirmed, options.mode);
+
+        confirmed.into_iter().map(|(attempt, confirmation)| match attempt.result {
+            Ok(receipt) => {
+                let signature = receipt.signature;
+                let confirmation = confirmation.expect("sent transactions are confirmed");
+                let confirmed = confirmation.reached(options.confirm_level);
+                let relay_latency = attempt.finished_at.duration_since(attempt.started_at);
+                let landed_slot = confirmation.result.as_ref().ok().and_then(|landing| landing.slot);
+                let error = match &confirmation.result {
+                    Err(e) => Some(e.to_string()),
+                    Ok(_) if confirmation.cancelled => Some("Cancelled while waiting for confirmation".to_string()),
+                    Ok(_) => None,
+                };
+                match (confirmed && landed_slot.is_some(), &error) {
+                    (true, _) => own_events::publish(self.trade_id.as_ref(), PumpfunEvent::OwnTradeConfirmed, signature, &attempt.transport, landed_slot, None),
+                    // landing was not awaited or not seen in time, so the outcome is unknown
+                    (false, None) => {}
+                    (false, Some(error)) => own_events::publish(self.trade_id.as_ref(), PumpfunEvent::OwnTradeFailed, signature, &attempt.transport, landed_slot, Some(error.clone())),
+                }
+                journal::record(self.trade_id.as_ref(), JournalEntry::Resolved {
+                    signature,
+                    transport: attempt.transport.clone(),
+                    confirmed,
+                    landed_slot,
+                    error,
+                });
+                SubmissionResult {
+                    transport: attempt.transport,
+                    started_at: attempt.started_at,
+                    sent_at: Some(attempt.finished_at),
+                    finished_at: confirmation.finished_at.max(attempt.finished_at),
+                    confirmed,
+                    cancelled: confirmation.cancelled,
+                    relay_status: receipt.status,
+                    relay_response: receipt.response.clone(),
+                    deadline: options.land_by_slot.zip(submitted_at_slot).map(|(land_by_slot, submitted_at_slot)| DeadlineOutcome { land_by_slot, submitted_at_slot, landed_slot }),
+                    result: confirmation.result.map(|landing| SendOutcome::new(receipt, landing, relay_latency)),
+                }
+            }
+            Err(e) => {
+                let relay_error = RelayError::find(&e);
+                SubmissionResult {
+                    transport: attempt.transport,
+                    started_at: attempt.started_at,
+                    sent_at: None,
+                    finished_at: attempt.finished_at,
+                    confirmed: false,
+                    cancelled: false,
+                    relay_status: relay_error.and_then(|relay_error| relay_error.status),
+                    relay_response: relay_error.and_then(|relay_error| relay_error.relay_response.clone()),
+                    deadline: None,
+                    result: Err(e),
+                }
+            }
+        }).collect()
+    }
+
+    /// One failed result per transport, for submissions that send nothing
+    fn unsent(&self, error: impl Fn() -> anyhow::Error) -> Vec<SubmissionResult> {
+        let now = Instant::now();
+        self.transports.iter().map(|transport| SubmissionResult {
+            transport: transport.client_type(),
+            started_at: now,
+            sent_at: None,
+            finished_at: now,
+            confirmed: false,
+            cancelled: false,
+            relay_status: None,
+            relay_response: None,
+            deadline: None,
+            result: Err(error()),
+        }).collect()
+    }
+
+    /// Records the tip of every transaction that landed; the first to land pays the trade's
+    /// tip and later ones are wasted
+    fn record_tips(&self, confirmed: &[(Sent, Option<Confirmation>)], mode: SendMode) {
+        let mut landed: Vec<(u64, Instant, &Sent, Signature, u64)> = confirmed.iter()
+            .filter_map(|(attempt, confirmation)| {
+                let signature = attempt.result.as_ref().ok()?.signature;
+                let slot = confirmation.as_ref()?.result.as_ref().ok()?.slot?;
+                Some((slot, attempt.finished_at, attempt, signature, attempt.tip_lamports?))
+            })
+            .collect();
+        landed.sort_by_key(|(slot, accepted_at, ..)| (*slot, *accepted_at));
+
+        let mut counted = HashSet::new();
+        let day = tip_spend::today();
+        for (_, _, attempt, signature, lamports) in landed {
+            if !counted.insert(signature) {
+                continue;
+            }
+            tip_spend::record(tip_spend::TipPaid {
+                signature,
+                transport: attempt.transport.clone(),
+                strategy: self.strategy.clone(),
+                mode,
+                lamports,
+                wasted: counted.len() > 1,
+                day,
+            });
+        }
+    }
+}
+
+async fn send(transport: &dyn Transport, transaction: &VersionedTransaction, options: &SubmitOptions, trade_id: Option<&TradeId>) -> Sent {
+    let started_at = Instant::now();
+    let result = transport.send_detailed(transaction, options).await;
+    let finished_at = Instant::now();
+
+    let tip_lamports = journal::tip_lamports(transaction);
+    let signature = transaction.signatures.first().copied().unwrap_or_default();
+    match &result {
+        Ok(_) => own_events::publish(trade_id, PumpfunEvent::OwnTradeSubmitted, signature, &transport.client_type(), None, None),
+        Err(e) => own_events::publish(trade_id, PumpfunEvent::OwnTradeFailed, signature, &transport.client_type(), None, Some(e.to_string())),
+    }
+    journal::record(trade_id, JournalEntry::Submitted {
+        signature,
+        transport: transport.client_type(),
+        tip_lamports,
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+    Sent { transport: transport.client_type(), started_at, finished_at, tip_lamports, result }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use solana_sdk::message::VersionedMessage;
+    use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+    use super::*;
+
+    struct MockTransport {
+        name: &'static str,
+        fails: bool,
+        sent: AtomicUsize,
+    }
+
+    impl MockTransport {
+        fn new(name: &'static str, fails: bool) -> Arc<Self> {
+            Arc::new(Self { name, fails, sent: AtomicUsize::new(0) })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for MockTransport {
+        fn client_type(&self) -> ClientType {
+            self.name.parse().unwrap()
+        }
+
+        async fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+            if self.fails {
+                let response = Some(r#"{"error":"unavailable"}"#.to_string());
+                return Err(RelayError::new(self.client_type(), Some(RelayStatus::Http(503)), response, format!("{} is down", self.name)).into());
+            }
+            Ok(transaction.signatures[0])
+        }
+    }
+
+    /// Reports every signature as confirmed in slot 7
+    struct Landed;
+
+    #[async_trait::async_trait]
+    impl SignatureStatuses for Landed {
+        async fn signature_statuses(&self, signatures: &[Signature]) -> Result<Vec<Option<TransactionStatus>>, anyhow::Error> {
+            Ok(signatures.iter().map(|_| Some(TransactionStatus {
+                slot: 7,
+                confirmations: Some(1),
+                status: Ok(()),
+                err: None,
+                confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+            })).collect())
+        }
+    }
+
+    fn transaction() -> VersionedTransaction {
+        VersionedTransaction {
+            signatures: vec![Signature::new_unique()],
+            message: VersionedMessage::Legacy(Default::default()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_sends_each_transaction_through_its_transport() {
+        let (jito, nextblock) = (MockTransport::new("Jito", false), MockTransport::new("NextBlock", true));
+        let submitter = Submitter::new(vec![jito.clone(), nextblock.clone()], Arc::new(Landed));
+        let transactions = vec![transaction(), transaction()];
+
+        let results = submitter.submit(transactions.clone(), SubmitOptions::default()).await;
+        assert_eq!(results.len(), 2);
+
+        assert_eq!(results[0].transport, ClientType::Jito);
+        assert!(results[0].confirmed);
+        assert_eq!(results[0].signature(), Some(transactions[0].signatures[0]));
+        assert_eq!(results[0].result.as_ref().unwrap().landed_slot, Some(7));
+
+        assert!(!results[1].confirmed);
+        assert!(results[1].sent_at.is_none());
+        assert!(results[1].result.as_ref().unwrap_err().to_string().contains("NextBlock is down"));
+        assert_eq!(results[1].relay_status, Some(RelayStatus::Http(503)));
+        assert_eq!(results[1].relay_response.as_deref(), Some(r#"{"error":"unavailable"}"#));
+        assert_eq!(results[0].relay_status, None);
+        assert_eq!(results[1].stage_timings(results[1].started_at).len(), 1);
+        assert_eq!(results[0].stage_timings(results[0].started_at).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tips_of_duplicate_landings_are_wasted() {
+        use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::Transaction};
+
+        let payer = Keypair::new();
+        let tip_account: Pubkey = crate::constants::accounts::JITO_TIP_ACCOUNTS[0].parse().unwrap();
+        let tipped = |lamports| -> VersionedTransaction {
+            let tip = system_instruction::transfer(&payer.pubkey(), &tip_account, lamports);
+            Transaction::new_signed_with_payer(&[tip], Some(&payer.pubkey()), &[&payer], Hash::new_unique()).into()
+        };
+        let strategy = format!("race-{}", Signature::new_unique());
+        let submitter = Submitter::new(vec![MockTransport::new("Jito", false), MockTransport::new("ZeroSlot", false)], Arc::new(Landed))
+            .with_strategy(Some(strategy.clone()));
+
+        submitter.submit(vec![tipped(1_000), tipped(2_000)], SubmitOptions::default()).await;
+        let spend = tip_spend::tip_ledger().report(..).by_strategy()[&Some(strategy)];
+        assert_eq!((spend.landed_transactions, spend.wasted_transactions), (1, 1));
+        assert_eq!(spend.total_lamports(), 3_000);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_stops_at_first_accepting_transport() {
+        let (down, rpc, unused) = (MockTransport::new("ZeroSlot", true), MockTransport::new("Rpc", false), MockTransport::new("Jito", false));
+        let submitter = Submitter::new(vec![down.clone(), rpc.clone(), unused.clone()], Arc::new(Landed));
+        let options = SubmitOptions { mode: SendMode::Fallback, ..Default::default() };
+
+        let results = submitter.submit(vec![transaction()], options).await;
+        let tried: Vec<String> = results.iter().map(|result| result.transport.to_string()).collect();
+        assert_eq!(tried, vec!["ZeroSlot", "Rpc"]);
+        assert!(results[1].confirmed);
+        assert_eq!(unused.sent.load(Ordering::Relaxed), 0);
+    }
+
+    /// Never sees any signature land
+    struct Pending;
+
+    #[async_trait::async_trait]
+    impl SignatureStatuses for Pending {
+        async fn signature_statuses(&self, signatures: &[Signature]) -> Result<Vec<Option<TransactionStatus>>, anyhow::Error> {
+            Ok(vec![None; signatures.len()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_before_and_after_sending() {
+        let jito = MockTransport::new("Jito", false);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let submitter = Submitter::new(vec![jito.clone()], Arc::new(Pending)).with_cancellation(Some(cancel));
+        let results = submitter.submit(vec![transaction()], SubmitOptions::default()).await;
+        assert_eq!(Cancelled::find(results[0].result.as_ref().unwrap_err()), Some(&Cancelled::BeforeSubmission));
+        assert_eq!(jito.sent.load(Ordering::Relaxed), 0);
+
+        let cancel = CancellationToken::new();
+        let submitter = Submitter::new(vec![jito.clone()], Arc::new(Pending)).with_cancellation(Some(cancel.clone()));
+        let transaction = transaction();
+        let stopper = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancel.cancel();
+        });
+        let started = Instant::now();
+        let results = submitter.submit(vec![transaction.clone()], SubmitOptions { timeout: Duration::from_secs(30), ..Default::default() }).await;
+        stopper.await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(results[0].cancelled && !results[0].confirmed);
+        assert_eq!(results[0].signature(), Some(transaction.signatures[0]));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_mismatched_transaction_count() {
+        let submitter = Submitter::new(vec![MockTransport::new("Jito", false), MockTransport::new("Rpc", false)], Arc::new(Landed));
+        let results = submitter.submit(vec![transaction(), transaction(), transaction()], SubmitOptions::default()).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.result.is_err()));
+    }
+
+    #[tokio::test]
+    async fn test_single_transaction_goes_through_every_transport() {
+        let (a, b) = (MockTransport::new("Jito", false), MockTransport::new("ZeroSlot", false));
+        let submitter = Submitter::new(vec![a.clone(), b.clone()], Arc::new(Landed));
+        let transaction = transaction();
+
+        let results = submitter.submit(vec![transaction.clone()], SubmitOptions { confirm_level: ConfirmLevel::None, ..Default::default() }).await;
+        assert!(results.iter().all(|result| result.confirmed && result.signature() == Some(transaction.signatures[0])));
+        assert_eq!(a.sent.load(Ordering::Relaxed) + b.sent.load(Ordering::Relaxed), 2);
+    }
+
+    /// Reports a fixed current slot
+    struct FixedSlot(u64);
+
+    #[async_trait::async_trait]
+    impl SlotSource for FixedSlot {
+        async fn current_slot(&self) -> Result<u64, anyhow::Error> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_refuses_late_sends_and_reports_landing() {
+        let jito = MockTransport::new("Jito", false);
+        let options = |land_by_slot| SubmitOptions { land_by_slot: Some(land_by_slot), ..Default::default() };
+
+        let late = Submitter::new(vec![jito.clone()], Arc::new(Landed)).with_slot_source(Arc::new(FixedSlot(10)));
+        let results = late.submit(vec![transaction()], options(9)).await;
+        let missed = DeadlineMissed::find(results[0].result.as_ref().unwrap_err()).copied();
+        assert_eq!(missed, Some(DeadlineMissed { land_by_slot: 9, current_slot: 10 }));
+        assert_eq!(jito.sent.load(Ordering::Relaxed), 0);
+
+        // Landed reports slot 7
+        let submitter = Submitter::new(vec![jito.clone()], Arc::new(Landed)).with_slot_source(Arc::new(FixedSlot(5)));
+        let in_time = submitter.submit(vec![transaction()], options(7)).await.remove(0).deadline.unwrap();
+        assert_eq!(in_time, DeadlineOutcome { land_by_slot: 7, submitted_at_slot: 5, landed_slot: Some(7) });
+        assert!(in_time.landed_in_time());
+        let too_late = submitter.submit(vec![transaction()], options(6)).await.remove(0).deadline.unwrap();
+        assert!(!too_late.landed_in_time());
+
+        let no_source = Submitter::new(vec![jito.clone()], Arc::new(Landed));
+        assert!(no_source.submit(vec![transaction()], options(7)).await[0].result.is_err());
+        assert!(no_source.submit(vec![transaction()], SubmitOptions::default()).await[0].deadline.is_none());
+    }
+
+    /// Accepts every transaction after `delay`
+    struct SlowTransport {
+        delay: Duration,
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for SlowTransport {
+        fn client_type(&self) -> ClientType {
+            ClientType::Rpc
+        }
+
+        async fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+            tokio::time::sleep(self.delay).await;
+            Ok(transaction.signatures[0])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_submissions_before_stopping_tasks() {
+        let drain = Arc::new(Drain::new());
+        let tasks = crate::common::tasks::TaskRegistry::new();
+        let subscription = tokio::spawn(futures::future::pending::<()>());
+        tasks.track_subscription(subscription.abort_handle());
+
+        let submitter = Submitter::new(vec![Arc::new(SlowTransport { delay: Duration::from_millis(200) })], Arc::new(Landed)).with_drain(Some(drain.clone()));
+        let admitted = drain.admit().unwrap();
+        let trade = tokio::spawn(async move {
+            let results = submitter.submit(vec![transaction()], SubmitOptions::default()).await;
+            drop(admitted);
+            (results[0].confirmed, Instant::now())
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(drain.in_flight(), 2);
+
+        let shutdown = {
+            let drain = drain.clone();
+            tokio::spawn(async move {
+                drain.shutdown(&tasks, drain::DEFAULT_DRAIN_TIMEOUT, Duration::from_secs(1)).await.unwrap();
+                Instant::now()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(drain.admit().err(), Some(drain::ShuttingDown));
+        assert!(!subscription.is_finished(), "subscriptions stop only after the drain");
+
+        let (confirmed, finished_at) = trade.await.unwrap();
+        let stopped_at = shutdown.await.unwrap();
+        assert!(confirmed);
+        assert!(stopped_at >= finished_at);
+        assert!(subscription.await.unwrap_err().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_drain_timeout_reports_unresolved_signatures() {
+        let drain = Arc::new(Drain::new());
+        let submitter = Submitter::new(vec![Arc::new(SlowTransport { delay: Duration::ZERO })], Arc::new(Pending)).with_drain(Some(drain.clone()));
+        let transaction = transaction();
+        let submission = {
+            let transaction = transaction.clone();
+            tokio::spawn(async move { submitter.submit(vec![transaction], SubmitOptions { timeout: Duration::from_secs(30), ..Default::default() }).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        drain.begin();
+        let timed_out = drain.wait(Duration::from_millis(100)).await.unwrap_err();
+        assert_eq!(timed_out.unresolved, vec![transaction.signatures[0]]);
+        assert_eq!(timed_out.trades, 1);
+
+        submission.abort();
+        let _ = submission.await;
+        assert_eq!(drain.wait(Duration::ZERO).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_mint_lock_is_released_before_confirmation() {
+        let locks = mint_lock::MintLocks::new();
+        let (payer, mint) = (solana_sdk::pubkey::Pubkey::new_unique(), solana_sdk::pubkey::Pubkey::new_unique());
+        let lock = locks.acquire(payer, mint, Duration::from_millis(50)).await.unwrap();
+        let submitter = Submitter::new(vec![MockTransport::new("Jito", false)], Arc::new(Pending));
+        let options = SubmitOptions { timeout: Duration::from_secs(1), ..Default::default() };
+
+        let (results, next) = tokio::join!(
+            mint_lock::hold(lock, submitter.submit(vec![transaction()], options)),
+            locks.acquire(payer, mint, Duration::from_millis(500)),
+        );
+        assert!(next.is_ok(), "the next trade of the mint builds while the first is confirmed");
+        assert!(!results[0].confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_trade_failing_on_every_transport_is_not_confirmed() {
+        let (jito, nextblock) = (MockTransport::new("Jito", true), MockTransport::new("NextBlock", true));
+        let submitter = Submitter::new(vec![jito, nextblock], Arc::new(Landed));
+
+        let results = submitter.submit(vec![transaction(), transaction()], SubmitOptions::default()).await;
+        let error = NotConfirmed::from_results(&results).unwrap();
+        assert_eq!(error.failures.iter().map(|(transport, _)| transport.clone()).collect::<Vec<_>>(), vec![ClientType::Jito, ClientType::NextBlock]);
+        assert!(error.to_string().contains("Jito is down") && error.to_string().contains("NextBlock is down"));
+        assert!(NotConfirmed::find(&anyhow::Error::from(error)).is_some());
+
+        let (jito, nextblock) = (MockTransport::new("Jito", false), MockTransport::new("NextBlock", true));
+        let results = Submitter::new(vec![jito, nextblock], Arc::new(Landed)).submit(vec![transaction(), transaction()], SubmitOptions::default()).await;
+        assert_eq!(NotConfirmed::from_results(&results), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! The SDK's own submissions as events
+//!
+//! A callback wrapped by [`with_own_trades`] receives, next to the events of its subscription,
+//! an [`OwnTradeEvent`] whenever the [`Submitter`](super::Submitter) sends, lands or fails a
+//! transaction of a trade, so one handler sees both the market and the bot's own activity.
+//! These variants of [`PumpfunEvent`] are never parsed from the chain, and serialize with
+//! `"synthetic": true`.
+//!
+//! Each own trade is reported to a callback once: by `OwnTradeConfirmed` or by the on-chain
+//! trade the subscription parses from the same transaction, whichever arrives first; the other
+//! is dropped.
+
+use std::sync::{Arc, Mutex, RwLock};
+
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use super::journal::{TradeId, TradeSide};
+use crate::{
+    common::{cache::PersistentLru, logs_events::PumpfunEvent},
+    fee_clients::ClientType,
+};
+
+/// Trades and signatures remembered to describe and correlate own trades
+const CORRELATION_CAPACITY: usize = 4_096;
+
+lazy_static::lazy_static! {
+    static ref SINKS: RwLock<Vec<Arc<Sink>>> = RwLock::new(Vec::new());
+    static ref INTENTS: PersistentLru<TradeId, (Pubkey, TradeSide)> = PersistentLru::in_memory(CORRELATION_CAPACITY);
+}
+
+/// A transaction of one of the SDK's own trades
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnTradeEvent {
+    pub mint: Pubkey,
+    pub side: TradeSide,
+    pub signature: Signature,
+    /// Transport the transaction was sent through
+    pub client_type: ClientType,
+    pub trade_id: Option<TradeId>,
+    /// Slot the transaction landed in, for confirmed trades
+    pub landed_slot: Option<u64>,
+    /// Why sending or confirming failed, for failed trades
+    pub error: Option<String>,
+}
+
+impl Serialize for OwnTradeEvent {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("OwnTradeEvent", 8)?;
+        state.serialize_field("synthetic", &true)?;
+        state.serialize_field("mint", &self.mint.to_string())?;
+        state.serialize_field("side", &self.side)?;
+        state.serialize_field("signature", &self.signature.to_string())?;
+        state.serialize_field("client_type", &self.client_type)?;
+        state.serialize_field("trade_id", &self.trade_id)?;
+        state.serialize_field("landed_slot", &self.landed_slot)?;
+        state.serialize_field("error", &self.error)?;
+        state.end()
+    }
+}
+
+/// Who reported a signature to a callback first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum ReportedBy {
+    Chain,
+    Sdk,
+}
+
+struct Sink {
+    callback: Box<dyn Fn(PumpfunEvent) + Send + Sync>,
+    reported: Mutex<PersistentLru<Signature, ReportedBy>>,
+}
+
+impl Sink {
+    /// Whether `signature` was not reported yet, marking it reported by `by`
+    fn first_report(&self, signature: Signature, by: ReportedBy) -> bool {
+        let reported = self.reported.lock().unwrap();
+        if reported.get(&signature).is_some() {
+            return false;
+        }
+        reported.insert(signature, by);
+        true
+    }
+
+    fn deliver(&self, event: PumpfunEvent) {
+        let on_chain = match &event {
+            PumpfunEvent::NewDevTrade(trade) | PumpfunEvent::NewUserTrade(trade) | PumpfunEvent::NewBotTrade(trade) => Some(trade.signature),
+            PumpfunEvent::OwnTradeConfirmed(own) => {
+                if self.first_report(own.signature, ReportedBy::Sdk) {
+                    (self.callback)(event);
+                }
+                return;
+            }
+            _ => None,
+        };
+        match on_chain {
+            // an unknown signature cannot be correlated
+            Some(signature) if signature != Signature::default() => {
+                let reported = self.reported.lock().unwrap();
+                if reported.get(&signature) == Some(ReportedBy::Sdk) {
+                    return;
+                }
+                reported.insert(signature, ReportedBy::Chain);
+            }
+            _ => {}
+        }
+        (self.callback)(event);
+    }
+}
+
+/// Keeps own trade events flowing into its callback; dropping it stops them
+pub struct OwnTradeRegistration {
+    sink: Arc<Sink>,
+}
+
+impl Drop for OwnTradeRegistration {
+    fn drop(&mut self) {
+        SINKS.write().unwrap().retain(|sink| !Arc::ptr_eq(sink, &self.sink));
+    }
+}
+
+/// Wraps `callback` for a subscription so it also receives the SDK's own trade events, which
+/// keep coming until the registration is dropped
+pub fn with_own_trades<F>(callback: F) -> (OwnTradeRegistration, impl Fn(PumpfunEvent) + Send + Sync + 'static)
+where
+    F: Fn(PumpfunEvent) + Send + Sync + 'static,
+{
+    let sink = Arc::new(Sink { callback: Box::new(callback), reported: Mutex::new(PersistentLru::in_memory(CORRELATION_CAPACITY)) });
+    SINKS.write().unwrap().push(sink.clone());
+    let subscription_sink = sink.clone();
+    (OwnTradeRegistration { sink }, move |event| subscription_sink.deliver(event))
+}
+
+fn listening() -> bool {
+    !SINKS.read().unwrap().is_empty()
+}
+
+/// Remembers what a trade is for, to describe its transactions
+pub(crate) fn track_intent(trade_id: &TradeId, mint: Pubkey, side: TradeSide) {
+    if listening() {
+        INTENTS.insert(trade_id.clone(), (mint, side));
+    }
+}
+
+/// Publishes an event of a transaction of trade `trade_id`; transactions sent outside a trade
+/// are not published
+pub(crate) fn publish(trade_id: Option<&TradeId>, event: impl Fn(OwnTradeEvent) -> PumpfunEvent, signature: Signature, client_type: &ClientType, landed_slot: Option<u64>, error: Option<String>) {
+    let sinks = SINKS.read().unwrap().clone();
+    if sinks.is_empty() {
+        return;
+    }
+    let Some((mint, side)) = trade_id.and_then(|trade_id| INTENTS.get(trade_id)) else {
+        return;
+    };
+    let own = OwnTradeEvent { mint, side, signature, client_type: client_type.clone(), trade_id: trade_id.cloned(), landed_slot, error };
+    for sink in sinks {
+        sink.deliver(event(own.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::logs_data::TradeInfo;
+
+    #[test]
+    fn test_each_own_trade_is_reported_once() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let (registration, callback) = with_own_trades(move |event| log.lock().unwrap().push(event));
+        let (mint, trade_id) = (Pubkey::new_unique(), TradeId::new());
+        track_intent(&trade_id, mint, TradeSide::Buy);
+
+        // confirmed by the SDK first, then parsed from the chain
+        let first = Signature::new_unique();
+        publish(Some(&trade_id), PumpfunEvent::OwnTradeSubmitted, first, &ClientType::Jito, None, None);
+        publish(Some(&trade_id), PumpfunEvent::OwnTradeConfirmed, first, &ClientType::Jito, Some(7), None);
+        callback(PumpfunEvent::NewUserTrade(TradeInfo { mint, signature: first, ..Default::default() }));
+
+        // parsed from the chain before the SDK saw it land
+        let second = Signature::new_unique();
+        callback(PumpfunEvent::NewUserTrade(TradeInfo { mint, signature: second, ..Default::default() }));
+        publish(Some(&trade_id), PumpfunEvent::OwnTradeConfirmed, second, &ClientType::Rpc, Some(8), None);
+
+        // trades of other tests may be published while registered
+        let kinds: Vec<&str> = seen.lock().unwrap().iter().filter_map(|event| match event {
+            PumpfunEvent::OwnTradeSubmitted(own) if own.mint == mint => Some("submitted"),
+            PumpfunEvent::OwnTradeConfirmed(own) if own.mint == mint => Some(if own.landed_slot == Some(7) { "confirmed" } else { "duplicate" }),
+            PumpfunEvent::NewUserTrade(trade) => Some(if trade.signature == second { "on-chain" } else { "duplicate" }),
+            _ => None,
+        }).collect();
+        assert_eq!(kinds, vec!["submitted", "confirmed", "on-chain"]);
+
+        drop(registration);
+        let failed = Signature::new_unique();
+        publish(Some(&trade_id), PumpfunEvent::OwnTradeFailed, failed, &ClientType::Jito, None, Some("expired".to_string()));
+        assert!(!seen.lock().unwrap().iter().any(|event| matches!(event, PumpfunEvent::OwnTradeFailed(own) if own.signature == failed)));
+    }
+
+    #[test]
+    fn test_own_trades_serialize_as_synthetic() {
+        let own = OwnTradeEvent {
+            mint: Pubkey::new_unique(),
+            side: TradeSide::Sell,
+            signature: Signature::new_unique(),
+            client_type: ClientType::Rpc,
+            trade_id: None,
+            landed_slot: None,
+            error: Some("blockhash expired".to_string()),
+        };
+        let value = serde_json::to_value(&own).unwrap();
+        assert_eq!(value["synthetic"], true);
+        assert_eq!(value["side"], "sell");
+        assert_eq!(value["signature"], own.signature.to_string());
+    }
+}
@@ -0,0 +1,365 @@
+//! Builder-style parameters of a single trade
+//!
+//! [`TradeParams`] gathers what a buy or sell needs, with chained setters for the optional
+//! settings, and `PumpFun::execute` runs it. The setters fill in a [`TradeOverrides`], so a
+//! trade's settings default to the client's. `PumpFun::buy`, `sell` and their `*_with_tip`
+//! variants are thin wrappers building default parameters. [`CreateParams`] does the same
+//! for `PumpFun::create_and_buy` and `create_and_buy_with_tip`.
+//!
+//! ```no_run
+//! # async fn run(client: &pumpfun_sdk::PumpFun, mint: solana_sdk::pubkey::Pubkey) -> Result<(), anyhow::Error> {
+//! use pumpfun_sdk::{common::TradeTag, trade::{params::TradeParams, SendMode}};
+//! use solana_sdk::{pubkey::Pubkey, system_instruction};
+//!
+//! let tip = system_instruction::transfer(&client.get_payer_pubkey(), &Pubkey::new_unique(), 1_000);
+//! let params = TradeParams::buy(mint, 100_000_000)
+//!     .slippage_bps(300)
+//!     .with_tip()
+//!     .send_mode(SendMode::Fallback)
+//!     .tag(TradeTag::new("sniper")?)
+//!     .extra_instruction(tip);
+//! client.execute(params).await?;
+//!
+//! client.execute(TradeParams::sell_percent(mint, 50).retry_fee_recipient_rotation(true)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair};
+
+use crate::{
+    common::{PriorityFee, TradeTag},
+    fee_clients::SubmissionOptions,
+    ipfs::TokenMetadataIPFS,
+    pumpfun::{create::MintCheck, launch_budget::LaunchBudget, metadata::MetadataPolicy},
+};
+
+use super::{cancel::CancellationToken, context::TradeOverrides, RpcSendOptions, SendMode};
+
+/// What a trade buys or sells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeAction {
+    Buy { amount_sol: u64 },
+    /// Sells `amount_token`, or the payer's whole balance with `None`
+    Sell { amount_token: Option<u64> },
+    /// Sells `percent` (1 to 100) of the payer's balance
+    SellPercent { percent: u64 },
+}
+
+/// A buy or sell for `PumpFun::execute`, see the [module docs](self)
+#[derive(Clone)]
+pub struct TradeParams {
+    pub mint: Pubkey,
+    pub action: TradeAction,
+    /// `None` for the default slippage
+    pub slippage_basis_points: Option<u64>,
+    /// Added to the trade's transactions before the trade tag memo
+    pub extra_instructions: Vec<Instruction>,
+    /// Send through the client's fee clients, tipping each, instead of plain RPC
+    pub with_tip: bool,
+    /// Settings replacing the client's for this trade
+    ///
+    /// Plain RPC trades send through the client's send endpoint, so they ignore `fee_clients`,
+    /// `send_mode`, `submission_options`, `proceeds`, `land_by_slot` and `idempotency_key`.
+    pub overrides: TradeOverrides,
+}
+
+impl TradeParams {
+    /// `action` on `mint` with the client's settings
+    pub fn new(mint: Pubkey, action: TradeAction) -> Self {
+        Self { mint, action, slippage_basis_points: None, extra_instructions: vec![], with_tip: false, overrides: TradeOverrides::default() }
+    }
+
+    /// Buy `mint` for `amount_sol` lamports
+    pub fn buy(mint: Pubkey, amount_sol: u64) -> Self {
+        Self::new(mint, TradeAction::Buy { amount_sol })
+    }
+
+    /// Sell `amount_token` of `mint`
+    pub fn sell(mint: Pubkey, amount_token: u64) -> Self {
+        Self::new(mint, TradeAction::Sell { amount_token: Some(amount_token) })
+    }
+
+    /// Sell the payer's whole balance of `mint`
+    pub fn sell_all(mint: Pubkey) -> Self {
+        Self::new(mint, TradeAction::Sell { amount_token: None })
+    }
+
+    /// Sell `percent` (1 to 100) of the payer's balance of `mint`
+    pub fn sell_percent(mint: Pubkey, percent: u64) -> Self {
+        Self::new(mint, TradeAction::SellPercent { percent })
+    }
+
+    pub fn slippage_bps(mut self, slippage_basis_points: u64) -> Self {
+        self.slippage_basis_points = Some(slippage_basis_points);
+        self
+    }
+
+    /// Sends through the client's fee clients, tipping each, instead of plain RPC
+    pub fn with_tip(mut self) -> Self {
+        self.with_tip = true;
+        self
+    }
+
+    pub fn priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.overrides.priority_fee = Some(priority_fee);
+        self
+    }
+
+    /// How the transactions are spread over the fee clients
+    pub fn send_mode(mut self, send_mode: SendMode) -> Self {
+        self.overrides.send_mode = Some(send_mode);
+        self
+    }
+
+    pub fn submission_options(mut self, submission_options: SubmissionOptions) -> Self {
+        self.overrides.submission_options = Some(submission_options);
+        self
+    }
+
+    /// Preflight and rebroadcast settings of transactions sent over RPC
+    pub fn rpc_send_options(mut self, rpc_send_options: RpcSendOptions) -> Self {
+        self.overrides.rpc_send_options = Some(rpc_send_options);
+        self
+    }
+
+    /// Rebuild and send the trade once more when it failed on a rotated fee recipient
+    pub fn retry_fee_recipient_rotation(mut self, retry: bool) -> Self {
+        self.overrides.retry_fee_recipient_rotation = Some(retry);
+        self
+    }
+
+    /// Tags the trade with `tag` instead of the client's trade tag
+    pub fn tag(mut self, tag: TradeTag) -> Self {
+        self.overrides.trade_tag = Some(Some(tag));
+        self
+    }
+
+    /// Sends the trade without the client's trade tag
+    pub fn untagged(mut self) -> Self {
+        self.overrides.trade_tag = Some(None);
+        self
+    }
+
+    pub fn extra_instruction(mut self, instruction: Instruction) -> Self {
+        self.extra_instructions.push(instruction);
+        self
+    }
+
+    pub fn extra_instructions(mut self, instructions: impl IntoIterator<Item = Instruction>) -> Self {
+        self.extra_instructions.extend(instructions);
+        self
+    }
+
+    /// Stops the trade when `cancel` is cancelled, see [`cancel`](super::cancel)
+    pub fn cancel(mut self, cancel: CancellationToken) -> Self {
+        self.overrides.cancel = Some(cancel);
+        self
+    }
+
+    /// Last slot the trade is worth landing in, see [`deadline`](super::deadline)
+    pub fn land_by_slot(mut self, land_by_slot: u64) -> Self {
+        self.overrides.land_by_slot = Some(land_by_slot);
+        self
+    }
+
+    /// Caller's key for the trade, recorded in the trade journal
+    pub fn idempotency_key(mut self, idempotency_key: impl Into<String>) -> Self {
+        self.overrides.idempotency_key = Some(idempotency_key.into());
+        self
+    }
+
+    /// Replaces every override set so far
+    pub fn overrides(mut self, overrides: TradeOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// The extra instructions, `None` when there are none
+    pub(crate) fn take_extra_instructions(&mut self) -> Option<Vec<Instruction>> {
+        let instructions = std::mem::take(&mut self.extra_instructions);
+        (!instructions.is_empty()).then_some(instructions)
+    }
+}
+
+/// A token launch with a dev buy, for `PumpFun::create_and_buy`
+///
+/// ```no_run
+/// # async fn run(client: &pumpfun_sdk::PumpFun, ipfs: pumpfun_sdk::ipfs::TokenMetadataIPFS) -> Result<(), anyhow::Error> {
+/// use pumpfun_sdk::{pumpfun::{create::MintCheck, launch_budget::LaunchBudget}, trade::params::CreateParams};
+/// use solana_sdk::signature::Keypair;
+///
+/// let params = CreateParams::new(Keypair::new(), ipfs, 1_000_000_000)
+///     .slippage_bps(500)
+///     .mint_check(MintCheck::Regenerate)
+///     .preflight(true)
+///     .launch_budget(LaunchBudget::new(1_200_000_000));
+/// client.create_and_buy(params).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct CreateParams {
+    pub mint: Keypair,
+    pub ipfs: TokenMetadataIPFS,
+    /// Lamports of the dev buy
+    pub amount_sol: u64,
+    /// `None` for the default slippage
+    pub slippage_basis_points: Option<u64>,
+    pub metadata_policy: MetadataPolicy,
+    pub mint_check: MintCheck,
+    /// Simulate the transaction first, sending nothing if the simulation fails
+    pub preflight: bool,
+    /// Added to the transaction before the trade tag memo
+    pub extra_instructions: Vec<Instruction>,
+    /// Fail before sending when the launch may spend more
+    pub launch_budget: Option<LaunchBudget>,
+    /// Settings replacing the client's for this launch
+    ///
+    /// Launches use the `priority_fee`, `rpc_send_options`, `buy_floor`, `trade_tag` and, with
+    /// a tip, `fee_clients` of the overrides and ignore the others.
+    pub overrides: TradeOverrides,
+}
+
+impl CreateParams {
+    /// Create `mint` with `ipfs` metadata and buy it for `amount_sol` lamports, with the
+    /// default metadata policy and mint check and no preflight
+    pub fn new(mint: Keypair, ipfs: TokenMetadataIPFS, amount_sol: u64) -> Self {
+        Self {
+            mint,
+            ipfs,
+            amount_sol,
+            slippage_basis_points: None,
+            metadata_policy: MetadataPolicy::default(),
+            mint_check: MintCheck::default(),
+            preflight: false,
+            extra_instructions: vec![],
+            launch_budget: None,
+            overrides: TradeOverrides::default(),
+        }
+    }
+
+    pub fn slippage_bps(mut self, slippage_basis_points: u64) -> Self {
+        self.slippage_basis_points = Some(slippage_basis_points);
+        self
+    }
+
+    pub fn metadata_policy(mut self, metadata_policy: MetadataPolicy) -> Self {
+        self.metadata_policy = metadata_policy;
+        self
+    }
+
+    /// What to do about a mint that already exists on-chain
+    pub fn mint_check(mut self, mint_check: MintCheck) -> Self {
+        self.mint_check = mint_check;
+        self
+    }
+
+    pub fn preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
+
+    pub fn extra_instruction(mut self, instruction: Instruction) -> Self {
+        self.extra_instructions.push(instruction);
+        self
+    }
+
+    pub fn extra_instructions(mut self, instructions: impl IntoIterator<Item = Instruction>) -> Self {
+        self.extra_instructions.extend(instructions);
+        self
+    }
+
+    pub fn launch_budget(mut self, launch_budget: LaunchBudget) -> Self {
+        self.launch_budget = Some(launch_budget);
+        self
+    }
+
+    pub fn priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.overrides.priority_fee = Some(priority_fee);
+        self
+    }
+
+    /// Preflight and rebroadcast settings of the transaction when sent over RPC
+    pub fn rpc_send_options(mut self, rpc_send_options: RpcSendOptions) -> Self {
+        self.overrides.rpc_send_options = Some(rpc_send_options);
+        self
+    }
+
+    /// Tags the launch with `tag` instead of the client's trade tag
+    pub fn tag(mut self, tag: TradeTag) -> Self {
+        self.overrides.trade_tag = Some(Some(tag));
+        self
+    }
+
+    /// Sends the launch without the client's trade tag
+    pub fn untagged(mut self) -> Self {
+        self.overrides.trade_tag = Some(None);
+        self
+    }
+
+    /// Replaces every override set so far
+    pub fn overrides(mut self, overrides: TradeOverrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// The extra instructions, `None` when there are none
+    pub(crate) fn take_extra_instructions(&mut self) -> Option<Vec<Instruction>> {
+        let instructions = std::mem::take(&mut self.extra_instructions);
+        (!instructions.is_empty()).then_some(instructions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::system_instruction;
+
+    use super::*;
+
+    #[test]
+    fn test_setters_fill_in_the_overrides() {
+        let mint = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&Pubkey::new_unique(), &Pubkey::new_unique(), 1);
+        let mut params = TradeParams::buy(mint, 1_000)
+            .slippage_bps(300)
+            .with_tip()
+            .send_mode(SendMode::Fallback)
+            .tag(TradeTag::new("bot-7").unwrap())
+            .land_by_slot(42)
+            .extra_instruction(transfer.clone());
+
+        assert_eq!((params.mint, params.action), (mint, TradeAction::Buy { amount_sol: 1_000 }));
+        assert_eq!(params.slippage_basis_points, Some(300));
+        assert!(params.with_tip);
+        assert_eq!(params.overrides.send_mode, Some(SendMode::Fallback));
+        assert_eq!(params.overrides.trade_tag, Some(Some(TradeTag::new("bot-7").unwrap())));
+        assert_eq!(params.overrides.land_by_slot, Some(42));
+        assert_eq!(params.take_extra_instructions(), Some(vec![transfer]));
+        assert_eq!(params.take_extra_instructions(), None);
+
+        let untagged = TradeParams::sell_all(mint).tag(TradeTag::new("bot-7").unwrap()).untagged();
+        assert_eq!(untagged.overrides.trade_tag, Some(None));
+        assert_eq!(untagged.action, TradeAction::Sell { amount_token: None });
+        assert!(!untagged.with_tip);
+    }
+
+    #[test]
+    fn test_create_params_default_to_a_plain_launch() {
+        let metadata = serde_json::from_value(serde_json::json!({ "name": "Fixture", "symbol": "FIX", "image": "https://ipfs.io/ipfs/image" })).unwrap();
+        let ipfs = TokenMetadataIPFS { metadata, metadata_uri: "https://ipfs.io/ipfs/metadata".to_string() };
+        let memo = system_instruction::transfer(&Pubkey::new_unique(), &Pubkey::new_unique(), 1);
+
+        let params = CreateParams::new(Keypair::new(), ipfs.clone(), 1_000);
+        assert_eq!((params.slippage_basis_points, params.metadata_policy, params.mint_check, params.preflight), (None, MetadataPolicy::Mutable, MintCheck::Fail, false));
+        assert_eq!(params.launch_budget, None);
+
+        let mut params = CreateParams::new(Keypair::new(), ipfs, 1_000)
+            .mint_check(MintCheck::Regenerate)
+            .launch_budget(LaunchBudget::new(5_000))
+            .tag(TradeTag::new("launcher").unwrap())
+            .extra_instruction(memo.clone());
+        assert_eq!((params.mint_check, params.launch_budget), (MintCheck::Regenerate, Some(LaunchBudget::new(5_000))));
+        assert_eq!(params.overrides.trade_tag, Some(Some(TradeTag::new("launcher").unwrap())));
+        assert_eq!(params.take_extra_instructions(), Some(vec![memo]));
+    }
+}
@@ -0,0 +1,191 @@
+//! Exclusive access to positions
+//!
+//! Automated sellers, such as the [`GraduationGuard`](super::graduation::GraduationGuard),
+//! claim a mint in a shared [`PositionLocks`] before selling it, so two of them never sell
+//! the same position concurrently.
+//!
+//! A [`PositionTracker`] keeps the balances a wallet is expected to hold from its fills, for
+//! [`reconcile`](super::reconcile) to compare against the chain.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use solana_sdk::pubkey::Pubkey;
+
+use crate::common::logs_data::TradeInfo;
+
+/// Mints currently being sold, shared by every automated seller of one wallet
+#[derive(Debug, Clone, Default)]
+pub struct PositionLocks {
+    claimed: Arc<Mutex<HashSet<Pubkey>>>,
+}
+
+impl PositionLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims `mint`, or returns `None` if another seller holds it
+    pub fn try_claim(&self, mint: Pubkey) -> Option<PositionClaim> {
+        match self.claimed.lock().unwrap().insert(mint) {
+            true => Some(PositionClaim { locks: self.clone(), mint }),
+            false => None,
+        }
+    }
+
+    pub fn is_claimed(&self, mint: &Pubkey) -> bool {
+        self.claimed.lock().unwrap().contains(mint)
+    }
+}
+
+/// Exclusive claim on a mint, released on drop
+#[derive(Debug)]
+pub struct PositionClaim {
+    locks: PositionLocks,
+    mint: Pubkey,
+}
+
+impl PositionClaim {
+    #[inline]
+    pub fn mint(&self) -> Pubkey {
+        self.mint
+    }
+}
+
+impl Drop for PositionClaim {
+    fn drop(&mut self) {
+        self.locks.claimed.lock().unwrap().remove(&self.mint);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Expected {
+    tokens: HashMap<Pubkey, u64>,
+    lamports: Option<u64>,
+}
+
+/// Token and SOL balances a wallet is expected to hold, shared by everything trading for it
+///
+/// Fills only move the amounts they traded; network fees, tips and rent are not known from
+/// a fill, so the expected SOL balance drifts until it is reconciled.
+#[derive(Debug, Clone, Default)]
+pub struct PositionTracker {
+    expected: Arc<Mutex<Expected>>,
+}
+
+impl PositionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts tracking `mint`, or overwrites its expected balance
+    pub fn set_position(&self, mint: Pubkey, amount: u64) {
+        self.expected.lock().unwrap().tokens.insert(mint, amount);
+    }
+
+    /// Starts tracking the SOL balance, or overwrites it
+    pub fn set_expected_lamports(&self, lamports: u64) {
+        self.expected.lock().unwrap().lamports = Some(lamports);
+    }
+
+    /// Applies a fill of the tracked wallet; trades of untracked mints start tracking them
+    pub fn record_fill(&self, trade: &TradeInfo) {
+        let mut guard = self.expected.lock().unwrap();
+        let expected = &mut *guard;
+        let tokens = expected.tokens.entry(trade.mint).or_default();
+        if trade.is_buy {
+            *tokens = tokens.saturating_add(trade.token_amount);
+        } else {
+            *tokens = tokens.saturating_sub(trade.token_amount);
+        }
+        if let Some(lamports) = expected.lamports.as_mut() {
+            *lamports = match trade.is_buy {
+                true => lamports.saturating_sub(trade.sol_amount),
+                false => lamports.saturating_add(trade.sol_amount),
+            };
+        }
+    }
+
+    /// Stops tracking `mint`, returning its expected balance
+    pub fn untrack(&self, mint: &Pubkey) -> Option<u64> {
+        self.expected.lock().unwrap().tokens.remove(mint)
+    }
+
+    pub fn expected(&self, mint: &Pubkey) -> Option<u64> {
+        self.expected.lock().unwrap().tokens.get(mint).copied()
+    }
+
+    /// Expected SOL balance, `None` while it isn't tracked
+    pub fn expected_lamports(&self) -> Option<u64> {
+        self.expected.lock().unwrap().lamports
+    }
+
+    /// The tracked mints, in no particular order
+    pub fn mints(&self) -> Vec<Pubkey> {
+        self.expected.lock().unwrap().tokens.keys().copied().collect()
+    }
+
+    /// Overwrites the expected balance of `mint`, or the SOL balance for the native mint, with
+    /// `actual` unless a fill moved it away from `seen` meanwhile; `false` if it moved
+    pub fn correct(&self, mint: &Pubkey, seen: u64, actual: u64) -> bool {
+        let mut expected = self.expected.lock().unwrap();
+        let current = match *mint == spl_token::native_mint::ID {
+            true => expected.lamports.as_mut(),
+            false => expected.tokens.get_mut(mint),
+        };
+        match current {
+            Some(current) if *current == seen => {
+                *current = actual;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Expected balances of every tracked mint and the SOL balance, read at once
+    pub fn snapshot(&self) -> (HashMap<Pubkey, u64>, Option<u64>) {
+        let expected = self.expected.lock().unwrap();
+        (expected.tokens.clone(), expected.lamports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claims_are_exclusive_until_dropped() {
+        let locks = PositionLocks::new();
+        let mint = Pubkey::new_unique();
+
+        let claim = locks.try_claim(mint).unwrap();
+        assert!(locks.clone().try_claim(mint).is_none());
+        assert!(locks.try_claim(Pubkey::new_unique()).is_some());
+        assert!(locks.is_claimed(&mint));
+
+        drop(claim);
+        assert!(!locks.is_claimed(&mint));
+        assert!(locks.try_claim(mint).is_some());
+    }
+
+    #[test]
+    fn test_fills_move_the_expected_balances() {
+        let tracker = PositionTracker::new();
+        let mint = Pubkey::new_unique();
+        let fill = |is_buy, token_amount, sol_amount| TradeInfo { mint, is_buy, token_amount, sol_amount, ..Default::default() };
+
+        tracker.record_fill(&fill(true, 1_000, 50));
+        assert_eq!(tracker.expected(&mint), Some(1_000));
+        assert_eq!(tracker.expected_lamports(), None);
+
+        tracker.set_expected_lamports(500);
+        tracker.record_fill(&fill(false, 400, 30));
+        assert_eq!(tracker.expected(&mint), Some(600));
+        assert_eq!(tracker.expected_lamports(), Some(530));
+
+        assert_eq!(tracker.untrack(&mint), Some(600));
+        assert!(tracker.mints().is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+//! Reconciling tracked positions with the chain
+//!
+//! A [`PositionTracker`] only learns of the fills it is told about, so fees, tips, rent,
+//! failed sells and transfers made elsewhere make it drift. [`reconcile_positions`] reads the
+//! actual balance of every tracked mint and the wallet's SOL through the batched
+//! [`get_wallet_balances`], three `getMultipleAccounts` calls for 100 positions, and reports
+//! each mismatch as a [`ReconciliationDiff`], optionally correcting the tracker.
+
+use std::{collections::HashMap, time::Duration};
+
+use rand::Rng;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    common::SolanaRpcClient,
+    pumpfun::common::{get_wallet_balances, WalletBalances},
+};
+
+use super::position::PositionTracker;
+
+/// Mismatch between the balance a tracker expects and the one on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconciliationDiff {
+    /// The tracked mint, or the native mint for the SOL balance
+    pub mint: Pubkey,
+    pub expected: u64,
+    pub actual: u64,
+    /// `actual - expected`
+    pub delta: i128,
+}
+
+impl ReconciliationDiff {
+    pub fn new(mint: Pubkey, expected: u64, actual: u64) -> Self {
+        Self { mint, expected, actual, delta: actual as i128 - expected as i128 }
+    }
+
+    /// Whether the diff is about the SOL balance
+    pub fn is_sol(&self) -> bool {
+        self.mint == spl_token::native_mint::ID
+    }
+}
+
+/// Mismatches between the `expected` token and SOL balances and the `actual` ones, the SOL
+/// balance first and the mints in order
+pub fn diff_balances(expected: &HashMap<Pubkey, u64>, expected_lamports: Option<u64>, actual: &WalletBalances) -> Vec<ReconciliationDiff> {
+    let mut diffs: Vec<ReconciliationDiff> = expected
+        .iter()
+        .map(|(mint, expected)| ReconciliationDiff::new(*mint, *expected, actual.tokens.get(mint).copied().unwrap_or_default()))
+        .filter(|diff| diff.delta != 0)
+        .collect();
+    diffs.sort_by_key(|diff| diff.mint);
+    if let Some(lamports) = expected_lamports.filter(|lamports| *lamports != actual.lamports) {
+        diffs.insert(0, ReconciliationDiff::new(spl_token::native_mint::ID, lamports, actual.lamports));
+    }
+    diffs
+}
+
+/// Compares the balances `tracker` expects `owner` to hold with the chain
+///
+/// With `auto_correct`, each mismatching expectation is set to the balance read, unless a fill
+/// recorded during the read moved it; such a diff is still returned and resolves on the next
+/// run.
+pub async fn reconcile_positions(rpc: &SolanaRpcClient, owner: &Pubkey, tracker: &PositionTracker, auto_correct: bool) -> Result<Vec<ReconciliationDiff>, anyhow::Error> {
+    let (expected, expected_lamports) = tracker.snapshot();
+    let mints: Vec<Pubkey> = expected.keys().copied().collect();
+    let actual = get_wallet_balances(rpc, owner, &mints).await?;
+
+    let diffs = diff_balances(&expected, expected_lamports, &actual);
+    for diff in &diffs {
+        tracing::warn!(mint = %diff.mint, expected = diff.expected, actual = diff.actual, delta = %diff.delta, "tracked balance differs from the chain");
+        if auto_correct {
+            tracker.correct(&diff.mint, diff.expected, diff.actual);
+        }
+    }
+    Ok(diffs)
+}
+
+/// `interval` plus up to a tenth of it at random, so reconcilers of several wallets don't
+/// hit the RPC in step
+pub fn jittered(interval: Duration) -> Duration {
+    let max_jitter = (interval.as_millis() / 10) as u64;
+    interval + Duration::from_millis(rand::rng().random_range(0..=max_jitter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_mismatches_are_reported() {
+        let (held, sold, missing) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let expected = HashMap::from([(held, 1_000), (sold, 500), (missing, 0)]);
+        let actual = WalletBalances { lamports: 900, tokens: HashMap::from([(held, 1_000), (sold, 0)]) };
+
+        let diffs = diff_balances(&expected, Some(1_000), &actual);
+        assert_eq!(diffs, vec![
+            ReconciliationDiff { mint: spl_token::native_mint::ID, expected: 1_000, actual: 900, delta: -100 },
+            ReconciliationDiff { mint: sold, expected: 500, actual: 0, delta: -500 },
+        ]);
+        assert!(diffs[0].is_sol() && !diffs[1].is_sol());
+        assert!(diff_balances(&expected, None, &actual).iter().all(|diff| !diff.is_sol()));
+    }
+
+    #[test]
+    fn test_corrections_skip_positions_filled_meanwhile() {
+        let (mint, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let tracker = PositionTracker::new();
+        tracker.set_position(mint, 500);
+        tracker.set_position(other, 100);
+        tracker.set_expected_lamports(1_000);
+
+        assert!(tracker.correct(&mint, 500, 450));
+        assert!(!tracker.correct(&other, 80, 0));
+        assert!(tracker.correct(&spl_token::native_mint::ID, 1_000, 990));
+        assert_eq!((tracker.expected(&mint), tracker.expected(&other), tracker.expected_lamports()), (Some(450), Some(100), Some(990)));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_a_tenth() {
+        let interval = Duration::from_secs(30);
+        for _ in 0..100 {
+            let delay = jittered(interval);
+            assert!(delay >= interval && delay <= interval + Duration::from_secs(3));
+        }
+    }
+}
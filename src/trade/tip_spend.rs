@@ -0,0 +1,238 @@
+//! Tips paid by landed transactions
+//!
+//! The [`Submitter`](super::Submitter) records the relay tip of every transaction it saw land
+//! successfully in the process-wide [`tip_ledger`], aggregated per UTC day, transport, trade
+//! tag and [`SendMode`]. Failed transactions don't pay their tip and aren't counted.
+//!
+//! A submission landing more than one of its transactions, e.g. differently tipped copies
+//! raced over several relays in [`SendMode::Parallel`], pays every landed tip. The first to
+//! land counts as landed spend and the others as wasted, which is the cost of the fan-out.
+//! A transaction sent through several transports is only counted once, for the transport
+//! that accepted it first.
+//!
+//! A [`TipSpendHook`] installed with [`set_tip_spend_hook`] is called with every tip, e.g. to
+//! feed a metrics counter.
+
+use std::{
+    collections::HashMap,
+    ops::RangeBounds,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use chrono::{NaiveDate, Utc};
+use solana_sdk::signature::Signature;
+
+use crate::fee_clients::ClientType;
+
+use super::SendMode;
+
+lazy_static::lazy_static! {
+    static ref TIP_LEDGER: TipLedger = TipLedger::default();
+    static ref TIP_SPEND_HOOK: RwLock<Option<Arc<dyn TipSpendHook>>> = RwLock::new(None);
+}
+
+/// A tip paid by a landed transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TipPaid {
+    pub signature: Signature,
+    pub transport: ClientType,
+    /// Trade tag of the trade, the strategy the tip is billed to
+    pub strategy: Option<String>,
+    pub mode: SendMode,
+    pub lamports: u64,
+    /// Whether another transaction of the same submission landed first
+    pub wasted: bool,
+    /// UTC day the landing was seen
+    pub day: NaiveDate,
+}
+
+/// Receives every tip the ledger records; implementations must not block the caller
+pub trait TipSpendHook: Send + Sync {
+    fn tip_paid(&self, tip: &TipPaid);
+}
+
+/// Installs `hook` for all clients, or removes it with `None`
+pub fn set_tip_spend_hook(hook: Option<Arc<dyn TipSpendHook>>) {
+    *TIP_SPEND_HOOK.write().unwrap() = hook;
+}
+
+/// The ledger the submitter records into
+pub fn tip_ledger() -> &'static TipLedger {
+    &TIP_LEDGER
+}
+
+/// Records `tip` in the [`tip_ledger`] and passes it to the installed hook
+pub fn record(tip: TipPaid) {
+    TIP_LEDGER.record(&tip);
+    if let Some(hook) = TIP_SPEND_HOOK.read().unwrap().clone() {
+        hook.tip_paid(&tip);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TipSpend {
+    pub landed_lamports: u64,
+    pub landed_transactions: u64,
+    /// Tips of transactions that landed after another one of their submission
+    pub wasted_lamports: u64,
+    pub wasted_transactions: u64,
+}
+
+impl TipSpend {
+    pub fn total_lamports(&self) -> u64 {
+        self.landed_lamports + self.wasted_lamports
+    }
+
+    /// Share of the spend that was wasted, `None` without spend
+    pub fn wasted_share(&self) -> Option<f64> {
+        let total = self.total_lamports();
+        (total > 0).then(|| self.wasted_lamports as f64 / total as f64)
+    }
+
+    fn add(&mut self, other: &TipSpend) {
+        self.landed_lamports += other.landed_lamports;
+        self.landed_transactions += other.landed_transactions;
+        self.wasted_lamports += other.wasted_lamports;
+        self.wasted_transactions += other.wasted_transactions;
+    }
+}
+
+/// Spend of one day, transport, strategy and send mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TipSpendRow {
+    pub day: NaiveDate,
+    pub transport: ClientType,
+    pub strategy: Option<String>,
+    pub mode: SendMode,
+    pub spend: TipSpend,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TipSpendReport {
+    /// Sorted by day, transport, strategy and mode
+    pub rows: Vec<TipSpendRow>,
+    pub total: TipSpend,
+}
+
+impl TipSpendReport {
+    pub fn by_day(&self) -> Vec<(NaiveDate, TipSpend)> {
+        let mut days: Vec<(NaiveDate, TipSpend)> = vec![];
+        for row in &self.rows {
+            match days.last_mut() {
+                Some((day, spend)) if *day == row.day => spend.add(&row.spend),
+                _ => days.push((row.day, row.spend)),
+            }
+        }
+        days
+    }
+
+    pub fn by_transport(&self) -> HashMap<ClientType, TipSpend> {
+        self.sum_by(|row| row.transport.clone())
+    }
+
+    pub fn by_strategy(&self) -> HashMap<Option<String>, TipSpend> {
+        self.sum_by(|row| row.strategy.clone())
+    }
+
+    /// Spend of fan-out versus fallback submissions
+    pub fn by_mode(&self) -> HashMap<SendMode, TipSpend> {
+        self.sum_by(|row| row.mode)
+    }
+
+    fn sum_by<K: std::hash::Hash + Eq>(&self, key: impl Fn(&TipSpendRow) -> K) -> HashMap<K, TipSpend> {
+        let mut sums: HashMap<K, TipSpend> = HashMap::new();
+        for row in &self.rows {
+            sums.entry(key(row)).or_default().add(&row.spend);
+        }
+        sums
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct TipSpendKey {
+    day: NaiveDate,
+    transport: ClientType,
+    strategy: Option<String>,
+    mode: SendMode,
+}
+
+/// Tip spend aggregated per day, transport, strategy and send mode
+#[derive(Debug, Default)]
+pub struct TipLedger {
+    spend: Mutex<HashMap<TipSpendKey, TipSpend>>,
+}
+
+impl TipLedger {
+    pub fn record(&self, tip: &TipPaid) {
+        let key = TipSpendKey { day: tip.day, transport: tip.transport.clone(), strategy: tip.strategy.clone(), mode: tip.mode };
+        let mut spend = self.spend.lock().unwrap();
+        let spend = spend.entry(key).or_default();
+        if tip.wasted {
+            spend.wasted_lamports += tip.lamports;
+            spend.wasted_transactions += 1;
+        } else {
+            spend.landed_lamports += tip.lamports;
+            spend.landed_transactions += 1;
+        }
+    }
+
+    /// Spend of the UTC days in `days`
+    pub fn report(&self, days: impl RangeBounds<NaiveDate>) -> TipSpendReport {
+        let mut rows: Vec<TipSpendRow> = self.spend.lock().unwrap()
+            .iter()
+            .filter(|(key, _)| days.contains(&key.day))
+            .map(|(key, spend)| TipSpendRow { day: key.day, transport: key.transport.clone(), strategy: key.strategy.clone(), mode: key.mode, spend: *spend })
+            .collect();
+        rows.sort_by(|a, b| {
+            (a.day, a.transport.to_string(), &a.strategy, a.mode == SendMode::Fallback)
+                .cmp(&(b.day, b.transport.to_string(), &b.strategy, b.mode == SendMode::Fallback))
+        });
+
+        let mut total = TipSpend::default();
+        rows.iter().for_each(|row| total.add(&row.spend));
+        TipSpendReport { rows, total }
+    }
+
+    pub fn clear(&self) {
+        self.spend.lock().unwrap().clear();
+    }
+}
+
+/// Today in UTC, the day tips are recorded under
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tip(transport: ClientType, strategy: &str, mode: SendMode, lamports: u64, wasted: bool, day: NaiveDate) -> TipPaid {
+        TipPaid { signature: Signature::new_unique(), transport, strategy: Some(strategy.to_string()), mode, lamports, wasted, day }
+    }
+
+    #[test]
+    fn test_report_aggregates_per_day_transport_and_strategy() {
+        let ledger = TipLedger::default();
+        let (monday, tuesday) = (NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(), NaiveDate::from_ymd_opt(2025, 3, 4).unwrap());
+        ledger.record(&tip(ClientType::Jito, "sniper", SendMode::Parallel, 1_000, false, monday));
+        ledger.record(&tip(ClientType::ZeroSlot, "sniper", SendMode::Parallel, 3_000, true, monday));
+        ledger.record(&tip(ClientType::Jito, "sniper", SendMode::Parallel, 500, false, monday));
+        ledger.record(&tip(ClientType::Jito, "exit", SendMode::Fallback, 2_000, false, tuesday));
+
+        let report = ledger.report(..);
+        assert_eq!(report.rows.len(), 3);
+        assert_eq!(report.rows[0].spend, TipSpend { landed_lamports: 1_500, landed_transactions: 2, wasted_lamports: 0, wasted_transactions: 0 });
+        assert_eq!(report.total.total_lamports(), 6_500);
+        assert_eq!(report.by_mode()[&SendMode::Parallel].wasted_share(), Some(0.6));
+        assert_eq!(report.by_strategy()[&Some("exit".to_string())].landed_lamports, 2_000);
+        assert_eq!(report.by_day(), vec![
+            (monday, TipSpend { landed_lamports: 1_500, landed_transactions: 2, wasted_lamports: 3_000, wasted_transactions: 1 }),
+            (tuesday, TipSpend { landed_lamports: 2_000, landed_transactions: 1, wasted_lamports: 0, wasted_transactions: 0 }),
+        ]);
+
+        let tuesday_only = ledger.report(tuesday..=tuesday);
+        assert_eq!(tuesday_only.rows.len(), 1);
+        assert_eq!(tuesday_only.by_transport()[&ClientType::Jito].landed_lamports, 2_000);
+    }
+}
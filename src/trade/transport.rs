@@ -0,0 +1,253 @@
+//! Transports the [`Submitter`](super::Submitter) sends transactions through
+//!
+//! A transport only hands a transaction over and returns its signature; waiting for
+//! confirmation is left to the shared engine in [`confirm`](super::confirm).
+
+use std::sync::Arc;
+
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+
+use crate::{
+    common::SolanaRpcClient,
+    fee_clients::{ClientType, NextBlockClient, NextBlockOptions, RelayReceipt, ZeroSlotClient},
+};
+#[cfg(feature = "jito")]
+use crate::fee_clients::JitoClient;
+
+use super::{RpcSendOptions, SubmitOptions};
+
+#[async_trait::async_trait]
+pub trait Transport: Send + Sync {
+    /// Label recorded in submission results and latency reports
+    fn client_type(&self) -> ClientType;
+
+    /// Hands `transaction` over without waiting for confirmation
+    async fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error>;
+
+    /// Hands `transaction` over with the settings of the submission; transports without
+    /// settings in [`SubmitOptions`] ignore them
+    async fn send_with_options(&self, transaction: &VersionedTransaction, options: &SubmitOptions) -> Result<Signature, anyhow::Error> {
+        let _ = options;
+        self.send(transaction).await
+    }
+
+    /// [`Transport::send_with_options`], keeping the relay's response; transports without
+    /// one only report the signature
+    async fn send_detailed(&self, transaction: &VersionedTransaction, options: &SubmitOptions) -> Result<RelayReceipt, anyhow::Error> {
+        Ok(RelayReceipt::new(self.send_with_options(transaction, options).await?))
+    }
+}
+
+/// Sends through the RPC node's `sendTransaction`, with preflight checks unless
+/// [`RpcSendOptions::skip_preflight`] is set
+pub struct RpcTransport {
+    rpc: Arc<SolanaRpcClient>,
+}
+
+impl RpcTransport {
+    pub fn new(rpc: Arc<SolanaRpcClient>) -> Self {
+        Self { rpc }
+    }
+
+    fn send_config(&self, options: &RpcSendOptions) -> RpcSendTransactionConfig {
+        RpcSendTransactionConfig {
+            skip_preflight: options.skip_preflight,
+            preflight_commitment: Some(options.preflight_commitment.unwrap_or(self.rpc.commitment().commitment)),
+            encoding: None,
+            max_retries: options.max_retries,
+            min_context_slot: options.min_context_slot,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for RpcTransport {
+    fn client_type(&self) -> ClientType {
+        ClientType::Rpc
+    }
+
+    async fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+        self.send_with_options(transaction, &SubmitOptions::default()).await
+    }
+
+    async fn send_with_options(&self, transaction: &VersionedTransaction, options: &SubmitOptions) -> Result<Signature, anyhow::Error> {
+        Ok(self.rpc.send_transaction_with_config(transaction, self.send_config(&options.rpc)).await?)
+    }
+}
+
+/// Sends each transaction as a single-transaction Jito bundle
+#[cfg(feature = "jito")]
+pub struct JitoTransport {
+    client: Arc<JitoClient>,
+}
+
+#[cfg(feature = "jito")]
+impl JitoTransport {
+    pub fn new(client: Arc<JitoClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(feature = "jito")]
+#[async_trait::async_trait]
+impl Transport for JitoTransport {
+    fn client_type(&self) -> ClientType {
+        ClientType::Jito
+    }
+
+    async fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+        Ok(self.client.submit_transaction_detailed(transaction).await?.signature)
+    }
+
+    async fn send_detailed(&self, transaction: &VersionedTransaction, _options: &SubmitOptions) -> Result<RelayReceipt, anyhow::Error> {
+        self.client.submit_transaction_detailed(transaction).await
+    }
+}
+
+/// Sends through NextBlock, overriding the client's flags with `options` when given
+pub struct NextBlockTransport {
+    client: Arc<NextBlockClient>,
+    options: Option<NextBlockOptions>,
+}
+
+impl NextBlockTransport {
+    pub fn new(client: Arc<NextBlockClient>, options: Option<NextBlockOptions>) -> Self {
+        Self { client, options }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for NextBlockTransport {
+    fn client_type(&self) -> ClientType {
+        ClientType::NextBlock
+    }
+
+    async fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+        self.client.submit_transaction(transaction, self.options).await
+    }
+
+    async fn send_detailed(&self, transaction: &VersionedTransaction, _options: &SubmitOptions) -> Result<RelayReceipt, anyhow::Error> {
+        self.client.submit_transaction_detailed(transaction, self.options).await
+    }
+}
+
+/// Sends through 0slot
+pub struct ZeroSlotTransport {
+    client: Arc<ZeroSlotClient>,
+}
+
+impl ZeroSlotTransport {
+    pub fn new(client: Arc<ZeroSlotClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for ZeroSlotTransport {
+    fn client_type(&self) -> ClientType {
+        ClientType::ZeroSlot
+    }
+
+    async fn send(&self, transaction: &VersionedTransaction) -> Result<Signature, anyhow::Error> {
+        self.client.submit_transaction(transaction).await
+    }
+
+    async fn send_detailed(&self, transaction: &VersionedTransaction, _options: &SubmitOptions) -> Result<RelayReceipt, anyhow::Error> {
+        self.client.submit_transaction_detailed(transaction).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "jito")]
+    use jito_protos::searcher::searcher_service_client::SearcherServiceClient;
+    use solana_hash::Hash;
+    use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey, signature::Keypair, signer::Signer, system_instruction, transaction::Transaction};
+    use tonic::transport::Endpoint;
+
+    use crate::common::user_agent::ClientHeaderInterceptor;
+    #[cfg(feature = "jito")]
+    use crate::fee_clients::searcher_client::SearcherConnection;
+
+    use super::*;
+
+    const UNREACHABLE: &str = "http://127.0.0.1:1";
+
+    fn transaction() -> VersionedTransaction {
+        let payer = Keypair::new();
+        let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1);
+        // A legacy message serializes like a `Transaction`, which the mock RPC decodes
+        Transaction::new_signed_with_payer(&[transfer], Some(&payer.pubkey()), &[&payer], Hash::new_unique()).into()
+    }
+
+    #[tokio::test]
+    async fn test_rpc_transport_returns_transaction_signature() {
+        let transport = RpcTransport::new(Arc::new(SolanaRpcClient::new_mock("succeeds".to_string())));
+        let transaction = transaction();
+
+        assert_eq!(transport.client_type(), ClientType::Rpc);
+        assert_eq!(transport.send(&transaction).await.unwrap(), transaction.signatures[0]);
+
+        let failing = RpcTransport::new(Arc::new(SolanaRpcClient::new_mock("fails".to_string())));
+        assert!(failing.send(&transaction).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rpc_transport_applies_send_options() {
+        let rpc = Arc::new(SolanaRpcClient::new_mock("succeeds".to_string()));
+        let transport = RpcTransport::new(rpc.clone());
+
+        let default = transport.send_config(&RpcSendOptions::default());
+        assert!(!default.skip_preflight);
+        assert_eq!(default.preflight_commitment, Some(rpc.commitment().commitment));
+        assert_eq!((default.max_retries, default.min_context_slot), (None, None));
+
+        let options = RpcSendOptions { skip_preflight: true, preflight_commitment: Some(CommitmentLevel::Processed), max_retries: Some(0), min_context_slot: Some(42) };
+        let config = transport.send_config(&options);
+        assert!(config.skip_preflight);
+        assert_eq!(config.preflight_commitment, Some(CommitmentLevel::Processed));
+        assert_eq!((config.max_retries, config.min_context_slot), (Some(0), Some(42)));
+
+        let transaction = transaction();
+        let options = SubmitOptions { rpc: options, ..Default::default() };
+        assert_eq!(transport.send_with_options(&transaction, &options).await.unwrap(), transaction.signatures[0]);
+    }
+
+    #[cfg(feature = "jito")]
+    #[tokio::test]
+    async fn test_jito_transport_propagates_send_errors() {
+        let channel = Endpoint::from_static(UNREACHABLE).connect_lazy();
+        let client = JitoClient {
+            rpc_client: Arc::new(SolanaRpcClient::new_mock("succeeds".to_string())),
+            searcher_client: Arc::new(SearcherConnection::fixed(SearcherServiceClient::with_interceptor(channel, ClientHeaderInterceptor))),
+        };
+        let transport = JitoTransport::new(Arc::new(client));
+
+        assert_eq!(transport.client_type(), ClientType::Jito);
+        assert!(transport.send(&transaction()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_nextblock_transport_fails_over_and_reports_errors() {
+        let client = Arc::new(NextBlockClient::new_multi(
+            UNREACHABLE.to_string(),
+            vec!["https://127.0.0.1:1".to_string(), "https://127.0.0.1:2".to_string()],
+            "token".to_string(),
+        ).unwrap());
+        let transport = NextBlockTransport::new(client.clone(), None);
+
+        assert_eq!(transport.client_type(), ClientType::NextBlock);
+        assert!(transport.send(&transaction()).await.is_err());
+        assert!(client.endpoint_stats().iter().all(|stats| stats.failures == 1));
+    }
+
+    #[tokio::test]
+    async fn test_zeroslot_transport_propagates_send_errors() {
+        let client = ZeroSlotClient::new(UNREACHABLE.to_string(), UNREACHABLE.to_string(), "token".to_string());
+        let transport = ZeroSlotTransport::new(Arc::new(client));
+
+        assert_eq!(transport.client_type(), ClientType::ZeroSlot);
+        assert!(transport.send(&transaction()).await.is_err());
+    }
+}
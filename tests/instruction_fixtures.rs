@@ -0,0 +1,251 @@
+//! Golden fixtures for the Pump.fun instruction builders
+//!
+//! Each builder runs with fixed keys and its data and accounts are compared byte for byte
+//! against goldens, accounts named as in the program's IDL. A failure here means the
+//! instruction layout changed: update the goldens only if the program did.
+//!
+//! The goldens were derived from the IDL, not captured from the chain, so they only pin the
+//! builders against accidental change. [`test_builders_match_mainnet_transactions`] checks
+//! the buy and sell builders against real transactions; goldens captured from mainnet replace
+//! these once their signatures are recorded next to them.
+
+use std::str::FromStr;
+
+use pumpfun_sdk::{
+    constants::accounts::{PUMPFUN, TOKEN_2022_PROGRAM, TOKEN_PROGRAM},
+    instruction::{self, Buy, Create, Sell},
+};
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::{keypair::keypair_from_seed, Signer},
+};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+
+/// Mainnet token the buy and sell fixtures trade
+const MINT: &str = "9BB6NFEcjBCtnNLFko2FqVQBq8HHM13kCyYcdQbgpump";
+/// Mainnet fee recipient from the global account
+const FEE_RECIPIENT: &str = "CebN5WGQ4jvEPvsVU4EoHEpgzq1VV7AbicfhtW4xC9iM";
+
+const GLOBAL: &str = "4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf";
+const MINT_AUTHORITY: &str = "TSLvdd1pWpHVjahSpsvCXUbgwsL3JAcvokwaKt1eokM";
+const EVENT_AUTHORITY: &str = "Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1";
+const BONDING_CURVE: &str = "TBHe5tJnuT4CQbHorJ1uVdfUoaYGPKgfCpiv2jgesVN";
+const PROGRAM: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+const SPL_TOKEN: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const SPL_TOKEN_2022: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const ASSOCIATED_TOKEN_PROGRAM: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+const RENT: &str = "SysvarRent111111111111111111111111111111111";
+const TOKEN_METADATA_PROGRAM: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+/// Keypair derived from 32 bytes of `seed`
+fn keypair(seed: u8) -> Keypair {
+    keypair_from_seed(&[seed; 32]).unwrap()
+}
+
+/// `AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9`
+fn payer() -> Keypair {
+    keypair(1)
+}
+
+fn pubkey(address: &str) -> Pubkey {
+    Pubkey::from_str(address).unwrap()
+}
+
+fn signer(address: &str) -> AccountMeta {
+    AccountMeta::new(pubkey(address), true)
+}
+
+fn writable(address: &str) -> AccountMeta {
+    AccountMeta::new(pubkey(address), false)
+}
+
+fn readonly(address: &str) -> AccountMeta {
+    AccountMeta::new_readonly(pubkey(address), false)
+}
+
+/// Compares `instruction` with the golden data, given as hex, and the golden accounts, each
+/// named as in the program's IDL so a mismatch says which account moved
+fn assert_instruction(instruction: &Instruction, data: &str, accounts: &[(&str, AccountMeta)]) {
+    assert_eq!(instruction.program_id, pubkey(PROGRAM));
+    assert_eq!(hex::encode(&instruction.data), data, "instruction data");
+    assert_eq!(instruction.accounts.len(), accounts.len(), "number of accounts");
+    for (index, (actual, (name, expected))) in instruction.accounts.iter().zip(accounts).enumerate() {
+        assert_eq!(actual, expected, "account {} ({})", index, name);
+    }
+}
+
+#[test]
+fn test_program_constants() {
+    assert_eq!(PUMPFUN, pubkey(PROGRAM));
+    assert_eq!(TOKEN_PROGRAM, pubkey(SPL_TOKEN));
+    assert_eq!(TOKEN_2022_PROGRAM, pubkey(SPL_TOKEN_2022));
+    assert_eq!(payer().pubkey(), pubkey("AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9"));
+}
+
+#[test]
+fn test_create_fixture() {
+    let (payer, mint) = (payer(), keypair(2));
+    let args = Create {
+        _name: "Fixture".to_string(),
+        _symbol: "FIX".to_string(),
+        _uri: "https://ipfs.io/ipfs/QmFixture".to_string(),
+        payer_pubkey: payer.pubkey(),
+    };
+
+    assert_instruction(
+        &instruction::create(&payer.pubkey(), &mint.pubkey(), args),
+        concat!(
+            "181ec828051c0777",                                                       // discriminator
+            "07000000", "46697874757265",                                             // name
+            "03000000", "464958",                                                     // symbol
+            "1e000000", "68747470733a2f2f697066732e696f2f697066732f516d46697874757265", // uri
+            "2c000000", "414b6e4c344e4e66334447575a4a533663506b6e427545476e5673563441346d35746765624c486152535a39", // creator
+        ),
+        &[
+            ("mint", signer("9hSR6S7WPtxmTojgo6GG3k4yDPecgJY292j7xrsUGWBu")),
+            ("mint_authority", writable(MINT_AUTHORITY)),
+            ("bonding_curve", writable("7vx2BNV1E9CWf89K8LEzBjrKXYJxFyWXaJC6JedA2LPG")),
+            ("associated_bonding_curve", writable("2CAB9tBFVVyveC16eNGrrFHi5Ng1mpEPfj2TCrUXEdpP")),
+            ("global", readonly(GLOBAL)),
+            ("mpl_token_metadata", readonly(TOKEN_METADATA_PROGRAM)),
+            ("metadata", writable("5WHKuWMRUkk1GfsgFDoUpQtryyKrmBAHUUqo7gmifcXh")),
+            ("user", signer("AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9")),
+            ("system_program", readonly(SYSTEM_PROGRAM)),
+            ("token_program", readonly(SPL_TOKEN)),
+            ("associated_token_program", readonly(ASSOCIATED_TOKEN_PROGRAM)),
+            ("rent", readonly(RENT)),
+            ("event_authority", readonly(EVENT_AUTHORITY)),
+            ("program", readonly(PROGRAM)),
+        ],
+    );
+}
+
+#[test]
+fn test_buy_fixture() {
+    let args = Buy { amount: 35_000_000_000_000, max_sol_cost: 1_050_000_000 };
+
+    assert_instruction(
+        &instruction::buy(&payer().pubkey(), &pubkey(MINT), &pubkey(FEE_RECIPIENT), &TOKEN_PROGRAM, args),
+        concat!(
+            "66063d1201daebea", // discriminator
+            "00309112d51f0000", // amount
+            "80ba953e00000000", // max_sol_cost
+        ),
+        &[
+            ("global", readonly(GLOBAL)),
+            ("fee_recipient", writable(FEE_RECIPIENT)),
+            ("mint", readonly(MINT)),
+            ("bonding_curve", writable(BONDING_CURVE)),
+            ("associated_bonding_curve", writable("4oPaRNdUyHNPn7oba9RnY66mkCUeUMZNVLmJ2X6J5a4Z")),
+            ("associated_user", writable("4emSd6G9XXTmjGF2P7rkC3Dwu1BpHYLwto4q1bVBfM9M")),
+            ("user", signer("AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9")),
+            ("system_program", readonly(SYSTEM_PROGRAM)),
+            ("token_program", readonly(SPL_TOKEN)),
+            ("rent", readonly(RENT)),
+            ("event_authority", readonly(EVENT_AUTHORITY)),
+            ("program", readonly(PROGRAM)),
+        ],
+    );
+}
+
+#[test]
+fn test_buy_fixture_token_2022() {
+    let args = Buy { amount: 35_000_000_000_000, max_sol_cost: 1_050_000_000 };
+    let buy = instruction::buy(&payer().pubkey(), &pubkey(MINT), &pubkey(FEE_RECIPIENT), &TOKEN_2022_PROGRAM, args);
+
+    assert_eq!(buy.accounts[4], writable("7MtvjeXhJAKqw2F1uNg8BJ4VgxCV2CuhNwLYM43v3EVX"), "associated_bonding_curve");
+    assert_eq!(buy.accounts[5], writable("Btbc51aHMhCWA4HzkN6M6KrJXeY1TpARQi4z8kER4Cfz"), "associated_user");
+    assert_eq!(buy.accounts[8], readonly(SPL_TOKEN_2022), "token_program");
+}
+
+#[test]
+fn test_sell_fixture() {
+    let args = Sell { amount: 35_000_000_000_000, min_sol_output: 950_000_000 };
+
+    assert_instruction(
+        &instruction::sell(&payer().pubkey(), &pubkey(MINT), &pubkey(FEE_RECIPIENT), &TOKEN_PROGRAM, args),
+        concat!(
+            "33e685a4017f83ad", // discriminator
+            "00309112d51f0000", // amount
+            "80d99f3800000000", // min_sol_output
+        ),
+        &[
+            ("global", readonly(GLOBAL)),
+            ("fee_recipient", writable(FEE_RECIPIENT)),
+            ("mint", readonly(MINT)),
+            ("bonding_curve", writable(BONDING_CURVE)),
+            ("associated_bonding_curve", writable("4oPaRNdUyHNPn7oba9RnY66mkCUeUMZNVLmJ2X6J5a4Z")),
+            ("associated_user", writable("4emSd6G9XXTmjGF2P7rkC3Dwu1BpHYLwto4q1bVBfM9M")),
+            ("user", signer("AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9")),
+            ("system_program", readonly(SYSTEM_PROGRAM)),
+            ("associated_token_program", readonly(ASSOCIATED_TOKEN_PROGRAM)),
+            ("token_program", readonly(SPL_TOKEN)),
+            ("event_authority", readonly(EVENT_AUTHORITY)),
+            ("program", readonly(PROGRAM)),
+        ],
+    );
+}
+
+#[test]
+fn test_sell_from_delegated_account_fixture() {
+    let token_account = pubkey("Hef95Xg4LAHNT7kwmfQi1pZRFXhGQkXynSWY6FqSRBqh");
+    let args = Sell { amount: 1, min_sol_output: 0 };
+    let sell = instruction::sell_from(&payer().pubkey(), &pubkey(MINT), &token_account, &pubkey(FEE_RECIPIENT), &TOKEN_PROGRAM, args);
+
+    assert_eq!(hex::encode(&sell.data), "33e685a4017f83ad01000000000000000000000000000000");
+    assert_eq!(sell.accounts[5], writable("Hef95Xg4LAHNT7kwmfQi1pZRFXhGQkXynSWY6FqSRBqh"), "associated_user");
+    assert_eq!(sell.accounts[6], signer("AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9"), "user");
+}
+
+/// Data and account keys of the first top-level Pump.fun instruction of `signature` whose
+/// data starts with `discriminator`
+async fn mainnet_instruction(rpc: &RpcClient, signature: &Signature, discriminator: &[u8]) -> (Vec<u8>, Vec<Pubkey>) {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let fetched = rpc.get_transaction_with_config(signature, config).await.unwrap().transaction;
+    let transaction = fetched.transaction.decode().unwrap();
+    let mut keys = transaction.message.static_account_keys().to_vec();
+    if let Some(OptionSerializer::Some(loaded)) = fetched.meta.map(|meta| meta.loaded_addresses) {
+        keys.extend(loaded.writable.iter().chain(&loaded.readonly).map(|key| pubkey(key)));
+    }
+
+    let instruction = transaction
+        .message
+        .instructions()
+        .iter()
+        .find(|instruction| keys[instruction.program_id_index as usize] == PUMPFUN && instruction.data.starts_with(discriminator))
+        .unwrap_or_else(|| panic!("{} has no top-level Pump.fun instruction with discriminator {}", signature, hex::encode(discriminator)));
+    (instruction.data.clone(), instruction.accounts.iter().map(|&index| keys[index as usize]).collect())
+}
+
+/// Rebuilds the buy and sell of direct (not routed) mainnet trades from their arguments and
+/// keys, and compares them with what was sent:
+/// `PUMPFUN_FIXTURE_RPC=<url> PUMPFUN_BUY_SIGNATURE=<sig> PUMPFUN_SELL_SIGNATURE=<sig> cargo test --test instruction_fixtures -- --ignored`
+#[tokio::test]
+#[ignore]
+async fn test_builders_match_mainnet_transactions() {
+    let rpc = RpcClient::new(std::env::var("PUMPFUN_FIXTURE_RPC").unwrap());
+    for (variable, discriminator) in [("PUMPFUN_BUY_SIGNATURE", "66063d1201daebea"), ("PUMPFUN_SELL_SIGNATURE", "33e685a4017f83ad")] {
+        let signature = Signature::from_str(&std::env::var(variable).unwrap()).unwrap();
+        let (data, accounts) = mainnet_instruction(&rpc, &signature, &hex::decode(discriminator).unwrap()).await;
+        let amount = u64::from_le_bytes(data[8..16].try_into().unwrap());
+        let sol_bound = u64::from_le_bytes(data[16..24].try_into().unwrap());
+        let (fee_recipient, mint, user) = (accounts[1], accounts[2], accounts[6]);
+
+        let rebuilt = match variable {
+            "PUMPFUN_BUY_SIGNATURE" => instruction::buy(&user, &mint, &fee_recipient, &accounts[8], Buy { amount, max_sol_cost: sol_bound }),
+            _ => instruction::sell(&user, &mint, &fee_recipient, &accounts[9], Sell { amount, min_sol_output: sol_bound }),
+        };
+        assert_eq!(hex::encode(&rebuilt.data), hex::encode(&data), "data of {}", signature);
+        let rebuilt_accounts: Vec<Pubkey> = rebuilt.accounts.iter().map(|account| account.pubkey).collect();
+        assert_eq!(rebuilt_accounts, accounts, "accounts of {}", signature);
+    }
+}